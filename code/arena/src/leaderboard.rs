@@ -0,0 +1,212 @@
+//! Results of every game, folded into wins, Elo ratings and damage per bot.
+
+use common::rl::contest::Outcome;
+use std::fmt::Write;
+
+pub const INITIAL_ELO: f64 = 1500.0;
+/// How far a single game moves the ratings.
+const ELO_K: f64 = 32.0;
+
+/// One finished game between two entrants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    /// Indices of the entrants; `outcome` and `damage` are in this order
+    pub entrants: [usize; 2],
+    pub outcome: Outcome,
+    pub damage: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Swiss rounds sat out, each worth a win in the points
+    pub byes: u32,
+    pub damage: f32,
+    pub elo: f64,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            byes: 0,
+            damage: 0.0,
+            elo: INITIAL_ELO,
+        }
+    }
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn points(&self) -> f32 {
+        (self.wins + self.byes) as f32 + self.draws as f32 / 2.0
+    }
+
+    pub fn average_damage(&self) -> f32 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        self.damage / self.games() as f32
+    }
+}
+
+pub struct Leaderboard {
+    names: Vec<String>,
+    records: Vec<Record>,
+}
+
+impl Leaderboard {
+    pub fn new(names: Vec<String>) -> Self {
+        let records = vec![Record::default(); names.len()];
+        Self { names, records }
+    }
+
+    pub fn record(&mut self, result: &GameResult) {
+        let [a, b] = result.entrants;
+        let score_a = match result.outcome {
+            Outcome::Win(0) => 1.0,
+            Outcome::Win(_) => 0.0,
+            Outcome::Draw => 0.5,
+        };
+        for (side, entrant) in [a, b].into_iter().enumerate() {
+            let record = &mut self.records[entrant];
+            match result.outcome {
+                Outcome::Draw => record.draws += 1,
+                Outcome::Win(winner) if winner == side => record.wins += 1,
+                Outcome::Win(_) => record.losses += 1,
+            }
+            record.damage += result.damage[side];
+        }
+
+        let expected_a =
+            1.0 / (1.0 + 10f64.powf((self.records[b].elo - self.records[a].elo) / 400.0));
+        let change = ELO_K * (score_a - expected_a);
+        self.records[a].elo += change;
+        self.records[b].elo -= change;
+    }
+
+    pub fn record_bye(&mut self, entrant: usize) {
+        self.records[entrant].byes += 1;
+    }
+
+    /// Entrant indices, best first: by points, then by Elo.
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&x, &y| {
+            let (x, y) = (&self.records[x], &self.records[y]);
+            y.points()
+                .total_cmp(&x.points())
+                .then(y.elo.total_cmp(&x.elo))
+        });
+        order
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (usize, &str, &Record)> {
+        self.ranking()
+            .into_iter()
+            .enumerate()
+            .map(|(place, i)| (place + 1, self.names[i].as_str(), &self.records[i]))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from(
+            "| # | Bot | Games | W | D | L | Points | Elo | Avg damage |\n\
+             |---|-----|-------|---|---|---|--------|-----|------------|\n",
+        );
+        for (place, name, r) in self.rows() {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {:.1} | {:.0} | {:.1} |",
+                place,
+                name,
+                r.games(),
+                r.wins,
+                r.draws,
+                r.losses,
+                r.points(),
+                r.elo,
+                r.average_damage()
+            );
+        }
+        out
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("rank,bot,games,wins,draws,losses,points,elo,avg_damage\n");
+        for (place, name, r) in self.rows() {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{:.1},{:.2}",
+                place,
+                name.replace(',', " "),
+                r.games(),
+                r.wins,
+                r.draws,
+                r.losses,
+                r.points(),
+                r.elo,
+                r.average_damage()
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(a: usize, b: usize, outcome: Outcome) -> GameResult {
+        GameResult {
+            entrants: [a, b],
+            outcome,
+            damage: [30.0, 10.0],
+        }
+    }
+
+    #[test]
+    fn test_elo_and_ranking() {
+        let mut board = Leaderboard::new(vec!["a".into(), "b".into(), "c".into()]);
+        board.record(&game(0, 1, Outcome::Win(0)));
+        board.record(&game(2, 1, Outcome::Draw));
+
+        let [a, b, c] = [&board.records[0], &board.records[1], &board.records[2]];
+        // Ratings only move between players, so they still average out.
+        assert!((a.elo + b.elo + c.elo - 3.0 * INITIAL_ELO).abs() < 1e-9);
+        assert_eq!(a.elo, INITIAL_ELO + ELO_K / 2.0);
+        assert!(
+            c.elo < INITIAL_ELO,
+            "a draw against a weaker bot costs rating"
+        );
+        assert_eq!((b.wins, b.draws, b.losses), (0, 1, 1));
+        assert_eq!(b.average_damage(), 10.0);
+        assert_eq!(board.ranking(), vec![0, 2, 1]);
+
+        board.record_bye(1);
+        assert_eq!(board.records[1].points(), 1.5);
+    }
+
+    #[test]
+    fn test_tables() {
+        let mut board = Leaderboard::new(vec!["champion".into(), "Hunter (scripted)".into()]);
+        board.record(&game(1, 0, Outcome::Win(0)));
+
+        let markdown = board.to_markdown();
+        let rows: Vec<&str> = markdown.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[2].starts_with("| 1 | Hunter (scripted) | 1 | 1 | 0 | 0 | 1.0 | 1516 |"));
+
+        let csv = board.to_csv();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("1,Hunter (scripted),1,1,0,0,1,1516.0,30.00")
+        );
+    }
+}
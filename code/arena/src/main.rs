@@ -0,0 +1,249 @@
+//! Headless bot-only tournaments: every model in a directory plus any scripted
+//! difficulties play round robin or Swiss, and the results come out as a leaderboard.
+//! Used to pick which model ships as the default `TrainedKiller`.
+
+mod leaderboard;
+mod schedule;
+
+use clap::{Parser, ValueEnum};
+use common::ai::BotDifficulty;
+use common::game::MAX_TEAM_SIZE;
+use common::protocol::{MapDefinition, MapName, Team};
+use common::rl::contest::{Contestant, ContestantSpec, play_match};
+use leaderboard::{GameResult, Leaderboard};
+use schedule::SwissHistory;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// Everyone plays everyone.
+    RoundRobin,
+    /// A few rounds, each pairing entrants with similar scores.
+    Swiss,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Every `*.bin` model in this directory enters.
+    #[arg(long, default_value = "assets/models")]
+    models: PathBuf,
+
+    /// Scripted bots to enter as well, e.g. `hunter,terminator`.
+    #[arg(long, value_delimiter = ',')]
+    scripted: Vec<BotDifficulty>,
+
+    #[arg(long, value_enum, default_value_t = Format::RoundRobin)]
+    format: Format,
+
+    /// Number of Swiss rounds [default: enough to leave one unbeaten bot]
+    #[arg(long)]
+    rounds: Option<usize>,
+
+    /// Games per pairing; sides alternate every game.
+    #[arg(long, default_value_t = 2)]
+    games: u32,
+
+    #[arg(long, default_value = "basic")]
+    map: MapName,
+
+    /// Tanks each bot fields per game, up to 8.
+    #[arg(long, default_value_t = 4)]
+    team_size: usize,
+
+    /// Time limit of a game in seconds; afterwards the team with more health left wins.
+    #[arg(long, default_value_t = 100)]
+    duration: u64,
+
+    /// Games played in parallel [default: number of CPUs]
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Also write the leaderboard as CSV to this file.
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Also write the markdown leaderboard to this file.
+    #[arg(long)]
+    markdown: Option<PathBuf>,
+}
+
+/// Everything a worker needs to play its share of games.
+struct Arena<'a> {
+    entrants: &'a [ContestantSpec],
+    models_dir: &'a Path,
+    map: MapDefinition,
+    team_size: usize,
+    duration: Duration,
+    games: u32,
+    threads: usize,
+}
+
+impl Arena<'_> {
+    /// Plays every game of `pairs`, spread over the worker threads. Results come back in
+    /// schedule order, so the ratings don't depend on which thread finished first.
+    fn play(&self, pairs: &[(usize, usize)], round: u64) -> Result<Vec<GameResult>, String> {
+        let jobs: Vec<(usize, u32)> = (0..pairs.len())
+            .flat_map(|pair| (0..self.games).map(move |game| (pair, game)))
+            .collect();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![None; jobs.len()]);
+
+        std::thread::scope(|scope| -> Result<(), String> {
+            let workers: Vec<_> = (0..self.threads.min(jobs.len()))
+                .map(|_| {
+                    scope.spawn(|| -> Result<(), String> {
+                        // Models are loaded per worker, as they can't be shared between
+                        // threads.
+                        let mut contestants = self
+                            .entrants
+                            .iter()
+                            .map(|spec| Contestant::load(spec, self.models_dir).map(Some))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(&(pair, game)) = jobs.get(index) else {
+                                return Ok(());
+                            };
+                            let (a, b) = pairs[pair];
+                            let sides = [contestants[a].take(), contestants[b].take()];
+                            let sides = sides.map(|side| side.expect("entrant plays itself"));
+                            let a_team = if game % 2 == 0 { Team::Blue } else { Team::Red };
+                            let seed = (round << 32) ^ ((index as u64) << 8) ^ u64::from(game);
+                            let report = play_match(
+                                &sides,
+                                a_team,
+                                &self.map,
+                                Some(self.team_size),
+                                self.duration,
+                                seed,
+                                None,
+                            );
+                            let [side_a, side_b] = sides;
+                            contestants[a] = Some(side_a);
+                            contestants[b] = Some(side_b);
+                            results.lock().unwrap()[index] = Some(GameResult {
+                                entrants: [a, b],
+                                outcome: report.outcome,
+                                damage: report.damage,
+                            });
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                worker.join().map_err(|_| "A worker thread panicked")??;
+            }
+            Ok(())
+        })?;
+
+        Ok(results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.expect("every game was played"))
+            .collect())
+    }
+}
+
+fn model_specs(dir: &Path) -> Result<Vec<ContestantSpec>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    Ok(names.into_iter().map(ContestantSpec::Model).collect())
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut entrants = model_specs(&args.models)?;
+    entrants.extend(args.scripted.iter().copied().map(ContestantSpec::Scripted));
+    if entrants.len() < 2 {
+        return Err("The arena needs at least two bots".to_string());
+    }
+    if args.games == 0 || args.team_size == 0 {
+        return Err("--games and --team-size must be at least 1".to_string());
+    }
+    if args.team_size > MAX_TEAM_SIZE {
+        return Err(format!("--team-size can be at most {}", MAX_TEAM_SIZE));
+    }
+    // Fail on a broken model now rather than in the middle of the tournament
+    for spec in &entrants {
+        Contestant::load(spec, &args.models)?;
+    }
+
+    let arena = Arena {
+        entrants: &entrants,
+        models_dir: &args.models,
+        map: MapDefinition::load_name(args.map),
+        team_size: args.team_size,
+        duration: Duration::from_secs(args.duration),
+        games: args.games,
+        threads: args
+            .threads
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .max(1),
+    };
+    let mut board = Leaderboard::new(entrants.iter().map(ToString::to_string).collect());
+
+    println!(
+        "{} bots, {:?} on {:?}, {} games per pairing, {} threads",
+        entrants.len(),
+        args.format,
+        args.map,
+        args.games,
+        arena.threads
+    );
+    let start = Instant::now();
+    match args.format {
+        Format::RoundRobin => {
+            for result in arena.play(&schedule::round_robin(entrants.len()), 0)? {
+                board.record(&result);
+            }
+        }
+        Format::Swiss => {
+            let rounds = args
+                .rounds
+                .unwrap_or_else(|| schedule::default_swiss_rounds(entrants.len()));
+            let mut history = SwissHistory::default();
+            for round in 0..rounds {
+                let (pairs, bye) = history.next_round(&board.ranking());
+                if let Some(entrant) = bye {
+                    board.record_bye(entrant);
+                }
+                for result in arena.play(&pairs, round as u64)? {
+                    board.record(&result);
+                }
+                println!("Round {}/{} done", round + 1, rounds);
+            }
+        }
+    }
+    println!("Finished in {:.1}s\n", start.elapsed().as_secs_f32());
+
+    let markdown = board.to_markdown();
+    print!("{}", markdown);
+    if let Some(path) = &args.markdown {
+        std::fs::write(path, &markdown)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &args.csv {
+        std::fs::write(path, board.to_csv())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
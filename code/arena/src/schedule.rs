@@ -0,0 +1,90 @@
+//! Who plays whom: every pair once (round robin), or rounds of entrants with similar
+//! scores (Swiss).
+
+use std::collections::HashSet;
+
+/// Every pair of entrants, once.
+pub fn round_robin(count: usize) -> Vec<(usize, usize)> {
+    (0..count)
+        .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+        .collect()
+}
+
+/// Enough Swiss rounds for a single unbeaten entrant to remain.
+pub fn default_swiss_rounds(count: usize) -> usize {
+    count.next_power_of_two().trailing_zeros().max(1) as usize
+}
+
+/// Remembers who played whom and who sat out, so Swiss rounds avoid repeats.
+#[derive(Default)]
+pub struct SwissHistory {
+    played: HashSet<(usize, usize)>,
+    byes: HashSet<usize>,
+}
+
+impl SwissHistory {
+    fn key(a: usize, b: usize) -> (usize, usize) {
+        (a.min(b), a.max(b))
+    }
+
+    pub fn has_played(&self, a: usize, b: usize) -> bool {
+        self.played.contains(&Self::key(a, b))
+    }
+
+    /// Pairs neighbours in `ranking` (best first) who haven't met yet, falling back to a
+    /// rematch when there is no other choice. With an odd count, the lowest-ranked
+    /// entrant without a bye yet sits out. Returns the pairs and the entrant sitting out.
+    pub fn next_round(&mut self, ranking: &[usize]) -> (Vec<(usize, usize)>, Option<usize>) {
+        let mut unpaired = ranking.to_vec();
+        let bye = (unpaired.len() % 2 == 1).then(|| {
+            let position = unpaired
+                .iter()
+                .rposition(|e| !self.byes.contains(e))
+                .unwrap_or(unpaired.len() - 1);
+            unpaired.remove(position)
+        });
+        if let Some(entrant) = bye {
+            self.byes.insert(entrant);
+        }
+
+        let mut pairs = Vec::new();
+        while !unpaired.is_empty() {
+            let a = unpaired.remove(0);
+            let position = unpaired
+                .iter()
+                .position(|&b| !self.has_played(a, b))
+                .unwrap_or(0);
+            let b = unpaired.remove(position);
+            self.played.insert(Self::key(a, b));
+            pairs.push((a, b));
+        }
+        (pairs, bye)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin() {
+        assert_eq!(round_robin(3), vec![(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(round_robin(8).len(), 28);
+        assert_eq!(default_swiss_rounds(2), 1);
+        assert_eq!(default_swiss_rounds(5), 3);
+    }
+
+    #[test]
+    fn test_swiss_avoids_rematches_and_repeat_byes() {
+        let mut history = SwissHistory::default();
+        let (pairs, bye) = history.next_round(&[0, 1, 2, 3, 4]);
+        assert_eq!(pairs, vec![(0, 1), (2, 3)]);
+        assert_eq!(bye, Some(4));
+
+        // Same standings again: 0 and 1 already met, and 4 already sat out.
+        let (pairs, bye) = history.next_round(&[0, 1, 2, 3, 4]);
+        assert_eq!(bye, Some(3));
+        assert_eq!(pairs, vec![(0, 2), (1, 4)]);
+        assert!(pairs.iter().all(|&(a, b)| a != b));
+    }
+}
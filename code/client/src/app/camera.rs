@@ -0,0 +1,479 @@
+use crate::i18n::tr;
+use std::collections::HashMap;
+
+use common::protocol::{Arena, KillEvent, MapDefinition, PlayerId, Projectile, Tank};
+use glam::Vec2;
+use macroquad::prelude::*;
+
+use crate::app::indicators::hit_by;
+use crate::ui::{TEXT_SMALL, Text, calc_transform};
+
+const FOLLOW_ZOOM: f32 = 2.0;
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 4.0;
+/// Free camera pan speed, in map units per second at zoom 1.
+const PAN_SPEED: f32 = 600.0;
+/// How fast the recorded action around a player fades (fraction lost per second).
+const ACTION_DECAY: f32 = 0.5;
+/// Minimum time the director stays on a target before switching to another one.
+const DIRECTOR_HOLD_SECS: f32 = 2.0;
+/// Action a kill adds for the killer, on top of the damage that led to it.
+const KILL_ACTION: f32 = 50.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum CameraMode {
+    /// Whole map visible.
+    Overview,
+    /// Locked onto one player's tank.
+    Follow(PlayerId),
+    /// Manually panned and zoomed.
+    Free,
+    /// Automatically follows whoever is in the middle of the most action.
+    Director,
+    /// One whole arena of a multi-arena map.
+    Arena(usize),
+}
+
+/// Camera used when watching a match without controlling a tank.
+///
+/// Controls: `1`-`9` lock onto a player (or click their tank), `F` free camera
+/// (`WASD` to pan, mouse wheel to zoom), `TAB` auto-director, `Q`/`E` previous/next arena
+/// of a multi-arena map, `0` overview.
+pub(crate) struct SpectatorCamera {
+    mode: CameraMode,
+    center: Vec2,
+    zoom: f32,
+    /// Damage and kills around each player lately, fading with [`ACTION_DECAY`]
+    action: HashMap<PlayerId, f32>,
+    director_target: Option<PlayerId>,
+    director_hold: f32,
+}
+
+impl SpectatorCamera {
+    pub fn new() -> Self {
+        Self {
+            mode: CameraMode::Overview,
+            center: Vec2::ZERO,
+            zoom: 1.0,
+            action: HashMap::new(),
+            director_target: None,
+            director_hold: 0.0,
+        }
+    }
+
+    /// The player currently being watched, if any.
+    pub fn target(&self) -> Option<PlayerId> {
+        match self.mode {
+            CameraMode::Follow(id) => Some(id),
+            CameraMode::Director => self.director_target,
+            CameraMode::Overview | CameraMode::Free | CameraMode::Arena(_) => None,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+        self.director_hold = 0.0;
+    }
+
+    /// Reads camera controls from the keyboard and mouse.
+    pub fn handle_input(&mut self, map: &MapDefinition, tanks: &[Tank], dt: f32) {
+        let mut players: Vec<&Tank> = tanks.iter().collect();
+        players.sort_by_key(|t| t.player_info.id);
+
+        const NUMBER_KEYS: [KeyCode; 9] = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+        for (key, tank) in NUMBER_KEYS.iter().zip(&players) {
+            if is_key_pressed(*key) {
+                self.set_mode(CameraMode::Follow(tank.player_info.id));
+            }
+        }
+
+        if is_key_pressed(KeyCode::Key0) {
+            self.set_mode(CameraMode::Overview);
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            self.set_mode(CameraMode::Director);
+        }
+        if is_key_pressed(KeyCode::F) {
+            self.set_mode(CameraMode::Free);
+        }
+        let step = is_key_pressed(KeyCode::E) as isize - is_key_pressed(KeyCode::Q) as isize;
+        if step != 0 && !map.arenas.is_empty() {
+            self.step_arena(map, step);
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let (scaling, x_off, y_off) = self.transform(map);
+            let (mx, my) = mouse_position();
+            let world = Vec2::new((mx - x_off) / scaling, (my - y_off) / scaling);
+            if let Some(tank) = tanks
+                .iter()
+                .find(|t| t.position.distance(world) <= t.radius * 1.5)
+            {
+                self.set_mode(CameraMode::Follow(tank.player_info.id));
+            }
+        }
+
+        if self.mode == CameraMode::Free {
+            let mut pan = Vec2::ZERO;
+            if is_key_down(KeyCode::W) {
+                pan.y -= 1.0;
+            }
+            if is_key_down(KeyCode::S) {
+                pan.y += 1.0;
+            }
+            if is_key_down(KeyCode::A) {
+                pan.x -= 1.0;
+            }
+            if is_key_down(KeyCode::D) {
+                pan.x += 1.0;
+            }
+            self.center += pan.normalize_or_zero() * PAN_SPEED / self.zoom * dt;
+
+            let (_, wheel) = mouse_wheel();
+            if wheel != 0.0 {
+                self.zoom = (self.zoom * if wheel > 0.0 { 1.1 } else { 1.0 / 1.1 })
+                    .clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+        }
+    }
+
+    /// Moves `step` arenas on from the watched one, or to the first/last arena.
+    fn step_arena(&mut self, map: &MapDefinition, step: isize) {
+        let count = map.arenas.len() as isize;
+        let next = match self.mode {
+            CameraMode::Arena(i) => (i as isize + step).rem_euclid(count),
+            _ if step > 0 => 0,
+            _ => count - 1,
+        };
+        self.set_mode(CameraMode::Arena(next as usize));
+    }
+
+    /// Counts `amount` of damage dealt to `victim`, by `attacker` if known, as action
+    /// around both.
+    pub fn record_damage(&mut self, attacker: Option<PlayerId>, victim: PlayerId, amount: f32) {
+        if amount <= 0.0 {
+            return;
+        }
+        *self.action.entry(victim).or_default() += amount;
+        if let Some(attacker) = attacker {
+            *self.action.entry(attacker).or_default() += amount;
+        }
+    }
+
+    /// Records the health every tank lost between two snapshots, crediting the owner of
+    /// the enemy projectile closest to it before the hit.
+    pub fn observe_hits(
+        &mut self,
+        previous_tanks: &[Tank],
+        previous_projectiles: &[Projectile],
+        tanks: &[Tank],
+    ) {
+        for before in previous_tanks {
+            let id = before.player_info.id;
+            let Some(after) = tanks.iter().find(|t| t.player_info.id == id) else {
+                continue;
+            };
+            let attacker = hit_by(before, previous_projectiles).map(|p| p.owner_info.id);
+            self.record_damage(attacker, id, before.health - after.health);
+        }
+    }
+
+    /// Counts a kill as action around the killer.
+    pub fn record_kill(&mut self, kill: &KillEvent) {
+        *self.action.entry(kill.killer_info.id).or_default() += KILL_ACTION;
+    }
+
+    /// Fades the recorded action and moves the camera towards its target.
+    pub fn update(&mut self, map: &MapDefinition, tanks: &[Tank], dt: f32) {
+        let decay = (1.0 - ACTION_DECAY * dt).max(0.0);
+        for score in self.action.values_mut() {
+            *score *= decay;
+        }
+
+        if self.mode == CameraMode::Director {
+            self.director_hold -= dt;
+            let hottest = self
+                .action
+                .iter()
+                .filter(|(id, _)| tanks.iter().any(|t| t.player_info.id == **id))
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(id, _)| *id);
+            let target_alive = self
+                .director_target
+                .is_some_and(|id| tanks.iter().any(|t| t.player_info.id == id));
+
+            if !target_alive || self.director_hold <= 0.0 {
+                match hottest {
+                    Some(id) => {
+                        self.director_target = Some(id);
+                        self.director_hold = DIRECTOR_HOLD_SECS;
+                    }
+                    None if !target_alive => {
+                        self.director_target = tanks.first().map(|t| t.player_info.id);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let map_center = Vec2::new(map.width / 2., map.height / 2.);
+        match self.mode {
+            CameraMode::Overview => {
+                self.center = map_center;
+                self.zoom = 1.0;
+            }
+            CameraMode::Follow(_) | CameraMode::Director => {
+                let target = self
+                    .target()
+                    .and_then(|id| tanks.iter().find(|t| t.player_info.id == id));
+                match target {
+                    Some(tank) => {
+                        self.center = tank.position;
+                        self.zoom = FOLLOW_ZOOM;
+                    }
+                    // Watched player is gone (e.g. dead), fall back to the whole map.
+                    None => {
+                        self.center = map_center;
+                        self.zoom = 1.0;
+                    }
+                }
+            }
+            CameraMode::Free => {
+                self.center = self
+                    .center
+                    .clamp(Vec2::ZERO, Vec2::new(map.width, map.height));
+            }
+            CameraMode::Arena(i) => {
+                self.center = map.arena(i).center();
+            }
+        }
+    }
+
+    /// World-to-screen transform for the current camera, in the same format as
+    /// [`calc_transform`]: `(scaling, x_offset, y_offset)`.
+    pub fn transform(&self, map: &MapDefinition) -> (f32, f32, f32) {
+        match self.mode {
+            CameraMode::Overview => return calc_transform(map.width, map.height),
+            CameraMode::Arena(i) => return arena_transform(map.arena(i)),
+            _ => {}
+        }
+        Self::transform_for(map, self.center, self.zoom, screen_width(), screen_height())
+    }
+
+    fn transform_for(
+        map: &MapDefinition,
+        center: Vec2,
+        zoom: f32,
+        screen_w: f32,
+        screen_h: f32,
+    ) -> (f32, f32, f32) {
+        let base = (screen_w / map.width).min(screen_h / map.height);
+        let scaling = base * zoom;
+        (
+            scaling,
+            screen_w / 2. - center.x * scaling,
+            screen_h / 2. - center.y * scaling,
+        )
+    }
+
+    pub fn draw_hud(&self, map: &MapDefinition, tanks: &[Tank]) {
+        let watched = self
+            .target()
+            .and_then(|id| tanks.iter().find(|t| t.player_info.id == id))
+            .map(|t| t.player_info.nickname.as_str());
+
+        let label = match (self.mode, watched) {
+            (CameraMode::Overview, _) => tr!("camera.overview"),
+            (CameraMode::Free, _) => tr!("camera.free"),
+            (CameraMode::Follow(_), Some(name)) => tr!("camera.following", name = name),
+            (CameraMode::Director, Some(name)) => tr!("camera.director", name = name),
+            (CameraMode::Arena(i), _) => arena_label(map, i),
+            (_, None) => tr!("camera.overview"),
+        };
+        let hud = if map.arenas.is_empty() {
+            tr!("camera.hud", label = label)
+        } else {
+            tr!("camera.hud_arenas", label = label)
+        };
+
+        Text::new_scaled(TEXT_SMALL).draw(
+            &hud,
+            crate::ui::CANONICAL_SCREEN_MID_X,
+            crate::ui::CANONICAL_SCREEN_HEIGHT - 20.,
+        );
+    }
+}
+
+/// Fits `arena` to the screen, like [`calc_transform`] does for a whole map.
+pub(crate) fn arena_transform(arena: Arena) -> (f32, f32, f32) {
+    let size = arena.size();
+    let (scaling, x_offset, y_offset) = calc_transform(size.x, size.y);
+    (
+        scaling,
+        x_offset - arena.min.x * scaling,
+        y_offset - arena.min.y * scaling,
+    )
+}
+
+/// Transform for playing: the arena around `focus` on a multi-arena map, otherwise the
+/// whole map.
+pub(crate) fn board_transform(map: &MapDefinition, focus: Option<Vec2>) -> (f32, f32, f32) {
+    match focus.and_then(|pos| map.arena_index(pos)) {
+        Some(i) if !map.arenas.is_empty() => arena_transform(map.arena(i)),
+        _ => calc_transform(map.width, map.height),
+    }
+}
+
+/// "Arena 2", or "Final" for the last arena of a multi-arena map.
+pub(crate) fn arena_label(map: &MapDefinition, arena: usize) -> String {
+    if arena + 1 == map.arenas.len() {
+        tr!("camera.final_arena")
+    } else {
+        tr!("camera.arena", number = arena + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::{GameMode, MapName, Team};
+
+    fn tank(id: PlayerId, pos: Vec2, health: f32) -> Tank {
+        let mut tank = Tank::new(PlayerInfo::new(id, format!("P{}", id), Team::Blue), pos);
+        tank.health = health;
+        tank
+    }
+
+    #[test]
+    fn test_transform_centers_on_target() {
+        let map = MapDefinition::load();
+        let center = Vec2::new(300., 200.);
+        let (scaling, x_off, y_off) =
+            SpectatorCamera::transform_for(&map, center, 2.0, 1000., 500.);
+
+        assert_eq!(center.x * scaling + x_off, 500.);
+        assert_eq!(center.y * scaling + y_off, 250.);
+    }
+
+    #[test]
+    fn test_follow_tracks_player_position() {
+        let map = MapDefinition::load();
+        let mut camera = SpectatorCamera::new();
+        camera.set_mode(CameraMode::Follow(1));
+
+        let tanks = vec![
+            tank(0, Vec2::new(10., 10.), 100.),
+            tank(1, Vec2::new(400., 300.), 100.),
+        ];
+        camera.update(&map, &tanks, 0.016);
+
+        assert_eq!(camera.center, Vec2::new(400., 300.));
+        assert_eq!(camera.target(), Some(1));
+    }
+
+    #[test]
+    fn test_director_follows_damaged_player() {
+        let map = MapDefinition::load();
+        let mut camera = SpectatorCamera::new();
+        camera.set_mode(CameraMode::Director);
+
+        let mut tanks = vec![
+            tank(0, Vec2::new(10., 10.), 100.),
+            tank(1, Vec2::new(400., 300.), 100.),
+        ];
+        camera.update(&map, &tanks, 0.016);
+
+        // Health changes alone, e.g. a new round, are no action
+        tanks[0].health = 20.;
+        camera.update(&map, &tanks, 0.016);
+        camera.record_damage(Some(0), 1, 20.);
+        camera.update(&map, &tanks, 0.016);
+        assert!(camera.action[&0] > 0. && camera.action[&1] > 0.);
+        assert!((camera.action[&0] - camera.action[&1]).abs() < 1e-4);
+
+        camera.record_kill(&KillEvent {
+            killer_info: tanks[1].player_info.clone(),
+            victim_info: tanks[0].player_info.clone(),
+        });
+        tanks.remove(0);
+        camera.update(&map, &tanks, 0.016);
+
+        assert_eq!(camera.target(), Some(1));
+        assert_eq!(camera.center, Vec2::new(400., 300.));
+    }
+
+    #[test]
+    fn test_snapshot_hits_credit_victim_and_shooter() {
+        let mut camera = SpectatorCamera::new();
+        let mut shooter = tank(2, Vec2::new(400., 100.), 100.);
+        shooter.player_info.team = Team::Red;
+        let before = vec![tank(0, Vec2::new(100., 100.), 100.), shooter.clone()];
+        let projectile = Projectile {
+            id: 0,
+            kind: common::protocol::ProjectileKind::Bullet,
+            owner_info: shooter.player_info.clone(),
+            position: Vec2::new(120., 100.),
+            velocity: Vec2::new(-500., 0.),
+            radius: 5.,
+            age: 0.,
+        };
+        let mut after = before.clone();
+        after[0].health = 75.;
+
+        camera.observe_hits(&before, &[projectile], &after);
+        assert_eq!(camera.action[&0], 25.);
+        assert_eq!(camera.action[&2], 25.);
+
+        // Health coming back, e.g. at a new round, is no action
+        camera.observe_hits(&after, &[], &before);
+        assert_eq!(camera.action[&0], 25.);
+    }
+
+    #[test]
+    fn test_director_holds_target_briefly() {
+        let map = MapDefinition::load();
+        let mut camera = SpectatorCamera::new();
+        camera.set_mode(CameraMode::Director);
+
+        let tanks = vec![tank(0, Vec2::ZERO, 100.), tank(1, Vec2::ZERO, 100.)];
+        camera.update(&map, &tanks, 0.016);
+        camera.record_damage(None, 0, 10.);
+        camera.update(&map, &tanks, 0.016);
+        assert_eq!(camera.target(), Some(0));
+
+        // More action elsewhere, but the director shouldn't cut away immediately.
+        camera.record_damage(None, 1, 50.);
+        camera.update(&map, &tanks, 0.016);
+        assert_eq!(camera.target(), Some(0));
+
+        for _ in 0..(DIRECTOR_HOLD_SECS / 0.016) as usize + 1 {
+            camera.update(&map, &tanks, 0.016);
+        }
+        assert_eq!(camera.target(), Some(1));
+    }
+
+    #[test]
+    fn test_arena_cycling() {
+        let map = MapDefinition::for_mode(MapName::Basic, GameMode::MultiArena);
+        let mut camera = SpectatorCamera::new();
+        camera.step_arena(&map, -1);
+        assert_eq!(camera.mode, CameraMode::Arena(2));
+        camera.step_arena(&map, 1);
+        assert_eq!(camera.mode, CameraMode::Arena(0));
+
+        camera.update(&map, &[], 0.016);
+        assert_eq!(camera.center, map.arena(0).center());
+        assert_eq!(camera.target(), None);
+    }
+}
@@ -0,0 +1,596 @@
+use crate::app::game::Game;
+use crate::app::hud;
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::presence::Presence;
+use crate::settings::SETTINGS_PATH;
+use crate::stats::ShotCounter;
+use crate::ui::{
+    self, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, CANONICAL_SCREEN_MID_Y, TEXT_LARGE,
+    calc_transform,
+};
+use bincode::{Decode, Encode};
+use common::ai::{BotAgent, BotDifficulty};
+use common::game::engine::{GameEngine, SAVE_EXTENSION, SaveError};
+use common::game::player::PlayerInfo;
+use common::net::protocol::{MapDefinition, MapName, PlayerId, Tank, Team};
+use macroquad::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const HUMAN_ID: PlayerId = 0;
+pub(crate) const MAX_STARS: u8 = 3;
+pub(crate) const SAVES_DIR: &str = "assets/saves";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Goal {
+    /// Destroy every opponent
+    Eliminate,
+    /// Stay alive for this many seconds
+    Survive { secs: f32 },
+    /// Spend this many seconds in total inside the circle in the middle of the map
+    HoldPoint { secs: f32, radius: f32 },
+}
+
+/// A predefined local scenario: the player alone against scripted bots.
+pub(crate) struct Challenge {
+    /// Key of the best result in the settings file; never change it once released
+    pub id: &'static str,
+    pub name_key: &'static str,
+    pub goal: Goal,
+    pub map: MapName,
+    pub opponents: &'static [BotDifficulty],
+}
+
+/// Ordered from easiest to hardest; each one unlocks after the previous is completed.
+pub(crate) const CHALLENGES: &[Challenge] = &[
+    Challenge {
+        id: "duel",
+        name_key: "challenge.duel",
+        goal: Goal::Eliminate,
+        map: MapName::Basic,
+        opponents: &[BotDifficulty::Wanderer],
+    },
+    Challenge {
+        id: "hunters",
+        name_key: "challenge.hunters",
+        goal: Goal::Eliminate,
+        map: MapName::Basic,
+        opponents: &[BotDifficulty::Hunter, BotDifficulty::Hunter],
+    },
+    Challenge {
+        id: "survive",
+        name_key: "challenge.survive",
+        goal: Goal::Survive { secs: 60. },
+        map: MapName::Loss,
+        opponents: &[
+            BotDifficulty::Terminator,
+            BotDifficulty::Terminator,
+            BotDifficulty::Terminator,
+        ],
+    },
+    Challenge {
+        id: "hold",
+        name_key: "challenge.hold",
+        goal: Goal::HoldPoint {
+            secs: 20.,
+            radius: 120.,
+        },
+        map: MapName::Basic,
+        opponents: &[
+            BotDifficulty::Hunter,
+            BotDifficulty::Hunter,
+            BotDifficulty::Terminator,
+        ],
+    },
+    Challenge {
+        id: "last_stand",
+        name_key: "challenge.last_stand",
+        goal: Goal::Eliminate,
+        map: MapName::Tiga,
+        opponents: &[
+            BotDifficulty::Terminator,
+            BotDifficulty::Terminator,
+            BotDifficulty::Terminator,
+            BotDifficulty::Terminator,
+        ],
+    },
+];
+
+/// The first challenge is always open, the rest need a star on the one before.
+pub(crate) fn is_unlocked(index: usize, stars: &BTreeMap<String, u8>) -> bool {
+    index == 0
+        || CHALLENGES
+            .get(index - 1)
+            .and_then(|previous| stars.get(previous.id))
+            .is_some_and(|&s| s > 0)
+}
+
+/// Stars for a completed challenge, more for finishing with more health left.
+pub(crate) fn stars_for_win(health_fraction: f32) -> u8 {
+    if health_fraction >= 0.75 {
+        3
+    } else if health_fraction >= 0.4 {
+        2
+    } else {
+        1
+    }
+}
+
+/// What is known about a running challenge at the end of a tick.
+struct Status {
+    /// Health share of the player's tank, `None` once it is destroyed
+    health: Option<f32>,
+    enemies_alive: usize,
+    elapsed: f32,
+    held: f32,
+}
+
+/// Stars earned once the challenge is over (0 for a loss), `None` while it goes on.
+fn outcome(goal: Goal, status: &Status) -> Option<u8> {
+    let Some(health) = status.health else {
+        return Some(0);
+    };
+    let won = status.enemies_alive == 0
+        || match goal {
+            Goal::Eliminate => false,
+            Goal::Survive { secs } => status.elapsed >= secs,
+            Goal::HoldPoint { secs, .. } => status.held >= secs,
+        };
+    won.then(|| stars_for_win(health))
+}
+
+/// The one slot a challenge run can be saved to.
+pub(crate) fn save_path() -> PathBuf {
+    Path::new(SAVES_DIR).join(format!("challenge.{}", SAVE_EXTENSION))
+}
+
+/// How far a saved run got, written after its engine.
+#[derive(Encode, Decode)]
+struct SavedProgress {
+    challenge: String,
+    elapsed: f32,
+    held: f32,
+}
+
+enum PauseButton {
+    Resume,
+    SaveAndExit,
+    Exit,
+}
+
+pub(crate) struct ChallengeView {
+    index: usize,
+    engine: GameEngine,
+    elapsed: f32,
+    /// Seconds spent on the control point so far
+    held: f32,
+    /// Stars earned, once the challenge is over
+    result: Option<u8>,
+    shots: ShotCounter,
+    paused: bool,
+    pause_clicked: Option<PauseButton>,
+    save_error: Option<String>,
+}
+
+impl ChallengeView {
+    pub fn new(index: usize) -> Self {
+        Self::with_engine(index, Self::setup_engine(&CHALLENGES[index]))
+    }
+
+    fn with_engine(index: usize, engine: GameEngine) -> Self {
+        Self {
+            index,
+            engine,
+            elapsed: 0.,
+            held: 0.,
+            result: None,
+            shots: ShotCounter::default(),
+            paused: false,
+            pause_clicked: None,
+            save_error: None,
+        }
+    }
+
+    /// Writes the run to the save slot, replacing what was saved there before.
+    fn save(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.engine.write_saved(&mut writer)?;
+        let progress = SavedProgress {
+            challenge: self.challenge().id.to_string(),
+            elapsed: self.elapsed,
+            held: self.held,
+        };
+        bincode::encode_into_std_write(progress, &mut writer, bincode::config::standard())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Picks up the run saved at `path`. The save is used up, so a run can't be retried
+    /// from the same point over and over.
+    pub fn resume(path: &Path) -> Result<Self, String> {
+        let mut reader = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+        let engine = GameEngine::read_saved(&mut reader).map_err(|e| e.to_string())?;
+        let progress: SavedProgress =
+            bincode::decode_from_std_read(&mut reader, bincode::config::standard())
+                .map_err(|e| e.to_string())?;
+        let index = CHALLENGES
+            .iter()
+            .position(|c| c.id == progress.challenge)
+            .ok_or_else(|| tr!("challenges.unknown", id = progress.challenge))?;
+        let _ = std::fs::remove_file(path);
+
+        Ok(Self {
+            elapsed: progress.elapsed,
+            held: progress.held,
+            ..Self::with_engine(index, engine)
+        })
+    }
+
+    fn challenge(&self) -> &'static Challenge {
+        &CHALLENGES[self.index]
+    }
+
+    fn setup_engine(challenge: &Challenge) -> GameEngine {
+        let mut engine = GameEngine::new(MapDefinition::load_name(challenge.map));
+        let spawns = |team| {
+            engine
+                .map
+                .spawn_points
+                .iter()
+                .filter(move |(t, _)| *t == team)
+                .map(|(_, pos)| *pos)
+        };
+        let human_spawn = spawns(Team::Blue).next();
+        let bot_spawns: Vec<Vec2> = spawns(Team::Red).collect();
+
+        let seed: u64 = ::rand::random();
+        let mut tanks = Vec::new();
+        let mut bots = Vec::new();
+        if let Some(pos) = human_spawn {
+            let info = PlayerInfo::new(HUMAN_ID, tr!("challenge.player"), Team::Blue);
+            tanks.push(Tank::new(info, pos));
+        }
+        for (i, (difficulty, pos)) in challenge.opponents.iter().zip(bot_spawns).enumerate() {
+            let id = (i + 1) as PlayerId;
+            let info = PlayerInfo::new(id, format!("{:?} {}", difficulty, i + 1), Team::Red);
+            bots.push(BotAgent::new(
+                info.clone(),
+                *difficulty,
+                seed ^ u64::from(id),
+            ));
+            tanks.push(Tank::new(info, pos));
+        }
+        engine.tanks = tanks;
+        engine.bots = bots;
+        engine
+    }
+
+    fn restart(&mut self) {
+        *self = Self::new(self.index);
+    }
+
+    fn control_point(&self) -> Vec2 {
+        vec2(self.engine.map.width / 2., self.engine.map.height / 2.)
+    }
+
+    fn me(&self) -> Option<&Tank> {
+        self.engine
+            .tanks
+            .iter()
+            .find(|t| t.player_info.id == HUMAN_ID && t.health > 0.)
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            health: self.me().map(hud::health_fraction),
+            enemies_alive: self
+                .engine
+                .tanks
+                .iter()
+                .filter(|t| t.player_info.team == Team::Red && t.health > 0.)
+                .count(),
+            elapsed: self.elapsed,
+            held: self.held,
+        }
+    }
+
+    /// Adds the run to the career stats and keeps the best star rating.
+    fn record_result(&mut self, stars: u8, ctx: &mut AppContext) {
+        let best = ctx
+            .settings
+            .challenge_stars
+            .entry(self.challenge().id.to_string())
+            .or_insert(0);
+        *best = (*best).max(stars);
+
+        let stats = &mut ctx.settings.stats;
+        stats.challenge_runs += 1;
+        if stars > 0 {
+            stats.challenges_completed += 1;
+        }
+        stats.add_shots(&mut self.shots);
+        if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+    }
+
+    fn objective_text(&self, status: &Status) -> String {
+        match self.challenge().goal {
+            Goal::Eliminate => tr!("challenge.goal_eliminate", count = status.enemies_alive),
+            Goal::Survive { secs } => tr!(
+                "challenge.goal_survive",
+                seconds = (secs - status.elapsed).max(0.).ceil()
+            ),
+            Goal::HoldPoint { secs, .. } => tr!(
+                "challenge.goal_hold",
+                held = status.held.floor(),
+                total = secs
+            ),
+        }
+    }
+}
+
+impl ChallengeView {
+    /// Buttons over the paused match, and why saving failed if it did.
+    fn draw_pause_menu(&mut self, has_input: bool) {
+        draw_rectangle(
+            0.,
+            0.,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+        let mut layout = ui::Layout::new(CANONICAL_SCREEN_MID_Y - 120., 30.);
+        ui::Text::new_scaled(TEXT_LARGE).draw(
+            &tr!("challenge.paused"),
+            CANONICAL_SCREEN_MID_X,
+            layout.next(),
+        );
+        layout.add(50.);
+
+        self.pause_clicked = None;
+        // A finished run has nothing left to save
+        let can_save = self.result.is_none();
+        for (button, label) in [
+            (PauseButton::Resume, tr!("game_menu.resume")),
+            (PauseButton::SaveAndExit, tr!("challenge.save_exit")),
+            (PauseButton::Exit, tr!("challenge.exit")),
+        ] {
+            let enabled = has_input && (can_save || !matches!(button, PauseButton::SaveAndExit));
+            if Button::default()
+                .draw_centered(
+                    CANONICAL_SCREEN_MID_X,
+                    layout.next(),
+                    BUTTON_W,
+                    BUTTON_H,
+                    Some(&label),
+                    enabled,
+                )
+                .poll()
+            {
+                self.pause_clicked = Some(button);
+            }
+            layout.add(BUTTON_H);
+        }
+
+        if let Some(error) = &self.save_error {
+            ui::Text::new_scaled(ui::TEXT_SMALL).draw(
+                &tr!("challenge.save_failed", error = error),
+                CANONICAL_SCREEN_MID_X,
+                layout.next(),
+            );
+        }
+    }
+}
+
+impl View for ChallengeView {
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        if is_key_pressed(KeyCode::Escape) {
+            self.paused = !self.paused;
+            self.save_error = None;
+            return Transition::None;
+        }
+        if self.paused {
+            match self.pause_clicked.take() {
+                Some(PauseButton::Resume) => self.paused = false,
+                Some(PauseButton::SaveAndExit) => match self.save(&save_path()) {
+                    Ok(()) => return Transition::Pop,
+                    Err(e) => self.save_error = Some(e.to_string()),
+                },
+                Some(PauseButton::Exit) => return Transition::Pop,
+                None => {}
+            }
+            return Transition::None;
+        }
+        if is_key_pressed(KeyCode::R) {
+            self.restart();
+            return Transition::None;
+        }
+        if self.result.is_some() {
+            return Transition::None;
+        }
+
+        let dt = get_frame_time();
+        let mut inputs = HashMap::new();
+        if self.me().is_some() {
+            let input = Game::gather_user_input(&self.engine, Some(HUMAN_ID), &ctx.settings);
+            inputs.insert(HUMAN_ID, input);
+        }
+        let previous_tanks = self.engine.tanks.clone();
+        let previous_projectiles = self.engine.projectiles.clone();
+        self.engine.tick(dt, inputs);
+        self.shots.observe(
+            HUMAN_ID,
+            (&previous_tanks, &previous_projectiles),
+            (&self.engine.tanks, &self.engine.projectiles),
+        );
+        self.elapsed += dt;
+
+        if let Goal::HoldPoint { radius, .. } = self.challenge().goal
+            && self
+                .me()
+                .is_some_and(|me| me.position.distance(self.control_point()) <= radius)
+        {
+            self.held += dt;
+        }
+
+        if let Some(stars) = outcome(self.challenge().goal, &self.status()) {
+            self.result = Some(stars);
+            self.record_result(stars, ctx);
+        }
+
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        Game::draw_game_board(&self.engine, Some(HUMAN_ID), ctx.settings.fog_of_war);
+
+        if let Goal::HoldPoint { radius, .. } = self.challenge().goal {
+            let (scaling, x_offset, y_offset) =
+                calc_transform(self.engine.map.width, self.engine.map.height);
+            let point = self.control_point();
+            let held = self
+                .me()
+                .is_some_and(|me| me.position.distance(point) <= radius);
+            let color = if held { GREEN } else { YELLOW };
+            draw_circle_lines(
+                point.x * scaling + x_offset,
+                point.y * scaling + y_offset,
+                radius * scaling,
+                3.,
+                color,
+            );
+        }
+
+        if let Some(tank) = self.me() {
+            hud::draw_player_hud(tank, ctx.settings.crosshair);
+        }
+
+        let status = self.status();
+        ui::Text::new_scaled(ui::TEXT_MID).draw(
+            &format!(
+                "{} | {}",
+                tr!(self.challenge().name_key),
+                self.objective_text(&status)
+            ),
+            CANONICAL_SCREEN_MID_X,
+            30.,
+        );
+        ui::Text::new_scaled(ui::TEXT_SMALL).draw(
+            &tr!("challenge.controls"),
+            CANONICAL_SCREEN_MID_X,
+            60.,
+        );
+
+        if let Some(stars) = self.result {
+            let text = if stars > 0 {
+                tr!("challenge.won", stars = stars, max = MAX_STARS)
+            } else {
+                tr!("challenge.lost")
+            };
+            ui::Text::new_scaled(TEXT_LARGE).draw(
+                &text,
+                CANONICAL_SCREEN_MID_X,
+                CANONICAL_SCREEN_MID_Y,
+            );
+        }
+
+        if self.paused {
+            self.draw_pause_menu(has_input);
+        }
+    }
+
+    fn presence(&self, _ctx: &AppContext) -> Option<Presence> {
+        Some(Presence::Challenge {
+            name: tr!(self.challenge().name_key),
+        })
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::Challenge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(health: Option<f32>, enemies_alive: usize, elapsed: f32, held: f32) -> Status {
+        Status {
+            health,
+            enemies_alive,
+            elapsed,
+            held,
+        }
+    }
+
+    #[test]
+    fn test_challenge_outcomes() {
+        let survive = Goal::Survive { secs: 60. };
+        assert_eq!(outcome(survive, &status(Some(1.), 3, 30., 0.)), None);
+        assert_eq!(outcome(survive, &status(Some(0.5), 3, 60., 0.)), Some(2));
+        assert_eq!(outcome(survive, &status(None, 3, 59., 0.)), Some(0));
+
+        let hold = Goal::HoldPoint {
+            secs: 20.,
+            radius: 100.,
+        };
+        assert_eq!(outcome(hold, &status(Some(1.), 2, 100., 19.)), None);
+        assert_eq!(outcome(hold, &status(Some(0.2), 2, 100., 20.)), Some(1));
+
+        // Clearing the map always completes the challenge
+        assert_eq!(
+            outcome(Goal::Eliminate, &status(Some(0.8), 0, 5., 0.)),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_challenges_unlock_in_order() {
+        let mut stars = BTreeMap::new();
+        assert!(is_unlocked(0, &stars));
+        assert!(!is_unlocked(1, &stars));
+
+        stars.insert(CHALLENGES[0].id.to_string(), 0);
+        assert!(!is_unlocked(1, &stars));
+        stars.insert(CHALLENGES[0].id.to_string(), 1);
+        assert!(is_unlocked(1, &stars));
+        assert!(!is_unlocked(2, &stars));
+    }
+
+    #[test]
+    fn test_every_challenge_has_room_for_its_opponents() {
+        for (i, challenge) in CHALLENGES.iter().enumerate() {
+            let view = ChallengeView::new(i);
+            assert_eq!(
+                view.engine.tanks.len(),
+                challenge.opponents.len() + 1,
+                "{}",
+                challenge.id
+            );
+            assert_eq!(view.engine.bots.len(), challenge.opponents.len());
+        }
+    }
+
+    #[test]
+    fn test_saved_runs_resume_once() {
+        let path = std::env::temp_dir().join(format!("nb_challenge_{}.nbsave", std::process::id()));
+        let mut view = ChallengeView::new(2);
+        view.engine.tick(0.1, HashMap::new());
+        view.elapsed = 12.5;
+        view.save(&path).unwrap();
+
+        let resumed = ChallengeView::resume(&path).unwrap();
+        assert_eq!(resumed.challenge().id, "survive");
+        assert_eq!(resumed.elapsed, 12.5);
+        assert_eq!(resumed.engine.snapshot(), view.engine.snapshot());
+        assert_eq!(resumed.engine.bots.len(), view.engine.bots.len());
+        assert!(ChallengeView::resume(&path).is_err());
+    }
+}
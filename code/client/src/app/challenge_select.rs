@@ -0,0 +1,129 @@
+use crate::app::challenge::{CHALLENGES, ChallengeView, Goal, MAX_STARS, is_unlocked, save_path};
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::ui::{self, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X};
+
+pub(crate) struct ChallengeSelect {
+    chosen: Option<usize>,
+    resume_clicked: bool,
+    resume_error: Option<String>,
+    back_clicked: bool,
+}
+
+impl ChallengeSelect {
+    pub fn new() -> Self {
+        Self {
+            chosen: None,
+            resume_clicked: false,
+            resume_error: None,
+            back_clicked: false,
+        }
+    }
+
+    fn goal_text(goal: Goal) -> String {
+        match goal {
+            Goal::Eliminate => tr!("challenges.eliminate"),
+            Goal::Survive { secs } => tr!("challenges.survive", seconds = secs),
+            Goal::HoldPoint { secs, .. } => tr!("challenges.hold", seconds = secs),
+        }
+    }
+}
+
+impl View for ChallengeSelect {
+    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+        if self.back_clicked {
+            return Transition::Pop;
+        }
+        if let Some(index) = self.chosen.take() {
+            self.resume_error = None;
+            return Transition::Push(Box::new(ChallengeView::new(index)));
+        }
+        if std::mem::take(&mut self.resume_clicked) {
+            match ChallengeView::resume(&save_path()) {
+                Ok(view) => {
+                    self.resume_error = None;
+                    return Transition::Push(Box::new(view));
+                }
+                Err(e) => self.resume_error = Some(e),
+            }
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        let mut layout = ui::Layout::new(80., 25.);
+
+        ui::Text::new_title().draw(&tr!("challenges.title"), x_mid, layout.next());
+        layout.add(40.);
+
+        self.chosen = None;
+        let stars = &ctx.settings.challenge_stars;
+        for (i, challenge) in CHALLENGES.iter().enumerate() {
+            let unlocked = is_unlocked(i, stars);
+            let label = if unlocked {
+                tr!(
+                    "challenges.entry",
+                    name = tr!(challenge.name_key),
+                    goal = Self::goal_text(challenge.goal),
+                    stars = stars.get(challenge.id).copied().unwrap_or(0),
+                    max = MAX_STARS
+                )
+            } else {
+                tr!("challenges.locked")
+            };
+
+            if Button::default()
+                .draw_centered(
+                    x_mid,
+                    layout.next(),
+                    BUTTON_W * 2.,
+                    BUTTON_H,
+                    Some(&label),
+                    has_input && unlocked,
+                )
+                .poll()
+            {
+                self.chosen = Some(i);
+            }
+            layout.add(BUTTON_H);
+        }
+
+        if save_path().exists() {
+            self.resume_clicked = Button::default()
+                .draw_centered(
+                    x_mid,
+                    layout.next(),
+                    BUTTON_W * 2.,
+                    BUTTON_H,
+                    Some(&tr!("challenges.resume")),
+                    has_input,
+                )
+                .poll();
+            layout.add(BUTTON_H);
+        }
+        if let Some(error) = &self.resume_error {
+            ui::Text::new_scaled(ui::TEXT_SMALL).draw(
+                &tr!("challenges.resume_failed", error = error),
+                x_mid,
+                layout.next(),
+            );
+            layout.add(20.);
+        }
+
+        self.back_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("common.back")),
+                has_input,
+            )
+            .poll();
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::ChallengeSelect
+    }
+}
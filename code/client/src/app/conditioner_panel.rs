@@ -0,0 +1,91 @@
+use crate::net_conditioner::{MAX_LATENCY, NetConditioner};
+use crate::ui::{
+    CANONICAL_SCREEN_HEIGHT, TEXT_SMALL, Text, TextHorizontalPositioning, TextVerticalPositioning,
+    default_text_params,
+};
+use macroquad::prelude::*;
+use std::time::Duration;
+
+pub(crate) const PANEL_TOGGLE_KEY: KeyCode = KeyCode::F9;
+
+const LATENCY_STEP: Duration = Duration::from_millis(20);
+const LOSS_STEP: f32 = 0.05;
+const LINE_HEIGHT: f32 = 22.;
+
+/// Debug builds' panel for the network conditioner. While it's shown, Up/Down change the
+/// latency of outgoing inputs and Left/Right how many get lost; the settings stay in effect
+/// when it's hidden.
+pub(crate) struct ConditionerPanel {
+    visible: bool,
+}
+
+impl ConditionerPanel {
+    pub fn new() -> Self {
+        Self { visible: false }
+    }
+
+    /// Handles the toggle and the arrow keys; runs every frame, whatever view is on top.
+    pub fn update(&mut self, conditioner: &mut NetConditioner) {
+        if is_key_pressed(PANEL_TOGGLE_KEY) {
+            self.visible = !self.visible;
+        }
+        if !self.visible {
+            return;
+        }
+
+        let conditions = &mut conditioner.conditions;
+        if is_key_pressed(KeyCode::Up) {
+            conditions.latency = (conditions.latency + LATENCY_STEP).min(MAX_LATENCY);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            conditions.latency = conditions.latency.saturating_sub(LATENCY_STEP);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            conditions.loss = (conditions.loss + LOSS_STEP).min(1.);
+        }
+        if is_key_pressed(KeyCode::Left) {
+            conditions.loss = (conditions.loss - LOSS_STEP).max(0.);
+        }
+    }
+
+    pub fn draw(&self, conditioner: &NetConditioner) {
+        let conditions = &conditioner.conditions;
+        let mut text = Text::new(
+            TextParams {
+                font_size: TEXT_SMALL,
+                ..default_text_params()
+            },
+            TextVerticalPositioning::CenterConsistent,
+            TextHorizontalPositioning::Left,
+        );
+        // A reminder that the lag is self-inflicted, even with the panel hidden
+        if !self.visible {
+            if !conditions.is_perfect() {
+                text.params.color = ORANGE;
+                text.draw_scaled_no_offset("Conditioner on (F9)", 20., 30.);
+            }
+            return;
+        }
+
+        let lines = [
+            format!(
+                "Input latency: {} ms (Up/Down)",
+                conditions.latency.as_millis()
+            ),
+            format!("Input loss: {:.0}% (Left/Right)", conditions.loss * 100.),
+            format!("Inputs held back: {}", conditioner.held()),
+        ];
+        let scale = screen_height() / CANONICAL_SCREEN_HEIGHT;
+        draw_rectangle(
+            10. * scale,
+            10. * scale,
+            360. * scale,
+            (lines.len() as f32 + 0.8) * LINE_HEIGHT * scale,
+            Color::new(0.0, 0.0, 0.0, 0.8),
+        );
+        text.params.color = ORANGE;
+        for (i, line) in lines.iter().enumerate() {
+            text.draw_scaled_no_offset(line, 20., 30. + i as f32 * LINE_HEIGHT);
+        }
+    }
+}
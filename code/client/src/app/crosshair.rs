@@ -0,0 +1,127 @@
+use crate::settings::CrosshairStyle;
+use crate::ui::scale_dims;
+use common::game::Tank;
+use glam::Vec2;
+use macroquad::prelude::*;
+
+const CROSSHAIR_RADIUS: f32 = 14.;
+/// Aim assist only considers enemies within this angle of the aim direction (radians).
+const AIM_ASSIST_CONE: f32 = 0.14;
+const AIM_ASSIST_RANGE: f32 = 600.;
+/// Share of the angle to the target that the aim is pulled by.
+const AIM_ASSIST_STRENGTH: f32 = 0.35;
+
+/// Crosshair at the mouse cursor; the ring turns into an arc while the weapon reloads.
+pub(crate) fn draw_crosshair(style: CrosshairStyle, cooldown: f32) {
+    let (mx, my) = mouse_position();
+    let (_, _, radius, _) = scale_dims(0., 0., CROSSHAIR_RADIUS, 0.);
+    let ready = 1. - cooldown;
+    let color = Color::new(1., 1., 1., 0.4 + 0.6 * ready);
+
+    match style {
+        CrosshairStyle::Ring => {
+            draw_circle_lines(mx, my, radius, 1., Color::new(1., 1., 1., 0.4));
+            draw_circle(mx, my, 2., WHITE);
+            if cooldown > 0. {
+                draw_arc(mx, my, 32, radius, -90., 3., 360. * ready, WHITE);
+            }
+        }
+        CrosshairStyle::Cross => {
+            let gap = radius * 0.35;
+            for (dx, dy) in [(1., 0.), (-1., 0.), (0., 1.), (0., -1.)] {
+                draw_line(
+                    mx + dx * gap,
+                    my + dy * gap,
+                    mx + dx * radius,
+                    my + dy * radius,
+                    2.,
+                    color,
+                );
+            }
+        }
+        CrosshairStyle::Dot => draw_circle(mx, my, 3., color),
+        CrosshairStyle::Off => {}
+    }
+}
+
+/// Nudges `aim_pos` toward the enemy closest to the aim direction, if one is within a
+/// small cone. Distance to the aim point is kept, only its direction changes.
+pub(crate) fn assist_aim(me: &Tank, aim_pos: Vec2, tanks: &[Tank]) -> Vec2 {
+    let offset = aim_pos - me.position;
+    if offset.length_squared() < f32::EPSILON {
+        return aim_pos;
+    }
+    let aim_angle = offset.to_angle();
+
+    let target_angle = tanks
+        .iter()
+        .filter(|t| t.player_info.team != me.player_info.team && t.health > 0.)
+        .map(|t| t.position - me.position)
+        .filter(|to_enemy| to_enemy.length() <= AIM_ASSIST_RANGE)
+        .map(|to_enemy| angle_between(aim_angle, to_enemy.to_angle()))
+        .filter(|delta| delta.abs() <= AIM_ASSIST_CONE)
+        .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+
+    match target_angle {
+        Some(delta) => {
+            let angle = aim_angle + delta * AIM_ASSIST_STRENGTH;
+            me.position + Vec2::from_angle(angle) * offset.length()
+        }
+        None => aim_pos,
+    }
+}
+
+/// Signed difference `to - from`, wrapped to `[-PI, PI]`.
+fn angle_between(from: f32, to: f32) -> f32 {
+    let delta = (to - from).rem_euclid(std::f32::consts::TAU);
+    if delta > std::f32::consts::PI {
+        delta - std::f32::consts::TAU
+    } else {
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::{PlayerId, Team};
+
+    fn tank(id: PlayerId, team: Team, position: Vec2) -> Tank {
+        Tank::new(PlayerInfo::new(id, format!("P{}", id), team), position)
+    }
+
+    #[test]
+    fn test_aim_is_pulled_toward_enemy_in_cone() {
+        let me = tank(0, Team::Blue, Vec2::ZERO);
+        let enemy = tank(1, Team::Red, Vec2::new(300., 30.));
+        let aim = Vec2::new(200., 0.);
+
+        let assisted = assist_aim(&me, aim, &[me.clone(), enemy]);
+
+        assert!(assisted.y > 0.);
+        assert!((assisted.length() - 200.).abs() < 0.01);
+        // Only a nudge, not a snap
+        assert!(assisted.to_angle() < Vec2::new(300., 30.).to_angle());
+    }
+
+    #[test]
+    fn test_aim_ignores_teammates_and_targets_outside_cone() {
+        let me = tank(0, Team::Blue, Vec2::ZERO);
+        let friend = tank(1, Team::Blue, Vec2::new(300., 10.));
+        let wide_enemy = tank(2, Team::Red, Vec2::new(0., 300.));
+        let far_enemy = tank(3, Team::Red, Vec2::new(1000., 0.));
+        let aim = Vec2::new(200., 0.);
+
+        assert_eq!(
+            assist_aim(&me, aim, &[me.clone(), friend, wide_enemy, far_enemy]),
+            aim
+        );
+    }
+
+    #[test]
+    fn test_angle_between_wraps() {
+        assert!((angle_between(3.0, -3.0) - (std::f32::consts::TAU - 6.0)).abs() < 1e-5);
+        assert!((angle_between(-3.0, 3.0) + (std::f32::consts::TAU - 6.0)).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,389 @@
+//! Sounds, particles and screen shake for what happens in a game, looked up by the kind
+//! of event in a data table, so new weapons and events get effects without code changes.
+//!
+//! The table is built into the binary from `assets/effects.ron`; a file at
+//! [`EFFECTS_PATH`] replaces it. It is checked once on startup by [`load_table`], and
+//! effects it names that don't exist are skipped.
+
+use crate::app::entities::{EntityEvent, EntityId, Lifecycle};
+use crate::assets::Handle;
+use macroquad::audio::{Sound, play_sound_once};
+use macroquad::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+pub(crate) const EFFECTS_PATH: &str = "assets/effects.ron";
+/// Sound ids are names of `.wav` files in here
+pub(crate) const SOUNDS_DIR: &str = "assets/sounds";
+const EMBEDDED: &str = include_str!("../../../../assets/effects.ron");
+
+/// How far the screen moves at full shake, in pixels
+const SHAKE_OFFSET: f32 = 12.;
+/// How long a shake lasts (in seconds)
+const SHAKE_DURATION: f64 = 0.3;
+
+static TABLE: OnceLock<EffectsTable> = OnceLock::new();
+
+/// Something in a game that can have effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub(crate) enum EffectKind {
+    BulletFired,
+    GrenadeThrown,
+    /// A bullet hit something or ran out of time
+    BulletImpact,
+    Explosion,
+    TankSpawned,
+    TankDestroyed,
+}
+
+impl EffectKind {
+    /// The kind of effect an entity appearing or disappearing has, if any.
+    pub fn of(event: &EntityEvent) -> Option<Self> {
+        match (event.id, event.lifecycle) {
+            (EntityId::Projectile(_), Lifecycle::Spawned) if event.grenade => {
+                Some(Self::GrenadeThrown)
+            }
+            (EntityId::Projectile(_), Lifecycle::Spawned) => Some(Self::BulletFired),
+            // Grenades get an explosion instead
+            (EntityId::Projectile(_), Lifecycle::Despawned) if !event.grenade => {
+                Some(Self::BulletImpact)
+            }
+            (EntityId::Tank(_), Lifecycle::Spawned) => Some(Self::TankSpawned),
+            (EntityId::Tank(_), Lifecycle::Despawned) => Some(Self::TankDestroyed),
+            _ => None,
+        }
+    }
+}
+
+/// A burst of particles flying out of a point and fading.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct ParticlePreset {
+    pub count: u8,
+    pub color: (f32, f32, f32),
+    /// Top speed of a particle; each gets between half of it and all of it
+    pub speed: f32,
+    /// Seconds a particle lives
+    pub lifetime: f32,
+    /// Radius of a particle
+    pub size: f32,
+}
+
+/// The effects of one kind of event.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct EventEffects {
+    pub sound: Option<String>,
+    /// Name of a preset in [`EffectsTable::particles`]
+    pub particles: Option<String>,
+    /// From 0 for none to 1 for the strongest
+    pub shake: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct EffectsTable {
+    pub particles: HashMap<String, ParticlePreset>,
+    /// Kinds of events left out have no effects
+    pub events: HashMap<EffectKind, EventEffects>,
+}
+
+impl EffectsTable {
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|e| e.to_string())
+    }
+
+    fn embedded() -> Self {
+        Self::from_ron(EMBEDDED).expect("the embedded effects table is valid")
+    }
+
+    pub fn get(&self, kind: EffectKind) -> Option<&EventEffects> {
+        self.events.get(&kind)
+    }
+
+    /// What is wrong with the table, given the sounds in `sounds_dir`, sorted.
+    pub fn problems(&self, sounds_dir: &Path) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (kind, effects) in &self.events {
+            if let Some(sound) = &effects.sound
+                && !sound_path(sounds_dir, sound).exists()
+            {
+                problems.push(format!("{:?}: no sound file for '{}'", kind, sound));
+            }
+            if let Some(preset) = &effects.particles
+                && !self.particles.contains_key(preset)
+            {
+                problems.push(format!("{:?}: no particle preset '{}'", kind, preset));
+            }
+            if !(0.0..=1.0).contains(&effects.shake) {
+                problems.push(format!(
+                    "{:?}: shake must be from 0 to 1, got {}",
+                    kind, effects.shake
+                ));
+            }
+        }
+        for (name, preset) in &self.particles {
+            if !(preset.lifetime > 0. && preset.speed >= 0. && preset.size > 0.) {
+                problems.push(format!(
+                    "particle preset '{}' needs a positive lifetime and size",
+                    name
+                ));
+            }
+        }
+        problems.sort();
+        problems
+    }
+}
+
+fn sound_path(dir: &Path, id: &str) -> std::path::PathBuf {
+    dir.join(format!("{}.wav", id))
+}
+
+/// Loads the table from [`EFFECTS_PATH`], or the built-in one without it, and returns
+/// what is wrong with it. Called once on startup; later calls change nothing.
+pub(crate) fn load_table() -> Vec<String> {
+    let path = Path::new(EFFECTS_PATH);
+    let (table, mut problems) = match std::fs::read_to_string(path) {
+        Ok(text) => match EffectsTable::from_ron(&text) {
+            Ok(table) => (table, Vec::new()),
+            Err(e) => (
+                EffectsTable::embedded(),
+                vec![format!(
+                    "Invalid {}, using the built-in one: {}",
+                    EFFECTS_PATH, e
+                )],
+            ),
+        },
+        Err(_) => (EffectsTable::embedded(), Vec::new()),
+    };
+    problems.extend(
+        table
+            .problems(Path::new(SOUNDS_DIR))
+            .into_iter()
+            .map(|problem| format!("Effects table: {}", problem)),
+    );
+    let _ = TABLE.set(table);
+    problems
+}
+
+fn table() -> &'static EffectsTable {
+    TABLE.get_or_init(EffectsTable::embedded)
+}
+
+struct Particle {
+    origin: Vec2,
+    velocity: Vec2,
+    since: f64,
+    lifetime: f32,
+    size: f32,
+    color: (f32, f32, f32),
+}
+
+/// The sounds the table names, loaded with the other assets and shared by every game.
+#[derive(Default)]
+pub(crate) struct SoundBank {
+    /// Sounds by id; empty in builds without the `audio` feature
+    sounds: HashMap<String, Handle<Sound>>,
+}
+
+impl SoundBank {
+    /// Registers every sound the table names to be loaded. Without the `audio` feature
+    /// there is nothing to play them on, so none are.
+    pub fn register() -> Self {
+        let mut sounds = HashMap::new();
+        if cfg!(feature = "audio") {
+            for id in table().events.values().filter_map(|e| e.sound.as_ref()) {
+                let path = sound_path(Path::new(SOUNDS_DIR), id);
+                sounds.insert(id.clone(), Handle::of(&path.to_string_lossy()));
+            }
+        }
+        Self { sounds }
+    }
+
+    /// Plays the sound `id`, unless it failed to load.
+    fn play(&self, id: &str) {
+        if let Some(sound) = self.sounds.get(id).and_then(|sound| sound.loaded()) {
+            play_sound_once(sound);
+        }
+    }
+}
+
+/// The effects playing in a game.
+pub(crate) struct Effects {
+    particles: Vec<Particle>,
+    /// Strength of the last shake and when it started
+    shake: Option<(f32, f64)>,
+    sounds: Rc<SoundBank>,
+}
+
+impl Effects {
+    pub fn new(sounds: Rc<SoundBank>) -> Self {
+        Self {
+            particles: Vec::new(),
+            shake: None,
+            sounds,
+        }
+    }
+
+    /// Starts the effects of `kind` happening at `position`.
+    pub fn trigger(&mut self, kind: EffectKind, position: Vec2, time: f64) {
+        let table = table();
+        let Some(effects) = table.get(kind) else {
+            return;
+        };
+        if let Some(id) = &effects.sound {
+            self.sounds.play(id);
+        }
+        if let Some(preset) = effects
+            .particles
+            .as_ref()
+            .and_then(|name| table.particles.get(name))
+        {
+            self.particles
+                .retain(|p| time - p.since < f64::from(p.lifetime));
+            for _ in 0..preset.count {
+                let angle = rand::gen_range(0., std::f32::consts::TAU);
+                let speed = preset.speed * rand::gen_range(0.5, 1.);
+                self.particles.push(Particle {
+                    origin: position,
+                    velocity: Vec2::from_angle(angle) * speed,
+                    since: time,
+                    lifetime: preset.lifetime,
+                    size: preset.size,
+                    color: preset.color,
+                });
+            }
+        }
+        if effects.shake > 0. {
+            let current = self.shake.map_or(0., |(strength, since)| {
+                strength * Self::fade(since, time).unwrap_or(0.)
+            });
+            if effects.shake >= current {
+                self.shake = Some((effects.shake, time));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.shake = None;
+    }
+
+    /// What is left of a shake that started at `since`, from 1 down, `None` once over.
+    fn fade(since: f64, time: f64) -> Option<f32> {
+        let t = (time - since) / SHAKE_DURATION;
+        (0.0..1.0).contains(&t).then_some(1. - t as f32)
+    }
+
+    /// How far the screen is moved by the shake at `time`, in pixels.
+    pub fn shake_offset(&self, time: f64) -> Vec2 {
+        let Some((strength, since)) = self.shake else {
+            return Vec2::ZERO;
+        };
+        let Some(left) = Self::fade(since, time) else {
+            return Vec2::ZERO;
+        };
+        let wobble = Vec2::new((time * 73.).sin() as f32, (time * 91.).cos() as f32);
+        wobble * SHAKE_OFFSET * strength * left
+    }
+
+    pub fn draw(&self, time: f64, (scaling, x_offset, y_offset): (f32, f32, f32)) {
+        for p in &self.particles {
+            let age = (time - p.since) as f32;
+            if !(0.0..p.lifetime).contains(&age) {
+                continue;
+            }
+            let position = p.origin + p.velocity * age;
+            let fade = 1. - age / p.lifetime;
+            let (r, g, b) = p.color;
+            draw_circle(
+                position.x * scaling + x_offset,
+                position.y * scaling + y_offset,
+                (p.size * scaling).max(1.),
+                Color::new(r, g, b, fade),
+            );
+        }
+    }
+}
+
+/// Shifts a `(scaling, x_offset, y_offset)` transform by `offset` pixels.
+pub(crate) fn shaken(
+    (scaling, x_offset, y_offset): (f32, f32, f32),
+    offset: Vec2,
+) -> (f32, f32, f32) {
+    (scaling, x_offset + offset.x, y_offset + offset.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_table_is_valid() {
+        let table = EffectsTable::embedded();
+        assert_eq!(table.problems(Path::new(SOUNDS_DIR)), Vec::<String>::new());
+        assert!(table.get(EffectKind::Explosion).unwrap().shake > 0.);
+    }
+
+    #[test]
+    fn test_problems_name_the_missing_pieces() {
+        let table = EffectsTable::from_ron(
+            r#"(events: { Explosion: (sound: Some("boom"), particles: Some("smoke"), shake: 2.0) })"#,
+        )
+        .unwrap();
+        assert_eq!(
+            table.problems(Path::new("nowhere")),
+            vec![
+                "Explosion: no particle preset 'smoke'".to_string(),
+                "Explosion: no sound file for 'boom'".to_string(),
+                "Explosion: shake must be from 0 to 1, got 2".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entity_events_map_to_kinds() {
+        let event = |id, lifecycle, grenade| EntityEvent {
+            id,
+            lifecycle,
+            position: Vec2::ZERO,
+            radius: 1.,
+            grenade,
+        };
+        let bullet = EntityId::Projectile(1);
+        assert_eq!(
+            EffectKind::of(&event(bullet, Lifecycle::Spawned, false)),
+            Some(EffectKind::BulletFired)
+        );
+        assert_eq!(
+            EffectKind::of(&event(bullet, Lifecycle::Spawned, true)),
+            Some(EffectKind::GrenadeThrown)
+        );
+        assert_eq!(
+            EffectKind::of(&event(bullet, Lifecycle::Despawned, true)),
+            None
+        );
+        assert_eq!(
+            EffectKind::of(&event(bullet, Lifecycle::Updated, false)),
+            None
+        );
+        assert_eq!(
+            EffectKind::of(&event(EntityId::Tank(0), Lifecycle::Despawned, false)),
+            Some(EffectKind::TankDestroyed)
+        );
+    }
+
+    #[test]
+    fn test_shakes_die_down() {
+        let mut effects = Effects::new(Rc::new(SoundBank::default()));
+        effects.trigger(EffectKind::Explosion, Vec2::ZERO, 1.);
+        assert!(effects.shake_offset(1.1).length() > 0.);
+        assert_eq!(effects.shake_offset(1. + SHAKE_DURATION), Vec2::ZERO);
+
+        // A weaker shake doesn't cut a stronger one short
+        effects.trigger(EffectKind::TankDestroyed, Vec2::ZERO, 1.01);
+        assert_eq!(effects.shake.unwrap().0, 0.6);
+    }
+}
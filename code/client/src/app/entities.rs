@@ -0,0 +1,341 @@
+//! The client's copy of the entities in the last snapshot.
+//!
+//! [`EntityStore`] diffs every snapshot against the previous one by tank and projectile id
+//! and reports what appeared, stayed or went away, which drives the spawn and despawn
+//! animations in [`LifecycleEffects`]. Its buffers are reused between snapshots, so keeping
+//! the previous state around doesn't allocate once the match is under way.
+
+use common::game::{Projectile, ProjectileKind, Tank};
+use common::protocol::PlayerId;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+/// How long spawn and despawn animations last (in seconds).
+const TANK_SPAWN_DURATION: f64 = 0.4;
+const TANK_DEATH_DURATION: f64 = 0.6;
+const PROJECTILE_POP_DURATION: f64 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum EntityId {
+    Tank(PlayerId),
+    Projectile(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lifecycle {
+    Spawned,
+    Updated,
+    Despawned,
+}
+
+/// Something that happened to one entity between two snapshots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EntityEvent {
+    pub id: EntityId,
+    pub lifecycle: Lifecycle,
+    /// Where the entity is, or was last seen when it despawned
+    pub position: Vec2,
+    pub radius: f32,
+    /// Grenades already get an explosion, so they don't pop
+    pub grenade: bool,
+}
+
+trait Entity: Clone {
+    fn entity_id(&self) -> EntityId;
+    fn event(&self, lifecycle: Lifecycle) -> EntityEvent;
+}
+
+impl Entity for Tank {
+    fn entity_id(&self) -> EntityId {
+        EntityId::Tank(self.player_info.id)
+    }
+
+    fn event(&self, lifecycle: Lifecycle) -> EntityEvent {
+        EntityEvent {
+            id: self.entity_id(),
+            lifecycle,
+            position: self.position,
+            radius: self.radius,
+            grenade: false,
+        }
+    }
+}
+
+impl Entity for Projectile {
+    fn entity_id(&self) -> EntityId {
+        EntityId::Projectile(self.id)
+    }
+
+    fn event(&self, lifecycle: Lifecycle) -> EntityEvent {
+        EntityEvent {
+            id: self.entity_id(),
+            lifecycle,
+            position: self.position,
+            radius: self.radius,
+            grenade: self.kind == ProjectileKind::Grenade,
+        }
+    }
+}
+
+/// Entities of the previous snapshot, indexed by id.
+#[derive(Default)]
+pub(crate) struct EntityStore {
+    tanks: Vec<Tank>,
+    projectiles: Vec<Projectile>,
+    /// Position in `tanks` or `projectiles` of every stored entity
+    index: HashMap<EntityId, usize>,
+    /// Index of the snapshot being observed, swapped with `index` afterwards
+    next_index: HashMap<EntityId, usize>,
+    events: Vec<EntityEvent>,
+}
+
+impl EntityStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tanks of the last observed snapshot.
+    pub fn tanks(&self) -> &[Tank] {
+        &self.tanks
+    }
+
+    /// Projectiles of the last observed snapshot.
+    pub fn projectiles(&self) -> &[Projectile] {
+        &self.projectiles
+    }
+
+    /// Stores the next snapshot's entities and returns how they differ from the last ones.
+    pub fn observe(&mut self, tanks: &[Tank], projectiles: &[Projectile]) -> &[EntityEvent] {
+        self.events.clear();
+        self.next_index.clear();
+        diff(
+            &mut self.tanks,
+            tanks,
+            &self.index,
+            &mut self.next_index,
+            &mut self.events,
+        );
+        diff(
+            &mut self.projectiles,
+            projectiles,
+            &self.index,
+            &mut self.next_index,
+            &mut self.events,
+        );
+        std::mem::swap(&mut self.index, &mut self.next_index);
+        &self.events
+    }
+}
+
+/// Reports the entities of `next` as spawned or updated against `index`, those of `stored`
+/// missing from `next` as despawned, then replaces `stored` with `next`.
+fn diff<T: Entity>(
+    stored: &mut Vec<T>,
+    next: &[T],
+    index: &HashMap<EntityId, usize>,
+    next_index: &mut HashMap<EntityId, usize>,
+    events: &mut Vec<EntityEvent>,
+) {
+    for (i, entity) in next.iter().enumerate() {
+        let id = entity.entity_id();
+        next_index.insert(id, i);
+        let lifecycle = if index.contains_key(&id) {
+            Lifecycle::Updated
+        } else {
+            Lifecycle::Spawned
+        };
+        events.push(entity.event(lifecycle));
+    }
+    events.extend(
+        stored
+            .iter()
+            .filter(|e| !next_index.contains_key(&e.entity_id()))
+            .map(|e| e.event(Lifecycle::Despawned)),
+    );
+    // Reuses the vector and the entities' own allocations (like nicknames)
+    stored.truncate(next.len());
+    let (overlap, rest) = next.split_at(stored.len());
+    stored.clone_from_slice(overlap);
+    stored.extend_from_slice(rest);
+}
+
+/// Animations for entities coming and going: a ring closing in on spawning tanks, a burst
+/// where a tank died and a small pop where a bullet ended.
+pub(crate) struct LifecycleEffects {
+    active: Vec<(EntityEvent, f64)>,
+}
+
+impl LifecycleEffects {
+    pub fn new() -> Self {
+        Self { active: Vec::new() }
+    }
+
+    pub fn add(&mut self, events: &[EntityEvent], time: f64) {
+        self.active
+            .retain(|(event, since)| progress(event, *since, time).is_some());
+        self.active.extend(
+            events
+                .iter()
+                .filter(|e| match (e.id, e.lifecycle) {
+                    (_, Lifecycle::Updated) => false,
+                    (EntityId::Tank(_), _) => true,
+                    (EntityId::Projectile(_), lifecycle) => {
+                        lifecycle == Lifecycle::Despawned && !e.grenade
+                    }
+                })
+                .map(|e| (*e, time)),
+        );
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    pub fn draw(&self, time: f64, (scaling, x_offset, y_offset): (f32, f32, f32)) {
+        for (event, since) in &self.active {
+            let Some(t) = progress(event, *since, time) else {
+                continue;
+            };
+            let x = event.position.x * scaling + x_offset;
+            let y = event.position.y * scaling + y_offset;
+            let radius = event.radius * scaling;
+            let fade = 1. - t;
+            match (event.id, event.lifecycle) {
+                (EntityId::Tank(_), Lifecycle::Spawned) => {
+                    draw_circle_lines(
+                        x,
+                        y,
+                        radius * (3. - 2. * t),
+                        2.,
+                        Color::new(1., 1., 1., fade),
+                    );
+                }
+                (EntityId::Tank(_), _) => {
+                    draw_circle(
+                        x,
+                        y,
+                        radius * (1. + 2. * t),
+                        Color::new(1., 0.3, 0.1, 0.5 * fade),
+                    );
+                    draw_circle(x, y, radius * (0.5 + t), Color::new(1., 0.9, 0.4, fade));
+                }
+                (EntityId::Projectile(_), _) => {
+                    draw_circle_lines(
+                        x,
+                        y,
+                        radius * (1. + 3. * t),
+                        1.5,
+                        Color::new(1., 1., 1., fade),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn duration(event: &EntityEvent) -> f64 {
+    match (event.id, event.lifecycle) {
+        (EntityId::Tank(_), Lifecycle::Spawned) => TANK_SPAWN_DURATION,
+        (EntityId::Tank(_), _) => TANK_DEATH_DURATION,
+        (EntityId::Projectile(_), _) => PROJECTILE_POP_DURATION,
+    }
+}
+
+/// Share of an animation's life that has passed, `None` once it is over.
+fn progress(event: &EntityEvent, since: f64, time: f64) -> Option<f32> {
+    let t = (time - since) / duration(event);
+    (0.0..1.0).contains(&t).then_some(t as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::Team;
+    use common::game::player::PlayerInfo;
+
+    fn tank(id: PlayerId, x: f32) -> Tank {
+        Tank::new(
+            PlayerInfo::new(id, format!("P{}", id), Team::Blue),
+            Vec2::new(x, 0.),
+        )
+    }
+
+    fn lifecycles(events: &[EntityEvent]) -> Vec<(EntityId, Lifecycle)> {
+        events.iter().map(|e| (e.id, e.lifecycle)).collect()
+    }
+
+    #[test]
+    fn test_snapshots_are_diffed_by_id() {
+        let mut store = EntityStore::new();
+        let events = store.observe(&[tank(1, 0.), tank(2, 0.)], &[]);
+        assert_eq!(
+            lifecycles(events),
+            [
+                (EntityId::Tank(1), Lifecycle::Spawned),
+                (EntityId::Tank(2), Lifecycle::Spawned)
+            ]
+        );
+
+        // Order in the snapshot doesn't matter; tank 1 died where it was last seen
+        let events = store.observe(&[tank(3, 0.), tank(2, 5.)], &[]);
+        assert_eq!(
+            lifecycles(events),
+            [
+                (EntityId::Tank(3), Lifecycle::Spawned),
+                (EntityId::Tank(2), Lifecycle::Updated),
+                (EntityId::Tank(1), Lifecycle::Despawned)
+            ]
+        );
+        assert_eq!(events[1].position, Vec2::new(5., 0.));
+        assert_eq!(store.tanks().len(), 2);
+
+        // Projectiles are keyed separately from tanks
+        let bullet = Projectile {
+            id: 3,
+            kind: ProjectileKind::Bullet,
+            owner_info: tank(2, 0.).player_info,
+            position: Vec2::ZERO,
+            velocity: Vec2::X,
+            radius: 5.,
+            age: 0.,
+        };
+        store.observe(&[tank(2, 5.), tank(3, 0.)], std::slice::from_ref(&bullet));
+        let events = store.observe(&[tank(2, 5.), tank(3, 0.)], &[]);
+        assert_eq!(
+            lifecycles(events),
+            [
+                (EntityId::Tank(2), Lifecycle::Updated),
+                (EntityId::Tank(3), Lifecycle::Updated),
+                (EntityId::Projectile(3), Lifecycle::Despawned)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_animations_skip_updates_and_grenades_and_expire() {
+        let event = |id, lifecycle, grenade| EntityEvent {
+            id,
+            lifecycle,
+            position: Vec2::ZERO,
+            radius: 5.,
+            grenade,
+        };
+        let mut effects = LifecycleEffects::new();
+        effects.add(
+            &[
+                event(EntityId::Tank(1), Lifecycle::Updated, false),
+                event(EntityId::Projectile(4), Lifecycle::Despawned, true),
+                event(EntityId::Projectile(5), Lifecycle::Despawned, false),
+                event(EntityId::Tank(2), Lifecycle::Despawned, false),
+            ],
+            0.,
+        );
+        assert_eq!(effects.active.len(), 2);
+
+        // The pop is over long before the death burst
+        effects.add(&[], PROJECTILE_POP_DURATION);
+        assert_eq!(effects.active.len(), 1);
+        effects.add(&[], TANK_DEATH_DURATION);
+        assert!(effects.active.is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! Batches of headless matches between the training model and an opponent, played on
+//! background threads while the training view waits for the result.
+
+use common::net::protocol::{MapDefinition, Team};
+use common::rl::contest::{self, Contestant, MatchReport, Outcome};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Matches played by one evaluation.
+pub(crate) const EVALUATION_MATCHES: usize = 100;
+
+pub(crate) struct Evaluation {
+    pub opponent: String,
+    pub total: usize,
+    reports: Receiver<MatchReport>,
+    played: usize,
+    wins: usize,
+    draws: usize,
+    /// Damage the model dealt over all matches
+    damage: f32,
+}
+
+impl Evaluation {
+    /// Starts `matches` matches of `model` against `opponent`, swapping sides after each.
+    /// Match `i` is seeded with `i`, so evaluating the same pair again gives the same result.
+    pub fn start(
+        model: Contestant,
+        opponent: Contestant,
+        opponent_name: String,
+        matches: usize,
+        match_duration: Duration,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let next = Arc::new(AtomicUsize::new(0));
+        let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+        for _ in 0..workers.min(matches) {
+            // Models can't be shared between threads, so every worker gets its own copy
+            let contestants = [model.clone(), opponent.clone()];
+            let (tx, next) = (tx.clone(), next.clone());
+            std::thread::spawn(move || {
+                let map = MapDefinition::load();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= matches {
+                        break;
+                    }
+                    let side = if i % 2 == 0 { Team::Blue } else { Team::Red };
+                    let report = contest::play_match(
+                        &contestants,
+                        side,
+                        &map,
+                        None,
+                        match_duration,
+                        i as u64,
+                        None,
+                    );
+                    // The view stopped waiting
+                    if tx.send(report).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        Self {
+            opponent: opponent_name,
+            total: matches,
+            reports: rx,
+            played: 0,
+            wins: 0,
+            draws: 0,
+            damage: 0.0,
+        }
+    }
+
+    /// Counts the matches finished since the last call.
+    pub fn poll(&mut self) {
+        for report in self.reports.try_iter() {
+            self.played += 1;
+            match report.outcome {
+                Outcome::Win(0) => self.wins += 1,
+                Outcome::Draw => self.draws += 1,
+                Outcome::Win(_) => {}
+            }
+            self.damage += report.damage[0];
+        }
+    }
+
+    pub fn played(&self) -> usize {
+        self.played
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.played >= self.total
+    }
+
+    pub fn draws(&self) -> usize {
+        self.draws
+    }
+
+    /// Share of the matches played so far that the model won.
+    pub fn win_rate(&self) -> Option<f32> {
+        (self.played > 0).then(|| self.wins as f32 / self.played as f32)
+    }
+
+    /// Damage the model dealt per match.
+    pub fn average_score(&self) -> Option<f32> {
+        (self.played > 0).then(|| self.damage / self.played as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::ai::BotDifficulty;
+
+    #[test]
+    fn test_evaluation_collects_every_match() {
+        let mut evaluation = Evaluation::start(
+            Contestant::Scripted(BotDifficulty::Dummy),
+            Contestant::Scripted(BotDifficulty::Dummy),
+            "Dummy".into(),
+            4,
+            Duration::from_millis(200),
+        );
+        assert_eq!(evaluation.win_rate(), None);
+
+        while !evaluation.is_done() {
+            evaluation.poll();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        // Idle tanks never hurt each other
+        assert_eq!(evaluation.played(), 4);
+        assert_eq!(evaluation.draws(), 4);
+        assert_eq!(evaluation.win_rate(), Some(0.0));
+        assert_eq!(evaluation.average_score(), Some(0.0));
+    }
+}
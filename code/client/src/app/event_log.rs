@@ -0,0 +1,266 @@
+use crate::ui::{
+    CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_WIDTH, TEXT_SMALL, Text, TextHorizontalPositioning,
+    TextVerticalPositioning, default_text_params,
+};
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+
+pub(crate) const CLIENT_LOG_PATH: &str = "client.log";
+pub(crate) const LOG_TOGGLE_KEY: KeyCode = KeyCode::GraveAccent;
+
+const LOG_CAPACITY: usize = 500;
+const VISIBLE_LINES: usize = 16;
+const LINE_HEIGHT: f32 = 22.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogCategory {
+    Connection,
+    Game,
+    Error,
+}
+
+impl LogCategory {
+    fn label(self) -> &'static str {
+        match self {
+            LogCategory::Connection => "NET",
+            LogCategory::Game => "GAME",
+            LogCategory::Error => "ERROR",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            LogCategory::Connection => SKYBLUE,
+            LogCategory::Game => LIGHTGRAY,
+            LogCategory::Error => RED,
+        }
+    }
+}
+
+struct LogEntry {
+    /// Seconds since the client started
+    time: f64,
+    category: LogCategory,
+    text: String,
+}
+
+impl LogEntry {
+    fn format(&self) -> String {
+        format!(
+            "[{:>8.2}] {:<5} {}",
+            self.time,
+            self.category.label(),
+            self.text
+        )
+    }
+}
+
+/// The last few hundred things that happened, shown in a pane toggled with
+/// [`LOG_TOGGLE_KEY`] and optionally mirrored to [`CLIENT_LOG_PATH`], so problems can
+/// be reported with context.
+pub(crate) struct EventLog {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    file: Option<LineWriter<File>>,
+    visible: bool,
+    /// Lines scrolled up from the newest entry
+    scroll: usize,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            file: None,
+            visible: false,
+            scroll: 0,
+        }
+    }
+
+    pub fn with_default_capacity() -> Self {
+        Self::new(LOG_CAPACITY)
+    }
+
+    /// Starts or stops appending new entries to the file at `path`.
+    pub fn set_file(&mut self, path: Option<&Path>) {
+        self.file = path.and_then(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(LineWriter::new)
+                .map_err(|e| eprintln!("Failed to open log file {}: {}", path.display(), e))
+                .ok()
+        });
+    }
+
+    pub fn push(&mut self, category: LogCategory, text: impl Into<String>) {
+        self.push_at(get_time(), category, text.into());
+    }
+
+    fn push_at(&mut self, time: f64, category: LogCategory, text: String) {
+        let entry = LogEntry {
+            time,
+            category,
+            text,
+        };
+        let line = entry.format();
+        if let Some(file) = &mut self.file
+            && writeln!(file, "{}", line).is_err()
+        {
+            // Don't keep failing on every entry
+            self.file = None;
+        }
+        common::crash::record_log(line);
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+
+        // Keep looking at the same lines while scrolled up
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.max_scroll());
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.entries.len().saturating_sub(VISIBLE_LINES)
+    }
+
+    pub fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines)
+            .min(self.max_scroll());
+    }
+
+    /// The entries currently in the pane, oldest first.
+    fn visible_entries(&self) -> impl Iterator<Item = &LogEntry> {
+        let end = self.entries.len() - self.scroll;
+        let start = end.saturating_sub(VISIBLE_LINES);
+        self.entries.range(start..end)
+    }
+
+    /// Handles the toggle key and scrolling; runs every frame, whatever view is on top.
+    pub fn update(&mut self) {
+        if is_key_pressed(LOG_TOGGLE_KEY) {
+            self.visible = !self.visible;
+            self.scroll = 0;
+        }
+        if !self.visible {
+            return;
+        }
+
+        if is_key_pressed(KeyCode::PageUp) {
+            self.scroll_by(VISIBLE_LINES as isize / 2);
+        }
+        if is_key_pressed(KeyCode::PageDown) {
+            self.scroll_by(-(VISIBLE_LINES as isize / 2));
+        }
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0. {
+            self.scroll_by(wheel.signum() as isize * 3);
+        }
+    }
+
+    pub fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+
+        let scale = screen_height() / CANONICAL_SCREEN_HEIGHT;
+        let pane_h = (VISIBLE_LINES as f32 + 1.5) * LINE_HEIGHT;
+        draw_rectangle(
+            0.,
+            0.,
+            screen_width(),
+            pane_h * scale,
+            Color::new(0.0, 0.0, 0.0, 0.8),
+        );
+
+        let mut text = Text::new(
+            TextParams {
+                font_size: TEXT_SMALL,
+                ..default_text_params()
+            },
+            TextVerticalPositioning::CenterConsistent,
+            TextHorizontalPositioning::Left,
+        );
+
+        let mut y = LINE_HEIGHT;
+        for entry in self.visible_entries() {
+            text.params.color = entry.category.color();
+            text.draw_scaled_no_offset(&entry.format(), 20., y);
+            y += LINE_HEIGHT;
+        }
+
+        text.params.color = GRAY;
+        let footer = if self.scroll > 0 {
+            format!("{} newer lines below (PgDn)", self.scroll)
+        } else {
+            "PgUp/PgDn or mouse wheel to scroll".to_string()
+        };
+        text.horizontal_positioning = TextHorizontalPositioning::Right;
+        text.draw_scaled_no_offset(&footer, CANONICAL_SCREEN_WIDTH - 20., pane_h);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(log: &EventLog) -> Vec<&str> {
+        log.visible_entries().map(|e| e.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_event_log_drops_oldest_entries() {
+        let mut log = EventLog::new(3);
+        for i in 0..5 {
+            log.push_at(i as f64, LogCategory::Game, i.to_string());
+        }
+
+        assert_eq!(log.entries.len(), 3);
+        assert_eq!(texts(&log), ["2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_event_log_scrolling_is_clamped() {
+        let mut log = EventLog::new(100);
+        for i in 0..VISIBLE_LINES + 4 {
+            log.push_at(0., LogCategory::Connection, i.to_string());
+        }
+
+        log.scroll_by(100);
+        assert_eq!(log.scroll, 4);
+        assert_eq!(texts(&log)[0], "0");
+
+        // New entries don't move the lines being read
+        log.push_at(0., LogCategory::Error, "new".into());
+        assert_eq!(texts(&log)[0], "0");
+
+        log.scroll_by(-100);
+        assert_eq!(log.scroll, 0);
+        assert_eq!(*texts(&log).last().unwrap(), "new");
+    }
+
+    #[test]
+    fn test_event_log_writes_file() {
+        let path = std::env::temp_dir().join("neuroblasters_event_log_test.log");
+        let _ = std::fs::remove_file(&path);
+
+        let mut log = EventLog::new(10);
+        log.set_file(Some(&path));
+        log.push_at(1.5, LogCategory::Error, "Server closed connection.".into());
+        log.set_file(None);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("ERROR Server closed connection."));
+        let _ = std::fs::remove_file(path);
+    }
+}
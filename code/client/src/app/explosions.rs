@@ -0,0 +1,61 @@
+//! Expanding rings where grenades went off.
+
+use common::protocol::Explosion;
+use macroquad::prelude::*;
+
+/// How long a ring stays up (in seconds).
+const BLAST_DURATION: f64 = 0.5;
+
+pub(crate) struct BlastEffects {
+    /// Explosions and when they were reported
+    active: Vec<(Explosion, f64)>,
+}
+
+impl BlastEffects {
+    pub fn new() -> Self {
+        Self { active: Vec::new() }
+    }
+
+    pub fn add(&mut self, explosion: Explosion, time: f64) {
+        self.active
+            .retain(|(_, since)| Self::progress(*since, time).is_some());
+        self.active.push((explosion, time));
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    /// Share of a ring's life that has passed, `None` once it is gone.
+    fn progress(since: f64, time: f64) -> Option<f32> {
+        let t = (time - since) / BLAST_DURATION;
+        (0.0..1.0).contains(&t).then_some(t as f32)
+    }
+
+    pub fn draw(&self, time: f64, (scaling, x_offset, y_offset): (f32, f32, f32)) {
+        for (explosion, since) in &self.active {
+            let Some(t) = Self::progress(*since, time) else {
+                continue;
+            };
+            let x = explosion.position.x * scaling + x_offset;
+            let y = explosion.position.y * scaling + y_offset;
+            let radius = explosion.radius * scaling * (0.3 + 0.7 * t);
+            let fade = 1. - t;
+            draw_circle(x, y, radius, Color::new(1., 0.45, 0., 0.35 * fade));
+            draw_circle_lines(x, y, radius, 3., Color::new(1., 0.85, 0.2, fade));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rings_expire() {
+        assert_eq!(BlastEffects::progress(1., 1.), Some(0.));
+        assert!(BlastEffects::progress(1., 1. + BLAST_DURATION / 2.).is_some());
+        assert_eq!(BlastEffects::progress(1., 1. + BLAST_DURATION), None);
+        assert_eq!(BlastEffects::progress(1., 0.5), None);
+    }
+}
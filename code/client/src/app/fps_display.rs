@@ -1,4 +1,6 @@
+use macroquad::prelude::*;
 use macroquad::{text::TextParams, time::get_fps};
+use std::collections::VecDeque;
 
 use crate::ui::{
     CANONICAL_SCREEN_WIDTH, TEXT_SMALL, Text, TextHorizontalPositioning, TextVerticalPositioning,
@@ -53,10 +55,98 @@ impl FPSDisplay {
     }
 }
 
+/// Frame times shown by the graph.
+const GRAPH_FRAMES: usize = 120;
+const GRAPH_WIDTH: f32 = 240.;
+const GRAPH_HEIGHT: f32 = 60.;
+/// Frame time at the top of the graph (in seconds); longer frames are clipped.
+const GRAPH_MAX: f32 = 1. / 20.;
+/// A frame at 60 FPS, marked with a line.
+const TARGET_FRAME: f32 = 1. / 60.;
+
+/// Bars of the last frames' durations, under the FPS counter. Spikes show stutter the
+/// averaged counter hides.
+pub(crate) struct FrameTimeGraph {
+    frame_times: VecDeque<f32>,
+}
+
+impl FrameTimeGraph {
+    pub fn new() -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(GRAPH_FRAMES),
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.frame_times.len() == GRAPH_FRAMES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// Longest of the recorded frames.
+    pub fn worst(&self) -> f32 {
+        self.frame_times.iter().copied().fold(0., f32::max)
+    }
+
+    pub fn draw(&self) {
+        let left = screen_width() - GRAPH_WIDTH - 20.;
+        let top = 50.;
+        let bottom = top + GRAPH_HEIGHT;
+        draw_rectangle(
+            left,
+            top,
+            GRAPH_WIDTH,
+            GRAPH_HEIGHT,
+            Color::new(0., 0., 0., 0.5),
+        );
+
+        let bar_width = GRAPH_WIDTH / GRAPH_FRAMES as f32;
+        for (i, &time) in self.frame_times.iter().enumerate() {
+            let height = (time / GRAPH_MAX).min(1.) * GRAPH_HEIGHT;
+            let color = if time > 2. * TARGET_FRAME {
+                RED
+            } else if time > TARGET_FRAME * 1.1 {
+                ORANGE
+            } else {
+                GREEN
+            };
+            draw_rectangle(
+                left + i as f32 * bar_width,
+                bottom - height,
+                bar_width,
+                height,
+                color,
+            );
+        }
+
+        let target_y = bottom - TARGET_FRAME / GRAPH_MAX * GRAPH_HEIGHT;
+        draw_line(left, target_y, left + GRAPH_WIDTH, target_y, 1., WHITE);
+        draw_text(
+            &format!("{:.1} ms max", self.worst() * 1000.),
+            left + 4.,
+            top + 14.,
+            16.,
+            WHITE,
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_frame_time_graph_keeps_the_latest_frames() {
+        let mut graph = FrameTimeGraph::new();
+        graph.record(0.5);
+        for _ in 0..GRAPH_FRAMES {
+            graph.record(0.01);
+        }
+        assert_eq!(graph.frame_times.len(), GRAPH_FRAMES);
+        assert_eq!(graph.worst(), 0.01);
+    }
+
     #[test]
     fn test_fps_display_initial_zero() {
         let display = FPSDisplay::new(5);
@@ -0,0 +1,45 @@
+//! Keeping the frame rate under the cap picked in the graphics options.
+
+use std::time::{Duration, Instant};
+
+pub(crate) struct FramePacer {
+    frame_start: Instant,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self {
+            frame_start: Instant::now(),
+        }
+    }
+
+    /// Sleeps off whatever is left of the frame's share of a second under `cap`. Called
+    /// once per frame, right before handing it to the screen.
+    pub fn wait(&mut self, cap: Option<u32>) {
+        if let Some(rest) = remaining(self.frame_start.elapsed(), cap) {
+            std::thread::sleep(rest);
+        }
+        self.frame_start = Instant::now();
+    }
+}
+
+/// Time left of a frame that took `elapsed` so far, if it has to wait to stay under `cap`.
+fn remaining(elapsed: Duration, cap: Option<u32>) -> Option<Duration> {
+    let budget = Duration::from_secs_f64(1.0 / cap.filter(|&fps| fps > 0)? as f64);
+    budget.checked_sub(elapsed).filter(|rest| !rest.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_wait_only_under_a_cap() {
+        let ms = Duration::from_millis;
+        assert_eq!(remaining(ms(5), None), None);
+        assert_eq!(remaining(ms(5), Some(0)), None);
+        assert_eq!(remaining(ms(5), Some(100)), Some(ms(5)));
+        assert_eq!(remaining(ms(10), Some(100)), None);
+        assert_eq!(remaining(ms(30), Some(60)), None);
+    }
+}
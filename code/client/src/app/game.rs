@@ -1,17 +1,74 @@
+use crate::i18n::tr;
 use common::{
-    game::{InputPayload, MapDefinition, Team, engine::GameEngine},
-    protocol::{ClientMessage, GameEvent, GameState, GameUpdate, InitialGameInfo, PlayerId},
+    game::{
+        InputPayload, MapDefinition, ProjectileKind, Team, arena::QUALIFYING_ARENAS,
+        engine::GameEngine, player::PlayerInfo,
+    },
+    protocol::{
+        CloseReason, GameEvent, GameMode, GameState, GameUpdate, InitialGameInfo, MatchSummary,
+        PlayerId, PlayerStats, ShopBalance,
+    },
 };
 
 use crate::{
+    app::camera::{SpectatorCamera, arena_label, board_transform},
+    app::crosshair::assist_aim,
+    app::effects::{EffectKind, Effects, shaken},
+    app::entities::{EntityStore, LifecycleEffects},
+    app::event_log::{EventLog, LogCategory},
+    app::explosions::BlastEffects,
     app::feeds::{MainFeed, SideFeed},
-    server::Server,
+    app::hud,
+    app::indicators::{self, DamageIndicators},
+    app::kill_ranking::KillRanking,
+    app::killcam::{KILLCAM_DURATION, Killcam, SlowMotion, SnapshotHistory},
+    app::lobby_roster::LobbyRoster,
+    app::map_features,
+    app::match_timeline,
+    app::name_tags,
+    app::pings::{self, PingMarkers, PingWheel},
+    app::prediction::InputTracker,
+    app::round_overlay::RoundOverlay,
+    app::scoreboard,
+    app::services::Services,
+    presence::Presence,
+    server::{RequestId, Server},
+    settings::{SETTINGS_PATH, Settings},
+    stats::ShotCounter,
     ui::{
-        TEXT_SMALL, Text, calc_transform,
+        CANONICAL_SCREEN_MID_X, TEXT_LARGE, TEXT_MID, Text,
         theme::{DARK_BG, GRID_COLOR, NEON_CYAN, NEON_PINK, WALL_COLOR, WALL_OUTLINE},
     },
 };
 use macroquad::prelude::*;
+use std::path::Path;
+
+/// How far ahead of a tank its aim is held while the menu is open, in world units
+const AIM_HOLD_DISTANCE: f32 = 100.0;
+
+/// What a player sees first on getting into a game, going by the state it was in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Arrival {
+    /// The lobby, with the seconds left if the match is counting down
+    Lobby { countdown: Option<u64> },
+    /// The match is under way; they watch until they have a tank
+    Spectator,
+    /// The match is over and its results are up
+    Results,
+}
+
+impl Arrival {
+    pub fn of(state: &GameState) -> Self {
+        match state {
+            GameState::Waiting => Arrival::Lobby { countdown: None },
+            GameState::Countdown(seconds) => Arrival::Lobby {
+                countdown: Some(*seconds),
+            },
+            GameState::Battle(_) | GameState::Intermission(_) => Arrival::Spectator,
+            GameState::Results { .. } => Arrival::Results,
+        }
+    }
+}
 
 pub(crate) struct Game {
     initial_game_info: InitialGameInfo,
@@ -21,103 +78,694 @@ pub(crate) struct Game {
     current_round: u8,
     main_feed: MainFeed,
     side_feed: SideFeed,
+    history: SnapshotHistory,
+    killcam: Option<Killcam>,
+    /// Replays the end of the last round in slow motion.
+    slow_motion: Option<SlowMotion>,
+    /// Engine the current killcam or slow-motion frame is loaded into for drawing.
+    playback_engine: GameEngine,
+    camera: SpectatorCamera,
+    damage: DamageIndicators,
+    blasts: BlastEffects,
+    /// Sounds, particles and shake from the effects table
+    effects: Effects,
+    /// Entities of the previous snapshot, diffed against every new one
+    entities: EntityStore,
+    lifecycle_effects: LifecycleEffects,
+    overlay: RoundOverlay,
+    shots: ShotCounter,
+    /// Kills per player, shown in free-for-all
+    ranking: KillRanking,
+    /// Everyone's shots this match, as last sent by the server
+    stats: Vec<PlayerStats>,
+    /// How the match went, once it is over
+    summary: Option<MatchSummary>,
+    /// Everyone's points and upgrades, as last sent in an intermission
+    shop: Vec<ShopBalance>,
+    /// Co-op: the wave on the map, and the seconds survived once the team fell
+    wave: u8,
+    survived: Option<u32>,
+    ping_wheel: PingWheel,
+    /// Where teammates pinged
+    pings: PingMarkers,
+    /// Team of the local player, remembered for the match result after dying
+    my_team: Option<Team>,
+    /// Arena the local player was last seen in, on a multi-arena map
+    my_arena: Option<usize>,
+    /// The in-game menu is open, so keyboard and mouse work it instead of the tank
+    menu_open: bool,
+    /// The server closed the game, so it's time to leave
+    closed: bool,
+    /// Inputs sent that the server hasn't processed yet
+    inputs: InputTracker,
+    /// Who is in the lobby, shown until the first round starts
+    roster: LobbyRoster,
+    /// The host's request to start the match, until the server answers it
+    start_request: Option<RequestId>,
 }
 
 impl Game {
-    pub fn new(initial_game_info: InitialGameInfo, is_host: bool) -> Self {
-        let map = MapDefinition::load_name(initial_game_info.map_name);
-        let game_engine = GameEngine::new(map);
-        Self {
+    pub fn new(initial_game_info: InitialGameInfo, is_host: bool, services: &Services) -> Self {
+        let map = MapDefinition::for_mode(initial_game_info.map_name, initial_game_info.mode);
+        let game_engine = GameEngine::new(map.clone());
+        let arrival = Arrival::of(&initial_game_info.state);
+        let mut game = Self {
+            game_state: initial_game_info.state.clone(),
+            current_round: initial_game_info.round,
             initial_game_info,
             game_engine,
-            game_state: GameState::Waiting,
             is_host,
-            current_round: 1,
             main_feed: MainFeed::new(),
             side_feed: SideFeed::new(5., 5),
+            history: SnapshotHistory::new(KILLCAM_DURATION),
+            killcam: None,
+            slow_motion: None,
+            playback_engine: GameEngine::new(map),
+            camera: SpectatorCamera::new(),
+            damage: DamageIndicators::new(),
+            blasts: BlastEffects::new(),
+            effects: Effects::new(services.sounds()),
+            entities: EntityStore::new(),
+            lifecycle_effects: LifecycleEffects::new(),
+            overlay: RoundOverlay::new(),
+            shots: ShotCounter::default(),
+            ranking: KillRanking::new(),
+            stats: Vec::new(),
+            summary: None,
+            shop: Vec::new(),
+            wave: 0,
+            survived: None,
+            ping_wheel: PingWheel::new(),
+            pings: PingMarkers::new(),
+            my_team: None,
+            my_arena: None,
+            menu_open: false,
+            closed: false,
+            inputs: InputTracker::new(),
+            roster: LobbyRoster::new(),
+            start_request: None,
+        };
+        // Events of what happened before joining never come, so the view starts where
+        // they would have left it
+        match arrival {
+            Arrival::Lobby { countdown: Some(_) } => {
+                game.overlay
+                    .countdown_started(game.current_round, None, get_time())
+            }
+            Arrival::Lobby { countdown: None } => {}
+            Arrival::Spectator => game.side_feed.add(tr!("game.joined_under_way")),
+            Arrival::Results => game.side_feed.add(tr!("game.joined_finished")),
         }
+        game
     }
 
-    pub fn update(&mut self, game_update: GameUpdate, server: &mut Server) {
+    pub fn update(
+        &mut self,
+        game_update: GameUpdate,
+        server: &mut Server,
+        settings: &mut Settings,
+        log: &mut EventLog,
+    ) {
         let old_round = self.current_round;
+        let time = get_time();
 
-        self.game_engine.apply_snapshot(game_update.snapshot.engine);
+        let player_id = self.initial_game_info.player_id;
+        self.history.push(time, game_update.snapshot.engine.clone());
+        if let Some((_, seq)) = game_update
+            .input_acks
+            .iter()
+            .find(|(id, _)| *id == player_id)
+        {
+            self.inputs.acknowledge(*seq);
+        }
+        // Our tank is drawn where the inputs the server hasn't got to yet will take it
+        let mut engine = game_update.snapshot.engine;
+        if let Some(me) = engine
+            .tanks
+            .iter_mut()
+            .find(|t| t.player_info.id == player_id)
+        {
+            self.inputs.predict(me, self.game_engine.map());
+        }
+        self.game_engine.apply_snapshot(engine);
+        self.roster
+            .observe(self.game_engine.tanks(), game_update.snapshot.host, time);
+        let (tanks, projectiles) = (self.game_engine.tanks(), self.game_engine.projectiles());
+        let previous = (self.entities.tanks(), self.entities.projectiles());
+        self.damage
+            .observe(time, player_id, previous.0, previous.1, tanks);
+        self.camera.observe_hits(previous.0, previous.1, tanks);
+        self.shots
+            .observe(player_id, previous, (tanks, projectiles));
+        let events = self.entities.observe(tanks, projectiles);
+        self.lifecycle_effects.add(events, time);
+        for event in events {
+            if let Some(kind) = EffectKind::of(event) {
+                self.effects.trigger(kind, event.position, time);
+            }
+        }
+        self.ranking.observe(self.game_engine.tanks());
+        if let Some(me) = self
+            .game_engine
+            .tanks()
+            .iter()
+            .find(|t| t.player_info.id == player_id)
+        {
+            self.my_team = Some(me.player_info.team);
+            self.my_arena = self.game_engine.map().arena_index(me.position);
+        }
+        let match_ended = !matches!(self.game_state, GameState::Results { .. })
+            && matches!(game_update.snapshot.state, GameState::Results { .. });
         self.game_state = game_update.snapshot.state;
         self.is_host = game_update.snapshot.game_master == server.get_client_id();
         self.current_round = game_update.snapshot.round_number;
         self.side_feed.update();
 
+        // Everything the side feed shows also goes to the event log
+        let mut messages = Vec::new();
+        let mut save_settings = false;
         for event in game_update.events {
             match event {
-                GameEvent::RoundEnded(winner) => self.side_feed.add(format!(
-                    "Round {} ended. Winner is {:?}!",
-                    old_round, winner
-                )),
+                GameEvent::CountdownStarted {
+                    round,
+                    blue_score,
+                    red_score,
+                } => {
+                    let score = if self.is_coop() {
+                        None
+                    } else if !self.is_free_for_all() {
+                        Some(tr!("game.score", blue = blue_score, red = red_score))
+                    } else if blue_score > 0 {
+                        // The ranking's leader, with the server's count of their kills
+                        self.ranking.ranking().first().map(|(player, _)| {
+                            tr!("game.ffa_leader", player = player, kills = blue_score)
+                        })
+                    } else {
+                        None
+                    };
+                    self.overlay.countdown_started(round, score, time);
+                }
 
-                GameEvent::RoundStarted => self
-                    .side_feed
-                    .add(format!("Round {} has started.", self.current_round)),
+                GameEvent::RoundEnded(winner) => {
+                    let summary =
+                        scoreboard::summary_line(&self.stats, self.initial_game_info.player_id);
+                    let winner = winner.map(|team| self.team_name(team));
+                    self.overlay.round_ended(winner.as_deref(), summary, time);
+                    settings.stats.add_shots(&mut self.shots);
+                    save_settings = true;
+                    if self.killcam.is_none() {
+                        self.slow_motion = Some(SlowMotion::new(&self.history, time));
+                    }
+                    messages.push(match winner {
+                        Some(team) => tr!("game.round_ended", round = old_round, team = team),
+                        None => tr!("game.round_drawn", round = old_round),
+                    });
+                }
+
+                GameEvent::RoundStarted => {
+                    self.history.clear();
+                    self.killcam = None;
+                    self.slow_motion = None;
+                    self.overlay.round_started(time);
+                    self.damage.clear();
+                    self.blasts.clear();
+                    self.effects.clear();
+                    self.lifecycle_effects.clear();
+                    messages.push(tr!("game.round_started", round = self.current_round));
+                }
 
                 GameEvent::Kill(kill_event) => {
-                    let victim = format!(
-                        "{} ({:?})",
-                        kill_event.victim_info.nickname, kill_event.victim_info.team
-                    );
-                    let killer = format!(
-                        "{} ({:?})",
-                        kill_event.killer_info.nickname, kill_event.killer_info.team
-                    );
+                    self.ranking.record(&kill_event);
+                    self.camera.record_kill(&kill_event);
+                    let victim = player_label(&kill_event.victim_info);
+                    let killer = player_label(&kill_event.killer_info);
 
-                    self.side_feed.add(format!("{} killed {}", killer, victim));
+                    messages.push(tr!("game.kill", killer = killer, victim = victim));
+
+                    if settings.killcam
+                        && kill_event.victim_info.id == self.initial_game_info.player_id
+                    {
+                        self.killcam = Some(Killcam::new(
+                            &self.history,
+                            kill_event.killer_info.id,
+                            kill_event.killer_info.nickname,
+                            time,
+                        ));
+                    }
+                }
+
+                GameEvent::ArenaDecided { arena, winner } => {
+                    let arena = arena as usize;
+                    if self.my_arena == Some(arena) && self.my_team != Some(winner) {
+                        // Knocked out; the final's result isn't ours
+                        self.my_team = None;
+                    }
+                    messages.push(tr!(
+                        "game.arena_decided",
+                        arena = arena_label(self.game_engine.map(), arena),
+                        team = format!("{:?}", winner)
+                    ));
+                }
+
+                GameEvent::MatchStats(stats) => self.stats = stats,
+
+                GameEvent::Shop(balances) => self.shop = balances,
+
+                GameEvent::MatchEnded(summary) => self.summary = Some(summary),
+
+                GameEvent::WaveStarted { wave, enemies } => {
+                    self.wave = wave;
+                    self.overlay.wave_started(wave, enemies, time);
+                    messages.push(tr!("game.wave_started", wave = wave, enemies = enemies));
+                }
+
+                GameEvent::WaveCleared(wave) => {
+                    messages.push(tr!("game.wave_cleared", wave = wave))
+                }
+
+                GameEvent::Survived { waves, seconds } => {
+                    self.survived = Some(seconds);
+                    messages.push(tr!("game.survived", seconds = seconds, waves = waves));
+                }
+
+                GameEvent::Ping(ping) => {
+                    let player = self
+                        .game_engine
+                        .tanks()
+                        .iter()
+                        .find(|t| t.player_info.id == ping.player)
+                        .map_or_else(
+                            || format!("#{}", ping.player),
+                            |t| t.player_info.nickname.clone(),
+                        );
+                    messages.push(tr!(
+                        "ping.message",
+                        player = player,
+                        ping = tr!(pings::label_key(ping.kind))
+                    ));
+                    self.pings.add(ping, player, time);
+                }
+
+                GameEvent::Explosion(explosion) => {
+                    self.effects
+                        .trigger(EffectKind::Explosion, explosion.position, time);
+                    self.blasts.add(explosion, time);
                 }
 
                 GameEvent::PlayerJoined(player) => {
-                    self.side_feed.add(format!("{} joined the game.", player));
+                    messages.push(tr!("game.player_joined", player = player));
                 }
 
                 GameEvent::PlayerLeft(player) => {
-                    self.side_feed.add(format!("{} left the game.", player));
+                    messages.push(tr!("game.player_left", player = player));
+                }
+
+                GameEvent::PlayerForfeited(player) => {
+                    messages.push(tr!("game.player_forfeited", player = player));
                 }
+
+                GameEvent::PlayerDisconnected(player) => {
+                    messages.push(tr!("game.player_disconnected", player = player));
+                }
+
+                GameEvent::PlayerRejoined(player) => {
+                    messages.push(tr!("game.player_rejoined", player = player));
+                }
+
+                GameEvent::ClosingSoon { reason, seconds } => {
+                    let key = match reason {
+                        CloseReason::Idle => "game.closing_idle",
+                        CloseReason::LobbyExpired => "game.closing_lobby",
+                        CloseReason::Empty => "game.closing_empty",
+                    };
+                    messages.push(tr!(key, seconds = seconds));
+                }
+
+                GameEvent::Closed(reason) => {
+                    self.closed = true;
+                    messages.push(tr!(match reason {
+                        CloseReason::Idle => "game.closed_idle",
+                        CloseReason::LobbyExpired => "game.closed_lobby",
+                        CloseReason::Empty => "game.closed_empty",
+                    }));
+                }
+            }
+        }
+
+        // Co-op teams always fall in the end, which is no loss
+        if match_ended
+            && !self.is_coop()
+            && let (GameState::Results { winner, .. }, Some(my_team)) =
+                (&self.game_state, self.my_team)
+        {
+            // A drawn match counts as neither
+            match winner {
+                Some(team) if *team == my_team => settings.stats.multiplayer_wins += 1,
+                Some(_) => settings.stats.multiplayer_losses += 1,
+                None => {}
             }
+            save_settings = true;
+        }
+        if save_settings && let Err(e) = settings.save(Path::new(SETTINGS_PATH)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+
+        for message in messages {
+            log.push(LogCategory::Game, &message);
+            self.side_feed.add(message);
         }
 
+        if let Some(killcam) = &self.killcam {
+            match killcam.frame_at(time) {
+                Some(frame) if !killcam.is_over(time) => {
+                    self.playback_engine.apply_snapshot(frame.clone())
+                }
+                _ => self.killcam = None,
+            }
+        } else if let Some(slow_motion) = &self.slow_motion {
+            match slow_motion.frame_at(time) {
+                Some(frame) if !slow_motion.is_over(time) => {
+                    self.playback_engine.apply_snapshot(frame.clone())
+                }
+                _ => self.slow_motion = None,
+            }
+        }
+
+        let countdown = match self.game_state {
+            GameState::Countdown(count) => Some(count),
+            _ => None,
+        };
+        self.overlay.set_countdown(countdown, time);
+
         let string = match self.game_state {
-            GameState::Waiting => String::from("Waiting for game start"),
+            GameState::Waiting => tr!("game.waiting"),
+            GameState::Countdown(count)
+                if self.initial_game_info.mode == GameMode::MultiArena
+                    && self.current_round > 1 =>
+            {
+                tr!("game.final_countdown", count = count)
+            }
             GameState::Countdown(count) => {
-                format!("Round {} starting in {}...", self.current_round, count)
+                tr!("game.countdown", round = self.current_round, count = count)
             }
-            GameState::Battle(seconds_left) => format!("Time: {}", seconds_left),
+            GameState::Battle(seconds) if self.is_coop() => {
+                tr!("game.coop_time", wave = self.wave, seconds = seconds)
+            }
+            GameState::Battle(seconds_left) if self.is_spectating() => {
+                tr!("game.spectating_time", seconds = seconds_left)
+            }
+            GameState::Battle(seconds_left) => tr!("game.time", seconds = seconds_left),
+            GameState::Intermission(seconds) => tr!("game.intermission", seconds = seconds),
             GameState::Results {
-                winner,
+                winner: Some(winner),
+                blue_score,
+                ..
+            } if self.is_free_for_all() => {
+                tr!(
+                    "game.ffa_results",
+                    player = self.team_name(winner),
+                    kills = blue_score
+                )
+            }
+            GameState::Results { blue_score, .. } if self.is_coop() => {
+                tr!(
+                    "game.coop_results",
+                    waves = blue_score,
+                    seconds = self.survived.unwrap_or_default()
+                )
+            }
+            GameState::Results {
+                winner: None,
+                blue_score,
+                red_score,
+            } => tr!("game.draw_results", blue = blue_score, red = red_score),
+            GameState::Results {
+                winner: Some(winner),
                 blue_score,
                 red_score,
             } => {
-                format!(
-                    "Team {:?} won! (Blue: {}, Red: {})",
-                    winner, blue_score, red_score
+                tr!(
+                    "game.results",
+                    team = format!("{:?}", winner),
+                    blue = blue_score,
+                    red = red_score
                 )
             }
         };
         self.main_feed.set(string);
+    }
+
+    /// Sends an input if one is due, `dt` seconds into the frame, and moves our tank by it
+    /// right away instead of waiting for the server's next snapshot.
+    pub fn send_input(&mut self, server: &mut Server, settings: &Settings, dt: f32) {
+        // The server put us back in the lobby
+        if self.closed || !server.is_playing() || !self.inputs.due(dt) {
+            return;
+        }
+
+        let input = if self.menu_open {
+            self.idle_input()
+        } else {
+            Game::gather_user_input(
+                &self.game_engine,
+                Some(self.initial_game_info.player_id),
+                settings,
+            )
+        };
+        let seq = self.inputs.sent(input.clone(), get_time());
+        server.send_input(seq, input);
+        let player_id = self.initial_game_info.player_id;
+        if let Some(me) = self
+            .game_engine
+            .tanks
+            .iter_mut()
+            .find(|t| t.player_info.id == player_id)
+        {
+            self.inputs.apply_latest(me, &self.game_engine.map);
+        }
+    }
+
+    /// The server closed the game and put us back in the lobby.
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
 
-        let input = Game::gather_user_input(&self.game_engine);
-        server.send_client_message(ClientMessage::GameInput(input));
+    pub fn set_menu_open(&mut self, open: bool) {
+        self.menu_open = open;
     }
 
-    pub fn draw(&self) {
-        Game::draw_game_board(&self.game_engine, Some(self.initial_game_info.player_id));
+    /// Holds the tank still, aiming where it points, while the player is busy in a menu.
+    fn idle_input(&self) -> InputPayload {
+        let player_id = self.initial_game_info.player_id;
+        let aim_pos = self
+            .game_engine
+            .tanks()
+            .iter()
+            .find(|t| t.player_info.id == player_id)
+            .map_or(Vec2::ZERO, |t| {
+                t.position + Vec2::from_angle(t.rotation) * AIM_HOLD_DISTANCE
+            });
+        InputPayload {
+            move_axis: Vec2::ZERO,
+            aim_pos,
+            shoot: false,
+            grenade: false,
+        }
+    }
+
+    pub fn draw(&self, settings: &Settings) {
+        if let Some(killcam) = &self.killcam {
+            // Shown from the killer's point of view.
+            Game::draw_game_board(
+                &self.playback_engine,
+                Some(killcam.killer_id()),
+                settings.fog_of_war,
+            );
+            Text::new_scaled(TEXT_LARGE).draw(
+                &tr!("game.killcam", killer = killcam.killer_name()),
+                CANONICAL_SCREEN_MID_X,
+                50.,
+            );
+            return;
+        }
+
+        if self.slow_motion.is_some() {
+            Game::draw_game_board(
+                &self.playback_engine,
+                Some(self.initial_game_info.player_id),
+                settings.fog_of_war,
+            );
+            self.overlay.draw(get_time());
+            self.side_feed.draw();
+            return;
+        }
+
+        let tanks = self.game_engine.tanks();
+        let find_tank = |id: Option<PlayerId>| tanks.iter().find(|t| Some(t.player_info.id) == id);
+        let shake = self.effects.shake_offset(get_time());
+        if self.is_spectating() {
+            let transform = shaken(self.camera.transform(self.game_engine.map()), shake);
+            Game::draw_game_board_with_transform(
+                &self.game_engine,
+                self.camera.target(),
+                transform,
+                settings.fog_of_war,
+            );
+            self.blasts.draw(get_time(), transform);
+            self.effects.draw(get_time(), transform);
+            self.lifecycle_effects.draw(get_time(), transform);
+            if settings.indicators
+                && let Some(target) = find_tank(self.camera.target())
+            {
+                indicators::draw_indicators(target, tanks, None, transform, get_time());
+            }
+            self.camera.draw_hud(self.game_engine.map(), tanks);
+        } else {
+            let player_id = self.initial_game_info.player_id;
+            let position = find_tank(Some(player_id)).map(|t| t.position);
+            let transform = shaken(board_transform(self.game_engine.map(), position), shake);
+            Game::draw_game_board_with_transform(
+                &self.game_engine,
+                Some(player_id),
+                transform,
+                settings.fog_of_war,
+            );
+            self.blasts.draw(get_time(), transform);
+            self.effects.draw(get_time(), transform);
+            self.lifecycle_effects.draw(get_time(), transform);
+            if let Some(tank) = find_tank(Some(player_id)) {
+                if settings.indicators {
+                    indicators::draw_indicators(
+                        tank,
+                        tanks,
+                        Some(&self.damage),
+                        transform,
+                        get_time(),
+                    );
+                }
+                hud::draw_player_hud(tank, settings.crosshair);
+            }
+        }
+        self.pings.draw(get_time(), self.view_transform());
+        self.ping_wheel.draw();
+        if self.is_free_for_all() {
+            self.ranking.draw();
+        }
+        let results = matches!(self.game_state, GameState::Results { .. });
+        if results || (is_key_down(KeyCode::Tab) && !self.is_spectating()) {
+            scoreboard::draw_scoreboard(&self.stats, self.initial_game_info.player_id);
+        }
+        if results && let Some(summary) = &self.summary {
+            match_timeline::draw_timeline(summary, self.initial_game_info.player_id);
+        }
+        if self.in_lobby() {
+            let countdown = match self.game_state {
+                GameState::Countdown(count) => Some(count),
+                _ => None,
+            };
+            self.roster.draw(countdown, get_time());
+        }
+        self.overlay.draw(get_time());
         self.main_feed.draw();
         self.side_feed.draw();
+        if self.inputs.unstable(get_time()) {
+            let mut text = Text::new_scaled(TEXT_MID);
+            text.params.color = NEON_PINK;
+            text.draw(
+                &tr!("game.connection_unstable"),
+                CANONICAL_SCREEN_MID_X,
+                95.,
+            );
+        }
     }
 
-    pub fn gather_user_input(game_engine: &GameEngine) -> InputPayload {
-        let map = game_engine.map();
-        let (scaling, x_offset, y_offset) = calc_transform(map.width, map.height);
+    fn is_free_for_all(&self) -> bool {
+        self.initial_game_info.mode == GameMode::FreeForAll
+    }
+
+    fn is_coop(&self) -> bool {
+        self.initial_game_info.mode == GameMode::Coop
+    }
+
+    /// What to call `team` in messages; in free-for-all, the player's nickname.
+    fn team_name(&self, team: Team) -> String {
+        match team {
+            Team::Ffa(id) => self
+                .ranking
+                .nickname(id)
+                .map_or_else(|| format!("#{}", id), str::to_string),
+            team => format!("{:?}", team),
+        }
+    }
+
+    /// How the board is drawn: around the local player, or as the spectator camera shows it.
+    fn view_transform(&self) -> (f32, f32, f32) {
+        let map = self.game_engine.map();
+        if self.is_spectating() {
+            return self.camera.transform(map);
+        }
+        let player_id = self.initial_game_info.player_id;
+        let me = self
+            .game_engine
+            .tanks()
+            .iter()
+            .find(|t| t.player_info.id == player_id);
+        board_transform(map, me.map(|t| t.position))
+    }
+
+    /// Runs the ping wheel, sending the ping picked with it.
+    pub fn update_pings(&mut self, server: &mut Server) {
+        let (scaling, x_offset, y_offset) = self.view_transform();
+        let (x, y) = mouse_position();
+        let world_pos = Vec2::new((x - x_offset) / scaling, (y - y_offset) / scaling);
+        if let Some((kind, world_pos)) = self.ping_wheel.update(world_pos, get_time()) {
+            server.send_ping(kind, world_pos);
+        }
+    }
+
+    /// Runs the spectator camera while the local player is not in the round.
+    pub fn update_spectator_camera(&mut self, dt: f32) {
+        if !self.is_spectating() || self.killcam.is_some() {
+            return;
+        }
+        let map = self.game_engine.map();
+        let tanks = self.game_engine.tanks();
+        self.camera.handle_input(map, tanks, dt);
+        self.camera.update(map, tanks, dt);
+    }
+
+    /// The local player has no tank in a running round, e.g. after dying.
+    fn is_spectating(&self) -> bool {
+        let player_id = self.initial_game_info.player_id;
+        !self
+            .game_engine
+            .tanks()
+            .iter()
+            .any(|t| t.player_info.id == player_id)
+    }
+
+    /// Reads keyboard and mouse; `player_id` is the local tank, used for aim assist.
+    pub fn gather_user_input(
+        game_engine: &GameEngine,
+        player_id: Option<PlayerId>,
+        settings: &Settings,
+    ) -> InputPayload {
+        let me = game_engine
+            .tanks()
+            .iter()
+            .find(|t| Some(t.player_info.id) == player_id);
+        let (scaling, x_offset, y_offset) =
+            board_transform(game_engine.map(), me.map(|t| t.position));
         let inv_transform_x = |x: f32| (x - x_offset) / scaling;
         let inv_transform_y = |y: f32| (y - y_offset) / scaling;
 
         let mouse_pos = mouse_position();
-        let aim_pos = (inv_transform_x(mouse_pos.0), inv_transform_y(mouse_pos.1)).into();
+        let mut aim_pos = (inv_transform_x(mouse_pos.0), inv_transform_y(mouse_pos.1)).into();
+        if settings.aim_assist
+            && let Some(me) = me
+        {
+            aim_pos = assist_aim(me, aim_pos, game_engine.tanks());
+        }
 
         InputPayload {
             move_axis: {
@@ -144,14 +792,41 @@ impl Game {
             },
             aim_pos,
             shoot: is_mouse_button_down(MouseButton::Left) || is_key_down(KeyCode::Space),
+            grenade: is_mouse_button_down(MouseButton::Right) || is_key_down(KeyCode::G),
         }
     }
 
-    pub fn draw_game_board(game_engine: &GameEngine, player_id: Option<PlayerId>) {
+    /// Draws the board around `player_id`: their arena on a multi-arena map, otherwise
+    /// the whole map. Name tags fade with the distance from them and, with `fog_of_war`,
+    /// enemies behind walls have none.
+    pub fn draw_game_board(
+        game_engine: &GameEngine,
+        player_id: Option<PlayerId>,
+        fog_of_war: bool,
+    ) {
+        let focus = game_engine
+            .tanks()
+            .iter()
+            .find(|t| Some(t.player_info.id) == player_id)
+            .map(|t| t.position);
+        Game::draw_game_board_with_transform(
+            game_engine,
+            player_id,
+            board_transform(game_engine.map(), focus),
+            fog_of_war,
+        );
+    }
+
+    /// Draws the board with a custom `(scaling, x_offset, y_offset)` world-to-screen transform.
+    pub fn draw_game_board_with_transform(
+        game_engine: &GameEngine,
+        player_id: Option<PlayerId>,
+        (scaling, x_offset, y_offset): (f32, f32, f32),
+        fog_of_war: bool,
+    ) {
         clear_background(DARK_BG);
 
         let map = game_engine.map();
-        let (scaling, x_offset, y_offset) = calc_transform(map.width, map.height);
         let transform_x = |x: f32| x * scaling + x_offset;
         let transform_y = |y: f32| y * scaling + y_offset;
         let scale = |dim: f32| dim * scaling;
@@ -195,13 +870,24 @@ impl Game {
             draw_rectangle(wx, wy, ww, wh, WALL_COLOR);
             draw_rectangle_lines(wx, wy, ww, wh, 2.0, WALL_OUTLINE);
         }
+        map_features::draw(map, (scaling, x_offset, y_offset));
 
+        let viewer = game_engine
+            .tanks()
+            .iter()
+            .find(|t| Some(t.player_info.id) == player_id);
         for tank in game_engine.tanks() {
             let px = transform_x(tank.position.x);
             let py = transform_y(tank.position.y);
             let pr = scale(tank.radius);
 
-            let (main_color, glow_color) = if tank.player_info.team == Team::Blue {
+            // In free-for-all only our own tank is blue
+            let blue = match tank.player_info.team {
+                Team::Blue => true,
+                Team::Red => false,
+                Team::Ffa(id) => player_id == Some(id),
+            };
+            let (main_color, glow_color) = if blue {
                 (NEON_CYAN, Color::new(0.0, 1.0, 1.0, 0.2))
             } else {
                 (NEON_PINK, Color::new(1.0, 0.0, 1.0, 0.2))
@@ -211,6 +897,11 @@ impl Game {
             draw_circle(px, py, pr * 1.5, glow_color);
             // Main Body
             draw_circle(px, py, pr, main_color);
+            // Trim the player picked
+            if let Some(trim) = tank.player_info.color {
+                let trim = Color::from_rgba(trim.r, trim.g, trim.b, 255);
+                draw_circle_lines(px, py, pr * 0.75, pr * 0.2, trim);
+            }
             // Inner Core
             draw_circle(px, py, pr * 0.5, BLACK);
 
@@ -230,45 +921,8 @@ impl Game {
                 main_color,
             );
 
-            // Display health bar
-            let (hb_w, hb_h) = (50., 6.);
-
-            let hb_x = transform_x(tank.position.x - hb_w / 2.);
-            let hb_y = transform_y(tank.position.y - tank.radius - hb_h - 15.);
-
-            // Health bar background
-            draw_rectangle(
-                hb_x,
-                hb_y,
-                scale(hb_w),
-                scale(hb_h),
-                Color::new(0.1, 0.1, 0.1, 0.8),
-            );
-
-            // Health bar fill
-            let health_percentage = tank.health / 100.;
-            let health_color = if health_percentage > 0.5 {
-                GREEN
-            } else if health_percentage > 0.25 {
-                YELLOW
-            } else {
-                RED
-            };
-
-            draw_rectangle(
-                hb_x,
-                hb_y,
-                scale(hb_w * health_percentage),
-                scale(hb_h),
-                health_color,
-            );
-
-            // Draw nick
-            Text::new_simple(TEXT_SMALL, scaling).draw_no_scaling(
-                &tank.player_info.nickname,
-                transform_x(tank.position.x),
-                transform_y(tank.position.y - tank.radius - hb_h - 35.),
-            );
+            let alpha = name_tags::tag_alpha(viewer, tank, map, fog_of_war);
+            name_tags::draw_tag(tank, alpha, (scaling, x_offset, y_offset));
         }
 
         for projectile in game_engine.projectiles() {
@@ -276,6 +930,12 @@ impl Game {
             let py = transform_y(projectile.position.y);
             let pr = scale(projectile.radius);
 
+            if projectile.kind == ProjectileKind::Grenade {
+                draw_circle(px, py, pr * 1.6, Color::new(1.0, 0.5, 0.0, 0.35));
+                draw_circle(px, py, pr, DARKGRAY);
+                draw_circle_lines(px, py, pr, 2.0, ORANGE);
+                continue;
+            }
             // Projectile Glow
             draw_circle(px, py, pr * 2.0, Color::new(1.0, 1.0, 0.0, 0.3));
             // Projectile Core
@@ -283,11 +943,149 @@ impl Game {
         }
     }
 
+    pub fn presence(&self) -> Presence {
+        let code = self.get_game_code().to_string();
+        // A team per side, or per side of every qualifying arena; co-op has one of humans
+        let teams = match self.initial_game_info.mode {
+            GameMode::Coop => 1,
+            GameMode::TeamDeathmatch | GameMode::FreeForAll => 2,
+            GameMode::MultiArena => 2 * QUALIFYING_ARENAS,
+        };
+        match self.game_state {
+            GameState::Waiting => Presence::Lobby {
+                code,
+                players: self.game_engine.tanks().len(),
+                max_players: teams * usize::from(self.initial_game_info.team_size),
+            },
+            _ => Presence::InGame {
+                code,
+                round: self.current_round.min(self.initial_game_info.num_rounds),
+                rounds: self.initial_game_info.num_rounds,
+            },
+        }
+    }
+
     pub fn can_user_start_game(&self) -> bool {
         self.is_host && matches!(self.game_state, GameState::Waiting)
     }
 
+    /// Waits for the server to answer the host's `request` to start the match.
+    pub fn await_start(&mut self, request: RequestId) {
+        self.start_request = Some(request);
+    }
+
+    /// Shows why the match didn't start, once the server said it wouldn't.
+    pub fn poll_start(&mut self, server: &mut Server, log: &mut EventLog) {
+        let Some(request) = self.start_request else {
+            return;
+        };
+        let Some(reply) = server.take_reply(request) else {
+            return;
+        };
+        self.start_request = None;
+        if let Err(reason) = reply {
+            log.push(LogCategory::Error, &reason);
+            self.roster.start_failed(&reason, get_time());
+        }
+    }
+
+    /// Before the first round, while the players gather and count down.
+    pub fn in_lobby(&self) -> bool {
+        match self.game_state {
+            GameState::Waiting => true,
+            GameState::Countdown(_) => self.current_round <= 1,
+            _ => false,
+        }
+    }
+
+    /// Counts leaving the match under way as a loss; in co-op nobody wins, so it isn't.
+    pub fn record_forfeit(&self, settings: &mut Settings) {
+        if !self.is_underway() || self.is_coop() {
+            return;
+        }
+        settings.stats.multiplayer_losses += 1;
+        if let Err(e) = settings.save(Path::new(SETTINGS_PATH)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+    }
+
+    /// The match has started and isn't over yet.
+    pub fn is_underway(&self) -> bool {
+        matches!(
+            self.game_state,
+            GameState::Countdown(_) | GameState::Battle(_) | GameState::Intermission(_)
+        )
+    }
+
+    /// The local player's points and upgrades, and the seconds left to spend them, during
+    /// an intermission.
+    pub fn intermission(&self) -> Option<(Option<&ShopBalance>, u64)> {
+        let GameState::Intermission(seconds_left) = self.game_state else {
+            return None;
+        };
+        let me = self.initial_game_info.player_id;
+        Some((self.shop.iter().find(|b| b.player == me), seconds_left))
+    }
+
+    /// What the game is at, for crash reports.
+    pub fn crash_summary(&self) -> String {
+        let info = &self.initial_game_info;
+        format!(
+            "game {} ({:?} on {:?}), {:?}, round {}/{}, {} tanks",
+            self.get_game_code(),
+            info.mode,
+            info.map_name,
+            self.game_state,
+            self.current_round,
+            info.num_rounds,
+            self.game_engine.tanks().len()
+        )
+    }
+
     pub fn get_game_code(&self) -> &str {
         &self.initial_game_info.game_code.0
     }
+
+    /// The seed of the match's bots, see [`InitialGameInfo::seed`].
+    pub fn seed(&self) -> u64 {
+        self.initial_game_info.seed
+    }
+}
+
+/// A killer or victim in the kill feed, with their team unless everyone fights alone.
+fn player_label(info: &PlayerInfo) -> String {
+    match info.team {
+        Team::Ffa(_) => info.nickname.clone(),
+        team => format!("{} ({:?})", info.nickname, team),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_joining_mid_match_starts_where_the_match_is() {
+        assert_eq!(
+            Arrival::of(&GameState::Waiting),
+            Arrival::Lobby { countdown: None }
+        );
+        assert_eq!(
+            Arrival::of(&GameState::Countdown(3)),
+            Arrival::Lobby { countdown: Some(3) }
+        );
+        assert_eq!(Arrival::of(&GameState::Battle(90)), Arrival::Spectator);
+        assert_eq!(
+            Arrival::of(&GameState::Intermission(10)),
+            Arrival::Spectator
+        );
+        assert_eq!(
+            Arrival::of(&GameState::Results {
+                winner: Some(Team::Red),
+                blue_score: 1,
+                red_score: 2,
+            }),
+            Arrival::Results
+        );
+    }
 }
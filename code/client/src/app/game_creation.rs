@@ -1,23 +1,50 @@
+use crate::app::map_preview::MapPreviews;
 use crate::app::request_view::RequestView;
 use crate::app::server_lobby::ServerLobby;
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
 use crate::server::ClientState;
 use crate::ui::{
     BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_MID, Text,
     TextVerticalPositioning, default_text_params,
 };
+use common::ai::BotDifficulty;
+use common::game::MapDefinition;
 use common::game::map::MapName;
-use common::protocol::ClientMessage;
+use common::game::{DEFAULT_TEAM_SIZE, MAX_TEAM_SIZE};
+use common::protocol::{ClientMessage, FiringMode, GameMode};
 use macroquad::prelude::*;
 
 const ROUND_NUMBER_CHOICES: [u8; 5] = [1, 5, 10, 15, 20];
+const DIFFICULTIES: [BotDifficulty; 6] = [
+    BotDifficulty::Dummy,
+    BotDifficulty::Turret,
+    BotDifficulty::Wanderer,
+    BotDifficulty::Hunter,
+    BotDifficulty::Terminator,
+    BotDifficulty::TrainedKiller,
+];
+/// Most bots a game can be limited to, enough to fill two of the biggest teams
+const MAX_BOT_COUNT: u8 = 2 * MAX_TEAM_SIZE as u8;
 
 #[derive(Copy, Clone)]
 enum GameCreationButtons {
+    ModeScrollLeft,
+    ModeScrollRight,
     MapScrollLeft,
     MapScrollRight,
     RoundScrollLeft,
     RoundScrollRight,
+    TeamSizeScrollLeft,
+    TeamSizeScrollRight,
+    DifficultyScrollLeft,
+    DifficultyScrollRight,
+    BotCountScrollLeft,
+    BotCountScrollRight,
+    ModelScrollLeft,
+    ModelScrollRight,
+    IntermissionToggle,
+    FiringToggle,
     Create,
     Back,
 }
@@ -26,6 +53,18 @@ pub(crate) struct GameCreation {
     button_pressed: Option<GameCreationButtons>,
     round_index: usize,
     current_map: MapName,
+    current_mode: GameMode,
+    team_size: usize,
+    /// Players buy upgrades between rounds
+    intermission: bool,
+    firing: FiringMode,
+    difficulty_index: usize,
+    /// Most bots in the game; `None` fills the teams
+    bot_count: Option<u8>,
+    /// Index of the server's model trained bots play with; `None` is the built-in one
+    model: Option<usize>,
+    models_asked: bool,
+    previews: MapPreviews,
 }
 
 impl GameCreation {
@@ -34,12 +73,157 @@ impl GameCreation {
             button_pressed: None,
             round_index: 1,
             current_map: MapName::Basic,
+            current_mode: GameMode::default(),
+            team_size: DEFAULT_TEAM_SIZE,
+            intermission: false,
+            firing: FiringMode::default(),
+            difficulty_index: 3,
+            bot_count: None,
+            model: None,
+            models_asked: false,
+            previews: MapPreviews::default(),
         }
     }
 }
 
+pub(crate) fn mode_name(mode: GameMode) -> String {
+    match mode {
+        GameMode::TeamDeathmatch => tr!("mode.team_deathmatch"),
+        GameMode::MultiArena => tr!("mode.multi_arena"),
+        GameMode::FreeForAll => tr!("mode.free_for_all"),
+        GameMode::Coop => tr!("mode.coop"),
+    }
+}
+
+pub(crate) fn firing_name(firing: FiringMode) -> String {
+    match firing {
+        FiringMode::Cooldown => tr!("firing.cooldown"),
+        FiringMode::Heat => tr!("firing.heat"),
+    }
+}
+
+pub(crate) fn difficulty_name(difficulty: BotDifficulty) -> String {
+    match difficulty {
+        BotDifficulty::Dummy => tr!("difficulty.dummy"),
+        BotDifficulty::Turret => tr!("difficulty.turret"),
+        BotDifficulty::Wanderer => tr!("difficulty.wanderer"),
+        BotDifficulty::Hunter => tr!("difficulty.hunter"),
+        BotDifficulty::Terminator => tr!("difficulty.terminator"),
+        BotDifficulty::TrainedKiller => tr!("difficulty.trained_killer"),
+    }
+}
+
+/// Draws the `<` and `>` of a scroller `spread` to either side of `x`, giving the one
+/// clicked.
+fn scroll_buttons(
+    x: f32,
+    y: f32,
+    spread: f32,
+    (left, right): (GameCreationButtons, GameCreationButtons),
+    has_input: bool,
+) -> Option<GameCreationButtons> {
+    let mut pressed = None;
+    if Button::default()
+        .draw_centered(x - spread, y, 50., 50., Some("<"), has_input)
+        .poll()
+    {
+        pressed = Some(left);
+    }
+    if Button::default()
+        .draw_centered(x + spread, y, 50., 50., Some(">"), has_input)
+        .poll()
+    {
+        pressed = Some(right);
+    }
+    pressed
+}
+
+impl GameCreation {
+    fn difficulty(&self) -> BotDifficulty {
+        DIFFICULTIES[self.difficulty_index]
+    }
+
+    /// The picked model, if trained bots play with one of the server's.
+    fn model<'a>(&self, models: &'a [String]) -> Option<&'a String> {
+        if self.difficulty() != BotDifficulty::TrainedKiller {
+            return None;
+        }
+        self.model.and_then(|index| models.get(index))
+    }
+
+    /// A row of the bots' difficulty, how many there are and the model trained ones use.
+    fn draw_bots(&mut self, ctx: &AppContext, text: &Text, layout: &mut Layout, has_input: bool) {
+        use GameCreationButtons::*;
+
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        // Multi-arena and co-op games fill every free place
+        let counted = matches!(
+            self.current_mode,
+            GameMode::TeamDeathmatch | GameMode::FreeForAll
+        );
+        let trained = self.difficulty() == BotDifficulty::TrainedKiller;
+        let (difficulty_x, count_x, model_x) = (x_mid - 360., x_mid, x_mid + 360.);
+
+        Text::new_scaled(TEXT_MID).draw(&tr!("create_game.bots"), difficulty_x, layout.next());
+        if counted {
+            Text::new_scaled(TEXT_MID).draw(&tr!("create_game.bot_count"), count_x, layout.next());
+        }
+        if trained {
+            Text::new_scaled(TEXT_MID).draw(&tr!("create_game.bot_model"), model_x, layout.next());
+        }
+        layout.add(20.);
+
+        text.draw(
+            &difficulty_name(self.difficulty()),
+            difficulty_x,
+            layout.next(),
+        );
+        let mut pressed = scroll_buttons(
+            difficulty_x,
+            layout.next(),
+            100.,
+            (DifficultyScrollLeft, DifficultyScrollRight),
+            has_input,
+        );
+        if counted {
+            let count = match self.bot_count {
+                Some(count) => count.to_string(),
+                None => tr!("create_game.bots_fill"),
+            };
+            text.draw(&count, count_x, layout.next());
+            pressed = scroll_buttons(
+                count_x,
+                layout.next(),
+                100.,
+                (BotCountScrollLeft, BotCountScrollRight),
+                has_input,
+            )
+            .or(pressed);
+        }
+        if trained {
+            let model = match self.model(ctx.server.models()) {
+                Some(name) => name.clone(),
+                None => tr!("create_game.built_in_model"),
+            };
+            text.draw(&model, model_x, layout.next());
+            pressed = scroll_buttons(
+                model_x,
+                layout.next(),
+                100.,
+                (ModelScrollLeft, ModelScrollRight),
+                has_input,
+            )
+            .or(pressed);
+        }
+        if pressed.is_some() {
+            self.button_pressed = pressed;
+        }
+        layout.add(BUTTON_H);
+    }
+}
+
 impl View for GameCreation {
-    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
         // For scrollers
         let consitent_text = Text {
             params: TextParams {
@@ -54,37 +238,120 @@ impl View for GameCreation {
         let el_w = BUTTON_W;
         let el_h = BUTTON_H;
 
-        let mut layout = Layout::new(100., 30.);
+        let mut layout = Layout::new(80., 20.);
         self.button_pressed = None;
 
-        Text::new_title().draw("Create Game", x_mid, layout.next());
-        layout.add(70.);
+        Text::new_title().draw(&tr!("create_game.title"), x_mid, layout.next());
+        layout.add(30.);
 
-        Text::new_scaled(TEXT_MID).draw("Choose number of rounds:", x_mid, layout.next());
+        Text::new_scaled(TEXT_MID).draw(&tr!("create_game.mode"), x_mid, layout.next());
         layout.add(20.);
 
-        let num_rounds = ROUND_NUMBER_CHOICES[self.round_index];
-
-        consitent_text.draw(&num_rounds.to_string(), x_mid, layout.next());
+        consitent_text.draw(&mode_name(self.current_mode), x_mid, layout.next());
         if Button::default()
-            .draw_centered(x_mid - 100., layout.next(), 50., 50., Some("<"), has_input)
+            .draw_centered(x_mid - 160., layout.next(), 50., 50., Some("<"), has_input)
             .poll()
         {
-            self.button_pressed = Some(GameCreationButtons::RoundScrollLeft);
+            self.button_pressed = Some(GameCreationButtons::ModeScrollLeft);
         }
         if Button::default()
-            .draw_centered(x_mid + 100., layout.next(), 50., 50., Some(">"), has_input)
+            .draw_centered(x_mid + 160., layout.next(), 50., 50., Some(">"), has_input)
             .poll()
         {
-            self.button_pressed = Some(GameCreationButtons::RoundScrollRight);
+            self.button_pressed = Some(GameCreationButtons::ModeScrollRight);
+        }
+        layout.add(el_h);
+
+        if self.current_mode == GameMode::MultiArena {
+            Text::new_scaled(TEXT_MID).draw(&tr!("create_game.rounds"), x_mid, layout.next());
+            layout.add(20.);
+            // Always the qualifiers and the final, in squads of four
+            consitent_text.draw(&tr!("create_game.multi_arena_rounds"), x_mid, layout.next());
+        } else {
+            // Rounds and team size share a row. A free-for-all has room for two teams' tanks.
+            let free_for_all = self.current_mode == GameMode::FreeForAll;
+            let coop = self.current_mode == GameMode::Coop;
+            let (rounds_x, size_x) = (x_mid - 180., x_mid + 180.);
+            let size_label = if free_for_all {
+                tr!("create_game.tanks")
+            } else {
+                tr!("create_game.team_size")
+            };
+            Text::new_scaled(TEXT_MID).draw(&tr!("create_game.rounds"), rounds_x, layout.next());
+            Text::new_scaled(TEXT_MID).draw(&size_label, size_x, layout.next());
+            layout.add(20.);
+
+            if coop {
+                // A single round, as long as the team holds out
+                consitent_text.draw(&tr!("create_game.coop_rounds"), rounds_x, layout.next());
+            } else {
+                let num_rounds = ROUND_NUMBER_CHOICES[self.round_index];
+                consitent_text.draw(&num_rounds.to_string(), rounds_x, layout.next());
+                if Button::default()
+                    .draw_centered(
+                        rounds_x - 100.,
+                        layout.next(),
+                        50.,
+                        50.,
+                        Some("<"),
+                        has_input,
+                    )
+                    .poll()
+                {
+                    self.button_pressed = Some(GameCreationButtons::RoundScrollLeft);
+                }
+                if Button::default()
+                    .draw_centered(
+                        rounds_x + 100.,
+                        layout.next(),
+                        50.,
+                        50.,
+                        Some(">"),
+                        has_input,
+                    )
+                    .poll()
+                {
+                    self.button_pressed = Some(GameCreationButtons::RoundScrollRight);
+                }
+            }
+
+            let size = if free_for_all {
+                (2 * self.team_size).to_string()
+            } else if coop {
+                tr!("create_game.coop_size", size = self.team_size)
+            } else {
+                tr!("create_game.versus", size = self.team_size)
+            };
+            consitent_text.draw(&size, size_x, layout.next());
+            if Button::default()
+                .draw_centered(size_x - 100., layout.next(), 50., 50., Some("<"), has_input)
+                .poll()
+            {
+                self.button_pressed = Some(GameCreationButtons::TeamSizeScrollLeft);
+            }
+            if Button::default()
+                .draw_centered(size_x + 100., layout.next(), 50., 50., Some(">"), has_input)
+                .poll()
+            {
+                self.button_pressed = Some(GameCreationButtons::TeamSizeScrollRight);
+            }
         }
         layout.add(el_h);
 
-        Text::new_scaled(TEXT_MID).draw("Choose map:", x_mid, layout.next());
+        self.draw_bots(ctx, &consitent_text, &mut layout, has_input);
+
+        Text::new_scaled(TEXT_MID).draw(&tr!("create_game.map"), x_mid, layout.next());
         layout.add(20.);
 
         let map_name = format!("{:?}", self.current_map);
         consitent_text.draw(&map_name, x_mid, layout.next());
+        let map = self.current_map;
+        self.previews.draw(
+            &map_name,
+            || MapDefinition::load_name(map),
+            x_mid + 420.,
+            layout.next(),
+        );
         if Button::default()
             .draw_centered(x_mid - 100., layout.next(), 50., 50., Some("<"), has_input)
             .poll()
@@ -99,16 +366,73 @@ impl View for GameCreation {
         }
         layout.add(el_h);
 
+        // Multi-arena and co-op games have no rounds to shop between
+        let firing_x = if matches!(
+            self.current_mode,
+            GameMode::TeamDeathmatch | GameMode::FreeForAll
+        ) {
+            let value = if self.intermission {
+                tr!("common.on")
+            } else {
+                tr!("common.off")
+            };
+            if Button::default()
+                .draw_centered(
+                    x_mid - 170.,
+                    layout.next(),
+                    el_w,
+                    el_h,
+                    Some(&tr!("create_game.intermission", value = value)),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_pressed = Some(GameCreationButtons::IntermissionToggle);
+            }
+            x_mid + 170.
+        } else {
+            x_mid
+        };
+        let firing = tr!("create_game.firing", value = firing_name(self.firing));
         if Button::default()
-            .draw_centered(x_mid, layout.next(), el_w, el_h, Some("Create"), has_input)
+            .draw_centered(
+                firing_x,
+                layout.next(),
+                el_w,
+                el_h,
+                Some(&firing),
+                has_input,
+            )
             .poll()
         {
-            self.button_pressed = Some(GameCreationButtons::Create);
+            self.button_pressed = Some(GameCreationButtons::FiringToggle);
         }
         layout.add(el_h);
 
+        // Side by side, to leave room for the settings
         if Button::default()
-            .draw_centered(x_mid, layout.next(), el_w, el_h, Some("Back"), has_input)
+            .draw_centered(
+                x_mid - 170.,
+                layout.next(),
+                el_w,
+                el_h,
+                Some(&tr!("create_game.create")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(GameCreationButtons::Create);
+        }
+
+        if Button::default()
+            .draw_centered(
+                x_mid + 170.,
+                layout.next(),
+                el_w,
+                el_h,
+                Some(&tr!("common.back")),
+                has_input,
+            )
             .poll()
         {
             self.button_pressed = Some(GameCreationButtons::Back);
@@ -117,20 +441,51 @@ impl View for GameCreation {
 
     fn update(&mut self, ctx: &mut AppContext) -> Transition {
         ctx.server.assert_state(ClientState::Connected);
+        if !self.models_asked {
+            ctx.server.list_models();
+            self.models_asked = true;
+        }
 
         match self.button_pressed {
             Some(button) => match button {
                 GameCreationButtons::Create => {
-                    ctx.server.send_client_message(ClientMessage::CreateGame {
+                    let request = ctx.server.send_request(ClientMessage::CreateGame {
                         map: self.current_map,
                         rounds: ROUND_NUMBER_CHOICES[self.round_index],
+                        mode: self.current_mode,
+                        team_size: self.team_size as u8,
+                        intermission: self.intermission,
+                        firing: self.firing,
+                        bot_difficulty: self.difficulty(),
+                        bot_count: self.bot_count,
+                        bot_model: self.model(ctx.server.models()).cloned(),
                     });
                     Transition::Push(Box::new(RequestView::new_action(
-                        "Creating game...".into(),
+                        tr!("create_game.creating"),
+                        request,
                         ServerLobby::get_game_completion_action(),
                     )))
                 }
+                GameCreationButtons::IntermissionToggle => {
+                    self.intermission = !self.intermission;
+                    Transition::None
+                }
+                GameCreationButtons::FiringToggle => {
+                    self.firing = match self.firing {
+                        FiringMode::Cooldown => FiringMode::Heat,
+                        FiringMode::Heat => FiringMode::Cooldown,
+                    };
+                    Transition::None
+                }
                 GameCreationButtons::Back => Transition::Pop,
+                GameCreationButtons::ModeScrollLeft => {
+                    self.current_mode = self.current_mode.prev();
+                    Transition::None
+                }
+                GameCreationButtons::ModeScrollRight => {
+                    self.current_mode = self.current_mode.next();
+                    Transition::None
+                }
                 GameCreationButtons::MapScrollLeft => {
                     self.current_map = self.current_map.prev();
                     Transition::None
@@ -149,6 +504,58 @@ impl View for GameCreation {
                     self.round_index = (self.round_index + 1) % len;
                     Transition::None
                 }
+                GameCreationButtons::TeamSizeScrollLeft => {
+                    self.team_size = (self.team_size + MAX_TEAM_SIZE - 2) % MAX_TEAM_SIZE + 1;
+                    Transition::None
+                }
+                GameCreationButtons::TeamSizeScrollRight => {
+                    self.team_size = self.team_size % MAX_TEAM_SIZE + 1;
+                    Transition::None
+                }
+                GameCreationButtons::DifficultyScrollLeft => {
+                    let len = DIFFICULTIES.len();
+                    self.difficulty_index = (len + self.difficulty_index - 1) % len;
+                    Transition::None
+                }
+                GameCreationButtons::DifficultyScrollRight => {
+                    self.difficulty_index = (self.difficulty_index + 1) % DIFFICULTIES.len();
+                    Transition::None
+                }
+                // Counts go round from filling the teams through no bots up to the most
+                GameCreationButtons::BotCountScrollLeft => {
+                    self.bot_count = match self.bot_count {
+                        None => Some(MAX_BOT_COUNT),
+                        Some(0) => None,
+                        Some(count) => Some(count - 1),
+                    };
+                    Transition::None
+                }
+                GameCreationButtons::BotCountScrollRight => {
+                    self.bot_count = match self.bot_count {
+                        None => Some(0),
+                        Some(MAX_BOT_COUNT) => None,
+                        Some(count) => Some(count + 1),
+                    };
+                    Transition::None
+                }
+                // Likewise from the built-in model through the server's
+                GameCreationButtons::ModelScrollLeft => {
+                    let len = ctx.server.models().len();
+                    self.model = match self.model.filter(|&index| index < len) {
+                        None => len.checked_sub(1),
+                        Some(0) => None,
+                        Some(index) => Some(index - 1),
+                    };
+                    Transition::None
+                }
+                GameCreationButtons::ModelScrollRight => {
+                    let len = ctx.server.models().len();
+                    self.model = match self.model.filter(|&index| index < len) {
+                        None => (len > 0).then_some(0),
+                        Some(index) => Some(index + 1).filter(|&next| next < len),
+                    };
+                    Transition::None
+                }
             },
             None => Transition::None,
         }
@@ -0,0 +1,190 @@
+//! The lobby's invitation box: the game code, buttons to copy it or a join link, and the
+//! link as a QR code to scan from another screen.
+
+use crate::i18n::tr;
+use crate::join_link::JoinLink;
+use crate::ui::field::Field;
+use crate::ui::{
+    Button, TEXT_MID, TEXT_SMALL, Text, default_text_params, scale_dims, theme::NEON_CYAN,
+};
+use common::protocol::GameCode;
+use macroquad::prelude::*;
+use qrcodegen::{QrCode, QrCodeEcc};
+
+const LEFT: f32 = 40.;
+const TOP: f32 = 130.;
+const WIDTH: f32 = 280.;
+const ROW_H: f32 = 50.;
+const QR_SIZE: f32 = 200.;
+/// Light modules around the code, so scanners find its edges
+const QUIET_ZONE: i32 = 2;
+/// Seconds the note that something was copied stays up
+const NOTE_DURATION: f64 = 2.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ShareButton {
+    CopyCode,
+    CopyLink,
+    ToggleQr,
+}
+
+pub(crate) struct GameShare {
+    code: GameCode,
+    /// Unknown when we can't tell which address the game's server goes by
+    link: Option<String>,
+    qr: Option<QrCode>,
+    show_qr: bool,
+    copied_at: Option<f64>,
+}
+
+impl GameShare {
+    /// The box for game `code`, on the server players reach at `server`.
+    pub fn new(code: GameCode, server: Option<&str>) -> Self {
+        let link = server.map(|server| {
+            JoinLink {
+                server: server.to_string(),
+                code: code.clone(),
+            }
+            .to_string()
+        });
+        let qr = link
+            .as_deref()
+            .and_then(|link| QrCode::encode_text(link, QrCodeEcc::Medium).ok());
+        Self {
+            code,
+            link,
+            qr,
+            show_qr: false,
+            copied_at: None,
+        }
+    }
+
+    /// Handles a click on `button` at `time`; returns the text to put on the clipboard.
+    pub fn press(&mut self, button: ShareButton, time: f64) -> Option<String> {
+        let copied = match button {
+            ShareButton::CopyCode => Some(self.code.0.clone()),
+            ShareButton::CopyLink => self.link.clone(),
+            ShareButton::ToggleQr => {
+                self.show_qr = !self.show_qr;
+                None
+            }
+        };
+        if copied.is_some() {
+            self.copied_at = Some(time);
+        }
+        copied
+    }
+
+    /// Draws the box in the lobby's top left corner. Returns the button clicked.
+    pub fn draw(&self, has_input: bool, time: f64) -> Option<ShareButton> {
+        let x_mid = LEFT + WIDTH / 2.;
+        let mut rows = if self.qr.is_some() { 3. } else { 2. };
+        if self.show_qr {
+            rows += QR_SIZE / ROW_H;
+        }
+        let (x, y, w, h) = scale_dims(LEFT - 20., TOP - 30., WIDTH + 40., ROW_H * rows + 30.);
+        draw_rectangle(x, y, w, h, Color::new(0., 0., 0., 0.6));
+
+        Text::new_scaled(TEXT_MID).draw(&tr!("share.code", code = self.code.0), x_mid, TOP);
+
+        let mut clicked = None;
+        let mut button = |label: String, x: f32, y: f32, w: f32, pressed: ShareButton| {
+            let params = TextParams {
+                font_size: TEXT_SMALL,
+                ..default_text_params()
+            };
+            if Button::new(Field::default(), Some(params))
+                .draw_centered(x, y, w, ROW_H - 10., Some(&label), has_input)
+                .poll()
+            {
+                clicked = Some(pressed);
+            }
+        };
+        let y = TOP + ROW_H;
+        if self.link.is_some() {
+            let half = (WIDTH - 10.) / 2.;
+            let copy_code = tr!("share.copy_code");
+            button(copy_code, LEFT + half / 2., y, half, ShareButton::CopyCode);
+            let copy_link = tr!("share.copy_link");
+            button(
+                copy_link,
+                LEFT + WIDTH - half / 2.,
+                y,
+                half,
+                ShareButton::CopyLink,
+            );
+        } else {
+            button(
+                tr!("share.copy_code"),
+                x_mid,
+                y,
+                WIDTH,
+                ShareButton::CopyCode,
+            );
+        }
+        if self.qr.is_some() {
+            let label = if self.show_qr {
+                tr!("share.hide_qr")
+            } else {
+                tr!("share.show_qr")
+            };
+            button(label, x_mid, y + ROW_H, WIDTH, ShareButton::ToggleQr);
+        }
+
+        if let Some(qr) = self.qr.as_ref().filter(|_| self.show_qr) {
+            draw_qr(qr, x_mid - QR_SIZE / 2., y + ROW_H * 1.6);
+        }
+        if self.copied_at.is_some_and(|at| time - at < NOTE_DURATION) {
+            let mut text = Text::new_scaled(TEXT_SMALL);
+            text.params.color = NEON_CYAN;
+            text.draw(&tr!("share.copied"), x_mid, TOP + ROW_H * rows - 5.);
+        }
+        clicked
+    }
+}
+
+/// Draws `qr` dark on light, `QR_SIZE` wide with its top left corner at `left`, `top`.
+fn draw_qr(qr: &QrCode, left: f32, top: f32) {
+    let (x, y, size, _) = scale_dims(left, top, QR_SIZE, QR_SIZE);
+    draw_rectangle(x, y, size, size, WHITE);
+    let module = size / (qr.size() + 2 * QUIET_ZONE) as f32;
+    for row in 0..qr.size() {
+        for column in 0..qr.size() {
+            if qr.get_module(column, row) {
+                draw_rectangle(
+                    x + (column + QUIET_ZONE) as f32 * module,
+                    y + (row + QUIET_ZONE) as f32 * module,
+                    module.ceil(),
+                    module.ceil(),
+                    BLACK,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_box_copies_the_code_or_a_link_to_the_game() {
+        let mut share = GameShare::new(GameCode("4821".into()), Some("eu.example.com"));
+        assert_eq!(
+            share.press(ShareButton::CopyCode, 1.),
+            Some("4821".to_string())
+        );
+        assert_eq!(
+            share.press(ShareButton::CopyLink, 2.),
+            Some("neuroblasters://join/eu.example.com/4821".to_string())
+        );
+        assert_eq!(share.copied_at, Some(2.));
+        assert_eq!(share.press(ShareButton::ToggleQr, 3.), None);
+        assert!(share.show_qr && share.qr.is_some());
+
+        // Without the server's address there is only the code to give out
+        let mut share = GameShare::new(GameCode("4821".into()), None);
+        assert_eq!(share.press(ShareButton::CopyLink, 1.), None);
+        assert!(share.qr.is_none());
+    }
+}
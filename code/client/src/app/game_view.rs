@@ -1,36 +1,102 @@
+use crate::app::game_share::{GameShare, ShareButton};
 use crate::app::in_game_menu::InGameMenu;
+use crate::app::shop;
 
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::presence::Presence;
 use crate::server::ClientState;
+use common::protocol::{GameCode, Upgrade};
+use macroquad::miniquad::window::clipboard_set;
 use macroquad::prelude::*;
 
-pub(crate) struct GameView;
+pub(crate) struct GameView {
+    /// Upgrade clicked in the intermission's shop
+    upgrade_picked: Option<Upgrade>,
+    /// The lobby's invitation box, set up once the game is known
+    share: Option<GameShare>,
+    share_clicked: Option<ShareButton>,
+}
 
 impl GameView {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            upgrade_picked: None,
+            share: None,
+            share_clicked: None,
+        }
+    }
+}
+
+/// Where players go when they leave a game: tournament entrants back to the bracket.
+pub(crate) fn exit_view(ctx: &AppContext) -> ViewId {
+    if ctx.server.tournament().is_some() {
+        ViewId::Tournament
+    } else {
+        ViewId::ServerLobby
     }
 }
 
 impl View for GameView {
-    fn draw(&mut self, ctx: &AppContext, _has_input: bool) {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        self.upgrade_picked = None;
+        self.share_clicked = None;
         if ctx.game.is_none() {
             return;
         }
         let game = &ctx.game.as_ref().unwrap();
-        game.draw();
+        game.draw(&ctx.settings);
+        if let Some((balance, seconds_left)) = game.intermission() {
+            self.upgrade_picked = shop::draw_shop(balance, seconds_left, has_input);
+        }
+        if game.in_lobby()
+            && let Some(share) = &self.share
+        {
+            self.share_clicked = share.draw(has_input, get_time());
+        }
     }
 
     fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        if ctx.game.as_ref().is_some_and(|game| game.closed()) {
+            return Transition::PopUntil(exit_view(ctx));
+        }
         ctx.server.assert_state(ClientState::Playing);
 
         if is_key_pressed(KeyCode::Escape) {
+            if let Some(game) = &mut ctx.game {
+                game.set_menu_open(true);
+            }
             return Transition::Push(Box::new(InGameMenu::new()));
         }
 
+        if let Some(game) = &mut ctx.game {
+            game.set_menu_open(false);
+            game.update_spectator_camera(get_frame_time());
+            game.update_pings(&mut ctx.server);
+            game.poll_start(&mut ctx.server, &mut ctx.log);
+        }
+        if let Some(upgrade) = self.upgrade_picked.take() {
+            ctx.server.send_upgrade(upgrade);
+        }
+        if self.share.is_none()
+            && let Some(game) = &ctx.game
+        {
+            let code = GameCode(game.get_game_code().to_string());
+            self.share = Some(GameShare::new(code, ctx.server.address()));
+        }
+        if let Some(button) = self.share_clicked.take()
+            && let Some(share) = &mut self.share
+            && let Some(copied) = share.press(button, get_time())
+        {
+            clipboard_set(&copied);
+        }
+
         Transition::None
     }
 
+    fn presence(&self, ctx: &AppContext) -> Option<Presence> {
+        ctx.game.as_ref().map(|game| game.presence())
+    }
+
     fn get_id(&self) -> ViewId {
         ViewId::GameView
     }
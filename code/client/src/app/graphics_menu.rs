@@ -0,0 +1,180 @@
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::settings::{FPS_CAPS, RESOLUTIONS, SETTINGS_PATH, next_option};
+use crate::ui::{BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_LARGE, Text};
+use macroquad::prelude::{Color, draw_rectangle, screen_height, screen_width};
+use macroquad::window::{request_new_screen_size, set_fullscreen};
+use std::path::Path;
+
+#[derive(Clone, Copy)]
+enum GraphicsButton {
+    Vsync,
+    FpsCap,
+    FrameGraph,
+    Fullscreen,
+    Resolution,
+    Back,
+}
+
+/// Window and frame pacing options, opened from the options menu or over a match.
+pub(crate) struct GraphicsMenu {
+    button_clicked: Option<GraphicsButton>,
+    /// Drawn over the match instead of replacing it
+    overlay: bool,
+}
+
+impl GraphicsMenu {
+    pub fn new() -> Self {
+        Self {
+            button_clicked: None,
+            overlay: false,
+        }
+    }
+
+    pub fn new_overlay() -> Self {
+        Self {
+            overlay: true,
+            ..Self::new()
+        }
+    }
+
+    fn on_off(value: bool) -> String {
+        if value {
+            tr!("common.on")
+        } else {
+            tr!("common.off")
+        }
+    }
+}
+
+impl View for GraphicsMenu {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        let mut layout = Layout::new(80., 15.);
+
+        if self.overlay {
+            draw_rectangle(
+                0.,
+                0.,
+                screen_width(),
+                screen_height(),
+                Color::new(0.0, 0.0, 0.0, 0.7),
+            );
+        }
+
+        Text::new_scaled(TEXT_LARGE).draw(&tr!("graphics.title"), x_mid, layout.next());
+        layout.add(50.);
+
+        self.button_clicked = None;
+
+        let graphics = &ctx.settings.graphics;
+        let fps_cap = graphics
+            .fps_cap
+            .map_or_else(|| tr!("graphics.uncapped"), |fps| fps.to_string());
+        let (width, height) = graphics.resolution;
+        let options = [
+            (
+                GraphicsButton::Vsync,
+                tr!("graphics.vsync", value = Self::on_off(graphics.vsync)),
+            ),
+            (
+                GraphicsButton::FpsCap,
+                tr!("graphics.fps_cap", value = fps_cap),
+            ),
+            (
+                GraphicsButton::FrameGraph,
+                tr!(
+                    "graphics.frame_graph",
+                    value = Self::on_off(graphics.frame_graph)
+                ),
+            ),
+            (
+                GraphicsButton::Fullscreen,
+                tr!(
+                    "graphics.fullscreen",
+                    value = Self::on_off(graphics.fullscreen)
+                ),
+            ),
+            (
+                GraphicsButton::Resolution,
+                tr!(
+                    "graphics.resolution",
+                    value = format!("{}x{}", width, height)
+                ),
+            ),
+        ];
+
+        for (button, label) in options {
+            if Button::default()
+                .draw_centered(
+                    x_mid,
+                    layout.next(),
+                    BUTTON_W * 1.3,
+                    BUTTON_H,
+                    Some(&label),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_clicked = Some(button);
+            }
+            layout.add(BUTTON_H);
+        }
+
+        layout.add(20.);
+
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("common.back")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_clicked = Some(GraphicsButton::Back);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        let Some(button) = self.button_clicked.take() else {
+            return Transition::None;
+        };
+        let graphics = &mut ctx.settings.graphics;
+        match button {
+            GraphicsButton::Vsync => graphics.vsync = !graphics.vsync,
+            GraphicsButton::FpsCap => graphics.fps_cap = next_option(&FPS_CAPS, graphics.fps_cap),
+            GraphicsButton::FrameGraph => graphics.frame_graph = !graphics.frame_graph,
+            GraphicsButton::Fullscreen => {
+                graphics.fullscreen = !graphics.fullscreen;
+                set_fullscreen(graphics.fullscreen);
+                if !graphics.fullscreen {
+                    let (width, height) = graphics.resolution;
+                    request_new_screen_size(width as f32, height as f32);
+                }
+            }
+            GraphicsButton::Resolution => {
+                graphics.resolution = next_option(&RESOLUTIONS, graphics.resolution);
+                if !graphics.fullscreen {
+                    let (width, height) = graphics.resolution;
+                    request_new_screen_size(width as f32, height as f32);
+                }
+            }
+            GraphicsButton::Back => return Transition::Pop,
+        }
+        if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        Transition::None
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::GraphicsMenu
+    }
+
+    fn is_overlay(&self) -> bool {
+        self.overlay
+    }
+}
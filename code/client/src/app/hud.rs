@@ -0,0 +1,273 @@
+use crate::app::crosshair::draw_crosshair;
+use crate::i18n::tr;
+use crate::settings::CrosshairStyle;
+use crate::ui::{CANONICAL_SCREEN_HEIGHT, TEXT_SMALL, Text, scale_dims};
+use common::game::{Tank, balance};
+use macroquad::prelude::*;
+
+const HEALTH_BAR_W: f32 = 300.;
+const HEALTH_BAR_H: f32 = 22.;
+const HEAT_BAR_H: f32 = 8.;
+const HUD_MARGIN: f32 = 30.;
+/// Below this share of health the screen edges start glowing red.
+const VIGNETTE_THRESHOLD: f32 = 0.3;
+
+/// A cooldown shown next to the health bar, e.g. the weapon or an ability.
+pub(crate) struct CooldownSlot {
+    pub label: String,
+    /// 0 when ready, 1 right after use
+    pub fraction: f32,
+}
+
+pub(crate) fn health_fraction(tank: &Tank) -> f32 {
+    (tank.health / tank.max_health).clamp(0., 1.)
+}
+
+pub(crate) fn health_color(fraction: f32) -> Color {
+    if fraction > 0.5 {
+        GREEN
+    } else if fraction > 0.25 {
+        YELLOW
+    } else {
+        RED
+    }
+}
+
+/// How long until the gun fires again; while it's overheated, how much it has left to
+/// cool.
+pub(crate) fn weapon_cooldown_fraction(tank: &Tank) -> f32 {
+    if tank.overheated {
+        return tank.heat.clamp(0., 1.);
+    }
+    (tank.weapon_cooldown / tank.fire_interval).clamp(0., 1.)
+}
+
+/// The heat gauge's colour: cool blue, through orange, to red when the gun is about to
+/// overheat or has.
+pub(crate) fn heat_color(tank: &Tank) -> Color {
+    if tank.overheated || tank.heat > 0.8 {
+        RED
+    } else if tank.heat > 0.5 {
+        ORANGE
+    } else {
+        SKYBLUE
+    }
+}
+
+/// Cooldowns of the tank's weapon and abilities, in display order.
+pub(crate) fn cooldown_slots(tank: &Tank) -> Vec<CooldownSlot> {
+    vec![
+        CooldownSlot {
+            label: tr!("hud.fire"),
+            fraction: weapon_cooldown_fraction(tank),
+        },
+        CooldownSlot {
+            label: tr!("hud.grenade"),
+            fraction: (tank.grenade_cooldown / balance().grenade_cooldown).clamp(0., 1.),
+        },
+    ]
+}
+
+/// The arc the tank's front armor covers, as `draw_arc` takes it: where it starts and how
+/// wide it is, in degrees. `None` for tanks without armor.
+pub(crate) fn armor_arc(tank: &Tank) -> Option<(f32, f32)> {
+    if tank.front_armor <= 0. {
+        return None;
+    }
+    let half = balance().front_arc.to_degrees();
+    Some((tank.rotation.to_degrees() - half, 2. * half))
+}
+
+/// How strongly the low-health vignette is drawn, from 0 (healthy) to 1 (almost dead).
+pub(crate) fn vignette_strength(health_fraction: f32) -> f32 {
+    if health_fraction >= VIGNETTE_THRESHOLD {
+        return 0.;
+    }
+    1. - health_fraction / VIGNETTE_THRESHOLD
+}
+
+/// Health bar, cooldowns, crosshair and low-health vignette of the local player.
+pub(crate) fn draw_player_hud(tank: &Tank, crosshair: CrosshairStyle) {
+    let health = health_fraction(tank);
+    draw_vignette(vignette_strength(health));
+    draw_health_bar(tank, health);
+    draw_heat_gauge(tank);
+    let slots = cooldown_slots(tank);
+    draw_cooldowns(&slots);
+    draw_armor(tank, slots.len());
+    draw_crosshair(crosshair, weapon_cooldown_fraction(tank));
+}
+
+fn draw_health_bar(tank: &Tank, health: f32) {
+    let x = HUD_MARGIN;
+    let y = CANONICAL_SCREEN_HEIGHT - HUD_MARGIN - HEALTH_BAR_H;
+
+    let (bx, by, bw, bh) = scale_dims(x, y, HEALTH_BAR_W, HEALTH_BAR_H);
+    draw_rectangle(bx, by, bw, bh, Color::new(0.1, 0.1, 0.1, 0.8));
+    draw_rectangle(bx, by, bw * health, bh, health_color(health));
+    draw_rectangle_lines(bx, by, bw, bh, 2., WHITE);
+
+    Text::new_scaled(TEXT_SMALL).draw(
+        &format!("{:.0}", tank.health.max(0.)),
+        x + HEALTH_BAR_W / 2.,
+        y + HEALTH_BAR_H / 2.,
+    );
+}
+
+/// A thin bar over the health bar, shown while the gun is warm; only games with heat
+/// firing warm it.
+fn draw_heat_gauge(tank: &Tank) {
+    if tank.heat <= 0. && !tank.overheated {
+        return;
+    }
+    let x = HUD_MARGIN;
+    let y = CANONICAL_SCREEN_HEIGHT - HUD_MARGIN - HEALTH_BAR_H - HEAT_BAR_H - 6.;
+
+    let (bx, by, bw, bh) = scale_dims(x, y, HEALTH_BAR_W, HEAT_BAR_H);
+    draw_rectangle(bx, by, bw, bh, Color::new(0.1, 0.1, 0.1, 0.8));
+    draw_rectangle(bx, by, bw * tank.heat.clamp(0., 1.), bh, heat_color(tank));
+    draw_rectangle_lines(bx, by, bw, bh, 1., WHITE);
+
+    if tank.overheated {
+        let mut text = Text::new_scaled(TEXT_SMALL / 2);
+        // Blinks, as nothing else says why the gun went quiet
+        if get_time().fract() < 0.5 {
+            text.params.color = RED;
+        }
+        text.draw(&tr!("hud.overheated"), x + HEALTH_BAR_W / 2., y - 12.);
+    }
+}
+
+fn draw_cooldowns(slots: &[CooldownSlot]) {
+    let size = HEALTH_BAR_H * 1.5;
+    let y = CANONICAL_SCREEN_HEIGHT - HUD_MARGIN - size;
+    for (i, slot) in slots.iter().enumerate() {
+        let x = HUD_MARGIN + HEALTH_BAR_W + 20. + i as f32 * (size + 10.);
+        let (sx, sy, sw, sh) = scale_dims(x, y, size, size);
+
+        draw_rectangle(sx, sy, sw, sh, Color::new(0.1, 0.1, 0.1, 0.8));
+        // The dark part shrinks from the top as the cooldown runs out
+        draw_rectangle(sx, sy, sw, sh * slot.fraction, Color::new(0., 0., 0., 0.7));
+        let outline = if slot.fraction > 0. { GRAY } else { WHITE };
+        draw_rectangle_lines(sx, sy, sw, sh, 2., outline);
+
+        Text::new_scaled(TEXT_SMALL / 2).draw(&slot.label, x + size / 2., y + size / 2.);
+    }
+}
+
+/// A dial after the cooldowns with the tank's armored front lit, turning with the tank,
+/// and how much of a hit the armor stops.
+fn draw_armor(tank: &Tank, slots: usize) {
+    let Some((start, width)) = armor_arc(tank) else {
+        return;
+    };
+    let size = HEALTH_BAR_H * 1.5;
+    let x = HUD_MARGIN + HEALTH_BAR_W + 20. + slots as f32 * (size + 10.) + size / 2.;
+    let y = CANONICAL_SCREEN_HEIGHT - HUD_MARGIN - size / 2.;
+    let (cx, cy, radius, _) = scale_dims(x, y, size / 2., size / 2.);
+
+    draw_circle(cx, cy, radius, Color::new(0.1, 0.1, 0.1, 0.8));
+    draw_circle_lines(cx, cy, radius, 1., GRAY);
+    draw_arc(cx, cy, 24, radius - 3., start, 4., width, SKYBLUE);
+
+    let percent = (tank.front_armor * 100.).round();
+    Text::new_scaled(TEXT_SMALL / 2).draw(
+        &tr!("hud.armor", value = percent),
+        x,
+        y - size / 2. - 10.,
+    );
+}
+
+fn draw_vignette(strength: f32) {
+    if strength <= 0. {
+        return;
+    }
+
+    // Pulse faster the closer the player is to dying
+    let pulse = 0.75 + 0.25 * (get_time() as f32 * (3. + 5. * strength)).sin();
+    let (w, h) = (screen_width(), screen_height());
+    let band = h * 0.04;
+    for i in 0..4 {
+        let inset = band * i as f32;
+        let alpha = strength * pulse * 0.35 * (1. - i as f32 / 4.);
+        draw_rectangle_lines(
+            inset,
+            inset,
+            w - 2. * inset,
+            h - 2. * inset,
+            band * 2.,
+            Color::new(0.8, 0., 0., alpha),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::Team;
+    use glam::Vec2;
+
+    fn tank(health: f32, weapon_cooldown: f32) -> Tank {
+        let mut tank = Tank::new(PlayerInfo::new(0, "P".into(), Team::Blue), Vec2::ZERO);
+        tank.health = health;
+        tank.weapon_cooldown = weapon_cooldown;
+        tank
+    }
+
+    #[test]
+    fn test_fractions_are_clamped() {
+        assert_eq!(health_fraction(&tank(50., 0.)), 0.5);
+        assert_eq!(health_fraction(&tank(-10., 0.)), 0.);
+        assert_eq!(
+            weapon_cooldown_fraction(&tank(100., balance().fire_interval / 2.)),
+            0.5
+        );
+        assert_eq!(weapon_cooldown_fraction(&tank(100., -0.01)), 0.);
+        assert_eq!(
+            cooldown_slots(&tank(100., balance().fire_interval))[0].fraction,
+            1.
+        );
+        assert_eq!(
+            cooldown_slots(&tank(100., balance().fire_interval))[1].fraction,
+            0.
+        );
+    }
+
+    #[test]
+    fn test_armor_arc_turns_with_the_tank() {
+        let mut tank = tank(100., 0.);
+        let half = balance().front_arc.to_degrees();
+        let (start, width) = armor_arc(&tank).unwrap();
+        assert!((start + half).abs() < 1e-3 && (width - 2. * half).abs() < 1e-3);
+
+        tank.rotation = std::f32::consts::FRAC_PI_2;
+        let (start, _) = armor_arc(&tank).unwrap();
+        assert!((start - (90. - half)).abs() < 1e-3);
+
+        tank.front_armor = 0.;
+        assert_eq!(armor_arc(&tank), None);
+    }
+
+    #[test]
+    fn test_an_overheated_gun_waits_for_its_heat() {
+        let mut hot = tank(100., 0.);
+        hot.heat = 0.6;
+        assert_eq!(weapon_cooldown_fraction(&hot), 0.);
+        assert_eq!(heat_color(&hot), ORANGE);
+
+        hot.overheated = true;
+        assert_eq!(weapon_cooldown_fraction(&hot), 0.6);
+        assert_eq!(heat_color(&hot), RED);
+        assert_eq!(heat_color(&tank(100., 0.)), SKYBLUE);
+    }
+
+    #[test]
+    fn test_vignette_only_at_low_health() {
+        assert_eq!(vignette_strength(1.), 0.);
+        assert_eq!(vignette_strength(VIGNETTE_THRESHOLD), 0.);
+        assert!(vignette_strength(0.2) > 0.);
+        assert!(vignette_strength(0.1) > vignette_strength(0.2));
+        assert_eq!(vignette_strength(0.), 1.);
+    }
+}
@@ -1,8 +1,12 @@
+use crate::app::game_view::exit_view;
+use crate::app::graphics_menu::GraphicsMenu;
 use crate::app::request_view::RequestView;
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
 use crate::server::ClientState;
 use crate::ui::{
-    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_LARGE, TEXT_MID, Text,
+    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_LARGE, TEXT_MID, TEXT_SMALL,
+    Text,
 };
 
 use common::protocol::ClientMessage;
@@ -10,18 +14,50 @@ use macroquad::prelude::*;
 
 enum MenuButton {
     Resume,
+    Options,
     Quit,
+    ConfirmQuit,
+    CancelQuit,
     StartGame,
 }
 
 pub(crate) struct InGameMenu {
     button_clicked: Option<MenuButton>,
+    /// Leaving was asked for and waits to be confirmed
+    confirming_quit: bool,
 }
 
 impl InGameMenu {
     pub fn new() -> Self {
         InGameMenu {
             button_clicked: None,
+            confirming_quit: false,
+        }
+    }
+
+    /// Asks whether to really leave a match that goes on without the player.
+    fn draw_quit_confirmation(&mut self, layout: &mut Layout, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        Text::new_scaled(TEXT_MID).draw(&tr!("game_menu.leave_confirm"), x_mid, layout.next());
+        layout.add(40.);
+        for (button, label) in [
+            (MenuButton::ConfirmQuit, tr!("game_menu.leave")),
+            (MenuButton::CancelQuit, tr!("game_menu.stay")),
+        ] {
+            if Button::default()
+                .draw_centered(
+                    x_mid,
+                    layout.next(),
+                    BUTTON_W,
+                    BUTTON_H,
+                    Some(&label),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_clicked = Some(button);
+            }
+            layout.add(BUTTON_H);
         }
     }
 }
@@ -47,11 +83,17 @@ impl View for InGameMenu {
             Color::new(0.0, 0.0, 0.0, 0.5),
         );
 
-        Text::new_scaled(TEXT_LARGE).draw("Game Menu", x_mid, layout.next());
+        Text::new_scaled(TEXT_LARGE).draw(&tr!("game_menu.title"), x_mid, layout.next());
         layout.add(50.);
 
         Text::new_scaled(TEXT_MID).draw(
-            &format!("Game code: {}", game.get_game_code()),
+            &tr!("game_menu.game_code", code = game.get_game_code()),
+            x_mid,
+            layout.next(),
+        );
+        layout.add(30.);
+        Text::new_scaled(TEXT_SMALL).draw(
+            &tr!("game_menu.seed", seed = game.seed()),
             x_mid,
             layout.next(),
         );
@@ -59,13 +101,18 @@ impl View for InGameMenu {
 
         self.button_clicked = None;
 
+        if self.confirming_quit {
+            self.draw_quit_confirmation(&mut layout, has_input);
+            return;
+        }
+
         if Button::default()
             .draw_centered(
                 x_mid,
                 layout.next(),
                 button_w,
                 button_h,
-                Some("Resume"),
+                Some(&tr!("game_menu.resume")),
                 has_input,
             )
             .poll()
@@ -74,6 +121,21 @@ impl View for InGameMenu {
         }
         layout.add(button_h);
 
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                button_w,
+                button_h,
+                Some(&tr!("game_menu.options")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_clicked = Some(MenuButton::Options);
+        }
+        layout.add(button_h);
+
         if game.can_user_start_game() {
             if Button::default()
                 .draw_centered(
@@ -81,7 +143,7 @@ impl View for InGameMenu {
                     layout.next(),
                     button_w,
                     button_h,
-                    Some("Start Game"),
+                    Some(&tr!("game_menu.start")),
                     has_input,
                 )
                 .poll()
@@ -97,7 +159,7 @@ impl View for InGameMenu {
                 layout.next(),
                 button_w,
                 button_h,
-                Some("Exit to Main Menu"),
+                Some(&tr!("game_menu.exit")),
                 has_input,
             )
             .poll()
@@ -107,31 +169,49 @@ impl View for InGameMenu {
     }
 
     fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        if ctx.game.as_ref().is_some_and(|game| game.closed()) {
+            return Transition::PopUntil(exit_view(ctx));
+        }
         ctx.server.assert_state(ClientState::Playing);
 
         if is_key_pressed(KeyCode::Escape) {
+            if self.confirming_quit {
+                self.confirming_quit = false;
+                return Transition::None;
+            }
             return Transition::Pop;
         }
 
-        if let Some(button) = &self.button_clicked {
+        if let Some(button) = self.button_clicked.take() {
             match button {
                 MenuButton::Resume => return Transition::Pop,
-                MenuButton::Quit => {
-                    ctx.server.send_client_message(ClientMessage::LeaveGame);
-                    let success_transition = Transition::PopUntil(ViewId::ServerLobby);
+                MenuButton::Options => {
+                    return Transition::Push(Box::new(GraphicsMenu::new_overlay()));
+                }
+                // Leaving mid-round hands the match to the others, so it's asked twice
+                MenuButton::Quit if ctx.game.as_ref().is_some_and(|g| g.is_underway()) => {
+                    self.confirming_quit = true;
+                }
+                MenuButton::CancelQuit => self.confirming_quit = false,
+                MenuButton::Quit | MenuButton::ConfirmQuit => {
+                    if let Some(game) = &ctx.game {
+                        game.record_forfeit(&mut ctx.settings);
+                    }
+                    let request = ctx.server.send_request(ClientMessage::LeaveGame);
+                    let success_transition = Transition::PopUntil(exit_view(ctx));
                     return Transition::Push(Box::new(RequestView::new_transition(
-                        "Exiting game...".into(),
+                        tr!("game_menu.exiting"),
+                        request,
                         success_transition,
                     )));
                 }
+                // Back to the lobby, which shows the countdown or why it didn't start
                 MenuButton::StartGame => {
-                    ctx.server
-                        .send_client_message(ClientMessage::StartCountdown);
-                    let success_transition = Transition::PopUntil(ViewId::GameView);
-                    return Transition::Push(Box::new(RequestView::new_transition(
-                        "Starting game...".into(),
-                        success_transition,
-                    )));
+                    let request = ctx.server.send_request(ClientMessage::StartCountdown);
+                    if let Some(game) = &mut ctx.game {
+                        game.await_start(request);
+                    }
+                    return Transition::Pop;
                 }
             }
         };
@@ -0,0 +1,262 @@
+use common::game::{Projectile, Tank, Team};
+use common::protocol::PlayerId;
+use macroquad::prelude::*;
+
+/// How long the compass keeps pointing at whoever hit us.
+const DAMAGE_MARKER_DURATION: f64 = 1.5;
+/// Distance of the damage compass arrows from the tank center, in pixels.
+const COMPASS_RADIUS: f32 = 70.;
+/// Distance of the off-screen arrows from the screen edge, in pixels.
+const EDGE_MARGIN: f32 = 30.;
+const ARROW_SIZE: f32 = 14.;
+
+struct DamageMarker {
+    /// World position the damage came from
+    source: Vec2,
+    time: f64,
+}
+
+/// Remembers where recent hits on the local tank came from.
+pub(crate) struct DamageIndicators {
+    markers: Vec<DamageMarker>,
+}
+
+impl DamageIndicators {
+    pub fn new() -> Self {
+        Self {
+            markers: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.markers.clear();
+    }
+
+    /// Compares the local tank between two snapshots and records a marker when it lost
+    /// health, pointing at the shooter of the projectile that hit it.
+    pub fn observe(
+        &mut self,
+        time: f64,
+        player_id: PlayerId,
+        previous_tanks: &[Tank],
+        previous_projectiles: &[Projectile],
+        tanks: &[Tank],
+    ) {
+        self.markers
+            .retain(|m| time - m.time < DAMAGE_MARKER_DURATION);
+
+        let find = |tanks: &[Tank]| {
+            tanks
+                .iter()
+                .find(|t| t.player_info.id == player_id)
+                .cloned()
+        };
+        let (Some(before), Some(after)) = (find(previous_tanks), find(tanks)) else {
+            return;
+        };
+        if after.health >= before.health {
+            return;
+        }
+        if let Some(source) = damage_source(&before, previous_projectiles, previous_tanks) {
+            self.markers.push(DamageMarker { source, time });
+        }
+    }
+
+    /// Directions of recent hits with how visible they still are (1 when fresh, fading to 0).
+    fn active(&self, time: f64) -> impl Iterator<Item = (Vec2, f32)> + '_ {
+        self.markers.iter().filter_map(move |m| {
+            let age = time - m.time;
+            (age < DAMAGE_MARKER_DURATION)
+                .then(|| (m.source, 1. - (age / DAMAGE_MARKER_DURATION) as f32))
+        })
+    }
+}
+
+/// The enemy projectile closest to `me`, the likeliest to have hit it.
+pub(crate) fn hit_by<'a>(me: &Tank, projectiles: &'a [Projectile]) -> Option<&'a Projectile> {
+    projectiles
+        .iter()
+        .filter(|p| p.owner_info.team != me.player_info.team)
+        .min_by(|a, b| {
+            a.position
+                .distance_squared(me.position)
+                .total_cmp(&b.position.distance_squared(me.position))
+        })
+}
+
+/// Where the hit on `me` came from: the shooter's position if they are still around,
+/// otherwise back along the path of the closest enemy projectile.
+pub(crate) fn damage_source(me: &Tank, projectiles: &[Projectile], tanks: &[Tank]) -> Option<Vec2> {
+    let projectile = hit_by(me, projectiles)?;
+
+    let shooter = tanks
+        .iter()
+        .find(|t| t.player_info.id == projectile.owner_info.id);
+    Some(match shooter {
+        Some(shooter) => shooter.position,
+        None => projectile.position - projectile.velocity,
+    })
+}
+
+/// Point on the border of `screen` (shrunk by `margin`) on the way from its center to
+/// `target`, or `None` when `target` is already inside.
+pub(crate) fn edge_position(screen: Rect, target: Vec2, margin: f32) -> Option<Vec2> {
+    if screen.contains(target) {
+        return None;
+    }
+    let center = screen.center();
+    let half = vec2(
+        (screen.w / 2. - margin).max(0.),
+        (screen.h / 2. - margin).max(0.),
+    );
+    let dir = target - center;
+    let scale_x = if dir.x != 0. {
+        half.x / dir.x.abs()
+    } else {
+        f32::INFINITY
+    };
+    let scale_y = if dir.y != 0. {
+        half.y / dir.y.abs()
+    } else {
+        f32::INFINITY
+    };
+    Some(center + dir * scale_x.min(scale_y))
+}
+
+fn team_color(team: Team) -> Color {
+    match team {
+        Team::Blue => SKYBLUE,
+        Team::Red | Team::Ffa(_) => PINK,
+    }
+}
+
+fn draw_arrow(tip: Vec2, dir: Vec2, size: f32, color: Color) {
+    let dir = dir.normalize_or_zero();
+    let side = dir.perp() * size * 0.6;
+    let base = tip - dir * size;
+    draw_triangle(tip, base + side, base - side, color);
+}
+
+/// Arrows at the screen edge toward teammates of `viewer` outside the view, and a
+/// compass around `viewer` toward recent damage sources.
+///
+/// `transform` is the `(scaling, x_offset, y_offset)` the board was drawn with.
+pub(crate) fn draw_indicators(
+    viewer: &Tank,
+    tanks: &[Tank],
+    damage: Option<&DamageIndicators>,
+    (scaling, x_offset, y_offset): (f32, f32, f32),
+    time: f64,
+) {
+    let to_screen = |p: Vec2| vec2(p.x * scaling + x_offset, p.y * scaling + y_offset);
+    let screen = Rect::new(0., 0., screen_width(), screen_height());
+    let ui_scale = screen_height() / crate::ui::CANONICAL_SCREEN_HEIGHT;
+
+    for mate in tanks.iter().filter(|t| {
+        t.player_info.team == viewer.player_info.team && t.player_info.id != viewer.player_info.id
+    }) {
+        let target = to_screen(mate.position);
+        if let Some(pos) = edge_position(screen, target, EDGE_MARGIN * ui_scale) {
+            draw_arrow(
+                pos,
+                target - screen.center(),
+                ARROW_SIZE * ui_scale,
+                team_color(mate.player_info.team),
+            );
+        }
+    }
+
+    let Some(damage) = damage else {
+        return;
+    };
+    let center = to_screen(viewer.position);
+    for (source, strength) in damage.active(time) {
+        let dir = (to_screen(source) - center).normalize_or_zero();
+        if dir == Vec2::ZERO {
+            continue;
+        }
+        draw_arrow(
+            center + dir * COMPASS_RADIUS * ui_scale,
+            dir,
+            ARROW_SIZE * 1.4 * ui_scale,
+            Color::new(1., 0.1, 0.1, 0.9 * strength),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::ProjectileKind;
+    use common::game::player::PlayerInfo;
+
+    fn tank(id: PlayerId, team: Team, position: Vec2) -> Tank {
+        Tank::new(PlayerInfo::new(id, format!("P{}", id), team), position)
+    }
+
+    #[test]
+    fn test_edge_position() {
+        let screen = Rect::new(0., 0., 800., 600.);
+        assert_eq!(edge_position(screen, vec2(100., 100.), 10.), None);
+
+        // Straight to the right
+        let pos = edge_position(screen, vec2(2000., 300.), 10.).unwrap();
+        assert_eq!(pos, vec2(790., 300.));
+
+        // Up and to the left, clamped by the top edge first
+        let pos = edge_position(screen, vec2(300., -700.), 10.).unwrap();
+        assert!((pos.y - 10.).abs() < 1e-3);
+        assert!(pos.x > 300. && pos.x < 400.);
+    }
+
+    #[test]
+    fn test_damage_source_prefers_shooter() {
+        let me = tank(1, Team::Blue, vec2(100., 100.));
+        let shooter = tank(2, Team::Red, vec2(400., 100.));
+        let projectile = Projectile {
+            id: 0,
+            kind: ProjectileKind::Bullet,
+            owner_info: shooter.player_info.clone(),
+            position: vec2(120., 100.),
+            velocity: vec2(-500., 0.),
+            radius: 5.,
+            age: 0.,
+        };
+
+        let tanks = vec![me.clone(), shooter];
+        assert_eq!(
+            damage_source(&me, std::slice::from_ref(&projectile), &tanks),
+            Some(vec2(400., 100.))
+        );
+        // Shooter gone: trace back along the projectile's path
+        assert_eq!(
+            damage_source(&me, &[projectile], &tanks[..1]),
+            Some(vec2(620., 100.))
+        );
+    }
+
+    #[test]
+    fn test_damage_marker_fades() {
+        let before = vec![tank(1, Team::Blue, Vec2::ZERO)];
+        let mut after = before.clone();
+        after[0].health -= 10.;
+        let projectile = Projectile {
+            id: 0,
+            kind: ProjectileKind::Bullet,
+            owner_info: PlayerInfo::new(2, "P2".into(), Team::Red),
+            position: vec2(10., 0.),
+            velocity: vec2(-1., 0.),
+            radius: 5.,
+            age: 0.,
+        };
+
+        let mut indicators = DamageIndicators::new();
+        indicators.observe(1., 1, &before, &[projectile], &after);
+        assert_eq!(indicators.active(1.5).count(), 1);
+        assert_eq!(indicators.active(1. + DAMAGE_MARKER_DURATION).count(), 0);
+
+        // No health lost, nothing recorded
+        indicators.observe(10., 1, &after, &[], &after);
+        assert_eq!(indicators.markers.len(), 0);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::i18n::tr;
+use crate::ui::{
+    CANONICAL_SCREEN_WIDTH, Layout, TEXT_SMALL, Text, TextHorizontalPositioning,
+    TextVerticalPositioning, default_text_params,
+};
+use common::protocol::{KillEvent, PlayerId, Tank};
+use macroquad::prelude::*;
+use std::collections::BTreeMap;
+
+/// Places shown in the corner.
+const SHOWN_PLACES: usize = 5;
+
+/// Kills of every player of a free-for-all, as seen in the game's kill events.
+pub(crate) struct KillRanking {
+    /// Nickname and kills by player
+    players: BTreeMap<PlayerId, (String, u8)>,
+}
+
+impl KillRanking {
+    pub fn new() -> Self {
+        Self {
+            players: BTreeMap::new(),
+        }
+    }
+
+    /// Puts everyone with a tank on the ranking, also before their first kill.
+    pub fn observe(&mut self, tanks: &[Tank]) {
+        for tank in tanks {
+            let info = &tank.player_info;
+            self.players
+                .entry(info.id)
+                .or_insert_with(|| (info.nickname.clone(), 0));
+        }
+    }
+
+    pub fn record(&mut self, kill: &KillEvent) {
+        let killer = &kill.killer_info;
+        if killer.id != kill.victim_info.id {
+            let (_, kills) = self
+                .players
+                .entry(killer.id)
+                .or_insert_with(|| (killer.nickname.clone(), 0));
+            *kills = kills.saturating_add(1);
+        }
+    }
+
+    pub fn nickname(&self, id: PlayerId) -> Option<&str> {
+        self.players.get(&id).map(|(name, _)| name.as_str())
+    }
+
+    /// Nicknames and kills, most kills first.
+    pub fn ranking(&self) -> Vec<(&str, u8)> {
+        let mut ranking: Vec<(&str, u8)> = self
+            .players
+            .values()
+            .map(|(name, kills)| (name.as_str(), *kills))
+            .collect();
+        ranking.sort_by_key(|&(_, kills)| std::cmp::Reverse(kills));
+        ranking
+    }
+
+    /// The top places, in the top-right corner below the FPS counter.
+    pub fn draw(&self) {
+        let text = Text::new(
+            TextParams {
+                font_size: TEXT_SMALL,
+                ..default_text_params()
+            },
+            TextVerticalPositioning::CenterConsistent,
+            TextHorizontalPositioning::Right,
+        );
+        let x = CANONICAL_SCREEN_WIDTH - 30.;
+        let mut layout = Layout::new(70., 6.);
+
+        text.draw(&tr!("game.kill_ranking"), x, layout.next());
+        layout.add(10.);
+        for (place, (name, kills)) in self.ranking().into_iter().take(SHOWN_PLACES).enumerate() {
+            text.draw(
+                &format!("{}. {}  {}", place + 1, name, kills),
+                x,
+                layout.next(),
+            );
+            layout.add(10.);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::Team;
+    use glam::Vec2;
+
+    fn info(id: PlayerId) -> PlayerInfo {
+        PlayerInfo::new(id, format!("P{}", id), Team::Ffa(id))
+    }
+
+    #[test]
+    fn test_ranking_follows_kills() {
+        let mut ranking = KillRanking::new();
+        ranking.observe(&[
+            Tank::new(info(1), Vec2::ZERO),
+            Tank::new(info(2), Vec2::ZERO),
+        ]);
+        assert_eq!(ranking.ranking(), vec![("P1", 0), ("P2", 0)]);
+
+        let kill = |killer, victim| KillEvent {
+            killer_info: info(killer),
+            victim_info: info(victim),
+        };
+        ranking.record(&kill(2, 1));
+        ranking.record(&kill(3, 2));
+        ranking.record(&kill(2, 3));
+        assert_eq!(ranking.ranking(), vec![("P2", 2), ("P3", 1), ("P1", 0)]);
+        assert_eq!(ranking.nickname(3), Some("P3"));
+    }
+}
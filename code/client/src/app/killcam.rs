@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use common::protocol::{EngineSnapshot, PlayerId};
+
+/// How much history the killcam shows (in seconds).
+pub(crate) const KILLCAM_DURATION: f64 = 3.0;
+/// How much history is replayed in slow motion when a round ends (in seconds).
+const SLOW_MOTION_REPLAY: f64 = 1.0;
+/// Playback speed of the end-of-round replay.
+const SLOW_MOTION_SPEED: f64 = 0.4;
+
+/// Ring buffer of the most recent engine snapshots received from the server.
+pub(crate) struct SnapshotHistory {
+    frames: VecDeque<(f64, EngineSnapshot)>,
+    max_age: f64,
+}
+
+impl SnapshotHistory {
+    pub fn new(max_age: f64) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            max_age,
+        }
+    }
+
+    pub fn push(&mut self, time: f64, snapshot: EngineSnapshot) {
+        self.frames.push_back((time, snapshot));
+        while let Some((oldest, _)) = self.frames.front() {
+            if time - oldest <= self.max_age {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Copies the buffered snapshots, with times relative to the oldest one.
+    fn to_frames(&self) -> Vec<(f64, EngineSnapshot)> {
+        self.last_frames(self.max_age)
+    }
+
+    /// Copies the snapshots of the last `duration` seconds, with times relative to the
+    /// oldest one copied.
+    fn last_frames(&self, duration: f64) -> Vec<(f64, EngineSnapshot)> {
+        let newest = self.frames.back().map_or(0.0, |(t, _)| *t);
+        let recent = self
+            .frames
+            .iter()
+            .skip_while(|(t, _)| newest - t > duration);
+        let start = recent.clone().next().map_or(0.0, |(t, _)| *t);
+        recent
+            .map(|(t, snapshot)| (t - start, snapshot.clone()))
+            .collect()
+    }
+}
+
+/// The frame shown `elapsed` seconds into a playback of `frames`.
+fn frame_at(frames: &[(f64, EngineSnapshot)], elapsed: f64) -> Option<&EngineSnapshot> {
+    let idx = frames.partition_point(|(t, _)| *t <= elapsed);
+    frames
+        .get(idx.saturating_sub(1))
+        .map(|(_, snapshot)| snapshot)
+}
+
+/// Plays back the snapshots leading up to the local player's death.
+pub(crate) struct Killcam {
+    frames: Vec<(f64, EngineSnapshot)>,
+    killer_id: PlayerId,
+    killer_name: String,
+    start_time: f64,
+}
+
+impl Killcam {
+    pub fn new(
+        history: &SnapshotHistory,
+        killer_id: PlayerId,
+        killer_name: String,
+        start_time: f64,
+    ) -> Self {
+        Self {
+            frames: history.to_frames(),
+            killer_id,
+            killer_name,
+            start_time,
+        }
+    }
+
+    pub fn killer_id(&self) -> PlayerId {
+        self.killer_id
+    }
+
+    pub fn killer_name(&self) -> &str {
+        &self.killer_name
+    }
+
+    fn duration(&self) -> f64 {
+        self.frames.last().map_or(0.0, |(t, _)| *t)
+    }
+
+    pub fn is_over(&self, time: f64) -> bool {
+        time - self.start_time >= self.duration()
+    }
+
+    /// The snapshot to display at the given time, or `None` if there is nothing to show.
+    pub fn frame_at(&self, time: f64) -> Option<&EngineSnapshot> {
+        frame_at(&self.frames, time - self.start_time)
+    }
+}
+
+/// Replays the last moment of a round slowed down, while the round-end banner is up.
+pub(crate) struct SlowMotion {
+    frames: Vec<(f64, EngineSnapshot)>,
+    start_time: f64,
+}
+
+impl SlowMotion {
+    pub fn new(history: &SnapshotHistory, start_time: f64) -> Self {
+        Self {
+            frames: history.last_frames(SLOW_MOTION_REPLAY),
+            start_time,
+        }
+    }
+
+    pub fn is_over(&self, time: f64) -> bool {
+        let duration = self.frames.last().map_or(0.0, |(t, _)| *t);
+        (time - self.start_time) * SLOW_MOTION_SPEED >= duration
+    }
+
+    pub fn frame_at(&self, time: f64) -> Option<&EngineSnapshot> {
+        frame_at(&self.frames, (time - self.start_time) * SLOW_MOTION_SPEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(projectiles: usize) -> EngineSnapshot {
+        use common::game::player::PlayerInfo;
+        use common::protocol::{Projectile, ProjectileKind, Team};
+
+        EngineSnapshot {
+            tanks: Vec::new(),
+            projectiles: (0..projectiles)
+                .map(|i| Projectile {
+                    id: i as u64,
+                    kind: ProjectileKind::Bullet,
+                    owner_info: PlayerInfo::new(0, "owner".into(), Team::Blue),
+                    position: Default::default(),
+                    velocity: Default::default(),
+                    radius: 1.0,
+                    age: 0.0,
+                })
+                .collect(),
+            moving_walls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_history_drops_old_frames() {
+        let mut history = SnapshotHistory::new(1.0);
+        history.push(0.0, snapshot(0));
+        history.push(0.5, snapshot(1));
+        history.push(1.5, snapshot(2));
+
+        let frames = history.to_frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].0, 0.0);
+        assert_eq!(frames[1].0, 1.0);
+    }
+
+    #[test]
+    fn test_killcam_plays_frames_in_order() {
+        let mut history = SnapshotHistory::new(KILLCAM_DURATION);
+        history.push(10.0, snapshot(0));
+        history.push(11.0, snapshot(1));
+        history.push(12.0, snapshot(2));
+
+        let killcam = Killcam::new(&history, 3, "killer".into(), 100.0);
+
+        assert_eq!(killcam.frame_at(100.0).unwrap().projectiles.len(), 0);
+        assert_eq!(killcam.frame_at(101.5).unwrap().projectiles.len(), 1);
+        assert_eq!(killcam.frame_at(102.0).unwrap().projectiles.len(), 2);
+        assert!(!killcam.is_over(101.9));
+        assert!(killcam.is_over(102.0));
+    }
+
+    #[test]
+    fn test_slow_motion_replays_last_second_slowly() {
+        let mut history = SnapshotHistory::new(KILLCAM_DURATION);
+        history.push(10.0, snapshot(0));
+        history.push(11.5, snapshot(1));
+        history.push(12.0, snapshot(2));
+
+        let slow = SlowMotion::new(&history, 100.0);
+        assert_eq!(slow.frames.len(), 2);
+        assert_eq!(slow.frame_at(100.0).unwrap().projectiles.len(), 1);
+        // Half a second of history takes longer than half a second to play
+        assert_eq!(slow.frame_at(100.5).unwrap().projectiles.len(), 1);
+        assert!(!slow.is_over(100.5));
+        assert_eq!(slow.frame_at(101.25).unwrap().projectiles.len(), 2);
+        assert!(slow.is_over(101.25));
+    }
+
+    #[test]
+    fn test_killcam_with_empty_history() {
+        let history = SnapshotHistory::new(KILLCAM_DURATION);
+        let killcam = Killcam::new(&history, 0, "killer".into(), 0.0);
+
+        assert!(killcam.frame_at(0.0).is_none());
+        assert!(killcam.is_over(0.0));
+    }
+}
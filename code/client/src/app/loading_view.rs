@@ -0,0 +1,161 @@
+use crate::app::event_log::LogCategory;
+use crate::app::popup::Popup;
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::ui::{
+    self, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_MID_X,
+    CANONICAL_SCREEN_MID_Y, CANONICAL_SCREEN_WIDTH, Layout, TEXT_LARGE, Text,
+};
+use macroquad::prelude::*;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Produces what is being loaded, like the next view or a model, on the loading thread.
+pub(crate) type LoadFn<T> = Box<dyn FnOnce() -> Result<T, String> + Send>;
+/// Decides what happens with the loaded value, once it has arrived.
+pub(crate) type LoadedAction<T> = Box<dyn FnOnce(T) -> Transition>;
+
+const SPINNER_DOTS: usize = 8;
+const SPINNER_RADIUS: f32 = 24.;
+/// Spinner revolutions per second
+const SPINNER_SPEED: f64 = 1.;
+
+/// Overlay that runs slow work, like decoding a model file, on a background thread so the
+/// window keeps rendering. Cancelling stops waiting for it; whatever it produces is dropped.
+pub(crate) struct LoadingView<T> {
+    text: String,
+    result: Receiver<Result<T, String>>,
+    on_loaded: Option<LoadedAction<T>>,
+    cancel_clicked: bool,
+}
+
+impl<T: Send + 'static> LoadingView<T> {
+    pub fn start(text: String, load: LoadFn<T>, on_loaded: LoadedAction<T>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            // Fails if the view was cancelled, which is fine
+            let _ = tx.send(load());
+        });
+
+        Self {
+            text,
+            result: rx,
+            on_loaded: Some(on_loaded),
+            cancel_clicked: false,
+        }
+    }
+
+    /// The result, once the loading thread has finished.
+    fn poll(&mut self) -> Option<Result<T, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(tr!("loading.crashed"))),
+        }
+    }
+}
+
+impl<T: Send + 'static> View for LoadingView<T> {
+    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        let mut layout = Layout::new(CANONICAL_SCREEN_MID_Y - 80., 30.);
+
+        // overlay the previous view
+        draw_rectangle(
+            0.,
+            0.,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+
+        Text::new_scaled(TEXT_LARGE).draw(&self.text, x_mid, layout.next());
+        layout.add(40.);
+
+        draw_spinner(x_mid, layout.next() + SPINNER_RADIUS);
+        layout.add(2. * SPINNER_RADIUS);
+
+        self.cancel_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("common.cancel")),
+                has_input,
+            )
+            .poll();
+    }
+
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        if self.cancel_clicked {
+            return Transition::Pop;
+        }
+
+        match self.poll() {
+            None => Transition::None,
+            Some(Ok(loaded)) => self.on_loaded.take().unwrap()(loaded),
+            Some(Err(reason)) => {
+                ctx.log.push(LogCategory::Error, &reason);
+                Transition::PopAnd(Box::new(Popup::new(reason)))
+            }
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::Loading
+    }
+}
+
+/// A ring of dots with a bright one going around, centered at canonical `(x, y)`.
+fn draw_spinner(x: f32, y: f32) {
+    let (scale, x_off, y_off) = ui::calc_transform(CANONICAL_SCREEN_WIDTH, CANONICAL_SCREEN_HEIGHT);
+    let lead = (get_time() * SPINNER_SPEED * SPINNER_DOTS as f64) as usize % SPINNER_DOTS;
+
+    for i in 0..SPINNER_DOTS {
+        let angle = i as f32 / SPINNER_DOTS as f32 * std::f32::consts::TAU;
+        let behind = (lead + SPINNER_DOTS - i) % SPINNER_DOTS;
+        let alpha = 1. - behind as f32 / SPINNER_DOTS as f32;
+        draw_circle(
+            (x + angle.cos() * SPINNER_RADIUS) * scale + x_off,
+            (y + angle.sin() * SPINNER_RADIUS) * scale + y_off,
+            4. * scale,
+            Color::new(0.9, 0.9, 0.9, alpha),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait_for<T: Send + 'static>(view: &mut LoadingView<T>) -> Result<T, String> {
+        loop {
+            if let Some(result) = view.poll() {
+                return result;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_loading_view_receives_the_result_of_the_thread() {
+        let mut view = LoadingView::start(
+            "Loading".into(),
+            Box::new(|| Ok(Popup::new("Loaded".into()))),
+            Box::new(|popup| Transition::PopAnd(Box::new(popup))),
+        );
+        assert_eq!(wait_for(&mut view).unwrap().get_id(), ViewId::Popup);
+
+        let mut view = LoadingView::<Popup>::start(
+            "Loading".into(),
+            Box::new(|| Err("broken file".into())),
+            Box::new(|popup| Transition::PopAnd(Box::new(popup))),
+        );
+        assert_eq!(wait_for(&mut view).err().unwrap(), "broken file");
+    }
+}
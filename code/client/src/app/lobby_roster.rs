@@ -0,0 +1,214 @@
+//! The players in a lobby, sliding in as they join and fading out as they leave, with the
+//! countdown over them once the host started the match and a note when starting failed.
+
+use crate::app::name_tags;
+use crate::i18n::tr;
+use crate::ui::{
+    CANONICAL_SCREEN_WIDTH, TEXT_HUGE, TEXT_MID, TEXT_SMALL, Text, TextHorizontalPositioning,
+    TextVerticalPositioning, default_text_params, scale_dims,
+    theme::{NEON_CYAN, NEON_PINK},
+};
+use common::game::Tank;
+use common::protocol::{PlayerId, Team};
+use macroquad::prelude::*;
+
+/// Seconds a player takes to slide in or fade out
+const FADE: f64 = 0.35;
+/// How far a joining player slides in from, in canonical pixels
+const SLIDE: f32 = 40.;
+/// Seconds a failed start stays up
+const NOTE_DURATION: f64 = 4.;
+const WIDTH: f32 = 280.;
+const TOP: f32 = 130.;
+const ROW_H: f32 = 28.;
+
+struct Member {
+    id: PlayerId,
+    nickname: String,
+    team: Team,
+    is_ai: bool,
+    is_host: bool,
+    joined_at: f64,
+    left_at: Option<f64>,
+}
+
+impl Member {
+    /// How far in the player is, from 0 when just joined or gone to 1.
+    fn presence(&self, time: f64) -> f32 {
+        let presence = match self.left_at {
+            Some(left_at) => 1. - (time - left_at) / FADE,
+            None => (time - self.joined_at) / FADE,
+        };
+        presence.clamp(0., 1.) as f32
+    }
+}
+
+/// Everyone in the lobby, in the order they came in.
+pub(crate) struct LobbyRoster {
+    members: Vec<Member>,
+    /// Why the last start failed, and when
+    note: Option<(String, f64)>,
+}
+
+impl LobbyRoster {
+    pub fn new() -> Self {
+        Self {
+            members: Vec::new(),
+            note: None,
+        }
+    }
+
+    /// Takes the tanks of the latest snapshot, seen at `time`; `host` is the game master's.
+    pub fn observe(&mut self, tanks: &[Tank], host: Option<PlayerId>, time: f64) {
+        for tank in tanks {
+            let info = &tank.player_info;
+            let member = match self.members.iter_mut().find(|m| m.id == info.id) {
+                Some(member) => member,
+                None => {
+                    self.members.push(Member {
+                        id: info.id,
+                        nickname: String::new(),
+                        team: info.team,
+                        is_ai: info.is_ai,
+                        is_host: false,
+                        joined_at: time,
+                        left_at: None,
+                    });
+                    self.members.last_mut().unwrap()
+                }
+            };
+            // Back before they were gone
+            if member.left_at.take().is_some() {
+                member.joined_at = time;
+            }
+            member.nickname.clone_from(&info.nickname);
+            member.team = info.team;
+            member.is_ai = info.is_ai;
+            member.is_host = host == Some(info.id);
+        }
+        for member in &mut self.members {
+            if member.left_at.is_none() && !tanks.iter().any(|t| t.player_info.id == member.id) {
+                member.left_at = Some(time);
+            }
+        }
+        self.members
+            .retain(|m| m.left_at.is_none_or(|left_at| time - left_at < FADE));
+    }
+
+    /// Shows why the host's start didn't go through.
+    pub fn start_failed(&mut self, reason: &str, time: f64) {
+        self.note = Some((tr!("lobby_roster.start_failed", reason = reason), time));
+    }
+
+    /// Draws the list on the right, with `countdown` seconds above it if counting down.
+    pub fn draw(&self, countdown: Option<u64>, time: f64) {
+        let left = CANONICAL_SCREEN_WIDTH - WIDTH - 40.;
+        let mut y = TOP;
+        if let Some(count) = countdown {
+            let mut text = Text::new_scaled(TEXT_MID);
+            text.draw(&tr!("lobby_roster.starting"), left + WIDTH / 2., y);
+            y += ROW_H * 1.6;
+            text = Text::new_scaled(TEXT_HUGE);
+            text.params.color = YELLOW;
+            text.draw(&count.to_string(), left + WIDTH / 2., y);
+            y += ROW_H * 1.6;
+        }
+
+        let height = ROW_H * (self.members.len() as f32 + 1.5);
+        let (x, panel_y, w, h) = scale_dims(left - 20., y - 20., WIDTH + 40., height + 20.);
+        draw_rectangle(x, panel_y, w, h, Color::new(0., 0., 0., 0.6));
+        Text::new_scaled(TEXT_MID).draw(
+            &tr!("lobby_roster.title", count = self.present()),
+            left + WIDTH / 2.,
+            y,
+        );
+
+        let text = |horizontal, color| {
+            Text::new(
+                TextParams {
+                    font_size: TEXT_SMALL,
+                    color,
+                    ..default_text_params()
+                },
+                TextVerticalPositioning::CenterConsistent,
+                horizontal,
+            )
+        };
+        for (row, member) in self.members.iter().enumerate() {
+            let presence = member.presence(time);
+            let x = left + SLIDE * (1. - presence);
+            let y = y + ROW_H * (row as f32 + 1.2);
+            let color = match member.team {
+                Team::Blue => NEON_CYAN,
+                Team::Red => NEON_PINK,
+                Team::Ffa(_) => LIGHTGRAY,
+            }
+            .with_alpha(presence);
+            text(TextHorizontalPositioning::Left, color).draw(&member.nickname, x, y);
+            if member.is_ai {
+                let (icon_x, icon_y, size, _) = scale_dims(x - 10., y, 12., 0.);
+                name_tags::draw_bot_icon(icon_x, icon_y, size, color);
+            }
+            if member.is_host {
+                text(
+                    TextHorizontalPositioning::Right,
+                    YELLOW.with_alpha(presence),
+                )
+                .draw(&tr!("lobby_roster.host"), x + WIDTH, y);
+            }
+        }
+
+        if let Some((note, at)) = &self.note
+            && time - at < NOTE_DURATION
+        {
+            let fade = ((NOTE_DURATION - (time - at)) / FADE).min(1.) as f32;
+            let y = y + height + ROW_H * 0.5;
+            text(TextHorizontalPositioning::Left, NEON_PINK.with_alpha(fade)).draw(note, left, y);
+        }
+    }
+
+    /// Players in the lobby, not counting those fading out.
+    fn present(&self) -> usize {
+        self.members.iter().filter(|m| m.left_at.is_none()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use glam::Vec2;
+
+    fn tank(id: PlayerId, name: &str) -> Tank {
+        Tank::new(PlayerInfo::new(id, name.into(), Team::Blue), Vec2::ZERO)
+    }
+
+    #[test]
+    fn test_players_slide_in_and_fade_out() {
+        let mut roster = LobbyRoster::new();
+        roster.observe(&[tank(0, "host"), tank(1, "guest")], Some(0), 0.);
+        assert_eq!(roster.present(), 2);
+        assert_eq!(roster.members[0].presence(0.), 0.);
+        assert_eq!(roster.members[0].presence(1.), 1.);
+        assert!(roster.members[0].is_host && !roster.members[1].is_host);
+
+        // The guest left; they fade out before they're gone from the list
+        roster.observe(&[tank(0, "host")], Some(0), 1.);
+        assert_eq!(roster.present(), 1);
+        assert_eq!(roster.members.len(), 2);
+        assert!(roster.members[1].presence(1. + FADE / 2.) < 1.);
+        roster.observe(&[tank(0, "host")], Some(0), 1. + FADE);
+        assert_eq!(roster.members.len(), 1);
+    }
+
+    #[test]
+    fn test_players_coming_back_slide_in_again() {
+        let mut roster = LobbyRoster::new();
+        roster.observe(&[tank(0, "host"), tank(1, "guest")], None, 0.);
+        roster.observe(&[tank(0, "host")], None, 1.);
+        roster.observe(&[tank(0, "host"), tank(1, "guest")], None, 1.1);
+        assert_eq!(roster.present(), 2);
+        assert_eq!(roster.members[1].presence(1.1), 0.);
+        assert_eq!(roster.members[1].presence(2.), 1.);
+    }
+}
@@ -1,49 +1,87 @@
+use crate::app::challenge_select::ChallengeSelect;
+use crate::app::menu_background::MenuBackground;
 use crate::app::model_select::ModelSelect;
 use crate::app::options_menu::OptionsMenu;
+use crate::app::replay_select::ReplaySelect;
+use crate::app::request_view::RequestView;
 use crate::app::server_connect_menu::ServerConnectMenu;
+use crate::app::server_lobby::ServerLobby;
+use crate::app::stats_view::StatsView;
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::presence::Presence;
+use crate::settings::SETTINGS_PATH;
 use crate::ui::{
-    BANNER_TEXUTRE, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout,
-    draw_texture_centered,
+    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_MID_X, Layout,
+    TEXT_SMALL, Text, banner_texture, draw_texture_centered,
 };
+use common::protocol::SessionToken;
+use macroquad::prelude::get_frame_time;
+use std::path::Path;
 
 #[derive(Clone, Copy)]
 enum MainMenuButtons {
+    Rejoin,
     Training,
+    Challenges,
     Multiplayer,
+    Replays,
+    Stats,
     Options,
     Quit,
 }
 
 pub(crate) struct MainMenu {
     button_pressed: Option<MainMenuButtons>,
+    background: MenuBackground,
 }
 
 impl MainMenu {
     pub fn new() -> Self {
         Self {
             button_pressed: None,
+            background: MenuBackground::new(),
         }
     }
 }
 
 impl View for MainMenu {
-    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
-        let mut layout = Layout::new(100., 30.);
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        self.background.draw();
+
+        let mut layout = Layout::new(90., 20.);
         let x_mid = CANONICAL_SCREEN_MID_X;
 
-        draw_texture_centered(&BANNER_TEXUTRE, x_mid, layout.next(), 1.5);
-        layout.add(100.);
+        draw_texture_centered(banner_texture().get(), x_mid, layout.next(), 1.5);
+        layout.add(90.);
 
         self.button_pressed = None;
 
+        // Above the rest, as a match left behind mid-round is the likeliest reason to be here
+        if let Some(last_game) = &ctx.settings.last_game {
+            if Button::default()
+                .draw_centered(
+                    x_mid,
+                    layout.next(),
+                    BUTTON_W,
+                    BUTTON_H,
+                    Some(&tr!("main_menu.rejoin", code = last_game.code)),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_pressed = Some(MainMenuButtons::Rejoin);
+            }
+            layout.add(BUTTON_H);
+        }
+
         if Button::default()
             .draw_centered(
                 x_mid,
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Train Models"),
+                Some(&tr!("main_menu.train")),
                 has_input,
             )
             .poll()
@@ -58,7 +96,22 @@ impl View for MainMenu {
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Multiplayer"),
+                Some(&tr!("main_menu.challenges")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(MainMenuButtons::Challenges);
+        }
+        layout.add(BUTTON_H);
+
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("main_menu.multiplayer")),
                 has_input,
             )
             .poll()
@@ -73,7 +126,37 @@ impl View for MainMenu {
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Options"),
+                Some(&tr!("main_menu.replays")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(MainMenuButtons::Replays);
+        }
+        layout.add(BUTTON_H);
+
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("main_menu.stats")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(MainMenuButtons::Stats);
+        }
+        layout.add(BUTTON_H);
+
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("main_menu.options")),
                 has_input,
             )
             .poll()
@@ -88,22 +171,59 @@ impl View for MainMenu {
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Quit"),
+                Some(&tr!("main_menu.quit")),
                 has_input,
             )
             .poll()
         {
             self.button_pressed = Some(MainMenuButtons::Quit);
         }
+
+        // Quoted in bug reports, and tells builds of the client apart
+        Text::new_scaled(TEXT_SMALL).draw_scaled_no_offset(
+            common::version(),
+            10.,
+            CANONICAL_SCREEN_HEIGHT - 10.,
+        );
     }
 
-    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        // Only runs while the menu is on top, so it never competes with a game
+        if ctx.game.is_none() {
+            self.background.update(get_frame_time(), &mut ctx.log);
+        }
+
         match self.button_pressed {
             Some(button) => match button {
+                // Offered once; being back in the match saves it again
+                MainMenuButtons::Rejoin => {
+                    let Some(last_game) = ctx.settings.last_game.take() else {
+                        return Transition::None;
+                    };
+                    if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                    ctx.server.resume_session(SessionToken(last_game.session));
+                    let request = ctx.server.connect(
+                        last_game.server,
+                        None,
+                        last_game.nickname,
+                        ctx.settings.trim.color(),
+                    );
+                    // The lobby takes us on into the match if the server kept our place
+                    Transition::Push(Box::new(RequestView::new_transition(
+                        tr!("main_menu.rejoining"),
+                        request,
+                        Transition::PopAnd(Box::new(ServerLobby::new())),
+                    )))
+                }
                 MainMenuButtons::Training => Transition::Push(Box::new(ModelSelect::new())),
+                MainMenuButtons::Challenges => Transition::Push(Box::new(ChallengeSelect::new())),
                 MainMenuButtons::Multiplayer => {
                     Transition::Push(Box::new(ServerConnectMenu::new()))
                 }
+                MainMenuButtons::Replays => Transition::Push(Box::new(ReplaySelect::new())),
+                MainMenuButtons::Stats => Transition::Push(Box::new(StatsView::new())),
                 MainMenuButtons::Options => Transition::Push(Box::new(OptionsMenu::new())),
                 MainMenuButtons::Quit => Transition::Pop,
             },
@@ -111,6 +231,10 @@ impl View for MainMenu {
         }
     }
 
+    fn presence(&self, _ctx: &AppContext) -> Option<Presence> {
+        Some(Presence::MainMenu)
+    }
+
     fn get_id(&self) -> ViewId {
         ViewId::MainMenu
     }
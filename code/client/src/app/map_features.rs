@@ -0,0 +1,94 @@
+//! Teleporter pads, one-way walls and moving walls, drawn over the floor below the tanks.
+
+use crate::ui::theme::WALL_COLOR;
+use common::game::{MapDefinition, TELEPORTER_RADIUS};
+use common::protocol::WallSchedule;
+use macroquad::prelude::*;
+
+/// Pads of a pair share a colour, taken in turn from these.
+const PAD_COLORS: [Color; 3] = [
+    Color::new(1.0, 0.8, 0.0, 1.0),
+    Color::new(0.6, 0.3, 1.0, 1.0),
+    Color::new(0.2, 1.0, 0.4, 1.0),
+];
+const ONE_WAY_COLOR: Color = Color::new(0.0, 0.6, 0.8, 0.25);
+const ONE_WAY_ARROW: Color = Color::new(0.0, 1.0, 1.0, 0.7);
+/// Room between the arrows on a one-way wall, in world units
+const ARROW_SPACING: f32 = 30.0;
+const MOVING_WALL_OUTLINE: Color = Color::new(1.0, 0.6, 0.0, 0.8);
+const CAPTURE_COLOR: Color = Color::new(1.0, 0.6, 0.0, 0.5);
+
+pub(crate) fn draw(map: &MapDefinition, (scaling, x_offset, y_offset): (f32, f32, f32)) {
+    let to_screen = |pos: Vec2| Vec2::new(pos.x * scaling + x_offset, pos.y * scaling + y_offset);
+
+    for one_way in &map.one_way_walls {
+        let (min, max) = (to_screen(one_way.wall.min), to_screen(one_way.wall.max));
+        let size = max - min;
+        draw_rectangle(min.x, min.y, size.x, size.y, ONE_WAY_COLOR);
+
+        // A row of chevrons across the wall, pointing the way through
+        let dir = one_way.direction;
+        let side = dir.perp();
+        let extent = (one_way.wall.max - one_way.wall.min).dot(side).abs();
+        let centre = (one_way.wall.min + one_way.wall.max) / 2.0;
+        let arrows = (extent / ARROW_SPACING).floor().max(1.0) as usize;
+        let arm = ARROW_SPACING / 3.0;
+        for i in 0..arrows {
+            let offset = (i as f32 + 0.5) * extent / arrows as f32 - extent / 2.0;
+            let tip = centre + side * offset + dir * arm / 2.0;
+            for wing in [side, -side] {
+                let end = to_screen(tip - dir * arm + wing * arm);
+                let tip = to_screen(tip);
+                draw_line(tip.x, tip.y, end.x, end.y, 2.0, ONE_WAY_ARROW);
+            }
+        }
+    }
+
+    for moving in &map.moving_walls {
+        let wall = moving.current();
+        let (min, max) = (to_screen(wall.min), to_screen(wall.max));
+        let size = max - min;
+        draw_rectangle(min.x, min.y, size.x, size.y, WALL_COLOR);
+        draw_rectangle_lines(min.x, min.y, size.x, size.y, 2.0, MOVING_WALL_OUTLINE);
+
+        // The point that opens it, filling up while it's being captured
+        if let WallSchedule::Capture {
+            point,
+            radius,
+            hold,
+        } = moving.schedule
+        {
+            let centre = to_screen(point);
+            let r = radius * scaling;
+            draw_circle_lines(centre.x, centre.y, r, 2.0, CAPTURE_COLOR);
+            let held = if moving.state.opening {
+                1.0
+            } else {
+                moving.state.progress / hold.max(f32::EPSILON)
+            };
+            draw_circle(
+                centre.x,
+                centre.y,
+                r * held.min(1.0),
+                Color {
+                    a: 0.2,
+                    ..CAPTURE_COLOR
+                },
+            );
+        }
+    }
+
+    let time = get_time() as f32;
+    for (i, teleporter) in map.teleporters.iter().enumerate() {
+        let color = PAD_COLORS[i % PAD_COLORS.len()];
+        for pad in [teleporter.a, teleporter.b] {
+            let pad = to_screen(pad);
+            let r = TELEPORTER_RADIUS * scaling;
+            draw_circle(pad.x, pad.y, r, Color { a: 0.15, ..color });
+            draw_circle_lines(pad.x, pad.y, r, 2.0, color);
+            // Shrinking ring, to tell pads from decoration
+            let pulse = 1.0 - (time * 0.8).fract();
+            draw_circle_lines(pad.x, pad.y, r * pulse, 1.5, Color { a: 0.6, ..color });
+        }
+    }
+}
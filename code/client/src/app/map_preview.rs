@@ -0,0 +1,31 @@
+//! Thumbnails of the maps in map pickers, rendered once per map.
+
+use crate::ui::draw_texture_centered;
+use common::game::MapDefinition;
+use common::game::thumbnail::render_thumbnail;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+/// Length of the longer side of a preview, in canonical screen pixels
+const PREVIEW_SIZE: u32 = 120;
+
+#[derive(Default)]
+pub(crate) struct MapPreviews {
+    textures: HashMap<String, Texture2D>,
+}
+
+impl MapPreviews {
+    /// Draws a thumbnail of the map known as `name`, centred on `x`, `y`. `map` is only
+    /// asked for the first time.
+    pub fn draw(&mut self, name: &str, map: impl FnOnce() -> MapDefinition, x: f32, y: f32) {
+        let texture = self.textures.entry(name.to_string()).or_insert_with(|| {
+            let thumbnail = render_thumbnail(&map(), PREVIEW_SIZE);
+            Texture2D::from_rgba8(
+                thumbnail.width as u16,
+                thumbnail.height as u16,
+                &thumbnail.rgba,
+            )
+        });
+        draw_texture_centered(texture, x, y, 1.0);
+    }
+}
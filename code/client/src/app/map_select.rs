@@ -0,0 +1,120 @@
+//! A list of the maps in a registry with their thumbnails, handing the picked one back to
+//! the view below as a [`PickedMap`].
+
+use crate::app::map_preview::MapPreviews;
+use crate::app::{AppContext, Transition, View, ViewId, ViewResult};
+use crate::i18n::tr;
+use crate::ui::{
+    self, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_WIDTH,
+};
+use common::game::map_registry::MapRegistry;
+use macroquad::prelude::*;
+
+/// Height of a row, fitting a thumbnail
+const ROW_H: f32 = 130.;
+
+/// The name of the map picked in a [`MapSelect`].
+pub(crate) struct PickedMap(pub String);
+
+pub(crate) struct MapSelect {
+    maps: MapRegistry,
+    names: Vec<String>,
+    /// The map picked before, highlighted
+    current: String,
+    scroll: ui::ScrollArea,
+    previews: MapPreviews,
+    back_clicked: bool,
+    picked: Option<String>,
+}
+
+impl MapSelect {
+    pub fn new(maps: MapRegistry, current: String) -> Self {
+        let names = maps.names().map(String::from).collect();
+        Self {
+            maps,
+            names,
+            current,
+            scroll: ui::ScrollArea::new(),
+            previews: MapPreviews::default(),
+            back_clicked: false,
+            picked: None,
+        }
+    }
+}
+
+impl View for MapSelect {
+    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+        self.scroll.update();
+
+        if self.back_clicked {
+            return Transition::Pop;
+        }
+        if let Some(name) = self.picked.take() {
+            return Transition::PopWith(ViewResult::new(PickedMap(name)));
+        }
+
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_WIDTH / 2.;
+        let mut layout = ui::Layout::new(80., 15.);
+
+        ui::Text::new_title().draw(&tr!("map_select.title"), x_mid, layout.next());
+        layout.add(60.);
+
+        let back_button_y = CANONICAL_SCREEN_HEIGHT - 80.;
+        let list_start_y = layout.next();
+        let list_end_y = back_button_y - 20.;
+        let content_height = self.names.len() as f32 * ROW_H;
+        let (_clip, content_y) = self.scroll.begin(list_start_y, list_end_y, content_height);
+
+        for (row, name) in self.names.iter().enumerate() {
+            let row_y = content_y + row as f32 * ROW_H;
+            if row_y + ROW_H < list_start_y || row_y > list_end_y {
+                continue;
+            }
+            let row_mid = row_y + ROW_H / 2.;
+            let label = if *name == self.current {
+                format!("> {} <", name)
+            } else {
+                name.clone()
+            };
+            if Button::default()
+                .draw_centered(
+                    x_mid - 80.,
+                    row_mid,
+                    BUTTON_W * 1.5,
+                    BUTTON_H,
+                    Some(&label),
+                    has_input,
+                )
+                .poll()
+            {
+                self.picked = Some(name.clone());
+            }
+            let map = self.maps.get(name);
+            self.previews.draw(
+                name,
+                || map.cloned().expect("the registry lists its own maps"),
+                x_mid + BUTTON_W * 0.75,
+                row_mid,
+            );
+        }
+
+        self.back_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                back_button_y,
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("common.back")),
+                has_input,
+            )
+            .poll();
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::MapSelect
+    }
+}
@@ -0,0 +1,208 @@
+//! How the match went, under the scoreboard on the winner screen: a bar of its rounds in
+//! their winners' colours, with a mark for every kill.
+
+use crate::i18n::tr;
+use crate::ui::{
+    TEXT_SMALL, Text, TextHorizontalPositioning, TextVerticalPositioning, default_text_params,
+    scale_dims,
+    theme::{NEON_CYAN, NEON_PINK},
+};
+use common::protocol::{MatchSummary, PlayerId, Team, TimelineEvent};
+use macroquad::prelude::*;
+
+const LEFT: f32 = 240.;
+const WIDTH: f32 = 800.;
+/// Center of the bar
+const Y: f32 = 665.;
+const BAR_H: f32 = 14.;
+/// How far kill marks stick out of the bar
+const MARK_OVERHANG: f32 = 5.;
+
+/// A round's number, when it started and ended, and who won it, if anyone did.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RoundSpan {
+    pub round: u8,
+    pub start_ms: u32,
+    pub end_ms: u32,
+    pub winner: Option<Team>,
+}
+
+/// The rounds of `summary`; one still running at the end lasts until then.
+pub(crate) fn round_spans(summary: &MatchSummary) -> Vec<RoundSpan> {
+    let mut spans: Vec<RoundSpan> = Vec::new();
+    for entry in &summary.timeline {
+        match entry.event {
+            TimelineEvent::RoundStarted(round) => spans.push(RoundSpan {
+                round,
+                start_ms: entry.at_ms,
+                end_ms: summary.duration_ms,
+                winner: None,
+            }),
+            TimelineEvent::RoundEnded { round, winner } => {
+                if let Some(span) = spans.iter_mut().rev().find(|span| span.round == round) {
+                    span.end_ms = entry.at_ms;
+                    span.winner = winner;
+                }
+            }
+            TimelineEvent::Kill { .. } | TimelineEvent::Forfeit(_) => {}
+        }
+    }
+    spans
+}
+
+fn x_at(at_ms: u32, duration_ms: u32) -> f32 {
+    LEFT + WIDTH * at_ms as f32 / duration_ms.max(1) as f32
+}
+
+fn team_color(team: Option<Team>) -> Color {
+    match team {
+        Some(Team::Blue) => NEON_CYAN,
+        Some(Team::Red) => NEON_PINK,
+        Some(Team::Ffa(_)) | None => LIGHTGRAY,
+    }
+}
+
+fn format_time(ms: u32) -> String {
+    let seconds = ms / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub(crate) fn draw_timeline(summary: &MatchSummary, me: PlayerId) {
+    let text = |horizontal, color| {
+        Text::new(
+            TextParams {
+                font_size: TEXT_SMALL,
+                color,
+                ..default_text_params()
+            },
+            TextVerticalPositioning::CenterConsistent,
+            horizontal,
+        )
+    };
+    let label = text(TextHorizontalPositioning::Center, LIGHTGRAY);
+    let (x, y, w, h) = scale_dims(LEFT - 20., Y - 50., WIDTH + 40., 95.);
+    draw_rectangle(x, y, w, h, Color::new(0., 0., 0., 0.7));
+    text(TextHorizontalPositioning::Left, LIGHTGRAY).draw(&tr!("timeline.title"), LEFT, Y - 30.);
+
+    let duration = summary.duration_ms;
+    let (x, y, w, h) = scale_dims(LEFT, Y - BAR_H / 2., WIDTH, BAR_H);
+    draw_rectangle(x, y, w, h, Color::new(0.15, 0.15, 0.2, 1.));
+    for span in round_spans(summary) {
+        let (start, end) = (x_at(span.start_ms, duration), x_at(span.end_ms, duration));
+        let (x, y, w, h) = scale_dims(start, Y - BAR_H / 2., end - start, BAR_H);
+        let color = team_color(span.winner);
+        draw_rectangle(x, y, w, h, Color { a: 0.35, ..color });
+        label.draw(
+            &tr!("timeline.round", round = span.round),
+            (start + end) / 2.,
+            Y + BAR_H + 8.,
+        );
+    }
+
+    let team_of = |id: PlayerId| {
+        summary
+            .players
+            .iter()
+            .find(|info| info.id == id)
+            .map(|info| info.team)
+    };
+    let top = Y - BAR_H / 2. - MARK_OVERHANG;
+    for entry in &summary.timeline {
+        if let TimelineEvent::Forfeit(player) = entry.event {
+            let name = summary
+                .players
+                .iter()
+                .find(|info| info.id == player)
+                .map_or_else(|| format!("#{}", player), |info| info.nickname.clone());
+            let mark_x = x_at(entry.at_ms, duration);
+            let (x, y, w, h) = scale_dims(mark_x, top, 2., BAR_H + 2. * MARK_OVERHANG);
+            draw_line(x, y, x, y + h, w, GRAY);
+            text(TextHorizontalPositioning::Left, GRAY).draw(
+                &tr!("timeline.forfeit", player = name),
+                mark_x + 8.,
+                top - 8.,
+            );
+            continue;
+        }
+        let TimelineEvent::Kill {
+            killer,
+            first_blood,
+            ..
+        } = entry.event
+        else {
+            continue;
+        };
+        let color = if killer == me {
+            YELLOW
+        } else {
+            team_color(team_of(killer))
+        };
+        let mark_x = x_at(entry.at_ms, duration);
+        let (x, y, w, h) = scale_dims(mark_x, top, 2., BAR_H + 2. * MARK_OVERHANG);
+        draw_line(x, y, x, y + h, w, color);
+        if first_blood {
+            let (x, y, r, _) = scale_dims(mark_x, top, 4., 0.);
+            draw_circle(x, y, r, RED);
+            text(TextHorizontalPositioning::Left, RED).draw(
+                &tr!("timeline.first_blood"),
+                mark_x + 8.,
+                top - 8.,
+            );
+        }
+    }
+
+    label.draw(&format_time(0), LEFT, Y + BAR_H + 8.);
+    label.draw(&format_time(duration), LEFT + WIDTH, Y + BAR_H + 8.);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::protocol::TimelineEntry;
+
+    #[test]
+    fn test_round_spans_end_with_their_round() {
+        let entry = |at_ms, event| TimelineEntry { at_ms, event };
+        let summary = MatchSummary {
+            players: Vec::new(),
+            timeline: vec![
+                entry(3000, TimelineEvent::RoundStarted(1)),
+                entry(
+                    9000,
+                    TimelineEvent::Kill {
+                        killer: 0,
+                        victim: 1,
+                        first_blood: true,
+                    },
+                ),
+                entry(
+                    20000,
+                    TimelineEvent::RoundEnded {
+                        round: 1,
+                        winner: Some(Team::Red),
+                    },
+                ),
+                entry(23000, TimelineEvent::RoundStarted(2)),
+            ],
+            duration_ms: 30000,
+        };
+        assert_eq!(
+            round_spans(&summary),
+            [
+                RoundSpan {
+                    round: 1,
+                    start_ms: 3000,
+                    end_ms: 20000,
+                    winner: Some(Team::Red),
+                },
+                RoundSpan {
+                    round: 2,
+                    start_ms: 23000,
+                    end_ms: 30000,
+                    winner: None,
+                },
+            ]
+        );
+        assert_eq!(format_time(83_500), "1:23");
+    }
+}
@@ -0,0 +1,145 @@
+use crate::app::event_log::{EventLog, LogCategory};
+use crate::app::game::Game;
+use ::rand::Rng;
+use common::ai::{BotAgent, BotDifficulty};
+use common::game::engine::GameEngine;
+use common::game::player::PlayerInfo;
+use common::net::protocol::{MapDefinition, MapName, PlayerId, Tank};
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+/// Matches that drag on longer than this are restarted (in seconds).
+const MATCH_SECS: f32 = 45.0;
+/// Longest step simulated at once, so a stalled frame doesn't teleport the tanks.
+const MAX_DT: f32 = 0.05;
+const DIFFICULTIES: [BotDifficulty; 3] = [
+    BotDifficulty::Hunter,
+    BotDifficulty::Wanderer,
+    BotDifficulty::Terminator,
+];
+
+/// A bot-only match playing behind the main menu. Besides looking nice it exercises the
+/// engine and the scripted AI on the player's machine, so breakage shows up in the event
+/// log before anyone tries to play.
+pub(crate) struct MenuBackground {
+    engine: GameEngine,
+    bots: HashMap<PlayerId, BotAgent>,
+    match_time: f32,
+    matches: u32,
+    /// Set once the simulation panicked; it is not restarted after that
+    stopped: bool,
+}
+
+impl MenuBackground {
+    pub fn new() -> Self {
+        let (engine, bots) = Self::setup(0);
+        Self {
+            engine,
+            bots,
+            match_time: 0.0,
+            matches: 0,
+            stopped: false,
+        }
+    }
+
+    fn setup(match_index: u32) -> (GameEngine, HashMap<PlayerId, BotAgent>) {
+        let maps = [MapName::Basic, MapName::Loss, MapName::Tiga];
+        let map = MapDefinition::load_name(maps[match_index as usize % maps.len()]);
+        let seed: u64 = ::rand::rng().random();
+
+        let mut engine = GameEngine::new(map);
+        let mut bots = HashMap::new();
+        for (i, (team, position)) in engine.map.spawn_points.clone().into_iter().enumerate() {
+            let id = i as PlayerId;
+            let info = PlayerInfo::new(id, format!("Bot {}", i), team);
+            let difficulty = DIFFICULTIES[i % DIFFICULTIES.len()];
+            bots.insert(
+                id,
+                BotAgent::new(info.clone(), difficulty, seed ^ u64::from(id)),
+            );
+            engine.tanks.push(Tank::new(info, position));
+        }
+        (engine, bots)
+    }
+
+    fn step(&mut self, dt: f32) {
+        let mut inputs = HashMap::new();
+        for tank in &self.engine.tanks {
+            if let Some(bot) = self.bots.get_mut(&tank.player_info.id) {
+                let input = bot.generate_input(
+                    tank,
+                    &self.engine.tanks,
+                    &self.engine.projectiles,
+                    &self.engine.map,
+                    dt,
+                    self.engine.blackboard.ping_for(tank, &self.engine.map),
+                );
+                inputs.insert(tank.player_info.id, input);
+            }
+        }
+
+        let result = self.engine.tick(dt, inputs);
+        self.match_time += dt;
+        if result.outcome.is_over() || self.match_time >= MATCH_SECS {
+            self.matches += 1;
+            (self.engine, self.bots) = Self::setup(self.matches);
+            self.match_time = 0.0;
+        }
+    }
+
+    /// Advances the match by `dt`. A panic stops the background and is reported in `log`.
+    pub fn update(&mut self, dt: f32, log: &mut EventLog) {
+        if self.stopped {
+            return;
+        }
+        let dt = dt.min(MAX_DT);
+        if let Err(panic) = catch_unwind(AssertUnwindSafe(|| self.step(dt))) {
+            let reason = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".into());
+            log.push(
+                LogCategory::Error,
+                format!("Menu background simulation stopped: {}", reason),
+            );
+            self.stopped = true;
+        }
+    }
+
+    /// Draws the match dimmed, to be covered by the menu.
+    pub fn draw(&self) {
+        if self.stopped {
+            return;
+        }
+        Game::draw_game_board(&self.engine, None, false);
+        draw_rectangle(
+            0.,
+            0.,
+            screen_width(),
+            screen_height(),
+            Color::new(0.02, 0.02, 0.06, 0.75),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_matches_keep_running() {
+        let mut background = MenuBackground::new();
+        let mut log = EventLog::new(10);
+
+        // Long enough to finish or time out at least one match
+        for _ in 0..(MATCH_SECS / MAX_DT) as usize + 10 {
+            background.update(MAX_DT, &mut log);
+        }
+
+        assert!(!background.stopped);
+        assert!(background.matches >= 1);
+        assert!(!background.engine.tanks.is_empty());
+    }
+}
@@ -1,32 +1,86 @@
-use crate::app::fps_display::FPSDisplay;
+use crate::app::conditioner_panel::ConditionerPanel;
+use crate::app::event_log::{CLIENT_LOG_PATH, EventLog, LogCategory};
+use crate::app::fps_display::{FPSDisplay, FrameTimeGraph};
+use crate::app::frame_pacing::FramePacer;
 use crate::app::game::Game;
 use crate::app::main_menu::MainMenu;
 use crate::app::popup::Popup;
-use crate::server::Server;
-use crate::ui::BACKGROUND_COLOR;
+use crate::app::server_connect_menu::ServerConnectMenu;
+use crate::app::services::Services;
+use crate::assets;
+use crate::i18n::{self, tr};
+use crate::join_link::JoinLink;
+use crate::model_cache::{self, MODEL_CACHE_DIR};
+use crate::presence::{self, Presence, PresenceProvider};
+use crate::server::{ConnectionState, Server};
+use crate::settings::{LastGame, SETTINGS_PATH, Settings};
+use crate::ui::{self, BACKGROUND_COLOR};
+use common::crash;
 
 use macroquad::prelude::*;
+use std::any::Any;
 
+mod camera;
+mod challenge;
+mod challenge_select;
+mod conditioner_panel;
+mod crosshair;
+mod effects;
+mod entities;
+mod evaluation;
+mod event_log;
+mod explosions;
 mod feeds;
 mod fps_display;
+mod frame_pacing;
 mod game;
 mod game_creation;
+mod game_share;
 mod game_view;
+mod graphics_menu;
+mod hud;
 mod in_game_menu;
+mod indicators;
+mod kill_ranking;
+mod killcam;
+mod loading_view;
+mod lobby_roster;
 mod main_menu;
+mod map_features;
+mod map_preview;
+mod map_select;
+mod match_timeline;
+mod menu_background;
 mod model_select;
+mod name_tags;
 mod options_menu;
+mod pings;
 mod popup;
+mod prediction;
+mod replay_select;
+mod replay_view;
 mod request_view;
+mod round_overlay;
+mod scoreboard;
 mod server_connect_menu;
 mod server_lobby;
+mod services;
+mod shop;
+mod stats_view;
+mod tournament;
 mod training;
 mod training_mode_select;
 
+/// Where crash reports are saved
+pub(crate) const CRASH_DIR: &str = "assets/crashes";
+
 // Global data that persists across views
 pub(crate) struct AppContext {
     pub game: Option<Game>,
     pub server: Server,
+    pub settings: Settings,
+    pub log: EventLog,
+    pub services: Services,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -43,6 +97,30 @@ pub(crate) enum ViewId {
     ModelSelect,
     TrainingModeSelect,
     Training,
+    ReplaySelect,
+    ReplayView,
+    ChallengeSelect,
+    Challenge,
+    Stats,
+    Tournament,
+    Loading,
+    GraphicsMenu,
+    MapSelect,
+}
+
+/// Something a view hands back to the one it returns to, like the model or map picked in
+/// it, given to [`View::on_result`].
+pub(crate) struct ViewResult(Box<dyn Any>);
+
+impl ViewResult {
+    pub fn new<T: Any>(value: T) -> Self {
+        Self(Box::new(value))
+    }
+
+    /// The value if it is a `T`, otherwise the result back for another try.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        self.0.downcast::<T>().map(|value| *value).map_err(Self)
+    }
 }
 
 pub(crate) enum Transition {
@@ -57,6 +135,10 @@ pub(crate) enum Transition {
     PopUntil(ViewId),
     /// Combination of PopUntil and PopAnd
     PopUntilAnd(ViewId, Box<dyn View>),
+    /// Pop the top state and hand the result to the one below
+    PopWith(ViewResult),
+    /// Pop states until the specific ID and hand it the result
+    PopUntilWith(ViewId, ViewResult),
     /// Perform if app should go to the serverless view
     ToServerlessView(String),
 }
@@ -75,40 +157,141 @@ pub(crate) trait View {
         // If we want to reset, we override this method.
     }
 
+    /// Takes what a view above handed back on returning to this one; called after
+    /// `visible_again`. Results the view doesn't expect are dropped.
+    fn on_result(&mut self, _ctx: &mut AppContext, _result: ViewResult) {}
+
     /// Helper to determine if we should draw the state below this one.
     fn is_overlay(&self) -> bool {
         false
     }
+
+    /// What the player is doing, for rich presence. The topmost view that knows wins.
+    fn presence(&self, _ctx: &AppContext) -> Option<Presence> {
+        None
+    }
 }
 
 pub(crate) struct App {
     stack: Vec<Box<dyn View>>,
     context: AppContext,
     fps_display: FPSDisplay,
+    frame_graph: FrameTimeGraph,
+    /// Only shown and listened to in debug builds
+    conditioner_panel: ConditionerPanel,
+    pacer: FramePacer,
+    /// Last connection state written to the log
+    logged_connection: ConnectionState,
+    presence: Box<dyn PresenceProvider>,
+    /// Last presence handed to the provider
+    reported_presence: Option<Presence>,
+    /// Last summary handed to crash reports
+    crash_state: String,
 }
 
 impl App {
-    pub async fn new() -> Self {
+    /// Starts at the main menu, or at the connect menu when opened with a `join_link`.
+    pub async fn new(join_link: Option<JoinLink>) -> Self {
+        let settings = Settings::load(std::path::Path::new(SETTINGS_PATH));
+        if let Err(e) = i18n::set_language(&settings.language) {
+            eprintln!("{}", e);
+        }
+        let mut log = EventLog::with_default_capacity();
+        if settings.log_to_file {
+            log.set_file(Some(std::path::Path::new(CLIENT_LOG_PATH)));
+        }
+        log.push(LogCategory::Connection, "Client started.");
+        for problem in effects::load_table() {
+            log.push(LogCategory::Error, problem);
+        }
+        let services = Services::load();
+        ui::main_font();
+        ui::banner_texture();
+        for problem in assets::load_pending(draw_loading_screen).await {
+            log.push(LogCategory::Error, problem);
+        }
+        let mut stack: Vec<Box<dyn View>> = vec![Box::new(MainMenu::new())];
+        if let Some(link) = join_link {
+            log.push(
+                LogCategory::Connection,
+                format!(
+                    "Opened with a link to game {} on {}",
+                    link.code.0, link.server
+                ),
+            );
+            stack.push(Box::new(ServerConnectMenu::with_link(link)));
+        }
+        if let Some(report) = crash::take_unseen_report(std::path::Path::new(CRASH_DIR)) {
+            log.push(
+                LogCategory::Error,
+                format!("Last run crashed, report at {}", report.display()),
+            );
+            stack.push(Box::new(Popup::new(tr!(
+                "crash.report_saved",
+                path = report.display()
+            ))));
+        }
+
         App {
-            stack: vec![Box::new(MainMenu::new())],
+            stack,
             context: AppContext {
                 game: None,
                 server: Server::new(),
+                settings,
+                log,
+                services,
             },
             fps_display: FPSDisplay::new(30),
+            frame_graph: FrameTimeGraph::new(),
+            conditioner_panel: ConditionerPanel::new(),
+            pacer: FramePacer::new(),
+            logged_connection: ConnectionState::Idle,
+            presence: presence::default_provider(),
+            reported_presence: None,
+            crash_state: String::new(),
         }
     }
 
     pub async fn run(&mut self) {
         while !self.stack.is_empty() {
-            if let Err(reason) = self.context.server.tick() {
+            let tick_result = self.context.server.tick();
+            self.log_connection_state();
+            if let Err(reason) = tick_result {
                 self.perform_transition(Transition::ToServerlessView(reason));
             }
 
             if let Some(game) = &mut self.context.game
                 && let Some(update) = self.context.server.game_update()
             {
-                game.update(update, &mut self.context.server);
+                game.update(
+                    update,
+                    &mut self.context.server,
+                    &mut self.context.settings,
+                    &mut self.context.log,
+                );
+            }
+            if let Some(game) = &mut self.context.game {
+                game.send_input(
+                    &mut self.context.server,
+                    &self.context.settings,
+                    get_frame_time(),
+                );
+            }
+
+            self.handle_model_downloads();
+            for reloaded in assets::reload_changed(get_time()) {
+                match reloaded {
+                    Ok(path) => self
+                        .context
+                        .log
+                        .push(LogCategory::Game, format!("Reloaded {}", path)),
+                    Err(e) => self.context.log.push(LogCategory::Error, e),
+                }
+            }
+            self.context.log.update();
+            if cfg!(debug_assertions) {
+                self.conditioner_panel
+                    .update(self.context.server.conditioner_mut());
             }
 
             // We only run update for the state on top of the stack
@@ -133,12 +316,122 @@ impl App {
             }
 
             self.perform_transition(transition);
+            self.remember_last_game();
+            self.report_presence();
+            self.report_crash_state();
 
             self.fps_display.update();
             self.fps_display.draw();
+            let graphics = &self.context.settings.graphics;
+            self.frame_graph.record(get_frame_time());
+            if graphics.frame_graph {
+                self.frame_graph.draw();
+            }
+            if cfg!(debug_assertions) {
+                self.conditioner_panel
+                    .draw(self.context.server.conditioner());
+            }
+            self.context.log.draw();
 
+            self.pacer.wait(graphics.fps_cap);
             next_frame().await;
         }
+        self.presence.clear();
+    }
+
+    /// Saves the match the player is in, so it can be rejoined after a crash or restart. It's
+    /// forgotten once they're back in the server lobby, having left the match or seen it end;
+    /// losing the connection keeps it.
+    fn remember_last_game(&mut self) {
+        let ctx = &mut self.context;
+        let last_game = match &ctx.game {
+            Some(game) if ctx.server.is_playing() => {
+                let (Some(server), Some(nickname), Some(session)) = (
+                    ctx.server.address(),
+                    ctx.server.nickname(),
+                    ctx.server.session(),
+                ) else {
+                    return;
+                };
+                Some(LastGame {
+                    server: server.to_string(),
+                    code: game.get_game_code().to_string(),
+                    nickname: nickname.to_string(),
+                    session: session.0,
+                })
+            }
+            _ if ctx.server.is_in_lobby() => None,
+            _ => return,
+        };
+        if ctx.settings.last_game != last_game {
+            ctx.settings.last_game = last_game;
+            if let Err(e) = ctx.settings.save(std::path::Path::new(SETTINGS_PATH)) {
+                eprintln!("Failed to save settings: {}", e);
+            }
+        }
+    }
+
+    /// Downloads the bot model a game offers if the player allowed it, and stores it once
+    /// it arrived.
+    fn handle_model_downloads(&mut self) {
+        let ctx = &mut self.context;
+        let cache = std::path::Path::new(MODEL_CACHE_DIR);
+        if let Some(offer) = ctx.server.take_model_offer()
+            && ctx.settings.download_models
+            && !model_cache::contains(cache, &offer.hash)
+        {
+            ctx.log.push(
+                LogCategory::Connection,
+                format!(
+                    "Downloading bot model {} ({} KB)...",
+                    offer.name,
+                    offer.size / 1024
+                ),
+            );
+            ctx.server.download_model(offer);
+        }
+
+        match ctx.server.take_downloaded_model() {
+            Some(Ok((info, bytes))) => match model_cache::store(cache, &info.hash, &bytes) {
+                Ok(_) => ctx.log.push(
+                    LogCategory::Connection,
+                    format!("Downloaded bot model {}.", info.name),
+                ),
+                Err(e) => ctx.log.push(LogCategory::Error, e),
+            },
+            Some(Err(e)) => ctx
+                .log
+                .push(LogCategory::Error, format!("Model download failed: {}", e)),
+            None => {}
+        }
+    }
+
+    fn report_presence(&mut self) {
+        let presence = self
+            .stack
+            .iter()
+            .rev()
+            .find_map(|view| view.presence(&self.context));
+        if presence != self.reported_presence {
+            if let Some(presence) = &presence {
+                self.context.log.push(LogCategory::Game, presence.text());
+                self.presence.update(presence);
+            }
+            self.reported_presence = presence;
+        }
+    }
+
+    /// Keeps the views open and the game played up to date for crash reports.
+    fn report_crash_state(&mut self) {
+        let views: Vec<ViewId> = self.stack.iter().map(|view| view.get_id()).collect();
+        let mut state = format!("views {:?}", views);
+        if let Some(game) = &self.context.game {
+            state = format!("{}, {}", state, game.crash_summary());
+        }
+        if state != self.crash_state {
+            crash::set_state(state.clone());
+            self.crash_state = state;
+        }
     }
 
     fn perform_transition(&mut self, transition: Transition) {
@@ -169,6 +462,19 @@ impl App {
 
                 self.stack.push(new_view);
             }
+            Transition::PopWith(result) => {
+                self.perform_transition(Transition::Pop);
+                if let Some(top) = self.stack.last_mut() {
+                    top.on_result(&mut self.context, result);
+                }
+            }
+            Transition::PopUntilWith(target_id, result) => {
+                self.perform_transition(Transition::PopUntil(target_id));
+                self.stack
+                    .last_mut()
+                    .unwrap()
+                    .on_result(&mut self.context, result);
+            }
             Transition::Pop => {
                 let from_overlay = self.stack.last_mut().unwrap().is_overlay();
                 self.stack.pop();
@@ -192,6 +498,7 @@ impl App {
                 self.stack.push(new_view);
             }
             Transition::ToServerlessView(reason) => {
+                self.context.log.push(LogCategory::Error, &reason);
                 self.context.server.close();
                 self.perform_transition(Transition::PopUntilAnd(
                     ViewId::ServerConnectMenu,
@@ -202,6 +509,58 @@ impl App {
         }
     }
 
+    fn log_connection_state(&mut self) {
+        let state = self.context.server.connection_state();
+        let changed = match (&self.logged_connection, state) {
+            // The retry time alone is not worth a line
+            (
+                ConnectionState::Reconnecting { attempt: a, .. },
+                ConnectionState::Reconnecting { attempt: b, .. },
+            ) => a != b,
+            (old, new) => old != new,
+        };
+        if !changed {
+            return;
+        }
+
+        let max_attempts = self.context.server.max_attempts();
+        let (category, text) = match state {
+            ConnectionState::Idle => (LogCategory::Connection, "Disconnected.".to_string()),
+            ConnectionState::Connecting { attempt } => (
+                LogCategory::Connection,
+                format!("Connecting (attempt {}/{}).", attempt, max_attempts),
+            ),
+            ConnectionState::Handshaking { .. } => (
+                LogCategory::Connection,
+                "Transport up, sending handshake.".to_string(),
+            ),
+            ConnectionState::Connected => {
+                (LogCategory::Connection, "Connected to server.".to_string())
+            }
+            ConnectionState::Reconnecting {
+                attempt,
+                retry_at,
+                reason,
+            } => (
+                LogCategory::Error,
+                format!(
+                    "{} Retrying in {:.1}s (attempt {}/{}).",
+                    reason,
+                    retry_at
+                        .saturating_duration_since(std::time::Instant::now())
+                        .as_secs_f32(),
+                    attempt,
+                    max_attempts
+                ),
+            ),
+            ConnectionState::Failed(reason) => {
+                (LogCategory::Error, format!("Connection failed: {}", reason))
+            }
+        };
+        self.logged_connection = state.clone();
+        self.context.log.push(category, text);
+    }
+
     fn pop_until(&mut self, target_id: ViewId) -> bool {
         let mut only_overlay = true;
         // We try to find the state with provided target_id. We panic if we don't
@@ -225,3 +584,32 @@ impl App {
         only_overlay
     }
 }
+
+/// The progress of loading the assets at startup, as a bar in the middle of the screen.
+fn draw_loading_screen(done: usize, total: usize) {
+    const WIDTH: f32 = 600.;
+    const HEIGHT: f32 = 16.;
+    clear_background(BACKGROUND_COLOR);
+    let left = ui::CANONICAL_SCREEN_MID_X - WIDTH / 2.;
+    let top = ui::CANONICAL_SCREEN_MID_Y - HEIGHT / 2.;
+    let (x, y, w, h) = ui::scale_dims(left, top, WIDTH, HEIGHT);
+    draw_rectangle_lines(x, y, w, h, 2., ui::NEON_CYAN);
+    let filled = if total == 0 {
+        1.
+    } else {
+        done as f32 / total as f32
+    };
+    draw_rectangle(x, y, w * filled, h, ui::NEON_CYAN);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_results_come_back_as_what_was_sent() {
+        let result = ViewResult::new(String::from("arena"));
+        let result = result.downcast::<u32>().unwrap_err();
+        assert_eq!(result.downcast::<String>().ok().as_deref(), Some("arena"));
+    }
+}
@@ -1,30 +1,53 @@
+use crate::app::loading_view::LoadingView;
 use crate::app::training_mode_select::TrainingModeSelect;
-use crate::app::{AppContext, Transition, View, ViewId};
+use crate::app::{AppContext, Transition, View, ViewId, ViewResult};
+use crate::i18n::tr;
 use crate::ui::{self};
 use crate::ui::{BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_WIDTH};
 use burn::backend::Wgpu;
 use burn::module::Module;
 use burn::record::{BinFileRecorder, FullPrecisionSettings};
 use common::rl::BotBrain;
-use macroquad::miniquad::gl::{GL_SCISSOR_TEST, glDisable, glEnable, glScissor};
+use common::rl::gpu::wgpu_available;
 use macroquad::prelude::*;
 use std::fs;
 
 type ClientBackend = Wgpu;
 
+/// What the picked model is going to be used for.
+enum Purpose {
+    Training,
+    /// Picking the Red team model to compare against an already chosen Blue one, handed
+    /// back to the `TrainingModeSelect` below as a [`PickedModel`].
+    Opponent,
+}
+
+/// A model picked and loaded to be the opponent.
+pub(crate) struct PickedModel {
+    pub name: String,
+    pub brain: BotBrain<ClientBackend>,
+}
+
 pub(crate) struct ModelSelect {
+    purpose: Purpose,
     files: Vec<String>,
-    scroll: f32,
+    scroll: ui::ScrollArea,
     back_clicked: bool,
     picked_file: Option<String>,
+    /// Loaded models run on `Wgpu`, so without it only an explanation is shown
+    gpu_available: bool,
 }
 
 impl ModelSelect {
     pub fn new() -> Self {
-        Self::refresh_file_list()
+        Self::refresh_file_list(Purpose::Training)
     }
 
-    fn refresh_file_list() -> Self {
+    pub fn new_opponent() -> Self {
+        Self::refresh_file_list(Purpose::Opponent)
+    }
+
+    fn refresh_file_list(purpose: Purpose) -> Self {
         let mut files = Vec::new();
         let path = "assets/models";
 
@@ -44,28 +67,30 @@ impl ModelSelect {
         files.sort();
 
         Self {
+            purpose,
             files,
-            scroll: 0.0,
+            scroll: ui::ScrollArea::new(),
             back_clicked: false,
             picked_file: None,
+            gpu_available: wgpu_available(),
         }
     }
 }
 
+/// Loads `assets/models/<fname>`.
+fn load_model(fname: &str) -> Result<BotBrain<ClientBackend>, String> {
+    let full_path = format!("assets/models/{}", fname);
+    let load_name = full_path.strip_suffix(".bin").unwrap_or(&full_path);
+    let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
+    let device = Default::default();
+    BotBrain::<ClientBackend>::new(&device)
+        .load_file(load_name, &recorder, &device)
+        .map_err(|e| tr!("models.load_failed", name = fname, error = e))
+}
+
 impl View for ModelSelect {
-    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
-        let (_, y_scroll) = mouse_wheel();
-        if y_scroll != 0.0 {
-            self.scroll -= y_scroll * 30.0;
-
-            let layout_padding = 15.0;
-            let item_height = BUTTON_H + layout_padding;
-            let content_height = self.files.len() as f32 * item_height;
-            let view_height = CANONICAL_SCREEN_HEIGHT - 250.0;
-            let max_scroll = (content_height - view_height + 50.0).max(0.0); // +50 padding for last item visibility
-
-            self.scroll = self.scroll.clamp(0.0, max_scroll);
-        }
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        self.scroll.update();
 
         // Handle Back Button Logic
         if self.back_clicked {
@@ -73,22 +98,35 @@ impl View for ModelSelect {
             return Transition::Pop;
         }
 
-        // Handle file pick logic
+        // Handle file pick logic: big models take a while to decode, so load off the render loop
         if let Some(fname) = self.picked_file.take() {
-            let full_path = format!("assets/models/{}", fname);
-            let load_name = if fname.ends_with(".bin") {
-                &full_path[..full_path.len() - 4]
-            } else {
-                &full_path
+            return match self.purpose {
+                Purpose::Training => {
+                    let maps = ctx.services.maps().clone();
+                    Transition::Push(Box::new(LoadingView::start(
+                        tr!("models.loading"),
+                        Box::new(move || {
+                            let brain = load_model(&fname)?;
+                            Ok(TrainingModeSelect::new(fname, brain, maps))
+                        }),
+                        Box::new(|view| Transition::PopAnd(Box::new(view))),
+                    )))
+                }
+                Purpose::Opponent => {
+                    let name = fname.clone();
+                    Transition::Push(Box::new(LoadingView::start(
+                        tr!("models.loading"),
+                        Box::new(move || load_model(&fname)),
+                        // Closes this menu as well
+                        Box::new(|brain| {
+                            Transition::PopUntilWith(
+                                ViewId::TrainingModeSelect,
+                                ViewResult::new(PickedModel { name, brain }),
+                            )
+                        }),
+                    )))
+                }
             };
-            let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
-            if let Ok(brain) = BotBrain::<ClientBackend>::new(&Default::default()).load_file(
-                load_name,
-                &recorder,
-                &Default::default(),
-            ) {
-                return Transition::Push(Box::new(TrainingModeSelect::new(fname, brain)));
-            }
         }
 
         Transition::None
@@ -98,69 +136,59 @@ impl View for ModelSelect {
         let x_mid = CANONICAL_SCREEN_WIDTH / 2.;
         let mut layout = ui::Layout::new(80., 15.);
 
-        ui::Text::new_title().draw("Select Model", x_mid, layout.next());
+        let title = match &self.purpose {
+            Purpose::Training => tr!("models.title"),
+            Purpose::Opponent => tr!("models.title_opponent"),
+        };
+        ui::Text::new_title().draw(&title, x_mid, layout.next());
         layout.add(60.);
 
-        ui::Text::new_scaled(ui::TEXT_MID).draw("Existing Models:", x_mid, layout.next());
-        layout.add(30.);
-
-        // --- SCROLLABLE AREA START ---
-        let list_start_y = layout.next();
         let back_button_y = CANONICAL_SCREEN_HEIGHT - 80.0; // Fixed position for Back button
-        let list_end_y = back_button_y - 20.0;
-
-        // Define clipping region (Scissor)
-        let (scale, x_off, y_off) =
-            ui::calc_transform(CANONICAL_SCREEN_WIDTH, CANONICAL_SCREEN_HEIGHT);
-        let sc_y_start = list_start_y * scale + y_off;
-
-        // Calculate dynamic height for the view area
-        let view_height_canonical = list_end_y - list_start_y;
-        let sc_h = view_height_canonical * scale;
-
-        let sc_x = 0.0 * scale + x_off; // Full width
-        let sc_w = CANONICAL_SCREEN_WIDTH * scale;
-
-        let screen_h_px = screen_height();
-        let gl_y = screen_h_px - (sc_y_start + sc_h);
-
-        unsafe {
-            get_internal_gl().flush();
-            glScissor(sc_x as i32, gl_y as i32, sc_w as i32, sc_h as i32);
-            glEnable(GL_SCISSOR_TEST);
-        }
-
-        // Draw List
-        let mut list_layout = ui::Layout::new(list_start_y - self.scroll, 15.);
-        self.picked_file = None;
-
-        for file in self.files.iter() {
-            // Culling: Only draw if roughly in view
-            let item_y = list_layout.next();
-            // Draw if the item is at least partially visible
-            if item_y + BUTTON_H > list_start_y
-                && item_y < list_end_y
-                && Button::default()
-                    .draw_centered(
-                        x_mid,
-                        item_y,
-                        BUTTON_W * 1.5,
-                        BUTTON_H,
-                        Some(file),
-                        has_input,
-                    )
-                    .poll()
-            {
-                self.picked_file = Some(file.clone());
+        if !self.gpu_available {
+            ui::Text::new_scaled(ui::TEXT_MID).draw(&tr!("models.no_gpu"), x_mid, layout.next());
+            layout.add(40.);
+            ui::Text::new_scaled(ui::TEXT_SMALL).draw(
+                &tr!("models.no_gpu_hint"),
+                x_mid,
+                layout.next(),
+            );
+        } else {
+            ui::Text::new_scaled(ui::TEXT_MID).draw(&tr!("models.existing"), x_mid, layout.next());
+            layout.add(30.);
+
+            // --- SCROLLABLE AREA START ---
+            let list_start_y = layout.next();
+            let list_end_y = back_button_y - 20.0;
+            let content_height = self.files.len() as f32 * (BUTTON_H + 15.);
+            let (_clip, content_y) = self.scroll.begin(list_start_y, list_end_y, content_height);
+
+            // Draw List
+            let mut list_layout = ui::Layout::new(content_y, 15.);
+            self.picked_file = None;
+
+            for file in self.files.iter() {
+                // Culling: Only draw if roughly in view
+                let item_y = list_layout.next();
+                // Draw if the item is at least partially visible
+                if item_y + BUTTON_H > list_start_y
+                    && item_y < list_end_y
+                    && Button::default()
+                        .draw_centered(
+                            x_mid,
+                            item_y,
+                            BUTTON_W * 1.5,
+                            BUTTON_H,
+                            Some(file),
+                            has_input,
+                        )
+                        .poll()
+                {
+                    self.picked_file = Some(file.clone());
+                }
+                list_layout.add(BUTTON_H);
             }
-            list_layout.add(BUTTON_H);
-        }
-
-        unsafe {
-            get_internal_gl().flush();
-            glDisable(GL_SCISSOR_TEST);
+            // --- SCROLLABLE AREA END ---
         }
-        // --- SCROLLABLE AREA END ---
 
         // Back Button (Fixed)
         self.back_clicked = Button::default()
@@ -169,7 +197,7 @@ impl View for ModelSelect {
                 back_button_y,
                 BUTTON_W,
                 BUTTON_H,
-                Some("Back"),
+                Some(&tr!("common.back")),
                 has_input,
             )
             .poll();
@@ -197,7 +225,7 @@ mod tests {
             file.write_all(b"dummy data").unwrap();
         }
 
-        let menu = ModelSelect::refresh_file_list();
+        let menu = ModelSelect::refresh_file_list(Purpose::Training);
 
         assert!(
             menu.files.contains(&test_filename.to_string()),
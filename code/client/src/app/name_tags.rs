@@ -0,0 +1,136 @@
+//! Nicknames and health bars above tanks.
+
+use crate::app::hud;
+use crate::ui::{TEXT_SMALL, Text};
+use common::game::{MapDefinition, Tank, wall_between};
+use macroquad::prelude::*;
+
+/// Tags are fully visible up to this distance from the viewer's tank, in world units.
+const FADE_START: f32 = 350.0;
+/// Tags are faintest from this distance on.
+const FADE_END: f32 = 750.0;
+/// Opacity of far tags, which still tell tanks apart.
+const FAR_ALPHA: f32 = 0.25;
+const BAR_W: f32 = 50.;
+const BAR_H: f32 = 6.;
+/// Width of the icon before the nicknames of bots, in world units
+const ICON_SIZE: f32 = 12.;
+
+/// Opacity of the tag above `tank` as seen from `viewer`, the local player's tank. Tags
+/// fade with distance; with `fog_of_war`, those of enemies behind walls are hidden.
+pub(crate) fn tag_alpha(
+    viewer: Option<&Tank>,
+    tank: &Tank,
+    map: &MapDefinition,
+    fog_of_war: bool,
+) -> f32 {
+    let Some(viewer) = viewer else {
+        return 1.;
+    };
+    if viewer.player_info.id == tank.player_info.id {
+        return 1.;
+    }
+    if fog_of_war
+        && viewer.player_info.team != tank.player_info.team
+        && wall_between(viewer.position, tank.position, map)
+    {
+        return 0.;
+    }
+    let distance = viewer.position.distance(tank.position);
+    let t = ((distance - FADE_START) / (FADE_END - FADE_START)).clamp(0., 1.);
+    1. - t * (1. - FAR_ALPHA)
+}
+
+/// Draws the health bar and nickname above `tank`, at opacity `alpha`.
+pub(crate) fn draw_tag(tank: &Tank, alpha: f32, (scaling, x_offset, y_offset): (f32, f32, f32)) {
+    if alpha <= 0. {
+        return;
+    }
+    let transform_x = |x: f32| x * scaling + x_offset;
+    let transform_y = |y: f32| y * scaling + y_offset;
+
+    let bar_x = transform_x(tank.position.x - BAR_W / 2.);
+    let bar_y = transform_y(tank.position.y - tank.radius - BAR_H - 15.);
+    draw_rectangle(
+        bar_x,
+        bar_y,
+        BAR_W * scaling,
+        BAR_H * scaling,
+        Color::new(0.1, 0.1, 0.1, 0.8 * alpha),
+    );
+    let health = hud::health_fraction(tank);
+    draw_rectangle(
+        bar_x,
+        bar_y,
+        BAR_W * health * scaling,
+        BAR_H * scaling,
+        Color {
+            a: alpha,
+            ..hud::health_color(health)
+        },
+    );
+
+    let mut text = Text::new_simple(TEXT_SMALL, scaling);
+    text.params.color = Color {
+        a: alpha,
+        ..text.params.color
+    };
+    let nickname = &tank.player_info.nickname;
+    let (x, y) = (
+        transform_x(tank.position.x),
+        transform_y(tank.position.y - tank.radius - BAR_H - 35.),
+    );
+    text.draw_no_scaling(nickname, x, y);
+    if tank.player_info.is_ai {
+        let size = ICON_SIZE * scaling;
+        let width = measure_text(nickname, text.params.font, text.params.font_size, 1.).width;
+        draw_bot_icon(x - width / 2. - size, y, size, text.params.color);
+    }
+}
+
+/// A robot head `size` pixels wide centered on `x`, `y`, which marks bots.
+pub(crate) fn draw_bot_icon(x: f32, y: f32, size: f32, color: Color) {
+    let (w, h) = (size, size * 0.75);
+    let top = y - h / 2. + size * 0.1;
+    draw_line(x, top, x, top - size * 0.25, size * 0.08, color);
+    draw_circle(x, top - size * 0.25, size * 0.08, color);
+    draw_rectangle(x - w / 2., top, w, h, color);
+    let eye = Color::new(0.1, 0.1, 0.1, color.a);
+    for side in [-1., 1.] {
+        draw_circle(x + side * w * 0.22, top + h * 0.4, size * 0.1, eye);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::{MapName, Team};
+    use glam::Vec2;
+
+    fn tank(id: u16, team: Team, position: Vec2) -> Tank {
+        Tank::new(PlayerInfo::new(id, format!("P{}", id), team), position)
+    }
+
+    #[test]
+    fn test_tags_fade_with_distance_and_hide_behind_walls() {
+        let map = MapDefinition::load_name(MapName::Basic);
+        let wall = &map.walls[0];
+        let me = tank(0, Team::Blue, Vec2::new(wall.min.x - 30., wall.min.y + 5.));
+        let near = tank(1, Team::Red, me.position + Vec2::new(0., -100.));
+        let far = tank(2, Team::Blue, me.position + Vec2::new(0., -FADE_END));
+
+        assert_eq!(tag_alpha(None, &far, &map, true), 1.);
+        assert_eq!(tag_alpha(Some(&me), &me, &map, true), 1.);
+        assert_eq!(tag_alpha(Some(&me), &near, &map, false), 1.);
+        assert_eq!(tag_alpha(Some(&me), &far, &map, false), FAR_ALPHA);
+
+        // An enemy on the other side of the wall
+        let behind = tank(3, Team::Red, Vec2::new(wall.max.x + 30., wall.min.y + 5.));
+        assert_eq!(tag_alpha(Some(&me), &behind, &map, true), 0.);
+        assert!(tag_alpha(Some(&me), &behind, &map, false) > 0.);
+        let mut teammate = behind.clone();
+        teammate.player_info.team = Team::Blue;
+        assert!(tag_alpha(Some(&me), &teammate, &map, true) > 0.);
+    }
+}
@@ -1,48 +1,249 @@
+use crate::app::event_log::CLIENT_LOG_PATH;
+use crate::app::graphics_menu::GraphicsMenu;
 use crate::app::{AppContext, Transition, View, ViewId};
-use crate::ui::{
-    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, CANONICAL_SCREEN_MID_Y, Layout, TEXT_LARGE,
-    Text,
-};
-use macroquad::prelude::*;
+use crate::i18n::{self, tr};
+use crate::settings::SETTINGS_PATH;
+use crate::ui::{BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_LARGE, Text};
+use std::path::Path;
+
+#[derive(Clone, Copy)]
+enum OptionsButton {
+    Killcam,
+    LogToFile,
+    Language,
+    Crosshair,
+    Trim,
+    AimAssist,
+    Indicators,
+    FogOfWar,
+    DownloadModels,
+    Graphics,
+    Back,
+}
 
 pub(crate) struct OptionsMenu {
-    back_clicked: bool,
+    button_clicked: Option<OptionsButton>,
+    /// Code and name of every language in `assets/lang/`
+    languages: Vec<(String, String)>,
 }
 
 impl OptionsMenu {
     pub fn new() -> Self {
         OptionsMenu {
-            back_clicked: false,
+            button_clicked: None,
+            languages: i18n::available_languages(Path::new(i18n::LANG_DIR)),
+        }
+    }
+
+    fn on_off(value: bool) -> String {
+        if value {
+            tr!("common.on")
+        } else {
+            tr!("common.off")
         }
     }
 }
 
 impl View for OptionsMenu {
-    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
         let x_mid = CANONICAL_SCREEN_MID_X;
-        let y_mid = CANONICAL_SCREEN_MID_Y;
-        let mut layout = Layout::new(y_mid - 50., 30.);
+        let mut layout = Layout::new(80., 15.);
+
+        Text::new_scaled(TEXT_LARGE).draw(&tr!("options.title"), x_mid, layout.next());
+        layout.add(50.);
 
-        Text::new_scaled(TEXT_LARGE).draw("Options here...", x_mid, layout.next());
-        layout.add(30.);
+        self.button_clicked = None;
 
-        self.back_clicked = Button::default()
+        let settings = &ctx.settings;
+        let language = self
+            .languages
+            .iter()
+            .find(|(code, _)| *code == settings.language)
+            .map_or(settings.language.as_str(), |(_, name)| name.as_str());
+        let options = [
+            (
+                OptionsButton::Killcam,
+                tr!("options.killcam", value = Self::on_off(settings.killcam)),
+            ),
+            (
+                OptionsButton::LogToFile,
+                tr!(
+                    "options.log_to_file",
+                    value = Self::on_off(settings.log_to_file)
+                ),
+            ),
+            (
+                OptionsButton::Language,
+                tr!("options.language", value = language),
+            ),
+            (
+                OptionsButton::Crosshair,
+                tr!(
+                    "options.crosshair",
+                    value = tr!(settings.crosshair.label_key())
+                ),
+            ),
+            (
+                OptionsButton::Trim,
+                tr!("options.trim", value = tr!(settings.trim.label_key())),
+            ),
+            (
+                OptionsButton::AimAssist,
+                tr!(
+                    "options.aim_assist",
+                    value = Self::on_off(settings.aim_assist)
+                ),
+            ),
+            (
+                OptionsButton::Indicators,
+                tr!(
+                    "options.indicators",
+                    value = Self::on_off(settings.indicators)
+                ),
+            ),
+            (
+                OptionsButton::FogOfWar,
+                tr!(
+                    "options.fog_of_war",
+                    value = Self::on_off(settings.fog_of_war)
+                ),
+            ),
+            (
+                OptionsButton::DownloadModels,
+                tr!(
+                    "options.download_models",
+                    value = Self::on_off(settings.download_models)
+                ),
+            ),
+            (OptionsButton::Graphics, tr!("options.graphics")),
+        ];
+
+        // Two columns, filled top to bottom
+        let rows = options.len().div_ceil(2);
+        let column_x = [x_mid - BUTTON_W / 2. - 15., x_mid + BUTTON_W / 2. + 15.];
+        let top = layout.next();
+        for (i, (button, label)) in options.into_iter().enumerate() {
+            let y = top + (i % rows) as f32 * (BUTTON_H + 15.);
+            if Button::default()
+                .draw_centered(
+                    column_x[i / rows],
+                    y,
+                    BUTTON_W,
+                    BUTTON_H,
+                    Some(&label),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_clicked = Some(button);
+            }
+        }
+        for _ in 0..rows {
+            layout.add(BUTTON_H);
+        }
+
+        if Button::default()
             .draw_centered(
                 x_mid,
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Back"),
+                Some(&tr!("common.back")),
                 has_input,
             )
-            .poll();
+            .poll()
+        {
+            self.button_clicked = Some(OptionsButton::Back);
+        }
     }
 
-    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
-        if self.back_clicked {
-            Transition::Pop
-        } else {
-            Transition::None
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        match self.button_clicked.take() {
+            Some(OptionsButton::Killcam) => {
+                ctx.settings.killcam = !ctx.settings.killcam;
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::LogToFile) => {
+                ctx.settings.log_to_file = !ctx.settings.log_to_file;
+                ctx.log.set_file(
+                    ctx.settings
+                        .log_to_file
+                        .then_some(Path::new(CLIENT_LOG_PATH)),
+                );
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::Language) => {
+                // Cycle through the installed languages
+                let current = self
+                    .languages
+                    .iter()
+                    .position(|(code, _)| *code == ctx.settings.language);
+                let next = current.map_or(0, |i| (i + 1) % self.languages.len());
+                if let Some((code, _)) = self.languages.get(next) {
+                    match i18n::set_language(code) {
+                        Ok(()) => {
+                            ctx.settings.language = code.clone();
+                            if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                                eprintln!("Failed to save settings: {}", e);
+                            }
+                        }
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+                Transition::None
+            }
+            Some(OptionsButton::Crosshair) => {
+                ctx.settings.crosshair = ctx.settings.crosshair.next();
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::Trim) => {
+                // Worn from the next connection on
+                ctx.settings.trim = ctx.settings.trim.next();
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::AimAssist) => {
+                ctx.settings.aim_assist = !ctx.settings.aim_assist;
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::Indicators) => {
+                ctx.settings.indicators = !ctx.settings.indicators;
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::FogOfWar) => {
+                ctx.settings.fog_of_war = !ctx.settings.fog_of_war;
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::DownloadModels) => {
+                ctx.settings.download_models = !ctx.settings.download_models;
+                if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+                Transition::None
+            }
+            Some(OptionsButton::Graphics) => Transition::Push(Box::new(GraphicsMenu::new())),
+            Some(OptionsButton::Back) => Transition::Pop,
+            None => Transition::None,
         }
     }
 
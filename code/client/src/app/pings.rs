@@ -0,0 +1,222 @@
+//! Quick chat: a wheel of pings while holding [`PING_KEY`], and markers where teammates
+//! pinged.
+
+use crate::i18n::tr;
+use crate::ui::{TEXT_SMALL, Text, scale_dims, theme::NEON_CYAN};
+use common::protocol::{PING_COOLDOWN, Ping, PingKind};
+use macroquad::prelude::*;
+
+pub(crate) const PING_KEY: KeyCode = KeyCode::V;
+const KINDS: [PingKind; 3] = [PingKind::Attack, PingKind::Defend, PingKind::Help];
+/// How long markers stay on the map, in seconds
+const MARKER_DURATION: f64 = 4.0;
+/// Size of markers, in world units
+const MARKER_RADIUS: f32 = 18.;
+/// Distance of the options from the wheel's center, in UI units
+const WHEEL_RADIUS: f32 = 70.;
+/// Releasing the key with the pointer this close to the wheel's center pings nothing
+const DEAD_ZONE: f32 = 20.;
+
+pub(crate) fn label_key(kind: PingKind) -> &'static str {
+    match kind {
+        PingKind::Attack => "ping.attack",
+        PingKind::Defend => "ping.defend",
+        PingKind::Help => "ping.help",
+    }
+}
+
+fn color(kind: PingKind) -> Color {
+    match kind {
+        PingKind::Attack => RED,
+        PingKind::Defend => NEON_CYAN,
+        PingKind::Help => YELLOW,
+    }
+}
+
+/// Where an option sits on the wheel, from its center.
+fn direction(kind: PingKind) -> Vec2 {
+    match kind {
+        PingKind::Attack => Vec2::new(0., -1.),
+        PingKind::Defend => Vec2::new(-0.866, 0.5),
+        PingKind::Help => Vec2::new(0.866, 0.5),
+    }
+}
+
+/// The option the pointer points at, `offset` pixels from the wheel's center.
+pub(crate) fn choice(offset: Vec2, scaling: f32) -> Option<PingKind> {
+    if offset.length() < DEAD_ZONE * scaling {
+        return None;
+    }
+    KINDS.into_iter().max_by(|a, b| {
+        direction(*a)
+            .dot(offset)
+            .total_cmp(&direction(*b).dot(offset))
+    })
+}
+
+fn ui_scaling() -> f32 {
+    scale_dims(0., 0., 1., 0.).2
+}
+
+pub(crate) struct PingWheel {
+    /// Screen position the wheel opened at, and the spot on the map it pings
+    open: Option<(Vec2, Vec2)>,
+    /// When the server takes the next ping
+    ready_at: f64,
+}
+
+impl PingWheel {
+    pub fn new() -> Self {
+        Self {
+            open: None,
+            ready_at: 0.,
+        }
+    }
+
+    /// Opens the wheel over `world_pos`, the spot under the pointer, when [`PING_KEY`] is
+    /// pressed. Returns the ping picked once the key is released.
+    pub fn update(&mut self, world_pos: Vec2, time: f64) -> Option<(PingKind, Vec2)> {
+        let mouse = Vec2::from(mouse_position());
+        if is_key_pressed(PING_KEY) && time >= self.ready_at {
+            self.open = Some((mouse, world_pos));
+        }
+        if is_key_down(PING_KEY) {
+            return None;
+        }
+        let (center, world_pos) = self.open.take()?;
+        let kind = choice(mouse - center, ui_scaling())?;
+        self.ready_at = time + f64::from(PING_COOLDOWN);
+        Some((kind, world_pos))
+    }
+
+    pub fn draw(&self) {
+        let Some((center, _)) = self.open else {
+            return;
+        };
+        let scaling = ui_scaling();
+        let radius = WHEEL_RADIUS * scaling;
+        draw_circle(
+            center.x,
+            center.y,
+            radius * 1.5,
+            Color::new(0., 0., 0., 0.5),
+        );
+        draw_circle_lines(center.x, center.y, DEAD_ZONE * scaling, 2., LIGHTGRAY);
+
+        let chosen = choice(Vec2::from(mouse_position()) - center, scaling);
+        for kind in KINDS {
+            let pos = center + direction(kind) * radius;
+            let mut text = Text::new_simple(TEXT_SMALL, scaling);
+            if chosen == Some(kind) {
+                draw_circle(
+                    pos.x,
+                    pos.y,
+                    30. * scaling,
+                    Color {
+                        a: 0.4,
+                        ..color(kind)
+                    },
+                );
+                text.params.color = color(kind);
+            }
+            text.draw_no_scaling(&tr!(label_key(kind)), pos.x, pos.y);
+        }
+    }
+}
+
+struct Marker {
+    ping: Ping,
+    nickname: String,
+    time: f64,
+}
+
+/// Teammates' pings, shown for [`MARKER_DURATION`].
+pub(crate) struct PingMarkers {
+    markers: Vec<Marker>,
+}
+
+impl PingMarkers {
+    pub fn new() -> Self {
+        Self {
+            markers: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, ping: Ping, nickname: String, time: f64) {
+        self.markers.retain(|m| time - m.time < MARKER_DURATION);
+        // A player's new ping replaces their old one
+        self.markers.retain(|m| m.ping.player != ping.player);
+        self.markers.push(Marker {
+            ping,
+            nickname,
+            time,
+        });
+    }
+
+    pub fn draw(&self, time: f64, (scaling, x_offset, y_offset): (f32, f32, f32)) {
+        for marker in &self.markers {
+            let age = (time - marker.time) as f32;
+            let progress = age / MARKER_DURATION as f32;
+            if !(0.0..1.).contains(&progress) {
+                continue;
+            }
+            let x = marker.ping.world_pos.x * scaling + x_offset;
+            let y = marker.ping.world_pos.y * scaling + y_offset;
+            let radius = MARKER_RADIUS * scaling;
+            let color = Color {
+                a: 1. - progress * progress,
+                ..color(marker.ping.kind)
+            };
+
+            // A ring spreading out every second draws the eye
+            let pulse = age.fract();
+            draw_circle_lines(
+                x,
+                y,
+                radius * (1. + pulse),
+                2.,
+                Color {
+                    a: color.a * (1. - pulse),
+                    ..color
+                },
+            );
+            match marker.ping.kind {
+                PingKind::Attack => {
+                    let arm = radius * 0.7;
+                    draw_line(x - arm, y - arm, x + arm, y + arm, 3., color);
+                    draw_line(x - arm, y + arm, x + arm, y - arm, 3., color);
+                }
+                PingKind::Defend => {
+                    draw_circle_lines(x, y, radius * 0.7, 3., color);
+                    draw_circle(x, y, radius * 0.25, color);
+                }
+                PingKind::Help => {
+                    draw_line(x, y - radius * 0.8, x, y + radius * 0.2, 3., color);
+                    draw_circle(x, y + radius * 0.6, 2.5, color);
+                }
+            }
+
+            let mut text = Text::new_simple(TEXT_SMALL, scaling);
+            text.params.color = Color {
+                a: color.a,
+                ..text.params.color
+            };
+            text.draw_no_scaling(&marker.nickname, x, y + radius * 1.8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheel_choice_follows_the_pointer() {
+        assert_eq!(choice(Vec2::new(0., -50.), 1.), Some(PingKind::Attack));
+        assert_eq!(choice(Vec2::new(-40., 30.), 1.), Some(PingKind::Defend));
+        assert_eq!(choice(Vec2::new(40., 30.), 1.), Some(PingKind::Help));
+        assert_eq!(choice(Vec2::new(5., 5.), 1.), None);
+        // The dead zone grows with the UI
+        assert_eq!(choice(Vec2::new(0., -30.), 2.), None);
+    }
+}
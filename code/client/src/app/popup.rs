@@ -1,4 +1,5 @@
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
 use crate::ui::{
     BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, CANONICAL_SCREEN_MID_Y, Layout, TEXT_LARGE,
     Text,
@@ -43,7 +44,7 @@ impl View for Popup {
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Okay"),
+                Some(&tr!("common.okay")),
                 has_input,
             )
             .poll();
@@ -0,0 +1,192 @@
+//! Numbers the inputs sent to the server, to move the local tank ahead of the server's
+//! snapshots and to notice when inputs stop getting through.
+//!
+//! An input goes out once a server tick and moves the local tank right away. When a
+//! snapshot comes, the tank is put back where the server has it and moved again by the
+//! inputs the server hasn't processed yet, so a wrong guess lasts until the next one.
+
+use common::game::{InputPayload, MapDefinition, Tank, apply_player_physics};
+use std::collections::VecDeque;
+
+/// The server's tick; every input it processes moves the tank for one
+const TICK_DT: f32 = 1.0 / 60.0;
+/// An input unacknowledged for this long (in seconds) means the connection is unstable
+const UNSTABLE_AFTER: f64 = 0.5;
+/// Inputs kept for prediction at most; older ones are dropped
+const MAX_PENDING: usize = 120;
+
+struct PendingInput {
+    seq: u32,
+    input: InputPayload,
+    sent_at: f64,
+}
+
+/// The inputs sent to the server that it hasn't processed yet.
+pub(crate) struct InputTracker {
+    next_seq: u32,
+    /// Seconds since the last input went out
+    since_sent: f32,
+    /// Oldest first
+    pending: VecDeque<PendingInput>,
+}
+
+impl InputTracker {
+    pub fn new() -> Self {
+        Self {
+            next_seq: 0,
+            since_sent: 0.0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Whether an input is due, `dt` seconds after the last call. Inputs go out once a
+    /// tick, and at most once a frame when frames are longer.
+    pub fn due(&mut self, dt: f32) -> bool {
+        self.since_sent += dt;
+        if self.since_sent < TICK_DT {
+            return false;
+        }
+        self.since_sent = (self.since_sent - TICK_DT).min(TICK_DT);
+        true
+    }
+
+    /// Remembers `input`, sent at `time`, until the server acknowledges it. Returns its
+    /// sequence number.
+    pub fn sent(&mut self, input: InputPayload, time: f64) -> u32 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if self.pending.len() == MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(PendingInput {
+            seq,
+            input,
+            sent_at: time,
+        });
+        seq
+    }
+
+    /// Forgets the inputs up to `seq`, which the server processed.
+    pub fn acknowledge(&mut self, seq: u32) {
+        while self.pending.front().is_some_and(|p| p.seq <= seq) {
+            self.pending.pop_front();
+        }
+    }
+
+    /// Moves `tank`, as the server last saw it, by the inputs the server hasn't processed
+    /// yet. Other tanks and the server's move checks are not taken into account.
+    pub fn predict(&self, tank: &mut Tank, map: &MapDefinition) {
+        for pending in &self.pending {
+            apply_player_physics(tank, &pending.input, map, TICK_DT);
+        }
+    }
+
+    /// Moves `tank` by the input sent last, as the server will once it gets it.
+    pub fn apply_latest(&self, tank: &mut Tank, map: &MapDefinition) {
+        if let Some(latest) = self.pending.back() {
+            apply_player_physics(tank, &latest.input, map, TICK_DT);
+        }
+    }
+
+    /// Whether some input has waited for the server longer than it should.
+    pub fn unstable(&self, time: f64) -> bool {
+        self.pending
+            .front()
+            .is_some_and(|p| time - p.sent_at > UNSTABLE_AFTER)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::{MapName, Team};
+    use glam::Vec2;
+
+    fn moving_right() -> InputPayload {
+        InputPayload {
+            move_axis: Vec2::X,
+            aim_pos: Vec2::new(1000.0, 0.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_acknowledged_inputs_are_not_replayed() {
+        let map = MapDefinition::load_name(MapName::Basic);
+        let start = Vec2::new(400.0, 400.0);
+        let mut tracker = InputTracker::new();
+        for seq in 0..3 {
+            assert_eq!(tracker.sent(moving_right(), 0.0), seq);
+        }
+
+        let server_tank = Tank::new(PlayerInfo::new(0, "me".into(), Team::Blue), start);
+        let mut predicted = server_tank.clone();
+        tracker.predict(&mut predicted, &map);
+        let three_ticks = predicted.position.x - start.x;
+        assert!(three_ticks > 0.0);
+
+        // The server moved the tank by the first two inputs itself
+        tracker.acknowledge(1);
+        let mut predicted = server_tank;
+        tracker.predict(&mut predicted, &map);
+        assert!((predicted.position.x - start.x - three_ticks / 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_reconciling_keeps_the_tank_where_it_was_predicted() {
+        let map = MapDefinition::load_name(MapName::Basic);
+        let start = Vec2::new(400.0, 400.0);
+        let inputs =
+            [Vec2::X, Vec2::X, Vec2::Y, Vec2::new(-1.0, 1.0)].map(|move_axis| InputPayload {
+                move_axis,
+                ..moving_right()
+            });
+        let mut tracker = InputTracker::new();
+        let mut local = Tank::new(PlayerInfo::new(0, "me".into(), Team::Blue), start);
+        let mut server_tank = local.clone();
+        for (i, input) in inputs.iter().enumerate() {
+            tracker.sent(input.clone(), 0.0);
+            tracker.apply_latest(&mut local, &map);
+            // The server got through the first two
+            if i < 2 {
+                apply_player_physics(&mut server_tank, input, &map, TICK_DT);
+            }
+        }
+
+        tracker.acknowledge(1);
+        let mut reconciled = server_tank;
+        tracker.predict(&mut reconciled, &map);
+        assert!(reconciled.position.distance(local.position) < 1e-3);
+        assert_ne!(local.position, start);
+    }
+
+    #[test]
+    fn test_inputs_go_out_once_a_tick() {
+        let mut tracker = InputTracker::new();
+        let sent = (0..144).filter(|_| tracker.due(1.0 / 144.0)).count();
+        assert!((59..=60).contains(&sent), "{}", sent);
+
+        // Slow frames don't send bursts of inputs
+        let mut tracker = InputTracker::new();
+        let sent = (0..30).filter(|_| tracker.due(1.0 / 30.0)).count();
+        assert_eq!(sent, 30);
+        // A long stall leaves one tick behind at most
+        assert!(tracker.due(0.5));
+        assert!(tracker.due(0.0));
+        assert!(!tracker.due(0.0));
+    }
+
+    #[test]
+    fn test_late_acknowledgements_mean_an_unstable_connection() {
+        let mut tracker = InputTracker::new();
+        assert!(!tracker.unstable(10.0));
+        tracker.sent(moving_right(), 0.0);
+        let seq = tracker.sent(moving_right(), 0.4);
+        assert!(!tracker.unstable(0.45));
+        assert!(tracker.unstable(0.6));
+
+        tracker.acknowledge(seq);
+        assert!(!tracker.unstable(0.6));
+    }
+}
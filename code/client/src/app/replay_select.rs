@@ -0,0 +1,168 @@
+use crate::app::popup::Popup;
+use crate::app::replay_view::ReplayView;
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::ui::{self};
+use crate::ui::{BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_WIDTH};
+use common::game::replay::{REPLAY_EXTENSION, Replay};
+use macroquad::prelude::*;
+use std::fs;
+use std::path::Path;
+
+pub(crate) const REPLAYS_DIR: &str = "assets/replays";
+
+pub(crate) struct ReplaySelect {
+    files: Vec<String>,
+    scroll: ui::ScrollArea,
+    back_clicked: bool,
+    picked_file: Option<String>,
+}
+
+impl ReplaySelect {
+    pub fn new() -> Self {
+        Self {
+            files: Self::list_replays(Path::new(REPLAYS_DIR)),
+            scroll: ui::ScrollArea::new(),
+            back_clicked: false,
+            picked_file: None,
+        }
+    }
+
+    /// Names of the replay files in `dir`, which is created if it is missing.
+    fn list_replays(dir: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        let _ = fs::create_dir_all(dir);
+
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                if let Ok(ft) = entry.file_type()
+                    && ft.is_file()
+                    && let Some(fname) = entry.file_name().to_str()
+                    && Path::new(fname).extension().and_then(|e| e.to_str())
+                        == Some(REPLAY_EXTENSION)
+                {
+                    files.push(fname.to_string());
+                }
+            }
+        }
+        // Replays are named by their timestamp, so show the newest first.
+        files.sort_by(|a, b| b.cmp(a));
+        files
+    }
+}
+
+impl View for ReplaySelect {
+    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+        self.scroll.update();
+
+        if self.back_clicked {
+            self.back_clicked = false;
+            return Transition::Pop;
+        }
+
+        if let Some(fname) = self.picked_file.take() {
+            let path = Path::new(REPLAYS_DIR).join(&fname);
+            return match Replay::load(&path) {
+                Ok(replay) => Transition::Push(Box::new(ReplayView::new(fname, replay))),
+                Err(e) => {
+                    Transition::Push(Box::new(Popup::new(tr!("replays.open_failed", error = e))))
+                }
+            };
+        }
+
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_WIDTH / 2.;
+        let mut layout = ui::Layout::new(80., 15.);
+
+        ui::Text::new_title().draw(&tr!("replays.title"), x_mid, layout.next());
+        layout.add(60.);
+
+        let label = if self.files.is_empty() {
+            tr!("replays.empty")
+        } else {
+            tr!("replays.saved")
+        };
+        ui::Text::new_scaled(ui::TEXT_MID).draw(&label, x_mid, layout.next());
+        layout.add(30.);
+
+        // --- SCROLLABLE AREA START ---
+        let list_start_y = layout.next();
+        let back_button_y = CANONICAL_SCREEN_HEIGHT - 80.0;
+        let list_end_y = back_button_y - 20.0;
+        let content_height = self.files.len() as f32 * (BUTTON_H + 15.);
+        let (clip, content_y) = self.scroll.begin(list_start_y, list_end_y, content_height);
+
+        let mut list_layout = ui::Layout::new(content_y, 15.);
+        self.picked_file = None;
+
+        for file in self.files.iter() {
+            let item_y = list_layout.next();
+            if item_y + BUTTON_H > list_start_y
+                && item_y < list_end_y
+                && Button::default()
+                    .draw_centered(
+                        x_mid,
+                        item_y,
+                        BUTTON_W * 1.5,
+                        BUTTON_H,
+                        Some(file),
+                        has_input,
+                    )
+                    .poll()
+            {
+                self.picked_file = Some(file.clone());
+            }
+            list_layout.add(BUTTON_H);
+        }
+        drop(clip);
+        // --- SCROLLABLE AREA END ---
+
+        self.back_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                back_button_y,
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("common.back")),
+                has_input,
+            )
+            .poll();
+    }
+
+    fn visible_again(&mut self, _ctx: &mut AppContext) {
+        self.files = Self::list_replays(Path::new(REPLAYS_DIR));
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::ReplaySelect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+
+    #[test]
+    fn test_list_replays_filters_by_extension() {
+        let dir = std::env::temp_dir().join("replay_select_test");
+        let replay_name = format!("test_replay_12345.{}", REPLAY_EXTENSION);
+        let other_name = "test_replay_12345.txt";
+
+        let _ = fs::create_dir_all(&dir);
+        File::create(dir.join(&replay_name))
+            .and_then(|mut f| f.write_all(b"dummy"))
+            .expect("Failed to create test file");
+        File::create(dir.join(other_name)).expect("Failed to create test file");
+
+        let files = ReplaySelect::list_replays(&dir);
+
+        assert_eq!(files, vec![replay_name]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
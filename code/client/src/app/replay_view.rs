@@ -0,0 +1,225 @@
+use crate::app::game::Game;
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::presence::Presence;
+use crate::ui::{self, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_WIDTH, NEON_CYAN, TEXT_SMALL};
+use common::game::replay::{Replay, ReplayPlayer};
+use macroquad::prelude::*;
+
+const SEEK_STEP_SECS: f32 = 5.0;
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const DEFAULT_SPEED_IDX: usize = 2;
+
+pub(crate) struct ReplayView {
+    name: String,
+    player: ReplayPlayer,
+    paused: bool,
+    speed_idx: usize,
+    /// Real time (already scaled by speed) not yet consumed by replay ticks.
+    time_accumulator: f32,
+}
+
+impl ReplayView {
+    pub fn new(name: String, replay: Replay) -> Self {
+        Self {
+            name,
+            player: ReplayPlayer::new(replay),
+            paused: false,
+            speed_idx: DEFAULT_SPEED_IDX,
+            time_accumulator: 0.0,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        SPEEDS[self.speed_idx]
+    }
+
+    /// Consumes `dt` seconds of playback time, stepping as many recorded ticks as fit.
+    fn advance(&mut self, dt: f32) {
+        self.time_accumulator += dt * self.speed();
+        while let Some(next_dt) = self.next_tick_dt() {
+            if self.time_accumulator < next_dt {
+                break;
+            }
+            self.time_accumulator -= next_dt;
+            self.player.step();
+        }
+
+        if self.player.is_finished() {
+            self.time_accumulator = 0.0;
+        }
+    }
+
+    fn next_tick_dt(&self) -> Option<f32> {
+        if self.player.is_finished() {
+            None
+        } else {
+            Some(self.player.tick_dt(self.player.current_tick()))
+        }
+    }
+
+    fn seek_by(&mut self, secs: f32) {
+        let target = (self.player.elapsed() + secs).max(0.0);
+        self.player.seek_time(target);
+        self.time_accumulator = 0.0;
+    }
+
+    fn format_time(secs: f32) -> String {
+        let secs = secs.max(0.0) as u32;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl View for ReplayView {
+    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+        if is_key_pressed(KeyCode::Escape) {
+            return Transition::Pop;
+        }
+        if is_key_pressed(KeyCode::Space) {
+            if self.player.is_finished() {
+                // Restart from the beginning when pressing play at the end.
+                self.player.seek(0);
+                self.paused = false;
+            } else {
+                self.paused = !self.paused;
+            }
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.seek_by(-SEEK_STEP_SECS);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.seek_by(SEEK_STEP_SECS);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.speed_idx = (self.speed_idx + 1).min(SPEEDS.len() - 1);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.speed_idx = self.speed_idx.saturating_sub(1);
+        }
+
+        if !self.paused {
+            self.advance(get_frame_time());
+        }
+
+        Transition::None
+    }
+
+    fn draw(&mut self, _ctx: &AppContext, _has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_WIDTH / 2.;
+
+        Game::draw_game_board(self.player.engine(), None, false);
+
+        let state = if self.player.is_finished() {
+            tr!("replays.ended")
+        } else if self.paused {
+            tr!("replays.paused")
+        } else {
+            tr!("replays.playing")
+        };
+        ui::Text::new_scaled(TEXT_SMALL).draw(
+            &tr!(
+                "replays.hud",
+                name = self.name,
+                state = state,
+                speed = self.speed()
+            ),
+            x_mid,
+            30.,
+        );
+
+        // Progress bar
+        let bar_w = CANONICAL_SCREEN_WIDTH * 0.6;
+        let bar_h = 8.;
+        let bar_x = x_mid - bar_w / 2.;
+        let bar_y = CANONICAL_SCREEN_HEIGHT - 40.;
+        let duration = self.player.duration();
+        let progress = if duration > 0.0 {
+            (self.player.elapsed() / duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let (scale, x_off, y_off) =
+            ui::calc_transform(CANONICAL_SCREEN_WIDTH, CANONICAL_SCREEN_HEIGHT);
+        let sx = bar_x * scale + x_off;
+        let sy = bar_y * scale + y_off;
+        draw_rectangle(
+            sx,
+            sy,
+            bar_w * scale,
+            bar_h * scale,
+            Color::new(1.0, 1.0, 1.0, 0.2),
+        );
+        draw_rectangle(sx, sy, bar_w * progress * scale, bar_h * scale, NEON_CYAN);
+
+        ui::Text::new_scaled(TEXT_SMALL).draw(
+            &format!(
+                "{} / {}",
+                Self::format_time(self.player.elapsed()),
+                Self::format_time(duration)
+            ),
+            x_mid,
+            bar_y - 15.,
+        );
+    }
+
+    fn presence(&self, _ctx: &AppContext) -> Option<Presence> {
+        Some(Presence::WatchingReplay)
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::ReplayView
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::engine::GameEngine;
+    use common::game::player::PlayerInfo;
+    use common::game::replay::ReplayRecorder;
+    use common::net::protocol::{MapDefinition, Tank, Team};
+    use std::collections::HashMap;
+
+    fn make_replay(ticks: usize, dt: f32) -> Replay {
+        let mut engine = GameEngine::new(MapDefinition::load());
+        engine.tanks.push(Tank::new(
+            PlayerInfo::new(0, "Player".into(), Team::Blue),
+            (200.0, 100.0).into(),
+        ));
+        let mut recorder = ReplayRecorder::new(&engine, 0);
+        for _ in 0..ticks {
+            recorder.record(dt, &HashMap::new());
+        }
+        recorder.finish()
+    }
+
+    #[test]
+    fn test_advance_respects_speed() {
+        let mut view = ReplayView::new("test".into(), make_replay(100, 0.125));
+
+        view.advance(1.25);
+        assert_eq!(view.player.current_tick(), 10);
+
+        view.speed_idx = SPEEDS.iter().position(|&s| s == 2.0).unwrap();
+        view.advance(1.25);
+        assert_eq!(view.player.current_tick(), 30);
+    }
+
+    #[test]
+    fn test_seek_by_clamps_to_bounds() {
+        let mut view = ReplayView::new("test".into(), make_replay(20, 0.125));
+
+        view.seek_by(-SEEK_STEP_SECS);
+        assert_eq!(view.player.current_tick(), 0);
+
+        view.seek_by(SEEK_STEP_SECS);
+        assert!(view.player.is_finished());
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(ReplayView::format_time(0.0), "0:00");
+        assert_eq!(ReplayView::format_time(75.4), "1:15");
+    }
+}
@@ -1,5 +1,8 @@
+use crate::app::event_log::LogCategory;
 use crate::app::popup::Popup;
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::server::RequestId;
 use crate::ui::{
     BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, CANONICAL_SCREEN_MID_Y, Layout, TEXT_LARGE,
     Text,
@@ -29,8 +32,10 @@ impl Timer {
     }
 }
 
+/// Waits for the reply to one request, then runs the success action or shows the error.
 pub(crate) struct RequestView {
     text: String,
+    request: RequestId,
     success_action: Option<RequestAction>,
     abort_clicked: bool,
     abort_show_timer: Timer,
@@ -38,19 +43,14 @@ pub(crate) struct RequestView {
 }
 
 impl RequestView {
-    pub fn new_open_view(text: String, success_view: Box<dyn View>) -> Self {
-        RequestView {
-            text,
-            success_action: Some(Box::new(|_| Transition::PopAnd(success_view))),
-            abort_clicked: false,
-            abort_show_timer: Timer::new(TIME_TO_SHOW_ABORT),
-            time_to_show_timer: Timer::new(TIME_TO_SHOW_BEFORE_ACTION),
-        }
-    }
-
-    pub fn new_transition(text: String, success_transition: Transition) -> Self {
+    pub fn new_transition(
+        text: String,
+        request: RequestId,
+        success_transition: Transition,
+    ) -> Self {
         RequestView {
             text,
+            request,
             success_action: Some(Box::new(|_| success_transition)),
             abort_clicked: false,
             abort_show_timer: Timer::new(TIME_TO_SHOW_ABORT),
@@ -58,9 +58,10 @@ impl RequestView {
         }
     }
 
-    pub fn new_action(text: String, success_action: RequestAction) -> Self {
+    pub fn new_action(text: String, request: RequestId, success_action: RequestAction) -> Self {
         RequestView {
             text,
+            request,
             success_action: Some(success_action),
             abort_clicked: false,
             abort_show_timer: Timer::new(TIME_TO_SHOW_ABORT),
@@ -94,7 +95,7 @@ impl View for RequestView {
                     layout.next(),
                     BUTTON_W,
                     BUTTON_H,
-                    Some("Abort"),
+                    Some(&tr!("common.abort")),
                     has_input,
                 )
                 .poll();
@@ -108,13 +109,16 @@ impl View for RequestView {
         }
 
         // Check for server request response
-        if let Some(resp) = ctx.server.take_request_response() {
+        if let Some(resp) = ctx.server.take_reply(self.request) {
             return match resp {
                 // Request was successful
                 Ok(_) => self.success_action.take().unwrap()(ctx),
 
                 // Request failed: show the reason why
-                Err(reason) => Transition::PopAnd(Box::new(Popup::new(reason))),
+                Err(reason) => {
+                    ctx.log.push(LogCategory::Error, &reason);
+                    Transition::PopAnd(Box::new(Popup::new(reason)))
+                }
             };
         }
 
@@ -0,0 +1,175 @@
+use crate::i18n::tr;
+use crate::ui::{
+    CANONICAL_SCREEN_MID_X, CANONICAL_SCREEN_MID_Y, TEXT_HUGE, TEXT_LARGE, Text, theme::TEXT_COLOR,
+};
+use macroquad::prelude::*;
+
+/// How long round banners stay up (in seconds).
+const BANNER_DURATION: f64 = 2.5;
+const BANNER_FADE: f64 = 0.4;
+/// How long "GO!" stays up after the round starts (in seconds).
+const GO_DURATION: f64 = 0.8;
+/// Only the last few seconds of the countdown are shown full-screen.
+const COUNTDOWN_SHOWN_FROM: u64 = 3;
+
+struct Banner {
+    title: String,
+    subtitle: Option<String>,
+    start_time: f64,
+}
+
+/// Full-screen countdown, "GO!" and round banners, driven by game events.
+pub(crate) struct RoundOverlay {
+    banner: Option<Banner>,
+    /// Number currently counting down and when it appeared
+    count: Option<(u64, f64)>,
+    go_time: Option<f64>,
+}
+
+impl RoundOverlay {
+    pub fn new() -> Self {
+        Self {
+            banner: None,
+            count: None,
+            go_time: None,
+        }
+    }
+
+    pub fn countdown_started(&mut self, round: u8, score: Option<String>, time: f64) {
+        self.banner = Some(Banner {
+            title: tr!("game.round_banner", round = round),
+            subtitle: score,
+            start_time: time,
+        });
+        self.go_time = None;
+    }
+
+    pub fn round_started(&mut self, time: f64) {
+        self.count = None;
+        self.go_time = Some(time);
+    }
+
+    pub fn wave_started(&mut self, wave: u8, enemies: u8, time: f64) {
+        self.banner = Some(Banner {
+            title: tr!("game.wave_banner", wave = wave),
+            subtitle: Some(tr!("game.wave_enemies", enemies = enemies)),
+            start_time: time,
+        });
+    }
+
+    /// `summary` is how the local player did; `winner` is `None` after a drawn round.
+    pub fn round_ended(&mut self, winner: Option<&str>, summary: Option<String>, time: f64) {
+        self.banner = Some(Banner {
+            title: match winner {
+                Some(team) => tr!("game.round_won", team = team),
+                None => tr!("game.round_draw"),
+            },
+            subtitle: summary,
+            start_time: time,
+        });
+    }
+
+    /// Follows the seconds left in the countdown, `None` outside of it.
+    pub fn set_countdown(&mut self, seconds_left: Option<u64>, time: f64) {
+        match seconds_left {
+            Some(count) if count <= COUNTDOWN_SHOWN_FROM => {
+                if self.count.map(|(c, _)| c) != Some(count) {
+                    self.count = Some((count, time));
+                }
+            }
+            _ => self.count = None,
+        }
+    }
+
+    pub fn draw(&self, time: f64) {
+        if let Some(banner) = &self.banner {
+            let alpha = banner_alpha(time - banner.start_time);
+            if alpha > 0. {
+                draw_rectangle(
+                    0.,
+                    screen_height() * 0.2,
+                    screen_width(),
+                    screen_height() * 0.16,
+                    Color::new(0., 0., 0., 0.6 * alpha),
+                );
+                let mut text = Text::new_scaled(TEXT_HUGE);
+                text.params.color = Color { a: alpha, ..WHITE };
+                text.draw(&banner.title, CANONICAL_SCREEN_MID_X, 170.);
+                if let Some(subtitle) = &banner.subtitle {
+                    let mut text = Text::new_scaled(TEXT_LARGE);
+                    text.params.color = Color {
+                        a: alpha,
+                        ..TEXT_COLOR
+                    };
+                    text.draw(subtitle, CANONICAL_SCREEN_MID_X, 220.);
+                }
+            }
+        }
+
+        let popped = match (self.count, self.go_time) {
+            (Some((count, since)), _) => Some((count.to_string(), time - since)),
+            (None, Some(since)) if time - since < GO_DURATION => {
+                Some((tr!("game.go"), (time - since) / GO_DURATION))
+            }
+            _ => None,
+        };
+        if let Some((label, elapsed)) = popped {
+            let (scale, alpha) = pop_in(elapsed);
+            draw_rectangle(
+                0.,
+                0.,
+                screen_width(),
+                screen_height(),
+                Color::new(0., 0., 0., 0.25 * alpha),
+            );
+            let mut text = Text::new_scaled((TEXT_HUGE as f32 * 2. * scale) as u16);
+            text.params.color = Color { a: alpha, ..YELLOW };
+            text.draw(&label, CANONICAL_SCREEN_MID_X, CANONICAL_SCREEN_MID_Y);
+        }
+    }
+}
+
+/// Size multiplier and opacity of a number that appeared `elapsed` seconds ago: it
+/// starts big and shrinks into place while fading over one second.
+fn pop_in(elapsed: f64) -> (f32, f32) {
+    let t = elapsed.clamp(0., 1.) as f32;
+    (1. + 0.8 * (1. - t).powi(3), 1. - t * t)
+}
+
+/// Opacity of a banner shown `elapsed` seconds ago.
+fn banner_alpha(elapsed: f64) -> f32 {
+    let fade_in = elapsed / BANNER_FADE;
+    let fade_out = (BANNER_DURATION - elapsed) / BANNER_FADE;
+    fade_in.min(fade_out).clamp(0., 1.) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pop_in_and_banner_fade() {
+        assert_eq!(pop_in(0.), (1.8, 1.));
+        assert_eq!(pop_in(1.), (1., 0.));
+        assert_eq!(pop_in(5.), (1., 0.));
+
+        assert_eq!(banner_alpha(0.), 0.);
+        assert_eq!(banner_alpha(1.), 1.);
+        assert_eq!(banner_alpha(BANNER_DURATION), 0.);
+    }
+
+    #[test]
+    fn test_countdown_follows_last_seconds() {
+        let mut overlay = RoundOverlay::new();
+        overlay.set_countdown(Some(5), 0.);
+        assert!(overlay.count.is_none());
+
+        overlay.set_countdown(Some(3), 2.);
+        overlay.set_countdown(Some(3), 2.5);
+        assert_eq!(overlay.count, Some((3, 2.)));
+
+        overlay.round_started(5.);
+        assert!(overlay.count.is_none());
+        assert_eq!(overlay.go_time, Some(5.));
+    }
+}
@@ -0,0 +1,109 @@
+//! Everyone's shots over the match, while holding Tab and on the winner screen.
+
+use crate::app::name_tags;
+use crate::i18n::tr;
+use crate::ui::{
+    CANONICAL_SCREEN_MID_X, TEXT_MID, TEXT_SMALL, Text, TextHorizontalPositioning,
+    TextVerticalPositioning, default_text_params, scale_dims,
+    theme::{NEON_CYAN, NEON_PINK},
+};
+use common::protocol::{PlayerId, PlayerStats, Team};
+use macroquad::prelude::*;
+
+const WIDTH: f32 = 640.;
+const TOP: f32 = 130.;
+const ROW_H: f32 = 26.;
+/// Right edges of the number columns, from the left edge of the board
+const COLUMNS: [f32; 4] = [330., 410., 510., 610.];
+
+pub(crate) fn format_accuracy(stats: &PlayerStats) -> String {
+    stats.accuracy().map_or("-".to_string(), |accuracy| {
+        format!("{:.0}%", accuracy * 100.)
+    })
+}
+
+/// One line about the local player, for the end of a round.
+pub(crate) fn summary_line(stats: &[PlayerStats], me: PlayerId) -> Option<String> {
+    stats.iter().find(|s| s.player_info.id == me).map(|s| {
+        tr!(
+            "game.round_stats",
+            accuracy = format_accuracy(s),
+            damage = s.damage_dealt.round()
+        )
+    })
+}
+
+pub(crate) fn draw_scoreboard(stats: &[PlayerStats], me: PlayerId) {
+    let left = CANONICAL_SCREEN_MID_X - WIDTH / 2.;
+    let height = ROW_H * (stats.len() as f32 + 2.5);
+    let (x, y, w, h) = scale_dims(left - 20., TOP - 20., WIDTH + 40., height + 20.);
+    draw_rectangle(x, y, w, h, Color::new(0., 0., 0., 0.7));
+
+    Text::new_scaled(TEXT_MID).draw(&tr!("scoreboard.title"), CANONICAL_SCREEN_MID_X, TOP);
+
+    let text = |horizontal, color| {
+        Text::new(
+            TextParams {
+                font_size: TEXT_SMALL,
+                color,
+                ..default_text_params()
+            },
+            TextVerticalPositioning::CenterConsistent,
+            horizontal,
+        )
+    };
+    let header = text(TextHorizontalPositioning::Right, LIGHTGRAY);
+    let y = TOP + ROW_H * 1.2;
+    text(TextHorizontalPositioning::Left, LIGHTGRAY).draw(&tr!("scoreboard.player"), left, y);
+    for (column, key) in COLUMNS.iter().zip([
+        "scoreboard.shots",
+        "scoreboard.hits",
+        "scoreboard.accuracy",
+        "scoreboard.damage",
+    ]) {
+        header.draw(&tr!(key), left + column, y);
+    }
+
+    for (row, player) in stats.iter().enumerate() {
+        let y = TOP + ROW_H * (row as f32 + 2.2);
+        let info = &player.player_info;
+        let color = if info.id == me {
+            YELLOW
+        } else if info.team == Team::Blue {
+            NEON_CYAN
+        } else {
+            NEON_PINK
+        };
+        text(TextHorizontalPositioning::Left, color).draw(&info.nickname, left, y);
+        if info.is_ai {
+            let (x, y, size, _) = scale_dims(left - 10., y, 12., 0.);
+            name_tags::draw_bot_icon(x, y, size, color);
+        }
+        let values = [
+            player.shots_fired.to_string(),
+            player.shots_hit.to_string(),
+            format_accuracy(player),
+            format!("{:.0}", player.damage_dealt),
+        ];
+        let cell = text(TextHorizontalPositioning::Right, color);
+        for (column, value) in COLUMNS.iter().zip(values) {
+            cell.draw(&value, left + column, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+
+    #[test]
+    fn test_accuracy_format() {
+        let mut stats = PlayerStats::new(PlayerInfo::new(1, "P1".into(), Team::Blue));
+        assert_eq!(format_accuracy(&stats), "-");
+        stats.shots_fired = 3;
+        stats.shots_hit = 2;
+        assert_eq!(format_accuracy(&stats), "67%");
+        assert!(summary_line(&[stats], 2).is_none());
+    }
+}
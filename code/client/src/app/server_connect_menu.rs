@@ -1,21 +1,49 @@
-use crate::app::request_view::RequestView;
+use crate::app::popup::Popup;
 use crate::app::server_lobby::ServerLobby;
 use crate::app::{AppContext, Transition, View, ViewId};
-use crate::server::ClientState;
+use crate::i18n::tr;
+use crate::join_link::JoinLink;
+use crate::server::{ClientState, ConnectionState, RequestId};
+use crate::server_probe::{Probe, ServerProbes};
+use crate::settings::{SETTINGS_PATH, ServerEntry};
 use crate::ui::{
-    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_MID, Text, TextField,
+    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, ScrollArea, TEXT_MID, TEXT_SMALL,
+    Text, TextField,
 };
+use common::protocol::GameCode;
+use std::path::Path;
+use std::time::Instant;
+
+/// Center of the saved servers column, right of the connect form.
+const SERVERS_X: f32 = 1030.;
+/// Bottom of the scrolling list of saved servers.
+const SERVERS_END_Y: f32 = 500.;
+const REMOVE_W: f32 = 50.;
+const LABEL_W: f32 = 100.;
 
 #[derive(Copy, Clone)]
 enum ServerConnectButtons {
     Connect,
+    QuickConnect,
+    Cancel,
     Back,
+    /// Fill in the address of the saved server at this index
+    PickServer(usize),
+    RemoveServer(usize),
+    SaveServer,
 }
 
 pub(crate) struct ServerConnectMenu {
     button_pressed: Option<ServerConnectButtons>,
     servername_field: TextField,
     username_field: TextField,
+    label_field: TextField,
+    relay_field: TextField,
+    connect_request: Option<RequestId>,
+    probes: ServerProbes,
+    servers_scroll: ScrollArea,
+    /// Game of the link the client was opened with, joined once connected
+    join_code: Option<GameCode>,
 }
 
 impl ServerConnectMenu {
@@ -24,70 +52,365 @@ impl ServerConnectMenu {
             button_pressed: None,
             servername_field: TextField::new_simple(30),
             username_field: TextField::new_simple(20),
+            label_field: TextField::new_simple(12),
+            relay_field: TextField::new_simple(30),
+            connect_request: None,
+            probes: ServerProbes::new(),
+            servers_scroll: ScrollArea::new(),
+            join_code: None,
+        }
+    }
+
+    /// The menu with the server of `link` filled in, joining its game once connected.
+    pub fn with_link(link: JoinLink) -> Self {
+        let mut menu = Self::new();
+        menu.servername_field.set_text(&link.server);
+        menu.join_code = Some(link.code);
+        menu
+    }
+
+    /// The saved servers with their latencies, Quick Connect and the form to save the typed
+    /// address under a label, with a relay to fall back to.
+    fn draw_servers(&mut self, servers: &[ServerEntry], has_input: bool) {
+        let x = SERVERS_X;
+        let mut layout = Layout::new(100., 30.);
+
+        Text::new_scaled(TEXT_MID).draw(&tr!("connect.servers"), x, layout.next());
+        layout.add(20.);
+
+        if Button::default()
+            .draw_centered(
+                x,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("connect.quick_connect")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(ServerConnectButtons::QuickConnect);
+        }
+        layout.add(BUTTON_H);
+
+        let list_start_y = layout.next() - BUTTON_H / 2.;
+        let content_height = servers.len() as f32 * (BUTTON_H + 10.);
+        let (clip, content_y) =
+            self.servers_scroll
+                .begin(list_start_y, SERVERS_END_Y, content_height);
+        let mut list_layout = Layout::new(content_y + BUTTON_H / 2., 10.);
+        let row_w = BUTTON_W - REMOVE_W - 10.;
+        for (i, server) in servers.iter().enumerate() {
+            let y = list_layout.next();
+            list_layout.add(BUTTON_H);
+            if y + BUTTON_H / 2. < list_start_y || y - BUTTON_H / 2. > SERVERS_END_Y {
+                continue;
+            }
+
+            let label = format!(
+                "{}  {}",
+                server.label,
+                probe_text(self.probes.get(&server.address))
+            );
+            if Button::default()
+                .draw_centered(
+                    x - (BUTTON_W - row_w) / 2.,
+                    y,
+                    row_w,
+                    BUTTON_H,
+                    Some(&label),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_pressed = Some(ServerConnectButtons::PickServer(i));
+            }
+            if Button::default()
+                .draw_centered(
+                    x + (BUTTON_W - REMOVE_W) / 2.,
+                    y,
+                    REMOVE_W,
+                    BUTTON_H,
+                    Some("X"),
+                    has_input,
+                )
+                .poll()
+            {
+                self.button_pressed = Some(ServerConnectButtons::RemoveServer(i));
+            }
+        }
+        drop(clip);
+
+        let mut layout = Layout::new(SERVERS_END_Y + 30., 20.);
+        Text::new_scaled(TEXT_SMALL).draw(&tr!("connect.server_label"), x, layout.next());
+        layout.add(10.);
+        let fields_y = layout.next() + BUTTON_H / 2.;
+        let relay_w = BUTTON_W - LABEL_W - 10.;
+        self.label_field.draw_centered(
+            x - (BUTTON_W - LABEL_W) / 2.,
+            fields_y,
+            LABEL_W,
+            BUTTON_H,
+            has_input,
+        );
+        self.relay_field.draw_centered(
+            x + (BUTTON_W - relay_w) / 2.,
+            fields_y,
+            relay_w,
+            BUTTON_H,
+            has_input,
+        );
+        layout.add(BUTTON_H);
+        if Button::default()
+            .draw_centered(
+                x,
+                layout.next() + BUTTON_H / 2.,
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("connect.save_server")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(ServerConnectButtons::SaveServer);
+        }
+    }
+
+    /// Connects to the typed server, through its relay if it's saved with one and can't be
+    /// reached directly.
+    fn connect(&mut self, ctx: &mut AppContext) {
+        let servername = self.servername_field.text();
+        let relay = ctx
+            .settings
+            .servers
+            .iter()
+            .find(|server| server.address == servername.trim())
+            .and_then(|server| server.relay.clone());
+        self.connect_request = Some(ctx.server.connect(
+            servername,
+            relay,
+            self.username_field.text(),
+            ctx.settings.trim.color(),
+        ));
+    }
+}
+
+/// Latency shown next to a saved server.
+fn probe_text(probe: Probe) -> String {
+    match probe {
+        Probe::Pending => "...".to_string(),
+        Probe::Reachable { ready: false, .. } => tr!("connect.not_ready"),
+        Probe::Reachable { latency, .. } => {
+            tr!("connect.latency", ms = latency.as_millis())
         }
+        Probe::Unreachable => tr!("connect.unreachable"),
+    }
+}
+
+/// Saves `entry`, replacing the server saved under the same label.
+fn save_server(servers: &mut Vec<ServerEntry>, entry: ServerEntry) {
+    match servers.iter_mut().find(|s| s.label == entry.label) {
+        Some(existing) => *existing = entry,
+        None => servers.push(entry),
+    }
+}
+
+/// Describes a connection in progress, `None` when there is nothing to show.
+fn connection_status(state: &ConnectionState, max_attempts: u32) -> Option<String> {
+    match state {
+        ConnectionState::Connecting { attempt } => Some(tr!(
+            "connect.connecting",
+            attempt = attempt,
+            max = max_attempts
+        )),
+        ConnectionState::Handshaking { attempt } => Some(tr!(
+            "connect.handshaking",
+            attempt = attempt,
+            max = max_attempts
+        )),
+        ConnectionState::Reconnecting {
+            attempt,
+            retry_at,
+            reason,
+        } => {
+            let wait = retry_at.saturating_duration_since(Instant::now());
+            Some(tr!(
+                "connect.retrying",
+                reason = reason,
+                seconds = format!("{:.1}", wait.as_secs_f32()),
+                attempt = attempt,
+                max = max_attempts
+            ))
+        }
+        ConnectionState::Idle | ConnectionState::Connected | ConnectionState::Failed(_) => None,
     }
 }
 
 impl View for ServerConnectMenu {
-    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
         let x_mid = CANONICAL_SCREEN_MID_X;
         let el_w = BUTTON_W;
         let el_h = BUTTON_H;
         let mut layout = Layout::new(100., 30.);
+        let status = connection_status(ctx.server.connection_state(), ctx.server.max_attempts());
+        let connecting = status.is_some();
 
-        Text::new_title().draw("Connect to server", x_mid, layout.next());
+        Text::new_title().draw(&tr!("connect.title"), x_mid, layout.next());
         layout.add(70.);
 
-        Text::new_scaled(TEXT_MID).draw("Enter server name:", x_mid, layout.next());
+        Text::new_scaled(TEXT_MID).draw(&tr!("connect.server_name"), x_mid, layout.next());
         layout.add(20.);
 
-        self.servername_field
-            .draw_centered(x_mid, layout.next(), el_w, el_h, has_input);
+        self.servername_field.draw_centered(
+            x_mid,
+            layout.next(),
+            el_w,
+            el_h,
+            has_input && !connecting,
+        );
         layout.add(el_h - 10.);
 
-        Text::new_scaled(TEXT_MID).draw("Enter username:", x_mid, layout.next());
+        Text::new_scaled(TEXT_MID).draw(&tr!("connect.username"), x_mid, layout.next());
         layout.add(20.);
 
-        self.username_field
-            .draw_centered(x_mid, layout.next(), el_w, el_h, has_input);
+        self.username_field.draw_centered(
+            x_mid,
+            layout.next(),
+            el_w,
+            el_h,
+            has_input && !connecting,
+        );
         layout.add(el_h);
 
         self.button_pressed = None;
 
+        let (label, action) = if connecting {
+            (tr!("connect.cancel"), ServerConnectButtons::Cancel)
+        } else {
+            (tr!("connect.connect"), ServerConnectButtons::Connect)
+        };
         if Button::default()
-            .draw_centered(x_mid, layout.next(), el_w, el_h, Some("Connect"), has_input)
+            .draw_centered(x_mid, layout.next(), el_w, el_h, Some(&label), has_input)
             .poll()
         {
-            self.button_pressed = Some(ServerConnectButtons::Connect);
+            self.button_pressed = Some(action);
         }
         layout.add(el_h);
 
         if Button::default()
-            .draw_centered(x_mid, layout.next(), el_w, el_h, Some("Back"), has_input)
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                el_w,
+                el_h,
+                Some(&tr!("common.back")),
+                has_input,
+            )
             .poll()
         {
             self.button_pressed = Some(ServerConnectButtons::Back);
         }
+        layout.add(el_h);
+
+        if let Some(status) = status {
+            Text::new_scaled(TEXT_SMALL).draw(&status, x_mid, layout.next());
+        } else if let Some(code) = &self.join_code {
+            Text::new_scaled(TEXT_SMALL).draw(
+                &tr!("connect.join_link", code = code.0),
+                x_mid,
+                layout.next(),
+            );
+        }
+
+        self.draw_servers(&ctx.settings.servers, has_input && !connecting);
     }
 
     fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        // The handshake answer arrives here, as that's where the connection was started
+        if let Some(resp) = self
+            .connect_request
+            .and_then(|request| ctx.server.take_reply(request))
+        {
+            self.connect_request = None;
+            return match resp {
+                Ok(()) => Transition::Push(Box::new(match self.join_code.take() {
+                    Some(code) => ServerLobby::joining(code),
+                    None => ServerLobby::new(),
+                })),
+                Err(reason) => Transition::Push(Box::new(Popup::new(reason))),
+            };
+        }
+
         ctx.server.assert_state(ClientState::Disconnected);
+        self.probes.update(&ctx.settings.servers);
 
-        self.servername_field.update();
-        self.username_field.update();
+        if connection_status(ctx.server.connection_state(), ctx.server.max_attempts()).is_none() {
+            self.servername_field.update();
+            self.username_field.update();
+            self.label_field.update();
+            self.relay_field.update();
+            self.servers_scroll.update();
+        }
 
         match self.button_pressed {
             Some(button) => match button {
                 ServerConnectButtons::Connect => {
-                    ctx.server
-                        .connect(self.servername_field.text(), self.username_field.text());
-                    let success_view = Box::new(ServerLobby::new()) as Box<dyn View>;
-                    Transition::Push(Box::new(RequestView::new_open_view(
-                        "Connecting to server...".into(),
-                        success_view,
-                    )))
+                    self.connect(ctx);
+                    Transition::None
+                }
+                ServerConnectButtons::QuickConnect => {
+                    let Some(best) = self.probes.best(&ctx.settings.servers) else {
+                        return Transition::Push(Box::new(Popup::new(tr!("connect.no_server"))));
+                    };
+                    self.servername_field.set_text(&best.address);
+                    self.connect(ctx);
+                    Transition::None
+                }
+                ServerConnectButtons::PickServer(i) => {
+                    if let Some(server) = ctx.settings.servers.get(i) {
+                        self.servername_field.set_text(&server.address);
+                    }
+                    Transition::None
+                }
+                ServerConnectButtons::RemoveServer(i) => {
+                    if i < ctx.settings.servers.len() {
+                        ctx.settings.servers.remove(i);
+                        if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                            eprintln!("Failed to save settings: {}", e);
+                        }
+                    }
+                    Transition::None
+                }
+                ServerConnectButtons::SaveServer => {
+                    let label = self.label_field.text().trim().to_string();
+                    let address = self.servername_field.text().trim().to_string();
+                    if label.is_empty() || address.is_empty() {
+                        return Transition::Push(Box::new(Popup::new(tr!(
+                            "connect.save_server_missing"
+                        ))));
+                    }
+                    let relay = self.relay_field.text().trim().to_string();
+                    let entry = ServerEntry {
+                        label,
+                        address,
+                        relay: (!relay.is_empty()).then_some(relay),
+                    };
+                    save_server(&mut ctx.settings.servers, entry);
+                    if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+                        eprintln!("Failed to save settings: {}", e);
+                    }
+                    self.label_field.reset();
+                    self.relay_field.reset();
+                    Transition::None
+                }
+                ServerConnectButtons::Cancel => {
+                    ctx.server.cancel_connect();
+                    Transition::None
+                }
+                ServerConnectButtons::Back => {
+                    ctx.server.cancel_connect();
+                    Transition::Pop
                 }
-                ServerConnectButtons::Back => Transition::Pop,
             },
             None => Transition::None,
         }
@@ -97,3 +420,23 @@ impl View for ServerConnectMenu {
         ViewId::ServerConnectMenu
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saving_a_server_replaces_the_one_with_the_same_label() {
+        let entry = |label: &str, address: &str| ServerEntry {
+            label: label.into(),
+            address: address.into(),
+            relay: None,
+        };
+        let mut servers = Vec::new();
+        save_server(&mut servers, entry("EU", "eu1.example.com"));
+        save_server(&mut servers, entry("US", "us.example.com"));
+        save_server(&mut servers, entry("EU", "eu2.example.com"));
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].address, "eu2.example.com");
+    }
+}
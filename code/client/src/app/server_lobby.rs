@@ -2,10 +2,14 @@ use crate::app::game::Game;
 use crate::app::game_creation::GameCreation;
 use crate::app::game_view::GameView;
 use crate::app::request_view::{RequestAction, RequestView};
+use crate::app::tournament::TournamentView;
 use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::presence::Presence;
 use crate::server::ClientState;
 use crate::ui::{
-    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_MID, Text, TextField,
+    BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X, Layout, TEXT_MID, TEXT_SMALL, Text,
+    TextField,
 };
 use common::protocol::{ClientMessage, GameCode};
 
@@ -13,12 +17,15 @@ use common::protocol::{ClientMessage, GameCode};
 enum ServerLobbyButtons {
     Create,
     Join,
+    Tournament,
     Back,
 }
 
 pub(crate) struct ServerLobby {
     button_pressed: Option<ServerLobbyButtons>,
     game_code_field: TextField,
+    /// Join the game in the code field on the next update, for join links
+    join_now: bool,
 }
 
 impl ServerLobby {
@@ -26,9 +33,18 @@ impl ServerLobby {
         ServerLobby {
             button_pressed: None,
             game_code_field: TextField::new_simple(6),
+            join_now: false,
         }
     }
 
+    /// The lobby, straight away joining the game with `code`.
+    pub fn joining(code: GameCode) -> Self {
+        let mut lobby = Self::new();
+        lobby.game_code_field.set_text(&code.0);
+        lobby.join_now = true;
+        lobby
+    }
+
     pub fn get_game_completion_action() -> RequestAction {
         Box::new(|ctx: &mut AppContext| {
             let initial = ctx.server.initial_game_info();
@@ -44,7 +60,7 @@ impl ServerLobby {
 
             let is_host = initial.game_master == client_id.unwrap();
 
-            ctx.game = Some(Game::new(initial, is_host));
+            ctx.game = Some(Game::new(initial, is_host, &ctx.services));
 
             Transition::PopAnd(Box::new(GameView::new()))
         })
@@ -52,7 +68,7 @@ impl ServerLobby {
 }
 
 impl View for ServerLobby {
-    fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
         let x_mid = CANONICAL_SCREEN_MID_X;
         let el_w = BUTTON_W;
         let el_h = BUTTON_H;
@@ -60,7 +76,18 @@ impl View for ServerLobby {
 
         self.button_pressed = None;
 
-        Text::new_title().draw("Games", x_mid, layout.next());
+        Text::new_title().draw(&tr!("lobby.title"), x_mid, layout.next());
+        if let Some(build) = ctx.server.server_build_mismatch() {
+            Text::new_scaled(TEXT_SMALL).draw(
+                &tr!(
+                    "lobby.build_mismatch",
+                    server = build,
+                    client = common::version()
+                ),
+                x_mid,
+                layout.next() + 50.,
+            );
+        }
         layout.add(70.);
 
         if Button::default()
@@ -69,7 +96,7 @@ impl View for ServerLobby {
                 layout.next(),
                 el_w,
                 el_h,
-                Some("Create new"),
+                Some(&tr!("lobby.create")),
                 has_input,
             )
             .poll()
@@ -78,7 +105,7 @@ impl View for ServerLobby {
         }
         layout.add(el_h);
 
-        Text::new_scaled(TEXT_MID).draw("Game code:", x_mid, layout.next());
+        Text::new_scaled(TEXT_MID).draw(&tr!("lobby.game_code"), x_mid, layout.next());
         layout.add(20.);
 
         let left_x = x_mid - el_w / 4.;
@@ -93,7 +120,7 @@ impl View for ServerLobby {
                 layout.next(),
                 el_w / 2.,
                 el_h,
-                Some("Join"),
+                Some(&tr!("lobby.join")),
                 has_input,
             )
             .poll()
@@ -103,7 +130,29 @@ impl View for ServerLobby {
         layout.add(el_h);
 
         if Button::default()
-            .draw_centered(x_mid, layout.next(), el_w, el_h, Some("Back"), has_input)
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                el_w,
+                el_h,
+                Some(&tr!("lobby.tournament")),
+                has_input,
+            )
+            .poll()
+        {
+            self.button_pressed = Some(ServerLobbyButtons::Tournament);
+        }
+        layout.add(el_h);
+
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                el_w,
+                el_h,
+                Some(&tr!("common.back")),
+                has_input,
+            )
             .poll()
         {
             self.button_pressed = Some(ServerLobbyButtons::Back);
@@ -112,23 +161,42 @@ impl View for ServerLobby {
     }
 
     fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        // Reconnected into the match we dropped out of
+        if let Some(initial) = ctx.server.initial_game_info() {
+            let is_host = ctx.server.client_id() == Some(initial.game_master);
+            ctx.game = Some(Game::new(initial, is_host, &ctx.services));
+            return Transition::Push(Box::new(GameView::new()));
+        }
+
         ctx.server.assert_state(ClientState::Connected);
 
         self.game_code_field.update();
+        if std::mem::take(&mut self.join_now) {
+            self.button_pressed = Some(ServerLobbyButtons::Join);
+        }
 
         match self.button_pressed {
             Some(button) => match button {
                 ServerLobbyButtons::Create => Transition::Push(Box::new(GameCreation::new())),
                 ServerLobbyButtons::Join => {
-                    ctx.server.send_client_message(ClientMessage::JoinGame {
+                    let request = ctx.server.send_request(ClientMessage::JoinGame {
                         game_code: GameCode(self.game_code_field.text()),
                     });
 
                     Transition::Push(Box::new(RequestView::new_action(
-                        "Joining game...".into(),
+                        tr!("lobby.joining"),
+                        request,
                         ServerLobby::get_game_completion_action(),
                     )))
                 }
+                ServerLobbyButtons::Tournament => {
+                    let request = ctx.server.send_request(ClientMessage::JoinTournament);
+                    Transition::Push(Box::new(RequestView::new_transition(
+                        tr!("lobby.joining_tournament"),
+                        request,
+                        Transition::PopAnd(Box::new(TournamentView::new())),
+                    )))
+                }
                 ServerLobbyButtons::Back => {
                     ctx.server.close();
                     Transition::Pop
@@ -142,6 +210,10 @@ impl View for ServerLobby {
         self.game_code_field.reset();
     }
 
+    fn presence(&self, _ctx: &AppContext) -> Option<Presence> {
+        Some(Presence::Online)
+    }
+
     fn get_id(&self) -> ViewId {
         ViewId::ServerLobby
     }
@@ -0,0 +1,32 @@
+//! Systems shared across views, set up once when the app starts and reached through
+//! [`AppContext::services`](crate::app::AppContext), so views don't load their own copies.
+
+use crate::app::effects::SoundBank;
+use common::game::map_registry::{MAPS_DIR, MapRegistry};
+use std::path::Path;
+use std::rc::Rc;
+
+pub(crate) struct Services {
+    sounds: Rc<SoundBank>,
+    maps: MapRegistry,
+}
+
+impl Services {
+    /// Sets up the services; their assets are registered, to be loaded with the rest.
+    pub fn load() -> Self {
+        Self {
+            sounds: Rc::new(SoundBank::register()),
+            maps: MapRegistry::load(Path::new(MAPS_DIR)),
+        }
+    }
+
+    /// The sounds of game effects.
+    pub fn sounds(&self) -> Rc<SoundBank> {
+        self.sounds.clone()
+    }
+
+    /// The built-in maps and those in [`MAPS_DIR`].
+    pub fn maps(&self) -> &MapRegistry {
+        &self.maps
+    }
+}
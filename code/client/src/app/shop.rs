@@ -0,0 +1,99 @@
+//! The upgrade shop, open during the intermission between rounds.
+
+use crate::i18n::tr;
+use crate::ui::field::Field;
+use crate::ui::{Button, CANONICAL_SCREEN_MID_X, TEXT_MID, Text, default_text_params, scale_dims};
+use common::game::upgrades::MAX_UPGRADE_LEVEL;
+use common::protocol::{Loadout, ShopBalance, Upgrade};
+use macroquad::prelude::*;
+
+const TOP: f32 = 150.;
+const WIDTH: f32 = 520.;
+const ROW_H: f32 = 70.;
+
+pub(crate) fn upgrade_name(upgrade: Upgrade) -> String {
+    match upgrade {
+        Upgrade::MaxHealth => tr!("shop.max_health"),
+        Upgrade::FireRate => tr!("shop.fire_rate"),
+        Upgrade::Speed => tr!("shop.speed"),
+    }
+}
+
+/// Label of the button buying the next level of `upgrade`.
+pub(crate) fn upgrade_label(upgrade: Upgrade, loadout: &Loadout) -> String {
+    let name = upgrade_name(upgrade);
+    match upgrade.next_cost(loadout) {
+        Some(cost) => tr!(
+            "shop.upgrade",
+            name = name,
+            level = loadout.level(upgrade),
+            max = MAX_UPGRADE_LEVEL,
+            cost = cost
+        ),
+        None => tr!("shop.maxed", name = name),
+    }
+}
+
+/// Draws the shop for the local player's `balance`, with a button for every upgrade they
+/// can afford. Returns the one they clicked.
+pub(crate) fn draw_shop(
+    balance: Option<&ShopBalance>,
+    seconds_left: u64,
+    has_input: bool,
+) -> Option<Upgrade> {
+    let loadout = balance.map(|b| b.loadout).unwrap_or_default();
+    let points = balance.map_or(0, |b| b.points);
+    let rows = Upgrade::ALL.len() as f32;
+    let (x, y, w, h) = scale_dims(
+        CANONICAL_SCREEN_MID_X - WIDTH / 2.,
+        TOP - 40.,
+        WIDTH,
+        ROW_H * (rows + 1.) + 20.,
+    );
+    draw_rectangle(x, y, w, h, Color::new(0., 0., 0., 0.7));
+    Text::new_scaled(TEXT_MID).draw(
+        &tr!("shop.title", points = points, seconds = seconds_left),
+        CANONICAL_SCREEN_MID_X,
+        TOP,
+    );
+
+    let mut picked = None;
+    for (row, upgrade) in Upgrade::ALL.into_iter().enumerate() {
+        let affordable = upgrade
+            .next_cost(&loadout)
+            .is_some_and(|cost| cost <= points);
+        let params = TextParams {
+            font_size: TEXT_MID,
+            ..default_text_params()
+        };
+        if Button::new(Field::default(), Some(params))
+            .draw_centered(
+                CANONICAL_SCREEN_MID_X,
+                TOP + ROW_H * (row as f32 + 1.),
+                WIDTH - 40.,
+                ROW_H - 15.,
+                Some(&upgrade_label(upgrade, &loadout)),
+                has_input && affordable,
+            )
+            .poll()
+        {
+            picked = Some(upgrade);
+        }
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_show_level_and_cost() {
+        let loadout = Loadout {
+            speed: MAX_UPGRADE_LEVEL,
+            ..Default::default()
+        };
+        assert!(upgrade_label(Upgrade::FireRate, &loadout).contains("100"));
+        assert!(!upgrade_label(Upgrade::Speed, &loadout).contains("100"));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::app::challenge::{CHALLENGES, MAX_STARS};
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::ui::{self, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_MID_X};
+
+/// Career statistics collected on this machine.
+pub(crate) struct StatsView {
+    back_clicked: bool,
+}
+
+impl StatsView {
+    pub fn new() -> Self {
+        Self {
+            back_clicked: false,
+        }
+    }
+}
+
+impl View for StatsView {
+    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+        if self.back_clicked {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        let mut layout = ui::Layout::new(100., 25.);
+        let stats = &ctx.settings.stats;
+
+        ui::Text::new_title().draw(&tr!("stats.title"), x_mid, layout.next());
+        layout.add(50.);
+
+        let stars: u32 = ctx
+            .settings
+            .challenge_stars
+            .values()
+            .map(|&s| u32::from(s))
+            .sum();
+        let accuracy = stats.accuracy().map_or("-".to_string(), |accuracy| {
+            format!("{:.0}%", accuracy * 100.)
+        });
+        let lines = [
+            tr!("stats.training", matches = stats.training_matches),
+            tr!(
+                "stats.challenges",
+                runs = stats.challenge_runs,
+                completed = stats.challenges_completed
+            ),
+            tr!(
+                "stats.stars",
+                stars = stars,
+                max = CHALLENGES.len() as u32 * u32::from(MAX_STARS)
+            ),
+            tr!(
+                "stats.multiplayer",
+                wins = stats.multiplayer_wins,
+                losses = stats.multiplayer_losses
+            ),
+            tr!(
+                "stats.accuracy",
+                accuracy = accuracy,
+                hits = stats.shots_hit,
+                shots = stats.shots_fired
+            ),
+        ];
+        for line in lines {
+            ui::Text::new_scaled(ui::TEXT_MID).draw(&line, x_mid, layout.next());
+            layout.add(20.);
+        }
+        layout.add(20.);
+
+        self.back_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("common.back")),
+                has_input,
+            )
+            .poll();
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::Stats
+    }
+}
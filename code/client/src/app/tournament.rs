@@ -0,0 +1,192 @@
+use crate::app::game::Game;
+use crate::app::game_view::GameView;
+use crate::app::request_view::RequestView;
+use crate::app::{AppContext, Transition, View, ViewId};
+use crate::i18n::tr;
+use crate::presence::Presence;
+use crate::server::ClientState;
+use crate::ui::{
+    self, BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_MID_X, TEXT_MID,
+    TEXT_SMALL, Text,
+};
+use common::protocol::{ClientMessage, TournamentInfo, TournamentState};
+
+/// Rows shown in each column; older matches scroll off the top.
+const MAX_ROWS: usize = 12;
+const COLUMN_OFFSET: f32 = 300.;
+
+/// Bracket and standings of the server's tournament. The server moves us into our matches,
+/// which are played on top of this view.
+pub(crate) struct TournamentView {
+    leave_clicked: bool,
+}
+
+impl TournamentView {
+    pub fn new() -> Self {
+        Self {
+            leave_clicked: false,
+        }
+    }
+
+    fn status(info: &TournamentInfo) -> String {
+        match &info.state {
+            TournamentState::SignUp {
+                seconds_left,
+                entrants,
+                capacity,
+            } => tr!(
+                "tournament.signup",
+                seconds = seconds_left,
+                entrants = entrants,
+                capacity = capacity
+            ),
+            TournamentState::Running { bracket_round } => {
+                tr!("tournament.running", round = bracket_round)
+            }
+            TournamentState::Finished { champion } => {
+                tr!("tournament.champion", name = champion)
+            }
+            TournamentState::Cancelled => tr!("tournament.cancelled"),
+        }
+    }
+
+    fn match_rows(info: &TournamentInfo) -> Vec<String> {
+        let skip = info.matches.len().saturating_sub(MAX_ROWS);
+        info.matches
+            .iter()
+            .skip(skip)
+            .map(|m| {
+                let [a, b] = &m.entrants;
+                let row = tr!(
+                    "tournament.match",
+                    round = m.bracket_round,
+                    a = a,
+                    b = b,
+                    map = format!("{:?}", m.map)
+                );
+                match m.winner {
+                    Some(winner) => tr!(
+                        "tournament.match_won",
+                        row = row,
+                        winner = m.entrants[usize::from(winner)]
+                    ),
+                    None => row,
+                }
+            })
+            .collect()
+    }
+
+    fn standing_rows(info: &TournamentInfo) -> Vec<String> {
+        info.standings
+            .iter()
+            .take(MAX_ROWS)
+            .enumerate()
+            .map(|(i, standing)| {
+                let key = if standing.eliminated {
+                    "tournament.standing_out"
+                } else {
+                    "tournament.standing"
+                };
+                tr!(
+                    key,
+                    place = i + 1,
+                    name = standing.name,
+                    wins = standing.wins
+                )
+            })
+            .collect()
+    }
+}
+
+impl View for TournamentView {
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
+        // The server put us into our next match
+        if let Some(initial) = ctx.server.initial_game_info() {
+            let is_host = ctx.server.client_id() == Some(initial.game_master);
+            ctx.game = Some(Game::new(initial, is_host, &ctx.services));
+            return Transition::Push(Box::new(GameView::new()));
+        }
+
+        ctx.server.assert_state(ClientState::Connected);
+
+        if self.leave_clicked {
+            let request = ctx.server.send_request(ClientMessage::LeaveTournament);
+            return Transition::Push(Box::new(RequestView::new_transition(
+                tr!("tournament.leaving"),
+                request,
+                Transition::PopUntil(ViewId::ServerLobby),
+            )));
+        }
+        Transition::None
+    }
+
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
+        let x_mid = CANONICAL_SCREEN_MID_X;
+        let mut layout = ui::Layout::new(60., 20.);
+        self.leave_clicked = false;
+
+        let Some(info) = ctx.server.tournament() else {
+            return;
+        };
+
+        Text::new_title().draw(&info.name, x_mid, layout.next());
+        layout.add(40.);
+        Text::new_scaled(TEXT_MID).draw(&Self::status(info), x_mid, layout.next());
+        layout.add(20.);
+        Text::new_scaled(TEXT_SMALL).draw(
+            &tr!(
+                "tournament.format",
+                size = info.team_size,
+                rounds = info.rounds
+            ),
+            x_mid,
+            layout.next(),
+        );
+        layout.add(30.);
+
+        let columns = [
+            (
+                x_mid - COLUMN_OFFSET,
+                tr!("tournament.matches"),
+                Self::match_rows(info),
+            ),
+            (
+                x_mid + COLUMN_OFFSET,
+                tr!("tournament.standings"),
+                Self::standing_rows(info),
+            ),
+        ];
+        let top = layout.next();
+        for (x, heading, rows) in columns {
+            let mut column = ui::Layout::new(top, 8.);
+            Text::new_scaled(TEXT_MID).draw(&heading, x, column.next());
+            column.add(25.);
+            for row in rows {
+                Text::new_scaled(TEXT_SMALL).draw(&row, x, column.next());
+                column.add(TEXT_SMALL as f32);
+            }
+        }
+
+        let leave_y = CANONICAL_SCREEN_HEIGHT - 60. - BUTTON_H / 2.;
+        self.leave_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                leave_y,
+                BUTTON_W,
+                BUTTON_H,
+                Some(&tr!("tournament.leave")),
+                has_input,
+            )
+            .poll();
+    }
+
+    fn presence(&self, ctx: &AppContext) -> Option<Presence> {
+        ctx.server.tournament().map(|info| Presence::Tournament {
+            name: info.name.clone(),
+        })
+    }
+
+    fn get_id(&self) -> ViewId {
+        ViewId::Tournament
+    }
+}
@@ -1,17 +1,29 @@
+use crate::app::camera::SpectatorCamera;
+use crate::app::evaluation::{EVALUATION_MATCHES, Evaluation};
 use crate::app::game::Game;
+use crate::app::hud;
+use crate::app::replay_select::REPLAYS_DIR;
 use crate::app::{AppContext, Transition, View, ViewId};
-use crate::ui::CANONICAL_SCREEN_WIDTH;
+use crate::i18n::tr;
+use crate::presence::Presence;
+use crate::settings::SETTINGS_PATH;
+use crate::stats::ShotCounter;
 use crate::ui::{self};
-use ::rand::SeedableRng;
-use ::rand::rngs::StdRng;
+use crate::ui::{BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_WIDTH};
+use ::rand::{Rng, SeedableRng};
 use burn::backend::Wgpu;
 
-use common::ai::BotContext;
+use common::ai::{BotAgent, BotContext, BotDifficulty, BotRng};
 use common::game::engine::GameEngine;
-use common::net::protocol::{InputPayload, MapDefinition, PlayerId, Tank};
-use common::rl::{BotBrain, extract_features};
-use glam::Vec2;
+use common::game::replay::{REPLAY_EXTENSION, ReplayRecorder};
+use common::game::{RoundOutcome, reload_balance};
+use common::net::protocol::{MapDefinition, PlayerId, Tank, Team};
+use common::rl::contest::{Contestant, ContestantSpec};
+use common::rl::scenario::ScenarioDriver;
+use common::rl::{BotBrain, Scenario, action_to_input, extract_features_with};
 use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
 
 type ClientBackend = Wgpu;
 
@@ -19,125 +31,464 @@ type ClientBackend = Wgpu;
 pub(crate) enum TrainingMode {
     Spectator,
     HumanVsAi,
+    /// Blue team driven by one model, Red team by another, restarting after every match.
+    Comparison,
+    /// The model plays the Learner tanks of a scenario, restarting after every match.
+    Scenario,
+}
+
+pub(crate) const SCENARIOS_DIR: &str = "assets/scenarios";
+
+/// Matches that last longer than this are counted as a draw in comparison mode.
+const COMPARISON_MATCH_SECS: f32 = 60.0;
+/// Scripted bots the model can be evaluated against, after the compared model if any.
+const EVALUATION_OPPONENTS: [BotDifficulty; 5] = [
+    BotDifficulty::Dummy,
+    BotDifficulty::Turret,
+    BotDifficulty::Wanderer,
+    BotDifficulty::Hunter,
+    BotDifficulty::Terminator,
+];
+/// Length of a trainer tick, the unit of a scenario's `max_ticks`.
+const TRAINER_TICK_SECS: f32 = 0.033;
+
+struct Comparison {
+    blue_name: String,
+    red_name: String,
+    red_brain: BotBrain<ClientBackend>,
+    blue_wins: u32,
+    red_wins: u32,
+    draws: u32,
+}
+
+enum EvaluationOpponent<'a> {
+    /// The compared model, with its file name
+    Model(&'a str, &'a BotBrain<ClientBackend>),
+    Scripted(BotDifficulty),
+}
+
+impl EvaluationOpponent<'_> {
+    fn name(&self) -> String {
+        match self {
+            EvaluationOpponent::Model(name, _) => name.to_string(),
+            EvaluationOpponent::Scripted(difficulty) => {
+                ContestantSpec::Scripted(*difficulty).to_string()
+            }
+        }
+    }
+}
+
+struct ScenarioRun {
+    scenario: Scenario,
+    /// Drivers of the scripted tanks, recreated for every match
+    bots: HashMap<PlayerId, BotAgent>,
+    deaths: usize,
+    wins: u32,
+    losses: u32,
+    draws: u32,
+}
+
+impl ScenarioRun {
+    fn reset(&mut self, engine: &GameEngine, seed: u64) {
+        self.deaths = 0;
+        self.bots = engine
+            .tanks
+            .iter()
+            .filter_map(|tank| {
+                let id = tank.player_info.id;
+                match self.scenario.tanks.get(id as usize)?.driver {
+                    ScenarioDriver::Scripted(difficulty) => Some((
+                        id,
+                        BotAgent::new(tank.player_info.clone(), difficulty, seed ^ id as u64),
+                    )),
+                    ScenarioDriver::Learner => None,
+                }
+            })
+            .collect();
+    }
 }
 
 pub(crate) struct Training {
     game_engine: GameEngine,
+    /// Map of every match but a scenario's, which brings its own
+    map: MapDefinition,
     brain: BotBrain<ClientBackend>,
     mode: TrainingMode,
     human_id: Option<PlayerId>,
-    rng: StdRng,
+    rng: BotRng,
+    recorder: ReplayRecorder,
+    camera: SpectatorCamera,
+    comparison: Option<Comparison>,
+    scenario: Option<ScenarioRun>,
+    /// Running or finished batch of headless matches; the match on screen waits while one runs
+    evaluation: Option<Evaluation>,
+    evaluation_error: Option<String>,
+    /// Why the balance table edited since the last reset was not picked up
+    balance_error: Option<String>,
+    /// Index into the compared model, if any, followed by `EVALUATION_OPPONENTS`
+    evaluation_opponent: usize,
+    evaluate_clicked: bool,
+    next_opponent_clicked: bool,
+    match_time: f32,
+    /// Matches finished or restarted, added to the career stats when leaving
+    matches_played: u32,
+    shots: ShotCounter,
 }
 
 impl Training {
-    pub fn new(brain: BotBrain<ClientBackend>, mode: TrainingMode) -> Self {
-        let mut game_engine = GameEngine::new(MapDefinition::load());
-        let spawn_points = &game_engine.map.spawn_points;
+    pub fn new(brain: BotBrain<ClientBackend>, mode: TrainingMode, map: MapDefinition) -> Self {
+        let (game_engine, human_id) = Self::setup_engine(mode, None, &map);
+        let seed = ::rand::rng().random();
+        let recorder = ReplayRecorder::new(&game_engine, seed);
+
+        Self {
+            game_engine,
+            map,
+            human_id,
+            mode,
+            brain,
+            rng: BotRng::seed_from_u64(seed),
+            recorder,
+            camera: SpectatorCamera::new(),
+            comparison: None,
+            scenario: None,
+            evaluation: None,
+            evaluation_error: None,
+            balance_error: None,
+            evaluation_opponent: 0,
+            evaluate_clicked: false,
+            next_opponent_clicked: false,
+            match_time: 0.0,
+            matches_played: 0,
+            shots: ShotCounter::default(),
+        }
+    }
+
+    pub fn new_comparison(
+        blue_name: String,
+        blue_brain: BotBrain<ClientBackend>,
+        red_name: String,
+        red_brain: BotBrain<ClientBackend>,
+        map: MapDefinition,
+    ) -> Self {
+        Self {
+            comparison: Some(Comparison {
+                blue_name,
+                red_name,
+                red_brain,
+                blue_wins: 0,
+                red_wins: 0,
+                draws: 0,
+            }),
+            ..Self::new(blue_brain, TrainingMode::Comparison, map)
+        }
+    }
+
+    pub fn new_scenario(brain: BotBrain<ClientBackend>, scenario: Scenario) -> Self {
+        let mut training = Self::new(
+            brain,
+            TrainingMode::Scenario,
+            MapDefinition::load_name(scenario.map),
+        );
+        training.scenario = Some(ScenarioRun {
+            scenario,
+            bots: HashMap::new(),
+            deaths: 0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+        });
+        training.restart();
+        training
+    }
+
+    fn setup_engine(
+        mode: TrainingMode,
+        scenario: Option<&Scenario>,
+        map: &MapDefinition,
+    ) -> (GameEngine, Option<PlayerId>) {
+        if let Some(scenario) = scenario {
+            return (scenario.spawn(), None);
+        }
+        let mut game_engine = GameEngine::new(map.clone());
+        let blue_spawns = map.team_spawns(Team::Blue, 4);
+        let red_spawns = map.team_spawns(Team::Red, 4);
         let mut human_id = None;
 
         match mode {
-            TrainingMode::Spectator => {
-                for i in 0..4 {
-                    if let Some((team, pos)) = spawn_points.get(i + 4) {
-                        game_engine.tanks.push(Tank::new(
-                            common::game::player::PlayerInfo::new(
-                                i as PlayerId,
-                                format!("Blue {}", i),
-                                *team,
-                            ),
-                            *pos,
-                        ));
-                    }
+            TrainingMode::Spectator | TrainingMode::Comparison | TrainingMode::Scenario => {
+                for (i, pos) in blue_spawns.into_iter().enumerate() {
+                    game_engine.tanks.push(Tank::new(
+                        common::game::player::PlayerInfo::new(
+                            i as PlayerId,
+                            format!("Blue {}", i),
+                            Team::Blue,
+                        ),
+                        pos,
+                    ));
                 }
-                for i in 0..4 {
-                    if let Some((team, pos)) = spawn_points.get(i) {
-                        game_engine.tanks.push(Tank::new(
-                            common::game::player::PlayerInfo::new(
-                                (i + 4) as PlayerId,
-                                format!("Red {}", i),
-                                *team,
-                            ),
-                            *pos,
-                        ));
-                    }
+                for (i, pos) in red_spawns.into_iter().enumerate() {
+                    game_engine.tanks.push(Tank::new(
+                        common::game::player::PlayerInfo::new(
+                            (i + 4) as PlayerId,
+                            format!("Red {}", i),
+                            Team::Red,
+                        ),
+                        pos,
+                    ));
                 }
             }
             TrainingMode::HumanVsAi => {
-                if let Some((team, pos)) = spawn_points.get(4) {
+                if let Some(&pos) = blue_spawns.first() {
                     let pid = 0;
                     human_id = Some(pid);
                     game_engine.tanks.push(Tank::new(
-                        common::game::player::PlayerInfo::new(pid, "Player".into(), *team),
-                        *pos,
+                        common::game::player::PlayerInfo::new(pid, "Player".into(), Team::Blue),
+                        pos,
                     ));
                 }
-                for i in 0..4 {
-                    if let Some((team, pos)) = spawn_points.get(i) {
-                        let pid = (i + 1) as PlayerId;
-                        game_engine.tanks.push(Tank::new(
-                            common::game::player::PlayerInfo::new(pid, format!("Bot {}", i), *team),
-                            *pos,
-                        ));
-                    }
+                for (i, pos) in red_spawns.into_iter().enumerate() {
+                    let pid = (i + 1) as PlayerId;
+                    game_engine.tanks.push(Tank::new(
+                        common::game::player::PlayerInfo::new_bot(
+                            pid,
+                            format!("Bot {}", i),
+                            Team::Red,
+                        ),
+                        pos,
+                    ));
                 }
             }
         }
 
-        Self {
-            game_engine,
-            human_id,
-            mode,
-            brain,
-            rng: StdRng::from_os_rng(),
+        (game_engine, human_id)
+    }
+
+    /// Saves the finished match and starts a fresh one, keeping the models and scores.
+    fn restart(&mut self) {
+        self.save_replay();
+        if self.recorder.tick_count() > 0 {
+            self.matches_played += 1;
         }
+
+        let scenario = self.scenario.as_ref().map(|run| &run.scenario);
+        let (game_engine, human_id) = Self::setup_engine(self.mode, scenario, &self.map);
+        let seed = ::rand::rng().random();
+        if let Some(run) = &mut self.scenario {
+            run.reset(&game_engine, seed);
+        }
+        self.recorder = ReplayRecorder::new(&game_engine, seed);
+        self.rng = BotRng::seed_from_u64(seed);
+        self.game_engine = game_engine;
+        self.human_id = human_id;
+        self.match_time = 0.0;
     }
 
-    fn bot_action_to_input(actions: &[f32], ctx: &BotContext) -> InputPayload {
-        let move_fwd = actions[0].tanh();
-        let move_side = actions[1].tanh();
-        let aim_fwd = actions[2];
-        let aim_side = actions[3];
-        let shoot_val = actions[4];
+    fn record_comparison_result(&mut self, outcome: RoundOutcome) {
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
 
-        let (sin, cos) = ctx.me.rotation.sin_cos();
-        let world_move = Vec2::new(
-            move_fwd * cos - move_side * sin,
-            move_fwd * sin + move_side * cos,
-        );
-        let world_aim_dir = Vec2::new(
-            aim_fwd * cos - aim_side * sin,
-            aim_fwd * sin + aim_side * cos,
-        );
-        let final_aim_dir = if world_aim_dir.length_squared() < 0.001 {
-            Vec2::new(cos, sin)
-        } else {
-            world_aim_dir.normalize()
+        match outcome {
+            RoundOutcome::Won(Team::Blue) => comparison.blue_wins += 1,
+            RoundOutcome::Won(Team::Red) => comparison.red_wins += 1,
+            RoundOutcome::Draw => comparison.draws += 1,
+            RoundOutcome::Ongoing if self.match_time >= COMPARISON_MATCH_SECS => {
+                comparison.draws += 1
+            }
+            RoundOutcome::Won(Team::Ffa(_)) | RoundOutcome::Ongoing => return,
+        }
+        self.restart();
+    }
+
+    fn can_evaluate(&self) -> bool {
+        matches!(
+            self.mode,
+            TrainingMode::Spectator | TrainingMode::Comparison
+        )
+    }
+
+    /// The opponent picked for evaluation.
+    fn evaluation_opponent(&self) -> EvaluationOpponent<'_> {
+        let mut index = self.evaluation_opponent;
+        if let Some(comparison) = &self.comparison {
+            if index == 0 {
+                return EvaluationOpponent::Model(&comparison.red_name, &comparison.red_brain);
+            }
+            index -= 1;
+        }
+        EvaluationOpponent::Scripted(EVALUATION_OPPONENTS[index % EVALUATION_OPPONENTS.len()])
+    }
+
+    fn next_evaluation_opponent(&mut self) {
+        let count = EVALUATION_OPPONENTS.len() + usize::from(self.comparison.is_some());
+        self.evaluation_opponent = (self.evaluation_opponent + 1) % count;
+    }
+
+    /// The model and the picked opponent, copied for headless matches.
+    fn evaluation_contestants(&self) -> Result<[Contestant; 2], String> {
+        let opponent = match self.evaluation_opponent() {
+            EvaluationOpponent::Model(_, brain) => Contestant::from_brain(brain)?,
+            EvaluationOpponent::Scripted(difficulty) => Contestant::Scripted(difficulty),
         };
-        let aim_pos = ctx.me.position + (final_aim_dir * 100.0);
+        Ok([Contestant::from_brain(&self.brain)?, opponent])
+    }
+
+    /// Starts evaluating the model against the picked opponent in the background.
+    fn start_evaluation(&mut self) {
+        match self.evaluation_contestants() {
+            Ok([model, opponent]) => {
+                self.evaluation_error = None;
+                self.evaluation = Some(Evaluation::start(
+                    model,
+                    opponent,
+                    self.evaluation_opponent().name(),
+                    EVALUATION_MATCHES,
+                    std::time::Duration::from_secs_f32(COMPARISON_MATCH_SECS),
+                ));
+            }
+            Err(e) => self.evaluation_error = Some(e),
+        }
+    }
+
+    fn is_evaluating(&self) -> bool {
+        self.evaluation.as_ref().is_some_and(|e| !e.is_done())
+    }
+
+    /// Counts the result of a scenario match once it is over and starts the next one.
+    fn record_scenario_result(&mut self) {
+        let Some(run) = &mut self.scenario else {
+            return;
+        };
+        let ticks = (self.match_time / TRAINER_TICK_SECS) as usize;
+        let default_ticks = (COMPARISON_MATCH_SECS / TRAINER_TICK_SECS) as usize;
+        if !run
+            .scenario
+            .is_over(&self.game_engine, ticks, run.deaths, default_ticks)
+        {
+            return;
+        }
+
+        let survivors = self.game_engine.tanks.iter().map(|t| t.player_info.team);
+        match run.scenario.winner(survivors) {
+            None => run.draws += 1,
+            winner if winner == run.scenario.learner_team() => run.wins += 1,
+            Some(_) => run.losses += 1,
+        }
+        self.restart();
+    }
+
+    /// Adds the matches played in this session to the career stats.
+    fn record_stats(&mut self, ctx: &mut AppContext) {
+        let current = u32::from(self.recorder.tick_count() > 0);
+        ctx.settings.stats.training_matches += self.matches_played + current;
+        ctx.settings.stats.add_shots(&mut self.shots);
+        self.matches_played = 0;
+        if let Err(e) = ctx.settings.save(Path::new(SETTINGS_PATH)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+    }
+
+    /// Writes the match played so far to the replays directory.
+    fn save_replay(&self) {
+        if self.recorder.tick_count() == 0 {
+            return;
+        }
 
-        InputPayload {
-            move_axis: world_move,
-            aim_pos,
-            shoot: shoot_val > 0.0,
+        let replay = self.recorder.replay();
+        let _ = std::fs::create_dir_all(REPLAYS_DIR);
+        // The seed tells apart matches saved within the same second
+        let header = &replay.header;
+        let path = std::path::Path::new(REPLAYS_DIR).join(format!(
+            "{}_{:016x}.{}",
+            header.created_at, header.seed, REPLAY_EXTENSION
+        ));
+        if let Err(e) = replay.save(&path) {
+            eprintln!("Failed to save replay to {}: {}", path.display(), e);
         }
     }
 }
 
+impl Training {
+    /// Evaluation buttons in the top-right corner and its progress or result.
+    fn draw_evaluation(&mut self, has_input: bool) {
+        let x = CANONICAL_SCREEN_WIDTH - BUTTON_W / 2. - 20.;
+        let opponent = self.evaluation_opponent().name();
+        let idle = has_input && !self.is_evaluating();
+
+        self.next_opponent_clicked |= Button::default()
+            .draw_centered(
+                x,
+                40.,
+                BUTTON_W,
+                BUTTON_H * 0.8,
+                Some(&tr!("training.eval_opponent", name = opponent)),
+                idle,
+            )
+            .poll();
+        self.evaluate_clicked |= Button::default()
+            .draw_centered(
+                x,
+                40. + BUTTON_H,
+                BUTTON_W,
+                BUTTON_H * 0.8,
+                Some(&tr!("training.evaluate")),
+                idle,
+            )
+            .poll();
+
+        let line = match (&self.evaluation, &self.evaluation_error) {
+            (_, Some(error)) => tr!("training.eval_failed", error = error),
+            (Some(e), None) if !e.is_done() => tr!(
+                "training.evaluating",
+                name = e.opponent,
+                played = e.played(),
+                total = e.total
+            ),
+            (Some(e), None) => tr!(
+                "training.eval_result",
+                name = e.opponent,
+                win_rate = format!("{:.0}", e.win_rate().unwrap_or(0.) * 100.),
+                draws = e.draws(),
+                score = format!("{:.0}", e.average_score().unwrap_or(0.))
+            ),
+            (None, None) => return,
+        };
+        ui::Text::new_scaled(ui::TEXT_SMALL).draw(&line, CANONICAL_SCREEN_WIDTH / 2., 80.);
+    }
+}
+
 impl View for Training {
-    fn update(&mut self, _ctx: &mut AppContext) -> Transition {
+    fn update(&mut self, ctx: &mut AppContext) -> Transition {
         if is_key_pressed(KeyCode::R) {
-            *self = Self::new(self.brain.clone(), self.mode);
+            self.balance_error = reload_balance().err();
+            self.restart();
             return Transition::None;
         }
         if is_key_pressed(KeyCode::Escape) {
+            self.save_replay();
+            self.record_stats(ctx);
             return Transition::Pop;
         }
 
+        if std::mem::take(&mut self.next_opponent_clicked) && !self.is_evaluating() {
+            self.next_evaluation_opponent();
+        }
+        if std::mem::take(&mut self.evaluate_clicked) && !self.is_evaluating() {
+            self.start_evaluation();
+        }
+        if let Some(evaluation) = &mut self.evaluation {
+            evaluation.poll();
+        }
+        if self.is_evaluating() {
+            return Transition::None;
+        }
+
         let dt = get_frame_time();
         let mut inputs = std::collections::HashMap::new();
 
         if let Some(hid) = self.human_id {
-            let input = Game::gather_user_input(&self.game_engine);
+            let input = Game::gather_user_input(&self.game_engine, Some(hid), &ctx.settings);
             inputs.insert(hid, input);
         }
 
@@ -148,6 +499,26 @@ impl View for Training {
             if Some(player.player_info.id) == self.human_id {
                 continue;
             }
+            let ping = self
+                .game_engine
+                .blackboard
+                .ping_for(player, &self.game_engine.map);
+            if let Some(bot) = self
+                .scenario
+                .as_mut()
+                .and_then(|run| run.bots.get_mut(&player.player_info.id))
+            {
+                let input = bot.generate_input(
+                    player,
+                    &self.game_engine.tanks,
+                    &self.game_engine.projectiles,
+                    &self.game_engine.map,
+                    dt,
+                    ping,
+                );
+                inputs.insert(player.player_info.id, input);
+                continue;
+            }
 
             let ctx = BotContext {
                 me: player,
@@ -156,32 +527,134 @@ impl View for Training {
                 map: &self.game_engine.map,
                 dt,
                 rng: &mut self.rng,
+                ping,
+            };
+            let brain = match &self.comparison {
+                Some(comparison) if player.player_info.team == Team::Red => &comparison.red_brain,
+                _ => &self.brain,
             };
-            let output = self
-                .brain
-                .forward(extract_features(&ctx, &Default::default()));
+            let features = extract_features_with(&ctx, brain.layout(), &Default::default());
+            let output = brain.forward(features);
             let values = output.into_data().to_vec::<f32>().unwrap();
-            inputs.insert(
-                player.player_info.id,
-                Self::bot_action_to_input(&values, &ctx),
+            inputs.insert(player.player_info.id, action_to_input(&values, ctx.me));
+        }
+
+        self.recorder.record(dt, &inputs);
+        let previous_tanks = self.game_engine.tanks.clone();
+        let previous_projectiles = self.game_engine.projectiles.clone();
+        let result = self.game_engine.tick(dt, inputs);
+        if let Some(hid) = self.human_id {
+            self.shots.observe(
+                hid,
+                (&previous_tanks, &previous_projectiles),
+                (&self.game_engine.tanks, &self.game_engine.projectiles),
             );
         }
+        self.match_time += dt;
+        if let Some(run) = &mut self.scenario {
+            run.deaths += result.kills.len();
+        }
+        self.record_comparison_result(result.outcome);
+        self.record_scenario_result();
 
-        self.game_engine.tick(dt, inputs);
+        if self.mode != TrainingMode::HumanVsAi {
+            for hit in &result.damage {
+                self.camera
+                    .record_damage(Some(hit.attacker_id), hit.victim_id, hit.amount);
+            }
+            for kill in &result.kills {
+                self.camera.record_kill(kill);
+            }
+            let map = &self.game_engine.map;
+            self.camera.handle_input(map, &self.game_engine.tanks, dt);
+            self.camera.update(map, &self.game_engine.tanks, dt);
+        }
 
         Transition::None
     }
 
-    fn draw(&mut self, _ctx: &AppContext, _has_input: bool) {
+    fn draw(&mut self, ctx: &AppContext, has_input: bool) {
         let x_mid = CANONICAL_SCREEN_WIDTH / 2.;
 
-        Game::draw_game_board(&self.game_engine, self.human_id);
+        match self.mode {
+            TrainingMode::Spectator | TrainingMode::Comparison | TrainingMode::Scenario => {
+                Game::draw_game_board_with_transform(
+                    &self.game_engine,
+                    self.camera.target(),
+                    self.camera.transform(&self.game_engine.map),
+                    ctx.settings.fog_of_war,
+                );
+                self.camera
+                    .draw_hud(&self.game_engine.map, &self.game_engine.tanks);
+            }
+            TrainingMode::HumanVsAi => {
+                Game::draw_game_board(&self.game_engine, self.human_id, ctx.settings.fog_of_war);
+                if let Some(tank) = self
+                    .game_engine
+                    .tanks
+                    .iter()
+                    .find(|t| Some(t.player_info.id) == self.human_id)
+                {
+                    hud::draw_player_hud(tank, ctx.settings.crosshair);
+                }
+            }
+        }
 
         let mode_str = match self.mode {
-            TrainingMode::Spectator => "SPECTATOR",
-            TrainingMode::HumanVsAi => "PLAYING",
+            TrainingMode::Spectator => tr!("training.spectator"),
+            TrainingMode::HumanVsAi => tr!("training.playing"),
+            TrainingMode::Comparison => tr!("training.comparison"),
+            TrainingMode::Scenario => tr!("training.scenario"),
         };
-        ui::Text::new_scaled(20).draw(&format!("{} | Reset: R | Exit: ESC", mode_str), x_mid, 30.);
+        ui::Text::new_scaled(20).draw(&tr!("training.hud", mode = mode_str), x_mid, 30.);
+
+        if let Some(c) = &self.comparison {
+            ui::Text::new_scaled(20).draw(
+                &tr!(
+                    "training.comparison_score",
+                    blue = c.blue_name,
+                    blue_wins = c.blue_wins,
+                    red = c.red_name,
+                    red_wins = c.red_wins,
+                    draws = c.draws
+                ),
+                x_mid,
+                55.,
+            );
+        }
+        if self.can_evaluate() {
+            self.draw_evaluation(has_input);
+        }
+        if let Some(error) = &self.balance_error {
+            ui::Text::new_scaled(ui::TEXT_SMALL).draw(
+                &tr!("training.balance_failed", error = error),
+                x_mid,
+                105.,
+            );
+        }
+        if let Some(run) = &self.scenario {
+            ui::Text::new_scaled(20).draw(
+                &tr!(
+                    "training.scenario_score",
+                    name = run.scenario.name,
+                    wins = run.wins,
+                    losses = run.losses,
+                    draws = run.draws
+                ),
+                x_mid,
+                55.,
+            );
+        }
+    }
+
+    fn presence(&self, _ctx: &AppContext) -> Option<Presence> {
+        let activity = match self.mode {
+            TrainingMode::Spectator => tr!("presence.training_watch"),
+            TrainingMode::HumanVsAi => tr!("presence.training_play"),
+            TrainingMode::Comparison => tr!("presence.training_compare"),
+            TrainingMode::Scenario => tr!("presence.training_scenario"),
+        };
+        Some(Presence::Training { activity })
     }
 
     fn get_id(&self) -> ViewId {
@@ -198,7 +671,7 @@ mod tests {
         let device = Default::default();
         let brain = BotBrain::<ClientBackend>::new(&device);
 
-        let state = Training::new(brain, TrainingMode::Spectator);
+        let state = Training::new(brain, TrainingMode::Spectator, MapDefinition::load());
 
         assert_eq!(state.mode, TrainingMode::Spectator);
         assert!(state.human_id.is_none());
@@ -211,10 +684,106 @@ mod tests {
         let device = Default::default();
         let brain = BotBrain::<ClientBackend>::new(&device);
 
-        let state = Training::new(brain, TrainingMode::HumanVsAi);
+        let state = Training::new(brain, TrainingMode::HumanVsAi, MapDefinition::load());
 
         assert_eq!(state.mode, TrainingMode::HumanVsAi);
         assert_eq!(state.human_id, Some(0));
         assert_eq!(state.game_engine.tanks.len(), 5);
     }
+
+    #[test]
+    fn test_comparison_counts_wins_and_restarts() {
+        let device = Default::default();
+        let blue = BotBrain::<ClientBackend>::new(&device);
+        let red = BotBrain::<ClientBackend>::new(&device);
+
+        let mut state = Training::new_comparison(
+            "a.bin".into(),
+            blue,
+            "b.bin".into(),
+            red,
+            MapDefinition::load(),
+        );
+        assert_eq!(state.mode, TrainingMode::Comparison);
+        assert_eq!(state.game_engine.tanks.len(), 8);
+
+        state
+            .game_engine
+            .tanks
+            .retain(|t| t.player_info.team == Team::Red);
+        state.record_comparison_result(RoundOutcome::Won(Team::Red));
+
+        let comparison = state.comparison.as_ref().unwrap();
+        assert_eq!(comparison.red_wins, 1);
+        assert_eq!(comparison.blue_wins, 0);
+        assert_eq!(state.game_engine.tanks.len(), 8);
+    }
+
+    #[test]
+    fn test_scenario_is_played_and_scored() {
+        let device = Default::default();
+        let brain = BotBrain::<ClientBackend>::new(&device);
+        let scenario =
+            Scenario::load(&Path::new("../../assets/scenarios").join("corner_2v1.ron")).unwrap();
+
+        let mut state = Training::new_scenario(brain, scenario);
+        assert_eq!(state.mode, TrainingMode::Scenario);
+        assert_eq!(state.game_engine.tanks.len(), 3);
+        let run = state.scenario.as_ref().unwrap();
+        assert_eq!(run.bots.len(), 1);
+        assert!(run.bots.contains_key(&2));
+
+        state
+            .game_engine
+            .tanks
+            .retain(|t| t.player_info.team == Team::Blue);
+        state.record_scenario_result();
+        assert_eq!(state.scenario.as_ref().unwrap().wins, 1);
+        assert_eq!(state.game_engine.tanks.len(), 3);
+    }
+
+    #[test]
+    fn test_evaluation_opponent_cycles() {
+        let device = Default::default();
+        let blue = BotBrain::<ClientBackend>::new(&device);
+        let red = BotBrain::<ClientBackend>::new(&device);
+
+        let mut state = Training::new_comparison(
+            "a.bin".into(),
+            blue,
+            "b.bin".into(),
+            red,
+            MapDefinition::load(),
+        );
+        assert_eq!(state.evaluation_opponent().name(), "b.bin");
+        state.next_evaluation_opponent();
+        assert_eq!(state.evaluation_opponent().name(), "Dummy (scripted)");
+        for _ in 0..EVALUATION_OPPONENTS.len() {
+            state.next_evaluation_opponent();
+        }
+        assert_eq!(state.evaluation_opponent().name(), "b.bin");
+    }
+
+    #[test]
+    fn test_comparison_times_out_as_draw() {
+        let device = Default::default();
+        let blue = BotBrain::<ClientBackend>::new(&device);
+        let red = BotBrain::<ClientBackend>::new(&device);
+
+        let mut state = Training::new_comparison(
+            "a.bin".into(),
+            blue,
+            "b.bin".into(),
+            red,
+            MapDefinition::load(),
+        );
+
+        state.record_comparison_result(RoundOutcome::Ongoing);
+        assert_eq!(state.comparison.as_ref().unwrap().draws, 0);
+
+        state.match_time = COMPARISON_MATCH_SECS;
+        state.record_comparison_result(RoundOutcome::Ongoing);
+        assert_eq!(state.comparison.as_ref().unwrap().draws, 1);
+        assert_eq!(state.match_time, 0.0);
+    }
 }
@@ -1,10 +1,17 @@
-use crate::app::training::{Training, TrainingMode};
-use crate::app::{AppContext, Transition, View, ViewId};
+use crate::app::map_preview::MapPreviews;
+use crate::app::map_select::{MapSelect, PickedMap};
+use crate::app::model_select::{ModelSelect, PickedModel};
+use crate::app::training::{SCENARIOS_DIR, Training, TrainingMode};
+use crate::app::{AppContext, Transition, View, ViewId, ViewResult};
+use crate::i18n::tr;
 use crate::ui::{self};
 use crate::ui::{BUTTON_H, BUTTON_W, Button, CANONICAL_SCREEN_WIDTH};
 use burn::backend::Wgpu;
 
-use common::rl::BotBrain;
+use common::game::MapDefinition;
+use common::game::map_registry::MapRegistry;
+use common::rl::{BotBrain, Scenario};
+use std::path::Path;
 
 use macroquad::prelude::*;
 
@@ -15,17 +22,42 @@ pub(crate) struct TrainingModeSelect {
     brain: BotBrain<ClientBackend>,
     back_clicked: bool,
     chosen_training_mode: Option<TrainingMode>,
+    scenarios: Vec<Scenario>,
+    chosen_scenario: Option<usize>,
+    maps: MapRegistry,
+    map_names: Vec<String>,
+    /// Index into `map_names` of the map of every mode but the scenarios
+    map_index: usize,
+    previews: MapPreviews,
+    map_clicked: bool,
+    /// The Red team model picked for a comparison, to start it with
+    opponent: Option<PickedModel>,
 }
 
 impl TrainingModeSelect {
-    pub fn new(model_name: String, brain: BotBrain<ClientBackend>) -> Self {
+    pub fn new(model_name: String, brain: BotBrain<ClientBackend>, maps: MapRegistry) -> Self {
+        let map_names = maps.names().map(String::from).collect();
         Self {
             model_name,
             brain,
             back_clicked: false,
             chosen_training_mode: None,
+            scenarios: Scenario::load_dir(Path::new(SCENARIOS_DIR)),
+            chosen_scenario: None,
+            maps,
+            map_names,
+            map_index: 0,
+            previews: MapPreviews::default(),
+            map_clicked: false,
+            opponent: None,
         }
     }
+
+    fn map(&self) -> MapDefinition {
+        self.maps
+            .resolve(&self.map_names[self.map_index])
+            .expect("the registry lists its own maps")
+    }
 }
 
 impl View for TrainingModeSelect {
@@ -35,8 +67,39 @@ impl View for TrainingModeSelect {
             return Transition::Pop;
         }
 
+        if std::mem::take(&mut self.map_clicked) {
+            return Transition::Push(Box::new(MapSelect::new(
+                self.maps.clone(),
+                self.map_names[self.map_index].clone(),
+            )));
+        }
+
+        if let Some(opponent) = self.opponent.take() {
+            return Transition::Push(Box::new(Training::new_comparison(
+                self.model_name.clone(),
+                self.brain.clone(),
+                opponent.name,
+                opponent.brain,
+                self.map(),
+            )));
+        }
+
+        if let Some(i) = self.chosen_scenario.take() {
+            return Transition::Push(Box::new(Training::new_scenario(
+                self.brain.clone(),
+                self.scenarios[i].clone(),
+            )));
+        }
+
         if let Some(mode) = self.chosen_training_mode.take() {
-            return Transition::Push(Box::new(Training::new(self.brain.clone(), mode)));
+            return match mode {
+                TrainingMode::Comparison => Transition::Push(Box::new(ModelSelect::new_opponent())),
+                _ => Transition::Push(Box::new(Training::new(
+                    self.brain.clone(),
+                    mode,
+                    self.map(),
+                ))),
+            };
         }
 
         Transition::None
@@ -44,25 +107,45 @@ impl View for TrainingModeSelect {
 
     fn draw(&mut self, _ctx: &AppContext, has_input: bool) {
         let x_mid = CANONICAL_SCREEN_WIDTH / 2.;
-        let mut layout = ui::Layout::new(100., 30.);
+        let mut layout = ui::Layout::new(80., 20.);
 
-        ui::Text::new_title().draw("Select Mode", x_mid, layout.next());
+        ui::Text::new_title().draw(&tr!("training_mode.title"), x_mid, layout.next());
         layout.add(50.);
 
         ui::Text::new_scaled(ui::TEXT_MID).draw(
-            &format!("Model: {}", self.model_name),
+            &tr!("training_mode.model", model = self.model_name),
             x_mid,
             layout.next(),
         );
         layout.add(50.);
 
+        let map_name = &self.map_names[self.map_index];
+        let map = self.maps.get(map_name);
+        self.previews.draw(
+            map_name,
+            || map.cloned().expect("the registry lists its own maps"),
+            x_mid + BUTTON_W * 0.75 + 90.,
+            layout.next(),
+        );
+        self.map_clicked = Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W * 1.5,
+                BUTTON_H,
+                Some(&tr!("training_mode.map", name = map_name)),
+                has_input,
+            )
+            .poll();
+        layout.add(BUTTON_H);
+
         if Button::default()
             .draw_centered(
                 x_mid,
                 layout.next(),
                 BUTTON_W * 1.5,
                 BUTTON_H,
-                Some("Spectator (4v4)"),
+                Some(&tr!("training_mode.spectator")),
                 has_input,
             )
             .poll()
@@ -77,7 +160,7 @@ impl View for TrainingModeSelect {
                 layout.next(),
                 BUTTON_W * 1.5,
                 BUTTON_H,
-                Some("Play Solo vs 4 Bots"),
+                Some(&tr!("training_mode.solo")),
                 has_input,
             )
             .poll()
@@ -86,18 +169,65 @@ impl View for TrainingModeSelect {
         }
         layout.add(BUTTON_H);
 
+        if Button::default()
+            .draw_centered(
+                x_mid,
+                layout.next(),
+                BUTTON_W * 1.5,
+                BUTTON_H,
+                Some(&tr!("training_mode.compare")),
+                has_input,
+            )
+            .poll()
+        {
+            self.chosen_training_mode = Some(TrainingMode::Comparison);
+        }
+        layout.add(BUTTON_H);
+
+        for (i, scenario) in self.scenarios.iter().enumerate() {
+            if Button::default()
+                .draw_centered(
+                    x_mid,
+                    layout.next(),
+                    BUTTON_W * 1.5,
+                    BUTTON_H,
+                    Some(&tr!("training_mode.scenario", name = scenario.name)),
+                    has_input,
+                )
+                .poll()
+            {
+                self.chosen_scenario = Some(i);
+            }
+            layout.add(BUTTON_H);
+        }
+
         self.back_clicked = Button::default()
             .draw_centered(
                 x_mid,
                 layout.next(),
                 BUTTON_W,
                 BUTTON_H,
-                Some("Back"),
+                Some(&tr!("common.back")),
                 has_input,
             )
             .poll();
     }
 
+    fn on_result(&mut self, _ctx: &mut AppContext, result: ViewResult) {
+        let result = match result.downcast::<PickedMap>() {
+            Ok(PickedMap(name)) => {
+                if let Some(i) = self.map_names.iter().position(|n| *n == name) {
+                    self.map_index = i;
+                }
+                return;
+            }
+            Err(result) => result,
+        };
+        if let Ok(opponent) = result.downcast::<PickedModel>() {
+            self.opponent = Some(opponent);
+        }
+    }
+
     fn get_id(&self) -> ViewId {
         ViewId::TrainingModeSelect
     }
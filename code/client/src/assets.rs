@@ -0,0 +1,301 @@
+//! Textures, fonts and sounds read from disk, cached by path and handed out as typed
+//! [`Handle`]s.
+//!
+//! Assets registered before the app starts are loaded by [`load_pending`] behind a
+//! loading screen; anything asked for later is loaded the first time it is used. Debug
+//! builds reload files changed on disk, see [`reload_changed`]. Loaded assets live for
+//! the rest of the run, so a reloaded one leaves its old version behind.
+
+use futures::executor::block_on;
+use macroquad::audio::{Sound, load_sound_from_bytes};
+use macroquad::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Seconds between checks for changed files
+const RELOAD_INTERVAL: f64 = 1.;
+
+thread_local! {
+    /// Macroquad draws from the main thread only, so that is where the assets are
+    static ASSETS: RefCell<Assets> = RefCell::new(Assets::default());
+}
+
+/// Something that can be loaded from the bytes of a file.
+pub(crate) trait Asset: Sized + 'static {
+    fn decode(bytes: &[u8]) -> Result<Self, String>;
+
+    fn store(assets: &mut Assets) -> &mut Store<Self>;
+}
+
+impl Asset for Texture2D {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let image = Image::from_file_with_format(bytes, None).map_err(|e| e.to_string())?;
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Nearest); // Better for pixel art
+        Ok(texture)
+    }
+
+    fn store(assets: &mut Assets) -> &mut Store<Self> {
+        &mut assets.textures
+    }
+}
+
+impl Asset for Font {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut font = load_ttf_font_from_bytes(bytes).map_err(|e| e.to_string())?;
+        font.set_filter(FilterMode::Nearest); // Better results for a pixelated font
+        Ok(font)
+    }
+
+    fn store(assets: &mut Assets) -> &mut Store<Self> {
+        &mut assets.fonts
+    }
+}
+
+impl Asset for Sound {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        block_on(load_sound_from_bytes(bytes)).map_err(|e| e.to_string())
+    }
+
+    fn store(assets: &mut Assets) -> &mut Store<Self> {
+        &mut assets.sounds
+    }
+}
+
+/// An asset of type `T`, loaded or not yet.
+pub(crate) struct Handle<T> {
+    index: usize,
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T: Asset> Handle<T> {
+    /// The asset at `path`, registered to be loaded if it wasn't yet.
+    pub fn of(path: &str) -> Self {
+        ASSETS.with_borrow_mut(|assets| T::store(assets).register(path))
+    }
+
+    /// The asset, loading it now if it wasn't yet. Panics if it can't be loaded, as the
+    /// game can't go without its files.
+    pub fn get(self) -> &'static T {
+        if let Some(asset) = self.loaded() {
+            return asset;
+        }
+        let path =
+            ASSETS.with_borrow_mut(|assets| T::store(assets).entries[self.index].path.clone());
+        let loaded = read::<T>(&path).unwrap_or_else(|e| panic!("{}", e));
+        ASSETS.with_borrow_mut(|assets| T::store(assets).set(self.index, loaded))
+    }
+
+    /// The asset if it was loaded, for those the game can go without.
+    pub fn loaded(self) -> Option<&'static T> {
+        ASSETS.with_borrow_mut(|assets| T::store(assets).entries[self.index].asset)
+    }
+}
+
+struct Entry<T: 'static> {
+    path: String,
+    asset: Option<&'static T>,
+    /// When the file was changed as of loading it
+    modified: Option<SystemTime>,
+}
+
+/// The assets of one type.
+pub(crate) struct Store<T: 'static> {
+    entries: Vec<Entry<T>>,
+    by_path: HashMap<String, usize>,
+}
+
+impl<T: 'static> Default for Store<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            by_path: HashMap::new(),
+        }
+    }
+}
+
+impl<T: 'static> Store<T> {
+    fn register(&mut self, path: &str) -> Handle<T> {
+        let index = *self.by_path.entry(path.to_string()).or_insert_with(|| {
+            self.entries.push(Entry {
+                path: path.to_string(),
+                asset: None,
+                modified: None,
+            });
+            self.entries.len() - 1
+        });
+        Handle {
+            index,
+            _asset: PhantomData,
+        }
+    }
+
+    fn set(&mut self, index: usize, (asset, modified): (T, Option<SystemTime>)) -> &'static T {
+        let asset = Box::leak(Box::new(asset));
+        let entry = &mut self.entries[index];
+        entry.asset = Some(asset);
+        entry.modified = modified;
+        asset
+    }
+
+    /// Paths registered but not loaded yet, with their indices.
+    fn pending(&self) -> Vec<(usize, String)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.asset.is_none())
+            .map(|(index, entry)| (index, entry.path.clone()))
+            .collect()
+    }
+
+    /// Loaded paths whose files changed since, with their indices.
+    fn changed(&self, modified: impl Fn(&str) -> Option<SystemTime>) -> Vec<(usize, String)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.asset.is_some() && modified(&entry.path) > entry.modified)
+            .map(|(index, entry)| (index, entry.path.clone()))
+            .collect()
+    }
+}
+
+/// Every cached asset, by type.
+#[derive(Default)]
+pub(crate) struct Assets {
+    textures: Store<Texture2D>,
+    fonts: Store<Font>,
+    sounds: Store<Sound>,
+    /// When files were last checked for changes
+    checked_at: f64,
+}
+
+/// Loads every asset registered so far, a frame each, calling `progress` with how many of
+/// how many are done to draw them. Fonts go first, so the rest can be shown loading with
+/// text. Gives why the assets that failed couldn't be loaded.
+pub(crate) async fn load_pending(mut progress: impl FnMut(usize, usize)) -> Vec<String> {
+    let (fonts, textures, sounds) = ASSETS.with_borrow(|assets| {
+        (
+            assets.fonts.pending(),
+            assets.textures.pending(),
+            assets.sounds.pending(),
+        )
+    });
+    let total = fonts.len() + textures.len() + sounds.len();
+    let mut done = 0;
+    let mut problems = Vec::new();
+    progress(done, total);
+    let mut step = |problem: Option<String>| {
+        problems.extend(problem);
+        done += 1;
+        progress(done, total);
+    };
+    for (index, path) in fonts {
+        step(load_into::<Font>(index, &path).await);
+    }
+    for (index, path) in textures {
+        step(load_into::<Texture2D>(index, &path).await);
+    }
+    for (index, path) in sounds {
+        step(load_into::<Sound>(index, &path).await);
+    }
+    problems
+}
+
+/// Loads the asset at `path` into the entry `index` of its store, or says why it couldn't.
+async fn load_into<T: Asset>(index: usize, path: &str) -> Option<String> {
+    // Give the loading screen a frame
+    next_frame().await;
+    match read::<T>(path) {
+        Ok(loaded) => {
+            ASSETS.with_borrow_mut(|assets| T::store(assets).set(index, loaded));
+            None
+        }
+        Err(e) => Some(e),
+    }
+}
+
+/// Reloads the assets whose files changed, at most once every [`RELOAD_INTERVAL`] and only
+/// in debug builds. Gives what happened to each, to be logged.
+pub(crate) fn reload_changed(time: f64) -> Vec<Result<String, String>> {
+    let due = ASSETS.with_borrow_mut(|assets| {
+        let due = time - assets.checked_at >= RELOAD_INTERVAL;
+        if due {
+            assets.checked_at = time;
+        }
+        due
+    });
+    if !cfg!(debug_assertions) || !due {
+        return Vec::new();
+    }
+    let mut reloaded = reload::<Font>();
+    reloaded.extend(reload::<Texture2D>());
+    reloaded.extend(reload::<Sound>());
+    reloaded
+}
+
+fn reload<T: Asset>() -> Vec<Result<String, String>> {
+    let changed = ASSETS.with_borrow_mut(|assets| T::store(assets).changed(modified));
+    changed
+        .into_iter()
+        .map(|(index, path)| {
+            let loaded = read::<T>(&path)?;
+            ASSETS.with_borrow_mut(|assets| T::store(assets).set(index, loaded));
+            Ok(path)
+        })
+        .collect()
+}
+
+/// The asset in the file at `path`, with when the file was changed.
+fn read<T: Asset>(path: &str) -> Result<(T, Option<SystemTime>), String> {
+    let modified = modified(path);
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+    let asset = T::decode(&bytes).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+    Ok((asset, modified))
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(Path::new(path)).ok()?.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_assets_are_cached_by_path() {
+        let mut store = Store::<u32>::default();
+        let a = store.register("assets/a.png");
+        let b = store.register("assets/b.png");
+        assert_eq!(store.register("assets/a.png").index, a.index);
+        assert_ne!(a.index, b.index);
+
+        store.set(b.index, (7, None));
+        assert_eq!(store.pending(), vec![(a.index, "assets/a.png".to_string())]);
+    }
+
+    #[test]
+    fn test_only_files_changed_since_loading_are_reloaded() {
+        let mut store = Store::<u32>::default();
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let later = then + Duration::from_secs(1);
+        for path in ["old", "new", "unloaded"] {
+            store.register(path);
+        }
+        store.set(0, (1, Some(then)));
+        store.set(1, (2, Some(then)));
+        let modified = |path: &str| Some(if path == "new" { later } else { then });
+        assert_eq!(store.changed(modified), vec![(1, "new".to_string())]);
+    }
+}
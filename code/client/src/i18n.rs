@@ -0,0 +1,155 @@
+//! UI strings by key, loaded from one JSON file per language in `assets/lang/`.
+//!
+//! Strings may contain `{name}` placeholders, filled in by [`tr!`]. Keys missing from
+//! the active language fall back to English, which is built into the binary.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+pub(crate) const LANG_DIR: &str = "assets/lang";
+pub(crate) const DEFAULT_LANGUAGE: &str = "en";
+const ENGLISH: &str = include_str!("../../../assets/lang/en.json");
+const NAME_KEY: &str = "language.name";
+
+type Strings = HashMap<String, String>;
+
+struct Localizer {
+    strings: Strings,
+    fallback: Strings,
+}
+
+thread_local! {
+    static LOCALIZER: RefCell<Localizer> = RefCell::new(Localizer {
+        strings: Strings::new(),
+        fallback: serde_json::from_str(ENGLISH).expect("Built-in English strings are valid."),
+    });
+}
+
+fn load_strings(dir: &Path, language: &str) -> Result<Strings, String> {
+    let path = dir.join(format!("{}.json", language));
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Switches the UI to `language` (a file name in `assets/lang/` without `.json`).
+pub(crate) fn set_language(language: &str) -> Result<(), String> {
+    let strings = if language == DEFAULT_LANGUAGE {
+        Strings::new()
+    } else {
+        load_strings(Path::new(LANG_DIR), language)?
+    };
+    LOCALIZER.with_borrow_mut(|localizer| localizer.strings = strings);
+    Ok(())
+}
+
+/// Codes and display names of the languages in `dir`, sorted by code.
+pub(crate) fn available_languages(dir: &Path) -> Vec<(String, String)> {
+    let mut languages: Vec<(String, String)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let code = path.file_stem()?.to_str()?.to_string();
+            let name = load_strings(dir, &code).ok()?.remove(NAME_KEY)?;
+            Some((code, name))
+        })
+        .collect();
+    if !languages.iter().any(|(code, _)| code == DEFAULT_LANGUAGE) {
+        languages.push((DEFAULT_LANGUAGE.to_string(), "English".to_string()));
+    }
+    languages.sort();
+    languages
+}
+
+fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+/// Looks up `key` and fills in its placeholders; unknown keys are shown as-is.
+pub(crate) fn translate(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    LOCALIZER.with_borrow(|localizer| {
+        let template = localizer
+            .strings
+            .get(key)
+            .or_else(|| localizer.fallback.get(key))
+            .map_or(key, String::as_str);
+        fill(template, args)
+    })
+}
+
+/// `tr!("key")` or `tr!("key", name = value, ...)`.
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::translate(
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+pub(crate) use tr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(strings: &Strings) -> Vec<&String> {
+        let mut keys: Vec<_> = strings.keys().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_placeholders_are_filled() {
+        assert_eq!(tr!("main_menu.quit"), "Quit");
+        assert_eq!(
+            tr!("connect.connecting", attempt = 2, max = 5),
+            "Connecting... (attempt 2/5)"
+        );
+        assert_eq!(tr!("no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_switching_language() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .join(LANG_DIR);
+        let pl = load_strings(&dir, "pl").unwrap();
+        LOCALIZER.with_borrow_mut(|localizer| localizer.strings = pl);
+        assert_eq!(tr!("main_menu.quit"), "Wyjdź");
+
+        set_language(DEFAULT_LANGUAGE).unwrap();
+        assert_eq!(tr!("main_menu.quit"), "Quit");
+        assert!(set_language("xx").is_err());
+        assert_eq!(tr!("main_menu.quit"), "Quit");
+    }
+
+    #[test]
+    fn test_translations_have_every_key() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .join(LANG_DIR);
+        let english: Strings = serde_json::from_str(ENGLISH).unwrap();
+        let languages = available_languages(&dir);
+        assert!(languages.len() >= 2);
+
+        for (code, _) in languages {
+            let strings = load_strings(&dir, &code).unwrap();
+            assert_eq!(keys(&strings), keys(&english), "{}.json", code);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Links that take a player straight to a game: `neuroblasters://join/<server>/<code>`.
+//! Lobbies hand them out to copy or scan, and the client takes one as its first argument,
+//! so operating systems that know the scheme can open the game from a link.
+
+use common::protocol::GameCode;
+use std::fmt;
+
+const PREFIX: &str = "neuroblasters://join/";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JoinLink {
+    /// Address the game's players connect to, as typed in the connect menu
+    pub server: String,
+    pub code: GameCode,
+}
+
+impl JoinLink {
+    /// Reads a link, with or without a trailing slash; anything else is `None`.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.trim().strip_prefix(PREFIX)?;
+        let (server, code) = rest.trim_end_matches('/').rsplit_once('/')?;
+        let valid = !server.is_empty()
+            && !server.contains('/')
+            && !code.is_empty()
+            && code.chars().all(|c| c.is_ascii_alphanumeric());
+        valid.then(|| Self {
+            server: server.to_string(),
+            code: GameCode(code.to_string()),
+        })
+    }
+
+    /// The first link among the client's command line arguments.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        args.into_iter().find_map(|arg| Self::parse(&arg))
+    }
+}
+
+impl fmt::Display for JoinLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}/{}", PREFIX, self.server, self.code.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_links_read_back_what_they_print() {
+        let link = JoinLink {
+            server: "eu.example.com:8080".into(),
+            code: GameCode("4821".into()),
+        };
+        assert_eq!(
+            link.to_string(),
+            "neuroblasters://join/eu.example.com:8080/4821"
+        );
+        assert_eq!(JoinLink::parse(&link.to_string()), Some(link.clone()));
+        assert_eq!(
+            JoinLink::parse("neuroblasters://join/eu.example.com:8080/4821/"),
+            Some(link.clone())
+        );
+        let args = ["client".to_string(), "--verbose".into(), link.to_string()];
+        assert_eq!(JoinLink::from_args(args), Some(link));
+    }
+
+    #[test]
+    fn test_malformed_join_links_are_ignored() {
+        for uri in [
+            "neuroblasters://join/4821",
+            "neuroblasters://join//4821",
+            "neuroblasters://join/host/",
+            "neuroblasters://join/host/48 21",
+            "neuroblasters://join/a/b/4821",
+            "https://join/host/4821",
+        ] {
+            assert_eq!(JoinLink::parse(uri), None, "{}", uri);
+        }
+    }
+}
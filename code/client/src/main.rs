@@ -1,17 +1,39 @@
-use app::App;
+#![recursion_limit = "256"]
+
+use app::{App, CRASH_DIR};
+use join_link::JoinLink;
 use macroquad::miniquad::conf::Icon;
+use macroquad::miniquad::conf::Platform;
 use macroquad::prelude::*;
+use settings::{SETTINGS_PATH, Settings};
+use std::path::Path;
 
 mod app;
+mod assets;
+mod i18n;
+mod join_link;
+mod model_cache;
+mod net_conditioner;
+mod presence;
 mod server;
+mod server_probe;
+mod settings;
+mod stats;
 mod ui;
 
 fn window_conf() -> Conf {
+    let graphics = Settings::load(Path::new(SETTINGS_PATH)).graphics;
+    let (width, height) = graphics.resolution;
     Conf {
         window_title: "NeuroBlasters".into(),
-        window_width: 1080,
-        window_height: 720,
+        window_width: width as i32,
+        window_height: height as i32,
+        fullscreen: graphics.fullscreen,
         icon: Some(load_icon()),
+        platform: Platform {
+            swap_interval: Some(i32::from(graphics.vsync)),
+            ..Default::default()
+        },
         ..Default::default()
     }
 }
@@ -36,6 +58,7 @@ fn load_icon() -> Icon {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-    let mut app = App::new().await;
+    common::crash::install_panic_hook("client", common::version(), CRASH_DIR.into());
+    let mut app = App::new(JoinLink::from_args(std::env::args().skip(1))).await;
     app.run().await;
 }
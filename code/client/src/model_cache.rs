@@ -0,0 +1,45 @@
+//! Bot models downloaded from servers, stored by hash so the same model is only
+//! downloaded once.
+
+use common::protocol::ModelHash;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) const MODEL_CACHE_DIR: &str = "assets/models/downloaded";
+
+/// Where the model with `hash` is stored; `burn` recorders take the path without `.bin`.
+pub(crate) fn path(dir: &Path, hash: &ModelHash) -> PathBuf {
+    dir.join(hash.to_string())
+}
+
+pub(crate) fn contains(dir: &Path, hash: &ModelHash) -> bool {
+    path(dir, hash).with_extension("bin").is_file()
+}
+
+/// Stores a model whose bytes were already checked against `hash`.
+pub(crate) fn store(dir: &Path, hash: &ModelHash, bytes: &[u8]) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let file = path(dir, hash).with_extension("bin");
+    fs::write(&file, bytes).map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_lookup() {
+        let dir = std::env::temp_dir().join("neuroblasters_model_cache");
+        let hash = ModelHash::of(b"model");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(!contains(&dir, &hash));
+
+        let file = store(&dir, &hash, b"model").unwrap();
+        assert!(contains(&dir, &hash));
+        assert_eq!(file, path(&dir, &hash).with_extension("bin"));
+        assert_eq!(fs::read(file).unwrap(), b"model");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
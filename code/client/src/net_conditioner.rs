@@ -0,0 +1,95 @@
+//! Makes our own connection worse on purpose, for reproducing lag bugs on one machine:
+//! outgoing inputs are held back and dropped as set in the debug builds' conditioner panel.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Longest latency the panel goes up to.
+pub(crate) const MAX_LATENCY: Duration = Duration::from_secs(1);
+
+/// How bad outgoing inputs have it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Conditions {
+    /// How long inputs are held back before they're sent
+    pub latency: Duration,
+    /// Share of inputs never sent, from 0 to 1
+    pub loss: f32,
+}
+
+impl Conditions {
+    /// Whether inputs go out as they would without the conditioner.
+    pub fn is_perfect(&self) -> bool {
+        self.latency.is_zero() && self.loss <= 0.
+    }
+}
+
+/// Inputs on their way out, oldest first.
+#[derive(Default)]
+pub(crate) struct NetConditioner {
+    pub conditions: Conditions,
+    held: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl NetConditioner {
+    /// Holds `payload` back until the latency has passed, or drops it if `roll` (from 0 to 1)
+    /// falls under the loss. Returns whether it will be sent.
+    pub fn hold(&mut self, payload: Vec<u8>, now: Instant, roll: f32) -> bool {
+        if roll < self.conditions.loss {
+            return false;
+        }
+        self.held
+            .push_back((now + self.conditions.latency, payload));
+        true
+    }
+
+    /// Takes the inputs whose latency has passed by `now`, in the order they were held.
+    pub fn due(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        let count = self.held.iter().take_while(|(due, _)| *due <= now).count();
+        self.held
+            .drain(..count)
+            .map(|(_, payload)| payload)
+            .collect()
+    }
+
+    /// Inputs held back right now.
+    pub fn held(&self) -> usize {
+        self.held.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inputs_wait_out_the_latency_in_order() {
+        let mut conditioner = NetConditioner::default();
+        conditioner.conditions.latency = Duration::from_millis(100);
+        let now = Instant::now();
+        assert!(conditioner.hold(vec![1], now, 0.5));
+        assert!(conditioner.hold(vec![2], now + Duration::from_millis(30), 0.5));
+
+        assert!(conditioner.due(now + Duration::from_millis(99)).is_empty());
+        assert_eq!(conditioner.due(now + Duration::from_millis(100)), [vec![1]]);
+        // Lowering the latency doesn't let later inputs overtake earlier ones
+        conditioner.conditions.latency = Duration::ZERO;
+        assert!(conditioner.hold(vec![3], now + Duration::from_millis(110), 0.5));
+        assert!(conditioner.due(now + Duration::from_millis(120)).is_empty());
+        assert_eq!(
+            conditioner.due(now + Duration::from_millis(130)),
+            [vec![2], vec![3]]
+        );
+        assert_eq!(conditioner.held(), 0);
+    }
+
+    #[test]
+    fn test_inputs_under_the_loss_are_dropped() {
+        let mut conditioner = NetConditioner::default();
+        assert!(conditioner.conditions.is_perfect());
+        conditioner.conditions.loss = 0.25;
+        let now = Instant::now();
+        assert!(!conditioner.hold(vec![1], now, 0.1));
+        assert!(conditioner.hold(vec![2], now, 0.25));
+        assert_eq!(conditioner.due(now), [vec![2]]);
+    }
+}
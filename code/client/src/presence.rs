@@ -0,0 +1,169 @@
+//! Rich presence: a short line about what the player is doing, shown by launchers and chat
+//! apps. Views report a [`Presence`]; the app hands it to a [`PresenceProvider`] whenever it
+//! changes.
+
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Presence {
+    MainMenu,
+    /// Connected to a server, choosing a game
+    Online,
+    Lobby {
+        code: String,
+        players: usize,
+        max_players: usize,
+    },
+    InGame {
+        code: String,
+        round: u8,
+        rounds: u8,
+    },
+    /// Local match against or between models; `activity` is already localized
+    Training {
+        activity: String,
+    },
+    Challenge {
+        name: String,
+    },
+    /// Signed up for a server tournament, between matches
+    Tournament {
+        name: String,
+    },
+    WatchingReplay,
+}
+
+impl Presence {
+    pub fn text(&self) -> String {
+        match self {
+            Presence::MainMenu => tr!("presence.main_menu"),
+            Presence::Online => tr!("presence.online"),
+            Presence::Lobby {
+                code,
+                players,
+                max_players,
+            } => tr!(
+                "presence.lobby",
+                code = code,
+                players = players,
+                max = max_players
+            ),
+            Presence::InGame {
+                code,
+                round,
+                rounds,
+            } => {
+                tr!(
+                    "presence.in_game",
+                    code = code,
+                    round = round,
+                    rounds = rounds
+                )
+            }
+            Presence::Training { activity } => tr!("presence.training", activity = activity),
+            Presence::Challenge { name } => tr!("presence.challenge", name = name),
+            Presence::Tournament { name } => tr!("presence.tournament", name = name),
+            Presence::WatchingReplay => tr!("presence.replay"),
+        }
+    }
+}
+
+/// Somewhere to publish the player's presence.
+pub(crate) trait PresenceProvider {
+    /// Called only when the presence changes.
+    fn update(&mut self, presence: &Presence);
+
+    /// Called when the client shuts down.
+    fn clear(&mut self);
+}
+
+/// Keeps the presence to itself; used when no integration is enabled.
+pub(crate) struct NoPresence;
+
+impl PresenceProvider for NoPresence {
+    fn update(&mut self, _presence: &Presence) {}
+
+    fn clear(&mut self) {}
+}
+
+#[cfg(feature = "discord")]
+mod discord {
+    use super::{Presence, PresenceProvider};
+    use discord_rich_presence::{DiscordIpc, DiscordIpcClient, activity::Activity};
+
+    /// Environment variable holding the Discord application id to publish as.
+    pub(crate) const APP_ID_VAR: &str = "NEUROBLASTERS_DISCORD_APP_ID";
+
+    /// Discord Rich Presence over the local Discord client's IPC socket.
+    pub(crate) struct DiscordPresence {
+        client: DiscordIpcClient,
+        connected: bool,
+    }
+
+    impl DiscordPresence {
+        pub fn new(app_id: &str) -> Self {
+            let mut client = DiscordIpcClient::new(app_id);
+            let connected = client
+                .connect()
+                .map_err(|e| eprintln!("Discord presence unavailable: {}", e))
+                .is_ok();
+            Self { client, connected }
+        }
+    }
+
+    impl PresenceProvider for DiscordPresence {
+        fn update(&mut self, presence: &Presence) {
+            if !self.connected {
+                return;
+            }
+            let text = presence.text();
+            if let Err(e) = self
+                .client
+                .set_activity(Activity::new().details("NeuroBlasters").state(&text))
+            {
+                eprintln!("Failed to update Discord presence: {}", e);
+                self.connected = false;
+            }
+        }
+
+        fn clear(&mut self) {
+            if self.connected {
+                let _ = self.client.clear_activity();
+                let _ = self.client.close();
+                self.connected = false;
+            }
+        }
+    }
+}
+
+/// The Discord integration when built with the `discord` feature and configured, otherwise
+/// [`NoPresence`].
+pub(crate) fn default_provider() -> Box<dyn PresenceProvider> {
+    #[cfg(feature = "discord")]
+    if let Ok(app_id) = std::env::var(discord::APP_ID_VAR) {
+        return Box::new(discord::DiscordPresence::new(&app_id));
+    }
+    Box::new(NoPresence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence_text() {
+        let lobby = Presence::Lobby {
+            code: "ABCD".into(),
+            players: 3,
+            max_players: 8,
+        };
+        assert_eq!(lobby.text(), "In lobby ABCD (3/8)");
+
+        let game = Presence::InGame {
+            code: "ABCD".into(),
+            round: 2,
+            rounds: 3,
+        };
+        assert_eq!(game.text(), "In game ABCD, round 2/3");
+    }
+}
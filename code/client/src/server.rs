@@ -1,15 +1,22 @@
-use std::net::ToSocketAddrs;
+use std::net::{SocketAddr, ToSocketAddrs};
 
+use crate::i18n::tr;
+use crate::net_conditioner::NetConditioner;
 use common::protocol::{
-    API_VERSION, CreateGameResponse, GameUpdate, HandshakeResponse, InitialGameInfo,
-    JoinGameResponse,
+    API_VERSION, CreateGameResponse, GameEvent, GameSnapshot, GameUpdate, HandshakeResponse,
+    InitialGameInfo, InputPayload, JoinGameResponse, ModelChunk, ModelInfo, PingKind, ServerError,
+    SessionToken, TournamentInfo, TournamentJoinResponse, Upgrade,
 };
+use common::transfer::ModelDownload;
 use common::{
     codec::{decode_server_message, encode_client_message},
-    game::player::is_valid_username,
+    game::balance::balance,
+    game::player::{TankColor, is_valid_username},
     protocol::{ClientMessage, ServerMessage},
 };
+use glam::Vec2;
 use rand::Rng;
+use std::collections::VecDeque;
 use std::sync::mpsc::Receiver;
 
 use std::net::UdpSocket;
@@ -32,6 +39,130 @@ pub(crate) enum ClientState {
     Playing,
 }
 
+/// Progress of getting a connection to the server, until the handshake is accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConnectionState {
+    /// No connection and no attempt in progress
+    Idle,
+    /// Resolving the server address and opening the socket
+    Connecting { attempt: u32 },
+    /// Transport is up, waiting for the handshake response
+    Handshaking { attempt: u32 },
+    /// Handshake accepted
+    Connected,
+    /// The last attempt failed with `reason`; the next one starts at `retry_at`
+    Reconnecting {
+        attempt: u32,
+        retry_at: Instant,
+        reason: String,
+    },
+    /// Gave up, either after a permanent error or after running out of attempts
+    Failed(String),
+}
+
+/// Exponential backoff between connection attempts
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(8),
+            max_attempts: 5,
+        }
+    }
+}
+
+impl Backoff {
+    /// Time to wait after the given (1-based) attempt failed
+    pub fn delay(&self, failed_attempt: u32) -> Duration {
+        let doublings = failed_attempt.saturating_sub(1).min(16);
+        self.initial.saturating_mul(1 << doublings).min(self.max)
+    }
+}
+
+/// Why a connection attempt failed; only transient failures are retried.
+#[derive(Debug, PartialEq)]
+enum ConnectError {
+    Transient(String),
+    Permanent(String),
+}
+
+/// Identifies one request, so a view only ever sees the reply to the request it made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RequestId(u64);
+
+/// The requests the client can make; each is answered by exactly one server message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestKind {
+    Connect,
+    CreateGame,
+    JoinGame,
+    StartCountdown,
+    LeaveGame,
+    JoinTournament,
+    LeaveTournament,
+}
+
+impl RequestKind {
+    fn of(msg: &ClientMessage) -> Option<Self> {
+        match msg {
+            ClientMessage::Handshake { .. } => Some(RequestKind::Connect),
+            ClientMessage::CreateGame { .. } => Some(RequestKind::CreateGame),
+            ClientMessage::JoinGame { .. } => Some(RequestKind::JoinGame),
+            ClientMessage::StartCountdown => Some(RequestKind::StartCountdown),
+            ClientMessage::LeaveGame => Some(RequestKind::LeaveGame),
+            ClientMessage::JoinTournament => Some(RequestKind::JoinTournament),
+            ClientMessage::LeaveTournament => Some(RequestKind::LeaveTournament),
+            ClientMessage::GameInput { .. }
+            | ClientMessage::RequestModel(_)
+            | ClientMessage::ListModels
+            | ClientMessage::BuyUpgrade(_)
+            | ClientMessage::Ping { .. } => None,
+        }
+    }
+}
+
+struct PendingRequest {
+    id: RequestId,
+    kind: RequestKind,
+    /// The request fails on its own if there is no reply by then
+    deadline: Option<Instant>,
+}
+
+/// The outcome of a finished request, waiting in the inbox until its view collects it.
+struct Reply {
+    id: RequestId,
+    result: Result<(), String>,
+}
+
+/// Where and as whom the connection in progress connects, kept for retries.
+#[derive(Debug, Clone)]
+struct ConnectTarget {
+    servername: String,
+    /// Relay to go through when the server can't be reached directly
+    relay: Option<String>,
+    /// The direct route failed, so attempts go through `relay`
+    via_relay: bool,
+    username: String,
+    color: Option<TankColor>,
+}
+
+impl ConnectTarget {
+    /// Address the next attempt goes to.
+    fn address(&self) -> &str {
+        match &self.relay {
+            Some(relay) if self.via_relay => relay,
+            _ => &self.servername,
+        }
+    }
+}
+
 struct ConnectionData {
     client: RenetClient,
     client_id: ClientId,
@@ -40,50 +171,250 @@ struct ConnectionData {
 
 pub(crate) struct Server {
     connection_data: Option<ConnectionData>,
-    connect_rx: Option<Receiver<Result<ConnectionData, String>>>,
+    connect_rx: Option<Receiver<Result<ConnectionData, ConnectError>>>,
+    connection_state: ConnectionState,
+    connect_target: Option<ConnectTarget>,
+    /// What we connected to the connected server with; its address is the relay's if we
+    /// got in through it
+    connected: Option<ConnectTarget>,
+    backoff: Backoff,
     last_tick: Instant,
     game_update: Option<GameUpdate>,
+    /// The latest snapshots received, oldest first, for the updates sent as changes
+    snapshots: VecDeque<(u32, GameSnapshot)>,
     initial_game_info: Option<InitialGameInfo>,
+    /// Latest state of the tournament we signed up for
+    tournament: Option<TournamentInfo>,
+    /// Bot model the current game offers, until someone decides whether to download it
+    model_offer: Option<ModelInfo>,
+    download: Option<ModelDownload>,
+    /// A finished download, with the model's bytes, until it's collected
+    downloaded_model: Option<Result<(ModelInfo, Vec<u8>), String>>,
+    /// The server's bot models, as of the last time we asked
+    models: Vec<String>,
     client_state: ClientState,
-    /// If request failed, the client can check why
-    request_response: Option<Result<(), String>>,
-    request_pending: bool,
+    pending: Option<PendingRequest>,
+    /// Replies nobody collected yet
+    inbox: VecDeque<Reply>,
+    /// Survives `close`, so ids of abandoned requests are never reused
+    next_request_id: u64,
+    /// The server's build, if it isn't ours
+    server_build_mismatch: Option<String>,
+    /// Our session on the last server we connected to; survives `close`, so after a
+    /// dropped connection we can get back into the match
+    session: Option<SessionToken>,
+    /// Holds back and drops our inputs on purpose; its conditions survive `close`
+    conditioner: NetConditioner,
 }
 
 const PROTOCOL_ID: u64 = 0;
 const RELIABLE_CHANNEL_ID: u8 = 0;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Snapshots kept to build the server's deltas on; it keeps fewer
+const SNAPSHOTS_KEPT: usize = 64;
 
 impl Server {
     pub fn new() -> Self {
         Self {
             connection_data: None,
             connect_rx: None,
+            connection_state: ConnectionState::Idle,
+            connect_target: None,
+            connected: None,
+            backoff: Backoff::default(),
             last_tick: Instant::now(),
             game_update: None,
+            snapshots: VecDeque::new(),
             initial_game_info: None,
+            tournament: None,
+            model_offer: None,
+            download: None,
+            downloaded_model: None,
+            models: Vec::new(),
+            server_build_mismatch: None,
             client_state: ClientState::Disconnected,
-            request_response: None,
-            request_pending: false,
+            pending: None,
+            inbox: VecDeque::new(),
+            next_request_id: 0,
+            session: None,
+            conditioner: NetConditioner::default(),
         }
     }
 
-    pub fn connect(&mut self, servername: String, username: String) {
-        if self.request_pending || !matches!(self.client_state, ClientState::Disconnected) {
+    /// Connects to `servername`, or through `relay` if the server can't be reached directly.
+    pub fn connect(
+        &mut self,
+        servername: String,
+        relay: Option<String>,
+        username: String,
+        color: Option<TankColor>,
+    ) -> RequestId {
+        if self.pending.is_some() || !matches!(self.client_state, ClientState::Disconnected) {
             panic!("Unexpected call to connect.");
         }
 
+        // The request will be pending for as long as we receive handshake response,
+        // a permanent error occurs or we run out of attempts, so it has no deadline
+        let id = self.begin_request(RequestKind::Connect, None);
+        self.connect_target = Some(ConnectTarget {
+            servername,
+            relay,
+            via_relay: false,
+            username,
+            color,
+        });
+        self.start_attempt(1);
+        id
+    }
+
+    fn begin_request(&mut self, kind: RequestKind, deadline: Option<Instant>) -> RequestId {
+        if self.pending.is_some() {
+            panic!(
+                "Trying to send another request when the previous one is still pending! Only one request at a time!"
+            )
+        }
+        let id = RequestId(self.next_request_id);
+        self.next_request_id += 1;
+        self.pending = Some(PendingRequest { id, kind, deadline });
+        id
+    }
+
+    /// Moves the pending request into the inbox with the given result.
+    fn finish_request(&mut self, result: Result<(), String>) {
+        if let Some(pending) = self.pending.take() {
+            self.inbox.push_back(Reply {
+                id: pending.id,
+                result,
+            });
+        }
+    }
+
+    fn start_attempt(&mut self, attempt: u32) {
+        let Some(target) = self.connect_target.clone() else {
+            return;
+        };
+
         let (tx, rx) = std::sync::mpsc::channel();
         self.connect_rx = Some(rx);
+        self.connection_state = ConnectionState::Connecting { attempt };
 
-        // The request will be pending for as long as we receive handshake response (or an error occurs earlier)
-        self.request_pending = true;
-
+        let session = self.session;
         std::thread::spawn(move || {
-            let result = connect_blocking(servername, username);
+            let result = connect_blocking(
+                target.address(),
+                target.username.clone(),
+                target.color,
+                session,
+            );
             let _ = tx.send(result);
         });
     }
 
+    /// Stops connecting; the background attempt, if any, is abandoned.
+    pub fn cancel_connect(&mut self) {
+        self.close();
+    }
+
+    #[must_use]
+    pub fn connection_state(&self) -> &ConnectionState {
+        &self.connection_state
+    }
+
+    /// The build of the connected server, when it differs from this client's. Things may
+    /// go wrong in ways the protocol version doesn't catch.
+    pub fn server_build_mismatch(&self) -> Option<&str> {
+        self.server_build_mismatch.as_deref()
+    }
+
+    /// Where others can reach the connected server, as far as we know.
+    pub fn address(&self) -> Option<&str> {
+        self.connected.as_ref().map(ConnectTarget::address)
+    }
+
+    /// The nickname we connected with.
+    pub fn nickname(&self) -> Option<&str> {
+        self.connected
+            .as_ref()
+            .map(|target| target.username.as_str())
+    }
+
+    /// Our session on the server we last connected to.
+    pub fn session(&self) -> Option<SessionToken> {
+        self.session
+    }
+
+    /// Presents `session` on the next connection, to get back into the match it was for,
+    /// e.g. after the client restarted.
+    pub fn resume_session(&mut self, session: SessionToken) {
+        self.session = Some(session);
+    }
+
+    /// Connected, but not in a game.
+    pub fn is_in_lobby(&self) -> bool {
+        self.client_state == ClientState::Connected
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.backoff.max_attempts
+    }
+
+    fn current_attempt(&self) -> u32 {
+        match self.connection_state {
+            ConnectionState::Connecting { attempt }
+            | ConnectionState::Handshaking { attempt }
+            | ConnectionState::Reconnecting { attempt, .. } => attempt,
+            _ => 0,
+        }
+    }
+
+    /// Schedules the next attempt after a transient failure, or gives up.
+    fn retry_later(&mut self, reason: String, now: Instant) {
+        self.connection_data = None;
+        self.connect_rx = None;
+
+        // Players behind NAT can't reach the server directly, so the relay gets a full set
+        // of attempts of its own right away
+        if let Some(target) = &mut self.connect_target
+            && target.relay.is_some()
+            && !target.via_relay
+        {
+            target.via_relay = true;
+            self.connection_state = ConnectionState::Reconnecting {
+                attempt: 1,
+                retry_at: now,
+                reason: format!("{} Trying the relay.", reason),
+            };
+            return;
+        }
+
+        let failed = self.current_attempt();
+        if failed >= self.backoff.max_attempts {
+            self.fail_connect(format!(
+                "{} (gave up after {} attempts)",
+                reason, self.backoff.max_attempts
+            ));
+            return;
+        }
+
+        self.connection_state = ConnectionState::Reconnecting {
+            attempt: failed + 1,
+            retry_at: now + self.backoff.delay(failed),
+            reason,
+        };
+    }
+
+    fn fail_connect(&mut self, reason: String) {
+        self.connection_data = None;
+        self.connect_rx = None;
+        self.connect_target = None;
+        self.connection_state = ConnectionState::Failed(reason.clone());
+        self.finish_request(Err(reason));
+    }
+
+    fn is_handshaking(&self) -> bool {
+        matches!(self.connection_state, ConnectionState::Handshaking { .. })
+    }
+
     pub fn get_client_id(&self) -> ClientId {
         self.connection_data
             .as_ref()
@@ -92,6 +423,8 @@ impl Server {
     }
 
     pub fn tick(&mut self) -> Result<(), String> {
+        let now = Instant::now();
+
         if let Some(rx) = &self.connect_rx {
             // Connecting to server has finished
             if let Ok(result) = rx.try_recv() {
@@ -101,17 +434,33 @@ impl Server {
                     Ok(connection_data) => {
                         // Succesfully connected, but we are still waiting for handshake response.
                         self.connection_data = Some(connection_data);
+                        self.connection_state = ConnectionState::Handshaking {
+                            attempt: self.current_attempt(),
+                        };
                     }
-                    Err(reason) => {
-                        // Could not connect.
-                        self.request_pending = false;
-                        self.request_response = Some(Err(reason));
-                    }
+                    Err(ConnectError::Transient(reason)) => self.retry_later(reason, now),
+                    Err(ConnectError::Permanent(reason)) => self.fail_connect(reason),
                 }
             }
         }
 
-        let now = Instant::now();
+        if self
+            .pending
+            .as_ref()
+            .and_then(|pending| pending.deadline)
+            .is_some_and(|deadline| now >= deadline)
+        {
+            self.finish_request(Err("Server did not respond in time.".into()));
+        }
+
+        if let ConnectionState::Reconnecting {
+            attempt, retry_at, ..
+        } = self.connection_state
+            && now >= retry_at
+        {
+            self.start_attempt(attempt);
+        }
+
         let dt = now.duration_since(self.last_tick);
         self.last_tick = now;
 
@@ -124,21 +473,47 @@ impl Server {
             let result = connection_data
                 .transport
                 .update(dt, &mut connection_data.client);
-            self.handle_net_result(result)?;
+            if let Err(reason) = self.handle_net_result(result) {
+                return self.handle_connection_lost(reason);
+            }
 
             if connection_data.client.is_connected() {
-                self.process_server_messages(&mut connection_data)?;
+                for payload in self.conditioner.due(Instant::now()) {
+                    connection_data
+                        .client
+                        .send_message(RELIABLE_CHANNEL_ID, payload);
+                }
+                if let Err(reason) = self.process_server_messages(&mut connection_data) {
+                    // A rejected handshake will not get better by retrying
+                    if self.is_handshaking() {
+                        self.fail_connect(reason);
+                        return Ok(());
+                    }
+                    return Err(reason);
+                }
 
                 let result = connection_data
                     .transport
                     .send_packets(&mut connection_data.client);
-                self.handle_net_result(result)?;
+                if let Err(reason) = self.handle_net_result(result) {
+                    return self.handle_connection_lost(reason);
+                }
             }
             self.connection_data = Some(connection_data);
         }
         Ok(())
     }
 
+    /// Losing the transport before the handshake is answered is retried, afterwards it's fatal.
+    fn handle_connection_lost(&mut self, reason: String) -> Result<(), String> {
+        if self.is_handshaking() {
+            self.retry_later(reason, Instant::now());
+            Ok(())
+        } else {
+            Err(reason)
+        }
+    }
+
     fn handle_net_result(
         &mut self,
         result: Result<(), NetcodeTransportError>,
@@ -185,14 +560,32 @@ impl Server {
     ) -> Result<ClientState, String> {
         match server_msg {
             ServerMessage::HandshakeResponse(resp) => match resp {
-                HandshakeResponse::Ok => self.complete_request(Ok(()), ClientState::Connected),
-                HandshakeResponse::ApiMismatch => Err("Server error: API mismatch.".into()),
-                HandshakeResponse::ServerFull => Err("Server error: server is full.".into()),
+                HandshakeResponse::Ok {
+                    build,
+                    session,
+                    resumed,
+                } => {
+                    self.server_build_mismatch = (build != common::version()).then_some(build);
+                    self.session = Some(session);
+                    self.complete_request_fn(RequestKind::Connect, Ok(()), |server: &mut Server| {
+                        server.connection_state = ConnectionState::Connected;
+                        server.connected = server.connect_target.take();
+                        // Back in the match we dropped out of
+                        Ok(match resumed {
+                            Some(initial_game_info) => {
+                                server.enter_game(initial_game_info);
+                                ClientState::Playing
+                            }
+                            None => ClientState::Connected,
+                        })
+                    })
+                }
+                HandshakeResponse::ApiMismatch => Err(tr!("server_error.api_mismatch")),
+                HandshakeResponse::BalanceMismatch => Err(tr!("server_error.balance_mismatch")),
+                HandshakeResponse::ServerFull => Err(tr!("server_error.server_full")),
             },
 
-            ServerMessage::Error(error) => {
-                Err(format!("Server error while handshaking: {}", error))
-            }
+            ServerMessage::Error(error) => Err(describe(&error)),
 
             _ => Err("Got invalid server message while handshaking.".into()),
         }
@@ -201,40 +594,101 @@ impl Server {
     fn handle_connected_state(&mut self, server_msg: ServerMessage) -> Result<ClientState, String> {
         match server_msg {
             ServerMessage::CreateGameReponse(resp) => match resp {
-                CreateGameResponse::Ok(initial_game_info) => {
-                    self.complete_request_fn(Ok(()), |server: &mut Server| {
-                        server.initial_game_info = Some(initial_game_info);
+                CreateGameResponse::Ok(initial_game_info) => self.complete_request_fn(
+                    RequestKind::CreateGame,
+                    Ok(()),
+                    |server: &mut Server| {
+                        server.enter_game(initial_game_info);
                         Ok(ClientState::Playing)
-                    })
-                }
+                    },
+                ),
                 CreateGameResponse::TooManyGames => self.complete_request(
-                    Err("Server game limit exhausted.".into()),
+                    RequestKind::CreateGame,
+                    Err(tr!("server_error.too_many_games")),
                     ClientState::Connected,
                 ),
             },
 
             ServerMessage::JoinGameResponse(resp) => match resp {
-                JoinGameResponse::Ok(initial_game_info) => {
-                    self.complete_request_fn(Ok(()), |server: &mut Server| {
-                        server.initial_game_info = Some(initial_game_info);
+                JoinGameResponse::Ok(initial_game_info) => self.complete_request_fn(
+                    RequestKind::JoinGame,
+                    Ok(()),
+                    |server: &mut Server| {
+                        server.enter_game(initial_game_info);
                         Ok(ClientState::Playing)
-                    })
-                }
+                    },
+                ),
 
-                JoinGameResponse::GameFull => {
-                    self.complete_request(Err("Game is full.".into()), ClientState::Connected)
-                }
-                JoinGameResponse::InvalidCode => self.complete_request(
-                    Err("Game does not exist (invalid code).".into()),
+                JoinGameResponse::GameFull => self.complete_request(
+                    RequestKind::JoinGame,
+                    Err(tr!("server_error.game_full")),
+                    ClientState::Connected,
+                ),
+                JoinGameResponse::InvalidCode(code) => self.complete_request(
+                    RequestKind::JoinGame,
+                    Err(tr!("server_error.no_such_game", code = code.0)),
                     ClientState::Connected,
                 ),
                 JoinGameResponse::GameStarted => self.complete_request(
-                    Err("Game has already started.".into()),
+                    RequestKind::JoinGame,
+                    Err(tr!("server_error.game_started")),
+                    ClientState::Connected,
+                ),
+            },
+
+            ServerMessage::TournamentJoinResponse(resp) => match resp {
+                TournamentJoinResponse::Ok(info) => self.complete_request_fn(
+                    RequestKind::JoinTournament,
+                    Ok(()),
+                    |server: &mut Server| {
+                        server.tournament = Some(info);
+                        Ok(ClientState::Connected)
+                    },
+                ),
+                TournamentJoinResponse::NoTournament => self.complete_request(
+                    RequestKind::JoinTournament,
+                    Err(tr!("server_error.no_tournament")),
+                    ClientState::Connected,
+                ),
+                TournamentJoinResponse::Closed => self.complete_request(
+                    RequestKind::JoinTournament,
+                    Err(tr!("server_error.tournament_closed")),
                     ClientState::Connected,
                 ),
             },
 
-            ServerMessage::Error(error) => Err(format!("Server errror: {}", error)),
+            ServerMessage::TournamentLeaveAck => self.complete_request_fn(
+                RequestKind::LeaveTournament,
+                Ok(()),
+                |server: &mut Server| {
+                    server.tournament = None;
+                    Ok(ClientState::Connected)
+                },
+            ),
+
+            ServerMessage::TournamentUpdate(info) => {
+                self.tournament = Some(info);
+                Ok(ClientState::Connected)
+            }
+
+            // The server moved us into our next tournament match
+            ServerMessage::TournamentMatch(initial_game_info) => {
+                self.enter_game(initial_game_info);
+                Ok(ClientState::Playing)
+            }
+
+            // The rest of a download that outlived its game
+            ServerMessage::ModelChunk(chunk) => {
+                self.receive_model_chunk(chunk);
+                Ok(ClientState::Connected)
+            }
+
+            ServerMessage::ModelList(models) => {
+                self.models = models;
+                Ok(ClientState::Connected)
+            }
+
+            ServerMessage::Error(error) => Err(describe(&error)),
 
             _ => Err("Got invalid server message.".into()),
         }
@@ -242,63 +696,213 @@ impl Server {
 
     fn handle_playing_state(&mut self, server_msg: ServerMessage) -> Result<ClientState, String> {
         match server_msg {
+            ServerMessage::GameDelta(delta) => {
+                let Some((_, base)) = self
+                    .snapshots
+                    .iter()
+                    .find(|(tick, _)| *tick == delta.delta.baseline)
+                else {
+                    // Nothing to build it on; the server sends whole snapshots once it
+                    // hears which one we have
+                    return Ok(ClientState::Playing);
+                };
+                let update = delta.apply(base);
+                self.handle_playing_state(ServerMessage::GameUpdate(update))
+            }
+
             ServerMessage::GameUpdate(new_update) => {
+                if self.snapshots.len() == SNAPSHOTS_KEPT {
+                    self.snapshots.pop_front();
+                }
+                self.snapshots
+                    .push_back((new_update.tick, new_update.snapshot.clone()));
+                // The server closed the game and put us back in the lobby
+                let closed = new_update
+                    .events
+                    .iter()
+                    .any(|event| matches!(event, GameEvent::Closed(_)));
                 self.game_update = Some(new_update);
+                if closed {
+                    Ok(ClientState::Connected)
+                } else {
+                    Ok(ClientState::Playing)
+                }
+            }
+
+            ServerMessage::StartCountdownAck => {
+                self.complete_request(RequestKind::StartCountdown, Ok(()), ClientState::Playing)
+            }
+
+            ServerMessage::LeaveGameAck => {
+                self.complete_request(RequestKind::LeaveGame, Ok(()), ClientState::Connected)
+            }
+
+            ServerMessage::TournamentUpdate(info) => {
+                self.tournament = Some(info);
                 Ok(ClientState::Playing)
             }
 
-            ServerMessage::StartCountdownAck => self.complete_request(Ok(()), ClientState::Playing),
+            ServerMessage::ModelChunk(chunk) => {
+                self.receive_model_chunk(chunk);
+                Ok(ClientState::Playing)
+            }
 
-            ServerMessage::LeaveGameAck => self.complete_request(Ok(()), ClientState::Connected),
+            ServerMessage::ModelUnavailable(hash) => {
+                if self
+                    .download
+                    .as_ref()
+                    .is_some_and(|d| d.info().hash == hash)
+                {
+                    self.download = None;
+                    self.downloaded_model = Some(Err(tr!("server_error.model_unavailable")));
+                }
+                Ok(ClientState::Playing)
+            }
 
-            ServerMessage::Error(e) => Err(format!(
-                "Got error response from server while in game: {}",
-                e
-            )),
+            ServerMessage::Error(error) => Err(describe(&error)),
 
             _ => Err("Got invalid server message while in game.".into()),
         }
     }
 
-    pub fn send_client_message(&mut self, msg: ClientMessage) {
+    /// Sends a request; its reply can be collected with [`Server::take_reply`].
+    pub fn send_request(&mut self, msg: ClientMessage) -> RequestId {
         // Checking if the message we are sending aligns with the state we are in
         match (&self.client_state, &msg) {
             // Trying to create / join a game
             (
                 ClientState::Connected,
-                ClientMessage::CreateGame { map: _, rounds: _ }
-                | ClientMessage::JoinGame { game_code: _ },
+                ClientMessage::CreateGame { .. }
+                | ClientMessage::JoinGame { game_code: _ }
+                | ClientMessage::JoinTournament
+                | ClientMessage::LeaveTournament,
             ) => {}
 
             // Available options in game
-            (
-                ClientState::Playing,
-                ClientMessage::LeaveGame
-                | ClientMessage::StartCountdown
-                | ClientMessage::GameInput(_),
-            ) => {}
+            (ClientState::Playing, ClientMessage::LeaveGame | ClientMessage::StartCountdown) => {}
 
             _ => {
                 panic!("Invalid message for current state!");
             }
         }
 
-        // All messages are requests besides the GameInput one
-        match &msg {
-            ClientMessage::GameInput(_) => {}
+        let kind = RequestKind::of(&msg).expect("Checked above that the message is a request.");
+        let id = self.begin_request(kind, Some(Instant::now() + REQUEST_TIMEOUT));
+        self.send_message(&msg);
+        id
+    }
 
-            _ => {
-                if self.request_pending {
-                    panic!(
-                        "Trying to send another request when the previous one is still pending! Only one request at a time!"
-                    )
-                }
-                self.request_pending = true;
+    fn enter_game(&mut self, initial_game_info: InitialGameInfo) {
+        self.model_offer = initial_game_info.bot_model.clone();
+        self.initial_game_info = Some(initial_game_info);
+    }
+
+    /// Bot model offered by the game we just entered, if any.
+    #[must_use]
+    pub fn take_model_offer(&mut self) -> Option<ModelInfo> {
+        self.model_offer.take()
+    }
+
+    /// Asks the server for a model the current game offers. Collect it with
+    /// [`Server::take_downloaded_model`].
+    pub fn download_model(&mut self, info: ModelInfo) {
+        if self.client_state != ClientState::Playing {
+            panic!("Invalid message for current state!");
+        }
+        match ModelDownload::new(info) {
+            Ok(download) => {
+                self.send_message(&ClientMessage::RequestModel(download.info().hash));
+                self.download = Some(download);
             }
+            Err(e) => self.downloaded_model = Some(Err(e)),
+        }
+    }
+
+    /// Asks the server which bot models games can be created with. Like inputs, this
+    /// isn't a request: the list shows up in [`Server::models`] when it arrives.
+    pub fn list_models(&mut self) {
+        if self.client_state != ClientState::Connected {
+            panic!("Invalid message for current state!");
         }
+        self.send_message(&ClientMessage::ListModels);
+    }
+
+    /// The server's bot models, as of the last [`Server::list_models`].
+    pub fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    fn receive_model_chunk(&mut self, chunk: ModelChunk) {
+        // Chunks of a download we gave up on
+        let Some(download) = &mut self.download else {
+            return;
+        };
+        let result = match download.accept(chunk) {
+            Ok(None) => return,
+            Ok(Some(bytes)) => Ok((download.info().clone(), bytes)),
+            Err(e) => Err(e),
+        };
+        self.download = None;
+        self.downloaded_model = Some(result);
+    }
+
+    /// A download that finished, successfully or not.
+    #[must_use]
+    pub fn take_downloaded_model(&mut self) -> Option<Result<(ModelInfo, Vec<u8>), String>> {
+        self.downloaded_model.take()
+    }
 
+    /// Sends the player's input; unlike requests, it's never answered.
+    /// Sends the input numbered `seq`; the server acknowledges it in a later update.
+    pub fn send_input(&mut self, seq: u32, input: InputPayload) {
+        if self.client_state != ClientState::Playing {
+            panic!("Invalid message for current state!");
+        }
+        let snapshot = self.snapshots.back().map(|(tick, _)| *tick);
+        let msg = ClientMessage::GameInput {
+            seq,
+            input,
+            snapshot,
+        };
+        if self.conditioner.conditions.is_perfect() {
+            self.send_message(&msg);
+            return;
+        }
         let payload =
             encode_client_message(&msg).expect("Serializing Client Message should never fail.");
+        self.conditioner
+            .hold(payload, Instant::now(), rand::random());
+    }
+
+    /// The conditioner our inputs go through, to set how bad the connection should get.
+    pub fn conditioner_mut(&mut self) -> &mut NetConditioner {
+        &mut self.conditioner
+    }
+
+    pub fn conditioner(&self) -> &NetConditioner {
+        &self.conditioner
+    }
+
+    /// Buys the next level of `upgrade`; the server answers with everyone's balances if it
+    /// went through.
+    pub fn send_upgrade(&mut self, upgrade: Upgrade) {
+        if self.client_state != ClientState::Playing {
+            panic!("Invalid message for current state!");
+        }
+        self.send_message(&ClientMessage::BuyUpgrade(upgrade));
+    }
+
+    /// Points the player's team at `world_pos`; like inputs, pings are never answered.
+    pub fn send_ping(&mut self, kind: PingKind, world_pos: Vec2) {
+        if self.client_state != ClientState::Playing {
+            panic!("Invalid message for current state!");
+        }
+        self.send_message(&ClientMessage::Ping { kind, world_pos });
+    }
+
+    fn send_message(&mut self, msg: &ClientMessage) {
+        let payload =
+            encode_client_message(msg).expect("Serializing Client Message should never fail.");
         self.connection_data
             .as_mut()
             .expect("Send should never be called when connection was not yet established")
@@ -311,37 +915,46 @@ impl Server {
     /// on failure, returns an error state with an appropriate message
     fn complete_request_fn<F: FnOnce(&mut Server) -> Result<ClientState, String>>(
         &mut self,
+        kind: RequestKind,
         response: Result<(), String>,
         success_action: F,
     ) -> Result<ClientState, String> {
-        // In both cases there is some unwanted response; the first case is simple, but in the second
-        // we have a guarantee from the send_client_message function that we do not make 2 consecutive requests,
-        // so this new response must also be at server's fault
-        if !self.request_pending || self.request_response.is_some() {
-            return Err("Server sent response but no request was made.".into());
+        // Only one request is in flight at a time, so a response that doesn't answer it
+        // (or arrives after it timed out) is the server's fault
+        match &self.pending {
+            Some(pending) if pending.kind == kind => {}
+            Some(_) => return Err("Server answered a request that was not made.".into()),
+            None => return Err("Server sent response but no request was made.".into()),
         }
 
-        self.request_response = Some(response);
-        self.request_pending = false;
+        self.finish_request(response);
         success_action(self)
     }
 
     fn complete_request(
         &mut self,
+        kind: RequestKind,
         response: Result<(), String>,
         success: ClientState,
     ) -> Result<ClientState, String> {
-        self.complete_request_fn(response, |_| Ok(success))
+        self.complete_request_fn(kind, response, |_| Ok(success))
     }
 
+    /// The reply to the given request, once it has arrived.
     #[must_use]
-    pub fn take_request_response(&mut self) -> Option<Result<(), String>> {
-        self.request_response.take()
+    pub fn take_reply(&mut self, id: RequestId) -> Option<Result<(), String>> {
+        let index = self.inbox.iter().position(|reply| reply.id == id)?;
+        self.inbox.remove(index).map(|reply| reply.result)
     }
 
     pub fn close(&mut self) {
         // Full reset
+        let (next_request_id, session) = (self.next_request_id, self.session);
+        let conditions = self.conditioner.conditions;
         *self = Self::new();
+        self.next_request_id = next_request_id;
+        self.session = session;
+        self.conditioner.conditions = conditions;
     }
 
     #[must_use]
@@ -354,11 +967,22 @@ impl Server {
         self.initial_game_info.take()
     }
 
+    /// The tournament we signed up for, if any.
+    #[must_use]
+    pub fn tournament(&self) -> Option<&TournamentInfo> {
+        self.tournament.as_ref()
+    }
+
     #[must_use]
     pub fn client_id(&self) -> Option<ClientId> {
         self.connection_data.as_ref().map(|c| c.client_id)
     }
 
+    /// Whether the client is in a game, where inputs may be sent.
+    pub fn is_playing(&self) -> bool {
+        self.client_state == ClientState::Playing
+    }
+
     pub fn assert_state(&self, state: ClientState) {
         if self.client_state != state {
             panic!("Server is in invalid state.");
@@ -366,25 +990,55 @@ impl Server {
     }
 }
 
-fn connect_blocking(mut servername: String, username: String) -> Result<ConnectionData, String> {
-    is_valid_username(&username)?;
+/// What the server turned a message down for, in the player's language.
+fn describe(error: &ServerError) -> String {
+    match error {
+        ServerError::UnknownSender => tr!("server_error.unknown_sender"),
+        ServerError::AlreadyConnected => tr!("server_error.already_connected"),
+        ServerError::InvalidMessage => tr!("server_error.invalid_message"),
+        ServerError::NoSuchGame(code) => tr!("server_error.no_such_game", code = code.0),
+        ServerError::GameFull => tr!("server_error.game_full"),
+        ServerError::NotInGame => tr!("server_error.not_in_game"),
+        ServerError::NotGameMaster => tr!("server_error.not_game_master"),
+        ServerError::NotWaiting => tr!("server_error.not_waiting"),
+        ServerError::TeamSize { requested, max } => {
+            tr!("server_error.team_size", size = requested, max = max)
+        }
+        ServerError::NoTournament => tr!("server_error.no_tournament"),
+        ServerError::NotInTournament => tr!("server_error.not_in_tournament"),
+        ServerError::UnknownModel(name) => tr!("server_error.unknown_model", name = name),
+        ServerError::BrokenModel(name) => tr!("server_error.broken_model", name = name),
+        ServerError::ModelNeedsTrainedBots(name) => {
+            tr!("server_error.model_needs_trained_bots", name = name)
+        }
+    }
+}
 
+/// Game address of the server called `servername`, a host name or address with an optional
+/// port. Blocks while resolving.
+pub(crate) fn resolve_server(servername: &str) -> Option<SocketAddr> {
     // If no port suffix present, append the 8080 port which is the default for our server
+    let mut servername = servername.to_string();
     if !servername.contains(':') {
         servername.push_str(":8080");
     }
 
-    let addrs: Vec<std::net::SocketAddr> = servername
-        .to_socket_addrs()
-        .map_err(|_| "Server not found.".to_string())?
-        .collect();
-    let mut addrs = addrs;
+    let mut addrs: Vec<SocketAddr> = servername.to_socket_addrs().ok()?.collect();
     // Prefer IPv4 when both families are available (common for "localhost" resolving to ::1 first on Linux).
     addrs.sort_by_key(|a| if a.is_ipv4() { 0 } else { 1 });
-    let server_addr = addrs
-        .first()
-        .copied()
-        .ok_or("Server not found.".to_string())?;
+    addrs.first().copied()
+}
+
+fn connect_blocking(
+    servername: &str,
+    username: String,
+    color: Option<TankColor>,
+    session: Option<SessionToken>,
+) -> Result<ConnectionData, ConnectError> {
+    is_valid_username(&username).map_err(ConnectError::Permanent)?;
+
+    let server_addr =
+        resolve_server(servername).ok_or(ConnectError::Permanent("Server not found.".into()))?;
 
     let connection_config = ConnectionConfig::default();
 
@@ -396,7 +1050,9 @@ fn connect_blocking(mut servername: String, username: String) -> Result<Connecti
     } else {
         UdpSocket::bind("[::]:0")
     }
-    .or(Err("Could not establish a connection.".to_string()))?;
+    .or(Err(ConnectError::Transient(
+        "Could not establish a connection.".into(),
+    )))?;
 
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -411,15 +1067,22 @@ fn connect_blocking(mut servername: String, username: String) -> Result<Connecti
         user_data: None,
     };
 
-    let transport = NetcodeClientTransport::new(current_time, authentication, socket)
-        .or(Err("Could not establish a connection."))?;
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket).or(Err(
+        ConnectError::Transient("Could not establish a connection.".into()),
+    ))?;
 
     // Send handshake as the final step. User will wait for server response.
     let payload = encode_client_message(&ClientMessage::Handshake {
         api_version: API_VERSION,
         nickname: username,
+        color,
+        build: common::version().into(),
+        balance_hash: balance().content_hash(),
+        session,
     })
-    .or(Err("Could not send handshake message."))?;
+    .or(Err(ConnectError::Permanent(
+        "Could not send handshake message.".into(),
+    )))?;
 
     client.send_message(RELIABLE_CHANNEL_ID, payload);
 
@@ -433,7 +1096,7 @@ fn connect_blocking(mut servername: String, username: String) -> Result<Connecti
 #[cfg(test)]
 mod tests {
     use super::*;
-    use common::protocol::{GameCode, GameSnapshot, MapName, PlayerId};
+    use common::protocol::{GameCode, GameMode, GameSnapshot, GameState, MapName, PlayerId};
 
     #[test]
     fn test_server_new_initial_state() {
@@ -444,8 +1107,8 @@ mod tests {
         assert!(server.game_update.is_none());
         assert!(server.initial_game_info.is_none());
         assert_eq!(server.client_state, ClientState::Disconnected);
-        assert!(server.request_response.is_none());
-        assert!(!server.request_pending);
+        assert!(server.inbox.is_empty());
+        assert!(server.pending.is_none());
     }
 
     #[test]
@@ -453,8 +1116,8 @@ mod tests {
         let mut server = Server::new();
 
         // Modify some state
-        server.request_pending = true;
-        server.request_response = Some(Ok(()));
+        let old = server.begin_request(RequestKind::CreateGame, None);
+        server.finish_request(Ok(()));
         server.client_state = ClientState::Connected;
 
         // Close should reset everything
@@ -462,36 +1125,266 @@ mod tests {
 
         assert!(server.connection_data.is_none());
         assert_eq!(server.client_state, ClientState::Disconnected);
-        assert!(server.request_response.is_none());
-        assert!(!server.request_pending);
+        assert!(server.inbox.is_empty());
+        assert!(server.pending.is_none());
+
+        // ...but never hand out the id of an abandoned request again
+        assert_ne!(server.begin_request(RequestKind::Connect, None), old);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let backoff = Backoff::default();
+
+        assert_eq!(backoff.delay(1), Duration::from_millis(500));
+        assert_eq!(backoff.delay(2), Duration::from_secs(1));
+        assert_eq!(backoff.delay(4), Duration::from_secs(4));
+        assert_eq!(backoff.delay(10), Duration::from_secs(8));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_transient_failure_schedules_retry() {
+        let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+        server.connection_state = ConnectionState::Handshaking { attempt: 1 };
+        let now = Instant::now();
+
+        server.retry_later("Network connection failed.".into(), now);
+
+        assert_eq!(
+            server.connection_state,
+            ConnectionState::Reconnecting {
+                attempt: 2,
+                retry_at: now + Duration::from_millis(500),
+                reason: "Network connection failed.".into(),
+            }
+        );
+        assert!(server.pending.is_some());
+        assert!(server.inbox.is_empty());
     }
 
     #[test]
-    fn test_take_request_response() {
+    fn test_failing_direct_route_falls_back_to_the_relay() {
         let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+        server.connect_target = Some(ConnectTarget {
+            servername: "192.168.1.20".into(),
+            relay: Some("relay.example.com:7042".into()),
+            via_relay: false,
+            username: "player".into(),
+            color: None,
+        });
+        server.connection_state = ConnectionState::Handshaking { attempt: 3 };
+        let now = Instant::now();
+
+        server.retry_later("Network connection failed.".into(), now);
+
+        // Straight away, with fresh attempts
+        assert_eq!(
+            server.connection_state,
+            ConnectionState::Reconnecting {
+                attempt: 1,
+                retry_at: now,
+                reason: "Network connection failed. Trying the relay.".into(),
+            }
+        );
+        let target = server.connect_target.as_ref().unwrap();
+        assert_eq!(target.address(), "relay.example.com:7042");
+
+        // The relay failing is retried like any other route
+        server.connection_state = ConnectionState::Handshaking { attempt: 1 };
+        server.retry_later("Network connection failed.".into(), now);
+        assert_eq!(
+            server.connection_state,
+            ConnectionState::Reconnecting {
+                attempt: 2,
+                retry_at: now + Duration::from_millis(500),
+                reason: "Network connection failed.".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let mut server = Server::new();
+        let id = server.begin_request(RequestKind::Connect, None);
+        server.connection_state = ConnectionState::Connecting {
+            attempt: server.max_attempts(),
+        };
+
+        server.retry_later("Could not establish a connection.".into(), Instant::now());
+
+        assert!(matches!(
+            server.connection_state,
+            ConnectionState::Failed(_)
+        ));
+        assert!(server.pending.is_none());
+        let resp = server.take_reply(id).unwrap();
+        assert!(resp.unwrap_err().contains("gave up after 5 attempts"));
+    }
+
+    #[test]
+    fn test_handshake_ok_marks_connected() {
+        let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+        server.connect_target = Some(ConnectTarget {
+            servername: "localhost".into(),
+            relay: None,
+            via_relay: false,
+            username: "player".into(),
+            color: None,
+        });
+        server.connection_state = ConnectionState::Handshaking { attempt: 2 };
+
+        let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
+            HandshakeResponse::Ok {
+                build: common::version().into(),
+                session: SessionToken(1),
+                resumed: None,
+            },
+        ));
+
+        assert_eq!(result.unwrap(), ClientState::Connected);
+        assert_eq!(server.connection_state, ConnectionState::Connected);
+        assert!(server.connect_target.is_none());
+        assert_eq!(server.address(), Some("localhost"));
+        assert_eq!(server.nickname(), Some("player"));
+        assert_eq!(server.session(), Some(SessionToken(1)));
+        assert_eq!(server.server_build_mismatch(), None);
+    }
+
+    #[test]
+    fn test_other_server_builds_are_noticed() {
+        let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+        server.connection_state = ConnectionState::Handshaking { attempt: 1 };
+        let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
+            HandshakeResponse::Ok {
+                build: "0.0.1 (0000000, debug)".into(),
+                session: SessionToken(1),
+                resumed: None,
+            },
+        ));
+
+        assert_eq!(result.unwrap(), ClientState::Connected);
+        assert_eq!(
+            server.server_build_mismatch(),
+            Some("0.0.1 (0000000, debug)")
+        );
+    }
+
+    #[test]
+    fn test_reconnecting_resumes_the_match_with_the_same_session() {
+        let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+        server.connection_state = ConnectionState::Handshaking { attempt: 1 };
+        let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
+            HandshakeResponse::Ok {
+                build: common::version().into(),
+                session: SessionToken(7),
+                resumed: None,
+            },
+        ));
+        assert_eq!(result.unwrap(), ClientState::Connected);
+
+        // The connection dropped; the token is kept for the next handshake
+        server.close();
+        assert_eq!(server.session, Some(SessionToken(7)));
+
+        server.begin_request(RequestKind::Connect, None);
+        server.connection_state = ConnectionState::Handshaking { attempt: 1 };
+        let game_info = InitialGameInfo {
+            game_code: GameCode("5678".to_string()),
+            player_id: 1,
+            num_rounds: 5,
+            map_name: MapName::Basic,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
+            game_master: 100,
+            bot_model: None,
+            seed: 0,
+            state: GameState::Battle(30),
+            round: 2,
+        };
+        let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
+            HandshakeResponse::Ok {
+                build: common::version().into(),
+                session: SessionToken(7),
+                resumed: Some(game_info.clone()),
+            },
+        ));
+        assert_eq!(result.unwrap(), ClientState::Playing);
+        assert_eq!(server.initial_game_info(), Some(game_info));
+    }
+
+    #[test]
+    fn test_cancel_connect_resets_state() {
+        let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+        server.connection_state = ConnectionState::Reconnecting {
+            attempt: 3,
+            retry_at: Instant::now(),
+            reason: "timeout".into(),
+        };
+
+        server.cancel_connect();
+
+        assert_eq!(server.connection_state, ConnectionState::Idle);
+        assert!(server.pending.is_none());
+    }
+
+    #[test]
+    fn test_take_reply() {
+        let mut server = Server::new();
+        let id = server.begin_request(RequestKind::CreateGame, None);
 
         // Initially none
-        assert!(server.take_request_response().is_none());
+        assert!(server.take_reply(id).is_none());
 
         // Set a response
-        server.request_response = Some(Ok(()));
-        assert!(server.take_request_response().is_some());
+        server.finish_request(Ok(()));
+        assert!(server.take_reply(id).is_some());
 
         // Should be consumed
-        assert!(server.take_request_response().is_none());
+        assert!(server.take_reply(id).is_none());
     }
 
     #[test]
-    fn test_take_request_response_with_error() {
+    fn test_take_reply_only_returns_own_reply() {
         let mut server = Server::new();
 
-        server.request_response = Some(Err("Test error".to_string()));
-        let response = server.take_request_response();
+        let first = server.begin_request(RequestKind::JoinGame, None);
+        server.finish_request(Err("Test error".to_string()));
+        let second = server.begin_request(RequestKind::JoinGame, None);
+        server.finish_request(Ok(()));
 
+        assert!(server.take_reply(second).unwrap().is_ok());
+        let response = server.take_reply(first);
         assert!(response.is_some());
         assert!(response.unwrap().is_err());
     }
 
+    #[test]
+    fn test_request_times_out() {
+        let mut server = Server::new();
+        let id = server.begin_request(RequestKind::StartCountdown, Some(Instant::now()));
+
+        server.tick().unwrap();
+
+        assert!(server.pending.is_none());
+        let resp = server.take_reply(id).unwrap();
+        assert!(resp.unwrap_err().contains("did not respond"));
+
+        // A reply arriving after the timeout is rejected
+        server.client_state = ClientState::Playing;
+        assert!(
+            server
+                .handle_playing_state(ServerMessage::StartCountdownAck)
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_game_update_take() {
         let mut server = Server::new();
@@ -501,16 +1394,20 @@ mod tests {
 
         // Set an update
         server.game_update = Some(GameUpdate {
+            tick: 0,
             snapshot: GameSnapshot {
                 engine: common::protocol::EngineSnapshot {
                     tanks: vec![],
                     projectiles: vec![],
+                    moving_walls: vec![],
                 },
-                state: common::protocol::GameState::Waiting,
+                state: GameState::Waiting,
                 game_master: 1,
+                host: None,
                 round_number: 1,
             },
             events: vec![],
+            input_acks: vec![],
         });
 
         assert!(server.game_update().is_some());
@@ -531,7 +1428,13 @@ mod tests {
             player_id: 0 as PlayerId,
             num_rounds: 3,
             map_name: MapName::Basic,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
             game_master: 1,
+            bot_model: None,
+            seed: 0,
+            state: GameState::Waiting,
+            round: 1,
         });
 
         assert!(server.initial_game_info().is_some());
@@ -559,40 +1462,58 @@ mod tests {
     }
 
     // Test state machine transitions via handle_*_state methods
-    // These require request_pending to be true to properly complete
+    // These require a pending request to properly complete
 
     #[test]
     fn test_handle_disconnected_state_handshake_ok() {
         let mut server = Server::new();
-        server.request_pending = true;
+        let id = server.begin_request(RequestKind::Connect, None);
 
-        let result = server
-            .handle_disconnected_state(ServerMessage::HandshakeResponse(HandshakeResponse::Ok));
+        let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
+            HandshakeResponse::Ok {
+                build: common::version().into(),
+                session: SessionToken(1),
+                resumed: None,
+            },
+        ));
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), ClientState::Connected);
-        assert!(!server.request_pending);
-        assert!(server.request_response.is_some());
-        assert!(server.request_response.as_ref().unwrap().is_ok());
+        assert!(server.pending.is_none());
+        let resp = server.take_reply(id);
+        assert!(resp.is_some());
+        assert!(resp.unwrap().is_ok());
     }
 
     #[test]
     fn test_handle_disconnected_state_api_mismatch() {
         let mut server = Server::new();
-        server.request_pending = true;
+        server.begin_request(RequestKind::Connect, None);
 
         let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
             HandshakeResponse::ApiMismatch,
         ));
 
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("API mismatch"));
+        assert_eq!(result, Err(tr!("server_error.api_mismatch")));
+    }
+
+    #[test]
+    fn test_handle_disconnected_state_balance_mismatch() {
+        let mut server = Server::new();
+        server.begin_request(RequestKind::Connect, None);
+
+        let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
+            HandshakeResponse::BalanceMismatch,
+        ));
+
+        assert_eq!(result, Err(tr!("server_error.balance_mismatch")));
     }
 
     #[test]
     fn test_handle_disconnected_state_server_full() {
         let mut server = Server::new();
-        server.request_pending = true;
+        server.begin_request(RequestKind::Connect, None);
 
         let result = server.handle_disconnected_state(ServerMessage::HandshakeResponse(
             HandshakeResponse::ServerFull,
@@ -605,19 +1526,18 @@ mod tests {
     #[test]
     fn test_handle_disconnected_state_error_message() {
         let mut server = Server::new();
-        server.request_pending = true;
+        server.begin_request(RequestKind::Connect, None);
 
         let result =
-            server.handle_disconnected_state(ServerMessage::Error("Custom error".to_string()));
+            server.handle_disconnected_state(ServerMessage::Error(ServerError::AlreadyConnected));
 
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Custom error"));
+        assert_eq!(result, Err(tr!("server_error.already_connected")));
     }
 
     #[test]
     fn test_handle_disconnected_state_invalid_message() {
         let mut server = Server::new();
-        server.request_pending = true;
+        server.begin_request(RequestKind::Connect, None);
 
         let result = server.handle_disconnected_state(ServerMessage::LeaveGameAck);
 
@@ -629,14 +1549,20 @@ mod tests {
     fn test_handle_connected_state_create_game_ok() {
         let mut server = Server::new();
         server.client_state = ClientState::Connected;
-        server.request_pending = true;
+        server.begin_request(RequestKind::CreateGame, None);
 
         let game_info = InitialGameInfo {
             game_code: GameCode("5678".to_string()),
             player_id: 1,
             num_rounds: 5,
             map_name: MapName::Basic,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
             game_master: 100,
+            bot_model: None,
+            seed: 0,
+            state: GameState::Waiting,
+            round: 1,
         };
 
         let result = server.handle_connected_state(ServerMessage::CreateGameReponse(
@@ -654,7 +1580,7 @@ mod tests {
     fn test_handle_connected_state_create_game_too_many() {
         let mut server = Server::new();
         server.client_state = ClientState::Connected;
-        server.request_pending = true;
+        let id = server.begin_request(RequestKind::CreateGame, None);
 
         let result = server.handle_connected_state(ServerMessage::CreateGameReponse(
             CreateGameResponse::TooManyGames,
@@ -662,21 +1588,42 @@ mod tests {
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), ClientState::Connected);
-        assert!(server.request_response.as_ref().unwrap().is_err());
+        assert!(server.take_reply(id).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_model_list_arrives_outside_requests() {
+        let mut server = Server::new();
+        server.client_state = ClientState::Connected;
+        let id = server.begin_request(RequestKind::CreateGame, None);
+
+        let models = vec!["alpha".to_string(), "beta".to_string()];
+        let result = server.handle_connected_state(ServerMessage::ModelList(models.clone()));
+
+        assert_eq!(result, Ok(ClientState::Connected));
+        assert_eq!(server.models(), models.as_slice());
+        // The request in flight is still waiting for its own reply
+        assert!(server.take_reply(id).is_none());
     }
 
     #[test]
     fn test_handle_connected_state_join_game_ok() {
         let mut server = Server::new();
         server.client_state = ClientState::Connected;
-        server.request_pending = true;
+        server.begin_request(RequestKind::JoinGame, None);
 
         let game_info = InitialGameInfo {
             game_code: GameCode("9999".to_string()),
             player_id: 2,
             num_rounds: 3,
             map_name: MapName::Basic,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
             game_master: 50,
+            bot_model: None,
+            seed: 0,
+            state: GameState::Waiting,
+            round: 1,
         };
 
         let result = server.handle_connected_state(ServerMessage::JoinGameResponse(
@@ -687,18 +1634,95 @@ mod tests {
         assert_eq!(result.unwrap(), ClientState::Playing);
     }
 
+    #[test]
+    fn test_tournament_match_starts_playing() {
+        let mut server = Server::new();
+        server.client_state = ClientState::Connected;
+        let id = server.begin_request(RequestKind::JoinTournament, None);
+
+        let info = TournamentInfo {
+            name: "Cup".into(),
+            team_size: 1,
+            rounds: 3,
+            state: common::protocol::TournamentState::Running { bracket_round: 1 },
+            matches: Vec::new(),
+            standings: Vec::new(),
+        };
+        let result = server.handle_connected_state(ServerMessage::TournamentJoinResponse(
+            TournamentJoinResponse::Ok(info.clone()),
+        ));
+        assert_eq!(result, Ok(ClientState::Connected));
+        assert_eq!(server.take_reply(id), Some(Ok(())));
+        assert_eq!(server.tournament(), Some(&info));
+
+        // Unrequested: the server pulls us into the match
+        let game_info = InitialGameInfo {
+            game_code: GameCode("1234".to_string()),
+            player_id: 0,
+            num_rounds: 3,
+            map_name: MapName::Basic,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
+            game_master: 7,
+            bot_model: None,
+            seed: 0,
+            state: GameState::Waiting,
+            round: 1,
+        };
+        let result =
+            server.handle_connected_state(ServerMessage::TournamentMatch(game_info.clone()));
+        assert_eq!(result, Ok(ClientState::Playing));
+        assert_eq!(server.initial_game_info(), Some(game_info));
+    }
+
+    #[test]
+    fn test_model_offer_and_download() {
+        let bytes = vec![3; 100];
+        let model = ModelInfo::new("champion".into(), &bytes).unwrap();
+        let mut server = Server::new();
+        server.client_state = ClientState::Connected;
+        server.enter_game(InitialGameInfo {
+            game_code: GameCode("1234".to_string()),
+            player_id: 0,
+            num_rounds: 3,
+            map_name: MapName::Basic,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
+            game_master: 7,
+            bot_model: Some(model.clone()),
+            seed: 0,
+            state: GameState::Waiting,
+            round: 1,
+        });
+        assert_eq!(server.take_model_offer(), Some(model.clone()));
+        assert_eq!(server.take_model_offer(), None);
+
+        // Chunks nobody asked for are dropped
+        server.client_state = ClientState::Playing;
+        let chunk = model.chunk(&bytes, 0);
+        let result = server.handle_playing_state(ServerMessage::ModelChunk(chunk.clone()));
+        assert_eq!(result, Ok(ClientState::Playing));
+        assert!(server.take_downloaded_model().is_none());
+
+        server.download = Some(ModelDownload::new(model.clone()).unwrap());
+        let result = server.handle_playing_state(ServerMessage::ModelChunk(chunk));
+        assert_eq!(result, Ok(ClientState::Playing));
+        assert_eq!(server.take_downloaded_model(), Some(Ok((model, bytes))));
+        assert!(server.download.is_none());
+    }
+
     #[test]
     fn test_handle_connected_state_join_game_full() {
         let mut server = Server::new();
         server.client_state = ClientState::Connected;
-        server.request_pending = true;
+        let id = server.begin_request(RequestKind::JoinGame, None);
 
         let result = server
             .handle_connected_state(ServerMessage::JoinGameResponse(JoinGameResponse::GameFull));
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), ClientState::Connected);
-        let resp = server.request_response.as_ref().unwrap();
+        let resp = server.take_reply(id).unwrap();
         assert!(resp.is_err());
         assert!(resp.as_ref().unwrap_err().contains("full"));
     }
@@ -707,29 +1731,29 @@ mod tests {
     fn test_handle_connected_state_join_game_invalid_code() {
         let mut server = Server::new();
         server.client_state = ClientState::Connected;
-        server.request_pending = true;
+        let id = server.begin_request(RequestKind::JoinGame, None);
 
         let result = server.handle_connected_state(ServerMessage::JoinGameResponse(
-            JoinGameResponse::InvalidCode,
+            JoinGameResponse::InvalidCode(GameCode("0000".to_string())),
         ));
 
         assert!(result.is_ok());
-        let resp = server.request_response.as_ref().unwrap();
-        assert!(resp.as_ref().unwrap_err().contains("invalid code"));
+        let resp = server.take_reply(id).unwrap();
+        assert!(resp.as_ref().unwrap_err().contains("0000"));
     }
 
     #[test]
     fn test_handle_connected_state_join_game_started() {
         let mut server = Server::new();
         server.client_state = ClientState::Connected;
-        server.request_pending = true;
+        let id = server.begin_request(RequestKind::JoinGame, None);
 
         let result = server.handle_connected_state(ServerMessage::JoinGameResponse(
             JoinGameResponse::GameStarted,
         ));
 
         assert!(result.is_ok());
-        let resp = server.request_response.as_ref().unwrap();
+        let resp = server.take_reply(id).unwrap();
         assert!(resp.as_ref().unwrap_err().contains("already started"));
     }
 
@@ -739,16 +1763,20 @@ mod tests {
         server.client_state = ClientState::Playing;
 
         let update = GameUpdate {
+            tick: 0,
             snapshot: GameSnapshot {
                 engine: common::protocol::EngineSnapshot {
                     tanks: vec![],
                     projectiles: vec![],
+                    moving_walls: vec![],
                 },
                 state: common::protocol::GameState::Battle(60),
                 game_master: 1,
+                host: None,
                 round_number: 2,
             },
             events: vec![],
+            input_acks: vec![],
         };
 
         let result = server.handle_playing_state(ServerMessage::GameUpdate(update));
@@ -758,24 +1786,91 @@ mod tests {
         assert!(server.game_update.is_some());
     }
 
+    #[test]
+    fn test_game_deltas_are_built_on_received_snapshots() {
+        let mut server = Server::new();
+        server.client_state = ClientState::Playing;
+        let update = |tick, seconds_left| GameUpdate {
+            tick,
+            snapshot: GameSnapshot {
+                engine: common::protocol::EngineSnapshot {
+                    tanks: vec![],
+                    projectiles: vec![],
+                    moving_walls: vec![],
+                },
+                state: common::protocol::GameState::Battle(seconds_left),
+                game_master: 1,
+                host: None,
+                round_number: 1,
+            },
+            events: vec![],
+            input_acks: vec![],
+        };
+        let base = update(4, 60);
+        let newer = update(5, 59);
+        let delta = common::protocol::GameDelta::of(&newer, &base.snapshot, 4).unwrap();
+
+        // Tick 4 hasn't come yet, so there is nothing to build on
+        let result = server.handle_playing_state(ServerMessage::GameDelta(delta.clone()));
+        assert_eq!(result, Ok(ClientState::Playing));
+        assert!(server.game_update.is_none());
+
+        server
+            .handle_playing_state(ServerMessage::GameUpdate(base))
+            .unwrap();
+        server
+            .handle_playing_state(ServerMessage::GameDelta(delta))
+            .unwrap();
+        assert_eq!(server.game_update, Some(newer));
+        assert_eq!(server.snapshots.back().map(|(tick, _)| *tick), Some(5));
+    }
+
+    #[test]
+    fn test_handle_playing_state_game_closed() {
+        let mut server = Server::new();
+        server.client_state = ClientState::Playing;
+
+        let update = GameUpdate {
+            tick: 0,
+            snapshot: GameSnapshot {
+                engine: common::protocol::EngineSnapshot {
+                    tanks: vec![],
+                    projectiles: vec![],
+                    moving_walls: vec![],
+                },
+                state: common::protocol::GameState::Waiting,
+                game_master: 1,
+                host: None,
+                round_number: 0,
+            },
+            events: vec![GameEvent::Closed(common::protocol::CloseReason::Idle)],
+            input_acks: vec![],
+        };
+
+        let result = server.handle_playing_state(ServerMessage::GameUpdate(update));
+
+        assert_eq!(result.unwrap(), ClientState::Connected);
+        assert!(server.game_update.is_some());
+    }
+
     #[test]
     fn test_handle_playing_state_start_countdown_ack() {
         let mut server = Server::new();
         server.client_state = ClientState::Playing;
-        server.request_pending = true;
+        let id = server.begin_request(RequestKind::StartCountdown, None);
 
         let result = server.handle_playing_state(ServerMessage::StartCountdownAck);
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), ClientState::Playing);
-        assert!(server.request_response.as_ref().unwrap().is_ok());
+        assert!(server.take_reply(id).unwrap().is_ok());
     }
 
     #[test]
     fn test_handle_playing_state_leave_game_ack() {
         let mut server = Server::new();
         server.client_state = ClientState::Playing;
-        server.request_pending = true;
+        server.begin_request(RequestKind::LeaveGame, None);
 
         let result = server.handle_playing_state(ServerMessage::LeaveGameAck);
 
@@ -788,10 +1883,12 @@ mod tests {
         let mut server = Server::new();
         server.client_state = ClientState::Playing;
 
-        let result = server.handle_playing_state(ServerMessage::Error("Game error".to_string()));
+        let result = server.handle_playing_state(ServerMessage::Error(ServerError::NoSuchGame(
+            GameCode("ABCD".to_string()),
+        )));
 
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Game error"));
+        // The server says which game, the client puts it into words
+        assert!(result.unwrap_err().contains("ABCD"));
     }
 
     #[test]
@@ -800,7 +1897,11 @@ mod tests {
         server.client_state = ClientState::Playing;
 
         let result =
-            server.handle_playing_state(ServerMessage::HandshakeResponse(HandshakeResponse::Ok));
+            server.handle_playing_state(ServerMessage::HandshakeResponse(HandshakeResponse::Ok {
+                build: common::version().into(),
+                session: SessionToken(1),
+                resumed: None,
+            }));
 
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("invalid server message"));
@@ -809,23 +1910,23 @@ mod tests {
     #[test]
     fn test_complete_request_without_pending_fails() {
         let mut server = Server::new();
-        server.request_pending = false;
 
-        let result = server.complete_request(Ok(()), ClientState::Connected);
+        let result = server.complete_request(RequestKind::JoinGame, Ok(()), ClientState::Connected);
 
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("no request was made"));
     }
 
     #[test]
-    fn test_complete_request_with_existing_response_fails() {
+    fn test_complete_request_of_other_kind_fails() {
         let mut server = Server::new();
-        server.request_pending = true;
-        server.request_response = Some(Ok(()));
+        server.begin_request(RequestKind::JoinGame, None);
 
-        let result = server.complete_request(Ok(()), ClientState::Connected);
+        let result =
+            server.complete_request(RequestKind::CreateGame, Ok(()), ClientState::Connected);
 
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("no request was made"));
+        assert!(result.unwrap_err().contains("not made"));
+        assert!(server.pending.is_some());
     }
 }
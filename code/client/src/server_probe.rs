@@ -0,0 +1,143 @@
+//! Latency of the saved servers, measured against their status endpoints.
+//!
+//! Every server is probed on its own thread, so one that doesn't answer doesn't hold up the
+//! others. A probe opens a connection to the status endpoint (about one round trip) and asks
+//! whether the server is ready, see [`common::status`].
+
+use crate::server::resolve_server;
+use crate::settings::ServerEntry;
+use common::status;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// How long to wait on a server before calling it unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time between two rounds of probes while the results are shown.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Probe {
+    /// No answer yet
+    Pending,
+    /// Answered; only `ready` servers take players
+    Reachable {
+        latency: Duration,
+        ready: bool,
+    },
+    Unreachable,
+}
+
+/// Latest probe of every server address.
+pub(crate) struct ServerProbes {
+    results: HashMap<String, Probe>,
+    tx: Sender<(String, Probe)>,
+    rx: Receiver<(String, Probe)>,
+    next_round: Instant,
+}
+
+impl ServerProbes {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            results: HashMap::new(),
+            tx,
+            rx,
+            next_round: Instant::now(),
+        }
+    }
+
+    /// Collects finished probes and starts a new round for `servers` when it's time.
+    pub fn update(&mut self, servers: &[ServerEntry]) {
+        while let Ok((address, probe)) = self.rx.try_recv() {
+            self.results.insert(address, probe);
+        }
+
+        let now = Instant::now();
+        if now < self.next_round {
+            return;
+        }
+        self.next_round = now + PROBE_INTERVAL;
+        for server in servers {
+            // Keep showing the last result until the new one arrives
+            self.results
+                .entry(server.address.clone())
+                .or_insert(Probe::Pending);
+            let address = server.address.clone();
+            let tx = self.tx.clone();
+            std::thread::spawn(move || {
+                let probe = probe(&address);
+                let _ = tx.send((address, probe));
+            });
+        }
+    }
+
+    pub fn get(&self, address: &str) -> Probe {
+        self.results.get(address).copied().unwrap_or(Probe::Pending)
+    }
+
+    /// The ready server with the lowest latency.
+    pub fn best<'a>(&self, servers: &'a [ServerEntry]) -> Option<&'a ServerEntry> {
+        servers
+            .iter()
+            .filter_map(|server| match self.get(&server.address) {
+                Probe::Reachable {
+                    latency,
+                    ready: true,
+                } => Some((latency, server)),
+                _ => None,
+            })
+            .min_by_key(|(latency, _)| *latency)
+            .map(|(_, server)| server)
+    }
+}
+
+fn probe(address: &str) -> Probe {
+    let Some(game_addr) = resolve_server(address) else {
+        return Probe::Unreachable;
+    };
+    match status::check_ready(status::status_addr(game_addr), PROBE_TIMEOUT) {
+        Ok(readiness) => Probe::Reachable {
+            latency: readiness.latency,
+            ready: readiness.ready,
+        },
+        Err(_) => Probe::Unreachable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(label: &str) -> ServerEntry {
+        ServerEntry {
+            label: label.into(),
+            address: format!("{}.example.com", label.to_lowercase()),
+            relay: None,
+        }
+    }
+
+    #[test]
+    fn test_best_server_is_the_fastest_ready_one() {
+        let servers = [entry("EU"), entry("US"), entry("LAN"), entry("Asia")];
+        let mut probes = ServerProbes::new();
+        let reachable = |ms, ready| Probe::Reachable {
+            latency: Duration::from_millis(ms),
+            ready,
+        };
+        probes.results = HashMap::from([
+            ("eu.example.com".to_string(), reachable(40, true)),
+            ("us.example.com".to_string(), reachable(120, true)),
+            // Fastest, but not taking players
+            ("lan.example.com".to_string(), reachable(1, false)),
+        ]);
+        assert_eq!(probes.best(&servers), Some(&servers[0]));
+        assert_eq!(probes.get("asia.example.com"), Probe::Pending);
+
+        probes
+            .results
+            .insert("eu.example.com".to_string(), Probe::Unreachable);
+        assert_eq!(probes.best(&servers), Some(&servers[1]));
+        assert_eq!(probes.best(&servers[2..]), None);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::stats::CareerStats;
+use common::game::player::TankColor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub(crate) const SETTINGS_PATH: &str = "settings.json";
+
+/// User preferences persisted between runs.
+///
+/// Missing fields fall back to their defaults, so older settings files keep loading
+/// after new options are added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// Show a short replay of the killer when the local player dies.
+    pub killcam: bool,
+    /// Append the event log to `client.log`.
+    pub log_to_file: bool,
+    /// UI language, the name of a file in `assets/lang/` without `.json`.
+    pub language: String,
+    pub crosshair: CrosshairStyle,
+    /// Accent worn on the player's tanks in multiplayer, over the team colour.
+    pub trim: TrimColor,
+    /// Pull the aim slightly toward an enemy close to the aim direction.
+    pub aim_assist: bool,
+    /// Arrows toward off-screen teammates and recent damage sources.
+    pub indicators: bool,
+    /// Hide the name tags and health bars of enemies behind walls.
+    pub fog_of_war: bool,
+    /// Download the model driving a server's bots when a game offers it.
+    pub download_models: bool,
+    /// Best star rating per challenge id.
+    pub challenge_stars: BTreeMap<String, u8>,
+    pub stats: CareerStats,
+    pub graphics: GraphicsSettings,
+    /// Servers listed in the connect menu.
+    pub servers: Vec<ServerEntry>,
+    /// The match the player was in when the client last stopped, offered to rejoin.
+    pub last_game: Option<LastGame>,
+}
+
+/// A multiplayer match the player hasn't left yet, saved so a client that crashed or was
+/// closed mid-match can get back into it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LastGame {
+    /// Where the server was reached, the relay's address if that's how we got in
+    pub server: String,
+    pub code: String,
+    pub nickname: String,
+    /// The session the server gave us, see [`common::protocol::SessionToken`]
+    pub session: u64,
+}
+
+/// A saved server, like "EU" at `eu.example.com:8080`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ServerEntry {
+    pub label: String,
+    /// Host name or address, with an optional port
+    pub address: String,
+    /// Relay room to connect through when `address` can't be reached, e.g. because the
+    /// server is behind a home router
+    #[serde(default)]
+    pub relay: Option<String>,
+}
+
+/// Window and frame pacing options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GraphicsSettings {
+    /// Wait for the display's refresh between frames. Only applied at startup.
+    pub vsync: bool,
+    /// Frames per second to stay under, uncapped when `None`.
+    pub fps_cap: Option<u32>,
+    /// Graph of recent frame times next to the FPS counter.
+    pub frame_graph: bool,
+    /// Borderless window covering the whole screen.
+    pub fullscreen: bool,
+    /// Size of the window when not fullscreen, one of [`RESOLUTIONS`].
+    pub resolution: (u32, u32),
+}
+
+/// Offered frame rate caps, `None` meaning uncapped.
+pub(crate) const FPS_CAPS: [Option<u32>; 6] =
+    [None, Some(30), Some(60), Some(120), Some(144), Some(240)];
+
+/// Offered window sizes.
+pub(crate) const RESOLUTIONS: [(u32, u32); 5] = [
+    (1080, 720),
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+];
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            fps_cap: None,
+            frame_graph: false,
+            fullscreen: false,
+            resolution: RESOLUTIONS[0],
+        }
+    }
+}
+
+/// The option after `current` in `options`, wrapping around; the first one if `current`
+/// isn't offered (e.g. edited in by hand).
+pub(crate) fn next_option<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let next = options
+        .iter()
+        .position(|&o| o == current)
+        .map_or(0, |i| (i + 1) % options.len());
+    options[next]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum CrosshairStyle {
+    /// Ring that fills up while the weapon reloads
+    #[default]
+    Ring,
+    Cross,
+    Dot,
+    Off,
+}
+
+impl CrosshairStyle {
+    pub fn next(self) -> Self {
+        match self {
+            CrosshairStyle::Ring => CrosshairStyle::Cross,
+            CrosshairStyle::Cross => CrosshairStyle::Dot,
+            CrosshairStyle::Dot => CrosshairStyle::Off,
+            CrosshairStyle::Off => CrosshairStyle::Ring,
+        }
+    }
+
+    /// Localization key of the style's name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            CrosshairStyle::Ring => "crosshair.ring",
+            CrosshairStyle::Cross => "crosshair.cross",
+            CrosshairStyle::Dot => "crosshair.dot",
+            CrosshairStyle::Off => "crosshair.off",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum TrimColor {
+    /// Just the team colour
+    #[default]
+    None,
+    Gold,
+    Orange,
+    Lime,
+    Violet,
+    White,
+}
+
+impl TrimColor {
+    pub fn next(self) -> Self {
+        match self {
+            TrimColor::None => TrimColor::Gold,
+            TrimColor::Gold => TrimColor::Orange,
+            TrimColor::Orange => TrimColor::Lime,
+            TrimColor::Lime => TrimColor::Violet,
+            TrimColor::Violet => TrimColor::White,
+            TrimColor::White => TrimColor::None,
+        }
+    }
+
+    /// Localization key of the colour's name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            TrimColor::None => "trim.none",
+            TrimColor::Gold => "trim.gold",
+            TrimColor::Orange => "trim.orange",
+            TrimColor::Lime => "trim.lime",
+            TrimColor::Violet => "trim.violet",
+            TrimColor::White => "trim.white",
+        }
+    }
+
+    /// As sent to the server, which may still adjust it to the team's colour.
+    pub fn color(self) -> Option<TankColor> {
+        match self {
+            TrimColor::None => None,
+            TrimColor::Gold => Some(TankColor::new(255, 200, 0)),
+            TrimColor::Orange => Some(TankColor::new(255, 120, 20)),
+            TrimColor::Lime => Some(TankColor::new(150, 255, 40)),
+            TrimColor::Violet => Some(TankColor::new(150, 80, 255)),
+            TrimColor::White => Some(TankColor::new(240, 240, 240)),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            killcam: true,
+            log_to_file: false,
+            language: crate::i18n::DEFAULT_LANGUAGE.to_string(),
+            crosshair: CrosshairStyle::default(),
+            trim: TrimColor::default(),
+            aim_assist: false,
+            indicators: true,
+            fog_of_war: false,
+            download_models: false,
+            challenge_stars: BTreeMap::new(),
+            stats: CareerStats::default(),
+            graphics: GraphicsSettings::default(),
+            servers: vec![ServerEntry {
+                label: "LAN".into(),
+                address: "localhost".into(),
+                relay: None,
+            }],
+            last_game: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings from `path`, using defaults if the file is missing or malformed.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let path = std::env::temp_dir().join("neuroblasters_settings_roundtrip.json");
+        let settings = Settings {
+            killcam: false,
+            log_to_file: true,
+            language: "pl".to_string(),
+            crosshair: CrosshairStyle::Dot,
+            trim: TrimColor::Lime,
+            aim_assist: true,
+            indicators: false,
+            fog_of_war: true,
+            download_models: true,
+            challenge_stars: BTreeMap::from([("duel".to_string(), 2)]),
+            stats: CareerStats {
+                multiplayer_wins: 3,
+                shots_fired: 120,
+                shots_hit: 45,
+                ..Default::default()
+            },
+            graphics: GraphicsSettings {
+                vsync: false,
+                fps_cap: Some(144),
+                frame_graph: true,
+                fullscreen: true,
+                resolution: (1920, 1080),
+            },
+            servers: vec![ServerEntry {
+                label: "EU".to_string(),
+                address: "eu.example.com:9000".to_string(),
+                relay: Some("relay.example.com:7042".to_string()),
+            }],
+            last_game: Some(LastGame {
+                server: "eu.example.com:9000".to_string(),
+                code: "4821".to_string(),
+                nickname: "player".to_string(),
+                session: 77,
+            }),
+        };
+
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path), settings);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_settings_missing_file_uses_defaults() {
+        let path = std::env::temp_dir().join("neuroblasters_settings_does_not_exist.json");
+        assert_eq!(Settings::load(&path), Settings::default());
+    }
+
+    #[test]
+    fn test_next_option_wraps_around() {
+        assert_eq!(next_option(&FPS_CAPS, None), Some(30));
+        assert_eq!(next_option(&FPS_CAPS, Some(240)), None);
+        assert_eq!(next_option(&RESOLUTIONS, (800, 600)), RESOLUTIONS[0]);
+    }
+
+    #[test]
+    fn test_settings_missing_fields_use_defaults() {
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, Settings::default());
+    }
+}
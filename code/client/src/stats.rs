@@ -0,0 +1,133 @@
+//! Local career statistics, stored with the settings and independent of any server.
+
+use common::game::{Projectile, Tank};
+use common::protocol::PlayerId;
+use serde::{Deserialize, Serialize};
+
+/// Extra distance at which a vanished projectile still counts as having hit a tank, since
+/// it may have moved up to a tick's worth before colliding.
+const HIT_MARGIN: f32 = 15.;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct CareerStats {
+    pub training_matches: u32,
+    pub challenge_runs: u32,
+    pub challenges_completed: u32,
+    pub multiplayer_wins: u32,
+    pub multiplayer_losses: u32,
+    pub shots_fired: u64,
+    pub shots_hit: u64,
+}
+
+impl CareerStats {
+    /// Share of shots that hit an enemy, `None` before the first shot.
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f32 / self.shots_fired as f32)
+    }
+
+    /// Moves the shots counted so far into the totals.
+    pub fn add_shots(&mut self, shots: &mut ShotCounter) {
+        self.shots_fired += shots.fired;
+        self.shots_hit += shots.hit.min(shots.fired);
+        *shots = ShotCounter::default();
+    }
+}
+
+/// Counts the local player's shots and hits by comparing consecutive engine states, so it
+/// works the same for local matches and server snapshots.
+#[derive(Debug, Default)]
+pub(crate) struct ShotCounter {
+    fired: u64,
+    hit: u64,
+}
+
+impl ShotCounter {
+    pub fn observe(
+        &mut self,
+        player_id: PlayerId,
+        (previous_tanks, previous_projectiles): (&[Tank], &[Projectile]),
+        (tanks, projectiles): (&[Tank], &[Projectile]),
+    ) {
+        let mine = |p: &&Projectile| p.owner_info.id == player_id;
+        let exists = |list: &[Projectile], id| list.iter().any(|p| p.id == id);
+
+        self.fired += projectiles
+            .iter()
+            .filter(mine)
+            .filter(|p| !exists(previous_projectiles, p.id))
+            .count() as u64;
+
+        for gone in previous_projectiles
+            .iter()
+            .filter(mine)
+            .filter(|p| !exists(projectiles, p.id))
+        {
+            let hit_someone = previous_tanks.iter().any(|target| {
+                let was_hurt = tanks
+                    .iter()
+                    .find(|t| t.player_info.id == target.player_info.id)
+                    .is_none_or(|t| t.health < target.health);
+                target.player_info.team != gone.owner_info.team
+                    && was_hurt
+                    && target.position.distance(gone.position)
+                        <= target.radius + gone.radius + HIT_MARGIN
+            });
+            if hit_someone {
+                self.hit += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::ProjectileKind;
+    use common::game::player::PlayerInfo;
+    use common::protocol::Team;
+    use macroquad::prelude::Vec2;
+
+    fn projectile(id: u64, owner: &Tank, position: Vec2) -> Projectile {
+        Projectile {
+            id,
+            kind: ProjectileKind::Bullet,
+            owner_info: owner.player_info.clone(),
+            position,
+            velocity: Vec2::ZERO,
+            radius: 5.,
+            age: 0.,
+        }
+    }
+
+    #[test]
+    fn test_shots_and_hits_are_counted() {
+        let me = Tank::new(PlayerInfo::new(1, "me".into(), Team::Blue), Vec2::ZERO);
+        let enemy = Tank::new(
+            PlayerInfo::new(2, "enemy".into(), Team::Red),
+            Vec2::new(100., 0.),
+        );
+        let tanks = vec![me.clone(), enemy.clone()];
+        let mut counter = ShotCounter::default();
+
+        // Two shots appear, one of them the enemy's
+        let flying = vec![
+            projectile(0, &me, Vec2::new(90., 0.)),
+            projectile(1, &me, Vec2::new(0., 300.)),
+            projectile(2, &enemy, Vec2::new(50., 0.)),
+        ];
+        counter.observe(1, (&tanks, &[]), (&tanks, &flying));
+        assert_eq!((counter.fired, counter.hit), (2, 0));
+
+        // One vanishes next to the enemy, which loses health; the other hits a wall
+        let mut hurt = tanks.clone();
+        hurt[1].health -= 10.;
+        counter.observe(1, (&tanks, &flying), (&hurt, &flying[2..]));
+        assert_eq!((counter.fired, counter.hit), (2, 1));
+
+        let mut stats = CareerStats::default();
+        stats.add_shots(&mut counter);
+        assert_eq!(stats.accuracy(), Some(0.5));
+        assert_eq!(counter.fired, 0);
+    }
+}
@@ -1,15 +1,17 @@
 use macroquad::miniquad::window::screen_size;
 use macroquad::prelude::*;
 
-use crate::ui::theme::{MAIN_FONT, TEXT_COLOR};
+use crate::ui::theme::TEXT_COLOR;
 
 pub(crate) mod button;
 pub(crate) mod field;
+pub(crate) mod scroll;
 pub(crate) mod text;
 pub(crate) mod text_field;
 pub(crate) mod theme;
 
 pub(crate) use button::*;
+pub(crate) use scroll::*;
 pub(crate) use text::*;
 pub(crate) use text_field::*;
 pub(crate) use theme::*;
@@ -30,14 +32,15 @@ fn get_ui_transform() -> (f32, f32, f32) {
 
 pub(crate) fn default_text_params() -> TextParams<'static> {
     TextParams {
-        font: Some(&MAIN_FONT),
+        font: Some(main_font().get()),
         font_scale: 1.,
         color: TEXT_COLOR,
         ..Default::default()
     }
 }
 
-fn scale_dims(x: f32, y: f32, w: f32, h: f32) -> (f32, f32, f32, f32) {
+/// Transforms a rectangle in canonical UI coordinates to screen coordinates.
+pub(crate) fn scale_dims(x: f32, y: f32, w: f32, h: f32) -> (f32, f32, f32, f32) {
     let (scale, transform_x, transform_y) = get_ui_transform();
     let x = x * scale + transform_x;
     let y = y * scale + transform_y;
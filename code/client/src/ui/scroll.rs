@@ -0,0 +1,120 @@
+//! Scrolling lists and the clipping they need.
+
+use macroquad::prelude::*;
+
+use crate::ui::{CANONICAL_SCREEN_HEIGHT, CANONICAL_SCREEN_WIDTH, calc_transform};
+
+/// Canonical pixels scrolled per mouse wheel step.
+const SCROLL_SPEED: f32 = 30.;
+
+/// Limits drawing to a rectangle given in canonical UI coordinates, until dropped.
+///
+/// Goes through macroquad's batcher instead of raw GL, so it applies to exactly the draw
+/// calls made while it lives, and follows the letterboxing and DPI scale of the current
+/// window size.
+pub(crate) struct ClipRect;
+
+impl ClipRect {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        let rect = scissor_rect(
+            (x, y, w, h),
+            calc_transform(CANONICAL_SCREEN_WIDTH, CANONICAL_SCREEN_HEIGHT),
+            screen_dpi_scale(),
+            macroquad::miniquad::window::screen_size(),
+        );
+        unsafe { get_internal_gl() }.quad_gl.scissor(Some(rect));
+        ClipRect
+    }
+}
+
+impl Drop for ClipRect {
+    fn drop(&mut self) {
+        unsafe { get_internal_gl() }.quad_gl.scissor(None);
+    }
+}
+
+/// The framebuffer pixels (from the top-left corner) covered by the canonical rectangle
+/// `(x, y, w, h)` drawn with `(scale, x_offset, y_offset)`, cut to the framebuffer.
+fn scissor_rect(
+    (x, y, w, h): (f32, f32, f32, f32),
+    (scale, x_offset, y_offset): (f32, f32, f32),
+    dpi_scale: f32,
+    (framebuffer_w, framebuffer_h): (f32, f32),
+) -> (i32, i32, i32, i32) {
+    let to_pixels = |v: f32| (v * dpi_scale).round().max(0.);
+    let left = to_pixels(x * scale + x_offset).min(framebuffer_w);
+    let top = to_pixels(y * scale + y_offset).min(framebuffer_h);
+    let right = to_pixels((x + w) * scale + x_offset).clamp(left, framebuffer_w);
+    let bottom = to_pixels((y + h) * scale + y_offset).clamp(top, framebuffer_h);
+    (
+        left as i32,
+        top as i32,
+        (right - left) as i32,
+        (bottom - top) as i32,
+    )
+}
+
+/// Vertical scroll position of a list shown in a band of the screen.
+#[derive(Default)]
+pub(crate) struct ScrollArea {
+    offset: f32,
+}
+
+impl ScrollArea {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows the mouse wheel. Clamped when drawn, against the content it is drawn with.
+    pub fn update(&mut self) {
+        let (_, wheel) = mouse_wheel();
+        self.offset -= wheel * SCROLL_SPEED;
+    }
+
+    /// Starts drawing a list `content_height` tall in the full-width band from `top` to
+    /// `bottom`: clips drawing to the band until the returned guard is dropped, and gives
+    /// the canonical y at which the list starts.
+    pub fn begin(&mut self, top: f32, bottom: f32, content_height: f32) -> (ClipRect, f32) {
+        self.clamp(bottom - top, content_height);
+        let clip = ClipRect::new(0., top, CANONICAL_SCREEN_WIDTH, bottom - top);
+        (clip, top - self.offset)
+    }
+
+    fn clamp(&mut self, view_height: f32, content_height: f32) {
+        let max = (content_height - view_height).max(0.);
+        self.offset = self.offset.clamp(0., max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scissor_rect_follows_letterbox_and_dpi() {
+        // 2x scale, pillarboxed 100 px on the left, on a 2x DPI screen
+        let rect = scissor_rect((10., 20., 30., 40.), (2., 100., 0.), 2., (2000., 1000.));
+        assert_eq!(rect, (240, 80, 120, 160));
+
+        // Cut to the framebuffer, never negative
+        let rect = scissor_rect((-10., 450., 30., 100.), (1., 0., 0.), 1., (800., 500.));
+        assert_eq!(rect, (0, 450, 20, 50));
+        let rect = scissor_rect((900., 600., 30., 100.), (1., 0., 0.), 1., (800., 500.));
+        assert_eq!(rect, (800, 500, 0, 0));
+    }
+
+    #[test]
+    fn test_scroll_area_stays_within_the_content() {
+        let mut area = ScrollArea::new();
+        area.offset = 500.;
+        area.clamp(300., 400.);
+        assert_eq!(area.offset, 100.);
+
+        // A list that got shorter (or a taller window) scrolls back
+        area.clamp(300., 200.);
+        assert_eq!(area.offset, 0.);
+        area.offset = -20.;
+        area.clamp(300., 400.);
+        assert_eq!(area.offset, 0.);
+    }
+}
@@ -83,6 +83,11 @@ impl TextField {
         self.text_string.clone()
     }
 
+    /// Replaces the text, cut to the field's length.
+    pub fn set_text(&mut self, text: &str) {
+        self.text_string = text.chars().take(self.max_len as usize).collect();
+    }
+
     pub fn reset(&mut self) {
         self.text_string.clear();
         self.focused = false;
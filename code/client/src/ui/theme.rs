@@ -1,6 +1,5 @@
-use futures::executor::block_on;
+use crate::assets::Handle;
 use macroquad::prelude::*;
-use once_cell::sync::Lazy;
 
 pub const DARK_BG: Color = Color::new(0.05, 0.05, 0.1, 1.0); // Very dark blue/black
 pub const GRID_COLOR: Color = Color::new(0.0, 1.0, 1.0, 0.1); // Faint cyan
@@ -23,14 +22,10 @@ pub const BUTTON_W: f32 = 300.;
 /// Typical button height
 pub const BUTTON_H: f32 = 55.;
 
-pub static MAIN_FONT: Lazy<Font> = Lazy::new(|| {
-    let mut font = block_on(load_ttf_font("assets/arcade_riders.ttf")).unwrap();
-    font.set_filter(FilterMode::Nearest); // Better results for a pixelated font
-    font
-});
+pub fn main_font() -> Handle<Font> {
+    Handle::of("assets/arcade_riders.ttf")
+}
 
-pub static BANNER_TEXUTRE: Lazy<Texture2D> = Lazy::new(|| {
-    let banner = block_on(load_texture("assets/banner.png")).unwrap();
-    banner.set_filter(FilterMode::Nearest); // Better for Pixel art
-    banner
-});
+pub fn banner_texture() -> Handle<Texture2D> {
+    Handle::of("assets/banner.png")
+}
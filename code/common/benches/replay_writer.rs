@@ -0,0 +1,102 @@
+//! Cost of recording a replay while a headless 4v4 match is played, as in long training
+//! sessions. Run with `cargo bench -p common --bench replay_writer`.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use common::ai::{BotAgent, BotDifficulty};
+use common::game::engine::GameEngine;
+use common::game::player::PlayerInfo;
+use common::game::replay::{ReplayHeader, ReplayRecorder};
+use common::game::replay_writer::ReplayWriter;
+use common::net::protocol::{MapDefinition, PlayerId, Tank, Team};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const TICKS: usize = 1000;
+const DT: f32 = 1.0 / 60.0;
+
+enum Recording<'a> {
+    None,
+    Recorder(&'a mut ReplayRecorder),
+    Writer(&'a mut ReplayWriter),
+}
+
+/// Plays `TICKS` ticks of Hunters against Hunters on the default map.
+fn run_4v4_match(engine: &mut GameEngine, mut recording: Recording) {
+    let mut bots: Vec<BotAgent> = engine
+        .tanks
+        .iter()
+        .map(|t| BotAgent::new(t.player_info.clone(), BotDifficulty::Hunter, 1))
+        .collect();
+
+    for _ in 0..TICKS {
+        let mut inputs = HashMap::new();
+        for (tank, bot) in engine.tanks.iter().zip(&mut bots) {
+            let ping = engine.blackboard.ping_for(tank, &engine.map);
+            let input = bot.generate_input(
+                tank,
+                &engine.tanks,
+                &engine.projectiles,
+                &engine.map,
+                DT,
+                ping,
+            );
+            inputs.insert(tank.player_info.id, input);
+        }
+        match &mut recording {
+            Recording::None => {}
+            Recording::Recorder(recorder) => recorder.record(DT, &inputs),
+            Recording::Writer(writer) => writer.record(DT, &inputs).unwrap(),
+        }
+        black_box(engine.tick(DT, inputs));
+    }
+}
+
+fn new_match() -> GameEngine {
+    let map = MapDefinition::load();
+    let mut engine = GameEngine::new(map.clone());
+    for team in [Team::Blue, Team::Red] {
+        for position in map.team_spawns(team, 4) {
+            let id = engine.tanks.len() as PlayerId;
+            let info = PlayerInfo::new(id, format!("P{}", id), team);
+            engine.tanks.push(Tank::new(info, position));
+        }
+    }
+    engine
+}
+
+fn bench_recording(c: &mut Criterion) {
+    let path = std::env::temp_dir().join("replay_writer_bench.nbreplay");
+    let mut group = c.benchmark_group("run_4v4_match");
+
+    group.bench_function("no replay", |b| {
+        b.iter(|| run_4v4_match(&mut new_match(), Recording::None))
+    });
+    group.bench_function("ReplayRecorder", |b| {
+        b.iter(|| {
+            let mut engine = new_match();
+            let mut recorder = ReplayRecorder::new(&engine, 1);
+            run_4v4_match(&mut engine, Recording::Recorder(&mut recorder));
+            black_box(recorder.finish());
+        })
+    });
+    group.bench_function("ReplayWriter", |b| {
+        b.iter(|| {
+            let mut engine = new_match();
+            let header = ReplayHeader::new(&engine, 1);
+            let mut writer = ReplayWriter::create(&path, &header).unwrap();
+            run_4v4_match(&mut engine, Recording::Writer(&mut writer));
+            writer.finish().unwrap();
+        })
+    });
+
+    group.finish();
+    let _ = std::fs::remove_file(path);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_recording
+}
+criterion_main!(benches);
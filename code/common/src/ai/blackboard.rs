@@ -0,0 +1,82 @@
+//! What the bots of a team know together, beyond what they see themselves.
+
+use crate::net::protocol::{MapDefinition, PingKind, Tank, Team};
+use bincode::{Decode, Encode};
+use glam::Vec2;
+use std::collections::HashMap;
+
+/// Bots forget a ping this many seconds after it was made.
+pub const PING_MEMORY: f32 = 10.0;
+
+/// The latest spot a teammate pinged.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct TeamPing {
+    pub kind: PingKind,
+    #[bincode(with_serde)]
+    pub world_pos: Vec2,
+    /// Seconds since the ping, below [`PING_MEMORY`]
+    pub age: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Encode, Decode)]
+pub struct TeamBlackboard {
+    pings: HashMap<Team, TeamPing>,
+}
+
+impl TeamBlackboard {
+    /// Replaces the latest ping of `team`.
+    pub fn ping(&mut self, team: Team, kind: PingKind, world_pos: Vec2) {
+        self.pings.insert(
+            team,
+            TeamPing {
+                kind,
+                world_pos,
+                age: 0.0,
+            },
+        );
+    }
+
+    pub fn latest_ping(&self, team: Team) -> Option<TeamPing> {
+        self.pings.get(&team).copied()
+    }
+
+    /// The latest ping of `tank`'s team, if it's in the tank's arena.
+    pub fn ping_for(&self, tank: &Tank, map: &MapDefinition) -> Option<TeamPing> {
+        self.latest_ping(tank.player_info.team)
+            .filter(|ping| map.arena_index(ping.world_pos) == map.arena_index(tank.position))
+    }
+
+    /// Ages the pings, forgetting those older than [`PING_MEMORY`].
+    pub fn tick(&mut self, dt: f32) {
+        self.pings.retain(|_, ping| {
+            ping.age += dt;
+            ping.age < PING_MEMORY
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.pings.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pings_are_per_team_and_forgotten() {
+        let mut board = TeamBlackboard::default();
+        board.ping(Team::Blue, PingKind::Attack, Vec2::new(1.0, 2.0));
+        board.tick(1.0);
+        board.ping(Team::Blue, PingKind::Help, Vec2::new(3.0, 4.0));
+        assert_eq!(board.latest_ping(Team::Red), None);
+
+        board.tick(2.0);
+        let ping = board.latest_ping(Team::Blue).unwrap();
+        assert_eq!(ping.kind, PingKind::Help);
+        assert_eq!(ping.age, 2.0);
+
+        board.tick(PING_MEMORY);
+        assert_eq!(board.latest_ping(Team::Blue), None);
+    }
+}
@@ -1,20 +1,53 @@
+pub mod blackboard;
+pub mod names;
 pub mod pathfinding;
 
+use self::blackboard::TeamPing;
 use self::pathfinding::find_path_a_star;
-use crate::game::PROJECTILE_SPEED;
+use crate::game::map::mix64;
 use crate::game::player::PlayerInfo;
+use crate::game::{HEAT_PER_SHOT, balance};
+use crate::net::protocol::PlayerId;
 use crate::net::protocol::objects::{InputPayload, MapDefinition, Projectile, Tank};
+use crate::rl::{RlPolicy, default_model};
+use bincode::{Decode, Encode};
 use glam::Vec2;
-use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// The bots' random numbers. Unlike `StdRng` its output is fixed across releases and its
+/// state can be saved, see [`SavedBot`].
+pub type BotRng = rand_chacha::ChaCha12Rng;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, serde::Serialize, serde::Deserialize,
+)]
 pub enum BotDifficulty {
-    Dummy,      // Does nothing
-    Turret,     // Static, shoots when he sees you
-    Wanderer,   // Moves randomly, shoots
-    Hunter,     // Hunts you down
-    Terminator, // Hunts you down but better
+    Dummy,         // Does nothing
+    Turret,        // Static, shoots when he sees you
+    Wanderer,      // Moves randomly, shoots
+    Hunter,        // Hunts you down
+    Terminator,    // Hunts you down but better
+    TrainedKiller, // The built-in RL model
+}
+
+impl std::str::FromStr for BotDifficulty {
+    type Err = String;
+
+    /// Case-insensitive difficulty name, e.g. `hunter`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "dummy" => Ok(Self::Dummy),
+            "turret" => Ok(Self::Turret),
+            "wanderer" => Ok(Self::Wanderer),
+            "hunter" => Ok(Self::Hunter),
+            "terminator" => Ok(Self::Terminator),
+            "trainedkiller" | "trained_killer" => Ok(Self::TrainedKiller),
+            other => Err(format!(
+                "unknown difficulty '{}' (expected dummy, turret, wanderer, hunter, terminator or trained_killer)",
+                other
+            )),
+        }
+    }
 }
 
 /// Everything a bot is allowed to know to make a decision.
@@ -24,7 +57,9 @@ pub struct BotContext<'a> {
     pub projectiles: &'a Vec<Projectile>,
     pub map: &'a MapDefinition,
     pub dt: f32,
-    pub rng: &'a mut StdRng,
+    pub rng: &'a mut BotRng,
+    /// The latest ping of a teammate, see [`blackboard`]
+    pub ping: Option<TeamPing>,
 }
 
 // Clone support for Policy
@@ -50,6 +85,15 @@ impl Clone for Box<dyn Policy> {
 /// Allows us to swap "Scripted Logic" with "Neural Networks" instantly.
 pub trait Policy: Send + Sync + PolicyClone {
     fn compute_input(&mut self, ctx: &mut BotContext) -> InputPayload;
+
+    /// Drops any planned route, e.g. because walls moved; the next one is planned afresh.
+    fn forget_path(&mut self) {}
+}
+
+/// The seed of bot `bot_id` in a match seeded with `match_seed`. Bots of one match get
+/// unrelated seeds, so they don't all make the same moves.
+pub fn bot_seed(match_seed: u64, bot_id: PlayerId) -> u64 {
+    mix64(match_seed ^ u64::from(bot_id).wrapping_mul(0x9E37_79B9_7F4A_7C15))
 }
 
 #[derive(Clone)]
@@ -57,15 +101,49 @@ pub struct BotAgent {
     pub player_info: PlayerInfo,
     pub difficulty: BotDifficulty,
     policy: Box<dyn Policy>, // The active brain
-    rng: StdRng,
+    rng: BotRng,
+}
+
+/// What of a bot goes into a saved game. Its policy isn't saved: a resumed bot plans its
+/// route afresh, as after [`BotAgent::forget_path`].
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SavedBot {
+    pub player_info: PlayerInfo,
+    pub difficulty: BotDifficulty,
+    #[bincode(with_serde)]
+    pub rng: BotRng,
 }
 
 impl BotAgent {
     pub fn new(player_info: PlayerInfo, difficulty: BotDifficulty, seed: u64) -> Self {
-        let rng = StdRng::seed_from_u64(seed);
+        Self {
+            player_info,
+            difficulty,
+            policy: Self::policy(difficulty),
+            rng: BotRng::seed_from_u64(seed),
+        }
+    }
 
+    pub fn save(&self) -> SavedBot {
+        SavedBot {
+            player_info: self.player_info.clone(),
+            difficulty: self.difficulty,
+            rng: self.rng.clone(),
+        }
+    }
+
+    pub fn restore(saved: SavedBot) -> Self {
+        Self {
+            player_info: saved.player_info,
+            difficulty: saved.difficulty,
+            policy: Self::policy(saved.difficulty),
+            rng: saved.rng,
+        }
+    }
+
+    fn policy(difficulty: BotDifficulty) -> Box<dyn Policy> {
         // Factory: Pick the right brain based on difficulty
-        let policy: Box<dyn Policy> = match difficulty {
+        match difficulty {
             BotDifficulty::Dummy => Box::new(DummyPolicy),
             BotDifficulty::Turret => Box::new(ScriptedPolicy::new(ScriptedBehavior::Turret)),
             BotDifficulty::Wanderer => Box::new(ScriptedPolicy::new(ScriptedBehavior::Wanderer)),
@@ -73,13 +151,10 @@ impl BotAgent {
             BotDifficulty::Terminator => {
                 Box::new(ScriptedPolicy::new(ScriptedBehavior::Terminator))
             }
-        };
-
-        Self {
-            player_info,
-            difficulty,
-            policy,
-            rng,
+            // Every trained bot thinks with the one model, loaded once
+            BotDifficulty::TrainedKiller => {
+                Box::new(RlPolicy::shared(default_model::shared_brain()))
+            }
         }
     }
 
@@ -91,6 +166,7 @@ impl BotAgent {
         projectiles: &Vec<Projectile>,
         map: &MapDefinition,
         dt: f32,
+        ping: Option<TeamPing>,
     ) -> InputPayload {
         let mut ctx = BotContext {
             me,
@@ -99,9 +175,14 @@ impl BotAgent {
             map,
             dt,
             rng: &mut self.rng,
+            ping,
         };
         self.policy.compute_input(&mut ctx)
     }
+
+    pub fn forget_path(&mut self) {
+        self.policy.forget_path();
+    }
 }
 
 // ---- Helper functions for scripted behaviours ----
@@ -122,6 +203,27 @@ fn find_closest_enemy<'a>(ctx: &BotContext<'a>) -> Option<&'a Tank> {
         })
 }
 
+/// Enemies count as this much further away per unit they are from a teammate's ping.
+const PING_BIAS: f32 = 0.5;
+
+/// The enemy to go for: the closest one, preferring those near the spot a teammate pinged.
+fn find_target<'a>(ctx: &BotContext<'a>) -> Option<&'a Tank> {
+    let Some(ping) = ctx.ping else {
+        return find_closest_enemy(ctx);
+    };
+    let score = |p: &Tank| {
+        ctx.me.position.distance(p.position) + PING_BIAS * p.position.distance(ping.world_pos)
+    };
+    ctx.players
+        .iter()
+        .filter(|p| {
+            p.health > 0.0
+                && p.player_info.id != ctx.me.player_info.id
+                && p.player_info.team != ctx.me.player_info.team
+        })
+        .min_by(|p1, p2| score(p1).total_cmp(&score(p2)))
+}
+
 /// Checks if a straight line between p1 and p2 is clear of walls AND other players.
 fn has_line_of_sight(ctx: &BotContext, p1: Vec2, p2: Vec2) -> bool {
     let diff = p2 - p1;
@@ -139,7 +241,7 @@ fn has_line_of_sight(ctx: &BotContext, p1: Vec2, p2: Vec2) -> bool {
         let sample_point = p1 + dir * (i as f32 * step_size);
 
         // Check Walls (Existing logic)
-        for wall in &ctx.map.walls {
+        for wall in ctx.map.solid_walls() {
             if sample_point.x >= wall.min.x
                 && sample_point.x <= wall.max.x
                 && sample_point.y >= wall.min.y
@@ -177,11 +279,17 @@ fn has_line_of_sight(ctx: &BotContext, p1: Vec2, p2: Vec2) -> bool {
     true
 }
 
-/// Calculates where to aim to hit a moving target (Interception).
-fn predict_aim_position(shooter_pos: Vec2, target_pos: Vec2, target_vel: Vec2) -> Vec2 {
+/// Calculates where to aim to hit a moving target (Interception) with shots flying at
+/// `proj_speed`.
+fn predict_aim_position(
+    shooter_pos: Vec2,
+    target_pos: Vec2,
+    target_vel: Vec2,
+    proj_speed: f32,
+) -> Vec2 {
     let to_target = target_pos - shooter_pos;
     let target_speed_sq = target_vel.length_squared();
-    let proj_speed_sq = PROJECTILE_SPEED * PROJECTILE_SPEED;
+    let proj_speed_sq = proj_speed * proj_speed;
 
     // Quadratic equation coefficients: a*t^2 + b*t + c = 0
     let a = target_speed_sq - proj_speed_sq;
@@ -277,6 +385,7 @@ impl ScriptedPolicy {
                 move_axis: Vec2::ZERO,
                 aim_pos: *pos,
                 shoot: true,
+                grenade: false,
             };
         }
 
@@ -284,6 +393,7 @@ impl ScriptedPolicy {
             move_axis: Vec2::ZERO,
             aim_pos: ctx.me.position,
             shoot: false,
+            grenade: false,
         }
     }
 
@@ -337,6 +447,7 @@ impl ScriptedPolicy {
             move_axis,
             aim_pos,
             shoot,
+            grenade: false,
         }
     }
 
@@ -381,6 +492,7 @@ impl ScriptedPolicy {
                 move_axis,
                 aim_pos,
                 shoot,
+                grenade: false,
             }
         } else {
             self.wanderer_logic(ctx)
@@ -388,7 +500,7 @@ impl ScriptedPolicy {
     }
 
     fn terminator_logic(&mut self, ctx: &mut BotContext) -> InputPayload {
-        if let Some(enemy) = find_closest_enemy(ctx) {
+        if let Some(enemy) = find_target(ctx) {
             let mut move_axis = Vec2::ZERO;
             let mut shoot = false;
 
@@ -414,10 +526,16 @@ impl ScriptedPolicy {
             }
 
             // Predictive Aiming (The "Terminator" part)
-            let aim_pos = predict_aim_position(ctx.me.position, enemy.position, enemy.velocity);
-
-            // Fire
-            if has_line_of_sight(ctx, ctx.me.position, aim_pos) {
+            let aim_pos = predict_aim_position(
+                ctx.me.position,
+                enemy.position,
+                enemy.velocity,
+                balance().projectile_speed() * ctx.map.scale(),
+            );
+
+            // Fire, stopping short of overheating the gun
+            if has_line_of_sight(ctx, ctx.me.position, aim_pos) && ctx.me.heat + HEAT_PER_SHOT < 1.0
+            {
                 shoot = true;
             }
 
@@ -425,6 +543,7 @@ impl ScriptedPolicy {
                 move_axis,
                 aim_pos,
                 shoot,
+                grenade: false,
             }
         } else {
             self.wanderer_logic(ctx)
@@ -443,6 +562,11 @@ impl Policy for ScriptedPolicy {
             ScriptedBehavior::Terminator => self.terminator_logic(ctx),
         }
     }
+
+    fn forget_path(&mut self) {
+        self.path.clear();
+        self.path_recalc_timer = 0.0;
+    }
 }
 // --- Dummy Policy ---
 
@@ -455,6 +579,7 @@ impl Policy for DummyPolicy {
             move_axis: Vec2::ZERO,
             aim_pos: Vec2::ZERO,
             shoot: false,
+            grenade: false,
         }
     }
 }
@@ -0,0 +1,105 @@
+//! Nicknames of bots, like `Falcon [H]`: a name themed after the bot's difficulty, which the
+//! tag after it spells out.
+
+use super::BotDifficulty;
+
+const DUMMY_NAMES: &[&str] = &[
+    "Scarecrow",
+    "Sandbag",
+    "Mannequin",
+    "Decoy",
+    "Punchbag",
+    "Cardboard",
+    "Crash Test",
+    "Target",
+];
+const TURRET_NAMES: &[&str] = &[
+    "Bastion",
+    "Sentinel",
+    "Bulwark",
+    "Rampart",
+    "Citadel",
+    "Outpost",
+    "Bunker",
+    "Watchtower",
+];
+const WANDERER_NAMES: &[&str] = &[
+    "Nomad", "Drifter", "Rover", "Vagabond", "Pilgrim", "Scout", "Ranger", "Voyager",
+];
+const HUNTER_NAMES: &[&str] = &[
+    "Falcon", "Wolf", "Viper", "Panther", "Hawk", "Jackal", "Lynx", "Cobra",
+];
+const TERMINATOR_NAMES: &[&str] = &[
+    "Titan",
+    "Juggernaut",
+    "Reaper",
+    "Colossus",
+    "Warlord",
+    "Goliath",
+    "Ravager",
+    "Behemoth",
+];
+const TRAINED_KILLER_NAMES: &[&str] = &[
+    "Synapse", "Neuron", "Axon", "Cortex", "Tensor", "Gradient", "Epoch", "Dendrite",
+];
+
+fn pool(difficulty: BotDifficulty) -> &'static [&'static str] {
+    match difficulty {
+        BotDifficulty::Dummy => DUMMY_NAMES,
+        BotDifficulty::Turret => TURRET_NAMES,
+        BotDifficulty::Wanderer => WANDERER_NAMES,
+        BotDifficulty::Hunter => HUNTER_NAMES,
+        BotDifficulty::Terminator => TERMINATOR_NAMES,
+        BotDifficulty::TrainedKiller => TRAINED_KILLER_NAMES,
+    }
+}
+
+fn tag(difficulty: BotDifficulty) -> &'static str {
+    match difficulty {
+        BotDifficulty::Dummy => "D",
+        BotDifficulty::Turret => "T",
+        BotDifficulty::Wanderer => "W",
+        BotDifficulty::Hunter => "H",
+        BotDifficulty::Terminator => "X",
+        BotDifficulty::TrainedKiller => "N",
+    }
+}
+
+/// A nickname for a bot of `difficulty` that isn't `taken` yet. `seed` picks where in the
+/// pool of names to start; once the pool runs out, names get numbered.
+pub fn bot_nickname(difficulty: BotDifficulty, seed: u64, taken: &[&str]) -> String {
+    let names = pool(difficulty);
+    let tag = tag(difficulty);
+    let start = (seed % names.len() as u64) as usize;
+    let candidates = names.iter().cycle().skip(start).take(names.len());
+    let numbered = (2..).map(|n| format!("{} {} [{}]", names[start], n, tag));
+    candidates
+        .map(|name| format!("{} [{}]", name, tag))
+        .chain(numbered)
+        .find(|nickname| !taken.contains(&nickname.as_str()))
+        .expect("numbered names never run out")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_nicknames_are_unique_within_a_game() {
+        let mut taken: Vec<String> = vec!["Falcon [H]".to_string()];
+        for _ in 0..HUNTER_NAMES.len() + 1 {
+            let names: Vec<&str> = taken.iter().map(String::as_str).collect();
+            let nickname = bot_nickname(BotDifficulty::Hunter, 0, &names);
+            assert!(!names.contains(&nickname.as_str()), "{} is taken", nickname);
+            taken.push(nickname);
+        }
+        assert_eq!(taken[1], "Wolf [H]");
+        assert_eq!(taken.last().unwrap(), "Falcon 3 [H]");
+
+        assert_eq!(bot_nickname(BotDifficulty::Turret, 1, &[]), "Sentinel [T]");
+        assert_eq!(
+            bot_nickname(BotDifficulty::Turret, 1, &[]),
+            bot_nickname(BotDifficulty::Turret, 1 + TURRET_NAMES.len() as u64, &[])
+        );
+    }
+}
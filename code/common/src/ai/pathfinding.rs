@@ -57,6 +57,32 @@ impl PartialOrd for AStarNode {
     }
 }
 
+/// Cells with a teleporter pad in `pos`, with where to step onto the pad and the cell of
+/// the pad it leads to.
+fn teleports(pos: GridPos, map: &MapDefinition) -> impl Iterator<Item = (Vec2, GridPos)> + '_ {
+    map.teleporters.iter().flat_map(move |t| {
+        [(t.a, t.b), (t.b, t.a)]
+            .into_iter()
+            .filter(move |(entry, _)| GridPos::from_vec2(*entry) == pos)
+            .map(|(entry, exit)| (entry, GridPos::from_vec2(exit)))
+    })
+}
+
+/// Whether stepping between neighbouring cells goes against a one-way wall in either of
+/// them.
+fn is_step_one_way_against(from: GridPos, to: GridPos, map: &MapDefinition) -> bool {
+    let step = Vec2::new((to.x - from.x) as f32, (to.y - from.y) as f32);
+    map.one_way_walls.iter().any(|one_way| {
+        step.dot(one_way.direction) < 0.0
+            && [from, to].into_iter().any(|cell| {
+                let min = Vec2::new(cell.x as f32 * GRID_SIZE, cell.y as f32 * GRID_SIZE);
+                let max = min + Vec2::splat(GRID_SIZE);
+                let wall = &one_way.wall;
+                min.x < wall.max.x && max.x > wall.min.x && min.y < wall.max.y && max.y > wall.min.y
+            })
+    })
+}
+
 fn is_cell_blocked(pos: GridPos, map: &MapDefinition) -> bool {
     let min = Vec2::new(pos.x as f32 * GRID_SIZE, pos.y as f32 * GRID_SIZE);
     let max = min + Vec2::splat(GRID_SIZE);
@@ -67,7 +93,7 @@ fn is_cell_blocked(pos: GridPos, map: &MapDefinition) -> bool {
     }
 
     // Check walls (AABB intersection)
-    for wall in &map.walls {
+    for wall in map.solid_walls() {
         if min.x < wall.max.x && max.x > wall.min.x && min.y < wall.max.y && max.y > wall.min.y {
             return true;
         }
@@ -124,7 +150,11 @@ pub fn find_path_a_star(start: Vec2, end: Vec2, map: &MapDefinition) -> Vec<Vec2
             },
         ];
 
-        for next in neighbors {
+        let steps = neighbors
+            .into_iter()
+            .filter(|next| !is_step_one_way_against(current.pos, *next, map));
+        let jumps = teleports(current.pos, map).map(|(_, exit)| exit);
+        for next in steps.chain(jumps) {
             if is_cell_blocked(next, map) {
                 continue;
             }
@@ -148,8 +178,13 @@ pub fn find_path_a_star(start: Vec2, end: Vec2, map: &MapDefinition) -> Vec<Vec2
         let mut path = Vec::new();
         let mut current = end_grid;
         while current != start_grid {
+            let previous = came_from[&current];
             path.push(current.to_vec2());
-            current = came_from[&current];
+            // Through a teleporter: drive onto its pad rather than to the cell's centre
+            if let Some((entry, _)) = teleports(previous, map).find(|(_, exit)| *exit == current) {
+                path.push(entry);
+            }
+            current = previous;
         }
         path.reverse();
         return path;
@@ -158,3 +193,56 @@ pub fn find_path_a_star(start: Vec2, end: Vec2, map: &MapDefinition) -> Vec<Vec2
     // If no path found, return direct line as fallback
     vec![end]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::units::DEFAULT_UNITS_PER_METER;
+    use crate::net::protocol::{OneWayWall, RectWall, Teleporter};
+
+    fn walled_map() -> MapDefinition {
+        // A wall splitting the map in a left and a right half
+        MapDefinition {
+            width: 800.0,
+            height: 400.0,
+            units_per_meter: DEFAULT_UNITS_PER_METER,
+            walls: vec![RectWall {
+                min: Vec2::new(380.0, 0.0),
+                max: Vec2::new(420.0, 360.0),
+            }],
+            spawn_points: Vec::new(),
+            arenas: Vec::new(),
+            teleporters: Vec::new(),
+            one_way_walls: Vec::new(),
+            moving_walls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_paths_take_teleporters_and_respect_one_way_walls() {
+        let (start, end) = (Vec2::new(100.0, 100.0), Vec2::new(700.0, 100.0));
+        let mut map = walled_map();
+        let around = find_path_a_star(start, end, &map);
+        assert!(around.len() > 15);
+
+        map.teleporters.push(Teleporter {
+            a: Vec2::new(150.0, 100.0),
+            b: Vec2::new(650.0, 100.0),
+        });
+        let through = find_path_a_star(start, end, &map);
+        assert!(through.len() < 8);
+        assert!(through.contains(&Vec2::new(150.0, 100.0)));
+
+        // Closing the gap below the wall to the right-going way leaves no path
+        map.teleporters.clear();
+        map.one_way_walls.push(OneWayWall {
+            wall: RectWall {
+                min: Vec2::new(380.0, 360.0),
+                max: Vec2::new(420.0, 400.0),
+            },
+            direction: Vec2::NEG_X,
+        });
+        assert_eq!(find_path_a_star(start, end, &map), vec![end]);
+        assert!(find_path_a_star(end, start, &map).len() > 15);
+    }
+}
@@ -0,0 +1,174 @@
+//! Crash reports. A panic hook writes a diagnostic bundle to disk: the panic message and
+//! where it happened, a backtrace, the last lines logged, the version and a summary of
+//! what the program was doing, so a crash a player reports can be looked into.
+//!
+//! Binaries feed the bundle as they run with [`record_log`] and [`set_state`], and install
+//! the hook with [`install_panic_hook`].
+
+use crate::net::protocol::API_VERSION;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Log lines kept for the next report
+const RECENT_LINES: usize = 200;
+/// Holds the path of the last report until [`take_unseen_report`] picks it up.
+const UNSEEN_MARKER: &str = "unseen";
+
+static RECENT_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static STATE: Mutex<String> = Mutex::new(String::new());
+
+/// Keeps `line` for a crash report, dropping the oldest line past [`RECENT_LINES`].
+pub fn record_log(line: impl Into<String>) {
+    let Ok(mut log) = RECENT_LOG.lock() else {
+        return;
+    };
+    if log.len() == RECENT_LINES {
+        log.pop_front();
+    }
+    log.push_back(line.into());
+}
+
+/// Replaces the summary of what the program is doing, e.g. the open view or the games
+/// being played.
+pub fn set_state(summary: impl Into<String>) {
+    if let Ok(mut state) = STATE.lock() {
+        *state = summary.into();
+    }
+}
+
+/// Everything known about a crash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    /// Name of the binary
+    pub app: String,
+    pub version: String,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub message: String,
+    /// `file:line:column` of the panic, when known
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub state: String,
+    /// Oldest first
+    pub recent_log: Vec<String>,
+}
+
+impl CrashReport {
+    /// The bundle's text.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{} {} crashed", self.app, self.version);
+        let _ = writeln!(text, "Protocol: {}", API_VERSION);
+        let _ = writeln!(text, "Time: {} (Unix seconds)", self.time);
+        let _ = writeln!(text, "Panic: {}", self.message);
+        if let Some(location) = &self.location {
+            let _ = writeln!(text, "At: {}", location);
+        }
+        let _ = writeln!(text, "State: {}", self.state);
+        let _ = writeln!(text, "\nRecent log:");
+        for line in &self.recent_log {
+            let _ = writeln!(text, "{}", line);
+        }
+        let _ = writeln!(text, "\nBacktrace:\n{}", self.backtrace);
+        text
+    }
+
+    /// Writes the bundle into `dir` and remembers it as unseen. Returns its path.
+    pub fn save(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}-{}.txt", self.app, self.time));
+        std::fs::write(&path, self.render())?;
+        std::fs::write(dir.join(UNSEEN_MARKER), path.to_string_lossy().as_bytes())?;
+        Ok(path)
+    }
+}
+
+/// The report saved into `dir` since this was last called, if any.
+pub fn take_unseen_report(dir: &Path) -> Option<PathBuf> {
+    let marker = dir.join(UNSEEN_MARKER);
+    let path = std::fs::read_to_string(&marker).ok()?;
+    let _ = std::fs::remove_file(&marker);
+    Some(PathBuf::from(path))
+}
+
+/// Saves a report into `dir` whenever the program panics, then carries on with the
+/// panic as before. `app` and `version` name the binary.
+pub fn install_panic_hook(app: &'static str, version: &'static str, dir: PathBuf) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let report = CrashReport {
+            app: app.to_string(),
+            version: version.to_string(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            message,
+            location: info.location().map(|l| l.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+            // `try_lock`, in case the panic happened while one was held
+            state: STATE.try_lock().map(|s| s.clone()).unwrap_or_default(),
+            recent_log: RECENT_LOG
+                .try_lock()
+                .map(|log| log.iter().cloned().collect())
+                .unwrap_or_default(),
+        };
+        match report.save(&dir) {
+            Ok(path) => eprintln!("Crash report saved at {}", path.display()),
+            Err(e) => eprintln!("Failed to save a crash report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_are_saved_and_seen_once() {
+        let dir = std::env::temp_dir().join(format!("crash_test_{}", std::process::id()));
+        let report = CrashReport {
+            app: "client".into(),
+            version: "0.1.0".into(),
+            time: 1_700_000_000,
+            message: "index out of bounds".into(),
+            location: Some("src/app/game.rs:10:5".into()),
+            backtrace: "0: main".into(),
+            state: "view GameView".into(),
+            recent_log: vec!["[NET] Connected.".into()],
+        };
+        let path = report.save(&dir).unwrap();
+        assert_eq!(path, dir.join("client-1700000000.txt"));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("client 0.1.0 crashed\n"));
+        for part in ["Panic: index out of bounds", "At: src/app/game.rs:10:5"] {
+            assert!(text.contains(part), "{}", part);
+        }
+        assert!(text.contains("State: view GameView\n\nRecent log:\n[NET] Connected.\n"));
+
+        assert_eq!(take_unseen_report(&dir), Some(path));
+        assert_eq!(take_unseen_report(&dir), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_only_recent_lines_are_kept() {
+        for i in 0..RECENT_LINES + 5 {
+            record_log(format!("line {}", i));
+        }
+        let log = RECENT_LOG.lock().unwrap();
+        assert_eq!(log.len(), RECENT_LINES);
+        assert_eq!(log.front().unwrap(), "line 5");
+    }
+}
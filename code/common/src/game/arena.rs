@@ -0,0 +1,279 @@
+//! Multi-arena maps: copies of a map side by side, walled off from each other, with the
+//! final arena centred below them. Every arena is a separate battlefield.
+
+use crate::net::protocol::{
+    Arena, GameMode, MapDefinition, MapName, MovingWall, OneWayWall, Projectile, RectWall, Tank,
+    Team, Teleporter, WallSchedule,
+};
+use glam::Vec2;
+use std::borrow::Cow;
+
+/// Arenas whose winners meet in the final of a multi-arena game.
+pub const QUALIFYING_ARENAS: usize = 2;
+/// Thickness of the walls between arenas.
+const ARENA_GAP: f32 = 60.0;
+
+impl Arena {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+}
+
+impl MapDefinition {
+    /// The map as played in `mode`.
+    pub fn for_mode(name: MapName, mode: GameMode) -> Self {
+        let map = Self::load_name(name);
+        match mode {
+            GameMode::TeamDeathmatch | GameMode::FreeForAll | GameMode::Coop => map,
+            GameMode::MultiArena => map.multi_arena(QUALIFYING_ARENAS),
+        }
+    }
+
+    /// Lays out `qualifiers` copies of this map in a row, plus the final arena centred
+    /// below them, which is the last arena. Everything between arenas is wall.
+    pub fn multi_arena(&self, qualifiers: usize) -> Self {
+        let tile = Vec2::new(self.width, self.height);
+        let width = qualifiers.max(1) as f32 * (tile.x + ARENA_GAP) - ARENA_GAP;
+        let height = 2.0 * tile.y + ARENA_GAP;
+        let final_origin = Vec2::new((width - tile.x) / 2.0, tile.y + ARENA_GAP);
+        let origins: Vec<Vec2> = (0..qualifiers)
+            .map(|i| Vec2::new(i as f32 * (tile.x + ARENA_GAP), 0.0))
+            .chain([final_origin])
+            .collect();
+
+        let mut walls = Vec::new();
+        let mut spawn_points = Vec::new();
+        let mut teleporters = Vec::new();
+        let mut one_way_walls = Vec::new();
+        let mut moving_walls = Vec::new();
+        for &origin in &origins {
+            walls.extend(self.walls.iter().map(|wall| RectWall {
+                min: wall.min + origin,
+                max: wall.max + origin,
+            }));
+            spawn_points.extend(
+                self.spawn_points
+                    .iter()
+                    .map(|&(team, pos)| (team, pos + origin)),
+            );
+            teleporters.extend(self.teleporters.iter().map(|t| Teleporter {
+                a: t.a + origin,
+                b: t.b + origin,
+            }));
+            one_way_walls.extend(self.one_way_walls.iter().map(|w| OneWayWall {
+                wall: RectWall {
+                    min: w.wall.min + origin,
+                    max: w.wall.max + origin,
+                },
+                direction: w.direction,
+            }));
+            moving_walls.extend(self.moving_walls.iter().map(|w| {
+                let schedule = match w.schedule {
+                    WallSchedule::Capture {
+                        point,
+                        radius,
+                        hold,
+                    } => WallSchedule::Capture {
+                        point: point + origin,
+                        radius,
+                        hold,
+                    },
+                    timer => timer,
+                };
+                MovingWall {
+                    closed: RectWall {
+                        min: w.closed.min + origin,
+                        max: w.closed.max + origin,
+                    },
+                    schedule,
+                    ..w.clone()
+                }
+            }));
+        }
+
+        let mut wall = |min: Vec2, max: Vec2| walls.push(RectWall { min, max });
+        for i in 1..qualifiers {
+            let x = i as f32 * (tile.x + ARENA_GAP);
+            wall(Vec2::new(x - ARENA_GAP, 0.0), Vec2::new(x, tile.y));
+        }
+        wall(Vec2::new(0.0, tile.y), Vec2::new(width, tile.y + ARENA_GAP));
+        if final_origin.x > 0.0 {
+            wall(
+                Vec2::new(0.0, final_origin.y),
+                Vec2::new(final_origin.x, height),
+            );
+            wall(
+                Vec2::new(final_origin.x + tile.x, final_origin.y),
+                Vec2::new(width, height),
+            );
+        }
+
+        Self {
+            width,
+            height,
+            units_per_meter: self.units_per_meter,
+            walls,
+            spawn_points,
+            arenas: origins
+                .into_iter()
+                .map(|min| Arena {
+                    min,
+                    max: min + tile,
+                })
+                .collect(),
+            teleporters,
+            one_way_walls,
+            moving_walls,
+        }
+    }
+
+    /// Number of separate battlefields; a regular map is a single one.
+    pub fn arena_count(&self) -> usize {
+        self.arenas.len().max(1)
+    }
+
+    /// The arena `pos` lies in, `None` inside the walls between arenas. The whole of a
+    /// regular map is arena 0.
+    pub fn arena_index(&self, pos: Vec2) -> Option<usize> {
+        if self.arenas.is_empty() {
+            return Some(0);
+        }
+        self.arenas.iter().position(|arena| arena.contains(pos))
+    }
+
+    /// Bounds of arena `index`; the whole map on a regular map.
+    pub fn arena(&self, index: usize) -> Arena {
+        self.arenas.get(index).copied().unwrap_or(Arena {
+            min: Vec2::ZERO,
+            max: Vec2::new(self.width, self.height),
+        })
+    }
+
+    pub fn arena_spawn_points(&self, arena: usize, team: Team) -> impl Iterator<Item = Vec2> {
+        self.spawn_points
+            .iter()
+            .filter(move |(t, pos)| *t == team && self.arena_index(*pos) == Some(arena))
+            .map(|(_, pos)| *pos)
+    }
+
+    /// What a tank at `pos` can see and fight: the tanks and projectiles of its arena.
+    /// Borrows everything on a regular map.
+    #[allow(clippy::ptr_arg)]
+    pub fn arena_view<'a>(
+        &self,
+        pos: Vec2,
+        tanks: &'a Vec<Tank>,
+        projectiles: &'a Vec<Projectile>,
+    ) -> (Cow<'a, Vec<Tank>>, Cow<'a, Vec<Projectile>>) {
+        if self.arenas.is_empty() {
+            return (Cow::Borrowed(tanks), Cow::Borrowed(projectiles));
+        }
+        let arena = self.arena_index(pos);
+        let tanks = tanks
+            .iter()
+            .filter(|t| self.arena_index(t.position) == arena)
+            .cloned()
+            .collect();
+        let projectiles = projectiles
+            .iter()
+            .filter(|p| self.arena_index(p.position) == arena)
+            .cloned()
+            .collect();
+        (Cow::Owned(tanks), Cow::Owned(projectiles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::engine::{GameEngine, Squad};
+    use crate::game::player::PlayerInfo;
+    use crate::game::{check_arena_winner, is_position_safe};
+
+    #[test]
+    fn test_multi_arena_layout() {
+        let base = MapDefinition::load_name(MapName::Basic);
+        let map = MapDefinition::for_mode(MapName::Basic, GameMode::MultiArena);
+
+        assert_eq!(map.arena_count(), QUALIFYING_ARENAS + 1);
+        for (i, arena) in map.arenas.iter().enumerate() {
+            assert_eq!(arena.size(), Vec2::new(base.width, base.height));
+            assert!(arena.max.x <= map.width && arena.max.y <= map.height);
+            assert_eq!(map.arena_index(arena.center()), Some(i));
+            for team in [Team::Blue, Team::Red] {
+                assert_eq!(map.arena_spawn_points(i, team).count(), 4);
+            }
+        }
+
+        // Between the arenas is solid wall
+        let gap = Vec2::new(base.width + ARENA_GAP / 2.0, base.height / 2.0);
+        assert_eq!(map.arena_index(gap), None);
+        assert!(!is_position_safe(gap, 1.0, &map));
+        let below_first = Vec2::new(50.0, base.height + ARENA_GAP + 50.0);
+        assert!(!is_position_safe(below_first, 1.0, &map));
+    }
+
+    #[test]
+    fn test_arenas_are_separate() {
+        let map = MapDefinition::for_mode(MapName::Tiga, GameMode::MultiArena);
+        let tank = |id, team, arena: usize| {
+            let info = PlayerInfo::new(id, format!("P{}", id), team);
+            Tank::new(info, map.arena(arena).center())
+        };
+        let tanks = vec![
+            tank(0, Team::Blue, 0),
+            tank(1, Team::Red, 0),
+            tank(2, Team::Blue, 1),
+        ];
+
+        let projectiles = Vec::new();
+        let (seen, _) = map.arena_view(tanks[0].position, &tanks, &projectiles);
+        assert_eq!(seen.len(), 2);
+        assert_eq!(check_arena_winner(&tanks, &map, 0), None);
+        assert_eq!(check_arena_winner(&tanks, &map, 1), Some(Team::Blue));
+        assert_eq!(check_arena_winner(&tanks, &map, 2), None);
+
+        let regular = MapDefinition::load_name(MapName::Tiga);
+        let (seen, _) = regular.arena_view(Vec2::ZERO, &tanks, &projectiles);
+        assert!(matches!(seen, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_squads_spawn_in_their_arenas() {
+        let map = MapDefinition::for_mode(MapName::Basic, GameMode::MultiArena);
+        let mut engine = GameEngine::new(map);
+        let first = engine.add_player("first".to_string()).unwrap();
+        let second = engine.add_player("second".to_string()).unwrap();
+
+        let mut squads = [Squad::new(2, Team::Blue), Squad::new(2, Team::Red)];
+        squads[0].members.push(second);
+        // Someone who has left the game meanwhile
+        squads[1].members.extend([first, 999]);
+        engine.prepare_arena_round(&mut squads, 4);
+
+        assert_eq!(engine.tanks.len(), 8);
+        assert_eq!(squads[1].members[0], first);
+        for squad in &squads {
+            assert_eq!(squad.members.len(), 4);
+            for id in &squad.members {
+                let tank = engine
+                    .tanks
+                    .iter()
+                    .find(|t| t.player_info.id == *id)
+                    .unwrap();
+                assert_eq!(tank.player_info.team, squad.team);
+                assert_eq!(engine.map.arena_index(tank.position), Some(2));
+            }
+        }
+        // Humans keep the team of their squad, e.g. for bots' targeting
+        assert_eq!(engine.humans[0].team, Team::Red);
+    }
+}
@@ -0,0 +1,227 @@
+//! The numbers the game is balanced by: fire rate, damage, health, sizes and speeds. They
+//! are built into the binaries from `assets/balance.ron`; a file at [`OVERRIDE_PATH`]
+//! replaces them, so balance can be tweaked without recompiling the server, the client
+//! and the trainer. All three have to read the same numbers, or the client's prediction
+//! and the trained bots drift from what the server simulates. Training views reload it
+//! between matches with [`reload_balance`]. Servers refuse clients, and replays refuse to
+//! play back, when [`BalanceConfig::content_hash`] differs.
+
+use super::map::fnv1a;
+use super::units::{BodyDimensions, default_units};
+use bincode::Encode;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Checked on first use and on every [`reload_balance`].
+pub const OVERRIDE_PATH: &str = "assets/balance.ron";
+
+static EMBEDDED: &str = include_str!("../../../../assets/balance.ron");
+
+static BALANCE: LazyLock<RwLock<Arc<BalanceConfig>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(load())));
+
+/// Game balance. Times are in seconds; sizes and speeds in [`BodyDimensions`] are in
+/// meters, see [`units`](super::units).
+#[derive(Debug, Clone, PartialEq, Deserialize, Encode)]
+pub struct BalanceConfig {
+    /// Seconds between shots of a tank without upgrades
+    pub fire_interval: f32,
+    pub max_health: f32,
+    /// How fast a tank turns towards where it aims, in radians per second
+    pub turn_rate: f32,
+    pub projectile_damage: f32,
+    /// Seconds between grenades
+    pub grenade_cooldown: f32,
+    /// Seconds after which a grenade that hit nothing goes off
+    pub grenade_fuse: f32,
+    /// Damage at the centre of the blast; it falls off linearly to nothing at its edge
+    pub blast_damage: f32,
+    /// Share of a hit's damage a tank's front armor stops, from 0 up to (not
+    /// including) 1
+    pub front_armor: f32,
+    /// How far off a tank's facing, in radians, a shot still comes in at its front
+    pub front_arc: f32,
+    pub bodies: BodyDimensions,
+}
+
+impl BalanceConfig {
+    /// Parses and validates a balance table.
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let balance: Self = ron::from_str(text).map_err(|e| e.to_string())?;
+        balance.validate()?;
+        Ok(balance)
+    }
+
+    /// Stable FNV-1a hash of the encoded table, to tell whether two builds play by the
+    /// same numbers.
+    pub fn content_hash(&self) -> u64 {
+        let bytes = bincode::encode_to_vec(self, bincode::config::standard())
+            .expect("balance tables are always encodable");
+        fnv1a(&bytes)
+    }
+
+    /// The table built into the binaries, ignoring any override.
+    pub fn embedded() -> Self {
+        Self::from_ron(EMBEDDED).expect("the embedded balance table is valid")
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let b = &self.bodies;
+        let values = [
+            ("fire_interval", self.fire_interval),
+            ("max_health", self.max_health),
+            ("turn_rate", self.turn_rate),
+            ("projectile_damage", self.projectile_damage),
+            ("grenade_cooldown", self.grenade_cooldown),
+            ("grenade_fuse", self.grenade_fuse),
+            ("blast_damage", self.blast_damage),
+            ("front_arc", self.front_arc),
+            ("tank_radius", b.tank_radius),
+            ("tank_speed", b.tank_speed),
+            ("projectile_radius", b.projectile_radius),
+            ("projectile_speed", b.projectile_speed),
+            ("grenade_radius", b.grenade_radius),
+            ("grenade_speed", b.grenade_speed),
+            ("blast_radius", b.blast_radius),
+        ];
+        for (name, value) in values {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(format!("{} must be positive, got {}", name, value));
+            }
+        }
+        if !(0.0..1.0).contains(&self.front_armor) {
+            return Err(format!(
+                "front_armor must be at least 0 and below 1, got {}",
+                self.front_armor
+            ));
+        }
+        Ok(())
+    }
+
+    // Sizes and speeds at the default scale; the engine scales them to each map.
+
+    pub fn tank_speed(&self) -> f32 {
+        default_units(self.bodies.tank_speed)
+    }
+
+    pub fn tank_radius(&self) -> f32 {
+        default_units(self.bodies.tank_radius)
+    }
+
+    pub fn projectile_speed(&self) -> f32 {
+        default_units(self.bodies.projectile_speed)
+    }
+
+    pub fn projectile_radius(&self) -> f32 {
+        default_units(self.bodies.projectile_radius)
+    }
+
+    pub fn grenade_speed(&self) -> f32 {
+        default_units(self.bodies.grenade_speed)
+    }
+
+    pub fn grenade_radius(&self) -> f32 {
+        default_units(self.bodies.grenade_radius)
+    }
+
+    /// Reach of a grenade's blast, measured to the edge of a tank
+    pub fn blast_radius(&self) -> f32 {
+        default_units(self.bodies.blast_radius)
+    }
+}
+
+/// The balance the game is played with: [`OVERRIDE_PATH`] if it exists and is valid,
+/// otherwise the embedded table. Tanks keep the stats they were created with when it
+/// is reloaded, so reload between matches only.
+pub fn balance() -> Arc<BalanceConfig> {
+    BALANCE.read().unwrap().clone()
+}
+
+/// Reads [`OVERRIDE_PATH`] again, falling back to the embedded table once the file is
+/// gone. Returns whether the balance changed; an invalid file leaves it as it was.
+pub fn reload_balance() -> Result<bool, String> {
+    let reloaded = match read_override() {
+        Some(loaded) => loaded?,
+        None => BalanceConfig::embedded(),
+    };
+    let mut current = BALANCE.write().unwrap();
+    if **current == reloaded {
+        return Ok(false);
+    }
+    log::info!("Reloaded the balance table");
+    *current = Arc::new(reloaded);
+    Ok(true)
+}
+
+fn load() -> BalanceConfig {
+    match read_override() {
+        Some(Ok(balance)) => {
+            log::info!("Using the balance table at {}", OVERRIDE_PATH);
+            return balance;
+        }
+        Some(Err(e)) => log::warn!("{}, using the built-in balance", e),
+        None => {}
+    }
+    BalanceConfig::embedded()
+}
+
+/// The table at [`OVERRIDE_PATH`], if there is a file.
+fn read_override() -> Option<Result<BalanceConfig, String>> {
+    let path = Path::new(OVERRIDE_PATH);
+    if !path.exists() {
+        return None;
+    }
+    let loaded = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| BalanceConfig::from_ron(&text));
+    Some(loaded.map_err(|e| format!("Failed to load {}: {}", OVERRIDE_PATH, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_table_parses() {
+        let balance = BalanceConfig::embedded();
+        assert_eq!(balance.fire_interval, 0.2);
+        assert_eq!(balance.max_health, 100.0);
+        assert_eq!(balance.tank_speed(), 200.0);
+        assert_eq!(balance.projectile_speed(), 500.0);
+    }
+
+    #[test]
+    fn test_invalid_tables_are_rejected() {
+        let negative = EMBEDDED.replace("fire_interval: 0.2", "fire_interval: -0.2");
+        assert_eq!(
+            BalanceConfig::from_ron(&negative),
+            Err("fire_interval must be positive, got -0.2".into())
+        );
+        let missing = EMBEDDED.replace("blast_damage: 40.0,", "");
+        assert!(BalanceConfig::from_ron(&missing).is_err());
+        let invulnerable = EMBEDDED.replace("front_armor: 0.3", "front_armor: 1.0");
+        assert!(BalanceConfig::from_ron(&invulnerable).is_err());
+    }
+
+    #[test]
+    fn test_content_hash_tells_tables_apart() {
+        let balance = BalanceConfig::embedded();
+        assert_eq!(
+            balance.content_hash(),
+            BalanceConfig::embedded().content_hash()
+        );
+        let tuned = BalanceConfig {
+            blast_damage: balance.blast_damage + 1.0,
+            ..balance.clone()
+        };
+        assert_ne!(tuned.content_hash(), balance.content_hash());
+    }
+
+    #[test]
+    fn test_reloading_an_unchanged_table() {
+        // Tests run from the crate directory, where there is no override
+        assert_eq!(reload_balance(), Ok(false));
+        assert_eq!(*balance(), BalanceConfig::embedded());
+    }
+}
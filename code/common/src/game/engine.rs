@@ -1,14 +1,32 @@
+use super::explosion::{explode, handle_grenade, trigger_grenades};
 use super::{
-    DamageEvent, apply_player_physics, check_round_winner, handle_shooting, resolve_combat,
-    resolve_player_collisions, update_projectiles,
+    CombatRules, DamageEvent, RoundOutcome, apply_player_physics, cap_projectiles,
+    check_round_winner, handle_shooting, resolve_combat, resolve_player_collisions,
+    update_projectiles,
 };
-use crate::ai::{BotAgent, BotDifficulty};
-use crate::game::player::PlayerInfo;
+use crate::ai::blackboard::TeamBlackboard;
+use crate::ai::names::bot_nickname;
+use crate::ai::{BotAgent, BotDifficulty, SavedBot, bot_seed};
+use crate::game::player::{PlayerInfo, TankColor};
 use crate::net::protocol::{
-    EngineSnapshot, InputPayload, KillEvent, MapDefinition, PlayerId, Projectile, Tank, Team,
+    EngineSnapshot, Explosion, InputPayload, KillEvent, Loadout, MapDefinition, PlayerId,
+    Projectile, Tank, Team,
 };
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
 use glam::Vec2;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use thiserror::Error;
+
+pub const SAVE_EXTENSION: &str = "nbsave";
+
+/// Bumped whenever [`SavedEngine`] changes, so older saves are refused rather than misread.
+pub const SAVE_VERSION: u16 = 6;
+
+const SAVE_MAGIC: [u8; 4] = *b"NBSV";
 
 #[derive(Clone)]
 pub struct GameEngine {
@@ -17,14 +35,116 @@ pub struct GameEngine {
     pub map: MapDefinition,
     pub humans: Vec<PlayerInfo>,
     pub bots: Vec<BotAgent>,
+    pub bot_fill: BotFill,
+    /// Every bot's seed is derived from this and its id, see [`bot_seed`]
+    pub bot_seed: u64,
+    pub combat: CombatRules,
+    /// Teammates' pings for the bots, forgotten every round
+    pub blackboard: TeamBlackboard,
+    pub next_player_id: PlayerId,
+    pub projectile_id_counter: u64,
+    /// Upgrades players bought, applied to their tanks as a round starts
+    pub loadouts: HashMap<PlayerId, Loadout>,
+}
+
+/// How [`GameEngine::prepare_new_round`] fills free spawn points with bots.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct BotFill {
+    /// Most tanks a team fields, counting humans; `None` fills every spawn point. Teams
+    /// bigger than the map's spawn points spawn around them.
+    pub team_size: Option<usize>,
+    /// Most bots in the game at once, across the teams; `None` fills every free place
+    pub max_bots: Option<usize>,
+    pub blue: BotDifficulty,
+    pub red: BotDifficulty,
+}
+
+impl Default for BotFill {
+    fn default() -> Self {
+        Self {
+            team_size: None,
+            max_bots: None,
+            blue: BotDifficulty::Hunter,
+            red: BotDifficulty::Hunter,
+        }
+    }
+}
+
+impl BotFill {
+    /// How many more bots may join the `bots` already in the game.
+    fn room(&self, bots: usize) -> usize {
+        self.max_bots
+            .map_or(usize::MAX, |max| max.saturating_sub(bots))
+    }
+
+    /// Free-for-all bots play at Red's difficulty.
+    pub fn difficulty(&self, team: Team) -> BotDifficulty {
+        match team {
+            Team::Blue => self.blue,
+            Team::Red | Team::Ffa(_) => self.red,
+        }
+    }
+}
+
+/// Players fighting for one team in one arena of a multi-arena map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Squad {
+    pub arena: usize,
+    pub team: Team,
+    pub members: Vec<PlayerId>,
+}
+
+impl Squad {
+    pub fn new(arena: usize, team: Team) -> Self {
+        Self {
+            arena,
+            team,
+            members: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to encode saved game: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("failed to decode saved game: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("not a saved game")]
+    BadMagic,
+    #[error("game was saved with version {found}, expected {expected}")]
+    VersionMismatch { found: u16, expected: u16 },
+}
+
+/// A match stopped midway, to be carried on with by [`GameEngine::from_saved`]. Holds all
+/// of the engine's state, down to the bots' random number generators.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SavedEngine {
+    pub tanks: Vec<Tank>,
+    pub projectiles: Vec<Projectile>,
+    pub map: MapDefinition,
+    pub humans: Vec<PlayerInfo>,
+    pub bots: Vec<SavedBot>,
+    pub bot_fill: BotFill,
+    pub bot_seed: u64,
+    pub combat: CombatRules,
+    pub blackboard: TeamBlackboard,
     pub next_player_id: PlayerId,
     pub projectile_id_counter: u64,
+    pub loadouts: HashMap<PlayerId, Loadout>,
 }
 
 pub struct GameTickResult {
+    /// Tanks that fired this tick
+    pub shots: Vec<PlayerId>,
     pub kills: Vec<KillEvent>,
     pub damage: Vec<DamageEvent>,
-    pub winner: Option<Team>,
+    pub explosions: Vec<Explosion>,
+    pub outcome: RoundOutcome,
+    /// Projectiles removed this tick to stay within [`MAX_PROJECTILES`](super::MAX_PROJECTILES)
+    pub culled_projectiles: usize,
 }
 
 impl GameEngine {
@@ -35,8 +155,13 @@ impl GameEngine {
             map,
             humans: Vec::new(),
             bots: Vec::new(),
+            bot_fill: BotFill::default(),
+            bot_seed: 0,
+            combat: CombatRules::default(),
+            blackboard: TeamBlackboard::default(),
             next_player_id: 0,
             projectile_id_counter: 0,
+            loadouts: HashMap::new(),
         }
     }
 
@@ -56,12 +181,14 @@ impl GameEngine {
         EngineSnapshot {
             tanks: self.tanks.clone(),
             projectiles: self.projectiles.clone(),
+            moving_walls: self.map.moving_wall_states(),
         }
     }
 
     pub fn apply_snapshot(&mut self, snapshot: EngineSnapshot) {
         self.tanks = snapshot.tanks;
         self.projectiles = snapshot.projectiles;
+        self.map.set_moving_wall_states(&snapshot.moving_walls);
 
         self.projectile_id_counter = self
             .projectiles
@@ -72,6 +199,79 @@ impl GameEngine {
             .unwrap_or(0);
     }
 
+    pub fn to_saved(&self) -> SavedEngine {
+        SavedEngine {
+            tanks: self.tanks.clone(),
+            projectiles: self.projectiles.clone(),
+            map: self.map.clone(),
+            humans: self.humans.clone(),
+            bots: self.bots.iter().map(BotAgent::save).collect(),
+            bot_fill: self.bot_fill.clone(),
+            bot_seed: self.bot_seed,
+            combat: self.combat.clone(),
+            blackboard: self.blackboard.clone(),
+            next_player_id: self.next_player_id,
+            projectile_id_counter: self.projectile_id_counter,
+            loadouts: self.loadouts.clone(),
+        }
+    }
+
+    pub fn from_saved(saved: SavedEngine) -> Self {
+        Self {
+            tanks: saved.tanks,
+            projectiles: saved.projectiles,
+            map: saved.map,
+            humans: saved.humans,
+            bots: saved.bots.into_iter().map(BotAgent::restore).collect(),
+            bot_fill: saved.bot_fill,
+            bot_seed: saved.bot_seed,
+            combat: saved.combat,
+            blackboard: saved.blackboard,
+            next_player_id: saved.next_player_id,
+            projectile_id_counter: saved.projectile_id_counter,
+            loadouts: saved.loadouts,
+        }
+    }
+
+    /// Writes the match as it stands. Callers may write their own state after it, and read
+    /// it back after [`Self::read_saved`].
+    pub fn write_saved<W: Write>(&self, writer: &mut W) -> Result<(), SaveError> {
+        writer.write_all(&SAVE_MAGIC)?;
+        let config = bincode::config::standard();
+        bincode::encode_into_std_write(SAVE_VERSION, writer, config)?;
+        bincode::encode_into_std_write(self.to_saved(), writer, config)?;
+        Ok(())
+    }
+
+    pub fn read_saved<R: Read>(reader: &mut R) -> Result<Self, SaveError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SAVE_MAGIC {
+            return Err(SaveError::BadMagic);
+        }
+        let config = bincode::config::standard();
+        let version: u16 = bincode::decode_from_std_read(reader, config)?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::VersionMismatch {
+                found: version,
+                expected: SAVE_VERSION,
+            });
+        }
+        let saved = bincode::decode_from_std_read(reader, config)?;
+        Ok(Self::from_saved(saved))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SaveError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_saved(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SaveError> {
+        Self::read_saved(&mut BufReader::new(File::open(path)?))
+    }
+
     pub fn clear_projectiles(&mut self) {
         self.projectiles.clear();
     }
@@ -83,13 +283,23 @@ impl GameEngine {
     ///
     /// Returns a list of kills that happened during this tick.
     pub fn tick(&mut self, dt: f32, mut inputs: HashMap<PlayerId, InputPayload>) -> GameTickResult {
+        self.blackboard.tick(dt);
+        if self.map.update_moving_walls(&self.tanks, dt) {
+            // Routes planned around the walls' old places may be blocked or longer now
+            for bot in &mut self.bots {
+                bot.forget_path();
+            }
+        }
         self.inject_bot_inputs(&mut inputs, dt);
+        let mut shots = Vec::new();
+        let scale = self.map.scale();
 
         for tank in &mut self.tanks {
             let default_input = InputPayload {
                 move_axis: Vec2::ZERO,
                 aim_pos: tank.position,
                 shoot: false,
+                grenade: false,
             };
 
             // Get input or use default (idle)
@@ -98,27 +308,62 @@ impl GameEngine {
             apply_player_physics(tank, input, &self.map, dt);
 
             // We use the engine's internal counter to assign IDs to new projectiles.
-            if let Some(proj) = handle_shooting(tank, input, dt, self.projectile_id_counter) {
+            if let Some(proj) = handle_shooting(
+                tank,
+                input,
+                dt,
+                self.projectile_id_counter,
+                scale,
+                &self.combat,
+            ) {
                 self.projectiles.push(proj);
                 self.projectile_id_counter += 1;
+                shots.push(tank.player_info.id);
+            }
+            if let Some(grenade) =
+                handle_grenade(tank, input, dt, self.projectile_id_counter, scale)
+            {
+                self.projectiles.push(grenade);
+                self.projectile_id_counter += 1;
+                shots.push(tank.player_info.id);
             }
         }
 
+        let culled_projectiles = cap_projectiles(&mut self.projectiles);
+
         // Resolves collisions between players (prevent overlapping)
         resolve_player_collisions(&mut self.tanks);
 
         // Process Projectiles (Move & Collide with walls)
-        update_projectiles(&mut self.projectiles, &self.map, dt);
+        let mut detonated = update_projectiles(&mut self.projectiles, &self.map, dt);
+        detonated.extend(trigger_grenades(
+            &self.tanks,
+            &mut self.projectiles,
+            &self.map,
+            &self.combat,
+        ));
 
         // Resolve Combat (Projectiles hitting Players)
         // This function modifies health, removes dead players/bullets, and returns KillEvents.
-        let (kills, damage) = resolve_combat(&mut self.tanks, &mut self.projectiles);
-        let winner = check_round_winner(&self.tanks);
+        let (mut kills, mut damage) = resolve_combat(
+            &mut self.tanks,
+            &mut self.projectiles,
+            &self.map,
+            &self.combat,
+        );
+        let (blast_kills, blast_damage, explosions) =
+            explode(&detonated, &mut self.tanks, &self.map);
+        kills.extend(blast_kills);
+        damage.extend(blast_damage);
+        let outcome = check_round_winner(&self.tanks);
 
         GameTickResult {
+            shots,
             kills,
             damage,
-            winner,
+            explosions,
+            outcome,
+            culled_projectiles,
         }
     }
 
@@ -126,32 +371,45 @@ impl GameEngine {
         // Clear transient round state.
         self.tanks.clear();
         self.projectiles.clear();
+        self.blackboard.clear();
+        self.map.reset_moving_walls();
         self.projectile_id_counter = 0;
 
-        // Split spawnpoints by team; order within a team doesn't matter.
-        let mut red_spawns: Vec<Vec2> = Vec::new();
-        let mut blue_spawns: Vec<Vec2> = Vec::new();
-        for (team, pos) in &self.map.spawn_points {
-            match team {
-                Team::Red => red_spawns.push(*pos),
-                Team::Blue => blue_spawns.push(*pos),
-            }
-        }
+        // Positions for everyone who plays and the bots filling up the team; popped from
+        // the back, so the map's own spawn points go first.
+        let spawns_for = |team: Team| {
+            let members = self.humans.iter().filter(|h| h.team == team).count()
+                + self
+                    .bots
+                    .iter()
+                    .filter(|b| b.player_info.team == team)
+                    .count();
+            let points = self
+                .map
+                .spawn_points
+                .iter()
+                .filter(|(t, _)| *t == team)
+                .count();
+            let wanted = self.bot_fill.team_size.unwrap_or(points).max(members);
+            let mut spawns = self.map.team_spawns(team, wanted);
+            spawns.reverse();
+            spawns
+        };
+        let mut red_spawns = spawns_for(Team::Red);
+        let mut blue_spawns = spawns_for(Team::Blue);
 
         // Spawn humans first (team fixed on join).
         for human in &self.humans {
-            let id = human.id;
-            let team = human.team;
-            let nickname = human.nickname.clone();
-            let pos = match team {
+            let pos = match human.team {
                 Team::Red => red_spawns.pop(),
                 Team::Blue => blue_spawns.pop(),
+                Team::Ffa(_) => None,
             }
             .or_else(|| self.random_free_position())
             .unwrap_or(Vec2::new(self.map.width * 0.5, self.map.height * 0.5));
 
             self.tanks
-                .push(Tank::new(PlayerInfo::new(id, nickname, team), pos));
+                .push(Tank::new(human.clone(), pos).sized_for(&self.map));
         }
 
         // Respawn existing bots at their assigned spawn points.
@@ -160,36 +418,201 @@ impl GameEngine {
             let pos = match team {
                 Team::Red => red_spawns.pop(),
                 Team::Blue => blue_spawns.pop(),
+                Team::Ffa(_) => None,
             }
             .or_else(|| self.random_free_position())
             .unwrap_or(Vec2::new(self.map.width * 0.5, self.map.height * 0.5));
 
-            self.tanks.push(Tank::new(bot.player_info.clone(), pos));
+            self.tanks
+                .push(Tank::new(bot.player_info.clone(), pos).sized_for(&self.map));
+        }
+
+        // Fill any remaining spawnpoints with new bots, up to the team size. The teams take
+        // turns, so bots the game is short of leave both teams a bot short.
+        let mut fills =
+            [(Team::Red, red_spawns), (Team::Blue, blue_spawns)].map(|(team, spawns)| {
+                let fielded = self
+                    .tanks
+                    .iter()
+                    .filter(|t| t.player_info.team == team)
+                    .count();
+                let free = self
+                    .bot_fill
+                    .team_size
+                    .map_or(usize::MAX, |size| size.saturating_sub(fielded));
+                let spawns: Vec<Vec2> = spawns.into_iter().rev().take(free).collect();
+                (team, spawns.into_iter())
+            });
+        let mut room = self.bot_fill.room(self.bots.len());
+        while room > 0 {
+            let mut spawned = false;
+            for (team, spawns) in &mut fills {
+                if room > 0
+                    && let Some(pos) = spawns.next()
+                {
+                    self.spawn_bot(*team, pos);
+                    room -= 1;
+                    spawned = true;
+                }
+            }
+            if !spawned {
+                break;
+            }
+        }
+        self.apply_loadouts();
+    }
+
+    /// Gives the tanks of players with upgrades their bought stats.
+    fn apply_loadouts(&mut self) {
+        for tank in &mut self.tanks {
+            if let Some(loadout) = self.loadouts.get(&tank.player_info.id) {
+                tank.apply_loadout(loadout);
+            }
+        }
+    }
+
+    /// Starts a co-op round: everyone on `team` spawns, with bots filling it up to the team
+    /// size. The other side stays empty until [`Self::spawn_wave`].
+    pub fn prepare_coop_round(&mut self, team: Team) {
+        self.prepare_new_round();
+        self.bots.retain(|b| b.player_info.team == team);
+        self.tanks.retain(|t| t.player_info.team == team);
+    }
+
+    /// Spawns `count` new bots of `difficulty` on `team`, at its spawn points and around
+    /// them. Bots of the team that fell before are dropped.
+    pub fn spawn_wave(&mut self, team: Team, difficulty: BotDifficulty, count: usize) {
+        let tanks = &self.tanks;
+        self.bots.retain(|b| {
+            b.player_info.team != team || tanks.iter().any(|t| t.player_info.id == b.player_info.id)
+        });
+        for pos in self.map.team_spawns(team, count) {
+            self.spawn_bot_with(team, difficulty, pos);
+        }
+    }
+
+    /// Starts a free-for-all round: everyone fights alone, on the [`Team::Ffa`] of their
+    /// player id. Bots fill up to [`Self::player_limit`] tanks.
+    pub fn prepare_ffa_round(&mut self) {
+        self.tanks.clear();
+        self.projectiles.clear();
+        self.blackboard.clear();
+        self.map.reset_moving_walls();
+        self.projectile_id_counter = 0;
+
+        let mut players: Vec<PlayerInfo> = self.humans.clone();
+        players.extend(self.bots.iter().map(|b| b.player_info.clone()));
+        let size = self.player_limit().max(players.len());
+        let mut spawns = self.map.ffa_spawns(size);
+        spawns.reverse();
+
+        for mut info in players {
+            info.team = Team::Ffa(info.id);
+            self.set_team(info.id, info.team);
+            let pos = spawns
+                .pop()
+                .or_else(|| self.random_free_position())
+                .unwrap_or(Vec2::new(self.map.width * 0.5, self.map.height * 0.5));
+            self.tanks.push(Tank::new(info, pos).sized_for(&self.map));
         }
+        let mut room = self.bot_fill.room(self.bots.len());
+        while self.tanks.len() < size && room > 0 {
+            let Some(pos) = spawns.pop() else { break };
+            room -= 1;
+            self.spawn_bot(Team::Ffa(self.next_player_id), pos);
+        }
+        self.apply_loadouts();
+    }
 
-        // Fill any remaining spawnpoints with new bots.
-        for pos in red_spawns {
-            self.spawn_bot(Team::Red, pos);
+    /// Moves a human or bot to `team`, including their tank if they have one.
+    pub fn set_team(&mut self, id: PlayerId, team: Team) {
+        let infos = self
+            .humans
+            .iter_mut()
+            .chain(self.bots.iter_mut().map(|b| &mut b.player_info))
+            .chain(self.tanks.iter_mut().map(|t| &mut t.player_info));
+        for info in infos.filter(|info| info.id == id) {
+            info.team = team;
         }
-        for pos in blue_spawns {
-            self.spawn_bot(Team::Blue, pos);
+    }
+
+    /// Sets the trim of a human's tank.
+    pub fn set_color(&mut self, id: PlayerId, color: Option<TankColor>) {
+        let infos = self
+            .humans
+            .iter_mut()
+            .chain(self.tanks.iter_mut().map(|t| &mut t.player_info));
+        for info in infos.filter(|info| info.id == id) {
+            info.color = color;
         }
     }
 
-    fn spawn_bot(&mut self, team: Team, pos: Vec2) {
+    /// Starts a round on a multi-arena map: every squad spawns in its own arena and plays
+    /// for the squad's team. Members who left are dropped; free places up to `squad_size`
+    /// get new bots, which join the squad.
+    pub fn prepare_arena_round(&mut self, squads: &mut [Squad], squad_size: usize) {
+        self.tanks.clear();
+        self.projectiles.clear();
+        self.blackboard.clear();
+        self.map.reset_moving_walls();
+        self.projectile_id_counter = 0;
+
+        for squad in squads {
+            let mut spawns: Vec<Vec2> = self
+                .map
+                .arena_spawn_points(squad.arena, squad.team)
+                .collect();
+            let fallback = self.map.arena(squad.arena).center();
+
+            squad.members.retain(|&id| {
+                let info = if let Some(human) = self.humans.iter_mut().find(|h| h.id == id) {
+                    human
+                } else if let Some(bot) = self.bots.iter_mut().find(|b| b.player_info.id == id) {
+                    &mut bot.player_info
+                } else {
+                    return false;
+                };
+                info.team = squad.team;
+                let pos = spawns.pop().unwrap_or(fallback);
+                self.tanks
+                    .push(Tank::new(info.clone(), pos).sized_for(&self.map));
+                true
+            });
+
+            while squad.members.len() < squad_size {
+                let Some(pos) = spawns.pop() else { break };
+                squad.members.push(self.spawn_bot(squad.team, pos));
+            }
+        }
+    }
+
+    fn spawn_bot(&mut self, team: Team, pos: Vec2) -> PlayerId {
+        self.spawn_bot_with(team, self.bot_fill.difficulty(team), pos)
+    }
+
+    fn spawn_bot_with(&mut self, team: Team, difficulty: BotDifficulty, pos: Vec2) -> PlayerId {
         let bot_id = self.next_player_id;
         self.next_player_id += 1;
 
-        let nickname = format!("Bot {}", bot_id);
-        let player_info = PlayerInfo::new(bot_id, nickname.clone(), team);
-        let bot = BotAgent::new(player_info.clone(), BotDifficulty::Hunter, bot_id as u64);
+        let seed = bot_seed(self.bot_seed, bot_id);
+        let taken: Vec<&str> = self
+            .humans
+            .iter()
+            .chain(self.bots.iter().map(|b| &b.player_info))
+            .map(|info| info.nickname.as_str())
+            .collect();
+        let nickname = bot_nickname(difficulty, seed, &taken);
+        let player_info = PlayerInfo::new_bot(bot_id, nickname, team);
+        let bot = BotAgent::new(player_info.clone(), difficulty, seed);
         self.bots.push(bot);
-        self.tanks.push(Tank::new(player_info, pos));
+        self.tanks
+            .push(Tank::new(player_info, pos).sized_for(&self.map));
+        bot_id
     }
 
     /// Helper to inject a player (e.g. on spawn)
     pub fn add_player(&mut self, nickname: String) -> Result<PlayerId, String> {
-        if self.humans.len() >= self.map.spawn_points.len() {
+        if self.humans.len() >= self.player_limit() {
             return Err("Player limit reached".to_string());
         }
 
@@ -208,11 +631,18 @@ impl GameEngine {
         let player_info = PlayerInfo::new(id, nickname.clone(), team);
         self.humans.push(player_info.clone());
 
-        let tank = Tank::new(player_info, position);
+        let tank = Tank::new(player_info, position).sized_for(&self.map);
         self.tanks.push(tank);
         Ok(id)
     }
 
+    /// Humans that fit: both full teams, or every spawn point without a team size.
+    pub fn player_limit(&self) -> usize {
+        self.bot_fill
+            .team_size
+            .map_or(self.map.spawn_points.len(), |size| 2 * size)
+    }
+
     fn random_free_position(&self) -> Option<Vec2> {
         use rand::Rng;
         let mut rng = rand::rng();
@@ -242,25 +672,194 @@ impl GameEngine {
 
     pub fn remove_player(&mut self, player_id: PlayerId) {
         self.humans.retain(|h| h.id != player_id);
+        self.loadouts.remove(&player_id);
         self.bots.retain(|b| b.player_info.id != player_id);
         self.tanks.retain(|tank| tank.player_info.id != player_id);
         self.projectiles
             .retain(|proj| proj.owner_info.id != player_id);
     }
 
+    /// Hands a human's tank to a bot of `difficulty`, which plays on in their place under
+    /// their name. Returns whether `player_id` was a human in the game.
+    pub fn hand_to_bot(&mut self, player_id: PlayerId, difficulty: BotDifficulty) -> bool {
+        let Some(index) = self.humans.iter().position(|h| h.id == player_id) else {
+            return false;
+        };
+        let mut player_info = self.humans.remove(index);
+        player_info.is_ai = true;
+        player_info.color = None;
+        for tank in self
+            .tanks
+            .iter_mut()
+            .filter(|t| t.player_info.id == player_id)
+        {
+            tank.player_info = player_info.clone();
+        }
+        let seed = bot_seed(self.bot_seed, player_id);
+        self.bots.push(BotAgent::new(player_info, difficulty, seed));
+        true
+    }
+
+    /// Gives a tank [`Self::hand_to_bot`] handed to a bot back to its player. Returns the
+    /// player, or `None` if no bot plays as `player_id`.
+    pub fn hand_back(&mut self, player_id: PlayerId) -> Option<PlayerInfo> {
+        let index = self
+            .bots
+            .iter()
+            .position(|b| b.player_info.id == player_id)?;
+        let mut player_info = self.bots.remove(index).player_info;
+        player_info.is_ai = false;
+        for tank in self
+            .tanks
+            .iter_mut()
+            .filter(|t| t.player_info.id == player_id)
+        {
+            tank.player_info = player_info.clone();
+        }
+        self.humans.push(player_info.clone());
+        Some(player_info)
+    }
+
+    /// Bots that already have an input are driven from outside (e.g. by a model) this tick.
     fn inject_bot_inputs(&mut self, inputs: &mut HashMap<PlayerId, InputPayload>, dt: f32) {
         // Snapshot borrows used during input generation.
         let tanks = &self.tanks;
         let projectiles = &self.projectiles;
         let map = &self.map;
+        let blackboard = &self.blackboard;
 
         for bot in &mut self.bots {
             let me_id = bot.player_info.id;
+            if inputs.contains_key(&me_id) {
+                continue;
+            }
             if let Some(me_index) = tanks.iter().position(|t| t.player_info.id == me_id) {
                 let me = &tanks[me_index];
-                let input = bot.generate_input(me, tanks, projectiles, map, dt);
+                let (tanks, projectiles) = map.arena_view(me.position, tanks, projectiles);
+                let ping = blackboard.ping_for(me, map);
+                let input = bot.generate_input(me, &tanks, &projectiles, map, dt, ping);
                 inputs.insert(me_id, input);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::protocol::MapName;
+
+    #[test]
+    fn test_saved_games_carry_on_where_they_stopped() {
+        let mut engine = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        engine.bot_fill = BotFill {
+            team_size: Some(2),
+            max_bots: None,
+            blue: BotDifficulty::Turret,
+            red: BotDifficulty::Turret,
+        };
+        engine.prepare_new_round();
+        for _ in 0..60 {
+            engine.tick(0.05, HashMap::new());
+        }
+
+        let mut file = Vec::new();
+        engine.write_saved(&mut file).unwrap();
+        let mut resumed = GameEngine::read_saved(&mut &file[..]).unwrap();
+        assert_eq!(resumed.to_saved(), engine.to_saved());
+
+        for _ in 0..60 {
+            engine.tick(0.05, HashMap::new());
+            resumed.tick(0.05, HashMap::new());
+        }
+        assert_eq!(resumed.snapshot(), engine.snapshot());
+
+        file[0] = b'X';
+        assert!(matches!(
+            GameEngine::read_saved(&mut &file[..]),
+            Err(SaveError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn test_loadouts_apply_as_rounds_start() {
+        let mut engine = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        let id = engine.add_player("P".to_string()).unwrap();
+        let loadout = Loadout {
+            max_health: 2,
+            ..Default::default()
+        };
+        engine.loadouts.insert(id, loadout);
+        engine.prepare_new_round();
+
+        let tank = |engine: &GameEngine, id| {
+            engine
+                .tanks
+                .iter()
+                .find(|t| t.player_info.id == id)
+                .cloned()
+                .unwrap()
+        };
+        let upgraded = tank(&engine, id);
+        assert!(upgraded.max_health > crate::game::balance().max_health);
+        assert_eq!(upgraded.health, upgraded.max_health);
+        let bot = engine.bots[0].player_info.id;
+        assert_eq!(
+            tank(&engine, bot).max_health,
+            crate::game::balance().max_health
+        );
+    }
+
+    #[test]
+    fn test_bots_stop_at_the_games_limit_sharing_it_between_teams() {
+        let mut engine = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        engine.add_player("P".to_string()).unwrap();
+        engine.bot_fill = BotFill {
+            team_size: Some(4),
+            max_bots: Some(3),
+            ..BotFill::default()
+        };
+        engine.prepare_new_round();
+        let on = |engine: &GameEngine, team| {
+            engine
+                .bots
+                .iter()
+                .filter(|b| b.player_info.team == team)
+                .count()
+        };
+        assert_eq!(engine.bots.len(), 3);
+        assert!(on(&engine, Team::Red).abs_diff(on(&engine, Team::Blue)) <= 1);
+
+        // Bots carry over, so no more join the next round
+        engine.prepare_new_round();
+        assert_eq!(engine.bots.len(), 3);
+
+        engine.bot_fill.max_bots = Some(0);
+        let mut empty = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        empty.bot_fill = engine.bot_fill.clone();
+        empty.add_player("P".to_string()).unwrap();
+        empty.prepare_new_round();
+        assert!(empty.bots.is_empty());
+    }
+
+    #[test]
+    fn test_tanks_handed_to_bots_can_be_handed_back() {
+        let mut engine = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        let id = engine.add_player("P".to_string()).unwrap();
+        engine.prepare_new_round();
+        assert!(engine.hand_to_bot(id, BotDifficulty::Turret));
+        assert!(engine.humans.iter().all(|h| h.id != id));
+
+        let info = engine.hand_back(id).unwrap();
+        assert!(!info.is_ai);
+        assert!(engine.bots.iter().all(|b| b.player_info.id != id));
+        assert!(engine.humans.iter().any(|h| h.id == id));
+        let tank = engine
+            .tanks
+            .iter()
+            .find(|t| t.player_info.id == id)
+            .unwrap();
+        assert!(!tank.player_info.is_ai);
+        assert_eq!(engine.hand_back(id), None);
+    }
+}
@@ -0,0 +1,219 @@
+//! Grenades: slow projectiles that explode on impact or when their fuse runs out, hurting
+//! every tank in reach. Walls shield tanks from the blast.
+
+use super::{CombatRules, DamageEvent, armor_factor, balance, wall_between};
+use crate::net::protocol::{
+    Explosion, InputPayload, KillEvent, MapDefinition, Projectile, ProjectileKind, Tank,
+};
+use glam::Vec2;
+
+/// Handles the grenade cooldown and throwing, for a map at `scale`.
+/// Returns Some(Projectile) if a grenade was thrown this frame.
+pub fn handle_grenade(
+    player: &mut Tank,
+    input: &InputPayload,
+    dt: f32,
+    new_projectile_id: u64,
+    scale: f32,
+) -> Option<Projectile> {
+    if player.grenade_cooldown > 0.0 {
+        player.grenade_cooldown -= dt;
+    }
+    if !input.grenade || player.grenade_cooldown > 0.0 {
+        return None;
+    }
+    let aim_dir = (input.aim_pos - player.position).normalize_or_zero();
+    if aim_dir == Vec2::ZERO {
+        return None;
+    }
+    let balance = balance();
+    player.grenade_cooldown = balance.grenade_cooldown;
+    let radius = balance.grenade_radius() * scale;
+    Some(Projectile {
+        id: new_projectile_id,
+        kind: ProjectileKind::Grenade,
+        owner_info: player.player_info.clone(),
+        position: player.position + aim_dir * (player.radius + radius + 1.0),
+        velocity: aim_dir * balance.grenade_speed() * scale,
+        radius,
+        age: 0.0,
+    })
+}
+
+/// Removes the grenades that touch a tank and returns them, to be exploded. Like bullets,
+/// they pass through their owner for the first `rules.owner_grace` seconds.
+pub fn trigger_grenades(
+    players: &[Tank],
+    projectiles: &mut Vec<Projectile>,
+    map: &MapDefinition,
+    rules: &CombatRules,
+) -> Vec<Projectile> {
+    let mut triggered = Vec::new();
+    projectiles.retain(|proj| {
+        if proj.kind != ProjectileKind::Grenade {
+            return true;
+        }
+        let arena = map.arena_index(proj.position);
+        let armed = proj.age >= rules.owner_grace;
+        let touches = players.iter().any(|p| {
+            map.arena_index(p.position) == arena
+                && (armed || p.player_info.id != proj.owner_info.id)
+                && p.position.distance(proj.position) < p.radius + proj.radius
+        });
+        if touches {
+            triggered.push(proj.clone());
+        }
+        !touches
+    });
+    triggered
+}
+
+/// Damage a tank at `distance` from the blast's centre takes, measured to its edge, on a
+/// map at the default scale.
+pub fn blast_damage(distance: f32) -> f32 {
+    let balance = balance();
+    balance.blast_damage * (1.0 - distance / balance.blast_radius()).clamp(0.0, 1.0)
+}
+
+/// Sets off `grenades` at their positions. Damages every tank in reach that no wall hides,
+/// the thrower and teammates included, and removes the tanks that die.
+pub fn explode(
+    grenades: &[Projectile],
+    players: &mut Vec<Tank>,
+    map: &MapDefinition,
+) -> (Vec<KillEvent>, Vec<DamageEvent>, Vec<Explosion>) {
+    let mut kills = Vec::new();
+    let mut damage_events = Vec::new();
+    let mut explosions = Vec::new();
+
+    for grenade in grenades {
+        let arena = map.arena_index(grenade.position);
+        for player in players
+            .iter_mut()
+            .filter(|p| p.health > 0.0 && map.arena_index(p.position) == arena)
+        {
+            let distance = (player.position.distance(grenade.position) - player.radius).max(0.0);
+            let amount = blast_damage(distance / map.scale());
+            if amount <= 0.0 || wall_between(grenade.position, player.position, map) {
+                continue;
+            }
+            let amount = amount * armor_factor(player, player.position - grenade.position);
+            player.health -= amount;
+            damage_events.push(DamageEvent {
+                attacker_id: grenade.owner_info.id,
+                victim_id: player.player_info.id,
+                amount,
+                projectile_id: grenade.id,
+            });
+            if player.health <= 0.0 {
+                kills.push(KillEvent {
+                    killer_info: grenade.owner_info.clone(),
+                    victim_info: player.player_info.clone(),
+                });
+            }
+        }
+        explosions.push(Explosion {
+            position: grenade.position,
+            radius: balance().blast_radius() * map.scale(),
+        });
+    }
+
+    players.retain(|p| p.health > 0.0);
+    (kills, damage_events, explosions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::PlayerInfo;
+    use crate::game::units::DEFAULT_UNITS_PER_METER;
+    use crate::game::update_projectiles;
+    use crate::net::protocol::{RectWall, Team};
+
+    fn map() -> MapDefinition {
+        MapDefinition {
+            width: 1000.0,
+            height: 1000.0,
+            units_per_meter: DEFAULT_UNITS_PER_METER,
+            walls: vec![RectWall {
+                min: Vec2::new(400.0, 0.0),
+                max: Vec2::new(420.0, 1000.0),
+            }],
+            spawn_points: Vec::new(),
+            arenas: Vec::new(),
+            teleporters: Vec::new(),
+            one_way_walls: Vec::new(),
+            moving_walls: Vec::new(),
+        }
+    }
+
+    fn tank(id: u16, x: f32) -> Tank {
+        Tank::new(
+            PlayerInfo::new(id, format!("P{}", id), Team::Red),
+            Vec2::new(x, 500.0),
+        )
+    }
+
+    #[test]
+    fn test_blast_falls_off_and_stops_at_walls() {
+        let mut thrower = tank(0, 200.0);
+        let input = InputPayload {
+            aim_pos: Vec2::new(1000.0, 500.0),
+            grenade: true,
+            ..Default::default()
+        };
+        let mut grenade = handle_grenade(&mut thrower, &input, 0.0, 1, 1.0).unwrap();
+        assert!(handle_grenade(&mut thrower, &input, 0.1, 2, 1.0).is_none());
+
+        // Goes off next to the first tank, with the second further away and the third
+        // behind the wall
+        grenade.position = Vec2::new(340.0, 500.0);
+        let mut players = vec![tank(1, 360.0), tank(2, 280.0), tank(3, 440.0)];
+        let mut projectiles = vec![grenade];
+        let triggered =
+            trigger_grenades(&players, &mut projectiles, &map(), &CombatRules::default());
+        assert_eq!(triggered.len(), 1);
+        assert!(projectiles.is_empty());
+
+        let (kills, damage, explosions) = explode(&triggered, &mut players, &map());
+        assert!(kills.is_empty());
+        assert_eq!(explosions.len(), 1);
+        assert_eq!(damage.len(), 2);
+        assert_eq!(damage[0].amount, blast_damage(5.0));
+        assert!(damage[1].amount < damage[0].amount);
+        // The second tank faces the blast, so its front armor takes some of it
+        let front = blast_damage((60.0 - players[1].radius) / map().scale());
+        assert_eq!(damage[1].amount, front * (1.0 - players[1].front_armor));
+        assert_eq!(players[2].health, tank(3, 0.0).health);
+    }
+
+    #[test]
+    fn test_fuse_and_walls_set_grenades_off() {
+        let mut thrower = tank(0, 100.0);
+        let input = InputPayload {
+            aim_pos: Vec2::new(100.0, 0.0),
+            grenade: true,
+            ..Default::default()
+        };
+        let grenade = handle_grenade(&mut thrower, &input, 0.0, 1, 1.0).unwrap();
+
+        // Thrown up into the open: goes off when the fuse runs out
+        let mut projectiles = vec![grenade.clone()];
+        assert!(
+            update_projectiles(&mut projectiles, &map(), balance().grenade_fuse / 2.0).is_empty()
+        );
+        let detonated = update_projectiles(&mut projectiles, &map(), balance().grenade_fuse / 2.0);
+        assert_eq!(detonated.len(), 1);
+        assert!(projectiles.is_empty());
+
+        // Thrown into the wall: goes off in front of it
+        let mut projectiles = vec![Projectile {
+            position: Vec2::new(390.0, 500.0),
+            velocity: Vec2::new(balance().grenade_speed(), 0.0),
+            ..grenade
+        }];
+        let detonated = update_projectiles(&mut projectiles, &map(), 0.05);
+        assert_eq!(detonated.len(), 1);
+        assert!(detonated[0].position.x < 400.0);
+    }
+}
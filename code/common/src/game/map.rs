@@ -1,7 +1,31 @@
+use crate::game::is_position_safe;
+use crate::game::units::DEFAULT_UNITS_PER_METER;
 pub use crate::protocol::MapName;
-use crate::protocol::{MapDefinition, RectWall, Team};
+use crate::protocol::{GameMode, MapDefinition, RectWall, Team};
+use glam::Vec2;
+use std::f32::consts::TAU;
+use std::path::Path;
 use strum::IntoEnumIterator;
 
+/// Room kept around every extra spawn position, enough for a tank to drive out.
+const SPAWN_SPACING: f32 = 45.0;
+/// Radius checked for walls at an extra spawn position, a tank's.
+const SPAWN_CLEARANCE: f32 = 15.0;
+
+/// Stable FNV-1a hash of `bytes`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// SplitMix64 finalizer: scrambles `z` so that nearby inputs give unrelated seeds.
+pub fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 impl MapName {
     pub fn next(self) -> Self {
         let all: Vec<_> = Self::iter().collect();
@@ -16,17 +40,135 @@ impl MapName {
     }
 }
 
+impl GameMode {
+    pub fn next(self) -> Self {
+        let all: Vec<_> = Self::iter().collect();
+        let i = all.iter().position(|&m| m == self).unwrap();
+        all[(i + 1) % all.len()]
+    }
+
+    pub fn prev(self) -> Self {
+        let all: Vec<_> = Self::iter().collect();
+        let i = all.iter().position(|&m| m == self).unwrap();
+        all[(i + all.len() - 1) % all.len()]
+    }
+}
+
+impl std::str::FromStr for MapName {
+    type Err = String;
+
+    /// Case-insensitive map name, e.g. `basic` or `Tiga`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|name| format!("{:?}", name).eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("unknown map '{}'", value))
+    }
+}
+
 impl MapDefinition {
     /// backwards compatibility
     pub fn load() -> Self {
         Self::load_name(MapName::Basic)
     }
 
+    /// Reads and validates a map from a `.ron` or `.json` file holding a
+    /// [`MapDefinition`]. Moving walls start closed.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => ron::from_str(&text).map_err(|e| e.to_string()),
+            Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string()),
+            _ => Err("maps are .ron or .json files".to_string()),
+        };
+        parsed
+            .and_then(|map: Self| map.validate().map(|()| map))
+            .map_err(|e| format!("Invalid map {}: {}", path.display(), e))
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !(self.width > 0.0 && self.height > 0.0 && self.units_per_meter > 0.0) {
+            return Err("width, height and units_per_meter must be positive".to_string());
+        }
+        for team in [Team::Blue, Team::Red] {
+            if !self.spawn_points.iter().any(|(t, _)| *t == team) {
+                return Err(format!("the {:?} team has no spawn points", team));
+            }
+        }
+        for (team, position) in &self.spawn_points {
+            if !is_position_safe(*position, SPAWN_CLEARANCE, self) {
+                return Err(format!(
+                    "the {:?} spawn point at {} is in a wall or off the map",
+                    team, position
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Stable FNV-1a hash of the encoded map, used to tell apart maps sharing a name. Where
+    /// the moving walls are doesn't count.
+    pub fn content_hash(&self) -> u64 {
+        let mut map = self.clone();
+        map.reset_moving_walls();
+        let bytes = bincode::encode_to_vec(&map, bincode::config::standard())
+            .expect("map definitions are always encodable");
+        fnv1a(&bytes)
+    }
+
+    /// `count` spawn positions for `team`: its spawn points, then positions in rings
+    /// around them once a team has more tanks than the map has spawn points. Extra
+    /// positions keep clear of walls and of each other, so a crowded map may return fewer.
+    pub fn team_spawns(&self, team: Team, count: usize) -> Vec<Vec2> {
+        let points: Vec<Vec2> = self
+            .spawn_points
+            .iter()
+            .filter(|(t, _)| *t == team)
+            .map(|(_, pos)| *pos)
+            .collect();
+        let mut spawns: Vec<Vec2> = points.iter().copied().take(count).collect();
+
+        for ring in 1..=3 {
+            for step in 0..8 {
+                let offset =
+                    Vec2::from_angle(step as f32 * TAU / 8.0) * SPAWN_SPACING * ring as f32;
+                for &point in &points {
+                    if spawns.len() >= count {
+                        return spawns;
+                    }
+                    let candidate = point + offset;
+                    let crowded = spawns
+                        .iter()
+                        .chain(&points)
+                        .any(|s| s.distance(candidate) < SPAWN_SPACING);
+                    if !crowded && is_position_safe(candidate, SPAWN_CLEARANCE, self) {
+                        spawns.push(candidate);
+                    }
+                }
+            }
+        }
+        spawns
+    }
+
+    /// `count` spawn positions for a free-for-all, taking turns between both teams' sides
+    /// of the map so that neighbours start apart.
+    pub fn ffa_spawns(&self, count: usize) -> Vec<Vec2> {
+        let red = self.team_spawns(Team::Red, count.div_ceil(2));
+        let blue = self.team_spawns(Team::Blue, count / 2);
+        let mut spawns = Vec::with_capacity(count);
+        for i in 0..red.len().max(blue.len()) {
+            spawns.extend(red.get(i));
+            spawns.extend(blue.get(i));
+        }
+        spawns
+    }
+
     pub fn load_name(name: MapName) -> Self {
         match name {
             MapName::Basic => Self {
                 width: 1600.0,
                 height: 900.0,
+                units_per_meter: DEFAULT_UNITS_PER_METER,
                 walls: vec![
                     RectWall {
                         min: (410.0, 658.0).into(),
@@ -67,10 +209,15 @@ impl MapDefinition {
                     (Team::Blue, (666.0, 74.0).into()),
                     (Team::Blue, (479.0, 78.0).into()),
                 ],
+                arenas: Vec::new(),
+                teleporters: Vec::new(),
+                one_way_walls: Vec::new(),
+                moving_walls: Vec::new(),
             },
             MapName::Loss => Self {
                 width: 1080.0,
                 height: 1080.0,
+                units_per_meter: DEFAULT_UNITS_PER_METER,
                 walls: vec![
                     RectWall {
                         min: (86.0, 479.0).into(),
@@ -119,10 +266,15 @@ impl MapDefinition {
                     (Team::Blue, (643.0, 746.0).into()),
                     (Team::Blue, (845.0, 691.0).into()),
                 ],
+                arenas: Vec::new(),
+                teleporters: Vec::new(),
+                one_way_walls: Vec::new(),
+                moving_walls: Vec::new(),
             },
             MapName::Tiga => MapDefinition {
                 width: 800.0,
                 height: 600.0,
+                units_per_meter: DEFAULT_UNITS_PER_METER,
                 walls: vec![
                     RectWall {
                         min: (46.0, 137.0).into(),
@@ -155,6 +307,10 @@ impl MapDefinition {
                     (Team::Blue, (654.0, 542.0).into()),
                     (Team::Blue, (716.0, 544.0).into()),
                 ],
+                arenas: Vec::new(),
+                teleporters: Vec::new(),
+                one_way_walls: Vec::new(),
+                moving_walls: Vec::new(),
             },
         }
     }
@@ -178,6 +334,39 @@ mod tests {
         assert_eq!(prev, MapName::Tiga);
     }
 
+    #[test]
+    fn map_name_parses_case_insensitively() {
+        assert_eq!("Tiga".parse(), Ok(MapName::Tiga));
+        assert_eq!("basic".parse(), Ok(MapName::Basic));
+        assert!("moon".parse::<MapName>().is_err());
+    }
+
+    #[test]
+    fn content_hash_differs_between_maps() {
+        let basic = MapDefinition::load_name(MapName::Basic);
+        assert_eq!(basic.content_hash(), basic.clone().content_hash());
+        assert_ne!(
+            basic.content_hash(),
+            MapDefinition::load_name(MapName::Loss).content_hash()
+        );
+    }
+
+    #[test]
+    fn team_spawns_extend_beyond_spawn_points() {
+        for name in MapName::iter() {
+            let map = MapDefinition::load_name(name);
+            let spawns = map.team_spawns(Team::Red, crate::game::MAX_TEAM_SIZE);
+            assert_eq!(spawns.len(), crate::game::MAX_TEAM_SIZE, "{:?}", name);
+
+            let points: Vec<Vec2> = map.team_spawns(Team::Red, 4);
+            assert_eq!(spawns[..4], points[..]);
+            for (i, a) in spawns.iter().enumerate() {
+                assert!(is_position_safe(*a, SPAWN_CLEARANCE, &map));
+                assert!(spawns[i + 1..].iter().all(|b| a.distance(*b) >= 30.0));
+            }
+        }
+    }
+
     #[test]
     fn map_name_next_then_prev_returns_original() {
         let original = MapName::Basic;
@@ -0,0 +1,251 @@
+//! Random arena layouts from a seed, so bots trained on them don't learn one map by heart.
+//!
+//! Walls are scattered over one half of the map and copied onto the other, mirrored or
+//! rotated, so neither team gets the better side. Red starts along the left edge and
+//! Blue along the right, where no wall is placed, and a layout where a team can't drive
+//! over to the other is thrown away.
+
+use crate::ai::BotRng;
+use crate::game::balance;
+use crate::game::units::{DEFAULT_UNITS_PER_METER, default_units};
+use crate::game::{MAX_TEAM_SIZE, is_position_safe};
+use crate::net::protocol::{MapDefinition, RectWall, Team};
+use glam::Vec2;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+/// Walls keep this far from the left and right edges, leaving room for the spawn points
+const SPAWN_ZONE: f32 = default_units(7.5);
+/// Distance of the spawn points from the left and right edges
+const SPAWN_INSET: f32 = default_units(3.0);
+/// Layouts tried before settling for one without walls
+const ATTEMPTS: usize = 20;
+
+/// How the walls on one half of the map are copied onto the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Reflected across the vertical centre line
+    Mirror,
+    /// Turned half a circle around the centre of the map
+    Rotation,
+}
+
+/// Settings of the layouts a generator makes; [`generate`](Self::generate) gives a map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapGenerator {
+    pub width: f32,
+    pub height: f32,
+    /// Walls on each half, picked at random from this range
+    pub walls_per_half: RangeInclusive<usize>,
+    /// Longest side of a wall; the shorter one is a thickness picked at random
+    pub max_wall_length: f32,
+    /// Picked at random for every map if `None`
+    pub symmetry: Option<Symmetry>,
+}
+
+impl Default for MapGenerator {
+    fn default() -> Self {
+        Self {
+            width: 1600.0,
+            height: 900.0,
+            walls_per_half: 3..=7,
+            max_wall_length: default_units(20.0),
+            symmetry: None,
+        }
+    }
+}
+
+impl MapGenerator {
+    /// A layout for `seed`; the same seed always gives the same map. Both teams have
+    /// `MAX_TEAM_SIZE` spawn points.
+    pub fn generate(&self, seed: u64) -> MapDefinition {
+        let mut rng = BotRng::seed_from_u64(seed);
+        let symmetry = self.symmetry.unwrap_or_else(|| {
+            if rng.random_bool(0.5) {
+                Symmetry::Mirror
+            } else {
+                Symmetry::Rotation
+            }
+        });
+        let mut map = self.empty_map(symmetry);
+        for _ in 0..ATTEMPTS {
+            let count = rng.random_range(self.walls_per_half.clone());
+            map.walls = (0..count)
+                .flat_map(|_| {
+                    let wall = self.random_wall(&mut rng);
+                    [wall, self.counterpart(wall, symmetry)]
+                })
+                .collect();
+            if teams_can_meet(&map) {
+                return map;
+            }
+        }
+        map.walls.clear();
+        map
+    }
+
+    fn empty_map(&self, symmetry: Symmetry) -> MapDefinition {
+        let red: Vec<Vec2> = (0..MAX_TEAM_SIZE)
+            .map(|i| {
+                let y = self.height * (i as f32 + 0.5) / MAX_TEAM_SIZE as f32;
+                Vec2::new(SPAWN_INSET, y)
+            })
+            .collect();
+        let blue = red.iter().map(|&pos| self.reflect(pos, symmetry));
+        MapDefinition {
+            width: self.width,
+            height: self.height,
+            units_per_meter: DEFAULT_UNITS_PER_METER,
+            walls: Vec::new(),
+            spawn_points: red
+                .iter()
+                .map(|&pos| (Team::Red, pos))
+                .chain(blue.map(|pos| (Team::Blue, pos)))
+                .collect(),
+            arenas: Vec::new(),
+            teleporters: Vec::new(),
+            one_way_walls: Vec::new(),
+            moving_walls: Vec::new(),
+        }
+    }
+
+    /// A wall somewhere on the left half, clear of the spawn zone.
+    fn random_wall(&self, rng: &mut BotRng) -> RectWall {
+        let thickness = rng.random_range(default_units(1.0)..=default_units(2.0));
+        let length = rng.random_range(default_units(4.0)..=self.max_wall_length);
+        let mut size = if rng.random_bool(0.5) {
+            Vec2::new(length, thickness)
+        } else {
+            Vec2::new(thickness, length)
+        };
+        size = size.min(Vec2::new(self.width / 2.0 - SPAWN_ZONE, self.height));
+        let min = Vec2::new(
+            rng.random_range(SPAWN_ZONE..=self.width / 2.0 - size.x),
+            rng.random_range(0.0..=self.height - size.y),
+        );
+        RectWall {
+            min,
+            max: min + size,
+        }
+    }
+
+    fn reflect(&self, pos: Vec2, symmetry: Symmetry) -> Vec2 {
+        match symmetry {
+            Symmetry::Mirror => Vec2::new(self.width - pos.x, pos.y),
+            Symmetry::Rotation => Vec2::new(self.width, self.height) - pos,
+        }
+    }
+
+    /// `wall` copied onto the right half.
+    fn counterpart(&self, wall: RectWall, symmetry: Symmetry) -> RectWall {
+        let (a, b) = (
+            self.reflect(wall.min, symmetry),
+            self.reflect(wall.max, symmetry),
+        );
+        RectWall {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+}
+
+/// Whether a tank can drive from the first Red spawn point to every Blue one. Checked on
+/// a grid of cells as wide as a tank.
+fn teams_can_meet(map: &MapDefinition) -> bool {
+    let radius = balance().tank_radius();
+    let cell = 2.0 * radius;
+    let (columns, rows) = (
+        (map.width / cell).floor() as usize,
+        (map.height / cell).floor() as usize,
+    );
+    let center =
+        |(x, y): (usize, usize)| Vec2::new((x as f32 + 0.5) * cell, (y as f32 + 0.5) * cell);
+    let cell_of = |pos: Vec2| {
+        (
+            ((pos.x / cell) as usize).min(columns - 1),
+            ((pos.y / cell) as usize).min(rows - 1),
+        )
+    };
+    let passable = |c: (usize, usize)| is_position_safe(center(c), radius, map);
+
+    let Some(&(_, start)) = map.spawn_points.iter().find(|(team, _)| *team == Team::Red) else {
+        return false;
+    };
+    let mut reached = vec![false; columns * rows];
+    let mut queue = VecDeque::from([cell_of(start)]);
+    reached[cell_of(start).1 * columns + cell_of(start).0] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx < columns && ny < rows && !reached[ny * columns + nx] && passable((nx, ny)) {
+                reached[ny * columns + nx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    map.spawn_points
+        .iter()
+        .filter(|(team, _)| *team == Team::Blue)
+        .all(|&(_, pos)| {
+            let (x, y) = cell_of(pos);
+            reached[y * columns + x]
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeds_give_the_same_maps() {
+        let generator = MapGenerator::default();
+        assert_eq!(generator.generate(7), generator.generate(7));
+        assert_ne!(generator.generate(7).walls, generator.generate(8).walls);
+    }
+
+    #[test]
+    fn test_generated_maps_are_fair_and_playable() {
+        for symmetry in [Symmetry::Mirror, Symmetry::Rotation] {
+            let generator = MapGenerator {
+                symmetry: Some(symmetry),
+                ..MapGenerator::default()
+            };
+            for seed in 0..20 {
+                let map = generator.generate(seed);
+                assert!(
+                    map.validate().is_ok(),
+                    "seed {}: {:?}",
+                    seed,
+                    map.validate()
+                );
+                assert!(teams_can_meet(&map));
+                assert!(!map.walls.is_empty());
+                // Every wall on the left half is followed by its copy on the right
+                for pair in map.walls.chunks(2) {
+                    assert!(pair[0].max.x <= generator.width / 2.0);
+                    assert_eq!(pair[1], generator.counterpart(pair[0], symmetry));
+                }
+                for team in [Team::Red, Team::Blue] {
+                    assert_eq!(map.team_spawns(team, MAX_TEAM_SIZE).len(), MAX_TEAM_SIZE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_walled_off_teams_are_noticed() {
+        let mut map = MapGenerator::default().empty_map(Symmetry::Mirror);
+        assert!(teams_can_meet(&map));
+        map.walls.push(RectWall {
+            min: Vec2::new(790.0, 0.0),
+            max: Vec2::new(810.0, 900.0),
+        });
+        assert!(!teams_can_meet(&map));
+    }
+}
@@ -0,0 +1,150 @@
+//! Maps by name: the built-in ones, and custom maps loaded from files in [`MAPS_DIR`], so
+//! map layouts can be tried out without recompiling. Custom maps are for headless and
+//! local play; multiplayer games stick to the built-in maps every client has.
+
+use crate::game::map::MapName;
+use crate::net::protocol::MapDefinition;
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+pub const MAPS_DIR: &str = "assets/maps";
+
+/// Maps in the order they are offered: the built-in ones, then custom ones by name.
+#[derive(Debug, Clone)]
+pub struct MapRegistry {
+    maps: Vec<(String, MapDefinition)>,
+}
+
+impl MapRegistry {
+    pub fn builtin() -> Self {
+        Self {
+            maps: MapName::iter()
+                .map(|name| (format!("{:?}", name), MapDefinition::load_name(name)))
+                .collect(),
+        }
+    }
+
+    /// The built-in maps and every map file in `dir`, named after the file without its
+    /// extension. Files that fail to load, or are named like a map already there, are
+    /// skipped with a warning.
+    pub fn load(dir: &Path) -> Self {
+        let mut registry = Self::builtin();
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "ron" || ext == "json")
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if registry.get(name).is_some() {
+                log::warn!(
+                    "Skipping {}: a map is already named {}",
+                    path.display(),
+                    name
+                );
+                continue;
+            }
+            match MapDefinition::from_file(&path) {
+                Ok(map) => registry.maps.push((name.to_string(), map)),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        registry
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.maps.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The map called `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&MapDefinition> {
+        self.maps
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, map)| map)
+    }
+
+    /// Like [`get`](Self::get), with an error listing the maps there are.
+    pub fn resolve(&self, name: &str) -> Result<MapDefinition, String> {
+        self.get(name).cloned().ok_or_else(|| {
+            let names: Vec<&str> = self.names().collect();
+            format!("unknown map '{}', try one of {}", name, names.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL_MAP: &str = "(width: 400.0, height: 300.0, walls: [(min: (190.0, 100.0), \
+        max: (210.0, 200.0))], spawn_points: [(Red, (50.0, 150.0)), (Blue, (350.0, 150.0))])";
+
+    #[test]
+    fn test_custom_maps_join_the_builtin_ones() {
+        let dir = std::env::temp_dir().join(format!("map_registry_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("small.ron"), SMALL_MAP).unwrap();
+        let json = r#"{"width": 400.0, "height": 300.0, "units_per_meter": 40.0, "walls": [],
+            "spawn_points": [["Red", [50.0, 150.0]], ["Blue", [350.0, 150.0]]]}"#;
+        std::fs::write(dir.join("big_tanks.json"), json).unwrap();
+        // A built-in name, and a spawn point in a wall
+        std::fs::write(dir.join("basic.ron"), SMALL_MAP).unwrap();
+        let walled = SMALL_MAP.replace("(350.0, 150.0)", "(200.0, 150.0)");
+        std::fs::write(dir.join("walled.ron"), walled).unwrap();
+
+        let registry = MapRegistry::load(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["Basic", "Loss", "Tiga", "big_tanks", "small"]
+        );
+        assert_eq!(registry.get("basic").unwrap().width, 1600.0);
+        let small = registry.get("Small").unwrap();
+        assert_eq!(small.walls.len(), 1);
+        assert_eq!(small.units_per_meter, 20.0);
+        assert!(small.moving_walls.is_empty());
+        assert_eq!(registry.get("big_tanks").unwrap().scale(), 2.0);
+        assert!(
+            registry
+                .resolve("walled")
+                .unwrap_err()
+                .contains("Tiga, big_tanks")
+        );
+    }
+
+    #[test]
+    fn test_example_map_loads() {
+        let registry = MapRegistry::load(&Path::new("../..").join(MAPS_DIR));
+        assert!(registry.get("crossroads").is_some());
+        assert_eq!(registry.get("tiga_doors").unwrap().moving_walls.len(), 2);
+        let portals = registry.get("loss_portals").unwrap();
+        assert_eq!(
+            (portals.teleporters.len(), portals.one_way_walls.len()),
+            (2, 2)
+        );
+    }
+
+    #[test]
+    fn test_maps_are_validated() {
+        let dir = std::env::temp_dir().join(format!("map_file_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("one_team.ron");
+        std::fs::write(&path, SMALL_MAP.replace("(Blue, (350.0, 150.0))", "")).unwrap();
+        let error = MapDefinition::from_file(&path).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            error.ends_with("the Blue team has no spawn points"),
+            "{}",
+            error
+        );
+    }
+}
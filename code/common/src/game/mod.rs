@@ -1,18 +1,68 @@
+pub mod arena;
+pub mod balance;
 pub mod engine;
+pub mod explosion;
 pub mod map;
+pub mod map_generator;
+pub mod map_registry;
+pub mod moving_walls;
 pub mod player;
+pub mod replay;
+pub mod replay_writer;
+pub mod stats;
 pub mod tank;
+pub mod thumbnail;
+pub mod timeline;
+pub mod units;
+pub mod upgrades;
 
 pub use crate::net::protocol::{
-    InputPayload, KillEvent, MapDefinition, Projectile, RectWall, Tank, Team,
+    FiringMode, InputPayload, KillEvent, MapDefinition, OneWayWall, Projectile, ProjectileKind,
+    RectWall, Tank, Team, Teleporter,
 };
+use bincode::{Decode, Encode};
 use glam::Vec2;
 use rand::Rng;
 
-pub const PROJECTILE_SPEED: f32 = 500.0;
-pub const FIRE_RATE: f32 = 0.2; // Seconds between shots
-const PROJECTILE_RADIUS: f32 = 5.0;
-const PROJECTILE_DAMAGE: f32 = 10.0;
+pub use balance::{BalanceConfig, balance, reload_balance};
+
+/// Largest team a game, tournament or training match can field.
+pub const MAX_TEAM_SIZE: usize = 8;
+/// Team size of games that don't choose one.
+pub const DEFAULT_TEAM_SIZE: usize = 4;
+/// Seconds a projectile flies before it is removed, wherever it is.
+pub const MAX_PROJECTILE_AGE: f32 = 5.0;
+/// Most projectiles a game holds at once; past it, the oldest are removed.
+pub const MAX_PROJECTILES: usize = 512;
+/// How close to a teleporter pad's centre a tank has to get to be sent through.
+pub const TELEPORTER_RADIUS: f32 = 25.0;
+/// Seconds before a tank that came out of a teleporter can use one again.
+pub const TELEPORT_COOLDOWN: f32 = 3.0;
+/// Heat a shot adds with [`FiringMode::Heat`]; the gun overheats at 1.
+pub const HEAT_PER_SHOT: f32 = 0.1;
+/// Heat the gun sheds per second.
+pub const HEAT_COOLING: f32 = 0.4;
+/// With [`FiringMode::Heat`], the wait between shots as a share of the tank's fire
+/// interval.
+pub const HEAT_FIRE_INTERVAL: f32 = 0.5;
+
+/// Rules of how tanks fire and who projectiles can hit.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct CombatRules {
+    /// Seconds after firing during which a projectile passes through its owner. Past it,
+    /// shots that come back to the shooter hurt them like anyone else.
+    pub owner_grace: f32,
+    pub firing: FiringMode,
+}
+
+impl Default for CombatRules {
+    fn default() -> Self {
+        Self {
+            owner_grace: 0.15,
+            firing: FiringMode::Cooldown,
+        }
+    }
+}
 
 // --- Helper Functions ---
 
@@ -84,8 +134,12 @@ pub fn is_position_safe(pos: Vec2, radius: f32, map: &MapDefinition) -> bool {
         return false;
     }
 
-    // 2. Check Walls
-    for wall in &map.walls {
+    // 2. Check Walls, one-way ones included
+    for wall in map
+        .walls
+        .iter()
+        .chain(map.one_way_walls.iter().map(|w| &w.wall))
+    {
         // AABB expansion check.
         // We expand the wall by the player's radius. If the center of the player
         // is inside this expanded box, they are colliding.
@@ -100,11 +154,36 @@ pub fn is_position_safe(pos: Vec2, radius: f32, map: &MapDefinition) -> bool {
     true
 }
 
+/// Whether a wall blocks the straight line from `from` to `to`. Tanks don't block the
+/// view.
+pub fn wall_between(from: Vec2, to: Vec2, map: &MapDefinition) -> bool {
+    let dir = to - from;
+    map.solid_walls().any(|wall| {
+        // Slab test: clip the segment's 0..1 range against the wall on both axes
+        let (mut enter, mut exit) = (0.0f32, 1.0f32);
+        for axis in 0..2 {
+            let (start, delta) = (from[axis], dir[axis]);
+            let (min, max) = (wall.min[axis], wall.max[axis]);
+            if delta.abs() < f32::EPSILON {
+                if start < min || start > max {
+                    return false;
+                }
+                continue;
+            }
+            let (t1, t2) = ((min - start) / delta, (max - start) / delta);
+            enter = enter.max(t1.min(t2));
+            exit = exit.min(t1.max(t2));
+        }
+        enter <= exit
+    })
+}
+
 #[derive(Clone, Debug)]
 pub struct DamageEvent {
     pub attacker_id: crate::net::protocol::PlayerId,
     pub victim_id: crate::net::protocol::PlayerId,
     pub amount: f32,
+    pub projectile_id: u64,
 }
 
 // --- Main Physics Logic ---
@@ -113,7 +192,7 @@ pub fn apply_player_physics(player: &mut Tank, input: &InputPayload, map: &MapDe
     // 1. Movement
     // Normalize the input vector to ensure diagonal movement isn't faster (length 1.0).
     if input.move_axis.length_squared() > 0.0 {
-        player.velocity = input.move_axis.normalize() * player.speed;
+        player.velocity = input.move_axis.normalize() * player.speed * map.scale();
     } else {
         player.velocity = Vec2::ZERO;
     }
@@ -126,109 +205,223 @@ pub fn apply_player_physics(player: &mut Tank, input: &InputPayload, map: &MapDe
     let look_dir = input.aim_pos - player.position;
     if look_dir.length_squared() > 0.0 {
         // atan2(y, x) gives the angle in radians.
-        player.rotation = look_dir.y.atan2(look_dir.x);
+        let target = look_dir.y.atan2(look_dir.x);
+        player.rotation = turn_towards(player.rotation, target, player.turn_rate * dt);
     }
 
     // 3. Boundaries & Collisions
     constrain_to_map(&mut player.position, player.radius, map);
-    for wall in &map.walls {
-        resolve_wall_collision(&mut player.position, player.radius, wall);
+    for wall in map.solid_walls() {
+        resolve_wall_collision(&mut player.position, player.radius, &wall);
+    }
+    for one_way in &map.one_way_walls {
+        if player.velocity.dot(one_way.direction) < 0.0 {
+            resolve_wall_collision(&mut player.position, player.radius, &one_way.wall);
+        }
+    }
+
+    // 4. Teleporters
+    player.teleport_cooldown = (player.teleport_cooldown - dt).max(0.0);
+    if player.teleport_cooldown <= 0.0
+        && let Some(exit) = teleporter_exit(player.position, map)
+    {
+        player.position = exit;
+        player.teleport_cooldown = TELEPORT_COOLDOWN;
     }
 }
 
-pub fn update_projectiles(projectiles: &mut Vec<Projectile>, map: &MapDefinition, dt: f32) {
+/// The pad a tank at `position` comes out on, if it stands on a teleporter pad.
+pub fn teleporter_exit(position: Vec2, map: &MapDefinition) -> Option<Vec2> {
+    map.teleporters.iter().find_map(|t| {
+        if position.distance(t.a) < TELEPORTER_RADIUS {
+            Some(t.b)
+        } else if position.distance(t.b) < TELEPORTER_RADIUS {
+            Some(t.a)
+        } else {
+            None
+        }
+    })
+}
+
+/// Turns `from` towards `to` the short way round by at most `max_step` radians. The result
+/// is within -PI..=PI.
+pub fn turn_towards(from: f32, to: f32, max_step: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    let wrap = |angle: f32| (angle + PI).rem_euclid(TAU) - PI;
+    wrap(from + wrap(to - from).clamp(-max_step, max_step))
+}
+
+/// Moves projectiles and removes those that hit a wall or left the map. Returns the
+/// grenades among them and those whose fuse ran out, set back to where they last were in
+/// the open, to be exploded.
+pub fn update_projectiles(
+    projectiles: &mut Vec<Projectile>,
+    map: &MapDefinition,
+    dt: f32,
+) -> Vec<Projectile> {
+    let mut detonated = Vec::new();
     projectiles.retain_mut(|proj| {
+        let previous = proj.position;
         proj.position += proj.velocity * dt;
+        proj.age += dt;
 
         // Bounds Check
-        if proj.position.x < 0.0
+        let out_of_bounds = proj.position.x < 0.0
             || proj.position.x > map.width
             || proj.position.y < 0.0
-            || proj.position.y > map.height
-        {
-            return false;
-        }
+            || proj.position.y > map.height;
 
         // Wall Check
-        for wall in &map.walls {
+        let touches = |wall: &RectWall| {
             let closest = proj.position.clamp(wall.min, wall.max);
-            if (proj.position - closest).length_squared() < proj.radius.powi(2) {
-                return false;
+            (proj.position - closest).length_squared() < proj.radius.powi(2)
+        };
+        let hit_wall = map.solid_walls().any(|wall| touches(&wall))
+            || map
+                .one_way_walls
+                .iter()
+                .any(|w| proj.velocity.dot(w.direction) < 0.0 && touches(&w.wall));
+
+        if proj.kind == ProjectileKind::Grenade {
+            if out_of_bounds || hit_wall {
+                proj.position = previous;
+            } else if proj.age < balance().grenade_fuse {
+                return true;
             }
+            detonated.push(proj.clone());
+            return false;
         }
-        true
+        !(out_of_bounds || hit_wall || proj.age > MAX_PROJECTILE_AGE)
     });
+    detonated
+}
+
+/// Removes the oldest projectiles past [`MAX_PROJECTILES`]. `projectiles` are in the
+/// order they were fired. Returns how many were removed.
+pub fn cap_projectiles(projectiles: &mut Vec<Projectile>) -> usize {
+    let excess = projectiles.len().saturating_sub(MAX_PROJECTILES);
+    projectiles.drain(..excess);
+    excess
 }
 
-/// Handles weapon cooldown and bullet spawning.
+/// Handles weapon cooldown, heat and bullet spawning, for a map at `scale`.
 /// Returns Some(Projectile) if a bullet was fired this frame.
 pub fn handle_shooting(
     player: &mut Tank,
     input: &InputPayload,
     dt: f32,
     new_projectile_id: u64,
+    scale: f32,
+    rules: &CombatRules,
 ) -> Option<Projectile> {
-    // 1. Tick down the cooldown
+    // 1. Tick down the cooldown, and let the gun cool
     if player.weapon_cooldown > 0.0 {
         player.weapon_cooldown -= dt;
     }
+    player.heat = (player.heat - HEAT_COOLING * dt).max(0.0);
+    if player.heat <= 0.0 {
+        player.overheated = false;
+    }
 
     // 2. Check if trying to shoot and cooldown is ready
-    if input.shoot && player.weapon_cooldown <= 0.0 {
+    if input.shoot && player.weapon_cooldown <= 0.0 && !player.overheated {
         // Reset cooldown
-        player.weapon_cooldown = FIRE_RATE;
-
-        // Calculate direction
-        let aim_dir = (input.aim_pos - player.position).normalize_or_zero();
+        player.weapon_cooldown = match rules.firing {
+            FiringMode::Cooldown => player.fire_interval,
+            FiringMode::Heat => player.fire_interval * HEAT_FIRE_INTERVAL,
+        };
 
-        if aim_dir == Vec2::ZERO {
+        if input.aim_pos == player.position {
             return None; // Don't shoot if aim is invalid (e.g. mouse exactly on player)
         }
+        if rules.firing == FiringMode::Heat {
+            player.heat = (player.heat + HEAT_PER_SHOT).min(1.0);
+            player.overheated = player.heat >= 1.0;
+        }
+        // Shots leave along the barrel, which may still be turning towards the aim
+        let aim_dir = Vec2::from_angle(player.rotation);
 
         // Spawn bullet slightly in front of player so they don't hit themselves immediately
-        let spawn_offset = aim_dir * (player.radius + PROJECTILE_RADIUS + 1.0);
+        let radius = balance().projectile_radius() * scale;
+        let spawn_offset = aim_dir * (player.radius + radius + 1.0);
 
         return Some(Projectile {
             id: new_projectile_id,
+            kind: ProjectileKind::Bullet,
             owner_info: player.player_info.clone(),
             position: player.position + spawn_offset,
-            velocity: aim_dir * PROJECTILE_SPEED,
-            radius: PROJECTILE_RADIUS,
+            velocity: aim_dir * balance().projectile_speed() * scale,
+            radius,
+            age: 0.0,
         });
     }
 
     None
 }
 
+/// Share of a hit's damage that gets through `tank`'s armor, for a hit travelling along
+/// `direction`: a bullet's velocity, or the way a blast spreads to the tank. Hits coming
+/// in at its front, within [`BalanceConfig::front_arc`] of where it faces, lose
+/// `tank.front_armor` of it; hits from the sides and the back, and bullets standing
+/// still, lose nothing.
+pub fn armor_factor(tank: &Tank, direction: Vec2) -> f32 {
+    let incoming = -direction.normalize_or_zero();
+    if incoming == Vec2::ZERO {
+        return 1.0;
+    }
+    let facing = Vec2::from_angle(tank.rotation);
+    if facing.dot(incoming) >= balance().front_arc.cos() {
+        1.0 - tank.front_armor
+    } else {
+        1.0
+    }
+}
+
 /// Checks for collisions between projectiles and players.
 ///
 /// 1. Removes projectiles that hit a player.
-/// 2. Deals damage to the hit player.
+/// 2. Deals damage to the hit player, less what its front armor stops.
 /// 3. Returns a list of kills if any players died.
 /// 4. Removes dead players from the list (so they vanish from the game).
+///
+/// On a multi-arena map a projectile only hits players in its own arena. It passes through
+/// its owner for the first `rules.owner_grace` seconds. Grenades are left to
+/// [`explosion::trigger_grenades`].
 pub fn resolve_combat(
     players: &mut Vec<Tank>,
     projectiles: &mut Vec<Projectile>,
+    map: &MapDefinition,
+    rules: &CombatRules,
 ) -> (Vec<KillEvent>, Vec<DamageEvent>) {
     let mut kills = Vec::new();
     let mut damage_events = Vec::new();
 
     projectiles.retain(|proj| {
+        if proj.kind == ProjectileKind::Grenade {
+            return true;
+        }
         let mut hit_someone = false;
 
-        for player in players.iter_mut() {
+        let arena = map.arena_index(proj.position);
+        let armed = proj.age >= rules.owner_grace;
+        for player in players.iter_mut().filter(|p| {
+            map.arena_index(p.position) == arena
+                && (armed || p.player_info.id != proj.owner_info.id)
+        }) {
             let dist_sq = player.position.distance_squared(proj.position);
             let sum_radii = player.radius + proj.radius;
 
             if dist_sq < sum_radii * sum_radii {
                 // COLLISION DETECTED
-                player.health -= PROJECTILE_DAMAGE;
+                let amount = balance().projectile_damage * armor_factor(player, proj.velocity);
+                player.health -= amount;
 
                 // --- NEW: Record the damage event ---
                 damage_events.push(DamageEvent {
                     attacker_id: proj.owner_info.id,
                     victim_id: player.player_info.id,
-                    amount: PROJECTILE_DAMAGE,
+                    amount,
+                    projectile_id: proj.id,
                 });
                 // ------------------------------------
 
@@ -298,35 +491,64 @@ pub fn find_spawn_position(
     None // there is no spawnable place on a map (or the map is bad-designed)
 }
 
-/// Checks if one team has been eliminated.
-/// Returns Some(Team) if a team has won (opponent wiped out), or None if the battle continues.
-pub fn check_round_winner(players: &[Tank]) -> Option<Team> {
-    let mut blue_alive = 0;
-    let mut red_alive = 0;
-
-    for p in players {
-        // We assume players with health <= 0 are already removed by resolve_combat,
-        // but checking > 0 doesn't hurt.
-        if p.health > 0.0 {
-            match p.player_info.team {
-                Team::Blue => blue_alive += 1,
-                Team::Red => red_alive += 1,
-            }
+/// How a round stands after a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundOutcome {
+    /// Tanks of more than one team are still alive
+    Ongoing,
+    /// Every opponent of the team was wiped out
+    Won(Team),
+    /// The last tanks of every team fell in the same tick
+    Draw,
+}
+
+impl RoundOutcome {
+    /// The team that won, `None` while the round goes on or when it was drawn.
+    pub fn winner(self) -> Option<Team> {
+        match self {
+            RoundOutcome::Won(team) => Some(team),
+            RoundOutcome::Ongoing | RoundOutcome::Draw => None,
         }
     }
 
-    // If Blue is wiped out and Red is standing, Red wins.
-    if blue_alive == 0 && red_alive > 0 {
-        return Some(Team::Red);
+    pub fn is_over(self) -> bool {
+        self != RoundOutcome::Ongoing
     }
+}
 
-    // If Red is wiped out and Blue is standing, Blue wins.
-    if red_alive == 0 && blue_alive > 0 {
-        return Some(Team::Blue);
+/// Checks if one team has been eliminated, or all of them at once.
+pub fn check_round_winner(players: &[Tank]) -> RoundOutcome {
+    if !players.iter().any(|p| p.health > 0.0) {
+        return RoundOutcome::Draw;
     }
+    last_team_standing(players).map_or(RoundOutcome::Ongoing, RoundOutcome::Won)
+}
 
-    // If both are alive (or both died same tick), round continues.
-    None
+/// [`check_round_winner`] for the players in one arena of a multi-arena map.
+pub fn check_arena_winner(players: &[Tank], map: &MapDefinition, arena: usize) -> Option<Team> {
+    last_team_standing(
+        players
+            .iter()
+            .filter(|p| map.arena_index(p.position) == Some(arena)),
+    )
+}
+
+fn last_team_standing<'a>(players: impl IntoIterator<Item = &'a Tank>) -> Option<Team> {
+    let mut standing = None;
+
+    // We assume players with health <= 0 are already removed by resolve_combat,
+    // but checking > 0 doesn't hurt.
+    for p in players.into_iter().filter(|p| p.health > 0.0) {
+        match standing {
+            None => standing = Some(p.player_info.team),
+            // Two teams alive, the round continues.
+            Some(team) if team != p.player_info.team => return None,
+            Some(_) => {}
+        }
+    }
+
+    // Nobody left when everyone died in the same tick; the caller decides what that means.
+    standing
 }
 
 #[cfg(test)]
@@ -338,18 +560,24 @@ mod tests {
     };
     use glam::Vec2;
     #[allow(deprecated)]
-    use rand::rngs::mock::StepRng; // Or use a seeded StdRng
+    use rand::rngs::mock::StepRng;
+    use units::DEFAULT_UNITS_PER_METER; // Or use a seeded StdRng
 
     // --- Helper to create dummy map ---
     fn make_map() -> MapDefinition {
         MapDefinition {
             width: 1000.0,
             height: 1000.0,
+            units_per_meter: DEFAULT_UNITS_PER_METER,
             walls: vec![RectWall {
                 min: Vec2::new(400.0, 400.0),
                 max: Vec2::new(600.0, 600.0),
             }],
             spawn_points: Vec::new(),
+            arenas: Vec::new(),
+            teleporters: Vec::new(),
+            one_way_walls: Vec::new(),
+            moving_walls: Vec::new(),
         }
     }
 
@@ -367,24 +595,126 @@ mod tests {
             move_axis: Vec2::ZERO,
             aim_pos: Vec2::new(200.0, 100.0),
             shoot: true,
+            grenade: false,
         };
 
         // 1. First shot should succeed
-        let proj = handle_shooting(&mut p, &input_shoot, dt, 101);
+        let rules = CombatRules::default();
+        let proj = handle_shooting(&mut p, &input_shoot, dt, 101, 1.0, &rules);
         assert!(proj.is_some(), "Should fire when cooldown is 0");
         assert!(p.weapon_cooldown > 0.0, "Cooldown should be set");
 
         // 2. Immediate second shot should fail
-        let proj_fail = handle_shooting(&mut p, &input_shoot, dt, 102);
+        let proj_fail = handle_shooting(&mut p, &input_shoot, dt, 102, 1.0, &rules);
         assert!(proj_fail.is_none(), "Should not fire during cooldown");
 
         // 3. Wait for cooldown to expire
         p.weapon_cooldown = 0.0;
-        let proj_again = handle_shooting(&mut p, &input_shoot, dt, 103);
+        let proj_again = handle_shooting(&mut p, &input_shoot, dt, 103, 1.0, &rules);
         assert!(
             proj_again.is_some(),
             "Should fire again after cooldown reset"
         );
+        assert_eq!(p.heat, 0.0, "Only heat firing heats the gun");
+    }
+
+    #[test]
+    fn test_heat_firing_overheats_until_the_gun_is_cold() {
+        let mut p = Tank::new(make_info(1, Team::Blue), Vec2::new(100.0, 100.0));
+        let rules = CombatRules {
+            firing: FiringMode::Heat,
+            ..Default::default()
+        };
+        let shoot = InputPayload {
+            aim_pos: Vec2::new(200.0, 100.0),
+            shoot: true,
+            ..Default::default()
+        };
+        let interval = p.fire_interval * HEAT_FIRE_INTERVAL;
+
+        // Shots come quicker than with the cooldown, until the gun overheats
+        let mut shots = 0;
+        while !p.overheated {
+            assert!(handle_shooting(&mut p, &shoot, interval, shots, 1.0, &rules).is_some());
+            assert!(p.weapon_cooldown < p.fire_interval);
+            shots += 1;
+        }
+        assert!(shots > 5, "overheated after {} shots", shots);
+        assert_eq!(p.heat, 1.0);
+
+        // Nothing comes out until it has cooled down all the way
+        let almost = (1.0 - 0.01) / HEAT_COOLING;
+        assert!(handle_shooting(&mut p, &shoot, almost, 100, 1.0, &rules).is_none());
+        assert!(p.overheated);
+        assert!(handle_shooting(&mut p, &shoot, 0.1, 101, 1.0, &rules).is_some());
+        assert!(!p.overheated);
+        assert_eq!(p.heat, HEAT_PER_SHOT);
+    }
+
+    #[test]
+    fn test_tanks_turn_at_their_turn_rate() {
+        use std::f32::consts::PI;
+        let map = make_map();
+        let mut p = Tank::new(make_info(1, Team::Blue), Vec2::new(100.0, 100.0));
+        let behind = InputPayload {
+            aim_pos: Vec2::new(0.0, 100.0),
+            shoot: true,
+            ..Default::default()
+        };
+
+        // Half a turn takes half a second, and shots leave along the barrel meanwhile
+        apply_player_physics(&mut p, &behind, &map, 0.25);
+        assert!((p.rotation.abs() - PI / 2.0).abs() < 1e-4);
+        let proj = handle_shooting(&mut p, &behind, 0.25, 1, 1.0, &CombatRules::default()).unwrap();
+        assert!(proj.velocity.x.abs() < 1e-3);
+        apply_player_physics(&mut p, &behind, &map, 0.25);
+        assert!((p.rotation.abs() - PI).abs() < 1e-4);
+
+        // Across the -PI/PI seam the short way round
+        assert!((turn_towards(3.0, -3.0, 0.1) - 3.1).abs() < 1e-4);
+        assert!((turn_towards(3.1, -3.0, 0.1) - (3.2 - 2.0 * PI)).abs() < 1e-4);
+        assert!((turn_towards(0.5, 0.6, 1.0) - 0.6).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_teleporters_and_one_way_walls() {
+        let mut map = make_map();
+        map.teleporters.push(Teleporter {
+            a: Vec2::new(100.0, 100.0),
+            b: Vec2::new(900.0, 900.0),
+        });
+        map.one_way_walls.push(OneWayWall {
+            wall: RectWall {
+                min: Vec2::new(0.0, 700.0),
+                max: Vec2::new(100.0, 720.0),
+            },
+            direction: Vec2::Y,
+        });
+        let drive = |dir: Vec2| InputPayload {
+            move_axis: dir,
+            aim_pos: Vec2::ZERO,
+            ..Default::default()
+        };
+
+        let mut p = Tank::new(make_info(1, Team::Blue), Vec2::new(100.0, 60.0));
+        apply_player_physics(&mut p, &drive(Vec2::Y), &map, 0.1);
+        assert_eq!(p.position, Vec2::new(900.0, 900.0));
+        // Standing on the other pad doesn't send it straight back
+        apply_player_physics(&mut p, &drive(Vec2::ZERO), &map, 0.1);
+        assert_eq!(p.position, Vec2::new(900.0, 900.0));
+        apply_player_physics(&mut p, &drive(Vec2::ZERO), &map, TELEPORT_COOLDOWN);
+        assert_eq!(p.position, Vec2::new(100.0, 100.0));
+
+        // Through the one-way wall downwards, but not back up
+        let mut p = Tank::new(make_info(1, Team::Blue), Vec2::new(50.0, 690.0));
+        for _ in 0..10 {
+            apply_player_physics(&mut p, &drive(Vec2::Y), &map, 0.1);
+        }
+        assert!(p.position.y > 720.0);
+        for _ in 0..10 {
+            apply_player_physics(&mut p, &drive(Vec2::NEG_Y), &map, 0.1);
+        }
+        assert!(p.position.y >= 720.0 + p.radius - 1e-3);
     }
 
     #[test]
@@ -403,14 +733,21 @@ mod tests {
 
         let mut projectiles = vec![Projectile {
             id: 99,
+            kind: ProjectileKind::Bullet,
             owner_info: infos[0].clone(),      // Owned by P1
             position: Vec2::new(200.0, 200.0), // Hits P2 immediately
             velocity: Vec2::ZERO,
             radius: 5.0,
+            age: 0.0,
         }];
 
         //     // Run Logic
-        let (kills, _) = resolve_combat(&mut players, &mut projectiles);
+        let (kills, _) = resolve_combat(
+            &mut players,
+            &mut projectiles,
+            &make_map(),
+            &CombatRules::default(),
+        );
 
         // Assertions
         assert_eq!(kills.len(), 1, "Should generate 1 kill event");
@@ -426,6 +763,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_front_armor_stops_shots_at_the_front() {
+        let infos = [make_info(1, Team::Blue), make_info(2, Team::Red)];
+        // Facing right, along +X
+        let target = Tank::new(infos[1].clone(), Vec2::new(200.0, 200.0));
+        let shot = |velocity: Vec2| Projectile {
+            id: 5,
+            kind: ProjectileKind::Bullet,
+            owner_info: infos[0].clone(),
+            position: Vec2::new(200.0, 200.0),
+            velocity,
+            radius: 5.0,
+            age: 0.0,
+        };
+        let damage_from = |velocity: Vec2| {
+            let mut players = vec![target.clone()];
+            let mut projectiles = vec![shot(velocity)];
+            let rules = CombatRules::default();
+            let (_, damage) = resolve_combat(&mut players, &mut projectiles, &make_map(), &rules);
+            assert_eq!(players[0].health, balance().max_health - damage[0].amount);
+            damage[0].amount
+        };
+
+        let full = balance().projectile_damage;
+        let blocked = full * (1.0 - balance().front_armor);
+        // Head on, and a little off to the side
+        assert!((damage_from(Vec2::new(-500.0, 0.0)) - blocked).abs() < 1e-4);
+        assert!((damage_from(Vec2::new(-500.0, 100.0)) - blocked).abs() < 1e-4);
+        // From the side and from behind
+        assert_eq!(damage_from(Vec2::new(0.0, 500.0)), full);
+        assert_eq!(damage_from(Vec2::new(500.0, 0.0)), full);
+
+        // Turning round puts the armor in the way of the shot from behind
+        let mut turned = target.clone();
+        turned.rotation = std::f32::consts::PI;
+        assert_eq!(
+            armor_factor(&turned, Vec2::new(500.0, 0.0)),
+            1.0 - turned.front_armor
+        );
+        assert_eq!(armor_factor(&turned, Vec2::ZERO), 1.0);
+    }
+
+    #[test]
+    fn test_shots_hit_their_owner_after_the_grace() {
+        let info = make_info(1, Team::Blue);
+        let mut players = vec![Tank::new(info.clone(), Vec2::new(100.0, 100.0))];
+        // A shot that came back to the shooter, e.g. off a wall
+        let mut projectiles = vec![Projectile {
+            id: 7,
+            kind: ProjectileKind::Bullet,
+            owner_info: info,
+            position: Vec2::new(100.0, 100.0),
+            velocity: Vec2::ZERO,
+            radius: 5.0,
+            age: 0.0,
+        }];
+        let rules = CombatRules::default();
+
+        resolve_combat(&mut players, &mut projectiles, &make_map(), &rules);
+        assert_eq!(players[0].health, balance().max_health);
+        assert_eq!(projectiles.len(), 1, "passes through its owner");
+
+        update_projectiles(&mut projectiles, &make_map(), rules.owner_grace);
+        let (_, damage) = resolve_combat(&mut players, &mut projectiles, &make_map(), &rules);
+        assert_eq!(damage.len(), 1);
+        assert!(players[0].health < balance().max_health);
+    }
+
     #[test]
     fn test_no_friendly_fire_logic_check() {
         // NOTE: Currently your code allows friendly fire.
@@ -439,13 +844,20 @@ mod tests {
 
         let mut projectiles = vec![Projectile {
             id: 88,
+            kind: ProjectileKind::Bullet,
             owner_info: infos[0].clone(),
             position: Vec2::new(50.0, 50.0), // Hits teammate
             velocity: Vec2::ZERO,
             radius: 5.0,
+            age: 0.0,
         }];
 
-        resolve_combat(&mut players, &mut projectiles);
+        resolve_combat(
+            &mut players,
+            &mut projectiles,
+            &make_map(),
+            &CombatRules::default(),
+        );
 
         // If you decide to add FF protection later, flip this assertion.
         assert!(
@@ -464,15 +876,54 @@ mod tests {
             Tank::new(infos[1].clone(), Vec2::ZERO),
         ];
 
-        assert_eq!(check_round_winner(&p1), None);
+        assert_eq!(check_round_winner(&p1), RoundOutcome::Ongoing);
 
         // Scenario 2: Blue Eliminated
         let p2 = vec![Tank::new(infos[1].clone(), Vec2::ZERO)];
-        assert_eq!(check_round_winner(&p2), Some(Team::Red));
+        assert_eq!(check_round_winner(&p2), RoundOutcome::Won(Team::Red));
 
         // Scenario 3: Red Eliminated
         let p3 = vec![Tank::new(infos[0].clone(), Vec2::ZERO)];
-        assert_eq!(check_round_winner(&p3), Some(Team::Blue));
+        assert_eq!(check_round_winner(&p3), RoundOutcome::Won(Team::Blue));
+
+        // Scenario 4: the last of both teams fell in the same tick
+        let mut p4 = p1.clone();
+        for tank in &mut p4 {
+            tank.health = 0.0;
+        }
+        assert_eq!(check_round_winner(&p4), RoundOutcome::Draw);
+        assert_eq!(check_round_winner(&[]), RoundOutcome::Draw);
+        assert_eq!(RoundOutcome::Draw.winner(), None);
+    }
+
+    #[test]
+    fn test_free_for_all_win_condition() {
+        let tank = |id| Tank::new(make_info(id, Team::Ffa(id)), Vec2::ZERO);
+        let mut players = vec![tank(0), tank(1), tank(2)];
+        assert_eq!(check_round_winner(&players), RoundOutcome::Ongoing);
+
+        players[0].health = 0.0;
+        assert_eq!(check_round_winner(&players), RoundOutcome::Ongoing);
+        players[2].health = 0.0;
+        assert_eq!(
+            check_round_winner(&players),
+            RoundOutcome::Won(Team::Ffa(1))
+        );
+    }
+
+    #[test]
+    fn test_wall_between() {
+        let map = make_map();
+        let left = Vec2::new(300.0, 500.0);
+        assert!(wall_between(left, Vec2::new(700.0, 500.0), &map));
+        assert!(wall_between(left, Vec2::new(550.0, 700.0), &map));
+        assert!(!wall_between(left, Vec2::new(300.0, 900.0), &map));
+        assert!(!wall_between(
+            Vec2::new(300.0, 390.0),
+            Vec2::new(700.0, 390.0),
+            &map
+        ));
+        assert!(!wall_between(left, left, &map));
     }
 
     #[test]
@@ -526,4 +977,35 @@ mod tests {
             "Player should be pushed left to x=90 (radius distance)"
         );
     }
+
+    fn bullet(id: u64, age: f32) -> Projectile {
+        Projectile {
+            id,
+            kind: ProjectileKind::Bullet,
+            owner_info: make_info(1, Team::Blue),
+            position: Vec2::new(100.0, 100.0),
+            velocity: Vec2::ZERO,
+            radius: 5.0,
+            age,
+        }
+    }
+
+    #[test]
+    fn test_projectiles_expire_with_age() {
+        let mut projectiles = vec![bullet(0, MAX_PROJECTILE_AGE - 0.05), bullet(1, 0.0)];
+        update_projectiles(&mut projectiles, &make_map(), 0.1);
+        assert_eq!(projectiles.len(), 1);
+        assert_eq!(projectiles[0].id, 1);
+    }
+
+    #[test]
+    fn test_projectile_cap_culls_the_oldest() {
+        let mut projectiles: Vec<_> = (0..MAX_PROJECTILES as u64 + 3)
+            .map(|id| bullet(id, 0.0))
+            .collect();
+        assert_eq!(cap_projectiles(&mut projectiles), 3);
+        assert_eq!(projectiles.len(), MAX_PROJECTILES);
+        assert_eq!(projectiles[0].id, 3);
+        assert_eq!(cap_projectiles(&mut projectiles), 0);
+    }
 }
@@ -0,0 +1,189 @@
+//! Walls that slide open and closed during a match, on a timer or once a control point is
+//! captured. Their state lives in the map, so everything that looks at walls sees them
+//! where they are.
+
+use crate::net::protocol::{
+    MapDefinition, MovingWall, MovingWallState, RectWall, Tank, WallSchedule,
+};
+
+impl MovingWall {
+    /// Where the wall stands now.
+    pub fn current(&self) -> RectWall {
+        let offset = self.open_offset * self.state.openness;
+        RectWall {
+            min: self.closed.min + offset,
+            max: self.closed.max + offset,
+        }
+    }
+
+    /// Advances the schedule by `dt` and slides the wall. Returns whether it moved.
+    fn update(&mut self, tanks: &[Tank], dt: f32) -> bool {
+        let state = &mut self.state;
+        match self.schedule {
+            WallSchedule::Timer { closed, open } => {
+                let cycle = closed + open;
+                if cycle > 0.0 {
+                    state.progress = (state.progress + dt) % cycle;
+                }
+                state.opening = state.progress >= closed;
+            }
+            WallSchedule::Capture {
+                point,
+                radius,
+                hold,
+            } => {
+                if !state.opening {
+                    let held = tanks.iter().any(|t| t.position.distance(point) <= radius);
+                    state.progress = if held { state.progress + dt } else { 0.0 };
+                    state.opening = state.progress >= hold;
+                }
+            }
+        }
+
+        let target = if state.opening { 1.0 } else { 0.0 };
+        let step = if self.slide_time > 0.0 {
+            dt / self.slide_time
+        } else {
+            1.0
+        };
+        let before = state.openness;
+        state.openness = if target > before {
+            (before + step).min(target)
+        } else {
+            (before - step).max(target)
+        };
+        state.openness != before
+    }
+}
+
+impl MapDefinition {
+    /// Every wall tanks and projectiles bump into as things stand: the fixed ones and the
+    /// moving ones where they are now. One-way walls are not included.
+    pub fn solid_walls(&self) -> impl Iterator<Item = RectWall> + '_ {
+        self.walls
+            .iter()
+            .copied()
+            .chain(self.moving_walls.iter().map(MovingWall::current))
+    }
+
+    /// Runs the moving walls' schedules. Returns whether any of them moved.
+    pub fn update_moving_walls(&mut self, tanks: &[Tank], dt: f32) -> bool {
+        let mut moved = false;
+        for wall in &mut self.moving_walls {
+            moved |= wall.update(tanks, dt);
+        }
+        moved
+    }
+
+    pub fn moving_wall_states(&self) -> Vec<MovingWallState> {
+        self.moving_walls.iter().map(|w| w.state).collect()
+    }
+
+    /// Puts the moving walls where `states` says, ignoring states for another map.
+    pub fn set_moving_wall_states(&mut self, states: &[MovingWallState]) {
+        if states.len() == self.moving_walls.len() {
+            for (wall, state) in self.moving_walls.iter_mut().zip(states) {
+                wall.state = *state;
+            }
+        }
+    }
+
+    /// Closes every moving wall and restarts its schedule, as at the start of a round.
+    pub fn reset_moving_walls(&mut self) {
+        for wall in &mut self.moving_walls {
+            wall.state = MovingWallState::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::engine::GameEngine;
+    use crate::game::map_registry::MAPS_DIR;
+    use crate::game::player::PlayerInfo;
+    use crate::net::protocol::Team;
+    use glam::Vec2;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    fn door(schedule: WallSchedule) -> MovingWall {
+        MovingWall {
+            closed: RectWall {
+                min: Vec2::new(100.0, 0.0),
+                max: Vec2::new(120.0, 100.0),
+            },
+            open_offset: Vec2::new(0.0, 100.0),
+            slide_time: 1.0,
+            schedule,
+            state: MovingWallState::default(),
+        }
+    }
+
+    #[test]
+    fn test_timer_walls_slide_open_and_closed() {
+        let mut wall = door(WallSchedule::Timer {
+            closed: 2.0,
+            open: 2.0,
+        });
+        assert!(!wall.update(&[], 1.5));
+        assert!(wall.update(&[], 0.5));
+        assert_eq!(wall.state.openness, 0.5);
+        assert_eq!(wall.current().min, Vec2::new(100.0, 50.0));
+        wall.update(&[], 0.5);
+        assert_eq!(wall.state.openness, 1.0);
+        // Closing again once the open part of the cycle is over
+        wall.update(&[], 2.0);
+        assert!(!wall.state.opening);
+        assert_eq!(wall.state.openness, 0.0);
+    }
+
+    #[test]
+    fn test_capture_walls_open_for_good_once_held() {
+        let mut wall = door(WallSchedule::Capture {
+            point: Vec2::new(50.0, 50.0),
+            radius: 20.0,
+            hold: 2.0,
+        });
+        let tank = |x| {
+            Tank::new(
+                PlayerInfo::new(0, "t".into(), Team::Blue),
+                Vec2::new(x, 50.0),
+            )
+        };
+
+        wall.update(&[tank(50.0)], 1.5);
+        // Stepping off starts the capture over
+        wall.update(&[tank(500.0)], 0.1);
+        wall.update(&[tank(50.0)], 1.5);
+        assert!(!wall.state.opening);
+        wall.update(&[tank(50.0)], 1.0);
+        assert!(wall.state.opening);
+
+        wall.update(&[], 5.0);
+        assert_eq!(wall.state.openness, 1.0);
+    }
+
+    #[test]
+    fn test_wall_states_travel_in_snapshots_and_reset_between_rounds() {
+        let doors = Path::new("../..").join(MAPS_DIR).join("tiga_doors.ron");
+        let map = MapDefinition::from_file(&doors).unwrap();
+        let mut server = GameEngine::new(map.clone());
+        server.tick(10.5, HashMap::new());
+        let snapshot = server.snapshot();
+        assert!(snapshot.moving_walls.iter().all(|w| w.openness == 1.0));
+
+        let mut client = GameEngine::new(map);
+        client.apply_snapshot(snapshot);
+        assert_eq!(client.map.moving_walls, server.map.moving_walls);
+
+        server.prepare_new_round();
+        assert!(
+            server
+                .snapshot()
+                .moving_walls
+                .iter()
+                .all(|w| *w == MovingWallState::default())
+        );
+    }
+}
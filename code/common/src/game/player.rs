@@ -1,17 +1,116 @@
 use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 
 use crate::net::protocol::{PlayerId, Team};
 
+/// Hues of the team colours clients draw tanks in, in degrees.
+const BLUE_TEAM_HUE: f32 = 180.0;
+const RED_TEAM_HUE: f32 = 300.0;
+/// How close a trim's hue may come to the other team's colour, in degrees.
+const MIN_HUE_DISTANCE: f32 = 50.0;
+const MIN_SATURATION: f32 = 0.5;
+const MIN_VALUE: f32 = 0.6;
+
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct PlayerInfo {
     pub id: PlayerId,
     pub nickname: String,
     pub team: Team,
+    /// Trim the player picked for their tank; bots have none. Servers send it as
+    /// [`TankColor::for_team`] of the tank's team.
+    pub color: Option<TankColor>,
+    /// Set for bots, which clients mark with an icon
+    pub is_ai: bool,
 }
 
 impl PlayerInfo {
     pub fn new(id: PlayerId, nickname: String, team: Team) -> Self {
-        Self { id, nickname, team }
+        Self {
+            id,
+            nickname,
+            team,
+            color: None,
+            is_ai: false,
+        }
+    }
+
+    pub fn new_bot(id: PlayerId, nickname: String, team: Team) -> Self {
+        Self {
+            is_ai: true,
+            ..Self::new(id, nickname, team)
+        }
+    }
+}
+
+/// Accent colour drawn as a trim over a tank's team colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+pub struct TankColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl TankColor {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// The colour as worn on a tank of `team`. Dull colours are brightened so the trim
+    /// shows, and hues close to the other team's colour are turned away from it, so that
+    /// nobody passes for an enemy. Free-for-all tanks keep clear of Blue, which marks the
+    /// player's own tank there.
+    pub fn for_team(self, team: Team) -> Self {
+        let (hue, saturation, value) = self.to_hsv();
+        let other = match team {
+            Team::Blue => RED_TEAM_HUE,
+            Team::Red | Team::Ffa(_) => BLUE_TEAM_HUE,
+        };
+        // Signed distance in (-180, 180]
+        let offset = (hue - other + 540.0).rem_euclid(360.0) - 180.0;
+        let hue = if offset.abs() < MIN_HUE_DISTANCE {
+            let side = if offset < 0.0 { -1.0 } else { 1.0 };
+            other + side * MIN_HUE_DISTANCE
+        } else {
+            hue
+        };
+        Self::from_hsv(
+            hue.rem_euclid(360.0),
+            saturation.max(MIN_SATURATION),
+            value.max(MIN_VALUE),
+        )
+    }
+
+    /// Hue in degrees, saturation and value in 0..=1.
+    fn to_hsv(self) -> (f32, f32, f32) {
+        let [r, g, b] = [self.r, self.g, self.b].map(|c| f32::from(c) / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        (hue, saturation, max)
+    }
+
+    fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match (hue / 60.0) as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let channel = |c: f32| ((c + value - chroma) * 255.0).round() as u8;
+        Self::new(channel(r), channel(g), channel(b))
     }
 }
 
@@ -35,6 +134,25 @@ pub fn is_valid_username(username: &str) -> Result<(), String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn tank_color_keeps_clear_of_the_other_team() {
+        let gold = TankColor::new(255, 200, 0);
+        assert_eq!(gold.for_team(Team::Blue), gold);
+        assert_eq!(gold.for_team(Team::Red), gold);
+
+        // Cyan would pass for Blue on a Red tank
+        let cyan = TankColor::new(0, 255, 255);
+        assert_eq!(cyan.for_team(Team::Blue), cyan);
+        let (hue, _, _) = cyan.for_team(Team::Red).to_hsv();
+        assert!((hue - BLUE_TEAM_HUE).abs() >= MIN_HUE_DISTANCE - 1.0);
+        let (hue, _, _) = TankColor::new(255, 0, 230).for_team(Team::Blue).to_hsv();
+        assert!((hue - RED_TEAM_HUE).abs() >= MIN_HUE_DISTANCE - 1.0);
+
+        // Black gets bright enough to show
+        let (_, saturation, value) = TankColor::new(0, 0, 0).for_team(Team::Ffa(3)).to_hsv();
+        assert!(saturation >= MIN_SATURATION - 0.01 && value >= MIN_VALUE - 0.01);
+    }
+
     #[test]
     fn valid_username_accepts_alphanumeric_and_underscore() {
         assert!(is_valid_username("Player_123").is_ok());
@@ -0,0 +1,536 @@
+//! `.nbreplay` recording format.
+//!
+//! A replay is a header describing the starting conditions of a match (map, players, seed,
+//! initial tank state) followed by the inputs fed into [`GameEngine::tick`] for every tick,
+//! along with the shop purchases made before it.
+//! Since the engine is deterministic for a given sequence of inputs, re-running the ticks
+//! reproduces the match exactly.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use thiserror::Error;
+
+use crate::game::balance::balance;
+use crate::game::engine::GameEngine;
+use crate::game::player::PlayerInfo;
+use crate::game::replay_writer::ReplayIndex;
+use crate::net::protocol::{
+    EngineSnapshot, InputPayload, Loadout, MapDefinition, PlayerId, Upgrade,
+};
+
+pub const REPLAY_EXTENSION: &str = "nbreplay";
+
+/// Bumped whenever a change to the engine would make old replays play back differently.
+pub const ENGINE_VERSION: u16 = 13;
+
+pub(crate) const REPLAY_MAGIC: [u8; 4] = *b"NBRP";
+
+/// How often (in ticks) the player stores a copy of the engine, so seeking backwards
+/// doesn't need to re-simulate the whole match.
+const CHECKPOINT_INTERVAL: usize = 300;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to encode replay: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("failed to decode replay: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("not a replay file")]
+    BadMagic,
+    #[error("replay was recorded with engine version {found}, expected {expected}")]
+    VersionMismatch { found: u16, expected: u16 },
+    #[error("replay map does not match its recorded hash")]
+    MapHashMismatch,
+    #[error("replay was recorded with a different balance table")]
+    BalanceMismatch,
+    #[error("replay has no index")]
+    MissingIndex,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ReplayHeader {
+    pub engine_version: u16,
+    pub map_hash: u64,
+    /// [`BalanceConfig::content_hash`](crate::game::balance::BalanceConfig::content_hash)
+    /// of the table the match was played with
+    pub balance_hash: u64,
+    pub map: MapDefinition,
+    pub players: Vec<PlayerInfo>,
+    pub seed: u64,
+    pub initial_state: EngineSnapshot,
+    /// Upgrades bought before the recording started, by player id
+    pub loadouts: Vec<(PlayerId, Loadout)>,
+    /// Unix timestamp (seconds) of when the recording started.
+    pub created_at: u64,
+}
+
+impl ReplayHeader {
+    /// Describes a match starting from the current state of `engine`.
+    pub fn new(engine: &GameEngine, seed: u64) -> Self {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut loadouts: Vec<_> = engine.loadouts.iter().map(|(id, l)| (*id, *l)).collect();
+        loadouts.sort_by_key(|(id, _)| *id);
+
+        Self {
+            engine_version: ENGINE_VERSION,
+            map_hash: engine.map.content_hash(),
+            balance_hash: balance().content_hash(),
+            map: engine.map.clone(),
+            players: engine.tanks.iter().map(|t| t.player_info.clone()).collect(),
+            seed,
+            initial_state: engine.snapshot(),
+            loadouts,
+            created_at,
+        }
+    }
+
+    /// Fails unless the header can be played back by this build.
+    pub(crate) fn check(&self) -> Result<(), ReplayError> {
+        if self.engine_version != ENGINE_VERSION {
+            return Err(ReplayError::VersionMismatch {
+                found: self.engine_version,
+                expected: ENGINE_VERSION,
+            });
+        }
+        if self.map.content_hash() != self.map_hash {
+            return Err(ReplayError::MapHashMismatch);
+        }
+        if self.balance_hash != balance().content_hash() {
+            return Err(ReplayError::BalanceMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ReplayTick {
+    pub dt: f32,
+    pub inputs: Vec<(PlayerId, InputPayload)>,
+    /// Upgrades bought since the previous tick, in the order they were bought
+    pub purchases: Vec<(PlayerId, Upgrade)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub ticks: Vec<ReplayTick>,
+}
+
+pub(crate) fn bincode_config() -> impl bincode::config::Config {
+    bincode::config::standard()
+}
+
+impl Replay {
+    pub fn duration(&self) -> f32 {
+        self.ticks.iter().map(|t| t.dt).sum()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), ReplayError> {
+        writer.write_all(&REPLAY_MAGIC)?;
+        bincode::encode_into_std_write(&self.header, writer, bincode_config())?;
+        for tick in &self.ticks {
+            bincode::encode_into_std_write(tick, writer, bincode_config())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, ReplayError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != REPLAY_MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+
+        let header: ReplayHeader = bincode::decode_from_std_read(reader, bincode_config())?;
+        header.check()?;
+
+        // The body has no length prefix; ticks are read until the stream runs out, or
+        // until the index a `ReplayWriter` leaves at the end.
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        let ticks_len = ReplayIndex::find(&body).map_or(body.len(), |(_, start)| start);
+        let reader = &mut &body[..ticks_len];
+        let mut ticks = Vec::new();
+        loop {
+            match bincode::decode_from_std_read(reader, bincode_config()) {
+                Ok(tick) => ticks.push(tick),
+                Err(DecodeError::UnexpectedEnd { .. }) => break,
+                Err(DecodeError::Io { inner, .. })
+                    if inner.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Self { header, ticks })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+/// Collects the inputs of a running match into a [`Replay`].
+pub struct ReplayRecorder {
+    replay: Replay,
+    purchases: Vec<(PlayerId, Upgrade)>,
+}
+
+impl ReplayRecorder {
+    /// Starts recording from the current state of `engine`.
+    pub fn new(engine: &GameEngine, seed: u64) -> Self {
+        Self {
+            replay: Replay {
+                header: ReplayHeader::new(engine, seed),
+                ticks: Vec::new(),
+            },
+            purchases: Vec::new(),
+        }
+    }
+
+    /// Records a level of `upgrade` bought by `player`. It is stored with the next tick.
+    pub fn record_purchase(&mut self, player: PlayerId, upgrade: Upgrade) {
+        self.purchases.push((player, upgrade));
+    }
+
+    /// Records the inputs passed to a single engine tick.
+    pub fn record(&mut self, dt: f32, inputs: &std::collections::HashMap<PlayerId, InputPayload>) {
+        let mut inputs: Vec<_> = inputs.iter().map(|(id, i)| (*id, i.clone())).collect();
+        // Keep the output stable regardless of HashMap iteration order.
+        inputs.sort_by_key(|(id, _)| *id);
+        self.replay.ticks.push(ReplayTick {
+            dt,
+            inputs,
+            purchases: std::mem::take(&mut self.purchases),
+        });
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.replay.ticks.len()
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+/// Re-simulates a [`Replay`], supporting random access to any tick.
+pub struct ReplayPlayer {
+    replay: Replay,
+    engine: GameEngine,
+    current_tick: usize,
+    elapsed: f32,
+    checkpoints: Vec<(GameEngine, f32)>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let engine = Self::initial_engine(&replay.header);
+        Self {
+            checkpoints: vec![(engine.clone(), 0.0)],
+            replay,
+            engine,
+            current_tick: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    fn initial_engine(header: &ReplayHeader) -> GameEngine {
+        let mut engine = GameEngine::new(header.map.clone());
+        engine.apply_snapshot(header.initial_state.clone());
+        engine.loadouts = header.loadouts.iter().copied().collect();
+        engine
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        &self.replay.header
+    }
+
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    pub fn current_tick(&self) -> usize {
+        self.current_tick
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.replay.ticks.len()
+    }
+
+    /// Match time (in seconds) at the current tick.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.replay.duration()
+    }
+
+    /// Recorded delta time of the given tick, or 0 past the end of the replay.
+    pub fn tick_dt(&self, tick: usize) -> f32 {
+        self.replay.ticks.get(tick).map_or(0.0, |t| t.dt)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current_tick >= self.replay.ticks.len()
+    }
+
+    /// Advances the simulation by one recorded tick. Returns false at the end of the replay.
+    pub fn step(&mut self) -> bool {
+        let Some(tick) = self.replay.ticks.get(self.current_tick) else {
+            return false;
+        };
+
+        for (player, upgrade) in &tick.purchases {
+            self.engine
+                .loadouts
+                .entry(*player)
+                .or_default()
+                .level_up(*upgrade);
+        }
+        self.engine
+            .tick(tick.dt, tick.inputs.iter().cloned().collect());
+        self.elapsed += tick.dt;
+        self.current_tick += 1;
+
+        if self.current_tick.is_multiple_of(CHECKPOINT_INTERVAL)
+            && self.checkpoints.len() == self.current_tick / CHECKPOINT_INTERVAL
+        {
+            self.checkpoints.push((self.engine.clone(), self.elapsed));
+        }
+        true
+    }
+
+    /// Jumps to the given tick (clamped to the replay length).
+    pub fn seek(&mut self, tick: usize) {
+        let target = tick.min(self.replay.ticks.len());
+
+        // Restart from the closest checkpoint at or before the target,
+        // unless simply stepping forward from here is cheaper.
+        let checkpoint_idx = (target / CHECKPOINT_INTERVAL).min(self.checkpoints.len() - 1);
+        let checkpoint_tick = checkpoint_idx * CHECKPOINT_INTERVAL;
+        if target < self.current_tick || checkpoint_tick > self.current_tick {
+            let (engine, elapsed) = &self.checkpoints[checkpoint_idx];
+            self.engine = engine.clone();
+            self.elapsed = *elapsed;
+            self.current_tick = checkpoint_tick;
+        }
+
+        while self.current_tick < target && self.step() {}
+    }
+
+    /// Jumps to the tick closest to the given match time (in seconds).
+    pub fn seek_time(&mut self, time: f32) {
+        let mut acc = 0.0;
+        let mut tick = 0;
+        for t in &self.replay.ticks {
+            if acc + t.dt > time {
+                break;
+            }
+            acc += t.dt;
+            tick += 1;
+        }
+        self.seek(tick);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::protocol::{Tank, Team};
+    use glam::Vec2;
+    use std::collections::HashMap;
+
+    fn make_engine() -> GameEngine {
+        let mut engine = GameEngine::new(MapDefinition::load());
+        engine.tanks.push(Tank::new(
+            PlayerInfo::new(0, "blue".into(), Team::Blue),
+            Vec2::new(200.0, 100.0),
+        ));
+        engine.tanks.push(Tank::new(
+            PlayerInfo::new(1, "red".into(), Team::Red),
+            Vec2::new(200.0, 300.0),
+        ));
+        engine
+    }
+
+    fn input(move_axis: Vec2, aim_pos: Vec2, shoot: bool) -> InputPayload {
+        InputPayload {
+            move_axis,
+            aim_pos,
+            shoot,
+            grenade: false,
+        }
+    }
+
+    /// Plays a short scripted match, returning the recording and the final engine state.
+    fn record_match(ticks: usize) -> (Replay, GameEngine) {
+        let mut engine = make_engine();
+        let mut recorder = ReplayRecorder::new(&engine, 42);
+
+        for i in 0..ticks {
+            let mut inputs = HashMap::new();
+            let wobble = if i % 20 < 10 { Vec2::X } else { -Vec2::X };
+            inputs.insert(0, input(wobble, Vec2::new(200.0, 300.0), true));
+            inputs.insert(1, input(Vec2::Y, Vec2::new(200.0, 100.0), i % 3 == 0));
+
+            recorder.record(0.016, &inputs);
+            engine.tick(0.016, inputs);
+        }
+
+        (recorder.finish(), engine)
+    }
+
+    #[test]
+    fn replay_roundtrips_through_bytes() {
+        let (replay, _) = record_match(50);
+
+        let mut bytes = Vec::new();
+        replay.write_to(&mut bytes).unwrap();
+        let decoded = Replay::read_from(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(decoded, replay);
+        assert_eq!(decoded.ticks.len(), 50);
+        assert_eq!(decoded.header.players.len(), 2);
+        assert_eq!(decoded.header.seed, 42);
+    }
+
+    #[test]
+    fn read_rejects_wrong_magic() {
+        let bytes = b"NOPE and some more bytes".to_vec();
+        assert!(matches!(
+            Replay::read_from(&mut bytes.as_slice()),
+            Err(ReplayError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn read_rejects_other_engine_version() {
+        let (mut replay, _) = record_match(1);
+        replay.header.engine_version = ENGINE_VERSION + 1;
+
+        let mut bytes = Vec::new();
+        replay.write_to(&mut bytes).unwrap();
+
+        assert!(matches!(
+            Replay::read_from(&mut bytes.as_slice()),
+            Err(ReplayError::VersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn read_rejects_other_balance() {
+        let (mut replay, _) = record_match(1);
+        replay.header.balance_hash ^= 1;
+
+        let mut bytes = Vec::new();
+        replay.write_to(&mut bytes).unwrap();
+
+        assert!(matches!(
+            Replay::read_from(&mut bytes.as_slice()),
+            Err(ReplayError::BalanceMismatch)
+        ));
+    }
+
+    #[test]
+    fn playback_reproduces_recorded_match() {
+        let (replay, live_engine) = record_match(400);
+
+        let mut player = ReplayPlayer::new(replay);
+        while player.step() {}
+
+        assert!(player.is_finished());
+        assert_eq!(player.engine().snapshot(), live_engine.snapshot());
+    }
+
+    #[test]
+    fn seek_matches_sequential_playback() {
+        let (replay, _) = record_match(700);
+
+        let mut sequential = ReplayPlayer::new(replay.clone());
+        for _ in 0..650 {
+            sequential.step();
+        }
+
+        let mut seeking = ReplayPlayer::new(replay);
+        seeking.seek(650);
+        assert_eq!(seeking.engine().snapshot(), sequential.engine().snapshot());
+
+        // Seeking backwards (past a checkpoint) and forward again must land in the same place.
+        seeking.seek(10);
+        assert_eq!(seeking.current_tick(), 10);
+        seeking.seek(650);
+        assert_eq!(seeking.engine().snapshot(), sequential.engine().snapshot());
+    }
+
+    #[test]
+    fn purchases_carry_into_the_next_round() {
+        let mut engine = make_engine();
+        engine.loadouts.insert(
+            1,
+            Loadout {
+                speed: 1,
+                ..Default::default()
+            },
+        );
+        let mut recorder = ReplayRecorder::new(&engine, 3);
+        for i in 0..20 {
+            if i == 10 {
+                for upgrade in [Upgrade::MaxHealth, Upgrade::MaxHealth] {
+                    engine.loadouts.entry(0).or_default().level_up(upgrade);
+                    recorder.record_purchase(0, upgrade);
+                }
+            }
+            let inputs = HashMap::from([(0, input(Vec2::X, Vec2::ZERO, false))]);
+            recorder.record(0.016, &inputs);
+            engine.tick(0.016, inputs);
+        }
+
+        let mut player = ReplayPlayer::new(recorder.finish());
+        player.seek(9);
+        assert_eq!(player.engine().loadouts.get(&0), None);
+        while player.step() {}
+        assert_eq!(player.engine().loadouts, engine.loadouts);
+
+        let mut replayed = player.engine().clone();
+        replayed.prepare_new_round();
+        engine.prepare_new_round();
+        let max_health = |engine: &GameEngine| -> Vec<f32> {
+            engine.tanks.iter().map(|t| t.max_health).collect()
+        };
+        assert_eq!(max_health(&replayed), max_health(&engine));
+    }
+
+    #[test]
+    fn seek_is_clamped_to_replay_length() {
+        let (replay, _) = record_match(10);
+        let mut player = ReplayPlayer::new(replay);
+
+        player.seek(1000);
+        assert_eq!(player.current_tick(), 10);
+        assert!(player.is_finished());
+    }
+}
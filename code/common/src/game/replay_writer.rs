@@ -0,0 +1,284 @@
+//! Streaming `.nbreplay` files for long sessions.
+//!
+//! [`ReplayWriter`] appends every tick to the file as it is played instead of keeping the
+//! match in memory, and closes the file with an index of where every
+//! [`INDEX_INTERVAL`]-th tick starts, so [`ReplayReader`] can jump into the middle of
+//! millions of ticks. Files stay readable by [`Replay::load`](super::replay::Replay::load),
+//! which ignores the index.
+//!
+//! The index is a bincode-encoded [`ReplayIndex`], followed by its length as a
+//! little-endian `u64` and [`INDEX_MAGIC`].
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use bincode::{Decode, Encode};
+
+use super::replay::{REPLAY_MAGIC, ReplayError, ReplayHeader, ReplayTick, bincode_config};
+use crate::net::protocol::{InputPayload, PlayerId, Upgrade};
+
+const INDEX_MAGIC: [u8; 4] = *b"NBIX";
+/// Ticks between two index entries.
+pub const INDEX_INTERVAL: u64 = 1000;
+/// Ticks between two flushes to disk, so a crash loses at most this much.
+const FLUSH_INTERVAL: u64 = 10_000;
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Where ticks start in a replay file.
+#[derive(Debug, Clone, Default, PartialEq, Encode, Decode)]
+pub struct ReplayIndex {
+    pub tick_count: u64,
+    /// Byte offset from the start of the file of every `INDEX_INTERVAL`-th tick
+    pub offsets: Vec<u64>,
+}
+
+impl ReplayIndex {
+    /// The index at the end of `bytes` with the position it starts at, if there is one.
+    pub(crate) fn find(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (rest, magic) = bytes.split_last_chunk::<4>()?;
+        if *magic != INDEX_MAGIC {
+            return None;
+        }
+        let (rest, len) = rest.split_last_chunk::<8>()?;
+        let start = rest
+            .len()
+            .checked_sub(usize::try_from(u64::from_le_bytes(*len)).ok()?)?;
+        let (index, _) = bincode::decode_from_slice(&rest[start..], bincode_config()).ok()?;
+        Some((index, start))
+    }
+}
+
+/// Appends ticks to a replay file as they are played.
+///
+/// Ticks are encoded into a reused buffer and written through a large `BufWriter`, so
+/// recording allocates nothing per tick once warmed up and only touches the disk when
+/// the buffer fills or every [`FLUSH_INTERVAL`] ticks.
+pub struct ReplayWriter {
+    file: BufWriter<File>,
+    scratch: Vec<u8>,
+    ids: Vec<PlayerId>,
+    purchases: Vec<(PlayerId, Upgrade)>,
+    offset: u64,
+    index: ReplayIndex,
+}
+
+impl ReplayWriter {
+    pub fn create(path: &Path, header: &ReplayHeader) -> Result<Self, ReplayError> {
+        let mut file = BufWriter::with_capacity(BUFFER_SIZE, File::create(path)?);
+        let mut scratch = REPLAY_MAGIC.to_vec();
+        bincode::encode_into_std_write(header, &mut scratch, bincode_config())?;
+        file.write_all(&scratch)?;
+
+        Ok(Self {
+            file,
+            offset: scratch.len() as u64,
+            scratch,
+            ids: Vec::new(),
+            purchases: Vec::new(),
+            index: ReplayIndex::default(),
+        })
+    }
+
+    /// Records a level of `upgrade` bought by `player`. It is written with the next tick.
+    pub fn record_purchase(&mut self, player: PlayerId, upgrade: Upgrade) {
+        self.purchases.push((player, upgrade));
+    }
+
+    /// Appends the inputs passed to a single engine tick, encoded like a [`ReplayTick`].
+    pub fn record(
+        &mut self,
+        dt: f32,
+        inputs: &HashMap<PlayerId, InputPayload>,
+    ) -> Result<(), ReplayError> {
+        let ticks = self.index.tick_count;
+        if ticks.is_multiple_of(INDEX_INTERVAL) {
+            self.index.offsets.push(self.offset);
+        }
+
+        // Same order as `ReplayRecorder`, regardless of HashMap iteration order
+        self.ids.clear();
+        self.ids.extend(inputs.keys());
+        self.ids.sort_unstable();
+
+        self.scratch.clear();
+        let config = bincode_config();
+        bincode::encode_into_std_write(dt, &mut self.scratch, config)?;
+        bincode::encode_into_std_write(self.ids.len() as u64, &mut self.scratch, config)?;
+        for id in &self.ids {
+            bincode::encode_into_std_write((id, &inputs[id]), &mut self.scratch, config)?;
+        }
+        bincode::encode_into_std_write(&self.purchases, &mut self.scratch, config)?;
+        self.purchases.clear();
+        self.file.write_all(&self.scratch)?;
+        self.offset += self.scratch.len() as u64;
+        self.index.tick_count += 1;
+
+        if self.index.tick_count.is_multiple_of(FLUSH_INTERVAL) {
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn tick_count(&self) -> u64 {
+        self.index.tick_count
+    }
+
+    /// Writes the index and closes the file.
+    pub fn finish(mut self) -> Result<(), ReplayError> {
+        self.scratch.clear();
+        bincode::encode_into_std_write(&self.index, &mut self.scratch, bincode_config())?;
+        let len = self.scratch.len() as u64;
+        self.scratch.extend(len.to_le_bytes());
+        self.scratch.extend(INDEX_MAGIC);
+        self.file.write_all(&self.scratch)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads ticks of a file written by [`ReplayWriter`] on demand, using its index.
+pub struct ReplayReader {
+    file: BufReader<File>,
+    header: ReplayHeader,
+    index: ReplayIndex,
+}
+
+impl ReplayReader {
+    pub fn open(path: &Path) -> Result<Self, ReplayError> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if magic != REPLAY_MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let header: ReplayHeader = bincode::decode_from_std_read(&mut file, bincode_config())?;
+        header.check()?;
+
+        let mut trailer = [0u8; 12];
+        let end = file.seek(SeekFrom::End(-(trailer.len() as i64)))?;
+        file.read_exact(&mut trailer)?;
+        let (len, magic) = trailer.split_at(8);
+        if magic != INDEX_MAGIC {
+            return Err(ReplayError::MissingIndex);
+        }
+        let len = u64::from_le_bytes(len.try_into().unwrap());
+        let start = end.checked_sub(len).ok_or(ReplayError::MissingIndex)?;
+        file.seek(SeekFrom::Start(start))?;
+        let index = bincode::decode_from_std_read(&mut file, bincode_config())?;
+
+        Ok(Self {
+            file,
+            header,
+            index,
+        })
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    pub fn tick_count(&self) -> u64 {
+        self.index.tick_count
+    }
+
+    /// Up to `count` ticks starting at `tick`. Starts decoding at the closest indexed tick
+    /// before it, so the cost doesn't grow with the length of the file.
+    pub fn read_ticks(&mut self, tick: u64, count: usize) -> Result<Vec<ReplayTick>, ReplayError> {
+        let tick = tick.min(self.index.tick_count);
+        let entry = (tick / INDEX_INTERVAL) as usize;
+        let Some(&offset) = self.index.offsets.get(entry) else {
+            return Ok(Vec::new());
+        };
+        self.file.seek(SeekFrom::Start(offset))?;
+
+        for _ in entry as u64 * INDEX_INTERVAL..tick {
+            let _: ReplayTick = bincode::decode_from_std_read(&mut self.file, bincode_config())?;
+        }
+        let available = (self.index.tick_count - tick).min(count as u64);
+        (0..available)
+            .map(|_| {
+                Ok(bincode::decode_from_std_read(
+                    &mut self.file,
+                    bincode_config(),
+                )?)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::engine::GameEngine;
+    use crate::game::player::PlayerInfo;
+    use crate::game::replay::{Replay, ReplayRecorder};
+    use crate::net::protocol::{MapDefinition, Tank, Team};
+    use glam::Vec2;
+
+    #[test]
+    fn test_written_ticks_match_the_recorder_and_can_be_sought() {
+        let mut engine = GameEngine::new(MapDefinition::load());
+        for (id, team) in [(0, Team::Blue), (1, Team::Red)] {
+            let info = PlayerInfo::new(id, format!("P{}", id), team);
+            engine
+                .tanks
+                .push(Tank::new(info, Vec2::new(200.0, 100.0 + 200.0 * id as f32)));
+        }
+
+        let path = std::env::temp_dir().join("replay_writer_test.nbreplay");
+        let mut recorder = ReplayRecorder::new(&engine, 7);
+        let mut writer = ReplayWriter::create(&path, &recorder.replay().header).unwrap();
+        let ticks = 2 * INDEX_INTERVAL + 10;
+        for i in 0..ticks {
+            let inputs = HashMap::from([
+                (
+                    1,
+                    InputPayload {
+                        move_axis: Vec2::Y,
+                        shoot: i % 3 == 0,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    0,
+                    InputPayload {
+                        aim_pos: Vec2::new(i as f32, 0.0),
+                        ..Default::default()
+                    },
+                ),
+            ]);
+            if i == 5 {
+                recorder.record_purchase(1, Upgrade::Speed);
+                writer.record_purchase(1, Upgrade::Speed);
+            }
+            recorder.record(0.016, &inputs);
+            writer.record(0.016, &inputs).unwrap();
+        }
+        assert_eq!(writer.tick_count(), ticks);
+        writer.finish().unwrap();
+
+        let recorded = recorder.finish();
+        assert_eq!(Replay::load(&path).unwrap(), recorded);
+
+        let mut reader = ReplayReader::open(&path).unwrap();
+        assert_eq!(reader.tick_count(), ticks);
+        assert_eq!(*reader.header(), recorded.header);
+        let from = INDEX_INTERVAL as usize + 5;
+        assert_eq!(
+            reader.read_ticks(from as u64, 3).unwrap(),
+            recorded.ticks[from..from + 3]
+        );
+        assert_eq!(reader.read_ticks(ticks - 1, 10).unwrap().len(), 1);
+        assert!(reader.read_ticks(ticks, 10).unwrap().is_empty());
+
+        // Replays saved whole have no index to seek with
+        recorded.save(&path).unwrap();
+        assert!(matches!(
+            ReplayReader::open(&path),
+            Err(ReplayError::MissingIndex)
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+}
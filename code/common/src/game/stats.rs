@@ -0,0 +1,155 @@
+//! Shots, hits and damage of every player over a match.
+
+use crate::game::engine::GameTickResult;
+use crate::game::player::PlayerInfo;
+use crate::net::protocol::{PlayerId, PlayerStats, Tank, Team};
+use std::collections::{BTreeMap, HashSet};
+
+impl PlayerStats {
+    pub fn new(player_info: PlayerInfo) -> Self {
+        Self {
+            player_info,
+            shots_fired: 0,
+            shots_hit: 0,
+            damage_dealt: 0.0,
+        }
+    }
+
+    /// Share of shots that hit, `None` before the first shot.
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f32 / self.shots_fired as f32)
+    }
+}
+
+/// Tallies the engine's ticks into [`PlayerStats`].
+#[derive(Debug, Clone, Default)]
+pub struct MatchStats {
+    players: BTreeMap<PlayerId, PlayerStats>,
+}
+
+impl MatchStats {
+    /// Counts the shots and hits of a tick. `tanks` are the ones left after it; those that
+    /// fell were seen in earlier ticks.
+    pub fn record(&mut self, tanks: &[Tank], result: &GameTickResult) {
+        for tank in tanks {
+            let info = &tank.player_info;
+            self.players
+                .entry(info.id)
+                .or_insert_with(|| PlayerStats::new(info.clone()))
+                .player_info = info.clone();
+        }
+        for id in &result.shots {
+            if let Some(stats) = self.players.get_mut(id) {
+                stats.shots_fired += 1;
+            }
+        }
+        // A grenade's blast can hit several tanks; it is still one shot
+        let mut landed = HashSet::new();
+        for hit in &result.damage {
+            let victim_team = self.players.get(&hit.victim_id).map(|s| s.player_info.team);
+            if let Some(stats) = self.players.get_mut(&hit.attacker_id) {
+                if landed.insert(hit.projectile_id) {
+                    stats.shots_hit += 1;
+                }
+                if victim_team != Some(stats.player_info.team) {
+                    stats.damage_dealt += hit.amount;
+                }
+            }
+        }
+    }
+
+    /// Damage `player` dealt to enemies.
+    pub fn damage_dealt(&self, player: PlayerId) -> f32 {
+        self.players.get(&player).map_or(0.0, |s| s.damage_dealt)
+    }
+
+    /// Damage dealt to enemies by everyone on `team`.
+    pub fn team_damage(&self, team: Team) -> f32 {
+        self.players
+            .values()
+            .filter(|s| s.player_info.team == team)
+            .map(|s| s.damage_dealt)
+            .sum()
+    }
+
+    /// Everyone's stats, most damage first.
+    pub fn summary(&self) -> Vec<PlayerStats> {
+        let mut summary: Vec<PlayerStats> = self.players.values().cloned().collect();
+        summary.sort_by(|a, b| b.damage_dealt.total_cmp(&a.damage_dealt));
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::engine::GameEngine;
+    use crate::game::{DamageEvent, RoundOutcome};
+    use crate::net::protocol::{InputPayload, MapDefinition, MapName, Team};
+    use glam::Vec2;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_shots_and_hits_are_attributed() {
+        let mut engine = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        let tank = |id, team, x| {
+            Tank::new(
+                PlayerInfo::new(id, format!("P{}", id), team),
+                Vec2::new(x, 100.0),
+            )
+        };
+        engine.tanks = vec![
+            tank(0, Team::Blue, 100.0),
+            tank(1, Team::Red, 160.0),
+            tank(2, Team::Blue, 40.0),
+        ];
+
+        let mut stats = MatchStats::default();
+        let aim_right = InputPayload {
+            move_axis: Vec2::ZERO,
+            aim_pos: Vec2::new(1000.0, 100.0),
+            shoot: true,
+            grenade: false,
+        };
+        let mut hit = false;
+        for _ in 0..20 {
+            let inputs = HashMap::from([(0, aim_right.clone())]);
+            let result = engine.tick(0.02, inputs);
+            hit |= !result.damage.is_empty();
+            stats.record(&engine.tanks, &result);
+        }
+        assert!(hit);
+
+        let summary = stats.summary();
+        let shooter = &summary[0];
+        assert_eq!(shooter.player_info.id, 0);
+        assert!(shooter.shots_fired >= 1);
+        assert!(shooter.shots_hit >= 1);
+        assert_eq!(shooter.damage_dealt, 10.0 * shooter.shots_hit as f32);
+        assert_eq!(summary[1].accuracy(), None);
+
+        // Hitting a teammate counts for accuracy, not damage
+        let result = GameTickResult {
+            shots: vec![1],
+            kills: Vec::new(),
+            damage: vec![DamageEvent {
+                attacker_id: 2,
+                victim_id: 0,
+                amount: 10.0,
+                projectile_id: 1000,
+            }],
+            explosions: Vec::new(),
+            outcome: RoundOutcome::Ongoing,
+            culled_projectiles: 0,
+        };
+        stats.record(&engine.tanks, &result);
+        let teammate = stats.players[&2].clone();
+        assert_eq!((teammate.shots_hit, teammate.damage_dealt), (1, 0.0));
+        assert_eq!(stats.players[&1].shots_fired, 1);
+        assert_eq!(
+            stats.team_damage(Team::Blue),
+            stats.players[&0].damage_dealt
+        );
+        assert_eq!(stats.team_damage(Team::Red), 0.0);
+    }
+}
@@ -1,19 +1,38 @@
 use glam::Vec2;
 
-use crate::{game::player::PlayerInfo, net::protocol::Tank};
+use crate::{
+    game::{balance, player::PlayerInfo},
+    net::protocol::{MapDefinition, Tank},
+};
 
 impl Tank {
-    // TODO: remove magic numbers
+    /// A tank sized for maps at the default scale.
     pub fn new(player_info: PlayerInfo, position: Vec2) -> Self {
+        let balance = balance();
         Tank {
             player_info,
             position,
             velocity: Vec2::ZERO,
             rotation: 0.0,
-            radius: 15.0,
-            speed: 200.0,
-            health: 100.0,
+            radius: balance.tank_radius(),
+            speed: balance.tank_speed(),
+            turn_rate: balance.turn_rate,
+            health: balance.max_health,
+            max_health: balance.max_health,
+            fire_interval: balance.fire_interval,
             weapon_cooldown: 0.0,
+            grenade_cooldown: 0.0,
+            teleport_cooldown: 0.0,
+            heat: 0.0,
+            overheated: false,
+            front_armor: balance.front_armor,
         }
     }
+
+    /// The tank sized for `map`. Its `speed` stays at the default scale; the engine
+    /// scales it as the tank moves.
+    pub fn sized_for(mut self, map: &MapDefinition) -> Self {
+        self.radius = balance().tank_radius() * map.scale();
+        self
+    }
 }
@@ -0,0 +1,161 @@
+//! Small pictures of maps, drawn on the CPU so they can be made without a window: for map
+//! pickers, and by the `thumbnail` tool for files next to the maps.
+
+use crate::net::protocol::{MapDefinition, RectWall, Team};
+use glam::Vec2;
+
+type Rgba = [u8; 4];
+
+const FLOOR: Rgba = [13, 13, 26, 255];
+const ARENA_FLOOR: Rgba = [20, 28, 48, 255];
+const WALL: Rgba = [0, 51, 102, 255];
+const WALL_OUTLINE: Rgba = [0, 180, 180, 255];
+const ONE_WAY_WALL: Rgba = [0, 90, 120, 255];
+const MOVING_WALL: Rgba = [200, 120, 0, 255];
+/// Pads of a pair share a colour, taken in turn from these, as in the game
+const PAD_COLORS: [Rgba; 3] = [[255, 204, 0, 255], [153, 77, 255, 255], [51, 255, 102, 255]];
+const RED_SPAWN: Rgba = [230, 60, 60, 255];
+const BLUE_SPAWN: Rgba = [60, 130, 255, 255];
+const OTHER_SPAWN: Rgba = [200, 200, 200, 255];
+
+/// A picture of a map, row by row from the top, four bytes a pixel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Thumbnail {
+    fn pixel_mut(&mut self, x: u32, y: u32) -> &mut [u8] {
+        let start = ((y * self.width + x) * 4) as usize;
+        &mut self.rgba[start..start + 4]
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        let start = ((y * self.width + x) * 4) as usize;
+        self.rgba[start..start + 4].try_into().unwrap()
+    }
+
+    /// Fills the pixels whose centres lie in `min..max`, in pixels.
+    fn fill_rect(&mut self, min: Vec2, max: Vec2, color: Rgba) {
+        let x0 = min.x.round().max(0.0) as u32;
+        let y0 = min.y.round().max(0.0) as u32;
+        let x1 = (max.x.round().max(0.0) as u32).min(self.width);
+        let y1 = (max.y.round().max(0.0) as u32).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.pixel_mut(x, y).copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn fill_circle(&mut self, center: Vec2, radius: f32, color: Rgba) {
+        let min = (center - radius).floor().max(Vec2::ZERO);
+        let max = (center + radius).ceil();
+        for y in min.y as u32..(max.y as u32).min(self.height) {
+            for x in min.x as u32..(max.x as u32).min(self.width) {
+                let pixel_center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if pixel_center.distance(center) <= radius {
+                    self.pixel_mut(x, y).copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+/// Draws `map` from above, its longer side `size` pixels long: arenas, walls, teleporter
+/// pads and spawn points coloured by team. Moving walls are drawn closed.
+pub fn render_thumbnail(map: &MapDefinition, size: u32) -> Thumbnail {
+    let scale = size as f32 / map.width.max(map.height);
+    let width = ((map.width * scale).round() as u32).max(1);
+    let height = ((map.height * scale).round() as u32).max(1);
+    let mut thumbnail = Thumbnail {
+        width,
+        height,
+        rgba: FLOOR.repeat((width * height) as usize),
+    };
+    // Features thinner than a pixel would vanish
+    let marker = (size as f32 / 64.0).max(1.5);
+
+    for arena in &map.arenas {
+        thumbnail.fill_rect(arena.min * scale, arena.max * scale, ARENA_FLOOR);
+    }
+    let mut draw_wall = |wall: &RectWall, fill: Rgba, outline: Option<Rgba>| {
+        let (min, max) = (wall.min * scale, wall.max * scale);
+        let max = max.max(min + 1.0);
+        match outline {
+            Some(outline) if max.x - min.x > 3.0 && max.y - min.y > 3.0 => {
+                thumbnail.fill_rect(min, max, outline);
+                thumbnail.fill_rect(min + 1.0, max - 1.0, fill);
+            }
+            _ => thumbnail.fill_rect(min, max, fill),
+        }
+    };
+    for one_way in &map.one_way_walls {
+        draw_wall(&one_way.wall, ONE_WAY_WALL, None);
+    }
+    for moving in &map.moving_walls {
+        draw_wall(&moving.closed, MOVING_WALL, None);
+    }
+    for wall in &map.walls {
+        draw_wall(wall, WALL, Some(WALL_OUTLINE));
+    }
+    for (i, pair) in map.teleporters.iter().enumerate() {
+        let color = PAD_COLORS[i % PAD_COLORS.len()];
+        thumbnail.fill_circle(pair.a * scale, marker * 1.5, color);
+        thumbnail.fill_circle(pair.b * scale, marker * 1.5, color);
+    }
+    for (team, pos) in &map.spawn_points {
+        let color = match team {
+            Team::Red => RED_SPAWN,
+            Team::Blue => BLUE_SPAWN,
+            Team::Ffa(_) => OTHER_SPAWN,
+        };
+        thumbnail.fill_circle(*pos * scale, marker, color);
+    }
+    thumbnail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::MapName;
+
+    #[test]
+    fn test_thumbnails_keep_the_map_shape() {
+        let map = MapDefinition::load_name(MapName::Basic);
+        let thumbnail = render_thumbnail(&map, 200);
+        assert_eq!(thumbnail.width.max(thumbnail.height), 200);
+        let aspect = thumbnail.width as f32 / thumbnail.height as f32;
+        assert!((aspect - map.width / map.height).abs() < 0.02);
+        assert_eq!(
+            thumbnail.rgba.len(),
+            (thumbnail.width * thumbnail.height * 4) as usize
+        );
+    }
+
+    #[test]
+    fn test_walls_and_spawns_are_drawn() {
+        let map = MapDefinition {
+            width: 100.0,
+            height: 50.0,
+            walls: vec![RectWall {
+                min: Vec2::new(40.0, 10.0),
+                max: Vec2::new(60.0, 40.0),
+            }],
+            spawn_points: vec![
+                (Team::Red, Vec2::new(10.0, 25.0)),
+                (Team::Blue, Vec2::new(90.0, 25.0)),
+            ],
+            ..MapDefinition::load_name(MapName::Basic)
+        };
+        let thumbnail = render_thumbnail(&map, 100);
+        assert_eq!((thumbnail.width, thumbnail.height), (100, 50));
+        assert_eq!(thumbnail.pixel(50, 25), WALL);
+        assert_eq!(thumbnail.pixel(40, 25), WALL_OUTLINE);
+        assert_eq!(thumbnail.pixel(10, 25), RED_SPAWN);
+        assert_eq!(thumbnail.pixel(90, 25), BLUE_SPAWN);
+        assert_eq!(thumbnail.pixel(25, 5), FLOOR);
+    }
+}
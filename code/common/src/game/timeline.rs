@@ -0,0 +1,130 @@
+//! Key moments of a match, for the [`MatchSummary`] sent once it ends.
+
+use crate::game::player::PlayerInfo;
+use crate::net::protocol::{KillEvent, MatchSummary, PlayerId, Team, TimelineEntry, TimelineEvent};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Kills past this many are left out, which keeps the summary small in long matches.
+/// Rounds are always recorded.
+pub const MAX_TIMELINE_KILLS: usize = 200;
+
+/// Records a match's timeline as it is played.
+#[derive(Debug, Clone, Default)]
+pub struct MatchTimeline {
+    elapsed: Duration,
+    entries: Vec<TimelineEntry>,
+    kills: usize,
+    players: BTreeMap<PlayerId, PlayerInfo>,
+}
+
+impl MatchTimeline {
+    /// Moves the match clock on by a tick.
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed += dt;
+    }
+
+    pub fn round_started(&mut self, round: u8) {
+        self.push(TimelineEvent::RoundStarted(round));
+    }
+
+    pub fn round_ended(&mut self, round: u8, winner: Option<Team>) {
+        self.push(TimelineEvent::RoundEnded { round, winner });
+    }
+
+    pub fn kill(&mut self, kill: &KillEvent) {
+        let first_blood = self.kills == 0;
+        self.kills += 1;
+        if self.kills > MAX_TIMELINE_KILLS {
+            return;
+        }
+        for info in [&kill.killer_info, &kill.victim_info] {
+            self.players.insert(info.id, info.clone());
+        }
+        self.push(TimelineEvent::Kill {
+            killer: kill.killer_info.id,
+            victim: kill.victim_info.id,
+            first_blood,
+        });
+    }
+
+    pub fn forfeit(&mut self, player: &PlayerInfo) {
+        self.players.insert(player.id, player.clone());
+        self.push(TimelineEvent::Forfeit(player.id));
+    }
+
+    pub fn summary(&self) -> MatchSummary {
+        MatchSummary {
+            players: self.players.values().cloned().collect(),
+            timeline: self.entries.clone(),
+            duration_ms: millis(self.elapsed),
+        }
+    }
+
+    fn push(&mut self, event: TimelineEvent) {
+        self.entries.push(TimelineEntry {
+            at_ms: millis(self.elapsed),
+            event,
+        });
+    }
+}
+
+fn millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kill(killer: PlayerId, victim: PlayerId) -> KillEvent {
+        KillEvent {
+            killer_info: PlayerInfo::new(killer, format!("P{}", killer), Team::Blue),
+            victim_info: PlayerInfo::new(victim, format!("P{}", victim), Team::Red),
+        }
+    }
+
+    #[test]
+    fn test_timeline_marks_first_blood_and_caps_kills() {
+        let mut timeline = MatchTimeline::default();
+        timeline.advance(Duration::from_secs(3));
+        timeline.round_started(1);
+        timeline.advance(Duration::from_millis(1500));
+        timeline.kill(&kill(0, 1));
+        timeline.kill(&kill(2, 3));
+        for _ in 0..MAX_TIMELINE_KILLS {
+            timeline.kill(&kill(0, 3));
+        }
+        timeline.round_ended(1, Some(Team::Blue));
+
+        let summary = timeline.summary();
+        assert_eq!(summary.duration_ms, 4500);
+        assert_eq!(summary.players.len(), 4);
+        assert_eq!(summary.timeline.len(), MAX_TIMELINE_KILLS + 2);
+        assert_eq!(
+            summary.timeline[1],
+            TimelineEntry {
+                at_ms: 4500,
+                event: TimelineEvent::Kill {
+                    killer: 0,
+                    victim: 1,
+                    first_blood: true
+                }
+            }
+        );
+        assert!(matches!(
+            summary.timeline[2].event,
+            TimelineEvent::Kill {
+                first_blood: false,
+                ..
+            }
+        ));
+        assert_eq!(
+            summary.timeline.last().unwrap().event,
+            TimelineEvent::RoundEnded {
+                round: 1,
+                winner: Some(Team::Blue)
+            }
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! World units. Positions, lengths and speeds in the engine are world units, not screen
+//! pixels. Every map says how many of them make a meter, while tanks and shots are sized
+//! in meters, so a map drawn bigger doesn't make tanks smaller and slower on it.
+
+use crate::net::protocol::MapDefinition;
+use bincode::Encode;
+use serde::Deserialize;
+
+/// World units per meter of the built-in maps, and of every size and speed the engine
+/// states in world units
+pub const DEFAULT_UNITS_PER_METER: f32 = 20.0;
+
+/// Sizes in meters and speeds in meters per second of what moves on a map.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Encode)]
+pub struct BodyDimensions {
+    pub tank_radius: f32,
+    pub tank_speed: f32,
+    pub projectile_radius: f32,
+    pub projectile_speed: f32,
+    pub grenade_radius: f32,
+    pub grenade_speed: f32,
+    /// Reach of a grenade's blast, measured to the edge of a tank
+    pub blast_radius: f32,
+}
+
+/// [`DEFAULT_UNITS_PER_METER`], for maps loaded from files that leave it out.
+pub(crate) fn default_units_per_meter() -> f32 {
+    DEFAULT_UNITS_PER_METER
+}
+
+/// `meters` in world units at the default scale.
+pub const fn default_units(meters: f32) -> f32 {
+    meters * DEFAULT_UNITS_PER_METER
+}
+
+impl MapDefinition {
+    /// How many times bigger than at the default scale everything on the map is drawn.
+    /// Sizes and speeds stated at the default scale are multiplied by this.
+    pub fn scale(&self) -> f32 {
+        self.units_per_meter / DEFAULT_UNITS_PER_METER
+    }
+
+    /// `meters` in the map's world units.
+    pub fn units(&self, meters: f32) -> f32 {
+        meters * self.units_per_meter
+    }
+
+    /// `units` of the map's world in meters.
+    pub fn meters(&self, units: f32) -> f32 {
+        units / self.units_per_meter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::balance::balance;
+    use crate::game::map::MapName;
+    use crate::game::player::PlayerInfo;
+    use crate::game::{CombatRules, apply_player_physics, handle_shooting};
+    use crate::net::protocol::{InputPayload, Tank, Team};
+    use glam::Vec2;
+
+    #[test]
+    fn test_maps_convert_between_meters_and_units() {
+        let mut map = MapDefinition::load_name(MapName::Basic);
+        assert_eq!(map.scale(), 1.0);
+        assert_eq!(map.units(0.75), default_units(0.75));
+
+        map.units_per_meter = 40.0;
+        assert_eq!(map.scale(), 2.0);
+        assert_eq!(map.units(1.5), 60.0);
+        assert_eq!(map.meters(60.0), 1.5);
+    }
+
+    #[test]
+    fn test_tanks_keep_their_size_and_speed_in_meters() {
+        let input = InputPayload {
+            move_axis: Vec2::X,
+            aim_pos: Vec2::new(1000.0, 300.0),
+            shoot: true,
+            grenade: false,
+        };
+        let meters_driven = |units_per_meter: f32| {
+            let mut map = MapDefinition::load_name(MapName::Basic);
+            map.units_per_meter = units_per_meter;
+            let info = PlayerInfo::new(0, "t".into(), Team::Blue);
+            let mut tank = Tank::new(info, Vec2::new(100.0, 300.0)).sized_for(&map);
+            let shot = handle_shooting(
+                &mut tank,
+                &input,
+                0.0,
+                0,
+                map.scale(),
+                &CombatRules::default(),
+            )
+            .unwrap();
+            let start = tank.position;
+            apply_player_physics(&mut tank, &input, &map, 0.1);
+            (
+                map.meters(tank.radius),
+                map.meters(tank.position.distance(start)),
+                map.meters(shot.velocity.length()),
+            )
+        };
+        let bodies = balance().bodies;
+        let (radius, driven, shot_speed) = meters_driven(DEFAULT_UNITS_PER_METER);
+        assert_eq!(radius, bodies.tank_radius);
+        assert!((driven - bodies.tank_speed * 0.1).abs() < 1e-4);
+        assert_eq!(shot_speed, bodies.projectile_speed);
+
+        let (radius, driven, shot_speed) = meters_driven(2.0 * DEFAULT_UNITS_PER_METER);
+        assert_eq!(radius, bodies.tank_radius);
+        assert!((driven - bodies.tank_speed * 0.1).abs() < 1e-4);
+        assert_eq!(shot_speed, bodies.projectile_speed);
+    }
+}
@@ -0,0 +1,182 @@
+//! Upgrades players buy for their tanks between rounds, with points earned in battle.
+
+use crate::game::balance;
+use crate::net::protocol::{Loadout, Tank, Upgrade};
+
+pub const MAX_UPGRADE_LEVEL: u8 = 3;
+/// Points for killing an enemy; every point of damage dealt to enemies is worth one more.
+pub const KILL_POINTS: u32 = 50;
+/// Price of each level of an upgrade, the first one first
+const LEVEL_COSTS: [u32; MAX_UPGRADE_LEVEL as usize] = [100, 200, 300];
+
+impl Upgrade {
+    /// Every upgrade, in the order the shop lists them.
+    pub const ALL: [Upgrade; 3] = [Upgrade::MaxHealth, Upgrade::FireRate, Upgrade::Speed];
+
+    /// Share of the base stat that each level adds.
+    fn bonus_per_level(self) -> f32 {
+        match self {
+            Upgrade::MaxHealth => 0.2,
+            Upgrade::FireRate => 0.15,
+            Upgrade::Speed => 0.1,
+        }
+    }
+
+    /// Price of the next level for `loadout`, `None` once it is maxed out.
+    pub fn next_cost(self, loadout: &Loadout) -> Option<u32> {
+        LEVEL_COSTS.get(usize::from(loadout.level(self))).copied()
+    }
+}
+
+impl Loadout {
+    pub fn level(&self, upgrade: Upgrade) -> u8 {
+        match upgrade {
+            Upgrade::MaxHealth => self.max_health,
+            Upgrade::FireRate => self.fire_rate,
+            Upgrade::Speed => self.speed,
+        }
+    }
+
+    fn level_mut(&mut self, upgrade: Upgrade) -> &mut u8 {
+        match upgrade {
+            Upgrade::MaxHealth => &mut self.max_health,
+            Upgrade::FireRate => &mut self.fire_rate,
+            Upgrade::Speed => &mut self.speed,
+        }
+    }
+
+    /// Buys the next level of `upgrade` out of `points`. Returns false, spending nothing,
+    /// when it is maxed out or `points` don't cover it.
+    pub fn buy(&mut self, upgrade: Upgrade, points: &mut u32) -> bool {
+        match upgrade.next_cost(self) {
+            Some(cost) if cost <= *points => {
+                *points -= cost;
+                self.level_up(upgrade)
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds a level of `upgrade` without paying for it, the way a replay re-applies a
+    /// purchase. Returns false when it is maxed out.
+    pub fn level_up(&mut self, upgrade: Upgrade) -> bool {
+        let level = self.level_mut(upgrade);
+        if *level >= MAX_UPGRADE_LEVEL {
+            return false;
+        }
+        *level += 1;
+        true
+    }
+
+    /// Spends `points` on the cheapest upgrades left, the way bots shop. Upgrades of
+    /// the same price go in [`Upgrade::ALL`] order.
+    pub fn buy_cheapest(&mut self, points: &mut u32) {
+        while let Some(upgrade) = Upgrade::ALL
+            .into_iter()
+            .filter(|upgrade| upgrade.next_cost(self).is_some())
+            .min_by_key(|upgrade| upgrade.next_cost(self))
+        {
+            if !self.buy(upgrade, points) {
+                break;
+            }
+        }
+    }
+
+    /// How much `upgrade` multiplies its stat by.
+    fn multiplier(&self, upgrade: Upgrade) -> f32 {
+        1.0 + upgrade.bonus_per_level() * f32::from(self.level(upgrade))
+    }
+}
+
+impl Tank {
+    /// Sets the tank's stats for `loadout`, at full health.
+    pub fn apply_loadout(&mut self, loadout: &Loadout) {
+        let balance = balance();
+        self.max_health = balance.max_health * loadout.multiplier(Upgrade::MaxHealth);
+        self.health = self.max_health;
+        self.fire_interval = balance.fire_interval / loadout.multiplier(Upgrade::FireRate);
+        self.speed = balance.tank_speed() * loadout.multiplier(Upgrade::Speed);
+    }
+
+    /// The level of `upgrade` read back from the tank's stats, from 0.0 to
+    /// `MAX_UPGRADE_LEVEL`. Works for any tank in sight, not only the player's own.
+    pub fn upgrade_level(&self, upgrade: Upgrade) -> f32 {
+        let balance = balance();
+        let multiplier = match upgrade {
+            Upgrade::MaxHealth => self.max_health / balance.max_health,
+            Upgrade::FireRate => balance.fire_interval / self.fire_interval,
+            Upgrade::Speed => self.speed / balance.tank_speed(),
+        };
+        ((multiplier - 1.0) / upgrade.bonus_per_level()).clamp(0.0, f32::from(MAX_UPGRADE_LEVEL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::PlayerInfo;
+    use crate::net::protocol::Team;
+    use glam::Vec2;
+
+    #[test]
+    fn test_buying_levels() {
+        let mut loadout = Loadout::default();
+        let mut points = 250;
+        assert!(loadout.buy(Upgrade::Speed, &mut points));
+        assert_eq!((loadout.speed, points), (1, 150));
+
+        // The second level costs more than what is left
+        assert!(!loadout.buy(Upgrade::Speed, &mut points));
+        assert!(loadout.buy(Upgrade::FireRate, &mut points));
+        assert_eq!((loadout.fire_rate, points), (1, 50));
+
+        let mut points = 1000;
+        let mut maxed = Loadout::default();
+        for _ in 0..MAX_UPGRADE_LEVEL {
+            assert!(maxed.buy(Upgrade::MaxHealth, &mut points));
+        }
+        assert_eq!(Upgrade::MaxHealth.next_cost(&maxed), None);
+        assert!(!maxed.buy(Upgrade::MaxHealth, &mut points));
+        assert_eq!(points, 400);
+    }
+
+    #[test]
+    fn test_loadout_sets_tank_stats() {
+        let mut tank = Tank::new(PlayerInfo::new(0, "P0".into(), Team::Blue), Vec2::ZERO);
+        tank.health = 10.0;
+        tank.apply_loadout(&Loadout {
+            max_health: 1,
+            fire_rate: 0,
+            speed: 2,
+        });
+        assert!((tank.max_health - 120.0).abs() < 1e-3);
+        assert_eq!(tank.health, tank.max_health);
+        assert_eq!(tank.fire_interval, balance().fire_interval);
+        assert_eq!(tank.speed, balance().tank_speed() * 1.2);
+
+        assert!((tank.upgrade_level(Upgrade::MaxHealth) - 1.0).abs() < 1e-3);
+        assert_eq!(tank.upgrade_level(Upgrade::FireRate), 0.0);
+        assert!((tank.upgrade_level(Upgrade::Speed) - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_buying_cheapest_first() {
+        let mut loadout = Loadout::default();
+        let mut points = 450;
+        loadout.buy_cheapest(&mut points);
+        // Every first level, then nothing: a second level costs more than the 150 left
+        assert_eq!(
+            loadout,
+            Loadout {
+                max_health: 1,
+                fire_rate: 1,
+                speed: 1,
+            }
+        );
+        assert_eq!(points, 150);
+
+        points += 50;
+        loadout.buy_cheapest(&mut points);
+        assert_eq!((loadout.max_health, points), (2, 0));
+    }
+}
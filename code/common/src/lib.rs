@@ -1,7 +1,13 @@
 pub mod ai;
+pub mod crash;
 pub mod game;
 pub mod net;
 pub mod rl;
+pub mod version;
 
 pub use net::codec;
 pub use net::protocol;
+pub use net::relay;
+pub use net::status;
+pub use net::transfer;
+pub use version::version;
@@ -33,13 +33,20 @@ pub fn decode_server_message(bytes: &[u8]) -> Result<ServerMessage, DecodeError>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::protocol::{API_VERSION, HandshakeResponse, MapName};
+    use crate::ai::BotDifficulty;
+    use crate::protocol::{
+        API_VERSION, FiringMode, GameMode, HandshakeResponse, MapName, SessionToken,
+    };
 
     #[test]
     fn client_message_handshake_roundtrip() {
         let original = ClientMessage::Handshake {
             api_version: API_VERSION,
             nickname: "TestPlayer".to_string(),
+            color: Some(crate::game::player::TankColor::new(255, 200, 0)),
+            build: crate::version().into(),
+            balance_hash: 7,
+            session: Some(SessionToken(42)),
         };
         let encoded = encode_client_message(&original).unwrap();
         let decoded = decode_client_message(&encoded).unwrap();
@@ -51,6 +58,13 @@ mod tests {
         let original = ClientMessage::CreateGame {
             map: MapName::Basic,
             rounds: 5,
+            mode: GameMode::MultiArena,
+            team_size: 4,
+            intermission: true,
+            firing: FiringMode::Heat,
+            bot_difficulty: BotDifficulty::TrainedKiller,
+            bot_count: Some(3),
+            bot_model: Some("champion".to_string()),
         };
         let encoded = encode_client_message(&original).unwrap();
         let decoded = decode_client_message(&encoded).unwrap();
@@ -59,7 +73,11 @@ mod tests {
 
     #[test]
     fn server_message_handshake_response_roundtrip() {
-        let original = ServerMessage::HandshakeResponse(HandshakeResponse::Ok);
+        let original = ServerMessage::HandshakeResponse(HandshakeResponse::Ok {
+            build: crate::version().into(),
+            session: SessionToken(42),
+            resumed: None,
+        });
         let encoded = encode_server_message(&original).unwrap();
         let decoded = decode_server_message(&encoded).unwrap();
         assert_eq!(original, decoded);
@@ -1,2 +1,5 @@
 pub mod codec;
 pub mod protocol;
+pub mod relay;
+pub mod status;
+pub mod transfer;
@@ -0,0 +1,195 @@
+//! Snapshots sent as the difference from one the client already has, so a quiet tick costs
+//! little more than its header.
+
+use super::objects::{
+    ClientId, EngineSnapshot, GameSnapshot, GameState, MovingWallState, PlayerId, Projectile, Tank,
+};
+use bincode::{Decode, Encode};
+
+/// What changed between the snapshot of tick `baseline` and a newer one.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SnapshotDelta {
+    /// Tick of the snapshot this one is built on
+    pub baseline: u32,
+    /// Tanks that are new or differ from the baseline's
+    pub tanks: Vec<Tank>,
+    pub removed_tanks: Vec<PlayerId>,
+    /// Projectiles that are new or differ from the baseline's
+    pub projectiles: Vec<Projectile>,
+    pub removed_projectiles: Vec<u64>,
+    /// Sent whole; there are few of them
+    pub moving_walls: Vec<MovingWallState>,
+    pub state: GameState,
+    pub game_master: ClientId,
+    pub host: Option<PlayerId>,
+    pub round_number: u8,
+}
+
+impl SnapshotDelta {
+    /// The changes from `base`, the snapshot of tick `baseline`, to `snapshot`. `None` if
+    /// applying them wouldn't give `snapshot` exactly, e.g. when things changed order;
+    /// then the whole snapshot has to be sent.
+    pub fn between(base: &GameSnapshot, baseline: u32, snapshot: &GameSnapshot) -> Option<Self> {
+        let (old, new) = (&base.engine, &snapshot.engine);
+        let delta = Self {
+            baseline,
+            tanks: changed(&old.tanks, &new.tanks, tank_id),
+            removed_tanks: removed(&old.tanks, &new.tanks, tank_id),
+            projectiles: changed(&old.projectiles, &new.projectiles, |p| p.id),
+            removed_projectiles: removed(&old.projectiles, &new.projectiles, |p| p.id),
+            moving_walls: new.moving_walls.clone(),
+            state: snapshot.state.clone(),
+            game_master: snapshot.game_master,
+            host: snapshot.host,
+            round_number: snapshot.round_number,
+        };
+        (delta.apply(base) == *snapshot).then_some(delta)
+    }
+
+    /// The snapshot these changes lead to from `base`, the snapshot of tick `baseline`.
+    pub fn apply(&self, base: &GameSnapshot) -> GameSnapshot {
+        GameSnapshot {
+            engine: EngineSnapshot {
+                tanks: patched(
+                    &base.engine.tanks,
+                    &self.tanks,
+                    &self.removed_tanks,
+                    tank_id,
+                ),
+                projectiles: patched(
+                    &base.engine.projectiles,
+                    &self.projectiles,
+                    &self.removed_projectiles,
+                    |p| p.id,
+                ),
+                moving_walls: self.moving_walls.clone(),
+            },
+            state: self.state.clone(),
+            game_master: self.game_master,
+            host: self.host,
+            round_number: self.round_number,
+        }
+    }
+}
+
+fn tank_id(tank: &Tank) -> PlayerId {
+    tank.player_info.id
+}
+
+/// The entries of `new` that aren't in `old` as they are.
+fn changed<T: Clone + PartialEq, K: PartialEq>(
+    old: &[T],
+    new: &[T],
+    id: impl Fn(&T) -> K,
+) -> Vec<T> {
+    new.iter()
+        .filter(|entry| !old.iter().any(|o| id(o) == id(entry) && o == *entry))
+        .cloned()
+        .collect()
+}
+
+/// The ids of the entries of `old` gone from `new`.
+fn removed<T, K: PartialEq>(old: &[T], new: &[T], id: impl Fn(&T) -> K) -> Vec<K> {
+    old.iter()
+        .filter(|entry| !new.iter().any(|n| id(n) == id(entry)))
+        .map(&id)
+        .collect()
+}
+
+/// `old` without the `removed` entries, with `changed` ones replaced in place and new ones
+/// at the end.
+fn patched<T: Clone, K: PartialEq>(
+    old: &[T],
+    changed: &[T],
+    removed: &[K],
+    id: impl Fn(&T) -> K,
+) -> Vec<T> {
+    let mut entries: Vec<T> = old
+        .iter()
+        .filter(|entry| !removed.contains(&id(entry)))
+        .map(|entry| {
+            changed
+                .iter()
+                .find(|c| id(c) == id(entry))
+                .unwrap_or(entry)
+                .clone()
+        })
+        .collect();
+    entries.extend(
+        changed
+            .iter()
+            .filter(|c| !old.iter().any(|o| id(o) == id(c)))
+            .cloned(),
+    );
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::PlayerInfo;
+    use crate::net::protocol::{ProjectileKind, Team};
+    use glam::Vec2;
+
+    fn snapshot(tanks: Vec<Tank>, projectiles: Vec<Projectile>) -> GameSnapshot {
+        GameSnapshot {
+            engine: EngineSnapshot {
+                tanks,
+                projectiles,
+                moving_walls: Vec::new(),
+            },
+            state: GameState::Battle(60),
+            game_master: 1,
+            host: Some(0),
+            round_number: 1,
+        }
+    }
+
+    fn tank(id: PlayerId, x: f32) -> Tank {
+        Tank::new(
+            PlayerInfo::new(id, format!("p{}", id), Team::Blue),
+            Vec2::new(x, 100.),
+        )
+    }
+
+    fn projectile(id: u64, x: f32) -> Projectile {
+        Projectile {
+            id,
+            kind: ProjectileKind::Bullet,
+            owner_info: PlayerInfo::new(0, "p0".into(), Team::Blue),
+            position: Vec2::new(x, 50.),
+            velocity: Vec2::X,
+            radius: 3.,
+            age: 0.,
+        }
+    }
+
+    #[test]
+    fn test_deltas_carry_only_what_changed() {
+        let base = snapshot(
+            vec![tank(0, 10.), tank(1, 20.), tank(2, 30.)],
+            vec![projectile(5, 0.), projectile(6, 0.)],
+        );
+        let new = snapshot(
+            vec![tank(0, 10.), tank(1, 25.), tank(3, 40.)],
+            vec![projectile(6, 5.), projectile(7, 0.)],
+        );
+        let delta = SnapshotDelta::between(&base, 9, &new).unwrap();
+        assert_eq!(delta.baseline, 9);
+        assert_eq!(delta.tanks, vec![tank(1, 25.), tank(3, 40.)]);
+        assert_eq!(delta.removed_tanks, vec![2]);
+        assert_eq!(
+            delta.projectiles,
+            vec![projectile(6, 5.), projectile(7, 0.)]
+        );
+        assert_eq!(delta.removed_projectiles, vec![5]);
+        assert_eq!(delta.apply(&base), new);
+    }
+
+    #[test]
+    fn test_reordered_entries_need_the_whole_snapshot() {
+        let base = snapshot(vec![tank(0, 10.), tank(1, 20.)], Vec::new());
+        let new = snapshot(vec![tank(1, 20.), tank(0, 10.)], Vec::new());
+        assert!(SnapshotDelta::between(&base, 0, &new).is_none());
+    }
+}
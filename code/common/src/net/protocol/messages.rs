@@ -1,11 +1,20 @@
 pub use renet::ClientId;
 
+use crate::ai::BotDifficulty;
+use crate::game::player::TankColor;
 use crate::protocol::InitialGameInfo;
 
-use super::objects::{GameSnapshot, InputPayload, KillEvent, MapName, Team};
+use super::delta::SnapshotDelta;
+use super::objects::{
+    CloseReason, Explosion, FiringMode, GameMode, GameSnapshot, InputPayload, KillEvent, MapName,
+    MatchSummary, ModelChunk, ModelHash, Ping, PingKind, PlayerId, PlayerStats, ShopBalance, Team,
+    TournamentInfo, Upgrade,
+};
 use bincode::{Decode, Encode};
+use glam::Vec2;
+use thiserror::Error;
 
-pub const API_VERSION: ApiVersion = 8;
+pub const API_VERSION: ApiVersion = 42;
 
 /// Messages from Client -> Server
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
@@ -13,18 +22,70 @@ pub enum ClientMessage {
     Handshake {
         api_version: ApiVersion,
         nickname: String,
+        /// Trim for the player's tanks in every game they join
+        color: Option<TankColor>,
+        /// The client's [`crate::version()`]
+        build: String,
+        /// [`BalanceConfig::content_hash`](crate::game::balance::BalanceConfig::content_hash)
+        /// of the client's balance table, which has to match the server's
+        balance_hash: u64,
+        /// The token of the client's last session on this server, to get back into the
+        /// match it dropped out of
+        session: Option<SessionToken>,
     },
     CreateGame {
         map: MapName,
         rounds: u8,
+        /// `rounds` is ignored in multi-arena games, which are one qualifying round and a
+        /// final
+        mode: GameMode,
+        /// Tanks per team, 1 to `MAX_TEAM_SIZE`; bots fill the places humans don't take.
+        /// Ignored in multi-arena games, which have squads of four
+        team_size: u8,
+        /// Lets players buy upgrades between rounds; ignored in multi-arena and co-op games
+        intermission: bool,
+        firing: FiringMode,
+        /// How the bots filling the teams play; co-op waves keep their own
+        bot_difficulty: BotDifficulty,
+        /// Most bots in the game; `None` fills every place humans don't take
+        bot_count: Option<u8>,
+        /// A model from [`ServerMessage::ModelList`] to drive the bots, with
+        /// [`BotDifficulty::TrainedKiller`]; `None` uses the built-in one
+        bot_model: Option<String>,
     },
     JoinGame {
         game_code: GameCode,
     },
     LeaveGame,
     StartCountdown,
-    /// Player input for the current game tick
-    GameInput(InputPayload),
+    /// Sign up for the server's tournament
+    JoinTournament,
+    /// Withdraw from the tournament, forfeiting any remaining matches
+    LeaveTournament,
+    /// Player input for the current game tick. `seq` goes up by one with every input
+    /// of a game, and is echoed back in [`GameUpdate::input_acks`] once processed; an
+    /// input numbered no higher than the last one is dropped as stale.
+    GameInput {
+        seq: u32,
+        input: InputPayload,
+        /// [`GameUpdate::tick`] of the latest snapshot the client has, which the server
+        /// sends the next ones as changes from; `None` asks for whole snapshots
+        snapshot: Option<u32>,
+    },
+    /// Download the bot model of the current game; answered by its chunks
+    RequestModel(ModelHash),
+    /// The bot models games can be created with; answered by [`ServerMessage::ModelList`]
+    ListModels,
+    /// Spend points on the next level of an upgrade; only taken during an intermission,
+    /// and answered with [`GameEvent::Shop`] if it went through
+    BuyUpgrade(Upgrade),
+    /// Point the player's team at a spot; never answered, and ignored within
+    /// [`PING_COOLDOWN`](super::objects::PING_COOLDOWN) of the player's last ping
+    Ping {
+        kind: PingKind,
+        #[bincode(with_serde)]
+        world_pos: Vec2,
+    },
 }
 
 /// Messages from Server -> Client
@@ -36,13 +97,62 @@ pub enum ServerMessage {
     LeaveGameAck,
     StartCountdownAck,
     GameUpdate(GameUpdate),
-    Error(String),
+    /// A [`GameUpdate`] whose snapshot is given as changes from one the client has
+    GameDelta(GameDelta),
+    TournamentJoinResponse(TournamentJoinResponse),
+    TournamentLeaveAck,
+    /// Bracket or standings changed; sent to every entrant
+    TournamentUpdate(TournamentInfo),
+    /// The server put the entrant into the lobby of their next match
+    TournamentMatch(InitialGameInfo),
+    ModelChunk(ModelChunk),
+    /// The requested model is not used by the client's game
+    ModelUnavailable(ModelHash),
+    /// Names of the server's bot models, sorted
+    ModelList(Vec<String>),
+    Error(ServerError),
 }
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct GameUpdate {
+    /// Numbers the snapshots of every game the server runs, going up by one a tick
+    pub tick: u32,
     pub snapshot: GameSnapshot,
     pub events: Vec<GameEvent>,
+    /// The `seq` of the last input processed for each of the recipients' players
+    pub input_acks: Vec<(PlayerId, u32)>,
+}
+
+/// A [`GameUpdate`] with the snapshot as changes from an earlier one.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct GameDelta {
+    pub tick: u32,
+    pub delta: SnapshotDelta,
+    pub events: Vec<GameEvent>,
+    pub input_acks: Vec<(PlayerId, u32)>,
+}
+
+impl GameDelta {
+    /// `update` as changes from `base`, the snapshot of tick `baseline`; `None` if it has
+    /// to be sent whole.
+    pub fn of(update: &GameUpdate, base: &GameSnapshot, baseline: u32) -> Option<Self> {
+        Some(Self {
+            tick: update.tick,
+            delta: SnapshotDelta::between(base, baseline, &update.snapshot)?,
+            events: update.events.clone(),
+            input_acks: update.input_acks.clone(),
+        })
+    }
+
+    /// The update, given the snapshot of tick `self.delta.baseline`.
+    pub fn apply(self, base: &GameSnapshot) -> GameUpdate {
+        GameUpdate {
+            tick: self.tick,
+            snapshot: self.delta.apply(base),
+            events: self.events,
+            input_acks: self.input_acks,
+        }
+    }
 }
 
 /// One-shot events for the UI/Audio (not persistent state)
@@ -50,17 +160,76 @@ pub struct GameUpdate {
 pub enum GameEvent {
     PlayerJoined(String),
     PlayerLeft(String),
+    /// A player left a match under way; a bot plays on in their place.
+    PlayerForfeited(String),
+    /// A player's connection dropped in a match under way; a bot plays for them until
+    /// they are back or [`REJOIN_GRACE`](super::objects::REJOIN_GRACE) runs out.
+    PlayerDisconnected(String),
+    /// A player who dropped out took their tank back from the bot.
+    PlayerRejoined(String),
+    /// The pre-round countdown began; scores are the rounds won so far.
+    CountdownStarted {
+        round: u8,
+        blue_score: u8,
+        red_score: u8,
+    },
     RoundStarted,
-    RoundEnded(Team),
+    /// The round's winner, `None` when it was drawn.
+    RoundEnded(Option<Team>),
+    /// One arena of a multi-arena game is over; the final arena's winner wins the match.
+    ArenaDecided {
+        arena: u8,
+        winner: Team,
+    },
     Kill(KillEvent),
+    Explosion(Explosion),
+    /// Everyone's shots so far this match; sent every second of battle and before a round
+    /// ends.
+    MatchStats(Vec<PlayerStats>),
+    /// The match is over; sent once, right after its last round ended.
+    MatchEnded(MatchSummary),
+    /// A teammate's ping; only their team gets it.
+    Ping(Ping),
+    /// Everyone's points and upgrades; sent as an intermission starts and after every
+    /// purchase.
+    Shop(Vec<ShopBalance>),
+    /// Co-op: a wave of `enemies` bots entered the map.
+    WaveStarted {
+        wave: u8,
+        enemies: u8,
+    },
+    /// Co-op: the last bot of the wave fell; the next one comes after a short break.
+    WaveCleared(u8),
+    /// Co-op: the human team fell after clearing `waves` waves in `seconds` seconds.
+    Survived {
+        waves: u8,
+        seconds: u32,
+    },
+    /// The server closes the game in `seconds` unless something changes.
+    ClosingSoon {
+        reason: CloseReason,
+        seconds: u32,
+    },
+    /// The server closed the game; its players are back in the lobby.
+    Closed(CloseReason),
 }
 
 // Change the error types to enum if needed
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub enum HandshakeResponse {
-    Ok,
+    /// `build` is the server's [`crate::version()`], which may differ from the client's
+    /// even when the protocol matches
+    Ok {
+        build: String,
+        /// Identifies the client, to rejoin with after a dropped connection
+        session: SessionToken,
+        /// The match the client dropped out of, which it is back in
+        resumed: Option<InitialGameInfo>,
+    },
     ApiMismatch,
+    /// The client's balance table differs from the server's
+    BalanceMismatch,
     ServerFull,
 }
 
@@ -73,11 +242,59 @@ pub enum CreateGameResponse {
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub enum JoinGameResponse {
     Ok(InitialGameInfo),
-    InvalidCode,
+    /// No game has the code the client gave
+    InvalidCode(GameCode),
     GameFull,
     GameStarted,
 }
 
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum TournamentJoinResponse {
+    Ok(TournamentInfo),
+    NoTournament,
+    /// Sign-up is over or the bracket is full
+    Closed,
+}
+
+/// Why the server turned down a message. Clients put it into words themselves, in their
+/// player's language; the messages here are for the server's log.
+#[derive(Debug, Clone, PartialEq, Error, Encode, Decode)]
+pub enum ServerError {
+    #[error("unknown sender")]
+    UnknownSender,
+    #[error("client already connected")]
+    AlreadyConnected,
+    #[error("message not allowed in the client's state")]
+    InvalidMessage,
+    #[error("game {} does not exist", .0.0)]
+    NoSuchGame(GameCode),
+    #[error("game is full")]
+    GameFull,
+    #[error("player not found in game")]
+    NotInGame,
+    #[error("only the game master can start the countdown")]
+    NotGameMaster,
+    #[error("game is not in waiting state")]
+    NotWaiting,
+    #[error("team size {requested} is not 1 to {max}")]
+    TeamSize { requested: u8, max: u8 },
+    #[error("no tournament on this server")]
+    NoTournament,
+    #[error("not signed up for the tournament")]
+    NotInTournament,
+    #[error("unknown model {0}")]
+    UnknownModel(String),
+    #[error("model {0} failed to load")]
+    BrokenModel(String),
+    #[error("model {0} needs trained bots")]
+    ModelNeedsTrainedBots(String),
+}
+
+/// Given to a client on its handshake; a client whose connection dropped presents it again
+/// to reclaim its place in the match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
+pub struct SessionToken(pub u64);
+
 /// Human–facing lobby code used to join games.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Encode, Decode)]
 pub struct GameCode(pub String);
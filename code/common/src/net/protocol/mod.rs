@@ -1,5 +1,7 @@
+pub mod delta;
 pub mod messages;
 pub mod objects;
 
+pub use delta::*;
 pub use messages::*;
 pub use objects::*;
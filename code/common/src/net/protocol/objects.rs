@@ -9,7 +9,7 @@ use crate::{game::player::PlayerInfo, protocol::GameCode};
 
 pub type PlayerId = u16;
 
-#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode, Deserialize)]
 pub struct RectWall {
     #[bincode(with_serde)]
     pub min: Vec2,
@@ -17,10 +17,12 @@ pub struct RectWall {
     pub max: Vec2,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode, Serialize, Deserialize)]
 pub enum Team {
     Blue,
     Red,
+    /// Free-for-all: every tank fights alone, on a team of its own player id
+    Ffa(PlayerId),
 }
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
@@ -32,20 +34,46 @@ pub struct Tank {
     pub velocity: Vec2,
     pub rotation: f32,
     pub radius: f32,
+    /// World units per second on a map at the default scale
     pub speed: f32,
+    /// Radians per second the tank turns towards its aim
+    pub turn_rate: f32,
     pub health: f32,
+    pub max_health: f32,
+    /// Seconds between shots
+    pub fire_interval: f32,
     pub weapon_cooldown: f32,
+    pub grenade_cooldown: f32,
+    /// Seconds until the tank can go through a teleporter again
+    pub teleport_cooldown: f32,
+    /// How hot the gun is, from 0 to 1; only shooting with [`FiringMode::Heat`] heats it
+    pub heat: f32,
+    /// The gun reached full heat and won't fire until it has cooled down completely
+    pub overheated: bool,
+    /// Share of the damage its front armor stops; see [`armor_factor`](crate::game::armor_factor)
+    pub front_armor: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub enum ProjectileKind {
+    #[default]
+    Bullet,
+    /// Explodes on impact or when its fuse runs out, hurting every tank around
+    Grenade,
 }
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct Projectile {
     pub id: u64,
+    pub kind: ProjectileKind,
     pub owner_info: PlayerInfo,
     #[bincode(with_serde)]
     pub position: Vec2,
     #[bincode(with_serde)]
     pub velocity: Vec2,
     pub radius: f32,
+    /// Seconds since it was fired
+    pub age: f32,
 }
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode, Default)]
@@ -55,18 +83,161 @@ pub struct InputPayload {
     #[bincode(with_serde)]
     pub aim_pos: Vec2,
     pub shoot: bool,
+    pub grenade: bool,
+}
+
+/// Players may ping at most once per this many seconds.
+pub const PING_COOLDOWN: f32 = 2.0;
+
+/// Seconds a player whose connection dropped mid-match has to come back before their
+/// tank is the bot's for good.
+pub const REJOIN_GRACE: f32 = 60.0;
+
+/// What a player asks their team to do at a spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum PingKind {
+    Attack,
+    Defend,
+    Help,
+}
+
+/// A spot on the map a player pointed their team at.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Ping {
+    pub player: PlayerId,
+    pub kind: PingKind,
+    #[bincode(with_serde)]
+    pub world_pos: Vec2,
 }
 
+/// A grenade going off, for effects on clients.
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Explosion {
+    #[bincode(with_serde)]
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+/// A map file may leave out everything after `spawn_points`, see
+/// [`MapDefinition::from_file`].
+#[derive(Debug, Clone, PartialEq, Encode, Decode, Deserialize)]
 pub struct MapDefinition {
     pub width: f32,
     pub height: f32,
+    /// World units in a meter; tanks and shots are sized in meters, see
+    /// [`units`](crate::game::units)
+    #[serde(default = "crate::game::units::default_units_per_meter")]
+    pub units_per_meter: f32,
     pub walls: Vec<RectWall>,
     #[bincode(with_serde)]
     pub spawn_points: Vec<(Team, Vec2)>,
+    /// Separate battlefields of a multi-arena map; empty when the whole map is one
+    #[serde(default)]
+    pub arenas: Vec<Arena>,
+    #[serde(default)]
+    pub teleporters: Vec<Teleporter>,
+    #[serde(default)]
+    pub one_way_walls: Vec<OneWayWall>,
+    #[serde(default)]
+    pub moving_walls: Vec<MovingWall>,
+}
+
+/// A pair of pads: a tank driving onto either one comes out on the other.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode, Deserialize)]
+pub struct Teleporter {
+    #[bincode(with_serde)]
+    pub a: Vec2,
+    #[bincode(with_serde)]
+    pub b: Vec2,
+}
+
+/// A wall that slides open and closed, like a door.
+#[derive(Debug, Clone, PartialEq, Encode, Decode, Deserialize)]
+pub struct MovingWall {
+    /// Where it stands when closed
+    pub closed: RectWall,
+    /// How far it slides to open
+    #[bincode(with_serde)]
+    pub open_offset: Vec2,
+    /// Seconds a full slide takes
+    pub slide_time: f32,
+    pub schedule: WallSchedule,
+    /// Where the wall is now; changes during a match
+    #[serde(default)]
+    pub state: MovingWallState,
+}
+
+/// When a [`MovingWall`] opens.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode, Deserialize)]
+pub enum WallSchedule {
+    /// Closed for `closed` seconds, then open for `open` seconds, over and over
+    Timer { closed: f32, open: f32 },
+    /// Opens for the rest of the round once tanks have stood within `radius` of `point`
+    /// for `hold` seconds in a row
+    Capture {
+        #[bincode(with_serde)]
+        point: Vec2,
+        radius: f32,
+        hold: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Encode, Decode, Deserialize)]
+pub struct MovingWallState {
+    /// From 0 when closed to 1 when open
+    pub openness: f32,
+    /// Seconds into the timer's cycle, or the capture point has been held
+    pub progress: f32,
+    /// Whether it opens, or stays open, rather than closes
+    pub opening: bool,
+}
+
+/// A passage that tanks and projectiles only cross in `direction`; from the other side it
+/// is a wall. It doesn't block the view.
+#[derive(Debug, Clone, PartialEq, Encode, Decode, Deserialize)]
+pub struct OneWayWall {
+    pub wall: RectWall,
+    /// Unit vector of the way through
+    #[bincode(with_serde)]
+    pub direction: Vec2,
+}
+
+/// A walled-off part of a multi-arena map; tanks only fight others in the same arena.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode, Deserialize)]
+pub struct Arena {
+    #[bincode(with_serde)]
+    pub min: Vec2,
+    #[bincode(with_serde)]
+    pub max: Vec2,
+}
+
+#[derive(EnumIter, Copy, Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub enum GameMode {
+    /// Red against Blue on one map, best of N rounds
+    #[default]
+    TeamDeathmatch,
+    /// 4v4 battles in separate arenas of one big map; the winners meet in the final arena
+    MultiArena,
+    /// Every tank for itself; the last one standing wins the round and kills decide the
+    /// match
+    FreeForAll,
+    /// Humans, with bots filling up their team, hold out against ever stronger waves of
+    /// scripted bots; how long they survive is their score
+    Coop,
 }
 
-#[derive(EnumIter, Copy, Clone, Debug, PartialEq, Eq, Encode, Decode)]
+/// What keeps tanks from firing nonstop.
+#[derive(EnumIter, Copy, Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub enum FiringMode {
+    /// A fixed wait after every shot, shortened by the fire rate upgrade
+    #[default]
+    Cooldown,
+    /// Shots come twice as fast but heat the gun up; an overheated gun only fires again
+    /// once it has cooled down completely
+    Heat,
+}
+
+#[derive(EnumIter, Copy, Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
 pub enum MapName {
     Basic,
     Loss,
@@ -78,6 +249,8 @@ pub struct GameSnapshot {
     pub engine: EngineSnapshot,
     pub state: GameState,
     pub game_master: ClientId,
+    /// The game master's tank; `None` in games the server runs itself
+    pub host: Option<PlayerId>,
     pub round_number: u8,
 }
 
@@ -85,31 +258,201 @@ pub struct GameSnapshot {
 pub struct EngineSnapshot {
     pub tanks: Vec<Tank>,
     pub projectiles: Vec<Projectile>,
+    /// State of every moving wall of the map, in the map's order
+    pub moving_walls: Vec<MovingWallState>,
+}
+
+/// Why the server closes a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum CloseReason {
+    /// Nobody joined, left, moved or shot for too long
+    Idle,
+    /// Nobody came back after the last player left
+    Empty,
+    /// The lobby wasn't started in time
+    LobbyExpired,
 }
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub enum GameState {
     Waiting,
     Countdown(u64),
+    /// Seconds left; in co-op, seconds survived so far
     Battle(u64),
+    /// Seconds left to buy upgrades before the next round's countdown
+    Intermission(u64),
+    /// In free-for-all, `winner` is the kill leader, `blue_score` their kills and
+    /// `red_score` the runner-up's. In co-op, `winner` is Red, the waves, and `blue_score`
+    /// the waves cleared. `winner` is `None` when both teams won as many rounds
     Results {
-        winner: Team,
+        winner: Option<Team>,
         blue_score: u8,
         red_score: u8,
     },
 }
 
+/// Something a player can buy for their tank during an intermission.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum Upgrade {
+    MaxHealth,
+    FireRate,
+    Speed,
+}
+
+/// Levels of the upgrades a player bought, each up to
+/// [`MAX_UPGRADE_LEVEL`](crate::game::upgrades::MAX_UPGRADE_LEVEL).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub struct Loadout {
+    pub max_health: u8,
+    pub fire_rate: u8,
+    pub speed: u8,
+}
+
+/// Points a player has left to spend, and what they bought with the rest.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ShopBalance {
+    pub player: PlayerId,
+    pub points: u32,
+    pub loadout: Loadout,
+}
+
+/// How a player has shot so far in a match.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct PlayerStats {
+    pub player_info: PlayerInfo,
+    pub shots_fired: u32,
+    /// Shots that hit any tank
+    pub shots_hit: u32,
+    /// Damage dealt to enemies
+    pub damage_dealt: f32,
+}
+
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct KillEvent {
     pub killer_info: PlayerInfo,
     pub victim_info: PlayerInfo,
 }
 
+/// A key moment of a match, `at_ms` milliseconds after its first countdown started.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct TimelineEntry {
+    pub at_ms: u32,
+    pub event: TimelineEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum TimelineEvent {
+    RoundStarted(u8),
+    /// `first_blood` marks the first kill of the match
+    Kill {
+        killer: PlayerId,
+        victim: PlayerId,
+        first_blood: bool,
+    },
+    /// `winner` is `None` when the round was drawn
+    RoundEnded {
+        round: u8,
+        winner: Option<Team>,
+    },
+    /// The player left before the match was over
+    Forfeit(PlayerId),
+}
+
+/// How a match went, sent once it is over for the winner screen.
+#[derive(Debug, Clone, PartialEq, Default, Encode, Decode)]
+pub struct MatchSummary {
+    /// Everyone the timeline mentions, so it can name them
+    pub players: Vec<PlayerInfo>,
+    pub timeline: Vec<TimelineEntry>,
+    /// Length of the match, in milliseconds
+    pub duration_ms: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct InitialGameInfo {
     pub game_code: GameCode,
     pub player_id: PlayerId,
     pub num_rounds: u8,
     pub map_name: MapName,
+    pub mode: GameMode,
+    /// Tanks per team
+    pub team_size: u8,
     pub game_master: ClientId,
+    /// The model driving this game's bots, which clients may download
+    pub bot_model: Option<ModelInfo>,
+    /// Seeds the game's bots, so the match can be reproduced
+    pub seed: u64,
+    /// Where the game was when the player got in, e.g. counting down, so the client
+    /// shows it right away instead of after the first update
+    pub state: GameState,
+    pub round: u8,
+}
+
+/// SHA-256 of a model file, which identifies it in transfers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
+pub struct ModelHash(pub [u8; 32]);
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ModelInfo {
+    /// File name on the server, without `.bin`
+    pub name: String,
+    pub hash: ModelHash,
+    /// In bytes
+    pub size: u32,
+}
+
+/// One piece of a model file; pieces are sent in order.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ModelChunk {
+    pub hash: ModelHash,
+    pub index: u32,
+    pub count: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct TournamentInfo {
+    pub name: String,
+    pub team_size: u8,
+    /// Rounds played in every match
+    pub rounds: u8,
+    pub state: TournamentState,
+    /// Matches whose entrants are known, in bracket order
+    pub matches: Vec<TournamentMatchInfo>,
+    pub standings: Vec<TournamentStanding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum TournamentState {
+    SignUp {
+        seconds_left: u64,
+        entrants: u8,
+        capacity: u8,
+    },
+    Running {
+        bracket_round: u8,
+    },
+    Finished {
+        champion: String,
+    },
+    /// Fewer than two entrants signed up
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct TournamentMatchInfo {
+    /// 1-based, the final being the last
+    pub bracket_round: u8,
+    /// The first entrant plays Blue
+    pub entrants: [String; 2],
+    pub map: MapName,
+    /// Index into `entrants`, once decided
+    pub winner: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct TournamentStanding {
+    pub name: String,
+    pub wins: u8,
+    pub eliminated: bool,
 }
@@ -0,0 +1,123 @@
+//! Wire format between the `relay` binary and the servers it relays for.
+//!
+//! A server behind NAT keeps sending [`RelayPacket::Register`] to the relay, which opens a
+//! room: a public UDP port that players connect to as if it were the server. Netcode packets
+//! arriving at the room travel to the server wrapped in [`RelayPacket::Data`] along with the
+//! player's address, and the server's answers come back the same way. The packets
+//! themselves are forwarded untouched.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+pub const RELAY_MAGIC: [u8; 4] = *b"NBRL";
+/// Default port of a relay's control socket.
+pub const DEFAULT_RELAY_PORT: u16 = 7000;
+/// How often a server renews its room.
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Rooms whose server stopped renewing them are closed after this long.
+pub const ROOM_TIMEOUT: Duration = Duration::from_secs(10);
+
+const REGISTER: u8 = 0;
+const REGISTERED: u8 = 1;
+const DATA: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayPacket<'a> {
+    /// Server to relay: open or keep open my room
+    Register,
+    /// Relay to server: your room is on this port
+    Registered { port: u16 },
+    /// A packet from (to the relay) or for (to the server) the player at `peer`
+    Data { peer: SocketAddr, payload: &'a [u8] },
+}
+
+impl<'a> RelayPacket<'a> {
+    /// Appends the packet to `buf`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend(RELAY_MAGIC);
+        match self {
+            RelayPacket::Register => buf.push(REGISTER),
+            RelayPacket::Registered { port } => {
+                buf.push(REGISTERED);
+                buf.extend(port.to_le_bytes());
+            }
+            RelayPacket::Data { peer, payload } => {
+                buf.push(DATA);
+                match peer.ip() {
+                    IpAddr::V4(ip) => {
+                        buf.push(4);
+                        buf.extend(ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        buf.push(6);
+                        buf.extend(ip.octets());
+                    }
+                }
+                buf.extend(peer.port().to_le_bytes());
+                buf.extend_from_slice(payload);
+            }
+        }
+    }
+
+    /// The packet in `bytes`, `None` for anything that isn't a relay packet.
+    pub fn decode(bytes: &'a [u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(&RELAY_MAGIC)?;
+        let (&kind, rest) = rest.split_first()?;
+        match kind {
+            REGISTER => Some(RelayPacket::Register),
+            REGISTERED => Some(RelayPacket::Registered {
+                port: u16::from_le_bytes(*rest.first_chunk()?),
+            }),
+            DATA => {
+                let (&family, rest) = rest.split_first()?;
+                let (ip, rest) = match family {
+                    4 => {
+                        let (octets, rest) = rest.split_first_chunk::<4>()?;
+                        (IpAddr::V4(Ipv4Addr::from(*octets)), rest)
+                    }
+                    6 => {
+                        let (octets, rest) = rest.split_first_chunk::<16>()?;
+                        (IpAddr::V6(Ipv6Addr::from(*octets)), rest)
+                    }
+                    _ => return None,
+                };
+                let (port, payload) = rest.split_first_chunk::<2>()?;
+                Some(RelayPacket::Data {
+                    peer: SocketAddr::new(ip, u16::from_le_bytes(*port)),
+                    payload,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_packets_roundtrip() {
+        let payload = [1, 2, 3, 4, 5];
+        for packet in [
+            RelayPacket::Register,
+            RelayPacket::Registered { port: 7042 },
+            RelayPacket::Data {
+                peer: "203.0.113.7:53211".parse().unwrap(),
+                payload: &payload,
+            },
+            RelayPacket::Data {
+                peer: "[2001:db8::1]:9000".parse().unwrap(),
+                payload: &[],
+            },
+        ] {
+            let mut buf = Vec::new();
+            packet.encode(&mut buf);
+            assert_eq!(RelayPacket::decode(&buf), Some(packet));
+        }
+
+        assert_eq!(RelayPacket::decode(b"NBRL"), None);
+        assert_eq!(RelayPacket::decode(b"NBRL\x02\x04\x7f"), None);
+        assert_eq!(RelayPacket::decode(&payload), None);
+    }
+}
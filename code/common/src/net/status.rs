@@ -0,0 +1,100 @@
+//! Asking the HTTP status endpoint of a server how it is doing.
+//!
+//! The server answers `/readyz` with 200 once it takes players (see its `health` module).
+//! Clients reach the endpoint on the port after the game port, which is where the server
+//! puts it by default.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Reply of a server's `/readyz`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Readiness {
+    pub ready: bool,
+    /// Time taken to open the connection, about one round trip
+    pub latency: Duration,
+    /// The JSON status the server sent along
+    pub body: String,
+}
+
+/// The status endpoint of the server playing on `game_addr`, by default.
+pub fn status_addr(game_addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(game_addr.ip(), game_addr.port().wrapping_add(1))
+}
+
+/// Asks the status endpoint at `addr` whether its server is ready, giving up on every step
+/// after `timeout`.
+pub fn check_ready(addr: SocketAddr, timeout: Duration) -> Result<Readiness, String> {
+    let started = Instant::now();
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|err| format!("Can't reach {}: {}", addr, err))?;
+    let latency = started.elapsed();
+
+    // In one write, since the endpoint answers as soon as it has the request line
+    let request = format!(
+        "GET /readyz HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        addr
+    );
+    let mut reply = String::new();
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .and_then(|_| stream.write_all(request.as_bytes()))
+        .and_then(|_| stream.read_to_string(&mut reply))
+        .map_err(|err| err.to_string())?;
+
+    let code = status_code(&reply).ok_or(format!("{} didn't answer with HTTP", addr))?;
+    Ok(Readiness {
+        ready: code == 200,
+        latency,
+        body: reply
+            .split("\r\n\r\n")
+            .nth(1)
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// Status code from the status line of an HTTP response.
+pub fn status_code(response: &str) -> Option<u16> {
+    let mut parts = response.lines().next()?.split_whitespace();
+    if !parts.next()?.starts_with("HTTP/") {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_readiness_comes_from_the_status_code() {
+        assert_eq!(
+            status_code("HTTP/1.1 503 Service Unavailable\r\n"),
+            Some(503)
+        );
+        assert_eq!(status_code("SSH-2.0-OpenSSH"), None);
+        assert_eq!(
+            status_addr("127.0.0.1:8080".parse().unwrap()),
+            "127.0.0.1:8081".parse().unwrap()
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 256];
+            let _ = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                .unwrap();
+        });
+        let readiness = check_ready(addr, Duration::from_secs(1)).unwrap();
+        server.join().unwrap();
+        assert!(readiness.ready);
+        assert_eq!(readiness.body, "{}");
+    }
+}
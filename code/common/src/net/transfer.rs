@@ -0,0 +1,168 @@
+//! Sending model files from the server to clients in chunks, identified by their hash.
+
+use crate::protocol::{ModelChunk, ModelHash, ModelInfo};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Larger models are neither offered nor accepted.
+pub const MAX_MODEL_SIZE: usize = 16 * 1024 * 1024;
+/// Small enough to leave room for game updates in the same tick.
+pub const MODEL_CHUNK_SIZE: usize = 16 * 1024;
+
+impl ModelHash {
+    pub fn of(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+}
+
+impl fmt::Display for ModelHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl ModelInfo {
+    pub fn new(name: String, bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() > MAX_MODEL_SIZE {
+            return Err(format!(
+                "Model {} is too large to share ({} bytes)",
+                name,
+                bytes.len()
+            ));
+        }
+        Ok(Self {
+            name,
+            hash: ModelHash::of(bytes),
+            size: bytes.len() as u32,
+        })
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        (self.size as usize).div_ceil(MODEL_CHUNK_SIZE).max(1) as u32
+    }
+
+    /// Chunk `index` of `bytes`, the file this info describes.
+    pub fn chunk(&self, bytes: &[u8], index: u32) -> ModelChunk {
+        let start = index as usize * MODEL_CHUNK_SIZE;
+        let end = (start + MODEL_CHUNK_SIZE).min(bytes.len());
+        ModelChunk {
+            hash: self.hash,
+            index,
+            count: self.chunk_count(),
+            data: bytes[start.min(end)..end].to_vec(),
+        }
+    }
+}
+
+/// A model being received; chunks must arrive in order.
+#[derive(Debug)]
+pub struct ModelDownload {
+    info: ModelInfo,
+    data: Vec<u8>,
+    next_index: u32,
+}
+
+impl ModelDownload {
+    pub fn new(info: ModelInfo) -> Result<Self, String> {
+        if info.size as usize > MAX_MODEL_SIZE {
+            return Err(format!("Model {} is too large to download", info.name));
+        }
+        Ok(Self {
+            data: Vec::with_capacity(info.size as usize),
+            info,
+            next_index: 0,
+        })
+    }
+
+    pub fn info(&self) -> &ModelInfo {
+        &self.info
+    }
+
+    /// Adds the next chunk. Returns the whole file once the last chunk arrived and the file
+    /// matches its hash.
+    pub fn accept(&mut self, chunk: ModelChunk) -> Result<Option<Vec<u8>>, String> {
+        if chunk.hash != self.info.hash {
+            return Err("Chunk of a different model".to_string());
+        }
+        if chunk.index != self.next_index || chunk.count != self.info.chunk_count() {
+            return Err(format!(
+                "Unexpected chunk {}/{}",
+                chunk.index + 1,
+                chunk.count
+            ));
+        }
+        if self.data.len() + chunk.data.len() > self.info.size as usize {
+            return Err("Model is larger than announced".to_string());
+        }
+
+        self.data.extend_from_slice(&chunk.data);
+        self.next_index += 1;
+        if self.next_index < chunk.count {
+            return Ok(None);
+        }
+        if self.data.len() != self.info.size as usize || ModelHash::of(&self.data) != self.info.hash
+        {
+            return Err(format!("Model {} arrived corrupted", self.info.name));
+        }
+        Ok(Some(std::mem::take(&mut self.data)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bytes = model(MODEL_CHUNK_SIZE * 2 + 100);
+        let info = ModelInfo::new("champion".into(), &bytes).unwrap();
+        assert_eq!(info.chunk_count(), 3);
+
+        let mut download = ModelDownload::new(info.clone()).unwrap();
+        assert_eq!(download.accept(info.chunk(&bytes, 0)), Ok(None));
+        assert_eq!(download.accept(info.chunk(&bytes, 1)), Ok(None));
+        assert_eq!(download.accept(info.chunk(&bytes, 2)), Ok(Some(bytes)));
+    }
+
+    #[test]
+    fn test_rejects_bad_chunks() {
+        let bytes = model(MODEL_CHUNK_SIZE + 1);
+        let info = ModelInfo::new("champion".into(), &bytes).unwrap();
+
+        // Out of order
+        let mut download = ModelDownload::new(info.clone()).unwrap();
+        assert!(download.accept(info.chunk(&bytes, 1)).is_err());
+
+        // Tampered with
+        let mut download = ModelDownload::new(info.clone()).unwrap();
+        let mut last = info.chunk(&bytes, 1);
+        last.data[0] ^= 1;
+        assert_eq!(download.accept(info.chunk(&bytes, 0)), Ok(None));
+        assert!(download.accept(last).is_err());
+
+        // Over the announced size
+        let mut download = ModelDownload::new(info.clone()).unwrap();
+        let mut first = info.chunk(&bytes, 0);
+        first.data.extend_from_slice(&[0, 0]);
+        assert!(download.accept(first).is_err());
+    }
+
+    #[test]
+    fn test_size_cap() {
+        assert!(ModelInfo::new("huge".into(), &vec![0; MAX_MODEL_SIZE + 1]).is_err());
+        let info = ModelInfo {
+            name: "huge".into(),
+            hash: ModelHash([0; 32]),
+            size: u32::MAX,
+        };
+        assert!(ModelDownload::new(info).is_err());
+        assert_eq!(
+            ModelHash::of(b"").to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::net::protocol::{InputPayload, Tank};
+use glam::Vec2;
+
+/// Outputs of [`super::BotBrain`]: forward/side movement, forward/side aim, shoot.
+pub const ACTION_COUNT: usize = 5;
+
+/// Turns raw network outputs (in the tank's local frame) into a world-space input.
+pub fn action_to_input(actions: &[f32], me: &Tank) -> InputPayload {
+    let move_fwd = actions[0].tanh();
+    let move_side = actions[1].tanh();
+    let aim_fwd = actions[2];
+    let aim_side = actions[3];
+    let shoot_val = actions[4];
+
+    let (sin, cos) = me.rotation.sin_cos();
+    let world_move = Vec2::new(
+        move_fwd * cos - move_side * sin,
+        move_fwd * sin + move_side * cos,
+    );
+    let world_aim_dir = Vec2::new(
+        aim_fwd * cos - aim_side * sin,
+        aim_fwd * sin + aim_side * cos,
+    );
+    let final_aim_dir = if world_aim_dir.length_squared() < 0.001 {
+        Vec2::new(cos, sin)
+    } else {
+        world_aim_dir.normalize()
+    };
+    let aim_pos = me.position + (final_aim_dir * 100.0);
+
+    InputPayload {
+        move_axis: world_move,
+        aim_pos,
+        shoot: shoot_val > 0.0,
+        grenade: false,
+    }
+}
@@ -0,0 +1,307 @@
+//! Headless matches between two bots, each a trained model or a scripted difficulty. Used
+//! by the server's exhibitions and tournaments and by the arena leaderboard.
+
+use crate::ai::{BotAgent, BotContext, BotDifficulty, BotRng};
+use crate::game::engine::GameEngine;
+use crate::game::player::PlayerInfo;
+use crate::game::replay::ReplayRecorder;
+use crate::net::protocol::{InputPayload, MapDefinition, PlayerId, Tank, Team};
+use crate::rl::{BotBrain, action_to_input, extract_features_with};
+use burn::module::Module;
+use burn::record::{BinFileRecorder, FullPrecisionSettings};
+use burn::tensor::backend::Backend;
+use burn_ndarray::NdArray;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const TICK_DT: f32 = 1.0 / 60.0;
+const SCRIPTED_PREFIX: &str = "scripted:";
+
+/// One side of a match, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContestantSpec {
+    /// A model from `<models dir>/<name>.bin`.
+    Model(String),
+    /// A built-in bot, written `scripted:<difficulty>`.
+    Scripted(BotDifficulty),
+}
+
+impl FromStr for ContestantSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(difficulty) = value.strip_prefix(SCRIPTED_PREFIX) {
+            return difficulty.parse().map(ContestantSpec::Scripted);
+        }
+        let name = value.strip_suffix(".bin").unwrap_or(value);
+        if name.is_empty() {
+            return Err("model name cannot be empty".to_string());
+        }
+        Ok(ContestantSpec::Model(name.to_string()))
+    }
+}
+
+impl fmt::Display for ContestantSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContestantSpec::Model(name) => write!(f, "{}", name),
+            ContestantSpec::Scripted(difficulty) => write!(f, "{:?} (scripted)", difficulty),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Contestant {
+    Model(Box<BotBrain<NdArray>>),
+    Scripted(BotDifficulty),
+}
+
+impl Contestant {
+    pub fn load(spec: &ContestantSpec, models_dir: &Path) -> Result<Self, String> {
+        match spec {
+            ContestantSpec::Scripted(difficulty) => Ok(Contestant::Scripted(*difficulty)),
+            ContestantSpec::Model(name) => {
+                let device = Default::default();
+                let path = models_dir.join(name);
+                let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
+                BotBrain::new(&device)
+                    .load_file(&path, &recorder, &device)
+                    .map(|brain| Contestant::Model(Box::new(brain)))
+                    .map_err(|e| format!("Failed to load model {}.bin: {}", path.display(), e))
+            }
+        }
+    }
+
+    /// A copy of `brain`, which may live on another backend, for headless matches.
+    pub fn from_brain<B: Backend>(brain: &BotBrain<B>) -> Result<Self, String> {
+        let brain = BotBrain::from_bytes(brain.to_bytes()?, &Default::default())?;
+        Ok(Contestant::Model(Box::new(brain)))
+    }
+}
+
+/// Result of one match, from the point of view of the contestants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Index into the contestants.
+    Win(usize),
+    Draw,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchReport {
+    pub outcome: Outcome,
+    /// Length of the match in seconds
+    pub duration: f32,
+    /// Damage each contestant dealt to the other one
+    pub damage: [f32; 2],
+}
+
+/// What `brain` does with `tank` in the current state of `engine`.
+pub fn model_input(
+    brain: &BotBrain<NdArray>,
+    tank: &Tank,
+    engine: &GameEngine,
+    dt: f32,
+    rng: &mut BotRng,
+) -> InputPayload {
+    let (tanks, projectiles) =
+        engine
+            .map
+            .arena_view(tank.position, &engine.tanks, &engine.projectiles);
+    let ctx = BotContext {
+        me: tank,
+        players: &tanks,
+        projectiles: &projectiles,
+        map: &engine.map,
+        dt,
+        rng,
+        ping: engine.blackboard.ping_for(tank, &engine.map),
+    };
+    let features = extract_features_with::<NdArray>(&ctx, brain.layout(), &Default::default());
+    let output = brain.forward(features);
+    let values = output.into_data().to_vec::<f32>().unwrap();
+    action_to_input(&values, tank)
+}
+
+/// Plays one match, contestant 0 ("A") on `a_team`. Without a `team_size` every spawn
+/// point gets a tank; bigger teams spawn around the spawn points.
+pub fn play_match(
+    contestants: &[Contestant; 2],
+    a_team: Team,
+    map: &MapDefinition,
+    team_size: Option<usize>,
+    match_duration: Duration,
+    seed: u64,
+    recorder: Option<&mut Option<ReplayRecorder>>,
+) -> MatchReport {
+    let mut engine = GameEngine::new(map.clone());
+    let mut controllers: HashMap<PlayerId, usize> = HashMap::new();
+    for team in [Team::Red, Team::Blue] {
+        let points = map.spawn_points.iter().filter(|(t, _)| *t == team).count();
+        let contestant = usize::from(team != a_team);
+        let label = if contestant == 0 { "A" } else { "B" };
+        for position in map.team_spawns(team, team_size.unwrap_or(points)) {
+            let id = engine.tanks.len() as PlayerId;
+            let info = PlayerInfo::new(id, format!("{}_{}", label, id), team);
+            engine.tanks.push(Tank::new(info, position));
+            controllers.insert(id, contestant);
+        }
+    }
+
+    let mut scripted: HashMap<PlayerId, BotAgent> = engine
+        .tanks
+        .iter()
+        .filter_map(|tank| {
+            let id = tank.player_info.id;
+            match &contestants[controllers[&id]] {
+                Contestant::Scripted(difficulty) => Some((
+                    id,
+                    BotAgent::new(tank.player_info.clone(), *difficulty, seed ^ u64::from(id)),
+                )),
+                Contestant::Model(_) => None,
+            }
+        })
+        .collect();
+
+    let mut recorder = recorder.map(|slot| slot.insert(ReplayRecorder::new(&engine, seed)));
+    let mut rng = BotRng::seed_from_u64(seed);
+    let max_ticks = (match_duration.as_secs_f32() / TICK_DT).ceil() as usize;
+    let mut damage = [0.0; 2];
+
+    for tick in 0..max_ticks {
+        let mut inputs = HashMap::new();
+        for tank in &engine.tanks {
+            let id = tank.player_info.id;
+            if let Some(agent) = scripted.get_mut(&id) {
+                let input = agent.generate_input(
+                    tank,
+                    &engine.tanks,
+                    &engine.projectiles,
+                    &engine.map,
+                    TICK_DT,
+                    engine.blackboard.ping_for(tank, &engine.map),
+                );
+                inputs.insert(id, input);
+            } else if let Contestant::Model(brain) = &contestants[controllers[&id]] {
+                inputs.insert(id, model_input(brain, tank, &engine, TICK_DT, &mut rng));
+            }
+        }
+
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.record(TICK_DT, &inputs);
+        }
+        let result = engine.tick(TICK_DT, inputs);
+        for hit in &result.damage {
+            let attacker = controllers[&hit.attacker_id];
+            if attacker != controllers[&hit.victim_id] {
+                damage[attacker] += hit.amount;
+            }
+        }
+        if result.outcome.is_over() {
+            return MatchReport {
+                outcome: outcome_for(result.outcome.winner(), a_team),
+                duration: (tick + 1) as f32 * TICK_DT,
+                damage,
+            };
+        }
+    }
+
+    MatchReport {
+        outcome: outcome_for(winner_by_health(&engine.tanks), a_team),
+        duration: max_ticks as f32 * TICK_DT,
+        damage,
+    }
+}
+
+fn outcome_for(winner: Option<Team>, a_team: Team) -> Outcome {
+    match winner {
+        Some(team) if team == a_team => Outcome::Win(0),
+        Some(_) => Outcome::Win(1),
+        None => Outcome::Draw,
+    }
+}
+
+/// Like a timed-out server round, but an exact tie is a draw instead of a coin flip.
+fn winner_by_health(tanks: &[Tank]) -> Option<Team> {
+    let health = |team| -> f32 {
+        tanks
+            .iter()
+            .filter(|t| t.player_info.team == team)
+            .map(|t| t.health)
+            .sum()
+    };
+    let (blue, red) = (health(Team::Blue), health(Team::Red));
+    if blue > red {
+        Some(Team::Blue)
+    } else if red > blue {
+        Some(Team::Red)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    #[test]
+    fn test_parse_contestants() {
+        assert_eq!(
+            "champion.bin".parse(),
+            Ok(ContestantSpec::Model("champion".into()))
+        );
+        assert_eq!(
+            "scripted:Terminator".parse(),
+            Ok(ContestantSpec::Scripted(BotDifficulty::Terminator))
+        );
+        assert!("scripted:godlike".parse::<ContestantSpec>().is_err());
+        assert!("".parse::<ContestantSpec>().is_err());
+    }
+
+    #[test]
+    fn test_winner_by_health() {
+        let tank = |id, team, health| {
+            let mut tank = Tank::new(PlayerInfo::new(id, "t".into(), team), Vec2::ZERO);
+            tank.health = health;
+            tank
+        };
+        assert_eq!(
+            winner_by_health(&[tank(0, Team::Blue, 30.), tank(1, Team::Red, 20.)]),
+            Some(Team::Blue)
+        );
+        assert_eq!(
+            winner_by_health(&[tank(0, Team::Blue, 30.), tank(1, Team::Red, 30.)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_idle_match_times_out_as_draw() {
+        let contestants = [
+            Contestant::Scripted(BotDifficulty::Dummy),
+            Contestant::Scripted(BotDifficulty::Dummy),
+        ];
+        let map = MapDefinition::load();
+        let mut recorder = None;
+        let report = play_match(
+            &contestants,
+            Team::Red,
+            &map,
+            None,
+            Duration::from_secs(1),
+            0,
+            Some(&mut recorder),
+        );
+
+        // Nobody shoots, so both teams keep full health.
+        assert_eq!(report.outcome, Outcome::Draw);
+        assert_eq!(report.damage, [0.0, 0.0]);
+        assert!((report.duration - 1.0).abs() < 0.05);
+        assert_eq!(recorder.unwrap().tick_count(), 60);
+    }
+}
@@ -0,0 +1,102 @@
+//! The model behind [`BotDifficulty::TrainedKiller`](crate::ai::BotDifficulty), built into
+//! the binaries so the difficulty works without any model files. Dropping a model at
+//! [`OVERRIDE_PATH`] replaces it.
+
+use crate::rl::BotBrain;
+use burn::module::Module;
+use burn::record::{BinFileRecorder, FullPrecisionSettings};
+use burn_ndarray::NdArray;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Checked once, on first use; the `.bin` extension is added by the recorder.
+pub const OVERRIDE_PATH: &str = "assets/models/trained_killer";
+
+static EMBEDDED: &[u8] = include_bytes!("../../../../assets/trained_killer.bin");
+
+/// Deserialized on first use, as the weights take a moment to decode, and shared by every
+/// bot after. Behind a mutex because burn modules aren't `Sync`.
+static DEFAULT_BRAIN: LazyLock<Arc<Mutex<BotBrain<NdArray>>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(load())));
+
+/// The `TrainedKiller` model: [`OVERRIDE_PATH`] if it exists and loads, otherwise the
+/// embedded one.
+pub fn default_brain() -> BotBrain<NdArray> {
+    DEFAULT_BRAIN.lock().unwrap().clone()
+}
+
+/// The `TrainedKiller` model itself rather than a copy, for bots to think with without
+/// each holding its own weights.
+pub fn shared_brain() -> Arc<Mutex<BotBrain<NdArray>>> {
+    Arc::clone(&DEFAULT_BRAIN)
+}
+
+/// The model built into the binaries, ignoring any override.
+pub fn embedded_brain() -> Result<BotBrain<NdArray>, String> {
+    BotBrain::from_bytes(EMBEDDED.to_vec(), &Default::default())
+        .map_err(|e| format!("Failed to decode the embedded model: {}", e))
+}
+
+fn load() -> BotBrain<NdArray> {
+    let path = Path::new(OVERRIDE_PATH);
+    if path.with_extension("bin").exists() {
+        let device = Default::default();
+        let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
+        match BotBrain::new(&device).load_file(path, &recorder, &device) {
+            Ok(brain) => {
+                log::info!("Using {}.bin for TrainedKiller", OVERRIDE_PATH);
+                return brain;
+            }
+            Err(e) => log::warn!(
+                "Failed to load {}.bin, using the built-in model: {}",
+                OVERRIDE_PATH,
+                e
+            ),
+        }
+    }
+    embedded_brain().expect("the embedded model matches BotBrain")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::tensor::Tensor;
+
+    #[test]
+    fn test_bots_share_one_default_model() {
+        let brain = shared_brain();
+        assert!(Arc::ptr_eq(&brain, &shared_brain()));
+
+        // Without an override next to the tests, it is the embedded model
+        let device = Default::default();
+        let input = Tensor::<NdArray, 2>::ones([1, crate::rl::FEATURE_COUNT], &device);
+        let shared = brain.lock().unwrap().forward(input.clone());
+        let embedded = embedded_brain().unwrap().forward(input);
+        assert_eq!(
+            shared.into_data().to_vec::<f32>().unwrap(),
+            embedded.into_data().to_vec::<f32>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_embedded_model_matches_file() {
+        // The bytes are a model file as the trainer saves it
+        let device = Default::default();
+        let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
+        let from_file = BotBrain::<NdArray>::new(&device)
+            .load_file("../../assets/trained_killer", &recorder, &device)
+            .unwrap();
+
+        let input = Tensor::<NdArray, 2>::ones([1, crate::rl::FEATURE_COUNT], &device);
+        let embedded = embedded_brain().unwrap().forward(input.clone());
+        assert_eq!(embedded.dims(), [1, crate::rl::ACTION_COUNT]);
+        assert_eq!(
+            embedded.into_data().to_vec::<f32>().unwrap(),
+            from_file
+                .forward(input)
+                .into_data()
+                .to_vec::<f32>()
+                .unwrap()
+        );
+    }
+}
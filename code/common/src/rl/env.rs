@@ -0,0 +1,315 @@
+//! Gym-style wrapper around [`GameEngine`], so any trainer can drive the exact same
+//! simulation the game runs.
+//!
+//! Agents are the tanks of a `team_size` vs `team_size` match, indexed by player id:
+//! `0..team_size` play Blue, `team_size..2 * team_size` play Red.
+
+use crate::ai::{BotContext, BotRng};
+use crate::game::engine::GameEngine;
+use crate::game::player::PlayerInfo;
+use crate::net::protocol::{MapDefinition, PlayerId, Tank, Team};
+use crate::rl::actions::{ACTION_COUNT, action_to_input};
+use crate::rl::features::{FeatureLayout, feature_vector_with};
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+/// One agent's view of the world, [`TankEnv::observation_size`] values. All zeros once
+/// it's dead.
+pub type Observation = Vec<f32>;
+
+/// One agent's raw network outputs, see [`action_to_input`].
+pub type Action = [f32; ACTION_COUNT];
+
+/// Reward for each kind of event, credited to the tank that caused it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvRewards {
+    /// Per point of damage dealt to an enemy.
+    pub enemy_damage: f32,
+    /// Per point of damage dealt to a teammate (usually negative).
+    pub friendly_damage: f32,
+    pub kill: f32,
+    /// For killing a teammate (usually negative).
+    pub teamkill: f32,
+}
+
+impl Default for EnvRewards {
+    /// The same weights the evolutionary trainer uses.
+    fn default() -> Self {
+        Self {
+            enemy_damage: 1.0,
+            friendly_damage: -2.0,
+            kill: 500.0,
+            teamkill: -1000.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+    pub map: MapDefinition,
+    /// Tanks per team, at most `MAX_TEAM_SIZE`. Teams bigger than the map's spawn points
+    /// spawn around them.
+    pub team_size: usize,
+    /// The episode ends after this many steps even if both teams are alive.
+    pub max_ticks: usize,
+    /// Simulated seconds per step.
+    pub dt: f32,
+    pub rewards: EnvRewards,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            map: MapDefinition::load(),
+            team_size: 4,
+            max_ticks: 1000,
+            dt: 0.033,
+            rewards: EnvRewards::default(),
+        }
+    }
+}
+
+/// What one call to [`TankEnv::step`] produced, indexed by agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepResult {
+    pub observations: Vec<Observation>,
+    pub rewards: Vec<f32>,
+    /// The episode is over: a team was wiped out or `max_ticks` was reached.
+    pub done: bool,
+    /// Set when a team was wiped out.
+    pub winner: Option<Team>,
+}
+
+pub struct TankEnv {
+    config: EnvConfig,
+    engine: GameEngine,
+    rng: BotRng,
+    tick: usize,
+    done: bool,
+}
+
+impl TankEnv {
+    pub fn new(config: EnvConfig, seed: u64) -> Self {
+        let mut env = Self {
+            engine: GameEngine::new(config.map.clone()),
+            config,
+            rng: BotRng::seed_from_u64(seed),
+            tick: 0,
+            done: false,
+        };
+        env.reset(seed);
+        env
+    }
+
+    pub fn agent_count(&self) -> usize {
+        self.config.team_size * 2
+    }
+
+    pub fn agent_team(&self, agent: usize) -> Team {
+        if agent < self.config.team_size {
+            Team::Blue
+        } else {
+            Team::Red
+        }
+    }
+
+    /// Values per observation, which grows with the team size.
+    pub fn observation_size(&self) -> usize {
+        self.layout().count()
+    }
+
+    fn layout(&self) -> FeatureLayout {
+        FeatureLayout::for_team_size(self.config.team_size)
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// The underlying engine, e.g. for rendering or recording the episode.
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    /// Steps taken since the last reset.
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Starts a new episode and returns the first observations.
+    ///
+    /// Blue spawns facing east, Red facing west, like in the trainer.
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.engine = GameEngine::new(self.config.map.clone());
+        self.rng = BotRng::seed_from_u64(seed);
+        self.tick = 0;
+        self.done = false;
+
+        let team_size = self.config.team_size;
+        let map = &self.config.map;
+        for (i, spawn) in map
+            .team_spawns(Team::Blue, team_size)
+            .into_iter()
+            .enumerate()
+        {
+            let info = PlayerInfo::new(i as PlayerId, format!("Blue_{}", i), Team::Blue);
+            self.engine.tanks.push(Tank::new(info, spawn));
+        }
+        for (i, spawn) in map
+            .team_spawns(Team::Red, team_size)
+            .into_iter()
+            .enumerate()
+        {
+            let id = (i + team_size) as PlayerId;
+            let info = PlayerInfo::new(id, format!("Red_{}", i), Team::Red);
+            let mut tank = Tank::new(info, spawn);
+            tank.rotation = std::f32::consts::PI;
+            self.engine.tanks.push(tank);
+        }
+
+        self.observe()
+    }
+
+    /// Current observation of every agent.
+    pub fn observe(&mut self) -> Vec<Observation> {
+        let layout = self.layout();
+        let mut observations = vec![vec![0.0; layout.count()]; self.agent_count()];
+        for tank in &self.engine.tanks {
+            let ctx = BotContext {
+                me: tank,
+                players: &self.engine.tanks,
+                projectiles: &self.engine.projectiles,
+                map: &self.engine.map,
+                dt: self.config.dt,
+                rng: &mut self.rng,
+                ping: self.engine.blackboard.ping_for(tank, &self.engine.map),
+            };
+            if let Some(observation) = observations.get_mut(tank.player_info.id as usize) {
+                *observation = feature_vector_with(&ctx, layout);
+            }
+        }
+        observations
+    }
+
+    /// Applies one action per agent (dead agents' actions are ignored) and advances the
+    /// simulation by one tick.
+    pub fn step(&mut self, actions: &[Action]) -> StepResult {
+        let mut inputs = HashMap::new();
+        for tank in &self.engine.tanks {
+            if let Some(action) = actions.get(tank.player_info.id as usize) {
+                inputs.insert(tank.player_info.id, action_to_input(action, tank));
+            }
+        }
+
+        let result = self.engine.tick(self.config.dt, inputs);
+        self.tick += 1;
+
+        let weights = self.config.rewards;
+        let mut rewards = vec![0.0; self.agent_count()];
+        for damage in &result.damage {
+            let friendly = self.agent_team(damage.attacker_id as usize)
+                == self.agent_team(damage.victim_id as usize);
+            if let Some(reward) = rewards.get_mut(damage.attacker_id as usize) {
+                let weight = if friendly {
+                    weights.friendly_damage
+                } else {
+                    weights.enemy_damage
+                };
+                *reward += damage.amount * weight;
+            }
+        }
+        for kill in &result.kills {
+            let friendly = kill.killer_info.team == kill.victim_info.team;
+            if let Some(reward) = rewards.get_mut(kill.killer_info.id as usize) {
+                *reward += if friendly {
+                    weights.teamkill
+                } else {
+                    weights.kill
+                };
+            }
+        }
+
+        self.done = result.outcome.is_over() || self.tick >= self.config.max_ticks;
+        StepResult {
+            observations: self.observe(),
+            rewards,
+            done: self.done,
+            winner: result.outcome.winner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_env(max_ticks: usize) -> TankEnv {
+        let config = EnvConfig {
+            team_size: 2,
+            max_ticks,
+            ..Default::default()
+        };
+        TankEnv::new(config, 7)
+    }
+
+    #[test]
+    fn test_reset_spawns_both_teams() {
+        let mut env = small_env(10);
+        let observations = env.reset(1);
+
+        assert_eq!(env.agent_count(), 4);
+        assert_eq!(observations.len(), 4);
+        assert!(
+            observations
+                .iter()
+                .all(|o| o.len() == crate::rl::FEATURE_COUNT)
+        );
+        assert_eq!(env.agent_team(1), Team::Blue);
+        assert_eq!(env.agent_team(2), Team::Red);
+        assert_eq!(env.engine().tanks.len(), 4);
+    }
+
+    #[test]
+    fn test_episode_ends_after_max_ticks() {
+        let mut env = small_env(3);
+        let idle = vec![[0.0; ACTION_COUNT]; env.agent_count()];
+
+        assert!(!env.step(&idle).done);
+        assert!(!env.step(&idle).done);
+        let last = env.step(&idle);
+        assert!(last.done);
+        assert_eq!(last.rewards, vec![0.0; 4]);
+        assert_eq!(env.tick(), 3);
+    }
+
+    #[test]
+    fn test_same_actions_give_same_episode() {
+        let mut a = small_env(50);
+        let mut b = small_env(50);
+        let actions: Vec<Action> = (0..a.agent_count())
+            .map(|i| [1.0, 0.0, 1.0, i as f32 * 0.1, 1.0])
+            .collect();
+
+        for _ in 0..50 {
+            assert_eq!(a.step(&actions), b.step(&actions));
+        }
+    }
+
+    #[test]
+    fn test_eight_per_team_see_more_neighbours() {
+        let config = EnvConfig {
+            team_size: crate::game::MAX_TEAM_SIZE,
+            ..Default::default()
+        };
+        let mut env = TankEnv::new(config, 7);
+        let observations = env.reset(1);
+
+        assert_eq!(env.engine().tanks.len(), 16);
+        assert_eq!(env.observation_size(), 45);
+        assert!(observations.iter().all(|o| o.len() == 45));
+    }
+}
@@ -1,17 +1,123 @@
 use crate::ai::BotContext;
-use crate::game::{FIRE_RATE, PROJECTILE_SPEED};
-use crate::net::protocol::{RectWall, Tank};
+use crate::ai::blackboard::PING_MEMORY;
+use crate::game::upgrades::MAX_UPGRADE_LEVEL;
+use crate::game::{MAX_TEAM_SIZE, armor_factor, balance};
+use crate::net::protocol::{RectWall, Tank, Upgrade};
 use burn::tensor::backend::Backend;
 use burn::tensor::{Tensor, TensorData};
 use glam::Vec2;
 
 // 2 (Self) + 9 (Enemies) + 6 (Friends) + 5 (Bullet) + 8 (Walls) = 30
-pub const FEATURE_COUNT: usize = 30;
+pub const FEATURE_COUNT: usize = FeatureLayout::DEFAULT.count();
 
 const SENSOR_MAX_DIST: f32 = 500.0; // Vision range
 
+/// Nearest enemies whose armor the bot sees with [`FeatureLayout::armor`], whatever the
+/// team size; four inputs each keep the armor layouts apart from all others.
+const ARMOR_ENEMIES: usize = 4;
+
+/// How many of the nearest enemies and teammates a bot sees. Bigger teams see more of
+/// them, so a model only works with the team sizes it was trained for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureLayout {
+    pub enemies: usize,
+    pub friends: usize,
+    /// Whether the bot sees its team's latest ping (4 more inputs)
+    pub ping: bool,
+    /// Whether the bot sees its own upgrades and those of the strongest enemy in sight
+    /// (8 more inputs)
+    pub upgrades: bool,
+    /// Whether the bot sees how much of a shot gets through its own armor and that of
+    /// its nearest enemies (16 more inputs)
+    pub armor: bool,
+}
+
+impl FeatureLayout {
+    /// The layout of teams up to 4v4, `FEATURE_COUNT` inputs.
+    pub const DEFAULT: Self = Self {
+        enemies: 3,
+        friends: 2,
+        ping: false,
+        upgrades: false,
+        armor: false,
+    };
+
+    /// Three quarters of the enemies and half the teammates, never fewer than
+    /// [`Self::DEFAULT`].
+    pub fn for_team_size(team_size: usize) -> Self {
+        Self {
+            enemies: (team_size * 3 / 4).max(Self::DEFAULT.enemies),
+            friends: (team_size / 2).max(Self::DEFAULT.friends),
+            ping: false,
+            upgrades: false,
+            armor: false,
+        }
+    }
+
+    /// This layout, also seeing the team's latest ping.
+    pub const fn with_ping(self) -> Self {
+        Self { ping: true, ..self }
+    }
+
+    /// This layout, also seeing upgrades.
+    pub const fn with_upgrades(self) -> Self {
+        Self {
+            upgrades: true,
+            ..self
+        }
+    }
+
+    /// This layout, also seeing armor.
+    pub const fn with_armor(self) -> Self {
+        Self {
+            armor: true,
+            ..self
+        }
+    }
+
+    /// The layout taking `count` inputs, e.g. the input size of a loaded model. The
+    /// sizes of the optional inputs are picked so that no two layouts take as many.
+    pub fn from_count(count: usize) -> Option<Self> {
+        (1..=MAX_TEAM_SIZE)
+            .map(Self::for_team_size)
+            .flat_map(|layout| [layout, layout.with_ping()])
+            .flat_map(|layout| [layout, layout.with_upgrades()])
+            .flat_map(|layout| [layout, layout.with_armor()])
+            .find(|layout| layout.count() == count)
+    }
+
+    pub const fn count(&self) -> usize {
+        2 + 3 * self.enemies
+            + 3 * self.friends
+            + 5
+            + 8
+            + if self.ping { 4 } else { 0 }
+            + if self.upgrades { 8 } else { 0 }
+            + if self.armor { 4 * ARMOR_ENEMIES } else { 0 }
+    }
+}
+
 pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Tensor<B, 2> {
-    let mut features = Vec::with_capacity(FEATURE_COUNT);
+    extract_features_with(ctx, FeatureLayout::DEFAULT, device)
+}
+
+pub fn extract_features_with<B: Backend>(
+    ctx: &BotContext,
+    layout: FeatureLayout,
+    device: &B::Device,
+) -> Tensor<B, 2> {
+    let data = TensorData::new(feature_vector_with(ctx, layout), [1, layout.count()]);
+    Tensor::from_data(data, device)
+}
+
+/// The raw `FEATURE_COUNT` inputs seen by the bot in `ctx`, before batching into a tensor.
+pub fn feature_vector(ctx: &BotContext) -> Vec<f32> {
+    feature_vector_with(ctx, FeatureLayout::DEFAULT)
+}
+
+/// [`feature_vector`] with `layout.count()` inputs.
+pub fn feature_vector_with(ctx: &BotContext, layout: FeatureLayout) -> Vec<f32> {
+    let mut features = Vec::with_capacity(layout.count());
 
     // --- TRANSFORMATION HELPERS ---
     // We rotate everything into the Bot's local perspective.
@@ -28,10 +134,17 @@ pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Ten
     };
 
     // --- 1. SELF STATE (2 inputs) ---
-    features.push((ctx.me.health / 100.0).clamp(0.0, 1.0));
-    features.push((ctx.me.weapon_cooldown / FIRE_RATE).clamp(0.0, 1.0));
+    features.push((ctx.me.health / balance().max_health).clamp(0.0, 1.0));
+    // How far the gun is from firing: the cooldown, or with heat firing how hot it is, so
+    // models trained without heat see it just the same
+    let weapon = if ctx.me.overheated {
+        1.0
+    } else {
+        (ctx.me.weapon_cooldown / balance().fire_interval).max(ctx.me.heat)
+    };
+    features.push(weapon.clamp(0.0, 1.0));
 
-    // --- 2. ENEMIES (3 Nearest) (9 inputs) ---
+    // --- 2. ENEMIES (K Nearest) (3 inputs each) ---
     // We explicitly sort ALL enemies by distance
     let mut enemies: Vec<&Tank> = ctx
         .players
@@ -51,7 +164,7 @@ pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Ten
             .unwrap()
     });
 
-    for i in 0..3 {
+    for i in 0..layout.enemies {
         if let Some(enemy) = enemies.get(i) {
             let local_pos = to_local(enemy.position);
             let dist = local_pos.length();
@@ -70,7 +183,7 @@ pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Ten
         }
     }
 
-    // --- 3. TEAMMATES (2 Nearest) (6 inputs) ---
+    // --- 3. TEAMMATES (K Nearest) (3 inputs each) ---
     let mut friends: Vec<&Tank> = ctx
         .players
         .iter()
@@ -89,7 +202,7 @@ pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Ten
             .unwrap()
     });
 
-    for i in 0..2 {
+    for i in 0..layout.friends {
         if let Some(friend) = friends.get(i) {
             let local_pos = to_local(friend.position);
             let dist = local_pos.length();
@@ -128,8 +241,8 @@ pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Ten
         features.push((1.0 - dist / SENSOR_MAX_DIST).clamp(0.0, 1.0));
         features.push(local_pos.normalize_or_zero().x);
         features.push(local_pos.normalize_or_zero().y);
-        features.push(local_vel.x / PROJECTILE_SPEED);
-        features.push(local_vel.y / PROJECTILE_SPEED);
+        features.push(local_vel.x / balance().projectile_speed());
+        features.push(local_vel.y / balance().projectile_speed());
     } else {
         features.push(0.0);
         features.push(0.0);
@@ -160,19 +273,85 @@ pub fn extract_features<B: Backend>(ctx: &BotContext, device: &B::Device) -> Ten
         features.push(raycast_normalized(ctx, world_dir));
     }
 
-    let data = TensorData::new(features, [1, FEATURE_COUNT]);
-    Tensor::from_data(data, device)
+    // --- 6. TEAM PING (4 inputs, optional) ---
+    if layout.ping {
+        if let Some(ping) = ctx.ping {
+            let local_pos = to_local(ping.world_pos);
+            let dist = local_pos.length();
+
+            // 1.0 = just pinged, 0.0 = about to be forgotten
+            features.push((1.0 - ping.age / PING_MEMORY).clamp(0.0, 1.0));
+            features.push((1.0 - dist / SENSOR_MAX_DIST).clamp(0.0, 1.0));
+            features.push(local_pos.normalize_or_zero().x);
+            features.push(local_pos.normalize_or_zero().y);
+        } else {
+            features.push(0.0);
+            features.push(0.0);
+            features.push(0.0);
+            features.push(0.0);
+        }
+    }
+
+    // --- 7. UPGRADES (8 inputs, optional) ---
+    if layout.upgrades {
+        push_upgrades(&mut features, Some(ctx.me));
+
+        // The enemy in sight with the most upgrade levels, the nearest one on a tie
+        let mut strongest: Option<(&Tank, f32)> = None;
+        for enemy in &enemies {
+            if to_local(enemy.position).length() > SENSOR_MAX_DIST {
+                break;
+            }
+            let levels: f32 = Upgrade::ALL.iter().map(|&u| enemy.upgrade_level(u)).sum();
+            if strongest.is_none_or(|(_, best)| levels > best) {
+                strongest = Some((enemy, levels));
+            }
+        }
+        push_upgrades(&mut features, strongest.map(|(enemy, _)| enemy));
+    }
+
+    // --- 8. ARMOR (16 inputs, optional) ---
+    if layout.armor {
+        for i in 0..ARMOR_ENEMIES {
+            if let Some(enemy) = enemies.get(i) {
+                let line_of_fire = enemy.position - ctx.me.position;
+                // Share of a shot that gets through: ours at them, theirs at us
+                features.push(armor_factor(enemy, line_of_fire));
+                features.push(armor_factor(ctx.me, -line_of_fire));
+                // Where they face, so the bot can find their sides
+                let facing = to_local(ctx.me.position + Vec2::from_angle(enemy.rotation));
+                features.push(facing.x);
+                features.push(facing.y);
+            } else {
+                features.extend([0.0; 4]);
+            }
+        }
+    }
+
+    features
 }
 
 // --- HELPERS ---
 
+/// Health relative to its upgraded maximum, then the level of every upgrade (4 inputs).
+fn push_upgrades(features: &mut Vec<f32>, tank: Option<&Tank>) {
+    let Some(tank) = tank else {
+        features.extend([0.0; 4]);
+        return;
+    };
+    features.push((tank.health / tank.max_health).clamp(0.0, 1.0));
+    for upgrade in Upgrade::ALL {
+        features.push(tank.upgrade_level(upgrade) / f32::from(MAX_UPGRADE_LEVEL));
+    }
+}
+
 fn raycast_normalized(ctx: &BotContext, direction: Vec2) -> f32 {
     let origin = ctx.me.position;
     let mut min_dist = SENSOR_MAX_DIST;
 
     // 1. Check Internal Obstacles
-    for wall in &ctx.map.walls {
-        if let Some(dist) = ray_aabb_intersect(origin, direction, wall)
+    for wall in ctx.map.solid_walls() {
+        if let Some(dist) = ray_aabb_intersect(origin, direction, &wall)
             && dist < min_dist
         {
             min_dist = dist;
@@ -232,3 +411,175 @@ fn ray_aabb_intersect(origin: Vec2, dir: Vec2, wall: &RectWall) -> Option<f32> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_grows_with_team_size() {
+        assert_eq!(FeatureLayout::for_team_size(1), FeatureLayout::DEFAULT);
+        assert_eq!(FeatureLayout::for_team_size(4), FeatureLayout::DEFAULT);
+        assert_eq!(FEATURE_COUNT, 30);
+
+        let big = FeatureLayout::for_team_size(MAX_TEAM_SIZE);
+        assert_eq!((big.enemies, big.friends), (6, 4));
+        for team_size in 1..=MAX_TEAM_SIZE {
+            let layout = FeatureLayout::for_team_size(team_size);
+            assert_eq!(FeatureLayout::from_count(layout.count()), Some(layout));
+        }
+        assert_eq!(FeatureLayout::from_count(31), None);
+    }
+
+    #[test]
+    fn test_ping_layouts_are_told_apart() {
+        for team_size in 1..=MAX_TEAM_SIZE {
+            let layout = FeatureLayout::for_team_size(team_size).with_ping();
+            assert_eq!(
+                layout.count(),
+                FeatureLayout::for_team_size(team_size).count() + 4
+            );
+            assert_eq!(FeatureLayout::from_count(layout.count()), Some(layout));
+        }
+    }
+
+    #[test]
+    fn test_every_layout_has_its_own_size() {
+        let mut layouts: Vec<FeatureLayout> = (1..=MAX_TEAM_SIZE)
+            .map(FeatureLayout::for_team_size)
+            .flat_map(|layout| [layout, layout.with_ping()])
+            .flat_map(|layout| [layout, layout.with_upgrades()])
+            .flat_map(|layout| [layout, layout.with_armor()])
+            .collect();
+        layouts.dedup();
+        for layout in &layouts {
+            assert_eq!(FeatureLayout::from_count(layout.count()), Some(*layout));
+        }
+        assert_eq!(
+            FeatureLayout::DEFAULT.with_upgrades().count(),
+            FEATURE_COUNT + 8
+        );
+        assert_eq!(
+            FeatureLayout::DEFAULT.with_armor().count(),
+            FEATURE_COUNT + 16
+        );
+    }
+
+    #[test]
+    fn test_armor_features_show_which_shots_get_through() {
+        use crate::ai::BotRng;
+        use crate::game::player::PlayerInfo;
+        use crate::net::protocol::{MapDefinition, Team};
+        use rand::SeedableRng;
+
+        // Facing along +X, towards an enemy that faces back at it
+        let me = Tank::new(
+            PlayerInfo::new(0, "P0".into(), Team::Blue),
+            Vec2::new(100.0, 100.0),
+        );
+        let mut enemy = Tank::new(
+            PlayerInfo::new(1, "P1".into(), Team::Red),
+            Vec2::new(200.0, 100.0),
+        );
+        enemy.rotation = std::f32::consts::PI;
+        let players = vec![me.clone(), enemy];
+        let map = MapDefinition::load();
+        let mut rng = BotRng::seed_from_u64(0);
+        let ctx = BotContext {
+            me: &me,
+            players: &players,
+            projectiles: &Vec::new(),
+            map: &map,
+            dt: 0.033,
+            rng: &mut rng,
+            ping: None,
+        };
+
+        let layout = FeatureLayout::DEFAULT.with_armor();
+        let features = feature_vector_with(&ctx, layout);
+        assert_eq!(features.len(), layout.count());
+        // Both tanks meet the other's shots with their front
+        let through = 1.0 - balance().front_armor;
+        let armor: Vec<f32> = features[FEATURE_COUNT..]
+            .iter()
+            .map(|f| (f * 100.0).round() / 100.0)
+            .collect();
+        let through = (through * 100.0).round() / 100.0;
+        assert_eq!(&armor[..4], [through, through, -1.0, 0.0]);
+        assert!(armor[4..].iter().all(|&f| f == 0.0));
+
+        // Without the flag nothing changes for existing models
+        assert_eq!(feature_vector(&ctx).len(), FEATURE_COUNT);
+    }
+
+    #[test]
+    fn test_upgrade_features_show_the_strongest_enemy_in_sight() {
+        use crate::ai::BotRng;
+        use crate::game::player::PlayerInfo;
+        use crate::net::protocol::{Loadout, MapDefinition, Team};
+        use rand::SeedableRng;
+
+        let tank = |id, team, x, loadout: Loadout| {
+            let mut tank = Tank::new(
+                PlayerInfo::new(id, format!("P{id}"), team),
+                Vec2::new(x, 100.0),
+            );
+            tank.apply_loadout(&loadout);
+            tank
+        };
+        let mut me = tank(
+            0,
+            Team::Blue,
+            100.0,
+            Loadout {
+                speed: 3,
+                ..Default::default()
+            },
+        );
+        me.health = me.max_health / 2.0;
+        let players = vec![
+            me.clone(),
+            tank(1, Team::Red, 200.0, Loadout::default()),
+            tank(
+                2,
+                Team::Red,
+                300.0,
+                Loadout {
+                    fire_rate: 3,
+                    ..Default::default()
+                },
+            ),
+            // Stronger still, but out of sight
+            tank(
+                3,
+                Team::Red,
+                100.0 + SENSOR_MAX_DIST * 2.0,
+                Loadout {
+                    max_health: 3,
+                    fire_rate: 3,
+                    speed: 3,
+                },
+            ),
+        ];
+        let map = MapDefinition::load();
+        let mut rng = BotRng::seed_from_u64(0);
+        let ctx = BotContext {
+            me: &me,
+            players: &players,
+            projectiles: &Vec::new(),
+            map: &map,
+            dt: 0.033,
+            rng: &mut rng,
+            ping: None,
+        };
+
+        let layout = FeatureLayout::DEFAULT.with_upgrades();
+        let features = feature_vector_with(&ctx, layout);
+        assert_eq!(features.len(), layout.count());
+        let upgrades: Vec<f32> = features[FEATURE_COUNT..]
+            .iter()
+            .map(|f| (f * 100.0).round() / 100.0)
+            .collect();
+        assert_eq!(upgrades, [0.5, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,33 @@
+//! Whether the GPU backend works on this machine.
+//!
+//! Without a usable adapter (headless servers, missing drivers) burn's `Wgpu` backend panics
+//! on the first tensor operation, deep inside whatever happened to run it. Binaries that
+//! prefer the GPU check [`wgpu_available`] up front and fall back to the CPU instead.
+
+use burn::backend::Wgpu;
+use burn::tensor::Tensor;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::LazyLock;
+
+/// Probed once, on first use.
+static WGPU_AVAILABLE: LazyLock<bool> = LazyLock::new(probe);
+
+/// Whether a `Wgpu` device could be set up and run a tensor operation.
+pub fn wgpu_available() -> bool {
+    *WGPU_AVAILABLE
+}
+
+fn probe() -> bool {
+    // The failure is expected and reported by the caller, so keep the panic message quiet
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let device = Default::default();
+        Tensor::<Wgpu, 1>::zeros([1], &device).into_data();
+    }));
+    panic::set_hook(hook);
+    if result.is_err() {
+        log::warn!("No usable GPU adapter found");
+    }
+    result.is_ok()
+}
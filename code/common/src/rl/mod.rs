@@ -1,7 +1,20 @@
+pub mod actions;
+pub mod contest;
+pub mod default_model;
+pub mod env;
 pub mod features;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
 pub mod model;
 pub mod policy;
+pub mod scenario;
 
-pub use features::{FEATURE_COUNT, extract_features};
+pub use actions::{ACTION_COUNT, action_to_input};
+pub use env::{EnvConfig, EnvRewards, StepResult, TankEnv};
+pub use features::{
+    FEATURE_COUNT, FeatureLayout, extract_features, extract_features_with, feature_vector,
+    feature_vector_with,
+};
 pub use model::BotBrain;
 pub use policy::RlPolicy;
+pub use scenario::Scenario;
@@ -1,7 +1,11 @@
+use super::features::FeatureLayout;
 use burn::module::{Module, Param};
 use burn::nn::{Linear, LinearConfig, Relu};
+use burn::record::{FullPrecisionSettings, Recorder, RecorderError};
 use burn::tensor::backend::Backend;
 use burn::tensor::{Distribution, Tensor};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 
 #[derive(Module, Debug)]
 pub struct BotBrain<B: Backend> {
@@ -13,14 +17,18 @@ pub struct BotBrain<B: Backend> {
 }
 
 impl<B: Backend> BotBrain<B> {
-    const INPUT_SIZE: usize = super::features::FEATURE_COUNT;
-    const OUTPUT_SIZE: usize = 5;
+    const OUTPUT_SIZE: usize = super::actions::ACTION_COUNT;
 
     const HIDDEN_SIZE: usize = 512;
 
     pub fn new(device: &B::Device) -> Self {
+        Self::with_layout(device, FeatureLayout::DEFAULT)
+    }
+
+    /// A fresh brain seeing the world through `layout`.
+    pub fn with_layout(device: &B::Device, layout: FeatureLayout) -> Self {
         Self {
-            linear1: LinearConfig::new(Self::INPUT_SIZE, Self::HIDDEN_SIZE).init(device),
+            linear1: LinearConfig::new(layout.count(), Self::HIDDEN_SIZE).init(device),
             linear2: LinearConfig::new(Self::HIDDEN_SIZE, Self::HIDDEN_SIZE).init(device),
             linear3: LinearConfig::new(Self::HIDDEN_SIZE, Self::HIDDEN_SIZE).init(device),
             output: LinearConfig::new(Self::HIDDEN_SIZE, Self::OUTPUT_SIZE).init(device),
@@ -28,6 +36,35 @@ impl<B: Backend> BotBrain<B> {
         }
     }
 
+    /// Decodes the contents of a model file, as saved by the trainer.
+    pub fn from_bytes(bytes: Vec<u8>, device: &B::Device) -> Result<Self, String> {
+        let record = CheckedBytesRecorder
+            .load(bytes, device)
+            .map_err(|e| e.to_string())?;
+        Ok(Self::new(device).load_record(record))
+    }
+
+    /// Encodes the brain the way [`Self::from_bytes`] reads it.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        Recorder::<B>::record(&CheckedBytesRecorder, self.clone().into_record(), ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// The features this brain takes, which loading a model file can change. Unknown
+    /// input sizes fall back to the default layout.
+    pub fn layout(&self) -> FeatureLayout {
+        self.stored_layout().unwrap_or(FeatureLayout::DEFAULT)
+    }
+
+    /// The layout of this brain's input size, `None` when no layout takes as many inputs.
+    pub fn stored_layout(&self) -> Option<FeatureLayout> {
+        FeatureLayout::from_count(self.input_count())
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.linear1.weight.dims()[0]
+    }
+
     pub fn forward(&self, input: Tensor<B, 2>) -> Tensor<B, 2> {
         let x = self.linear1.forward(input);
         let x = self.activation.forward(x);
@@ -81,4 +118,86 @@ impl<B: Backend> BotBrain<B> {
             activation: Relu::new(),
         }
     }
+
+    /// Cheap identity hash, equal for clones of the same brain.
+    ///
+    /// Only the output layer is hashed: every mutation changes all layers, so this is
+    /// enough to tell brains apart without reading every weight back from the device.
+    pub fn fingerprint(&self) -> u64 {
+        let mut values = self
+            .output
+            .weight
+            .val()
+            .into_data()
+            .to_vec::<f32>()
+            .unwrap();
+        if let Some(bias) = &self.output.bias {
+            values.extend(bias.val().into_data().to_vec::<f32>().unwrap());
+        }
+
+        // FNV-1a
+        values
+            .iter()
+            .flat_map(|v| v.to_bits().to_le_bytes())
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Reads the format of `BinFileRecorder` from memory. Burn's own `BinBytesRecorder`
+/// panics on malformed input, which a server reloading files at runtime can't afford.
+#[derive(Debug, Default, Clone)]
+struct CheckedBytesRecorder;
+
+impl<B: Backend> Recorder<B> for CheckedBytesRecorder {
+    type Settings = FullPrecisionSettings;
+    type RecordArgs = ();
+    type RecordOutput = Vec<u8>;
+    type LoadArgs = Vec<u8>;
+
+    fn save_item<I: Serialize>(&self, item: I, _args: ()) -> Result<Vec<u8>, RecorderError> {
+        bincode::serde::encode_to_vec(item, bincode::config::standard())
+            .map_err(|e| RecorderError::Unknown(e.to_string()))
+    }
+
+    fn load_item<I: DeserializeOwned>(&self, bytes: &mut Vec<u8>) -> Result<I, RecorderError> {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(item, _)| item)
+            .map_err(|e| RecorderError::DeserializeError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn_ndarray::NdArray;
+
+    #[test]
+    fn test_fingerprint_identifies_clones() {
+        let device = Default::default();
+        let brain = BotBrain::<NdArray>::new(&device);
+
+        assert_eq!(brain.fingerprint(), brain.clone().fingerprint());
+        assert_ne!(brain.fingerprint(), brain.mutate(0.05).fingerprint());
+        assert_ne!(
+            brain.fingerprint(),
+            BotBrain::<NdArray>::new(&device).fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_loading_keeps_the_layout_of_the_file() {
+        let device = Default::default();
+        let layout = FeatureLayout::for_team_size(8);
+        let brain = BotBrain::<NdArray>::with_layout(&device, layout);
+        let bytes = brain.to_bytes().unwrap();
+
+        let loaded = BotBrain::<NdArray>::from_bytes(bytes, &device).unwrap();
+        assert_eq!(loaded.layout(), layout);
+        assert_eq!(
+            BotBrain::<NdArray>::new(&device).layout(),
+            FeatureLayout::DEFAULT
+        );
+    }
 }
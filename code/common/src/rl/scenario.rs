@@ -0,0 +1,322 @@
+//! Training scenarios: hand-made situations such as a "2v1 corner fight", described in RON
+//! and played by the trainer (`--scenario`) and the client's training view.
+//!
+//! ```ron
+//! Scenario(
+//!     name: "2v1 corner fight",
+//!     map: Basic,
+//!     tanks: [
+//!         (team: Blue, driver: Learner, position: Some((80.0, 80.0))),
+//!         (team: Red, driver: Scripted(Hunter)),
+//!     ],
+//!     end: (max_ticks: Some(600)),
+//!     rewards: (kill_bonus: Some(800.0)),
+//! )
+//! ```
+
+use crate::ai::BotDifficulty;
+use crate::game::engine::GameEngine;
+use crate::game::player::PlayerInfo;
+use crate::net::protocol::{MapDefinition, MapName, PlayerId, Tank, Team};
+use glam::Vec2;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Extension of scenario files.
+pub const SCENARIO_EXTENSION: &str = "ron";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default = "default_map")]
+    pub map: MapName,
+    /// Tank `i` gets player id `i`
+    pub tanks: Vec<ScenarioTank>,
+    #[serde(default)]
+    pub end: EndConditions,
+    #[serde(default)]
+    pub rewards: RewardOverrides,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioTank {
+    pub team: Team,
+    pub driver: ScenarioDriver,
+    /// The team's next free spawn point if omitted
+    #[serde(default)]
+    pub position: Option<Vec2>,
+    /// Radians; Blue faces east and Red west if omitted
+    #[serde(default)]
+    pub rotation: Option<f32>,
+    /// Full health if omitted
+    #[serde(default)]
+    pub health: Option<f32>,
+    /// Share of a hit its front stops, from 0 up to (not including) 1; the balance
+    /// table's `front_armor` if omitted
+    #[serde(default)]
+    pub front_armor: Option<f32>,
+}
+
+/// Who controls a scenario tank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ScenarioDriver {
+    /// The model being trained or watched
+    Learner,
+    Scripted(BotDifficulty),
+}
+
+/// When a scenario match ends, besides one team being wiped out.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct EndConditions {
+    /// Overrides the trainer's `--max-ticks`
+    pub max_ticks: Option<usize>,
+    /// End as soon as any tank dies
+    pub first_kill: bool,
+}
+
+/// Reward weights that replace the trainer's defaults while the scenario is played.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RewardOverrides {
+    pub enemy_damage: Option<f32>,
+    pub friendly_damage_penalty: Option<f32>,
+    pub kill_bonus: Option<f32>,
+    pub teamkill_penalty: Option<f32>,
+}
+
+fn default_map() -> MapName {
+    MapName::Basic
+}
+
+impl Scenario {
+    /// Parses and validates a scenario.
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let scenario: Self = ron::from_str(text).map_err(|e| e.to_string())?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_ron(&text).map_err(|e| format!("Invalid scenario {}: {}", path.display(), e))
+    }
+
+    /// Every scenario in `dir`, sorted by name. Files that fail to load are skipped.
+    pub fn load_dir(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut scenarios: Vec<Self> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == SCENARIO_EXTENSION)
+            })
+            .filter_map(|path| Self::load(&path).ok())
+            .collect();
+        scenarios.sort_by(|a, b| a.name.cmp(&b.name));
+        scenarios
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let map = MapDefinition::load_name(self.map);
+        for team in [Team::Blue, Team::Red] {
+            if !self.tanks.iter().any(|t| t.team == team) {
+                return Err(format!("the {:?} team has no tanks", team));
+            }
+        }
+        for (i, tank) in self.tanks.iter().enumerate() {
+            if !matches!(tank.team, Team::Blue | Team::Red) {
+                return Err(format!("tank {} must be on the Blue or Red team", i));
+            }
+            if let Some(pos) = tank.position
+                && (!(0.0..=map.width).contains(&pos.x) || !(0.0..=map.height).contains(&pos.y))
+            {
+                return Err(format!("tank {} starts outside the map", i));
+            }
+            if tank
+                .front_armor
+                .is_some_and(|armor| !(0.0..1.0).contains(&armor))
+            {
+                return Err(format!(
+                    "tank {} must have a front_armor of at least 0 and below 1",
+                    i
+                ));
+            }
+        }
+        if self.learner_count() == 0 {
+            return Err("no tank is driven by the Learner".into());
+        }
+        if self.learners().any(|t| Some(t.team) != self.learner_team()) {
+            return Err("all Learner tanks must be on the same team".into());
+        }
+        Ok(())
+    }
+
+    fn learners(&self) -> impl Iterator<Item = &ScenarioTank> {
+        self.tanks
+            .iter()
+            .filter(|t| t.driver == ScenarioDriver::Learner)
+    }
+
+    pub fn learner_count(&self) -> usize {
+        self.learners().count()
+    }
+
+    /// The team the model plays for.
+    pub fn learner_team(&self) -> Option<Team> {
+        self.learners().next().map(|t| t.team)
+    }
+
+    /// An engine on the scenario's map with its tanks placed, tank `i` having id `i`.
+    pub fn spawn(&self) -> GameEngine {
+        let mut engine = GameEngine::new(MapDefinition::load_name(self.map));
+        let team_size = |team| self.tanks.iter().filter(|t| t.team == team).count();
+        let mut spawns = [Team::Blue, Team::Red]
+            .map(|team| engine.map.team_spawns(team, team_size(team)).into_iter());
+
+        for (i, spec) in self.tanks.iter().enumerate() {
+            let (side, name, facing) = match spec.team {
+                Team::Red => (1, "Red", std::f32::consts::PI),
+                _ => (0, "Blue", 0.0),
+            };
+            let next_spawn = spawns[side].next().unwrap_or_default();
+            let info = PlayerInfo::new(i as PlayerId, format!("{}_{}", name, i), spec.team);
+            let mut tank = Tank::new(info, spec.position.unwrap_or(next_spawn));
+            tank.rotation = spec.rotation.unwrap_or(facing);
+            if let Some(health) = spec.health {
+                tank.health = health;
+            }
+            if let Some(front_armor) = spec.front_armor {
+                tank.front_armor = front_armor;
+            }
+            engine.tanks.push(tank);
+        }
+        engine
+    }
+
+    /// Whether a match that has run `ticks` ticks with `kills` deaths so far is over.
+    /// `max_ticks` applies when the scenario doesn't set its own limit.
+    pub fn is_over(
+        &self,
+        engine: &GameEngine,
+        ticks: usize,
+        kills: usize,
+        max_ticks: usize,
+    ) -> bool {
+        let alive = |team| {
+            engine
+                .tanks
+                .iter()
+                .any(|t| t.player_info.team == team && t.health > 0.0)
+        };
+        !alive(Team::Blue)
+            || !alive(Team::Red)
+            || ticks >= self.end.max_ticks.unwrap_or(max_ticks)
+            || (self.end.first_kill && kills > 0)
+    }
+
+    /// Who won a finished match, given the team of every tank still alive: the team that
+    /// wiped out the other, or else the one that lost fewer tanks.
+    pub fn winner(&self, survivors: impl IntoIterator<Item = Team>) -> Option<Team> {
+        let survivors: Vec<Team> = survivors.into_iter().collect();
+        let alive = |team| survivors.iter().filter(|&&t| t == team).count();
+        let lost = |team| {
+            self.tanks
+                .iter()
+                .filter(|t| t.team == team)
+                .count()
+                .saturating_sub(alive(team))
+        };
+
+        match (alive(Team::Blue), alive(Team::Red)) {
+            (0, 0) => None,
+            (_, 0) => Some(Team::Blue),
+            (0, _) => Some(Team::Red),
+            _ => match lost(Team::Blue).cmp(&lost(Team::Red)) {
+                std::cmp::Ordering::Less => Some(Team::Blue),
+                std::cmp::Ordering::Greater => Some(Team::Red),
+                std::cmp::Ordering::Equal => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORNER_FIGHT: &str = r#"
+        Scenario(
+            name: "2v1 corner fight",
+            map: Basic,
+            tanks: [
+                (team: Blue, driver: Learner, position: Some((80.0, 80.0))),
+                (team: Blue, driver: Learner),
+                (team: Red, driver: Scripted(Hunter), health: Some(50.0), front_armor: Some(0.6)),
+            ],
+            end: (max_ticks: Some(300), first_kill: true),
+            rewards: (kill_bonus: Some(800.0)),
+        )
+    "#;
+
+    #[test]
+    fn test_scenario_is_parsed_and_spawned() {
+        let scenario = Scenario::from_ron(CORNER_FIGHT).unwrap();
+        assert_eq!(scenario.learner_count(), 2);
+        assert_eq!(scenario.learner_team(), Some(Team::Blue));
+        assert_eq!(scenario.rewards.kill_bonus, Some(800.0));
+        assert_eq!(scenario.rewards.enemy_damage, None);
+
+        let engine = scenario.spawn();
+        assert_eq!(engine.tanks.len(), 3);
+        assert_eq!(engine.tanks[0].position, Vec2::new(80.0, 80.0));
+        assert_eq!(
+            engine.tanks[1].position,
+            engine.map.team_spawns(Team::Blue, 2)[1]
+        );
+        assert_eq!(engine.tanks[2].player_info.id, 2);
+        assert_eq!(engine.tanks[2].health, 50.0);
+        assert_eq!(engine.tanks[2].front_armor, 0.6);
+        assert_eq!(
+            engine.tanks[0].front_armor,
+            crate::game::balance().front_armor
+        );
+
+        assert!(!scenario.is_over(&engine, 0, 0, 1000));
+        assert!(scenario.is_over(&engine, 300, 0, 1000));
+        assert!(scenario.is_over(&engine, 10, 1, 1000));
+
+        assert_eq!(scenario.winner([Team::Blue, Team::Blue, Team::Red]), None);
+        assert_eq!(scenario.winner([Team::Blue, Team::Red]), Some(Team::Red));
+        assert_eq!(scenario.winner([Team::Blue]), Some(Team::Blue));
+    }
+
+    #[test]
+    fn test_invalid_scenarios_are_rejected() {
+        let no_learner = CORNER_FIGHT.replace("Learner", "Scripted(Dummy)");
+        assert!(Scenario::from_ron(&no_learner).is_err());
+
+        let one_sided = CORNER_FIGHT.replace("team: Red", "team: Blue");
+        assert!(Scenario::from_ron(&one_sided).is_err());
+
+        let invulnerable = CORNER_FIGHT.replace("front_armor: Some(0.6)", "front_armor: Some(1.0)");
+        assert!(Scenario::from_ron(&invulnerable).is_err());
+
+        let outside = CORNER_FIGHT.replace("(80.0, 80.0)", "(-5.0, 80.0)");
+        assert!(Scenario::from_ron(&outside).is_err());
+
+        assert!(Scenario::from_ron("Scenario(name: \"x\")").is_err());
+    }
+
+    #[test]
+    fn test_bundled_scenarios_load() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/scenarios");
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        assert!(files > 0);
+        assert_eq!(Scenario::load_dir(&dir).len(), files);
+    }
+}
@@ -0,0 +1,14 @@
+//! Which build of the game is running, so mismatched clients and servers can be told
+//! apart even when they speak the same protocol version.
+
+/// Crate version, git commit and build profile, e.g. `0.1.0 (1a2b3c4, release)`.
+pub fn version() -> &'static str {
+    concat!(
+        env!("CARGO_PKG_VERSION"),
+        " (",
+        env!("NB_GIT_HASH"),
+        ", ",
+        env!("NB_BUILD_PROFILE"),
+        ")"
+    )
+}
@@ -0,0 +1,131 @@
+//! Serves the gym-style [`TankEnv`] to external training frameworks.
+//!
+//! Every connection gets its own environment. See [`protocol`] for the message format.
+
+use clap::Parser;
+use common::game::MAX_TEAM_SIZE;
+use common::protocol::{MapDefinition, MapName};
+use common::rl::{EnvConfig, TankEnv};
+use protocol::{Session, read_frame, write_frame};
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+mod protocol;
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// TCP address to listen on.
+    #[arg(long, default_value = "127.0.0.1:5555")]
+    listen: String,
+
+    /// Listen on this unix socket instead of TCP.
+    #[cfg(unix)]
+    #[arg(long)]
+    unix: Option<std::path::PathBuf>,
+
+    /// Map to play on (basic, loss, tiga).
+    #[arg(long, default_value = "basic")]
+    map: MapName,
+
+    /// Tanks per team, up to 8. Observations get longer for teams above 4.
+    #[arg(long, default_value_t = 4)]
+    team_size: usize,
+
+    /// Episode length limit, in steps.
+    #[arg(long, default_value_t = 1000)]
+    max_ticks: usize,
+}
+
+fn env_config(args: &Args) -> Result<EnvConfig, String> {
+    let map = MapDefinition::load_name(args.map);
+    if !(1..=MAX_TEAM_SIZE).contains(&args.team_size) {
+        return Err(format!("team size must be between 1 and {}", MAX_TEAM_SIZE));
+    }
+
+    Ok(EnvConfig {
+        map,
+        team_size: args.team_size,
+        max_ticks: args.max_ticks,
+        ..Default::default()
+    })
+}
+
+/// Answers requests until the client closes the connection.
+fn serve(mut stream: impl Read + Write, config: EnvConfig) -> io::Result<()> {
+    let mut session = Session::new(TankEnv::new(config, 0));
+    loop {
+        let request = match read_frame(&mut stream) {
+            Ok(request) => request,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        match session.handle_frame(&request) {
+            Some(response) => write_frame(&mut stream, &response)?,
+            None => return Ok(()),
+        }
+    }
+}
+
+fn spawn_session(stream: impl Read + Write + Send + 'static, config: EnvConfig, peer: String) {
+    thread::spawn(move || {
+        println!("Client connected: {}", peer);
+        match serve(stream, config) {
+            Ok(()) => println!("Client disconnected: {}", peer),
+            Err(e) => eprintln!("Connection to {} failed: {}", peer, e),
+        }
+    });
+}
+
+fn main() {
+    let args = Args::parse();
+    let config = match env_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    #[cfg(unix)]
+    if let Some(path) = &args.unix {
+        let _ = std::fs::remove_file(path);
+        let listener = match std::os::unix::net::UnixListener::bind(path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Failed to bind {}: {}", path.display(), e);
+                return;
+            }
+        };
+        println!("Environment server listening on {}", path.display());
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => spawn_session(stream, config.clone(), path.display().to_string()),
+                Err(e) => eprintln!("Failed to accept connection: {}", e),
+            }
+        }
+        return;
+    }
+
+    let listener = match TcpListener::bind(&args.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", args.listen, e);
+            return;
+        }
+    };
+    println!("Environment server listening on {}", args.listen);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let _ = stream.set_nodelay(true);
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_string(), |a| a.to_string());
+                spawn_session(stream, config.clone(), peer);
+            }
+            Err(e) => eprintln!("Failed to accept connection: {}", e),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Wire format: every message is a little-endian `u32` byte length followed by that many
+//! bytes of JSON. Requests and responses are objects tagged by `"type"`.
+
+use common::protocol::Team;
+use common::rl::ACTION_COUNT;
+use common::rl::env::{Action, Observation, TankEnv};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+/// Frames larger than this are rejected instead of allocated.
+const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Sizes of the environment.
+    Info,
+    /// Starts a new episode. Without a seed, the episode counter is used.
+    Reset { seed: Option<u64> },
+    /// One action (`action_size` floats) per agent, in agent order.
+    Step { actions: Vec<Action> },
+    /// Current observations, without advancing the simulation.
+    Observe,
+    /// Ends the connection.
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Info {
+        agents: usize,
+        teams: Vec<Team>,
+        observation_size: usize,
+        action_size: usize,
+        max_ticks: usize,
+    },
+    Observation {
+        observations: Vec<Observation>,
+    },
+    Step {
+        observations: Vec<Observation>,
+        rewards: Vec<f32>,
+        done: bool,
+        winner: Option<Team>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "frame of {} bytes exceeds the {} byte limit",
+                len, MAX_FRAME_LEN
+            ),
+        ));
+    }
+
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+pub fn write_frame(writer: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// One connected client and its private environment.
+pub struct Session {
+    env: TankEnv,
+    episodes: u64,
+}
+
+impl Session {
+    pub fn new(env: TankEnv) -> Self {
+        Self { env, episodes: 0 }
+    }
+
+    /// Answers one request; `None` means the client asked to close the connection.
+    pub fn handle(&mut self, request: Request) -> Option<Response> {
+        let response = match request {
+            Request::Info => Response::Info {
+                agents: self.env.agent_count(),
+                teams: (0..self.env.agent_count())
+                    .map(|agent| self.env.agent_team(agent))
+                    .collect(),
+                observation_size: self.env.observation_size(),
+                action_size: ACTION_COUNT,
+                max_ticks: self.env.config().max_ticks,
+            },
+            Request::Reset { seed } => {
+                let seed = seed.unwrap_or(self.episodes);
+                self.episodes += 1;
+                Response::Observation {
+                    observations: self.env.reset(seed),
+                }
+            }
+            Request::Step { actions } => self.step(&actions),
+            Request::Observe => Response::Observation {
+                observations: self.env.observe(),
+            },
+            Request::Close => return None,
+        };
+        Some(response)
+    }
+
+    fn step(&mut self, actions: &[Action]) -> Response {
+        if self.env.is_done() {
+            return Response::Error {
+                message: "episode is over, send a reset".to_string(),
+            };
+        }
+        if actions.len() != self.env.agent_count() {
+            return Response::Error {
+                message: format!(
+                    "expected {} actions, got {}",
+                    self.env.agent_count(),
+                    actions.len()
+                ),
+            };
+        }
+
+        let result = self.env.step(actions);
+        Response::Step {
+            observations: result.observations,
+            rewards: result.rewards,
+            done: result.done,
+            winner: result.winner,
+        }
+    }
+
+    /// Decodes a request frame and encodes the answer; malformed requests get an
+    /// [`Response::Error`] rather than closing the connection.
+    pub fn handle_frame(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
+        let response = match serde_json::from_slice::<Request>(payload) {
+            Ok(request) => self.handle(request)?,
+            Err(e) => Response::Error {
+                message: format!("invalid request: {}", e),
+            },
+        };
+        Some(serde_json::to_vec(&response).expect("responses always serialize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::rl::{EnvConfig, FEATURE_COUNT};
+    use std::io::Cursor;
+
+    fn session(max_ticks: usize) -> Session {
+        let config = EnvConfig {
+            team_size: 1,
+            max_ticks,
+            ..Default::default()
+        };
+        Session::new(TankEnv::new(config, 0))
+    }
+
+    fn ask(session: &mut Session, json: &str) -> serde_json::Value {
+        let reply = session.handle_frame(json.as_bytes()).unwrap();
+        serde_json::from_slice(&reply).unwrap()
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"{\"type\":\"info\"}").unwrap();
+        assert_eq!(&buffer[..4], &15u32.to_le_bytes());
+
+        let payload = read_frame(&mut Cursor::new(buffer)).unwrap();
+        assert_eq!(payload, b"{\"type\":\"info\"}");
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let buffer = (MAX_FRAME_LEN + 1).to_le_bytes().to_vec();
+        assert!(read_frame(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[test]
+    fn reset_and_step_follow_the_episode() {
+        let mut session = session(1);
+
+        let info = ask(&mut session, r#"{"type":"info"}"#);
+        assert_eq!(info["agents"], 2);
+        assert_eq!(info["observation_size"], FEATURE_COUNT);
+
+        let reset = ask(&mut session, r#"{"type":"reset","seed":3}"#);
+        assert_eq!(reset["type"], "observation");
+        assert_eq!(reset["observations"].as_array().unwrap().len(), 2);
+
+        let step = ask(
+            &mut session,
+            r#"{"type":"step","actions":[[0,0,0,0,0],[0,0,0,0,0]]}"#,
+        );
+        assert_eq!(step["type"], "step");
+        assert_eq!(step["done"], true);
+
+        let again = ask(
+            &mut session,
+            r#"{"type":"step","actions":[[0,0,0,0,0],[0,0,0,0,0]]}"#,
+        );
+        assert_eq!(again["type"], "error");
+    }
+
+    #[test]
+    fn bad_requests_get_errors() {
+        let mut session = session(10);
+        assert_eq!(ask(&mut session, "not json")["type"], "error");
+        assert_eq!(
+            ask(&mut session, r#"{"type":"step","actions":[[0,0,0,0,0]]}"#)["type"],
+            "error"
+        );
+        assert!(session.handle_frame(br#"{"type":"close"}"#).is_none());
+    }
+}
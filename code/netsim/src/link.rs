@@ -0,0 +1,204 @@
+//! What the network does to packets on their way: when each one arrives, if at all.
+
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Least a reordered packet is held back, so it is overtaken even with no latency set.
+const REORDER_HOLD: Duration = Duration::from_millis(20);
+
+/// How bad a player's connection is, the same both ways.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conditions {
+    /// One-way delay; a round trip takes twice as long
+    pub latency: Duration,
+    /// Most a packet's delay strays from `latency`, either way
+    pub jitter: Duration,
+    /// Share of packets dropped, from 0 to 1
+    pub loss: f64,
+    /// Share of packets held back long enough for later ones to overtake them, from 0 to 1
+    pub reorder: f64,
+}
+
+impl Conditions {
+    pub const PERFECT: Self = Self {
+        latency: Duration::ZERO,
+        jitter: Duration::ZERO,
+        loss: 0.0,
+        reorder: 0.0,
+    };
+
+    /// Changes one setting given as `name=value`: `latency` and `jitter` in milliseconds,
+    /// `loss` and `reorder` in percent.
+    pub fn set(&mut self, setting: &str) -> Result<(), String> {
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=value, got {}", setting))?;
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("{} is not a number", value))?;
+        let millis = || {
+            (value >= 0.0)
+                .then(|| Duration::from_secs_f64(value / 1000.0))
+                .ok_or_else(|| format!("{} can't be negative", name))
+        };
+        let share = || {
+            (0.0..=100.0)
+                .contains(&value)
+                .then_some(value / 100.0)
+                .ok_or_else(|| format!("{} is a percentage from 0 to 100", name))
+        };
+        match name.trim() {
+            "latency" => self.latency = millis()?,
+            "jitter" => self.jitter = millis()?,
+            "loss" => self.loss = share()?,
+            "reorder" => self.reorder = share()?,
+            other => return Err(format!("no setting called {}", other)),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "latency {} ms, jitter {} ms, loss {}%, reorder {}%",
+            self.latency.as_millis(),
+            self.jitter.as_millis(),
+            self.loss * 100.0,
+            self.reorder * 100.0
+        )
+    }
+}
+
+/// Packets on their way in one direction.
+#[derive(Debug, Default)]
+pub struct Link {
+    /// Packets by when they arrive, then by when they were sent
+    in_flight: BTreeMap<(Instant, u64), Vec<u8>>,
+    sent: u64,
+    /// When the last packet that kept its place in line arrives
+    last_in_line: Option<Instant>,
+}
+
+impl Link {
+    /// Puts `packet` on its way at `now`. Returns false if the network lost it.
+    pub fn send(
+        &mut self,
+        packet: &[u8],
+        now: Instant,
+        conditions: &Conditions,
+        rng: &mut impl Rng,
+    ) -> bool {
+        if rng.random_bool(conditions.loss) {
+            return false;
+        }
+        let jitter = conditions.jitter.as_secs_f64();
+        let offset = if jitter > 0.0 {
+            rng.random_range(-jitter..=jitter)
+        } else {
+            0.0
+        };
+        let delay = Duration::from_secs_f64((conditions.latency.as_secs_f64() + offset).max(0.0));
+        let mut arrival = now + delay;
+
+        if rng.random_bool(conditions.reorder) {
+            arrival += conditions.jitter + conditions.latency.max(REORDER_HOLD);
+        } else {
+            // Jitter alone never reorders packets, like on most real connections
+            if let Some(last) = self.last_in_line {
+                arrival = arrival.max(last);
+            }
+            self.last_in_line = Some(arrival);
+        }
+
+        self.in_flight.insert((arrival, self.sent), packet.to_vec());
+        self.sent += 1;
+        true
+    }
+
+    /// Takes the packets that have arrived by `now`, in the order they arrived.
+    pub fn arrived(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        let later = self.in_flight.split_off(&(now, u64::MAX));
+        std::mem::replace(&mut self.in_flight, later)
+            .into_values()
+            .collect()
+    }
+
+    /// When the next packet arrives, if any are on their way.
+    pub fn next_arrival(&self) -> Option<Instant> {
+        self.in_flight.keys().next().map(|(arrival, _)| *arrival)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn send_all(link: &mut Link, conditions: &Conditions, now: Instant, count: u8) -> usize {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..count)
+            .filter(|i| link.send(&[*i], now, conditions, &mut rng))
+            .count()
+    }
+
+    #[test]
+    fn test_packets_arrive_in_order_after_the_latency() {
+        let conditions = Conditions {
+            latency: Duration::from_millis(50),
+            jitter: Duration::from_millis(30),
+            ..Conditions::PERFECT
+        };
+        let mut link = Link::default();
+        let now = Instant::now();
+        assert_eq!(send_all(&mut link, &conditions, now, 100), 100);
+
+        assert!(link.arrived(now + Duration::from_millis(19)).is_empty());
+        let mut arrived = link.arrived(now + Duration::from_millis(80));
+        arrived.extend(link.arrived(now + Duration::from_millis(200)));
+        assert_eq!(arrived, (0..100).map(|i| vec![i]).collect::<Vec<_>>());
+        assert_eq!(link.next_arrival(), None);
+    }
+
+    #[test]
+    fn test_packets_get_lost_and_overtaken_as_often_as_set() {
+        let mut conditions = Conditions::PERFECT;
+        conditions.set("loss=20").unwrap();
+        conditions.set("reorder=10").unwrap();
+        let mut link = Link::default();
+        let now = Instant::now();
+        let delivered = send_all(&mut link, &conditions, now, 250);
+        assert!((170..230).contains(&delivered), "{} delivered", delivered);
+
+        let arrived = link.arrived(now + Duration::from_secs(1));
+        assert_eq!(arrived.len(), delivered);
+        let overtaken = arrived
+            .iter()
+            .enumerate()
+            .filter(|(i, packet)| arrived[..*i].iter().any(|earlier| earlier > packet))
+            .count();
+        assert!((5..40).contains(&overtaken), "{} overtaken", overtaken);
+    }
+
+    #[test]
+    fn test_settings_are_checked() {
+        let mut conditions = Conditions::PERFECT;
+        conditions.set("latency=80").unwrap();
+        conditions.set("jitter=12.5").unwrap();
+        assert_eq!(conditions.latency, Duration::from_millis(80));
+        assert_eq!(conditions.jitter, Duration::from_micros(12_500));
+        assert!(conditions.set("loss=120").is_err());
+        assert!(conditions.set("latency=-1").is_err());
+        assert!(conditions.set("latency=inf").is_err());
+        assert!(conditions.set("speed=3").is_err());
+        assert!(conditions.set("loss").is_err());
+        assert_eq!(conditions.loss, 0.0);
+    }
+}
@@ -0,0 +1,89 @@
+//! Sits between players and a server on a developer's machine and makes the network worse on
+//! purpose: packets arrive late, jittery, out of order or not at all, as set per player. Type
+//! `list` or `set` into its console to change the conditions while a match is running.
+
+mod link;
+mod proxy;
+
+use clap::Parser;
+use link::Conditions;
+use proxy::Proxy;
+use std::io::BufRead;
+use std::net::SocketAddr;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// UDP address players connect to instead of the server.
+    #[arg(long, default_value_t = SocketAddr::from(([0, 0, 0, 0], 9080)))]
+    listen: SocketAddr,
+
+    /// The server the packets are passed on to.
+    #[arg(long, default_value_t = SocketAddr::from(([127, 0, 0, 1], 8080)))]
+    server: SocketAddr,
+
+    /// One-way delay in milliseconds.
+    #[arg(long, default_value_t = 0.0)]
+    latency: f64,
+
+    /// Most a packet's delay strays from the latency, in milliseconds.
+    #[arg(long, default_value_t = 0.0)]
+    jitter: f64,
+
+    /// Percentage of packets dropped.
+    #[arg(long, default_value_t = 0.0)]
+    loss: f64,
+
+    /// Percentage of packets overtaken by later ones.
+    #[arg(long, default_value_t = 0.0)]
+    reorder: f64,
+
+    /// Makes which packets are lost and delayed the same from run to run.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+impl Args {
+    fn conditions(&self) -> Result<Conditions, String> {
+        let mut conditions = Conditions::PERFECT;
+        conditions.set(&format!("latency={}", self.latency))?;
+        conditions.set(&format!("jitter={}", self.jitter))?;
+        conditions.set(&format!("loss={}", self.loss))?;
+        conditions.set(&format!("reorder={}", self.reorder))?;
+        Ok(conditions)
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let conditions = args.conditions().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    });
+    let mut proxy = match Proxy::bind(args.listen, args.server, conditions, args.seed) {
+        Ok(proxy) => proxy,
+        Err(err) => {
+            eprintln!("Can't listen on {}: {}", args.listen, err);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Passing packets from {} on to {} with {}",
+        proxy.local_addr().unwrap_or(args.listen),
+        args.server,
+        conditions
+    );
+
+    let (commands, received) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if commands.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    if let Err(err) = proxy.run(received) {
+        eprintln!("Proxy stopped: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,294 @@
+//! Stands between players and a server, passing packets on as late, as rarely and as out of
+//! order as each player's [`Conditions`] say.
+
+use crate::link::{Conditions, Link};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// Large enough for any netcode packet.
+const MAX_PACKET_SIZE: usize = 2048;
+/// Players who send nothing for this long are forgotten.
+const PEER_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest the proxy sleeps with nothing to do.
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+struct Peer {
+    /// Shown in the console to pick the player by
+    id: u32,
+    /// Talks to the server for this player, so each player keeps an address of their own
+    upstream: UdpSocket,
+    conditions: Conditions,
+    to_server: Link,
+    to_player: Link,
+    last_seen: Instant,
+}
+
+pub struct Proxy {
+    listen: UdpSocket,
+    server: SocketAddr,
+    /// Conditions of players who connect from now on
+    defaults: Conditions,
+    /// Players by their address
+    peers: HashMap<SocketAddr, Peer>,
+    next_id: u32,
+    rng: StdRng,
+}
+
+impl Proxy {
+    /// Listens for players at `listen`, passing their packets on to `server`. A `seed` makes
+    /// which packets get lost and how late the rest arrive the same from run to run.
+    pub fn bind(
+        listen: SocketAddr,
+        server: SocketAddr,
+        defaults: Conditions,
+        seed: Option<u64>,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(listen)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            listen: socket,
+            server,
+            defaults,
+            peers: HashMap::new(),
+            next_id: 1,
+            rng: seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listen.local_addr()
+    }
+
+    /// Passes packets on until a socket fails, taking console commands from `commands`.
+    pub fn run(&mut self, commands: Receiver<String>) -> io::Result<()> {
+        loop {
+            while let Ok(command) = commands.try_recv() {
+                println!("{}", self.command(&command));
+            }
+            let now = Instant::now();
+            self.step(now)?;
+
+            let next = self
+                .peers
+                .values()
+                .flat_map(|peer| [peer.to_server.next_arrival(), peer.to_player.next_arrival()])
+                .flatten()
+                .min();
+            let sleep = next.map_or(IDLE_SLEEP, |next| {
+                next.saturating_duration_since(now).min(IDLE_SLEEP)
+            });
+            std::thread::sleep(sleep);
+        }
+    }
+
+    /// Takes in whatever was sent since the last step and delivers whatever has arrived by
+    /// `now`.
+    pub fn step(&mut self, now: Instant) -> io::Result<()> {
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        loop {
+            let (len, from) = match self.listen.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                // An ICMP error for an earlier packet, e.g. a player that went away
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(err),
+            };
+            if !self.peers.contains_key(&from) {
+                let peer = self.connect(from)?;
+                self.peers.insert(from, peer);
+            }
+            let peer = self.peers.get_mut(&from).unwrap();
+            peer.last_seen = now;
+            peer.to_server
+                .send(&buf[..len], now, &peer.conditions, &mut self.rng);
+        }
+
+        for peer in self.peers.values_mut() {
+            loop {
+                let len = match peer.upstream.recv(&mut buf) {
+                    Ok(len) => len,
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::ConnectionReset
+                        ) =>
+                    {
+                        break;
+                    }
+                    Err(err) => return Err(err),
+                };
+                peer.to_player
+                    .send(&buf[..len], now, &peer.conditions, &mut self.rng);
+            }
+        }
+
+        for (addr, peer) in &mut self.peers {
+            for packet in peer.to_server.arrived(now) {
+                let _ = peer.upstream.send(&packet);
+            }
+            for packet in peer.to_player.arrived(now) {
+                let _ = self.listen.send_to(&packet, addr);
+            }
+        }
+
+        self.peers.retain(|addr, peer| {
+            let active = now.saturating_duration_since(peer.last_seen) < PEER_TIMEOUT;
+            if !active {
+                println!("Player {} at {} went quiet", peer.id, addr);
+            }
+            active
+        });
+        Ok(())
+    }
+
+    fn connect(&mut self, player: SocketAddr) -> io::Result<Peer> {
+        let ip = self.listen.local_addr()?.ip();
+        let upstream = UdpSocket::bind((ip, 0))?;
+        upstream.connect(self.server)?;
+        upstream.set_nonblocking(true)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        println!(
+            "Player {} connected from {} ({})",
+            id, player, self.defaults
+        );
+        Ok(Peer {
+            id,
+            upstream,
+            conditions: self.defaults,
+            to_server: Link::default(),
+            to_player: Link::default(),
+            last_seen: Instant::now(),
+        })
+    }
+
+    /// Runs a console command, returning what to show for it:
+    /// - `list` shows the conditions of every player
+    /// - `set <player|all|new> name=value...` changes the conditions of one player, of
+    ///   everyone, or of players who connect from now on
+    pub fn command(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+        match words.next() {
+            Some("list") => {
+                let mut peers: Vec<_> = self.peers.iter().collect();
+                peers.sort_by_key(|(_, peer)| peer.id);
+                let mut lines = vec![format!("new: {}", self.defaults)];
+                lines.extend(
+                    peers
+                        .into_iter()
+                        .map(|(addr, peer)| format!("{} ({}): {}", peer.id, addr, peer.conditions)),
+                );
+                lines.join("\n")
+            }
+            Some("set") => {
+                let who = words.next().unwrap_or_default();
+                let settings: Vec<_> = words.collect();
+                if settings.is_empty() {
+                    return "usage: set <player|all|new> latency=ms jitter=ms loss=% reorder=%"
+                        .into();
+                }
+                let targets: Vec<&mut Conditions> = match who {
+                    "all" => self
+                        .peers
+                        .values_mut()
+                        .map(|peer| &mut peer.conditions)
+                        .chain([&mut self.defaults])
+                        .collect(),
+                    "new" => vec![&mut self.defaults],
+                    id => match self
+                        .peers
+                        .values_mut()
+                        .find(|peer| id.parse() == Ok(peer.id))
+                    {
+                        Some(peer) => vec![&mut peer.conditions],
+                        None => return format!("no player {}", id),
+                    },
+                };
+                // Checked on a copy first, so a bad setting changes nothing
+                let mut checked = *targets[0];
+                if let Err(err) = settings.iter().try_for_each(|setting| checked.set(setting)) {
+                    return err;
+                }
+                for conditions in targets {
+                    for setting in &settings {
+                        let _ = conditions.set(setting);
+                    }
+                }
+                self.command("list")
+            }
+            _ => {
+                "commands: list, set <player|all|new> latency=ms jitter=ms loss=% reorder=%".into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_players_get_their_own_address_and_conditions() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut proxy = Proxy::bind(
+            "127.0.0.1:0".parse().unwrap(),
+            server.local_addr().unwrap(),
+            Conditions::PERFECT,
+            Some(1),
+        )
+        .unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        let (first, second) = (
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+        );
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+
+        first.send_to(b"one", proxy_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        proxy.step(Instant::now()).unwrap();
+        assert!(
+            proxy
+                .command("set new latency=100")
+                .starts_with("new: latency 100 ms")
+        );
+        second.send_to(b"two", proxy_addr).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        let now = Instant::now();
+        proxy.step(now).unwrap();
+
+        // The first player still has a perfect connection, the second waits out its latency
+        let (len, first_upstream) = server.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"one");
+        proxy.step(now + Duration::from_millis(99)).unwrap();
+        server.set_nonblocking(true).unwrap();
+        assert!(server.recv_from(&mut buf).is_err());
+        proxy.step(now + Duration::from_millis(100)).unwrap();
+        server.set_nonblocking(false).unwrap();
+        let (len, second_upstream) = server.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"two");
+        assert_ne!(first_upstream, second_upstream);
+
+        // Replies find their way back to the right player
+        server.send_to(b"back", first_upstream).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        proxy.step(Instant::now()).unwrap();
+        first
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let (len, from) = first.recv_from(&mut buf).unwrap();
+        assert_eq!((&buf[..len], from), (&b"back"[..], proxy_addr));
+
+        assert!(proxy.command("set 3 loss=5").starts_with("no player"));
+        assert!(proxy.command("set all loss=500").contains("percentage"));
+        assert!(proxy.command("list").contains("2 ("));
+    }
+}
@@ -0,0 +1,59 @@
+//! Relays netcode traffic for servers that players can't reach directly, like one hosted
+//! behind a home router. See [`common::relay`] for how it works.
+
+mod rooms;
+
+use clap::Parser;
+use common::relay::DEFAULT_RELAY_PORT;
+use rooms::Relay;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// UDP address servers register at.
+    #[arg(long, default_value_t = SocketAddr::from(([0, 0, 0, 0], DEFAULT_RELAY_PORT)))]
+    listen: SocketAddr,
+
+    /// Ports players connect to, one per relayed server, e.g. `7001-7100`.
+    #[arg(long, default_value = "7001-7100", value_parser = parse_port_range)]
+    room_ports: RangeInclusive<u16>,
+
+    /// Servers relayed at the same time.
+    #[arg(long, default_value_t = 64)]
+    max_rooms: usize,
+}
+
+fn parse_port_range(range: &str) -> Result<RangeInclusive<u16>, String> {
+    let (first, last) = range
+        .split_once('-')
+        .ok_or("expected a range like 7001-7100")?;
+    let parse = |port: &str| port.trim().parse::<u16>().map_err(|e| e.to_string());
+    let (first, last) = (parse(first)?, parse(last)?);
+    if first == 0 || first > last {
+        return Err(format!("{} is not a range of ports", range));
+    }
+    Ok(first..=last)
+}
+
+fn main() {
+    let args = Args::parse();
+    let relay = match Relay::bind(args.listen, Some(args.room_ports.clone()), args.max_rooms) {
+        Ok(relay) => relay,
+        Err(err) => {
+            eprintln!("Can't listen on {}: {}", args.listen, err);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Relay listening on {}, rooms on ports {}-{}",
+        relay.local_addr().unwrap_or(args.listen),
+        args.room_ports.start(),
+        args.room_ports.end()
+    );
+    if let Err(err) = relay.run() {
+        eprintln!("Relay stopped: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,237 @@
+//! The relay's rooms: one public UDP port per registered server.
+
+use common::relay::{ROOM_TIMEOUT, RelayPacket};
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Large enough for any netcode packet with the relay header around it.
+const MAX_PACKET_SIZE: usize = 2048;
+/// How often rooms check whether their server is still around.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Room {
+    port: u16,
+    socket: Arc<UdpSocket>,
+    last_seen: Instant,
+}
+
+type Rooms = Arc<Mutex<HashMap<SocketAddr, Room>>>;
+
+pub struct Relay {
+    control: Arc<UdpSocket>,
+    /// Rooms by the address of the server they relay for
+    rooms: Rooms,
+    /// Ports rooms are opened on, any free port if `None`
+    ports: Option<RangeInclusive<u16>>,
+    max_rooms: usize,
+}
+
+impl Relay {
+    pub fn bind(
+        listen: SocketAddr,
+        ports: Option<RangeInclusive<u16>>,
+        max_rooms: usize,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            control: Arc::new(UdpSocket::bind(listen)?),
+            rooms: Arc::default(),
+            ports,
+            max_rooms,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.control.local_addr()
+    }
+
+    /// Handles the control socket until it fails.
+    pub fn run(&self) -> io::Result<()> {
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        let mut reply = Vec::new();
+        loop {
+            let (len, from) = match self.control.recv_from(&mut buf) {
+                Ok(received) => received,
+                // An ICMP error for an earlier packet, e.g. a server that went away
+                Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(err) => return Err(err),
+            };
+
+            match RelayPacket::decode(&buf[..len]) {
+                Some(RelayPacket::Register) => match self.register(from) {
+                    Ok(port) => {
+                        reply.clear();
+                        RelayPacket::Registered { port }.encode(&mut reply);
+                        let _ = self.control.send_to(&reply, from);
+                    }
+                    Err(err) => eprintln!("Can't open a room for {}: {}", from, err),
+                },
+                Some(RelayPacket::Data { peer, payload }) => {
+                    let socket = self
+                        .rooms
+                        .lock()
+                        .unwrap()
+                        .get(&from)
+                        .map(|room| room.socket.clone());
+                    if let Some(socket) = socket {
+                        let _ = socket.send_to(payload, peer);
+                    }
+                }
+                Some(RelayPacket::Registered { .. }) | None => {}
+            }
+        }
+    }
+
+    /// Renews the room of the server at `host`, opening it first if needed. Returns its port.
+    fn register(&self, host: SocketAddr) -> io::Result<u16> {
+        let mut rooms = self.rooms.lock().unwrap();
+        if let Some(room) = rooms.get_mut(&host) {
+            room.last_seen = Instant::now();
+            return Ok(room.port);
+        }
+        if rooms.len() >= self.max_rooms {
+            return Err(io::Error::other("all rooms are taken"));
+        }
+
+        let socket = Arc::new(self.bind_room(&rooms)?);
+        let port = socket.local_addr()?.port();
+        rooms.insert(
+            host,
+            Room {
+                port,
+                socket: socket.clone(),
+                last_seen: Instant::now(),
+            },
+        );
+        println!("Opened room on port {} for {}", port, host);
+
+        let control = self.control.clone();
+        let rooms = self.rooms.clone();
+        std::thread::spawn(move || forward_to_host(&socket, &control, host, &rooms));
+        Ok(port)
+    }
+
+    fn bind_room(&self, rooms: &HashMap<SocketAddr, Room>) -> io::Result<UdpSocket> {
+        let ip = self.control.local_addr()?.ip();
+        let Some(ports) = self.ports.clone() else {
+            return UdpSocket::bind((ip, 0));
+        };
+        ports
+            .filter(|port| rooms.values().all(|room| room.port != *port))
+            .find_map(|port| UdpSocket::bind((ip, port)).ok())
+            .ok_or_else(|| io::Error::other("no free room port"))
+    }
+}
+
+/// Passes what players send to the room on to its server, until the server stops renewing
+/// the room.
+fn forward_to_host(socket: &UdpSocket, control: &UdpSocket, host: SocketAddr, rooms: &Rooms) {
+    let _ = socket.set_read_timeout(Some(EXPIRY_CHECK_INTERVAL));
+    let port = socket.local_addr().map_or(0, |addr| addr.port());
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    let mut packet = Vec::new();
+    let mut next_check = Instant::now() + EXPIRY_CHECK_INTERVAL;
+
+    loop {
+        if let Ok((len, peer)) = socket.recv_from(&mut buf) {
+            packet.clear();
+            RelayPacket::Data {
+                peer,
+                payload: &buf[..len],
+            }
+            .encode(&mut packet);
+            let _ = control.send_to(&packet, host);
+        }
+
+        if Instant::now() < next_check {
+            continue;
+        }
+        next_check = Instant::now() + EXPIRY_CHECK_INTERVAL;
+        let mut rooms = rooms.lock().unwrap();
+        if rooms
+            .get(&host)
+            .is_some_and(|room| room.last_seen.elapsed() > ROOM_TIMEOUT)
+        {
+            rooms.remove(&host);
+            println!("Closed room on port {} for {}", port, host);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(socket: &UdpSocket) -> (Vec<u8>, SocketAddr) {
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        let (len, from) = socket.recv_from(&mut buf).unwrap();
+        (buf[..len].to_vec(), from)
+    }
+
+    #[test]
+    fn test_room_forwards_both_ways() {
+        let relay = Relay::bind("127.0.0.1:0".parse().unwrap(), None, 1).unwrap();
+        let relay_addr = relay.local_addr().unwrap();
+        std::thread::spawn(move || relay.run());
+
+        let socket = || {
+            let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+            socket
+        };
+        let (host, player, other_host) = (socket(), socket(), socket());
+        let send = |socket: &UdpSocket, packet: RelayPacket| {
+            let mut buf = Vec::new();
+            packet.encode(&mut buf);
+            socket.send_to(&buf, relay_addr).unwrap();
+        };
+
+        send(&host, RelayPacket::Register);
+        let (reply, _) = receive(&host);
+        let Some(RelayPacket::Registered { port }) = RelayPacket::decode(&reply) else {
+            panic!("expected a room, got {:?}", reply);
+        };
+        let room: SocketAddr = ([127, 0, 0, 1], port).into();
+
+        // Player to server, with the player's address
+        player.send_to(b"hello", room).unwrap();
+        let (packet, _) = receive(&host);
+        let player_addr = player.local_addr().unwrap();
+        assert_eq!(
+            RelayPacket::decode(&packet),
+            Some(RelayPacket::Data {
+                peer: player_addr,
+                payload: b"hello"
+            })
+        );
+
+        // Server to player, from the room's port
+        send(
+            &host,
+            RelayPacket::Data {
+                peer: player_addr,
+                payload: b"world",
+            },
+        );
+        assert_eq!(receive(&player), (b"world".to_vec(), room));
+
+        // Renewing keeps the room; there is no room for anyone else
+        send(&host, RelayPacket::Register);
+        assert_eq!(
+            RelayPacket::decode(&receive(&host).0),
+            Some(RelayPacket::Registered { port })
+        );
+        send(&other_host, RelayPacket::Register);
+        other_host
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let mut buf = [0u8; 16];
+        assert!(other_host.recv_from(&mut buf).is_err());
+    }
+}
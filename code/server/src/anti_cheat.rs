@@ -0,0 +1,118 @@
+//! Server-side checks against modified clients: inputs sent faster than games tick, and
+//! tanks covering more ground in a tick than their speed allows.
+
+use common::protocol::InputPayload;
+use std::time::Duration;
+
+/// Inputs a client may send per second. Clients send one per game update, at the tick
+/// rate, so this leaves room for network jitter.
+const MAX_INPUTS_PER_SEC: u32 = 120;
+/// Extra distance a tank may cover in a tick, for collisions pushing it around.
+pub(crate) const DISPLACEMENT_SLACK: f32 = 20.0;
+/// Seconds in a row with violations after which a client is kicked.
+const KICK_AFTER: u32 = 5;
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Violations of one client, counted in windows of a second.
+#[derive(Debug, Default)]
+pub(crate) struct ClientGuard {
+    elapsed: Duration,
+    inputs: u32,
+    violations: u32,
+    /// Windows in a row that had violations
+    strikes: u32,
+}
+
+impl ClientGuard {
+    /// Counts an input. Returns false once the client is over the rate, or for values no
+    /// honest client sends; the input is then dropped.
+    pub fn allow_input(&mut self, input: &InputPayload) -> bool {
+        self.inputs += 1;
+        let finite = input.move_axis.is_finite() && input.aim_pos.is_finite();
+        if self.inputs > MAX_INPUTS_PER_SEC || !finite {
+            self.violations += 1;
+            return false;
+        }
+        true
+    }
+
+    /// Counts a move that had to be clamped.
+    pub fn flag_move(&mut self) {
+        self.violations += 1;
+    }
+
+    /// Advances time. Returns true when the client has been violating for so long that
+    /// it should be kicked.
+    pub fn tick(&mut self, dt: Duration) -> bool {
+        self.elapsed += dt;
+        if self.elapsed >= WINDOW {
+            self.elapsed = Duration::ZERO;
+            self.strikes = if self.violations > 0 {
+                self.strikes + 1
+            } else {
+                0
+            };
+            self.inputs = 0;
+            self.violations = 0;
+        }
+        self.strikes >= KICK_AFTER
+    }
+}
+
+/// Totals since the server started, for the `status` command.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct AntiCheatStats {
+    pub dropped_inputs: u64,
+    pub clamped_moves: u64,
+    pub kicks: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    #[test]
+    fn test_persistent_flooding_gets_kicked() {
+        let mut guard = ClientGuard::default();
+        let input = InputPayload {
+            move_axis: Vec2::X,
+            aim_pos: Vec2::ZERO,
+            shoot: false,
+            grenade: false,
+        };
+        // A normal client stays within the rate
+        for _ in 0..60 {
+            assert!(guard.allow_input(&input));
+        }
+        assert!(!guard.tick(WINDOW));
+
+        for second in 1..=KICK_AFTER {
+            let dropped = (0..2 * MAX_INPUTS_PER_SEC)
+                .filter(|_| !guard.allow_input(&input))
+                .count();
+            assert_eq!(dropped, MAX_INPUTS_PER_SEC as usize);
+            assert_eq!(guard.tick(WINDOW), second == KICK_AFTER);
+        }
+    }
+
+    #[test]
+    fn test_a_clean_second_clears_strikes() {
+        let mut guard = ClientGuard::default();
+        let broken = InputPayload {
+            move_axis: Vec2::new(f32::INFINITY, 0.0),
+            aim_pos: Vec2::ZERO,
+            shoot: false,
+            grenade: false,
+        };
+        assert!(!guard.allow_input(&broken));
+        assert!(!guard.tick(WINDOW));
+        for _ in 0..KICK_AFTER - 2 {
+            guard.flag_move();
+            assert!(!guard.tick(WINDOW));
+        }
+        assert!(!guard.tick(WINDOW));
+        guard.flag_move();
+        assert!(!guard.tick(WINDOW));
+    }
+}
@@ -0,0 +1,169 @@
+//! The snapshots each client was sent lately, so game updates go out as changes from the
+//! latest one the client says it has. A client that fell too far behind, or has none,
+//! gets whole snapshots until it catches up.
+
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use common::protocol::{GameDelta, GameSnapshot, GameUpdate, ServerMessage};
+use renet::ClientId;
+
+/// Snapshots kept per client, about half a second of them
+const KEPT: usize = 32;
+
+/// A snapshot sent, with its tick
+type Sent = (u32, Rc<GameSnapshot>);
+
+#[derive(Default)]
+pub struct Baselines {
+    /// Oldest first, by tick
+    sent: HashMap<ClientId, VecDeque<Sent>>,
+    /// The tick of the latest snapshot each client has
+    acked: HashMap<ClientId, u32>,
+}
+
+impl Baselines {
+    /// `client_id` has the snapshot of tick `snapshot`, or none if `None`.
+    pub fn acknowledge(&mut self, client_id: ClientId, snapshot: Option<u32>) {
+        match snapshot {
+            Some(tick) => self.acked.insert(client_id, tick),
+            None => self.acked.remove(&client_id),
+        };
+    }
+
+    pub fn forget(&mut self, client_id: ClientId) {
+        self.sent.remove(&client_id);
+        self.acked.remove(&client_id);
+    }
+
+    /// The messages carrying `updates`, each to its recipients. Recipients of an update
+    /// who have the same snapshot share a delta from it; the others get it whole.
+    pub fn pack(
+        &mut self,
+        updates: Vec<(Vec<ClientId>, GameUpdate)>,
+    ) -> Vec<(Vec<ClientId>, ServerMessage)> {
+        let mut messages = Vec::new();
+        for (recipients, update) in updates {
+            let mut by_baseline: Vec<(Option<Sent>, Vec<ClientId>)> = Vec::new();
+            for &client_id in &recipients {
+                let baseline = self.baseline(client_id);
+                let same = |other: &Option<Sent>| match (&baseline, other) {
+                    (None, None) => true,
+                    (Some((tick, base)), Some((other_tick, other_base))) => {
+                        tick == other_tick && (Rc::ptr_eq(base, other_base) || base == other_base)
+                    }
+                    _ => false,
+                };
+                match by_baseline.iter_mut().find(|(other, _)| same(other)) {
+                    Some((_, clients)) => clients.push(client_id),
+                    None => by_baseline.push((baseline, vec![client_id])),
+                }
+            }
+            for (baseline, clients) in by_baseline {
+                let delta = baseline
+                    .and_then(|(tick, base)| GameDelta::of(&update, &base, tick))
+                    .map(ServerMessage::GameDelta);
+                let message = delta.unwrap_or_else(|| ServerMessage::GameUpdate(update.clone()));
+                messages.push((clients, message));
+            }
+
+            let snapshot = Rc::new(update.snapshot);
+            for client_id in recipients {
+                let sent = self.sent.entry(client_id).or_default();
+                if sent.len() == KEPT {
+                    sent.pop_front();
+                }
+                sent.push_back((update.tick, snapshot.clone()));
+            }
+        }
+        messages
+    }
+
+    /// The snapshot `client_id` said it has, if it is still kept.
+    fn baseline(&self, client_id: ClientId) -> Option<Sent> {
+        let tick = *self.acked.get(&client_id)?;
+        self.sent
+            .get(&client_id)?
+            .iter()
+            .find(|(sent, _)| *sent == tick)
+            .map(|(_, snapshot)| (tick, snapshot.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::{EngineSnapshot, GameState, Tank, Team};
+    use glam::Vec2;
+
+    fn update(tick: u32, x: f32) -> GameUpdate {
+        let tanks = [0, 1]
+            .map(|id| {
+                Tank::new(
+                    PlayerInfo::new(id, format!("p{}", id), Team::Blue),
+                    Vec2::new(x * id as f32, 10.),
+                )
+            })
+            .to_vec();
+        GameUpdate {
+            tick,
+            snapshot: GameSnapshot {
+                engine: EngineSnapshot {
+                    tanks,
+                    projectiles: Vec::new(),
+                    moving_walls: Vec::new(),
+                },
+                state: GameState::Battle(60),
+                game_master: 1,
+                host: Some(0),
+                round_number: 1,
+            },
+            events: Vec::new(),
+            input_acks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn acknowledged_snapshots_are_built_on() {
+        let mut baselines = Baselines::default();
+        let first = baselines.pack(vec![(vec![1, 2], update(0, 5.))]);
+        assert!(matches!(
+            first.as_slice(),
+            [(clients, ServerMessage::GameUpdate(_))] if *clients == vec![1, 2]
+        ));
+
+        // Only client 1 said it got tick 0
+        baselines.acknowledge(1, Some(0));
+        let second = baselines.pack(vec![(vec![1, 2], update(1, 6.))]);
+        assert_eq!(second.len(), 2);
+        let ServerMessage::GameDelta(delta) = &second[0].1 else {
+            panic!("expected a delta, got {:?}", second[0].1);
+        };
+        assert_eq!(second[0].0, vec![1]);
+        assert_eq!(delta.delta.baseline, 0);
+        // Tank 0 stays at x = 0
+        assert_eq!(delta.delta.tanks.len(), 1);
+        assert_eq!(delta.clone().apply(&update(0, 5.).snapshot), update(1, 6.));
+        assert!(
+            matches!(&second[1], (clients, ServerMessage::GameUpdate(_)) if *clients == vec![2])
+        );
+    }
+
+    #[test]
+    fn clients_too_far_behind_get_whole_snapshots() {
+        let mut baselines = Baselines::default();
+        baselines.pack(vec![(vec![1], update(0, 5.))]);
+        baselines.acknowledge(1, Some(0));
+        for tick in 1..=KEPT as u32 {
+            baselines.pack(vec![(vec![1], update(tick, 5.))]);
+        }
+        let late = baselines.pack(vec![(vec![1], update(100, 6.))]);
+        assert!(matches!(late[0].1, ServerMessage::GameUpdate(_)));
+
+        baselines.forget(1);
+        baselines.acknowledge(1, None);
+        let fresh = baselines.pack(vec![(vec![1], update(101, 6.))]);
+        assert!(matches!(fresh[0].1, ServerMessage::GameUpdate(_)));
+    }
+}
@@ -1,4 +1,6 @@
-use common::protocol::{GameCode, PlayerId};
+use crate::anti_cheat::ClientGuard;
+use common::game::player::TankColor;
+use common::protocol::{GameCode, PlayerId, SessionToken};
 
 #[derive(Clone, Debug)]
 pub enum ClientState {
@@ -12,14 +14,22 @@ pub enum ClientState {
 
 pub struct Client {
     pub nickname: String,
+    /// Trim the player picked for their tanks
+    pub color: Option<TankColor>,
     pub state: ClientState,
+    pub guard: ClientGuard,
+    /// Identifies the client to reclaim its place in a match after a dropped connection
+    pub session: SessionToken,
 }
 
 impl Default for Client {
     fn default() -> Self {
         Self {
             nickname: String::new(),
+            color: None,
             state: ClientState::Lobby,
+            guard: ClientGuard::default(),
+            session: SessionToken(0),
         }
     }
 }
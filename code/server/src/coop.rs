@@ -0,0 +1,170 @@
+//! Co-op games: the humans, with bots filling up their team, hold out against waves of
+//! scripted bots that grow in number and skill. The match lasts until the humans' team
+//! falls, and the time it survived is its score.
+
+use crate::countdown::Countdown;
+use common::ai::BotDifficulty;
+use common::game::engine::GameEngine;
+use common::protocol::{GameEvent, Team};
+use std::time::Duration;
+
+/// The humans' side.
+pub(crate) const PLAYERS: Team = Team::Blue;
+/// The side the waves spawn on.
+pub(crate) const WAVES: Team = Team::Red;
+/// Breather between clearing a wave and the next one.
+const WAVE_BREAK: Duration = Duration::from_secs(5);
+/// Biggest wave, however long the humans hold out.
+const MAX_WAVE_SIZE: usize = 16;
+
+pub(crate) struct Coop {
+    /// The wave on the map, or the last one cleared; 0 before the first
+    wave: u8,
+    survived: Duration,
+    /// Until the next wave, once the last one is cleared
+    next_wave: Option<Countdown>,
+}
+
+/// Bots in wave `wave` against a team of `team_size`, and how well they play: one more
+/// bot every wave, Hunters from the third and Terminators from the fifth.
+fn wave_spec(wave: u8, team_size: usize) -> (usize, BotDifficulty) {
+    let size = (team_size + usize::from(wave) - 1).min(MAX_WAVE_SIZE);
+    let difficulty = match wave {
+        0..=2 => BotDifficulty::Wanderer,
+        3..=4 => BotDifficulty::Hunter,
+        _ => BotDifficulty::Terminator,
+    };
+    (size, difficulty)
+}
+
+impl Coop {
+    pub fn new() -> Self {
+        Self {
+            wave: 0,
+            survived: Duration::ZERO,
+            next_wave: None,
+        }
+    }
+
+    /// Spawns the players' team and sends in the first wave.
+    pub fn start(&mut self, engine: &mut GameEngine, team_size: usize) -> GameEvent {
+        engine.prepare_coop_round(PLAYERS);
+        self.next_wave(engine, team_size)
+    }
+
+    /// Counts the time survived and sends in the next wave once the break after the last
+    /// one is over.
+    pub fn update(
+        &mut self,
+        engine: &mut GameEngine,
+        team_size: usize,
+        dt: Duration,
+    ) -> Option<GameEvent> {
+        self.survived += dt;
+        if let Some(countdown) = &mut self.next_wave {
+            return countdown
+                .tick(dt)
+                .then(|| self.next_wave(engine, team_size));
+        }
+        if engine.tanks.iter().any(|t| t.player_info.team == WAVES) {
+            return None;
+        }
+        self.next_wave = Some(Countdown::new(WAVE_BREAK));
+        Some(GameEvent::WaveCleared(self.wave))
+    }
+
+    fn next_wave(&mut self, engine: &mut GameEngine, team_size: usize) -> GameEvent {
+        self.wave = self.wave.saturating_add(1);
+        self.next_wave = None;
+        let (size, difficulty) = wave_spec(self.wave, team_size);
+        engine.spawn_wave(WAVES, difficulty, size);
+        let enemies = engine
+            .tanks
+            .iter()
+            .filter(|t| t.player_info.team == WAVES)
+            .count();
+        GameEvent::WaveStarted {
+            wave: self.wave,
+            enemies: enemies as u8,
+        }
+    }
+
+    /// The players' team has fallen.
+    pub fn is_over(&self, engine: &GameEngine) -> bool {
+        !engine.tanks.iter().any(|t| t.player_info.team == PLAYERS)
+    }
+
+    pub fn waves_cleared(&self) -> u8 {
+        match self.next_wave {
+            Some(_) => self.wave,
+            None => self.wave.saturating_sub(1),
+        }
+    }
+
+    pub fn seconds_survived(&self) -> u64 {
+        self.survived.as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::protocol::{MapDefinition, MapName};
+
+    fn engine() -> GameEngine {
+        let mut engine = GameEngine::new(MapDefinition::load_name(MapName::Basic));
+        engine.bot_fill.team_size = Some(2);
+        engine.add_player("p1".to_string()).unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_waves_grow_and_get_tougher() {
+        assert_eq!(wave_spec(1, 4), (4, BotDifficulty::Wanderer));
+        assert_eq!(wave_spec(3, 4), (6, BotDifficulty::Hunter));
+        assert_eq!(wave_spec(5, 4), (8, BotDifficulty::Terminator));
+        assert_eq!(wave_spec(40, 4).0, MAX_WAVE_SIZE);
+    }
+
+    #[test]
+    fn test_cleared_wave_is_followed_by_the_next() {
+        let mut engine = engine();
+        let mut coop = Coop::new();
+        assert_eq!(
+            coop.start(&mut engine, 2),
+            GameEvent::WaveStarted {
+                wave: 1,
+                enemies: 2
+            }
+        );
+        // The human and a bot filling up their team
+        let players = engine
+            .tanks
+            .iter()
+            .filter(|t| t.player_info.team == PLAYERS);
+        assert_eq!(players.count(), 2);
+
+        let dt = Duration::from_secs(1);
+        assert_eq!(coop.update(&mut engine, 2, dt), None);
+        engine.tanks.retain(|t| t.player_info.team == PLAYERS);
+        assert_eq!(
+            coop.update(&mut engine, 2, dt),
+            Some(GameEvent::WaveCleared(1))
+        );
+        assert_eq!(coop.waves_cleared(), 1);
+
+        assert_eq!(
+            coop.update(&mut engine, 2, WAVE_BREAK),
+            Some(GameEvent::WaveStarted {
+                wave: 2,
+                enemies: 3
+            })
+        );
+        // The fallen bots of the first wave are gone
+        let waves = engine.bots.iter().filter(|b| b.player_info.team == WAVES);
+        assert_eq!(waves.count(), 3);
+        assert_eq!(coop.waves_cleared(), 1);
+        assert_eq!(coop.seconds_survived(), 2 + WAVE_BREAK.as_secs());
+        assert!(!coop.is_over(&engine));
+    }
+}
@@ -0,0 +1,136 @@
+//! Headless bot-vs-bot matches (`server --exhibition A B`), so an operator can check
+//! how a model plays before enabling it for bot backfill.
+
+use common::game::map_registry::{MAPS_DIR, MapRegistry};
+use common::game::replay::REPLAY_EXTENSION;
+use common::protocol::Team;
+use common::rl::contest::{Contestant, ContestantSpec, Outcome, play_match};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info};
+
+pub const MODELS_DIR: &str = "assets/models";
+
+pub struct ExhibitionConfig {
+    pub contestants: [ContestantSpec; 2],
+    /// A built-in map or one in `assets/maps`, see [`MapRegistry`]
+    pub map: String,
+    pub matches: u32,
+    /// Time limit of a match; afterwards the team with more health left wins.
+    pub match_duration: Duration,
+    /// Save a replay of every match into this directory.
+    pub replay_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub wins: [u32; 2],
+    pub draws: u32,
+}
+
+impl Summary {
+    pub fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Win(contestant) => self.wins[contestant] += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+    }
+
+    pub fn matches(&self) -> u32 {
+        self.wins[0] + self.wins[1] + self.draws
+    }
+
+    /// Share of matches won by `contestant`, draws counting as half a win.
+    pub fn score(&self, contestant: usize) -> f32 {
+        if self.matches() == 0 {
+            return 0.0;
+        }
+        (self.wins[contestant] as f32 + self.draws as f32 / 2.0) / self.matches() as f32
+    }
+}
+
+/// Plays all matches, alternating sides, and prints a line per match plus a summary.
+pub fn run(config: &ExhibitionConfig) -> Result<Summary, String> {
+    let models_dir = Path::new(MODELS_DIR);
+    let contestants = [
+        Contestant::load(&config.contestants[0], models_dir)?,
+        Contestant::load(&config.contestants[1], models_dir)?,
+    ];
+    let map = MapRegistry::load(Path::new(MAPS_DIR)).resolve(&config.map)?;
+    if let Some(dir) = &config.replay_dir {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let [a, b] = &config.contestants;
+    info!(
+        "Exhibition on {}: {} (A) vs {} (B), {} matches",
+        config.map, a, b, config.matches
+    );
+
+    let mut summary = Summary::default();
+    for index in 0..config.matches {
+        // A plays Blue in even matches and Red in odd ones, so neither side keeps the
+        // better spawns.
+        let a_team = if index % 2 == 0 {
+            Team::Blue
+        } else {
+            Team::Red
+        };
+        let seed = u64::from(index);
+        let mut recorder = None;
+        let report = play_match(
+            &contestants,
+            a_team,
+            &map,
+            None,
+            config.match_duration,
+            seed,
+            config.replay_dir.as_ref().map(|_| &mut recorder),
+        );
+        summary.record(report.outcome);
+
+        let result = match report.outcome {
+            Outcome::Win(0) => "A wins",
+            Outcome::Win(_) => "B wins",
+            Outcome::Draw => "draw",
+        };
+        info!(
+            "Match {:>3}: A as {:?}, {} after {:.1}s",
+            index + 1,
+            a_team,
+            result,
+            report.duration
+        );
+
+        if let (Some(dir), Some(recorder)) = (&config.replay_dir, recorder) {
+            let path = dir.join(format!("exhibition_{:03}.{}", index + 1, REPLAY_EXTENSION));
+            if let Err(e) = recorder.finish().save(&path) {
+                error!(path = %path.display(), error = %e, "Failed to save replay");
+            }
+        }
+    }
+
+    info!(
+        "Result: A {} - {} B ({} draws). A scored {:.0}%.",
+        summary.wins[0],
+        summary.wins[1],
+        summary.draws,
+        summary.score(0) * 100.0
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_score() {
+        let mut summary = Summary::default();
+        summary.record(Outcome::Win(0));
+        summary.record(Outcome::Draw);
+        assert_eq!(summary.matches(), 2);
+        assert_eq!(summary.score(0), 0.75);
+    }
+}
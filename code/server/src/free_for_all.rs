@@ -0,0 +1,122 @@
+//! Free-for-all games: every tank fights alone. The last one standing wins a round, and
+//! kills over all rounds decide the match.
+
+use common::protocol::{KillEvent, PlayerId, Tank, Team};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+pub(crate) struct FreeForAll {
+    /// Kills of everyone who has played a round
+    kills: BTreeMap<PlayerId, u8>,
+    /// Rounds won, which break ties in kills
+    round_wins: BTreeMap<PlayerId, u8>,
+}
+
+impl FreeForAll {
+    /// Puts the players of a new round on the tally, so that a match without kills still
+    /// has a leader.
+    pub fn enter<'a>(&mut self, tanks: impl IntoIterator<Item = &'a Tank>) {
+        for tank in tanks {
+            self.kills.entry(tank.player_info.id).or_default();
+        }
+    }
+
+    pub fn record_kill(&mut self, kill: &KillEvent) {
+        if kill.killer_info.id != kill.victim_info.id {
+            let kills = self.kills.entry(kill.killer_info.id).or_default();
+            *kills = kills.saturating_add(1);
+        }
+    }
+
+    pub fn record_round(&mut self, winner: Team) {
+        if let Team::Ffa(id) = winner {
+            let wins = self.round_wins.entry(id).or_default();
+            *wins = wins.saturating_add(1);
+        }
+    }
+
+    /// The tank with the most health left among `tanks`, the kill leader when none are
+    /// left.
+    pub fn round_winner<'a>(&self, tanks: impl IntoIterator<Item = &'a Tank>) -> Team {
+        tanks
+            .into_iter()
+            .max_by(|a, b| a.health.total_cmp(&b.health))
+            .map(|t| t.player_info.team)
+            .unwrap_or(self.leader().0)
+    }
+
+    /// Most kills first, then most round wins, then who joined earliest.
+    fn standings(&self) -> Vec<(PlayerId, u8)> {
+        let mut standings: Vec<(PlayerId, u8)> =
+            self.kills.iter().map(|(&id, &kills)| (id, kills)).collect();
+        standings.sort_by_key(|&(id, kills)| {
+            let wins = self.round_wins.get(&id).copied().unwrap_or(0);
+            (std::cmp::Reverse((kills, wins)), id)
+        });
+        standings
+    }
+
+    /// The kill leader's team and kills.
+    pub fn leader(&self) -> (Team, u8) {
+        self.standings()
+            .first()
+            .map_or((Team::Ffa(0), 0), |&(id, kills)| (Team::Ffa(id), kills))
+    }
+
+    /// Kills of the leader and of the runner-up, shown in place of the team scores.
+    pub fn scores(&self) -> (u8, u8) {
+        let standings = self.standings();
+        let kills = |place: usize| standings.get(place).map_or(0, |&(_, kills)| kills);
+        (kills(0), kills(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use glam::Vec2;
+
+    fn tank(id: PlayerId) -> Tank {
+        let info = PlayerInfo::new(id, format!("P{}", id), Team::Ffa(id));
+        Tank::new(info, Vec2::ZERO)
+    }
+
+    fn kill(killer: PlayerId, victim: PlayerId) -> KillEvent {
+        KillEvent {
+            killer_info: tank(killer).player_info,
+            victim_info: tank(victim).player_info,
+        }
+    }
+
+    #[test]
+    fn test_kills_decide_the_leader() {
+        let mut ffa = FreeForAll::default();
+        let tanks = [tank(3), tank(5), tank(7)];
+        ffa.enter(&tanks);
+        assert_eq!(ffa.leader(), (Team::Ffa(3), 0));
+
+        ffa.record_kill(&kill(7, 3));
+        ffa.record_kill(&kill(5, 7));
+        ffa.record_round(Team::Ffa(5));
+        assert_eq!(ffa.leader(), (Team::Ffa(5), 1));
+        assert_eq!(ffa.scores(), (1, 1));
+
+        ffa.record_kill(&kill(7, 5));
+        assert_eq!(ffa.leader(), (Team::Ffa(7), 2));
+        assert_eq!(ffa.scores(), (2, 1));
+    }
+
+    #[test]
+    fn test_round_winner_by_health() {
+        let mut ffa = FreeForAll::default();
+        let mut tanks = [tank(1), tank(2)];
+        tanks[0].health = 20.0;
+        assert_eq!(ffa.round_winner(&tanks), Team::Ffa(2));
+
+        // Nobody left: the kill leader takes the round
+        ffa.enter(&tanks);
+        ffa.record_kill(&kill(1, 2));
+        assert_eq!(ffa.round_winner(&[]), Team::Ffa(1));
+    }
+}
@@ -1,63 +1,333 @@
+use crate::anti_cheat::DISPLACEMENT_SLACK;
+use crate::coop::{self, Coop};
 use crate::countdown::Countdown;
-use common::game::engine::GameEngine;
+use crate::free_for_all::FreeForAll;
+use crate::lifecycle::Lifecycle;
+use crate::load::LoadCounter;
+use crate::model_transfer::SharedModel;
+use crate::multi_arena::{self, MultiArena};
+use crate::shop::{INTERMISSION_DURATION, Shop};
+use crate::sight::{self, Sight, SightRules};
+use burn_ndarray::NdArray;
+use common::ai::{BotDifficulty, BotRng};
+use common::game::engine::{BotFill, GameEngine};
+use common::game::player::{PlayerInfo, TankColor};
+use common::game::stats::MatchStats;
+use common::game::timeline::MatchTimeline;
+use common::game::{DEFAULT_TEAM_SIZE, RoundOutcome, TELEPORT_COOLDOWN};
 use common::protocol::{
-    ClientId, GameCode, GameEvent, GameSnapshot, GameState as GameStateInfo, InitialGameInfo,
-    InputPayload, MapDefinition, MapName, PlayerId, Team,
+    ClientId, FiringMode, GameCode, GameEvent, GameMode, GameSnapshot, GameState as GameStateInfo,
+    InitialGameInfo, InputPayload, MapDefinition, MapName, PING_COOLDOWN, Ping, PingKind, PlayerId,
+    REJOIN_GRACE, SessionToken, ShopBalance, Tank, Team, Upgrade,
 };
-use rand::Rng;
+use common::rl::BotBrain;
+use common::rl::contest;
+use glam::Vec2;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::time::Duration;
 use tracing::warn;
 
-const ROUND_DURATION: Duration = Duration::from_secs(100);
+pub(crate) const ROUND_DURATION: Duration = Duration::from_secs(100);
+/// How often players get everyone's stats during a battle.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A player whose connection dropped mid-match; a bot plays their tank until they're back.
+struct Away {
+    session: SessionToken,
+    nickname: String,
+    info: PlayerInfo,
+    /// Seconds left to come back
+    left: f32,
+}
 
 pub struct Game {
     state: GameState,
     players: HashMap<ClientId, (PlayerId, String)>, // client -> (player_id, nickname)
+    /// Players who dropped out and may still come back
+    away: Vec<Away>,
     game_master: ClientId,
     engine: GameEngine,
     inputs: HashMap<PlayerId, InputPayload>,
+    /// Sequence number of each player's last input; the next tick processes it
+    input_seqs: HashMap<PlayerId, u32>,
     curr_round: u8,
     total_rounds: u8,
     blue_wins: u8,
     red_wins: u8,
     map: MapName,
+    /// Set in multi-arena games
+    multi_arena: Option<MultiArena>,
+    /// Set in free-for-all games
+    free_for_all: Option<FreeForAll>,
+    /// Set in co-op games
+    coop: Option<Coop>,
+    /// Set in games with an intermission between rounds
+    shop: Option<Shop>,
+    /// Drives the bots of one team, or of every team if `None`, instead of their own
+    /// behaviour
+    model: Option<(Option<Team>, Box<BotBrain<NdArray>>)>,
+    /// The file of `model`, offered to clients
+    shared_model: Option<SharedModel>,
+    /// Seeds `rng` and the bots, disclosed to players so the match can be reproduced
+    seed: u64,
+    rng: BotRng,
+    /// Shots of everyone over the match
+    stats: MatchStats,
+    /// Shots of everyone this round, to break the tie of a round where the last tanks of
+    /// both teams fell together
+    round_stats: MatchStats,
+    /// Key moments of the match, sent when it ends
+    timeline: MatchTimeline,
+    /// Until the stats are sent next
+    stats_countdown: Countdown,
     pub outgoing_events: Vec<GameEvent>,
+    /// Events only the players of a team get, like pings
+    pub team_events: Vec<(Team, GameEvent)>,
+    /// Seconds until players who pinged may ping again
+    ping_cooldowns: HashMap<ClientId, f32>,
+    /// Clients whose tanks moved further than their speed allows, since last taken
+    pub clamped_moves: Vec<ClientId>,
+    /// How much of the battle each client is sent
+    sight: SightRules,
+    /// How long the game has been around and unused
+    pub lifecycle: Lifecycle,
+    /// What the game costs the server
+    pub load: LoadCounter,
+    /// The engine culled projectiles last tick; it is warned about once per streak
+    projectiles_capped: bool,
 }
 
 impl Game {
     pub fn new(game_master: ClientId, map: MapName, rounds: u8) -> Self {
+        Self::with_mode(
+            game_master,
+            map,
+            GameMode::TeamDeathmatch,
+            rounds,
+            DEFAULT_TEAM_SIZE,
+        )
+    }
+
+    /// `rounds` and `team_size` are ignored in multi-arena games. A free-for-all has room
+    /// for the tanks of two teams. Co-op games are one round, the humans' team of
+    /// `team_size` against the waves.
+    pub fn with_mode(
+        game_master: ClientId,
+        map: MapName,
+        mode: GameMode,
+        rounds: u8,
+        team_size: usize,
+    ) -> Self {
+        let (multi_arena, total_rounds) = match mode {
+            GameMode::TeamDeathmatch => (None, rounds),
+            GameMode::MultiArena => (Some(MultiArena::default()), multi_arena::ROUNDS),
+            GameMode::FreeForAll => (None, rounds),
+            GameMode::Coop => (None, 1),
+        };
+        let free_for_all = (mode == GameMode::FreeForAll).then(FreeForAll::default);
+        let coop = (mode == GameMode::Coop).then(Coop::new);
+        let seed = rand::rng().random();
+        let mut engine = GameEngine::new(MapDefinition::for_mode(map, mode));
+        engine.bot_seed = seed;
+        if multi_arena.is_none() {
+            engine.bot_fill.team_size = Some(team_size);
+        }
         Self {
             state: GameState::Waiting,
             players: HashMap::new(),
+            away: Vec::new(),
             game_master,
-            engine: GameEngine::new(MapDefinition::load_name(map)),
+            engine,
             inputs: HashMap::new(),
+            input_seqs: HashMap::new(),
             curr_round: 1,
-            total_rounds: rounds,
+            total_rounds,
             blue_wins: 0,
             red_wins: 0,
             map,
+            multi_arena,
+            free_for_all,
+            coop,
+            shop: None,
+            model: None,
+            shared_model: None,
+            seed,
+            rng: BotRng::seed_from_u64(seed),
+            stats: MatchStats::default(),
+            round_stats: MatchStats::default(),
+            timeline: MatchTimeline::default(),
+            stats_countdown: Countdown::new(STATS_INTERVAL),
             outgoing_events: Vec::new(),
+            team_events: Vec::new(),
+            ping_cooldowns: HashMap::new(),
+            clamped_moves: Vec::new(),
+            sight: SightRules::default(),
+            lifecycle: Lifecycle::default(),
+            load: LoadCounter::default(),
+            projectiles_capped: false,
+        }
+    }
+
+    /// Reseeds the game, before any bot has spawned.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.engine.bot_seed = seed;
+        self.rng = BotRng::seed_from_u64(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_bot_fill(&mut self, fill: BotFill) {
+        self.engine.bot_fill = fill;
+    }
+
+    /// Sets how the bots filling the teams play, and how many of them there are at most.
+    /// Co-op waves keep their own difficulty, and multi-arena and co-op games fill every
+    /// free place.
+    pub fn set_bots(&mut self, difficulty: BotDifficulty, count: Option<usize>) {
+        let fill = &mut self.engine.bot_fill;
+        fill.blue = difficulty;
+        fill.red = difficulty;
+        if self.multi_arena.is_none() && self.coop.is_none() {
+            fill.max_bots = count;
+        }
+    }
+
+    /// Lets `brain` control every bot on `team`, or every bot if `None`. Clients can
+    /// download `shared`, the file it was loaded from.
+    pub fn set_model(
+        &mut self,
+        team: Option<Team>,
+        brain: Box<BotBrain<NdArray>>,
+        shared: Option<SharedModel>,
+    ) {
+        self.model = Some((team, brain));
+        self.shared_model = shared;
+    }
+
+    /// Lets players buy upgrades between rounds. Multi-arena and co-op games go without.
+    pub fn enable_intermission(&mut self) {
+        if self.multi_arena.is_none() && self.coop.is_none() {
+            self.shop = Some(Shop::default());
+        }
+    }
+
+    /// Picks what keeps tanks from firing nonstop, for the whole match.
+    pub fn set_firing(&mut self, firing: FiringMode) {
+        self.engine.combat.firing = firing;
+    }
+
+    pub fn set_sight(&mut self, rules: SightRules) {
+        self.sight = rules;
+    }
+
+    pub fn shared_model(&self) -> Option<&SharedModel> {
+        self.shared_model.as_ref()
+    }
+
+    /// Starts the match without waiting for the game master, for server-run games.
+    pub fn force_countdown(&mut self) {
+        if matches!(self.state, GameState::Waiting) {
+            self.begin_countdown();
         }
     }
 
     pub fn snapshot(&self) -> GameSnapshot {
+        let mut engine = self.engine.snapshot();
+        // Trims are picked freely but shown within the limits of the tank's team
+        for tank in &mut engine.tanks {
+            let info = &mut tank.player_info;
+            info.color = info.color.map(|color| color.for_team(info.team));
+        }
         GameSnapshot {
-            engine: self.engine.snapshot(),
+            engine,
             state: self.game_state_info(),
             game_master: self.game_master,
+            host: self
+                .players
+                .get(&self.game_master)
+                .map(|(player_id, _)| *player_id),
             round_number: self.curr_round,
         }
     }
 
+    /// What a client with `sight` on `team` gets of `snapshot`.
+    pub fn snapshot_seen(
+        &self,
+        snapshot: &GameSnapshot,
+        team: Option<Team>,
+        sight: Sight,
+    ) -> GameSnapshot {
+        let mut snapshot = snapshot.clone();
+        sight::restrict(&mut snapshot.engine, team, sight, &self.engine.map);
+        snapshot
+    }
+
+    /// How much of the battle `client_id` sees, by whether their tank is in the round.
+    pub fn sight_of(&self, client_id: ClientId) -> Sight {
+        let Some((player_id, _)) = self.players.get(&client_id) else {
+            return self.sight.spectators;
+        };
+        if self.team_of(*player_id).is_none() {
+            self.sight.spectators
+        } else if self
+            .engine
+            .tanks
+            .iter()
+            .any(|t| t.player_info.id == *player_id)
+        {
+            self.sight.alive
+        } else {
+            self.sight.dead
+        }
+    }
+
     pub fn initial_game_info(&self, game_code: GameCode, player_id: PlayerId) -> InitialGameInfo {
         InitialGameInfo {
             game_code,
             player_id,
             num_rounds: self.total_rounds,
             map_name: self.map,
+            mode: self.mode(),
+            team_size: self.team_size() as u8,
             game_master: self.game_master,
+            bot_model: self.shared_model.as_ref().map(|model| model.info.clone()),
+            seed: self.seed,
+            state: self.game_state_info(),
+            round: self.curr_round,
+        }
+    }
+
+    /// Players may join until the first round starts, its countdown included; every tank
+    /// is placed anew when it does.
+    pub fn accepts_joins(&self) -> bool {
+        match self.state {
+            GameState::Waiting => true,
+            GameState::Countdown(_) => self.curr_round == 1,
+            _ => false,
+        }
+    }
+
+    pub fn mode(&self) -> GameMode {
+        if self.multi_arena.is_some() {
+            GameMode::MultiArena
+        } else if self.free_for_all.is_some() {
+            GameMode::FreeForAll
+        } else if self.coop.is_some() {
+            GameMode::Coop
+        } else {
+            GameMode::TeamDeathmatch
+        }
+    }
+
+    pub fn team_size(&self) -> usize {
+        match self.multi_arena {
+            Some(_) => multi_arena::SQUAD_SIZE,
+            None => self.engine.bot_fill.team_size.unwrap_or(DEFAULT_TEAM_SIZE),
         }
     }
 
@@ -65,7 +335,13 @@ impl Game {
         match &self.state {
             GameState::Waiting => GameStateInfo::Waiting,
             GameState::Countdown(countdown) => GameStateInfo::Countdown(countdown.seconds_left()),
-            GameState::Battle(countdown) => GameStateInfo::Battle(countdown.seconds_left()),
+            GameState::Intermission(countdown) => {
+                GameStateInfo::Intermission(countdown.seconds_left())
+            }
+            GameState::Battle(countdown) => GameStateInfo::Battle(match &self.coop {
+                Some(coop) => coop.seconds_survived(),
+                None => countdown.seconds_left(),
+            }),
             GameState::Results {
                 winner,
                 blue_score,
@@ -78,29 +354,178 @@ impl Game {
         }
     }
 
+    #[cfg(test)]
     pub fn client_ids(&self) -> Vec<ClientId> {
         self.players.keys().copied().collect()
     }
 
+    /// Every client with the team of their player, if it has one yet.
+    pub fn client_teams(&self) -> Vec<(ClientId, Option<Team>)> {
+        self.players
+            .iter()
+            .map(|(client_id, (player_id, _))| (*client_id, self.team_of(*player_id)))
+            .collect()
+    }
+
+    fn team_of(&self, player_id: PlayerId) -> Option<Team> {
+        self.engine
+            .humans
+            .iter()
+            .find(|info| info.id == player_id)
+            .map(|info| info.team)
+    }
+
+    /// No one plays, or could come back to play.
     pub fn is_empty(&self) -> bool {
-        self.players.is_empty()
+        self.players.is_empty() && self.away.is_empty()
+    }
+
+    /// The match has started and isn't over yet.
+    fn is_underway(&self) -> bool {
+        matches!(
+            self.state,
+            GameState::Countdown(_) | GameState::Battle(_) | GameState::Intermission(_)
+        )
     }
 
     pub fn add_player(&mut self, client_id: ClientId, nickname: String) -> Option<PlayerId> {
+        if self.multi_arena.is_some() && self.players.len() >= multi_arena::PLAYER_LIMIT {
+            return None;
+        }
+        // Everyone plays on one team in co-op
+        if self.coop.is_some() && self.players.len() >= self.team_size() {
+            return None;
+        }
         let player_id = self.engine.add_player(nickname.clone()).ok()?;
+        // Whoever comes back to an empty game runs it
+        if self.players.is_empty() {
+            self.game_master = client_id;
+        }
+        self.lifecycle.touch();
+        if self.free_for_all.is_some() {
+            self.engine.set_team(player_id, Team::Ffa(player_id));
+        }
+        if self.coop.is_some() {
+            self.engine.set_team(player_id, coop::PLAYERS);
+        }
         self.players
             .insert(client_id, (player_id, nickname.clone()));
         self.outgoing_events.push(GameEvent::PlayerJoined(nickname));
         Some(player_id)
     }
 
+    pub fn set_color(&mut self, player_id: PlayerId, color: Option<TankColor>) {
+        self.engine.set_color(player_id, color);
+    }
+
+    /// Takes the player out; the match goes on for the rest. Leaving a match under way
+    /// forfeits it: a bot takes over the tank, so the teams stay even. A leaving game
+    /// master hands the role on to another player, so someone can still start the game.
     pub fn remove_player(&mut self, client_id: ClientId) -> Option<PlayerId> {
         let (player_id, nickname) = self.players.remove(&client_id)?;
-        self.engine.remove_player(player_id);
-        self.outgoing_events.push(GameEvent::PlayerLeft(nickname));
+        self.lifecycle.touch();
+        self.input_seqs.remove(&player_id);
+        let info = self
+            .engine
+            .humans
+            .iter()
+            .find(|h| h.id == player_id)
+            .cloned();
+        match info {
+            Some(info) if self.is_underway() => {
+                let difficulty = self.engine.bot_fill.difficulty(info.team);
+                self.engine.hand_to_bot(player_id, difficulty);
+                self.timeline.forfeit(&info);
+                self.outgoing_events
+                    .push(GameEvent::PlayerForfeited(nickname));
+            }
+            _ => {
+                self.engine.remove_player(player_id);
+                self.outgoing_events.push(GameEvent::PlayerLeft(nickname));
+            }
+        }
+        self.pass_game_master(client_id);
+        Some(player_id)
+    }
+
+    /// Takes out a player whose connection dropped. In a match under way a bot plays
+    /// their tank for [`REJOIN_GRACE`] seconds, for them to [`Self::rejoin`] with
+    /// `session`; after that they forfeit. Otherwise it's like [`Self::remove_player`].
+    pub fn disconnect_player(
+        &mut self,
+        client_id: ClientId,
+        session: SessionToken,
+    ) -> Option<PlayerId> {
+        let &(player_id, _) = self.players.get(&client_id)?;
+        let Some(info) = self
+            .engine
+            .humans
+            .iter()
+            .find(|h| h.id == player_id)
+            .cloned()
+            .filter(|_| self.is_underway())
+        else {
+            return self.remove_player(client_id);
+        };
+        let (_, nickname) = self.players.remove(&client_id)?;
+        self.lifecycle.touch();
+        self.input_seqs.remove(&player_id);
+        let difficulty = self.engine.bot_fill.difficulty(info.team);
+        self.engine.hand_to_bot(player_id, difficulty);
+        self.outgoing_events
+            .push(GameEvent::PlayerDisconnected(nickname.clone()));
+        self.away.push(Away {
+            session,
+            nickname,
+            info,
+            left: REJOIN_GRACE,
+        });
+        self.pass_game_master(client_id);
         Some(player_id)
     }
 
+    /// Gives the player who dropped out with `session` their tank back, now as
+    /// `client_id`. `None` if no one is away with that session.
+    pub fn rejoin(&mut self, client_id: ClientId, session: SessionToken) -> Option<PlayerId> {
+        let index = self.away.iter().position(|away| away.session == session)?;
+        let away = self.away.remove(index);
+        let player_id = self.engine.hand_back(away.info.id)?.id;
+        if self.players.is_empty() {
+            self.game_master = client_id;
+        }
+        self.lifecycle.touch();
+        self.players
+            .insert(client_id, (player_id, away.nickname.clone()));
+        self.outgoing_events
+            .push(GameEvent::PlayerRejoined(away.nickname));
+        Some(player_id)
+    }
+
+    /// Hands the game master role on from `client_id`, if they had it and anyone is left.
+    fn pass_game_master(&mut self, client_id: ClientId) {
+        if client_id == self.game_master
+            && let Some(&next) = self.players.keys().min()
+        {
+            self.game_master = next;
+        }
+    }
+
+    /// Counts down the time the players away have to come back; those out of it forfeit.
+    fn tick_away(&mut self, dt: f32) {
+        for away in &mut self.away {
+            away.left -= dt;
+        }
+        let (gone, away) = std::mem::take(&mut self.away)
+            .into_iter()
+            .partition(|away| away.left <= 0.);
+        self.away = away;
+        for away in gone {
+            self.timeline.forfeit(&away.info);
+            self.outgoing_events
+                .push(GameEvent::PlayerForfeited(away.nickname));
+        }
+    }
+
     pub fn start_countdown(&mut self, client_id: ClientId) -> Result<(), StartCountdownError> {
         if !matches!(self.state, GameState::Waiting) {
             return Err(StartCountdownError::NotInWaitingState);
@@ -109,50 +534,271 @@ impl Game {
         if client_id != self.game_master {
             return Err(StartCountdownError::NotTheGameMaster);
         }
+        self.lifecycle.touch();
 
-        self.state = GameState::Countdown(Countdown::default());
+        self.begin_countdown();
         Ok(())
     }
 
-    pub fn handle_player_input(&mut self, client_id: ClientId, input: InputPayload) {
+    fn begin_countdown(&mut self) {
+        self.state = GameState::Countdown(Countdown::default());
+        let (blue_score, red_score) = match &self.free_for_all {
+            Some(ffa) => ffa.scores(),
+            None => (self.blue_wins, self.red_wins),
+        };
+        self.outgoing_events.push(GameEvent::CountdownStarted {
+            round: self.curr_round,
+            blue_score,
+            red_score,
+        });
+    }
+
+    /// Opens the shop before the next round's countdown, in games that have one.
+    fn begin_next_round(&mut self) {
+        if self.shop.is_some() {
+            self.state = GameState::Intermission(Countdown::new(INTERMISSION_DURATION));
+            self.outgoing_events
+                .push(GameEvent::Shop(self.shop_balances()));
+        } else {
+            self.begin_countdown();
+        }
+    }
+
+    /// Everyone's points and upgrades, by player id.
+    fn shop_balances(&self) -> Vec<ShopBalance> {
+        let Some(shop) = &self.shop else {
+            return Vec::new();
+        };
+        let mut players: Vec<PlayerId> = self.players.values().map(|(id, _)| *id).collect();
+        players.sort_unstable();
+        players
+            .into_iter()
+            .map(|player| ShopBalance {
+                player,
+                points: shop.points(player, &self.stats),
+                loadout: self
+                    .engine
+                    .loadouts
+                    .get(&player)
+                    .copied()
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Buys the next level of `upgrade` for the player, if it is an intermission and they
+    /// can afford it. Everyone hears of a purchase that went through.
+    pub fn handle_upgrade(&mut self, client_id: ClientId, upgrade: Upgrade) {
+        let Some(&(player, _)) = self.players.get(&client_id) else {
+            return;
+        };
+        let (GameState::Intermission(_), Some(shop)) = (&self.state, &mut self.shop) else {
+            return;
+        };
+        let loadout = self.engine.loadouts.entry(player).or_default();
+        if shop.buy(player, upgrade, loadout, &self.stats) {
+            self.outgoing_events
+                .push(GameEvent::Shop(self.shop_balances()));
+        }
+    }
+
+    /// Passes a ping on to the player's team and its bots, unless they pinged within
+    /// [`PING_COOLDOWN`]. Spots off the map are moved onto it.
+    pub fn handle_ping(&mut self, client_id: ClientId, kind: PingKind, world_pos: Vec2) {
+        let Some(&(player, _)) = self.players.get(&client_id) else {
+            return;
+        };
+        let Some(team) = self.team_of(player) else {
+            return;
+        };
+        if !world_pos.is_finite() || self.ping_cooldowns.contains_key(&client_id) {
+            return;
+        }
+        self.ping_cooldowns.insert(client_id, PING_COOLDOWN);
+        let map = self.engine.map();
+        let world_pos = world_pos.clamp(Vec2::ZERO, Vec2::new(map.width, map.height));
+        self.engine.blackboard.ping(team, kind, world_pos);
+        self.team_events.push((
+            team,
+            GameEvent::Ping(Ping {
+                player,
+                kind,
+                world_pos,
+            }),
+        ));
+    }
+
+    pub fn handle_player_input(&mut self, client_id: ClientId, seq: u32, input: InputPayload) {
         let Some((player_id, _)) = self.players.get(&client_id) else {
             warn!(%client_id, "Player not found, ignoring input");
             return;
         };
+        self.load.current.inputs_received += 1;
+        if self
+            .input_seqs
+            .get(player_id)
+            .is_some_and(|&last| seq <= last)
+        {
+            self.load.current.stale_inputs += 1;
+            return;
+        }
+        // Clients send input every frame; only moving or shooting shows someone is there
+        if input.move_axis != Vec2::ZERO || input.shoot || input.grenade {
+            self.lifecycle.touch();
+        }
         let input = match self.state {
             GameState::Battle(_) => input,
-            // Countdown/Waiting/Results: movement/aim is allowed, shooting is not.
-            GameState::Waiting | GameState::Countdown(_) | GameState::Results { .. } => {
-                InputPayload {
-                    shoot: false,
-                    ..input
-                }
-            }
+            // Countdown/Waiting/Intermission/Results: movement/aim is allowed, shooting is not.
+            GameState::Waiting
+            | GameState::Countdown(_)
+            | GameState::Intermission(_)
+            | GameState::Results { .. } => InputPayload {
+                shoot: false,
+                ..input
+            },
         };
         self.inputs.insert(*player_id, input);
+        self.input_seqs.insert(*player_id, seq);
+    }
+
+    /// The sequence number of the last input of each of `clients`' players. Inputs are
+    /// processed in the tick after they arrive, so after a tick all of them were.
+    pub fn input_acks(&self, clients: &[ClientId]) -> Vec<(PlayerId, u32)> {
+        clients
+            .iter()
+            .filter_map(|client_id| self.players.get(client_id))
+            .filter_map(|(player_id, _)| Some((*player_id, *self.input_seqs.get(player_id)?)))
+            .collect()
+    }
+
+    /// Pulls back the tanks of players that got further than their speed allows since
+    /// `positions`, which no honest client can do.
+    fn clamp_moves(&mut self, positions: &HashMap<PlayerId, Vec2>, dt: f32) {
+        for (client_id, (player_id, _)) in &self.players {
+            let (Some(tank), Some(&from)) = (
+                self.engine
+                    .tanks
+                    .iter_mut()
+                    .find(|t| t.player_info.id == *player_id),
+                positions.get(player_id),
+            ) else {
+                continue;
+            };
+            // Just came out of a teleporter
+            if tank.teleport_cooldown >= TELEPORT_COOLDOWN {
+                continue;
+            }
+            let max_step = tank.speed * dt + DISPLACEMENT_SLACK;
+            let step = tank.position - from;
+            if !step.is_finite() || step.length() > max_step {
+                tank.position = from + step.clamp_length_max(max_step);
+                if !tank.position.is_finite() {
+                    tank.position = from;
+                }
+                self.clamped_moves.push(*client_id);
+            }
+        }
     }
 
     pub fn tick(&mut self, dt: f32) {
-        let result = self.engine.tick(dt, self.inputs.clone());
+        self.ping_cooldowns.retain(|_, left| {
+            *left -= dt;
+            *left > 0.0
+        });
+        self.tick_away(dt);
+        let mut inputs = self.inputs.clone();
+        if let Some((team, brain)) = &self.model {
+            for bot in self
+                .engine
+                .bots
+                .iter()
+                .filter(|b| team.is_none_or(|team| b.player_info.team == team))
+            {
+                let id = bot.player_info.id;
+                if let Some(tank) = self.engine.tanks.iter().find(|t| t.player_info.id == id) {
+                    let input = contest::model_input(brain, tank, &self.engine, dt, &mut self.rng);
+                    inputs.insert(id, input);
+                }
+            }
+        }
+        let positions: HashMap<PlayerId, Vec2> = self
+            .engine
+            .tanks
+            .iter()
+            .map(|t| (t.player_info.id, t.position))
+            .collect();
+        let result = self.engine.tick(dt, inputs);
         self.inputs.clear();
+        self.load.current.bots_simulated += self.engine.bots.len() as u64;
+        self.load.current.culled_projectiles += result.culled_projectiles as u64;
+        let capped = result.culled_projectiles > 0;
+        if capped && !self.projectiles_capped {
+            warn!(
+                seed = self.seed,
+                projectiles = self.engine.projectiles.len(),
+                "Projectile cap hit, culling the oldest"
+            );
+        }
+        self.projectiles_capped = capped;
+        self.load.tick(Duration::from_secs_f32(dt));
+        self.clamp_moves(&positions, dt);
+        let team_size = self.team_size();
 
+        if self.is_underway() {
+            self.timeline.advance(Duration::from_secs_f32(dt));
+        }
         match &mut self.state {
             GameState::Countdown(countdown) => {
                 if countdown.tick(Duration::from_secs_f32(dt)) {
                     self.state = GameState::Battle(Countdown::new(ROUND_DURATION));
-                    self.engine.prepare_new_round();
+                    match &mut self.multi_arena {
+                        Some(arenas) if self.curr_round == 1 => {
+                            arenas.start_qualifiers(&mut self.engine)
+                        }
+                        Some(arenas) => arenas.start_final(&mut self.engine),
+                        None if self.free_for_all.is_some() => self.engine.prepare_ffa_round(),
+                        // Spawned with the first wave below
+                        None if self.coop.is_some() => {}
+                        None => self.engine.prepare_new_round(),
+                    }
+                    if let Some(ffa) = &mut self.free_for_all {
+                        ffa.enter(self.engine.tanks());
+                    }
+                    self.round_stats = MatchStats::default();
+                    self.timeline.round_started(self.curr_round);
+                    self.outgoing_events.push(GameEvent::RoundStarted);
+                    if let Some(coop) = &mut self.coop {
+                        let wave = coop.start(&mut self.engine, team_size);
+                        self.outgoing_events.push(wave);
+                    }
                 }
             }
             GameState::Battle(countdown) => {
                 let mut round_ended = false;
-                let mut winner = None;
+                let mut outcome = RoundOutcome::Ongoing;
+
+                self.stats.record(self.engine.tanks(), &result);
+                self.round_stats.record(self.engine.tanks(), &result);
+                if self.stats_countdown.tick(Duration::from_secs_f32(dt)) {
+                    self.stats_countdown = Countdown::new(STATS_INTERVAL);
+                    self.outgoing_events
+                        .push(GameEvent::MatchStats(self.stats.summary()));
+                }
 
-                if countdown.tick(Duration::from_secs_f32(dt)) {
-                    winner = Some(self.resolve_winner_by_hp());
+                // Co-op rounds have no time limit
+                if self.coop.is_none() && countdown.tick(Duration::from_secs_f32(dt)) {
+                    if let Some(ffa) = &self.free_for_all {
+                        outcome = RoundOutcome::Won(ffa.round_winner(self.engine.tanks()));
+                    } else if self.multi_arena.is_none() {
+                        outcome = RoundOutcome::Won(self.resolve_winner_by_hp());
+                    }
                     round_ended = true;
                 }
 
                 if !round_ended {
+                    for kill in &result.kills {
+                        self.timeline.kill(kill);
+                    }
                     let mut kill_events = result
                         .kills
                         .iter()
@@ -160,41 +806,129 @@ impl Game {
                         .collect();
 
                     self.outgoing_events.append(&mut kill_events);
+                    self.outgoing_events
+                        .extend(result.explosions.iter().cloned().map(GameEvent::Explosion));
+
+                    if self.multi_arena.is_none() && self.coop.is_none() {
+                        match result.outcome {
+                            RoundOutcome::Draw if self.free_for_all.is_none() => {
+                                outcome = self.break_tie_by_damage();
+                            }
+                            RoundOutcome::Won(_) => outcome = result.outcome,
+                            RoundOutcome::Draw | RoundOutcome::Ongoing => {}
+                        }
+                    }
+                }
+
+                if let Some(shop) = &mut self.shop {
+                    for kill in &result.kills {
+                        shop.record_kill(kill);
+                    }
+                }
+
+                if let Some(ffa) = &mut self.free_for_all {
+                    for kill in &result.kills {
+                        ffa.record_kill(kill);
+                    }
+                    // The last tanks fell in the same tick
+                    if outcome == RoundOutcome::Ongoing && self.engine.tanks().is_empty() {
+                        outcome = RoundOutcome::Won(ffa.round_winner([]));
+                    }
+                }
+
+                if let Some(coop) = &mut self.coop {
+                    let dt = Duration::from_secs_f32(dt);
+                    if let Some(event) = coop.update(&mut self.engine, team_size, dt) {
+                        self.outgoing_events.push(event);
+                    }
+                    if coop.is_over(&self.engine) {
+                        outcome = RoundOutcome::Won(coop::WAVES);
+                    }
+                }
 
-                    if let Some(w) = result.winner {
-                        winner = Some(w);
+                if let Some(arenas) = &mut self.multi_arena {
+                    for (arena, team) in arenas.update(&self.engine, round_ended, &mut self.rng) {
+                        self.outgoing_events.push(GameEvent::ArenaDecided {
+                            arena: arena as u8,
+                            winner: team,
+                        });
+                    }
+                    outcome = arenas
+                        .champion()
+                        .map_or(RoundOutcome::Ongoing, RoundOutcome::Won);
+                    if arenas.round_over() && outcome == RoundOutcome::Ongoing {
+                        // On to the final
+                        self.curr_round += 1;
+                        self.begin_countdown();
                     }
                 }
 
-                if let Some(winner) = winner {
-                    // Increment the winning team's score
+                if outcome.is_over() {
+                    let winner = outcome.winner();
+                    // Increment the winning team's score; a drawn round counts for nobody
                     match winner {
-                        common::protocol::Team::Blue => self.blue_wins += 1,
-                        common::protocol::Team::Red => self.red_wins += 1,
+                        Some(common::protocol::Team::Blue) => self.blue_wins += 1,
+                        Some(common::protocol::Team::Red) => self.red_wins += 1,
+                        Some(team @ common::protocol::Team::Ffa(_)) => {
+                            if let Some(ffa) = &mut self.free_for_all {
+                                ffa.record_round(team);
+                            }
+                        }
+                        None => {}
                     }
 
+                    self.outgoing_events
+                        .push(GameEvent::MatchStats(self.stats.summary()));
                     self.outgoing_events.push(GameEvent::RoundEnded(winner));
+                    self.timeline.round_ended(self.curr_round, winner);
                     self.curr_round += 1;
                     if self.curr_round <= self.total_rounds {
-                        self.state = GameState::Countdown(Countdown::default());
+                        self.begin_next_round();
                     } else {
-                        // End of match: determine overall winner based on best-of-N
-                        let overall_winner = if self.blue_wins > self.red_wins {
-                            common::protocol::Team::Blue
+                        // End of match: determine overall winner based on best-of-N,
+                        // on kills in free-for-all, or the waves cleared in co-op
+                        self.state = if let Some(ffa) = &self.free_for_all {
+                            let (blue_score, red_score) = ffa.scores();
+                            GameState::Results {
+                                winner: Some(ffa.leader().0),
+                                blue_score,
+                                red_score,
+                            }
+                        } else if let Some(coop) = &self.coop {
+                            self.outgoing_events.push(GameEvent::Survived {
+                                waves: coop.waves_cleared(),
+                                seconds: u32::try_from(coop.seconds_survived()).unwrap_or(u32::MAX),
+                            });
+                            GameState::Results {
+                                winner: Some(coop::WAVES),
+                                blue_score: coop.waves_cleared(),
+                                red_score: 0,
+                            }
                         } else {
-                            common::protocol::Team::Red
-                        };
-
-                        self.state = GameState::Results {
-                            winner: overall_winner,
-                            blue_score: self.blue_wins,
-                            red_score: self.red_wins,
+                            // Drawn rounds can leave the teams level
+                            let overall_winner = match self.blue_wins.cmp(&self.red_wins) {
+                                Ordering::Greater => Some(common::protocol::Team::Blue),
+                                Ordering::Less => Some(common::protocol::Team::Red),
+                                Ordering::Equal => None,
+                            };
+                            GameState::Results {
+                                winner: overall_winner,
+                                blue_score: self.blue_wins,
+                                red_score: self.red_wins,
+                            }
                         };
                         // Clear any remaining projectiles so no post-match kills happen.
                         self.engine.clear_projectiles();
+                        self.outgoing_events
+                            .push(GameEvent::MatchEnded(self.timeline.summary()));
                     }
                 }
             }
+            GameState::Intermission(countdown) => {
+                if countdown.tick(Duration::from_secs_f32(dt)) {
+                    self.begin_countdown();
+                }
+            }
             GameState::Waiting => {}
             GameState::Results { .. } => {
                 // Stay in Results; GameEngine still ticks (movement/aim allowed),
@@ -203,26 +937,49 @@ impl Game {
         }
     }
 
-    fn resolve_winner_by_hp(&self) -> Team {
-        let mut red_hp = 0.0;
-        let mut blue_hp = 0.0;
-        for t in self.engine.tanks() {
-            match t.player_info.team {
-                Team::Red => red_hp += t.health,
-                Team::Blue => blue_hp += t.health,
-            }
-        }
+    fn resolve_winner_by_hp(&mut self) -> Team {
+        winner_by_health(self.engine.tanks(), &mut self.rng)
+    }
 
-        if red_hp > blue_hp {
-            Team::Red
-        } else if blue_hp > red_hp {
-            Team::Blue
-        } else if rand::rng().random_bool(0.5) {
-            Team::Red
+    /// The last tanks of both teams fell together: the team that dealt more damage this
+    /// round wins it, and it is a draw if they dealt the same.
+    fn break_tie_by_damage(&self) -> RoundOutcome {
+        let blue = self.round_stats.team_damage(Team::Blue);
+        let red = self.round_stats.team_damage(Team::Red);
+        if blue > red {
+            RoundOutcome::Won(Team::Blue)
+        } else if red > blue {
+            RoundOutcome::Won(Team::Red)
         } else {
-            Team::Blue
+            RoundOutcome::Draw
+        }
+    }
+}
+
+/// The team with more health left among `tanks`; a coin flip with the game's `rng` on a tie.
+pub(crate) fn winner_by_health<'a>(
+    tanks: impl IntoIterator<Item = &'a Tank>,
+    rng: &mut impl Rng,
+) -> Team {
+    let mut red_hp = 0.0;
+    let mut blue_hp = 0.0;
+    for t in tanks {
+        match t.player_info.team {
+            Team::Red => red_hp += t.health,
+            Team::Blue => blue_hp += t.health,
+            Team::Ffa(_) => {}
         }
     }
+
+    if red_hp > blue_hp {
+        Team::Red
+    } else if blue_hp > red_hp {
+        Team::Blue
+    } else if rng.random_bool(0.5) {
+        Team::Red
+    } else {
+        Team::Blue
+    }
 }
 
 #[derive(Debug)]
@@ -236,8 +993,10 @@ enum GameState {
     Waiting,
     Countdown(Countdown),
     Battle(Countdown),
+    Intermission(Countdown),
     Results {
-        winner: common::protocol::Team,
+        /// `None` when the teams won as many rounds
+        winner: Option<common::protocol::Team>,
         blue_score: u8,
         red_score: u8,
     },
@@ -247,8 +1006,8 @@ enum GameState {
 mod tests {
     use super::*;
     use common::{
-        game::{Tank, player::PlayerInfo},
-        protocol::{EngineSnapshot, GameEvent},
+        game::{DamageEvent, Tank, engine::GameTickResult, player::PlayerInfo},
+        protocol::{EngineSnapshot, GameEvent, MatchSummary, TimelineEvent},
     };
     use glam::Vec2;
 
@@ -257,6 +1016,7 @@ mod tests {
             move_axis: Vec2::ZERO,
             aim_pos: to,
             shoot: true,
+            grenade: false,
         }
     }
 
@@ -297,6 +1057,55 @@ mod tests {
         assert!(matches!(g.game_state_info(), GameStateInfo::Countdown(_)));
     }
 
+    #[test]
+    fn big_teams_are_filled_with_bots() {
+        let master: ClientId = 1;
+        let mut g = Game::with_mode(master, MapName::Loss, GameMode::TeamDeathmatch, 3, 8);
+        g.add_player(master, "p1".to_string()).unwrap();
+        g.start_countdown(master).unwrap();
+        assert_eq!(g.initial_game_info(GameCode("1234".into()), 0).team_size, 8);
+
+        g.tick(6.0);
+        assert!(matches!(g.game_state_info(), GameStateInfo::Battle(_)));
+        for team in [Team::Blue, Team::Red] {
+            let tanks: Vec<_> = g
+                .engine
+                .tanks()
+                .iter()
+                .filter(|t| t.player_info.team == team)
+                .collect();
+            assert_eq!(tanks.len(), 8);
+            for (i, a) in tanks.iter().enumerate() {
+                assert!(tanks[i + 1..].iter().all(|b| a.position != b.position));
+            }
+        }
+    }
+
+    #[test]
+    fn hosts_choose_how_many_bots_join_and_how_they_play() {
+        let master: ClientId = 1;
+        let mut g = Game::with_mode(master, MapName::Basic, GameMode::TeamDeathmatch, 3, 4);
+        g.add_player(master, "p1".to_string()).unwrap();
+        g.set_bots(BotDifficulty::Terminator, Some(3));
+        g.start_countdown(master).unwrap();
+
+        g.tick(6.0);
+        assert!(matches!(g.game_state_info(), GameStateInfo::Battle(_)));
+        assert_eq!(g.engine.bots.len(), 3);
+        assert!(
+            g.engine
+                .bots
+                .iter()
+                .all(|b| b.difficulty == BotDifficulty::Terminator)
+        );
+
+        // Co-op games fill the team whatever the host asked for
+        let mut coop = Game::with_mode(master, MapName::Basic, GameMode::Coop, 1, 4);
+        coop.set_bots(BotDifficulty::Dummy, Some(0));
+        assert_eq!(coop.engine.bot_fill.max_bots, None);
+        assert_eq!(coop.engine.bot_fill.blue, BotDifficulty::Dummy);
+    }
+
     #[test]
     fn countdown_transition_to_battle_after_enough_time() {
         let master: ClientId = 1;
@@ -305,10 +1114,24 @@ mod tests {
 
         g.add_player(master, "p1".to_string()).unwrap();
         g.add_player(other, "p2".to_string()).unwrap();
+        g.outgoing_events.clear();
         g.start_countdown(master).unwrap();
+        assert!(matches!(
+            g.outgoing_events.as_slice(),
+            [GameEvent::CountdownStarted {
+                round: 1,
+                blue_score: 0,
+                red_score: 0
+            }]
+        ));
 
+        g.outgoing_events.clear();
         g.tick(6.0);
         assert!(matches!(g.game_state_info(), GameStateInfo::Battle(_)));
+        assert!(matches!(
+            g.outgoing_events.as_slice(),
+            [GameEvent::RoundStarted]
+        ));
     }
 
     #[test]
@@ -330,6 +1153,7 @@ mod tests {
                 },
             ],
             projectiles: Vec::new(),
+            moving_walls: Vec::new(),
         });
 
         // Tick 1.0s to finish countdown
@@ -339,8 +1163,121 @@ mod tests {
         assert!(
             g.outgoing_events
                 .iter()
-                .any(|e| matches!(e, GameEvent::RoundEnded(common::protocol::Team::Red)))
+                .any(|e| matches!(e, GameEvent::RoundEnded(Some(common::protocol::Team::Red))))
+        );
+    }
+
+    #[test]
+    fn wipe_out_of_both_teams_is_a_draw_or_goes_to_damage() {
+        let master: ClientId = 1;
+        let mut g = Game::new(master, MapName::Basic, 1);
+        g.add_player(master, "p1".to_string()).unwrap();
+        g.state = GameState::Battle(Countdown::new(ROUND_DURATION));
+        g.engine.apply_snapshot(EngineSnapshot {
+            tanks: Vec::new(),
+            projectiles: Vec::new(),
+            moving_walls: Vec::new(),
+        });
+
+        // Nobody dealt any damage
+        g.tick(0.0);
+        assert!(
+            g.outgoing_events
+                .iter()
+                .any(|e| matches!(e, GameEvent::RoundEnded(None)))
+        );
+        assert_eq!((g.blue_wins, g.red_wins), (0, 0));
+        assert!(matches!(
+            g.game_state_info(),
+            GameStateInfo::Results { winner: None, .. }
+        ));
+
+        // Red hit harder before the last tanks fell
+        let mut g = Game::new(master, MapName::Basic, 1);
+        g.add_player(master, "p1".to_string()).unwrap();
+        g.state = GameState::Battle(Countdown::new(ROUND_DURATION));
+        let tanks = [
+            make_player(0, "r", Team::Red),
+            make_player(1, "b", Team::Blue),
+        ];
+        g.round_stats.record(
+            &tanks,
+            &GameTickResult {
+                shots: vec![0],
+                kills: Vec::new(),
+                damage: vec![DamageEvent {
+                    attacker_id: 0,
+                    victim_id: 1,
+                    amount: 30.0,
+                    projectile_id: 0,
+                }],
+                explosions: Vec::new(),
+                outcome: RoundOutcome::Ongoing,
+                culled_projectiles: 0,
+            },
+        );
+        g.engine.apply_snapshot(EngineSnapshot {
+            tanks: Vec::new(),
+            projectiles: Vec::new(),
+            moving_walls: Vec::new(),
+        });
+        g.tick(0.0);
+        assert!(
+            g.outgoing_events
+                .iter()
+                .any(|e| matches!(e, GameEvent::RoundEnded(Some(Team::Red))))
         );
+        assert_eq!(g.red_wins, 1);
+    }
+
+    #[test]
+    fn intermission_lets_players_buy_upgrades_before_the_next_round() {
+        let master: ClientId = 1;
+        let mut g = Game::new(master, MapName::Basic, 3);
+        let me = g.add_player(master, "p1".to_string()).unwrap();
+        g.enable_intermission();
+        g.state = GameState::Battle(Countdown::new(ROUND_DURATION));
+        g.engine.apply_snapshot(EngineSnapshot {
+            tanks: vec![make_player(me, "p1", Team::Red)],
+            projectiles: Vec::new(),
+            moving_walls: Vec::new(),
+        });
+        g.tick(0.0);
+        assert!(matches!(
+            g.game_state_info(),
+            GameStateInfo::Intermission(_)
+        ));
+
+        // Two kills pay for the first level
+        let victim = PlayerInfo::new(9, "b".to_string(), Team::Blue);
+        let kill = common::protocol::KillEvent {
+            killer_info: make_player(me, "p1", Team::Red).player_info,
+            victim_info: victim,
+        };
+        let shop = g.shop.as_mut().unwrap();
+        shop.record_kill(&kill);
+        shop.record_kill(&kill);
+        g.outgoing_events.clear();
+        g.handle_upgrade(master, Upgrade::Speed);
+        g.handle_upgrade(master, Upgrade::Speed);
+        assert_eq!(g.engine.loadouts[&me].speed, 1);
+        let shops: Vec<&Vec<ShopBalance>> = g
+            .outgoing_events
+            .iter()
+            .filter_map(|e| match e {
+                GameEvent::Shop(balances) => Some(balances),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(shops.len(), 1);
+        assert_eq!(shops[0][0].points, 0);
+
+        g.tick(INTERMISSION_DURATION.as_secs_f32());
+        assert!(matches!(g.game_state_info(), GameStateInfo::Countdown(_)));
+        // Nothing is for sale outside of an intermission
+        g.shop.as_mut().unwrap().record_kill(&kill);
+        g.handle_upgrade(master, Upgrade::MaxHealth);
+        assert_eq!(g.engine.loadouts[&me].max_health, 0);
     }
 
     #[test]
@@ -363,7 +1300,7 @@ mod tests {
             .unwrap()
             .position;
 
-        g.handle_player_input(master, input_shooting_towards(my_pos + Vec2::X * 10.0));
+        g.handle_player_input(master, 0, input_shooting_towards(my_pos + Vec2::X * 10.0));
         g.tick(0.0);
 
         // Countdown suppresses shooting.
@@ -382,7 +1319,7 @@ mod tests {
             .unwrap()
             .position;
 
-        g.handle_player_input(master, input_shooting_towards(my_pos + Vec2::X * 10.0));
+        g.handle_player_input(master, 1, input_shooting_towards(my_pos + Vec2::X * 10.0));
         g.tick(0.0);
 
         assert!(!g.snapshot().engine.projectiles.is_empty());
@@ -396,15 +1333,34 @@ mod tests {
         // Unknown client should be ignored (no panic, no input recorded).
         g.handle_player_input(
             999,
+            0,
             InputPayload {
                 move_axis: Vec2::ZERO,
                 aim_pos: Vec2::ZERO,
                 shoot: true,
+                grenade: false,
             },
         );
         assert!(g.inputs.is_empty());
     }
 
+    #[test]
+    fn stale_inputs_are_dropped_and_counted() {
+        let master: ClientId = 1;
+        let mut g = Game::new(master, MapName::Basic, 3);
+        let me = g.add_player(master, "p1".to_string()).unwrap();
+
+        g.handle_player_input(master, 5, InputPayload::default());
+        g.handle_player_input(master, 4, input_shooting_towards(Vec2::ZERO));
+        assert_eq!(g.input_acks(&[master]), vec![(me, 5)]);
+        assert!(!g.inputs[&me].shoot);
+
+        g.tick(1.0);
+        let load = g.load.last_window();
+        assert_eq!((load.inputs_received, load.stale_inputs), (2, 1));
+        assert_eq!(load.bots_simulated, g.engine.bots.len() as u64);
+    }
+
     #[test]
     fn client_ids_and_is_empty_reflect_players() {
         let master: ClientId = 1;
@@ -423,6 +1379,93 @@ mod tests {
         assert!(!g.is_empty());
     }
 
+    #[test]
+    fn leaving_a_match_under_way_hands_the_tank_to_a_bot() {
+        let mut g = Game::new(1, MapName::Basic, 3);
+        let leaver = g.add_player(1, "p1".to_string()).unwrap();
+        g.add_player(2, "p2".to_string()).unwrap();
+        g.start_countdown(1).unwrap();
+        g.state = GameState::Battle(Countdown::new(ROUND_DURATION));
+        g.engine.prepare_new_round();
+        let tanks = g.engine.tanks.len();
+        g.outgoing_events.clear();
+
+        g.remove_player(1).unwrap();
+        assert!(matches!(
+            g.outgoing_events.as_slice(),
+            [GameEvent::PlayerForfeited(n)] if n == "p1"
+        ));
+        assert_eq!(g.engine.tanks.len(), tanks);
+        let tank = g
+            .engine
+            .tanks
+            .iter()
+            .find(|t| t.player_info.id == leaver)
+            .unwrap();
+        assert!(tank.player_info.is_ai);
+        assert!(g.engine.bots.iter().any(|b| b.player_info.id == leaver));
+        assert!(g.engine.humans.iter().all(|h| h.id != leaver));
+        assert!(matches!(
+            g.timeline.summary().timeline.last().unwrap().event,
+            TimelineEvent::Forfeit(id) if id == leaver
+        ));
+    }
+
+    #[test]
+    fn dropped_players_have_a_while_to_come_back() {
+        let mut g = Game::new(1, MapName::Basic, 3);
+        let dropped = g.add_player(1, "p1".to_string()).unwrap();
+        g.add_player(2, "p2".to_string()).unwrap();
+        g.start_countdown(1).unwrap();
+        g.state = GameState::Battle(Countdown::new(ROUND_DURATION));
+        g.engine.prepare_new_round();
+        g.outgoing_events.clear();
+
+        g.disconnect_player(1, SessionToken(7)).unwrap();
+        assert!(matches!(
+            g.outgoing_events.as_slice(),
+            [GameEvent::PlayerDisconnected(n)] if n == "p1"
+        ));
+        assert!(g.engine.bots.iter().any(|b| b.player_info.id == dropped));
+        assert_eq!(g.game_master, 2);
+
+        // Back under another connection, in their own tank
+        assert_eq!(g.rejoin(3, SessionToken(8)), None);
+        assert_eq!(g.rejoin(3, SessionToken(7)), Some(dropped));
+        assert!(g.engine.humans.iter().any(|h| h.id == dropped));
+        assert!(g.client_ids().contains(&3));
+
+        // Gone for longer than the grace period, they forfeit
+        g.disconnect_player(3, SessionToken(7)).unwrap();
+        g.outgoing_events.clear();
+        g.tick_away(REJOIN_GRACE);
+        assert!(matches!(
+            g.outgoing_events.as_slice(),
+            [GameEvent::PlayerForfeited(n)] if n == "p1"
+        ));
+        assert_eq!(g.rejoin(3, SessionToken(7)), None);
+        assert!(matches!(
+            g.timeline.summary().timeline.last().unwrap().event,
+            TimelineEvent::Forfeit(id) if id == dropped
+        ));
+    }
+
+    #[test]
+    fn game_master_role_passes_on_when_the_master_leaves() {
+        let mut g = Game::new(1, MapName::Basic, 3);
+        for client in [1, 3, 2] {
+            g.add_player(client, format!("p{client}")).unwrap();
+        }
+
+        g.remove_player(1).unwrap();
+        assert_eq!(g.game_master, 2);
+        assert!(g.start_countdown(2).is_ok());
+
+        // Anyone else leaving changes nothing
+        g.remove_player(3).unwrap();
+        assert_eq!(g.game_master, 2);
+    }
+
     #[test]
     fn remove_player_unknown_client_is_error() {
         let master: ClientId = 1;
@@ -434,7 +1477,7 @@ mod tests {
 
     #[test]
     fn battle_tick_emits_kill_events() {
-        use common::protocol::{KillEvent, Projectile, Team};
+        use common::protocol::{KillEvent, Projectile, ProjectileKind, Team};
 
         let master: ClientId = 1;
         let mut g = Game::new(master, MapName::Basic, 3);
@@ -454,11 +1497,14 @@ mod tests {
             }],
             projectiles: vec![Projectile {
                 id: 1,
+                kind: ProjectileKind::Bullet,
                 owner_info: infos[0].clone(),
                 position: Vec2::new(200.0, 200.0), // hits victim immediately
                 velocity: Vec2::ZERO,
                 radius: 5.0,
+                age: 0.0,
             }],
+            moving_walls: Vec::new(),
         });
 
         g.tick(0.0);
@@ -489,6 +1535,7 @@ mod tests {
                 Vec2::ZERO,
             )],
             projectiles: Vec::new(),
+            moving_walls: Vec::new(),
         });
 
         g.tick(0.0);
@@ -498,6 +1545,17 @@ mod tests {
                 .iter()
                 .any(|e| matches!(e, GameEvent::RoundEnded(_)))
         );
+        // Everyone's stats come with the end of the round
+        let stats = g
+            .outgoing_events
+            .iter()
+            .find_map(|e| match e {
+                GameEvent::MatchStats(stats) => Some(stats),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].player_info.nickname, "p1");
         assert!(matches!(g.game_state_info(), GameStateInfo::Countdown(_)));
     }
 
@@ -513,6 +1571,7 @@ mod tests {
                 Vec2::ZERO,
             )],
             projectiles: Vec::new(),
+            moving_walls: Vec::new(),
         });
 
         g.tick(0.0);
@@ -549,6 +1608,7 @@ mod tests {
                 Vec2::ZERO,
             )],
             projectiles: Vec::new(),
+            moving_walls: Vec::new(),
         });
         g.tick(0.0);
         assert_eq!(g.blue_wins, 0);
@@ -566,6 +1626,7 @@ mod tests {
                 Vec2::ZERO,
             )],
             projectiles: Vec::new(),
+            moving_walls: Vec::new(),
         });
         g.tick(0.0);
         assert_eq!(g.blue_wins, 1);
@@ -583,6 +1644,7 @@ mod tests {
                 Vec2::ZERO,
             )],
             projectiles: Vec::new(),
+            moving_walls: Vec::new(),
         });
         g.tick(0.0);
         assert_eq!(g.blue_wins, 1);
@@ -595,11 +1657,251 @@ mod tests {
                 blue_score,
                 red_score,
             } => {
-                assert_eq!(winner, common::protocol::Team::Red);
+                assert_eq!(winner, Some(common::protocol::Team::Red));
                 assert_eq!(blue_score, 1);
                 assert_eq!(red_score, 2);
             }
             _ => panic!("Expected Results state"),
         }
+
+        // The match's summary goes out once, with every round's result
+        let summaries: Vec<&MatchSummary> = g
+            .outgoing_events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::MatchEnded(summary) => Some(summary),
+                _ => None,
+            })
+            .collect();
+        let [summary] = summaries[..] else {
+            panic!("Expected one match summary, got {:?}", summaries);
+        };
+        let winners: Vec<Option<Team>> = summary
+            .timeline
+            .iter()
+            .filter_map(|entry| match entry.event {
+                TimelineEvent::RoundEnded { winner, .. } => Some(winner),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            winners,
+            [Some(Team::Red), Some(Team::Blue), Some(Team::Red)]
+        );
+    }
+
+    #[test]
+    fn moves_beyond_tank_speed_are_clamped() {
+        let master: ClientId = 1;
+        let mut g = Game::new(master, MapName::Basic, 1);
+        let me = g.add_player(master, "p1".to_string()).unwrap();
+        g.start_countdown(master).unwrap();
+        g.tick(6.0);
+
+        g.handle_player_input(
+            master,
+            0,
+            InputPayload {
+                move_axis: Vec2::X,
+                ..input_shooting_towards(Vec2::ZERO)
+            },
+        );
+        g.tick(0.05);
+        assert!(g.clamped_moves.is_empty());
+
+        fn tank(g: &mut Game, id: PlayerId) -> &mut Tank {
+            g.engine
+                .tanks
+                .iter_mut()
+                .find(|t| t.player_info.id == id)
+                .unwrap()
+        }
+        let from = tank(&mut g, me).position;
+        let positions = HashMap::from([(me, from)]);
+        tank(&mut g, me).position = from + Vec2::new(500.0, 0.0);
+        g.clamp_moves(&positions, 0.05);
+        let max_step = tank(&mut g, me).speed * 0.05 + DISPLACEMENT_SLACK;
+        assert!((tank(&mut g, me).position.x - from.x - max_step).abs() < 1e-3);
+        assert_eq!(g.clamped_moves, vec![master]);
+
+        tank(&mut g, me).position = Vec2::NAN;
+        g.clamp_moves(&positions, 0.05);
+        assert_eq!(tank(&mut g, me).position, from);
+    }
+
+    #[test]
+    fn free_for_all_puts_everyone_on_their_own_team() {
+        let master: ClientId = 1;
+        let mut g = Game::with_mode(master, MapName::Basic, GameMode::FreeForAll, 1, 2);
+        let me = g.add_player(master, "p1".to_string()).unwrap();
+        assert_eq!(g.mode(), GameMode::FreeForAll);
+        assert_eq!(g.engine.tanks()[0].player_info.team, Team::Ffa(me));
+
+        g.start_countdown(master).unwrap();
+        g.tick(6.0);
+        assert_eq!(g.engine.tanks().len(), 4);
+        assert!(
+            g.engine
+                .tanks()
+                .iter()
+                .all(|t| t.player_info.team == Team::Ffa(t.player_info.id))
+        );
+
+        // The others fall: the last one standing wins the round and, with it, the match
+        let survivor = g.engine.tanks()[0].clone();
+        g.engine.apply_snapshot(EngineSnapshot {
+            tanks: vec![survivor],
+            projectiles: Vec::new(),
+            moving_walls: Vec::new(),
+        });
+        g.tick(0.0);
+        assert!(
+            g.outgoing_events
+                .iter()
+                .any(|e| matches!(e, GameEvent::RoundEnded(Some(Team::Ffa(id))) if *id == me))
+        );
+        assert_eq!(
+            g.game_state_info(),
+            GameStateInfo::Results {
+                winner: Some(Team::Ffa(me)),
+                blue_score: 0,
+                red_score: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn multi_arena_game_plays_qualifiers_then_final() {
+        let master: ClientId = 1;
+        let mut g = Game::with_mode(master, MapName::Tiga, GameMode::MultiArena, 5, 8);
+        let player_id = g.add_player(master, "p1".to_string()).unwrap();
+        let info = g.initial_game_info(GameCode("1234".to_string()), player_id);
+        assert_eq!((info.mode, info.num_rounds), (GameMode::MultiArena, 2));
+
+        g.start_countdown(master).unwrap();
+        g.tick(6.0);
+        assert_eq!(g.engine.tanks().len(), multi_arena::PLAYER_LIMIT);
+
+        // Time runs out: both qualifying arenas are decided and the final is next
+        g.outgoing_events.clear();
+        g.state = GameState::Battle(Countdown::new(Duration::from_millis(10)));
+        g.tick(0.02);
+        let decided = g
+            .outgoing_events
+            .iter()
+            .filter(|e| matches!(e, GameEvent::ArenaDecided { .. }))
+            .count();
+        assert_eq!(decided, 2);
+        assert!(matches!(g.game_state_info(), GameStateInfo::Countdown(_)));
+        assert_eq!(g.curr_round, 2);
+
+        g.tick(6.0);
+        let map = g.engine.map().clone();
+        assert_eq!(g.engine.tanks().len(), 2 * multi_arena::SQUAD_SIZE);
+        assert!(
+            g.engine
+                .tanks()
+                .iter()
+                .all(|t| map.arena_index(t.position) == Some(2))
+        );
+
+        g.state = GameState::Battle(Countdown::new(Duration::from_millis(10)));
+        g.tick(0.02);
+        assert!(
+            g.outgoing_events
+                .iter()
+                .any(|e| matches!(e, GameEvent::RoundEnded(_)))
+        );
+        assert!(matches!(g.game_state_info(), GameStateInfo::Results { .. }));
+    }
+
+    #[test]
+    fn pings_reach_the_team_once_per_cooldown() {
+        let master: ClientId = 1;
+        let mut g = Game::new(master, MapName::Basic, 3);
+        let player = g.add_player(master, "p1".to_string()).unwrap();
+        let team = g.team_of(player).unwrap();
+
+        g.handle_ping(master, PingKind::Attack, Vec2::new(-50., 100.));
+        g.handle_ping(master, PingKind::Help, Vec2::new(10., 10.));
+        let [(to, GameEvent::Ping(ping))] = g.team_events.as_slice() else {
+            panic!("expected one ping, got {:?}", g.team_events);
+        };
+        assert_eq!(*to, team);
+        assert_eq!(ping.kind, PingKind::Attack);
+        assert_eq!(ping.world_pos, Vec2::new(0., 100.), "moved onto the map");
+
+        g.tick(PING_COOLDOWN + 0.1);
+        g.handle_ping(master, PingKind::Help, Vec2::new(10., 10.));
+        assert_eq!(g.team_events.len(), 2);
+        let ping = g.engine.blackboard.latest_ping(team).unwrap();
+        assert_eq!(ping.kind, PingKind::Help);
+    }
+
+    #[test]
+    fn coop_match_ends_when_the_players_fall() {
+        let master: ClientId = 1;
+        let mut g = Game::with_mode(master, MapName::Basic, GameMode::Coop, 3, 2);
+        let player = g.add_player(master, "p1".to_string()).unwrap();
+        assert_eq!(g.team_of(player), Some(coop::PLAYERS));
+        assert!(g.add_player(2, "p2".to_string()).is_some());
+        assert!(
+            g.add_player(3, "p3".to_string()).is_none(),
+            "the team is full"
+        );
+
+        g.start_countdown(master).unwrap();
+        g.outgoing_events.clear();
+        g.tick(6.0);
+        assert!(
+            g.outgoing_events
+                .iter()
+                .any(|e| matches!(e, GameEvent::WaveStarted { wave: 1, .. }))
+        );
+
+        g.engine
+            .tanks
+            .retain(|t| t.player_info.team != coop::PLAYERS);
+        g.tick(0.1);
+        assert!(matches!(
+            g.game_state_info(),
+            GameStateInfo::Results {
+                winner: Some(coop::WAVES),
+                blue_score: 0,
+                ..
+            }
+        ));
+        assert!(
+            g.outgoing_events
+                .iter()
+                .any(|e| matches!(e, GameEvent::Survived { waves: 0, .. }))
+        );
+    }
+
+    #[test]
+    fn fallen_players_see_no_more_than_their_team() {
+        let master: ClientId = 1;
+        let mut g = Game::new(master, MapName::Basic, 3);
+        g.set_sight(SightRules {
+            alive: Sight::Everything,
+            ..SightRules::FOG_OF_WAR
+        });
+        let player = g.add_player(master, "p1".to_string()).unwrap();
+        let team = g.team_of(player).unwrap();
+        g.start_countdown(master).unwrap();
+        g.tick(6.0);
+        assert_eq!(g.sight_of(master), Sight::Everything);
+        assert_eq!(g.sight_of(99), Sight::Allies);
+
+        g.engine.tanks.retain(|t| t.player_info.id != player);
+        assert_eq!(g.sight_of(master), Sight::Team);
+        let seen = g.snapshot_seen(&g.snapshot(), Some(team), Sight::Team);
+        assert!(seen.engine.tanks.iter().any(|t| t.player_info.team == team));
+
+        // With the whole team down, the enemy is out of sight
+        g.engine.tanks.retain(|t| t.player_info.team != team);
+        let seen = g.snapshot_seen(&g.snapshot(), Some(team), g.sight_of(master));
+        assert!(seen.engine.tanks.is_empty());
+        assert!(!g.snapshot().engine.tanks.is_empty());
     }
 }
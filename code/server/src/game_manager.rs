@@ -1,76 +1,211 @@
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
+use std::time::Duration;
 use tracing::{debug, info};
 
+use crate::exhibition::MODELS_DIR;
 use crate::game::{Game, StartCountdownError};
+use crate::lifecycle::{LobbyRules, Verdict};
+use crate::load::GameLoad;
+use crate::model_library::ModelLibrary;
+use crate::sight::{Sight, SightRules};
+use common::game::MAX_TEAM_SIZE;
+use common::game::map::mix64;
+use common::game::player::TankColor;
 use common::protocol::{
-    ClientId, CreateGameResponse, GameCode, GameState, GameUpdate, InitialGameInfo, InputPayload,
-    JoinGameResponse, MapName,
+    ClientId, CloseReason, CreateGameResponse, GameCode, GameEvent, GameMode, GameState,
+    GameUpdate, InitialGameInfo, InputPayload, JoinGameResponse, MapName, PlayerId, ServerError,
+    SessionToken, Team,
 };
 
 pub struct GameManager {
     pub games: HashMap<GameCode, Game>,
+    /// Models that new games can use
+    pub models: ModelLibrary,
+    /// Sight rules of new games
+    pub sight: SightRules,
+    /// When unused games are closed
+    pub lobby: LobbyRules,
+    /// Clients of the games closed since last taken
+    closed: Vec<(GameCode, ClientId)>,
+    /// Every game's seed is derived from this and the game's code, see [`game_seed`]
+    seed: u64,
+    /// Draws game codes
     rng: StdRng,
+    /// Number of the snapshots taken this tick
+    tick: u32,
 }
 
 const MAX_GAMES: usize = 128;
 
 impl GameManager {
     pub fn new() -> Self {
-        Self {
-            games: HashMap::new(),
-            rng: StdRng::from_os_rng(),
-        }
+        Self::new_seeded(rand::rng().random())
     }
 
-    /// Create a game manager with deterministic randomness (useful for tests).
-    #[cfg(test)]
-    #[allow(dead_code)]
+    /// Create a game manager whose game codes and game seeds all follow from `seed`.
     pub fn new_seeded(seed: u64) -> Self {
         Self {
             games: HashMap::new(),
+            models: ModelLibrary::new(MODELS_DIR),
+            sight: SightRules::default(),
+            lobby: LobbyRules::default(),
+            closed: Vec::new(),
+            seed,
             rng: StdRng::seed_from_u64(seed),
+            tick: 0,
         }
     }
 
     /// Create a game manager with a provided RNG (useful for tests).
     #[cfg(test)]
     #[allow(dead_code)]
-    pub fn new_with_rng(rng: StdRng) -> Self {
+    pub fn new_with_rng(mut rng: StdRng) -> Self {
         Self {
-            games: HashMap::new(),
+            seed: rng.random(),
             rng,
+            ..Self::new_seeded(0)
         }
     }
 
-    /// Advances all games by `dt`.
+    /// The master seed of every game's seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Replaces the master seed, e.g. with one given on the command line. Only games
+    /// created from now on follow it.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Advances all games by `dt`, and closes the unused ones after their last update.
     /// Returns a list of (Recipients, UpdatePacket) pairs to be broadcasted.
     pub fn tick(&mut self, dt: f32) -> Vec<(Vec<ClientId>, GameUpdate)> {
         let mut updates = Vec::new();
+        let mut closing = Vec::new();
+        self.tick = self.tick.wrapping_add(1);
 
-        for game in self.games.values_mut() {
+        for (game_code, game) in self.games.iter_mut() {
             game.tick(dt);
 
-            let events = std::mem::take(&mut game.outgoing_events);
+            let in_lobby = game.game_state_info() == GameState::Waiting;
+            let empty = game.is_empty();
+            match game
+                .lifecycle
+                .tick(Duration::from_secs_f32(dt), empty, in_lobby, &self.lobby)
+            {
+                Verdict::Keep => {}
+                Verdict::Warn(reason, left) => {
+                    game.outgoing_events.push(GameEvent::ClosingSoon {
+                        reason,
+                        seconds: left.as_secs_f32().ceil() as u32,
+                    });
+                }
+                Verdict::Close(reason) => {
+                    game.outgoing_events.push(GameEvent::Closed(reason));
+                    closing.push((game_code.clone(), reason));
+                }
+            }
 
-            let update = GameUpdate {
-                snapshot: game.snapshot(),
-                events,
-            };
+            let events = std::mem::take(&mut game.outgoing_events);
+            let team_events = std::mem::take(&mut game.team_events);
+
+            let snapshot = game.snapshot();
+
+            // Clients who get the same events and see the same share an update. The team
+            // only tells clients apart when it changes either.
+            let mut groups: Vec<(Option<Team>, Sight, Vec<ClientId>)> = Vec::new();
+            for (client_id, team) in game.client_teams() {
+                let sight = game.sight_of(client_id);
+                let team = team.filter(|_| !team_events.is_empty() || sight != Sight::Everything);
+                match groups
+                    .iter_mut()
+                    .find(|(t, s, _)| (*t, *s) == (team, sight))
+                {
+                    Some((_, _, clients)) => clients.push(client_id),
+                    None => groups.push((team, sight, vec![client_id])),
+                }
+            }
+            for (team, sight, clients) in groups {
+                let mut events = events.clone();
+                events.extend(
+                    team_events
+                        .iter()
+                        .filter(|(t, _)| Some(*t) == team)
+                        .map(|(_, event)| event.clone()),
+                );
+                let update = GameUpdate {
+                    tick: self.tick,
+                    snapshot: game.snapshot_seen(&snapshot, team, sight),
+                    events,
+                    input_acks: game.input_acks(&clients),
+                };
+                updates.push((clients, update));
+            }
+        }
 
-            updates.push((game.client_ids(), update));
+        for (game_code, reason) in closing {
+            self.close_game(&game_code, reason);
         }
         updates
     }
 
+    /// Removes a game; its players are taken with [`GameManager::take_closed`].
+    fn close_game(&mut self, game_code: &GameCode, reason: CloseReason) {
+        let Some(game) = self.games.remove(game_code) else {
+            return;
+        };
+        info!(?reason, "Game closed: {:?}", game_code);
+        self.closed.extend(
+            game.client_teams()
+                .into_iter()
+                .map(|(client_id, _)| (game_code.clone(), client_id)),
+        );
+    }
+
+    /// The players of the games closed since the last call, with their game.
+    pub fn take_closed(&mut self) -> Vec<(GameCode, ClientId)> {
+        std::mem::take(&mut self.closed)
+    }
+
+    /// Each game's load over the last second, by game code.
+    pub fn loads(&self) -> Vec<(GameCode, GameLoad)> {
+        let mut loads: Vec<_> = self
+            .games
+            .iter()
+            .map(|(code, game)| (code.clone(), game.load.last_window()))
+            .collect();
+        loads.sort_by(|a, b| a.0.0.cmp(&b.0.0));
+        loads
+    }
+
+    /// Clients whose tanks had their moves clamped since the last call.
+    pub fn take_clamped_moves(&mut self) -> Vec<ClientId> {
+        self.games
+            .values_mut()
+            .flat_map(|game| std::mem::take(&mut game.clamped_moves))
+            .collect()
+    }
+
     pub fn create_game(
         &mut self,
         game_master: ClientId,
         nickname: String,
         map: MapName,
         rounds: u8,
-    ) -> Result<CreateGameResponse, String> {
+        mode: GameMode,
+        team_size: u8,
+    ) -> Result<CreateGameResponse, ServerError> {
+        if !(1..=MAX_TEAM_SIZE).contains(&usize::from(team_size)) {
+            return Err(ServerError::TeamSize {
+                requested: team_size,
+                max: MAX_TEAM_SIZE as u8,
+            });
+        }
+        let team_size = usize::from(team_size);
         if self.games.len() >= MAX_GAMES {
             debug!(%game_master, "Failed to create game: server full of games");
             return Ok(CreateGameResponse::TooManyGames);
@@ -78,22 +213,59 @@ impl GameManager {
 
         let game_code = self.generate_code();
 
-        let mut game = Game::new(game_master, map, rounds);
+        let mut game = Game::with_mode(game_master, map, mode, rounds, team_size);
+        game.set_seed(game_seed(self.seed, &game_code));
+        game.set_sight(self.sight);
 
         let player_id = game
             .add_player(game_master, nickname)
-            .ok_or("Failed to add player to game")?;
+            .ok_or(ServerError::GameFull)?;
 
+        let info = game.initial_game_info(game_code.clone(), player_id);
         self.games.insert(game_code.clone(), game);
-        info!("Game created: {:?}", game_code);
+        info!(seed = info.seed, "Game created: {:?}", game_code);
 
-        Ok(CreateGameResponse::Ok(InitialGameInfo {
-            game_code,
-            player_id,
-            num_rounds: rounds,
-            map_name: map,
-            game_master,
-        }))
+        Ok(CreateGameResponse::Ok(info))
+    }
+
+    /// Creates a game run by the server rather than a game master: every player is added
+    /// up front and `setup` configures bots before the countdown starts right away.
+    pub fn create_server_game(
+        &mut self,
+        map: MapName,
+        rounds: u8,
+        players: &[(ClientId, String)],
+        setup: impl FnOnce(&mut Game),
+    ) -> Result<(GameCode, Vec<InitialGameInfo>), String> {
+        let &[(game_master, _), ..] = players else {
+            return Err("A game needs at least one player".to_string());
+        };
+        if self.games.len() >= MAX_GAMES {
+            return Err("Server full of games".to_string());
+        }
+
+        let game_code = self.generate_code();
+        let mut game = Game::new(game_master, map, rounds);
+        game.set_seed(game_seed(self.seed, &game_code));
+        game.set_sight(self.sight);
+        setup(&mut game);
+        let mut player_ids = Vec::new();
+        for (client_id, nickname) in players {
+            let player_id = game
+                .add_player(*client_id, nickname.clone())
+                .ok_or("Failed to add player to game")?;
+            player_ids.push(player_id);
+        }
+        game.force_countdown();
+        // Told after the countdown started, so the players see it from the start
+        let infos = player_ids
+            .into_iter()
+            .map(|player_id| game.initial_game_info(game_code.clone(), player_id))
+            .collect();
+
+        info!(seed = game.seed(), "Server game created: {:?}", game_code);
+        self.games.insert(game_code.clone(), game);
+        Ok((game_code, infos))
     }
 
     pub fn join_game(
@@ -104,10 +276,10 @@ impl GameManager {
     ) -> JoinGameResponse {
         let Some(game) = self.games.get_mut(game_code) else {
             debug!(?game_code, %client_id, "Failed to join game: invalid code");
-            return JoinGameResponse::InvalidCode;
+            return JoinGameResponse::InvalidCode(game_code.clone());
         };
 
-        if game.game_state_info() != GameState::Waiting {
+        if !game.accepts_joins() {
             debug!(?game_code, %client_id, "Failed to join game: game already started");
             return JoinGameResponse::GameStarted;
         }
@@ -124,17 +296,37 @@ impl GameManager {
         }
     }
 
-    pub fn leave_game(&mut self, game_code: &GameCode, client_id: ClientId) -> Result<(), String> {
-        let Some(game) = self.games.get_mut(game_code) else {
-            return Err("Game does not exist".to_string());
-        };
+    /// Sets the trim of a player's tanks in the game.
+    pub fn set_color(
+        &mut self,
+        game_code: &GameCode,
+        player_id: PlayerId,
+        color: Option<TankColor>,
+    ) {
+        if let Some(game) = self.games.get_mut(game_code) {
+            game.set_color(player_id, color);
+        }
+    }
+
+    /// The game with `game_code`, or the error telling the client it's gone.
+    pub fn game_mut(&mut self, game_code: &GameCode) -> Result<&mut Game, ServerError> {
+        self.games
+            .get_mut(game_code)
+            .ok_or_else(|| ServerError::NoSuchGame(game_code.clone()))
+    }
 
+    pub fn leave_game(
+        &mut self,
+        game_code: &GameCode,
+        client_id: ClientId,
+    ) -> Result<(), ServerError> {
+        let game = self.game_mut(game_code)?;
         game.remove_player(client_id)
-            .ok_or_else(|| "Player not found in game".to_string())?;
+            .ok_or(ServerError::NotInGame)?;
 
         info!(?game_code, %client_id, "Player left game");
 
-        if game.is_empty() {
+        if game.is_empty() && self.lobby.empty_grace.is_zero() {
             self.games.remove(game_code);
             info!("Game removed (no players left): {:?}", game_code);
         }
@@ -146,22 +338,14 @@ impl GameManager {
         &mut self,
         game_code: &GameCode,
         client_id: ClientId,
-    ) -> Result<(), String> {
-        let Some(game) = self.games.get_mut(game_code) else {
-            return Err("Game does not exist".to_string());
-        };
-
-        match game.start_countdown(client_id) {
+    ) -> Result<(), ServerError> {
+        match self.game_mut(game_code)?.start_countdown(client_id) {
             Ok(()) => {
                 info!(?game_code, %client_id, "Countdown started");
                 Ok(())
             }
-            Err(StartCountdownError::NotTheGameMaster) => {
-                Err("Only the game master can start the countdown".to_string())
-            }
-            Err(StartCountdownError::NotInWaitingState) => {
-                Err("Game is not in waiting state".to_string())
-            }
+            Err(StartCountdownError::NotTheGameMaster) => Err(ServerError::NotGameMaster),
+            Err(StartCountdownError::NotInWaitingState) => Err(ServerError::NotWaiting),
         }
     }
 
@@ -169,23 +353,26 @@ impl GameManager {
         &mut self,
         game_code: &GameCode,
         client_id: ClientId,
+        seq: u32,
         input: InputPayload,
-    ) -> Result<(), String> {
-        let game = self.games.get_mut(game_code).ok_or("Game does not exist")?;
-        game.handle_player_input(client_id, input);
+    ) -> Result<(), ServerError> {
+        let game = self.game_mut(game_code)?;
+        game.handle_player_input(client_id, seq, input);
         Ok(())
     }
 
-    pub fn remove_player(
+    /// Takes out a player whose connection dropped; see [`Game::disconnect_player`].
+    pub fn disconnect_player(
         &mut self,
         game_code: &GameCode,
         client_id: ClientId,
-    ) -> Result<(), String> {
-        let game = self.games.get_mut(game_code).ok_or("Game does not exist")?;
-        game.remove_player(client_id)
-            .ok_or("Player not found in game")?;
+        session: SessionToken,
+    ) -> Result<(), ServerError> {
+        let game = self.game_mut(game_code)?;
+        game.disconnect_player(client_id, session)
+            .ok_or(ServerError::NotInGame)?;
 
-        if game.is_empty() {
+        if game.is_empty() && self.lobby.empty_grace.is_zero() {
             self.games.remove(game_code);
             info!("Game removed (no players left): {:?}", game_code);
         }
@@ -193,6 +380,20 @@ impl GameManager {
         Ok(())
     }
 
+    /// Puts `client_id` back into the match it dropped out of with `session`, if it can
+    /// still come back.
+    pub fn rejoin(
+        &mut self,
+        client_id: ClientId,
+        session: SessionToken,
+    ) -> Option<InitialGameInfo> {
+        self.games.iter_mut().find_map(|(game_code, game)| {
+            let player_id = game.rejoin(client_id, session)?;
+            info!(?game_code, %client_id, "Player rejoined game");
+            Some(game.initial_game_info(game_code.clone(), player_id))
+        })
+    }
+
     fn generate_code(&mut self) -> GameCode {
         loop {
             let code = GameCode(self.rng.random_range(1000..9999).to_string());
@@ -203,12 +404,21 @@ impl GameManager {
     }
 }
 
+/// The seed of the game with `code` on a server with `master_seed`. It only depends on
+/// the two, not on the other games, so any game can be replayed on its own.
+pub fn game_seed(master_seed: u64, code: &GameCode) -> u64 {
+    let z = code.0.bytes().fold(master_seed, |z, byte| {
+        (z ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+    });
+    mix64(z)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use common::protocol::{CreateGameResponse, GameEvent};
 
-    fn unwrap_game_code(resp: Result<CreateGameResponse, String>) -> GameCode {
+    fn unwrap_game_code(resp: Result<CreateGameResponse, ServerError>) -> GameCode {
         match resp.expect("create_game should not unexpectedly fail") {
             CreateGameResponse::Ok(info) => info.game_code,
             _ => unreachable!("create_game should succeed for MapName::Basic"),
@@ -220,7 +430,14 @@ mod tests {
         let mut gm = GameManager::new_seeded(123);
         let host: ClientId = 1;
 
-        let resp = gm.create_game(host, "host".to_string(), MapName::Basic, 3);
+        let resp = gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        );
         let (game_code, player_id) = match resp {
             Ok(CreateGameResponse::Ok(info)) => (info.game_code, info.player_id),
             _ => unreachable!("create_game should succeed for MapName::Basic"),
@@ -237,7 +454,7 @@ mod tests {
     fn join_nonexistent_game_is_error() {
         let mut gm = GameManager::new_seeded(0);
         let resp = gm.join_game(&GameCode("9999".to_string()), 1, "p1".to_string());
-        assert!(matches!(resp, JoinGameResponse::InvalidCode));
+        assert!(matches!(resp, JoinGameResponse::InvalidCode(_)));
     }
 
     #[test]
@@ -245,10 +462,16 @@ mod tests {
         let mut gm = GameManager::new_seeded(0);
         let host: ClientId = 1;
 
-        let game_code =
-            unwrap_game_code(gm.create_game(host, "host".to_string(), MapName::Basic, 3));
+        let game_code = unwrap_game_code(gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ));
 
-        // MapName::Basic has 8 spawn points. create_game added 1 player already,
+        // A 4v4 game fits 8 players. create_game added 1 player already,
         // so 7 more joins should succeed, and the 9th should fail.
         for i in 0..7 {
             let client_id: ClientId = 10 + i;
@@ -260,13 +483,56 @@ mod tests {
         assert!(matches!(join, JoinGameResponse::GameFull));
     }
 
+    #[test]
+    fn team_size_sets_the_player_limit() {
+        let mut gm = GameManager::new_seeded(0);
+        let game_code = unwrap_game_code(gm.create_game(
+            1,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            8,
+        ));
+        for client_id in 10..25 {
+            let join = gm.join_game(&game_code, client_id, format!("p{client_id}"));
+            assert!(matches!(join, JoinGameResponse::Ok(_)));
+        }
+        let join = gm.join_game(&game_code, 999, "too_many".to_string());
+        assert!(matches!(join, JoinGameResponse::GameFull));
+
+        for team_size in [0, 9] {
+            let resp = gm.create_game(
+                2,
+                "host".to_string(),
+                MapName::Basic,
+                3,
+                GameMode::TeamDeathmatch,
+                team_size,
+            );
+            assert_eq!(
+                resp,
+                Err(ServerError::TeamSize {
+                    requested: team_size,
+                    max: 8
+                })
+            );
+        }
+    }
+
     #[test]
     fn leave_game_removes_game_when_last_player_leaves() {
         let mut gm = GameManager::new_seeded(1);
         let host: ClientId = 1;
 
-        let game_code =
-            unwrap_game_code(gm.create_game(host, "host".to_string(), MapName::Basic, 3));
+        let game_code = unwrap_game_code(gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ));
 
         gm.leave_game(&game_code, host).unwrap();
         assert!(!gm.games.contains_key(&game_code));
@@ -284,8 +550,14 @@ mod tests {
         let mut gm = GameManager::new_seeded(0);
         let host: ClientId = 1;
 
-        let game_code =
-            unwrap_game_code(gm.create_game(host, "host".to_string(), MapName::Basic, 3));
+        let game_code = unwrap_game_code(gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ));
 
         let leave = gm.leave_game(&game_code, 999);
         assert!(leave.is_err());
@@ -298,8 +570,14 @@ mod tests {
         let host: ClientId = 1;
         let joiner: ClientId = 2;
 
-        let game_code =
-            unwrap_game_code(gm.create_game(host, "host".to_string(), MapName::Basic, 3));
+        let game_code = unwrap_game_code(gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ));
 
         let join = gm.join_game(&game_code, joiner, "joiner".to_string());
         assert!(matches!(join, JoinGameResponse::Ok(_)));
@@ -326,23 +604,25 @@ mod tests {
             .submit_input(
                 &GameCode("9999".to_string()),
                 1,
+                0,
                 InputPayload {
                     move_axis: glam::Vec2::ZERO,
                     aim_pos: glam::Vec2::ZERO,
                     shoot: false,
+                    grenade: false,
                 },
             )
             .unwrap_err();
-        assert!(err.contains("Game does not exist"));
+        assert_eq!(err, ServerError::NoSuchGame(GameCode("9999".to_string())));
     }
 
     #[test]
-    fn remove_player_nonexistent_game_is_err() {
+    fn disconnect_player_nonexistent_game_is_err() {
         let mut gm = GameManager::new_seeded(0);
         let err = gm
-            .remove_player(&GameCode("9999".to_string()), 1)
+            .disconnect_player(&GameCode("9999".to_string()), 1, SessionToken(0))
             .unwrap_err();
-        assert!(err.contains("Game does not exist"));
+        assert_eq!(err, ServerError::NoSuchGame(GameCode("9999".to_string())));
     }
 
     #[test]
@@ -350,8 +630,14 @@ mod tests {
         let mut gm = GameManager::new_seeded(3);
         let host: ClientId = 1;
 
-        let game_code =
-            unwrap_game_code(gm.create_game(host, "host".to_string(), MapName::Basic, 3));
+        let game_code = unwrap_game_code(gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ));
 
         // create_game adds PlayerJoined event.
         assert!(
@@ -374,4 +660,143 @@ mod tests {
         // Should be drained from the game.
         assert!(gm.games[&game_code].outgoing_events.is_empty());
     }
+
+    #[test]
+    fn game_seeds_follow_the_master_seed_and_code() {
+        let create = |gm: &mut GameManager, host| match gm.create_game(
+            host,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ) {
+            Ok(CreateGameResponse::Ok(info)) => (info.game_code, info.seed),
+            _ => unreachable!("create_game should succeed for MapName::Basic"),
+        };
+        let (mut a, mut b) = (GameManager::new_seeded(7), GameManager::new_seeded(7));
+        let (code, seed) = create(&mut a, 1);
+        assert_eq!((code.clone(), seed), create(&mut b, 1));
+        assert_eq!(seed, game_seed(7, &code));
+
+        // Other games don't change it
+        let (other_code, other_seed) = create(&mut a, 2);
+        assert_ne!(other_seed, seed);
+        assert_eq!(other_seed, game_seed(7, &other_code));
+        assert_ne!(game_seed(8, &code), seed);
+    }
+
+    fn manager_with_rules(lobby: LobbyRules) -> (GameManager, GameCode) {
+        let mut gm = GameManager::new_seeded(0);
+        gm.lobby = lobby;
+        let game_code = unwrap_game_code(gm.create_game(
+            1,
+            "host".to_string(),
+            MapName::Basic,
+            3,
+            GameMode::TeamDeathmatch,
+            4,
+        ));
+        (gm, game_code)
+    }
+
+    fn events(updates: &[(Vec<ClientId>, GameUpdate)]) -> Vec<GameEvent> {
+        updates
+            .iter()
+            .flat_map(|(_, update)| update.events.clone())
+            .collect()
+    }
+
+    #[test]
+    fn updates_acknowledge_the_last_input_of_their_recipients() {
+        let (mut gm, game_code) = manager_with_rules(LobbyRules::default());
+        gm.join_game(&game_code, 2, "guest".to_string());
+        for seq in [6, 7] {
+            gm.submit_input(&game_code, 1, seq, InputPayload::default())
+                .unwrap();
+        }
+
+        let updates = gm.tick(0.1);
+        // Both players see everything in the lobby, so they share an update
+        assert_eq!(updates.len(), 1);
+        let seqs: Vec<u32> = updates[0]
+            .1
+            .input_acks
+            .iter()
+            .map(|(_, seq)| *seq)
+            .collect();
+        // The guest hasn't sent any input yet
+        assert_eq!(seqs, vec![7]);
+    }
+
+    #[test]
+    fn idle_games_are_closed_after_a_warning() {
+        let (mut gm, game_code) = manager_with_rules(LobbyRules {
+            idle_timeout: Duration::from_secs(10),
+            warning: Duration::from_secs(3),
+            ..LobbyRules::default()
+        });
+
+        let updates = gm.tick(8.0);
+        assert!(events(&updates).contains(&GameEvent::ClosingSoon {
+            reason: CloseReason::Idle,
+            seconds: 2,
+        }));
+        assert!(gm.take_closed().is_empty());
+
+        let updates = gm.tick(2.0);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0, vec![1]);
+        assert!(events(&updates).contains(&GameEvent::Closed(CloseReason::Idle)));
+        assert!(gm.games.is_empty());
+        assert_eq!(gm.take_closed(), vec![(game_code, 1)]);
+    }
+
+    #[test]
+    fn rejoining_an_empty_game_just_before_it_closes_keeps_it() {
+        let (mut gm, game_code) = manager_with_rules(LobbyRules {
+            empty_grace: Duration::from_secs(5),
+            ..LobbyRules::default()
+        });
+        gm.leave_game(&game_code, 1).unwrap();
+        gm.tick(4.9);
+        assert!(gm.games.contains_key(&game_code));
+
+        assert!(matches!(
+            gm.join_game(&game_code, 2, "back".to_string()),
+            JoinGameResponse::Ok(_)
+        ));
+        gm.tick(1.0);
+        // The one who came back runs the game now
+        assert!(gm.start_countdown(&game_code, 2).is_ok());
+
+        gm.leave_game(&game_code, 2).unwrap();
+        gm.tick(5.0);
+        assert!(gm.games.is_empty());
+        // Nobody was left in it
+        assert!(gm.take_closed().is_empty());
+    }
+
+    #[test]
+    fn starting_a_lobby_about_to_expire_keeps_it() {
+        let (mut gm, game_code) = manager_with_rules(LobbyRules {
+            max_lobby_age: Duration::from_secs(10),
+            warning: Duration::from_secs(3),
+            ..LobbyRules::default()
+        });
+        let updates = gm.tick(9.0);
+        assert!(events(&updates).contains(&GameEvent::ClosingSoon {
+            reason: CloseReason::LobbyExpired,
+            seconds: 1,
+        }));
+
+        gm.start_countdown(&game_code, 1).unwrap();
+        let updates = gm.tick(5.0);
+        assert!(gm.games.contains_key(&game_code));
+        assert!(
+            !events(&updates)
+                .iter()
+                .any(|event| matches!(event, GameEvent::Closed(_)))
+        );
+    }
 }
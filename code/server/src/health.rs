@@ -0,0 +1,235 @@
+//! HTTP status endpoint for whatever supervises the server (systemd, Docker, Kubernetes).
+//!
+//! - `GET /livez`: 200 while the tick loop keeps ticking. A supervisor should restart the
+//!   server when this fails.
+//! - `GET /readyz`: 200 once the server is live and accepts players, 503 before that and
+//!   while shutting down. Send players elsewhere when this fails.
+//! - `GET /status`: always 200, with the counts behind the two checks and each game's load
+//!   over the last second as JSON.
+//!
+//! `server --healthcheck` asks `/readyz` and exits with 0 or 1, for `HEALTHCHECK` lines and
+//! `ExecStartPost` scripts that can't speak HTTP themselves.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use common::protocol::GameCode;
+use common::status;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info};
+
+use crate::load::GameLoad;
+
+pub const DEFAULT_STATUS_ADDR: &str = "0.0.0.0:8081";
+/// Where `--healthcheck` looks by default: the status endpoint of a server on this machine.
+pub const DEFAULT_CHECK_ADDR: &str = "127.0.0.1:8081";
+/// The server counts as stuck when it hasn't ticked for this long.
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long `--healthcheck` and the endpoint wait on a slow peer.
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_SIZE: usize = 1024;
+
+/// What the tick loop tells the status endpoint.
+pub struct Health {
+    started: Instant,
+    /// Milliseconds after `started` of the last tick
+    last_tick_ms: AtomicU64,
+    ready: AtomicBool,
+    clients: AtomicUsize,
+    games: AtomicUsize,
+    /// Each game's load over the last second
+    game_loads: Mutex<Vec<(GameCode, GameLoad)>>,
+}
+
+impl Health {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            started: Instant::now(),
+            last_tick_ms: AtomicU64::new(0),
+            ready: AtomicBool::new(false),
+            clients: AtomicUsize::new(0),
+            games: AtomicUsize::new(0),
+            game_loads: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Records a finished tick.
+    pub fn heartbeat(&self, clients: usize, game_loads: Vec<(GameCode, GameLoad)>) {
+        let now = self.started.elapsed().as_millis() as u64;
+        self.last_tick_ms.store(now, Ordering::Relaxed);
+        self.clients.store(clients, Ordering::Relaxed);
+        self.games.store(game_loads.len(), Ordering::Relaxed);
+        *self.game_loads.lock().unwrap() = game_loads;
+    }
+
+    /// Whether the server takes players: true once it has started, false again when it is
+    /// shutting down.
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Relaxed);
+    }
+
+    fn live_at(&self, now: Instant) -> bool {
+        let last_tick =
+            self.started + Duration::from_millis(self.last_tick_ms.load(Ordering::Relaxed));
+        now.saturating_duration_since(last_tick) < LIVENESS_TIMEOUT
+    }
+
+    /// Status code and JSON body of the answer to `GET path`.
+    fn respond(&self, path: &str, now: Instant) -> (u16, String) {
+        let live = self.live_at(now);
+        let ready = live && self.ready.load(Ordering::Relaxed);
+        let game_load: Vec<_> = self
+            .game_loads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(code, load)| json!({ "code": code.0, "load": load }))
+            .collect();
+        let body = json!({
+            "live": live,
+            "ready": ready,
+            "clients": self.clients.load(Ordering::Relaxed),
+            "games": self.games.load(Ordering::Relaxed),
+            "uptime_secs": now.saturating_duration_since(self.started).as_secs(),
+            "version": common::version(),
+            "game_load": game_load,
+        })
+        .to_string();
+
+        let code = match path {
+            "/livez" if live => 200,
+            "/readyz" if ready => 200,
+            "/livez" | "/readyz" => 503,
+            "/status" => 200,
+            _ => return (404, json!({ "error": "not found" }).to_string()),
+        };
+        (code, body)
+    }
+}
+
+/// Answers status requests until the runtime shuts down.
+pub async fn serve(listener: TcpListener, health: Arc<Health>) {
+    if let Ok(addr) = listener.local_addr() {
+        info!("Status endpoint listening on {}", addr);
+    }
+    loop {
+        let Ok((stream, peer)) = listener.accept().await else {
+            continue;
+        };
+        let health = health.clone();
+        tokio::spawn(async move {
+            if let Err(err) = answer(stream, &health).await {
+                debug!(%peer, error = %err, "Status request failed");
+            }
+        });
+    }
+}
+
+async fn answer(mut stream: tokio::net::TcpStream, health: &Health) -> std::io::Result<()> {
+    let mut request = [0u8; MAX_REQUEST_SIZE];
+    let len = tokio::time::timeout(IO_TIMEOUT, stream.read(&mut request))
+        .await
+        .map_err(|_| std::io::ErrorKind::TimedOut)??;
+    let (code, body) = match request_path(&String::from_utf8_lossy(&request[..len])) {
+        Some(path) => health.respond(path, Instant::now()),
+        None => (400, json!({ "error": "bad request" }).to_string()),
+    };
+    stream.write_all(response(code, &body).as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Path of a `GET` request, from its request line.
+fn request_path(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    match (parts.next()?, parts.next()?) {
+        ("GET", path) => Some(path.split('?').next().unwrap_or(path)),
+        _ => None,
+    }
+}
+
+fn response(code: u16, body: &str) -> String {
+    let reason = match code {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Service Unavailable",
+    };
+    format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )
+}
+
+/// Asks the server whose status endpoint is at `addr` whether it is ready.
+pub fn check(addr: SocketAddr) -> Result<(), String> {
+    let readiness = status::check_ready(addr, IO_TIMEOUT)?;
+    if readiness.ready {
+        Ok(())
+    } else {
+        Err(format!("Not ready: {}", readiness.body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_needs_a_recent_tick_and_the_ready_flag() {
+        let health = Health::new();
+        let now = health.started;
+        let load = GameLoad {
+            inputs_received: 60,
+            ..GameLoad::default()
+        };
+        health.heartbeat(3, vec![(GameCode("1234".into()), load)]);
+        assert_eq!(health.respond("/livez", now).0, 200);
+        assert_eq!(health.respond("/readyz", now).0, 503);
+
+        health.set_ready(true);
+        let (code, body) = health.respond("/readyz", now);
+        assert_eq!(code, 200);
+        assert!(body.contains("\"clients\":3"));
+        assert!(body.contains("\"games\":1"));
+        assert!(body.contains("\"inputs_received\":60"));
+
+        // A stuck tick loop is neither live nor ready, but still reports its status
+        let later = now + LIVENESS_TIMEOUT + Duration::from_secs(1);
+        assert_eq!(health.respond("/livez", later).0, 503);
+        assert_eq!(health.respond("/readyz", later).0, 503);
+        assert_eq!(health.respond("/status", later).0, 200);
+        assert_eq!(health.respond("/metrics", now).0, 404);
+    }
+
+    #[test]
+    fn test_http_lines_are_parsed() {
+        assert_eq!(
+            request_path("GET /readyz?verbose HTTP/1.1\r\nHost: x\r\n\r\n"),
+            Some("/readyz")
+        );
+        assert_eq!(request_path("POST /readyz HTTP/1.1\r\n"), None);
+        assert_eq!(request_path(""), None);
+        assert_eq!(status::status_code(&response(503, "{}")), Some(503));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_healthcheck_follows_readiness() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let health = Health::new();
+        health.heartbeat(0, Vec::new());
+        tokio::spawn(serve(listener, health.clone()));
+
+        let check_now = move || tokio::task::spawn_blocking(move || check(addr));
+        assert!(check_now().await.unwrap().is_err());
+        health.set_ready(true);
+        assert_eq!(check_now().await.unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,213 @@
+//! When the server closes games that nobody uses any more.
+
+use common::protocol::CloseReason;
+use std::time::Duration;
+
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 600;
+pub const DEFAULT_EMPTY_GRACE_SECS: u64 = 0;
+pub const DEFAULT_MAX_LOBBY_AGE_SECS: u64 = 1800;
+pub const DEFAULT_CLOSE_WARNING_SECS: u64 = 30;
+
+/// How long games may sit unused before the server closes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LobbyRules {
+    /// A game where nobody joined, left, moved or shot for this long is closed
+    pub idle_timeout: Duration,
+    /// An empty game is kept this long, so its players can come back. Zero closes it
+    /// as the last player leaves.
+    pub empty_grace: Duration,
+    /// A lobby that hasn't started by this age is closed
+    pub max_lobby_age: Duration,
+    /// How long before a game is closed its players are warned
+    pub warning: Duration,
+}
+
+impl Default for LobbyRules {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS),
+            empty_grace: Duration::from_secs(DEFAULT_EMPTY_GRACE_SECS),
+            max_lobby_age: Duration::from_secs(DEFAULT_MAX_LOBBY_AGE_SECS),
+            warning: Duration::from_secs(DEFAULT_CLOSE_WARNING_SECS),
+        }
+    }
+}
+
+/// What to do with a game after a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Keep,
+    /// Tell the players it closes in this long
+    Warn(CloseReason, Duration),
+    Close(CloseReason),
+}
+
+/// How long a game has been around, and unused.
+#[derive(Debug, Clone, Default)]
+pub struct Lifecycle {
+    age: Duration,
+    idle: Duration,
+    /// How long the game has had no players, if it has none
+    empty_for: Option<Duration>,
+    /// The players were warned that the game closes for this reason
+    warned: Option<CloseReason>,
+}
+
+impl Lifecycle {
+    /// Someone did something in the game, so it isn't idle.
+    pub fn touch(&mut self) {
+        self.idle = Duration::ZERO;
+        if self.warned == Some(CloseReason::Idle) {
+            self.warned = None;
+        }
+    }
+
+    /// Advances the clocks by `dt` and checks them against `rules`. `empty` is whether
+    /// the game has no players; `in_lobby` whether it is still waiting to start.
+    pub fn tick(
+        &mut self,
+        dt: Duration,
+        empty: bool,
+        in_lobby: bool,
+        rules: &LobbyRules,
+    ) -> Verdict {
+        self.age += dt;
+        self.idle += dt;
+        self.empty_for = empty.then(|| self.empty_for.unwrap_or_default() + dt);
+
+        let (reason, left) = self.time_left(in_lobby, rules);
+        if left.is_zero() {
+            Verdict::Close(reason)
+        } else if left <= rules.warning && self.warned != Some(reason) && !empty {
+            self.warned = Some(reason);
+            Verdict::Warn(reason, left)
+        } else {
+            Verdict::Keep
+        }
+    }
+
+    /// The soonest reason the game will be closed for, and how long until then.
+    fn time_left(&self, in_lobby: bool, rules: &LobbyRules) -> (CloseReason, Duration) {
+        let mut soonest = (
+            CloseReason::Idle,
+            rules.idle_timeout.saturating_sub(self.idle),
+        );
+        let mut consider = |reason, left: Duration| {
+            if left < soonest.1 {
+                soonest = (reason, left);
+            }
+        };
+        if in_lobby {
+            consider(
+                CloseReason::LobbyExpired,
+                rules.max_lobby_age.saturating_sub(self.age),
+            );
+        }
+        if let Some(empty_for) = self.empty_for {
+            consider(
+                CloseReason::Empty,
+                rules.empty_grace.saturating_sub(empty_for),
+            );
+        }
+        soonest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn rules() -> LobbyRules {
+        LobbyRules {
+            idle_timeout: Duration::from_secs(60),
+            empty_grace: Duration::from_secs(5),
+            max_lobby_age: Duration::from_secs(100),
+            warning: Duration::from_secs(10),
+        }
+    }
+
+    #[test]
+    fn test_idle_games_are_warned_once_then_closed() {
+        let mut lifecycle = Lifecycle::default();
+        let rules = rules();
+        assert_eq!(
+            lifecycle.tick(49 * SECOND, false, false, &rules),
+            Verdict::Keep
+        );
+        assert_eq!(
+            lifecycle.tick(SECOND, false, false, &rules),
+            Verdict::Warn(CloseReason::Idle, 10 * SECOND)
+        );
+        assert_eq!(lifecycle.tick(SECOND, false, false, &rules), Verdict::Keep);
+
+        // Activity puts it off, and it is warned again the next time
+        lifecycle.touch();
+        assert_eq!(
+            lifecycle.tick(50 * SECOND, false, false, &rules),
+            Verdict::Warn(CloseReason::Idle, 10 * SECOND)
+        );
+        assert_eq!(
+            lifecycle.tick(10 * SECOND, false, false, &rules),
+            Verdict::Close(CloseReason::Idle)
+        );
+    }
+
+    #[test]
+    fn test_lobbies_expire_however_busy_they_are() {
+        let mut lifecycle = Lifecycle::default();
+        let rules = rules();
+        for _ in 0..9 {
+            lifecycle.tick(10 * SECOND, false, true, &rules);
+            lifecycle.touch();
+        }
+        assert_eq!(lifecycle.tick(SECOND, false, true, &rules), Verdict::Keep);
+        lifecycle.touch();
+        assert_eq!(
+            lifecycle.tick(9 * SECOND, false, true, &rules),
+            Verdict::Close(CloseReason::LobbyExpired)
+        );
+
+        // Started games are past their lobby
+        let mut started = Lifecycle::default();
+        started.tick(50 * SECOND, false, false, &rules);
+        started.touch();
+        started.tick(45 * SECOND, false, false, &rules);
+        started.touch();
+        assert_eq!(
+            started.tick(45 * SECOND, false, false, &rules),
+            Verdict::Keep
+        );
+    }
+
+    #[test]
+    fn test_empty_games_have_a_grace_period() {
+        let mut lifecycle = Lifecycle::default();
+        let rules = rules();
+        // Nobody is left to warn
+        assert_eq!(
+            lifecycle.tick(4 * SECOND, true, true, &rules),
+            Verdict::Keep
+        );
+        // Coming back in time starts the grace period afresh
+        lifecycle.tick(SECOND / 2, false, true, &rules);
+        assert_eq!(
+            lifecycle.tick(4 * SECOND, true, true, &rules),
+            Verdict::Keep
+        );
+        assert_eq!(
+            lifecycle.tick(SECOND, true, true, &rules),
+            Verdict::Close(CloseReason::Empty)
+        );
+
+        let no_grace = LobbyRules {
+            empty_grace: Duration::ZERO,
+            ..rules
+        };
+        assert_eq!(
+            Lifecycle::default().tick(Duration::ZERO, true, true, &no_grace),
+            Verdict::Close(CloseReason::Empty)
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! How much work each game makes the server do, to find the one behind a load spike.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Counts are summed over this long before they are reported.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// What a game cost the server over a window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GameLoad {
+    pub inputs_received: u64,
+    /// Inputs that arrived after a later one of the same player, and were dropped
+    pub stale_inputs: u64,
+    /// Bot updates, one per bot and tick
+    pub bots_simulated: u64,
+    /// Bytes of game updates, counted once per recipient
+    pub bytes_sent: u64,
+    /// Projectiles removed to stay within the engine's cap
+    pub culled_projectiles: u64,
+}
+
+/// Counts a game's load, reporting the totals of the last full window.
+#[derive(Debug, Default, Clone)]
+pub struct LoadCounter {
+    pub current: GameLoad,
+    elapsed: Duration,
+    last_window: GameLoad,
+}
+
+impl LoadCounter {
+    /// Advances time by `dt`, closing the window once it is full.
+    pub fn tick(&mut self, dt: Duration) {
+        self.elapsed += dt;
+        if self.elapsed >= WINDOW {
+            self.elapsed = Duration::ZERO;
+            self.last_window = std::mem::take(&mut self.current);
+        }
+    }
+
+    /// The totals of the last full window.
+    pub fn last_window(&self) -> GameLoad {
+        self.last_window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_reported_per_window() {
+        let mut load = LoadCounter::default();
+        load.current.inputs_received += 3;
+        load.tick(Duration::from_millis(600));
+        assert_eq!(load.last_window(), GameLoad::default());
+
+        load.current.bytes_sent += 100;
+        load.tick(Duration::from_millis(400));
+        assert_eq!(
+            load.last_window(),
+            GameLoad {
+                inputs_received: 3,
+                bytes_sent: 100,
+                ..GameLoad::default()
+            }
+        );
+        assert_eq!(load.current, GameLoad::default());
+    }
+}
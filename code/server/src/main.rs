@@ -1,24 +1,179 @@
+mod anti_cheat;
+mod baselines;
 mod client;
+mod coop;
 mod countdown;
+mod exhibition;
+mod free_for_all;
 mod game;
 mod game_manager;
+mod health;
+mod lifecycle;
+mod load;
+mod model_library;
+mod model_transfer;
+mod multi_arena;
+mod relay_agent;
 mod server;
 mod server_logic;
+mod shop;
+mod sight;
+mod tournament;
 
+use clap::Parser;
+use common::crash;
+use common::rl::contest::ContestantSpec;
+use exhibition::ExhibitionConfig;
+use health::Health;
+use lifecycle::LobbyRules;
 use server::ServerApp;
+use sight::SightRules;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::time::{self, Duration, MissedTickBehavior};
+use tournament::TournamentConfig;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
 
 const TICK_INTERVAL: Duration = Duration::from_micros(16_666); // ≈60 Hz
+/// Where crash reports are saved
+const CRASH_DIR: &str = "crashes";
 
 type AppResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Instead of serving, play headless matches between two bots and exit.
+    /// Each is a model name from assets/models or `scripted:<difficulty>`.
+    #[arg(long, num_args = 2, value_names = ["BOT_A", "BOT_B"])]
+    exhibition: Option<Vec<ContestantSpec>>,
+
+    /// Map for exhibition matches: a built-in one or a file name from assets/maps.
+    #[arg(long, default_value = "basic", requires = "exhibition")]
+    map: String,
+
+    /// Number of exhibition matches; sides alternate every match.
+    #[arg(long, default_value_t = 10, requires = "exhibition")]
+    matches: u32,
+
+    /// Save a replay of every exhibition match into this directory.
+    #[arg(long, requires = "exhibition")]
+    replays: Option<PathBuf>,
+
+    /// Run the tournament described in this JSON file alongside normal games.
+    #[arg(long, conflicts_with = "exhibition")]
+    tournament: Option<PathBuf>,
+
+    /// Don't pick up changed model files on their own; the `reload` console command
+    /// still works.
+    #[arg(long, conflicts_with = "exhibition")]
+    no_watch: bool,
+
+    /// Address of the HTTP status endpoint (`/livez`, `/readyz`, `/status`).
+    #[arg(long, default_value = health::DEFAULT_STATUS_ADDR, conflicts_with = "exhibition")]
+    status_addr: SocketAddr,
+
+    /// Instead of serving, ask the status endpoint of a running server whether it is
+    /// ready and exit with 0 if it is, 1 if not.
+    #[arg(
+        long,
+        value_name = "STATUS_ADDR",
+        num_args = 0..=1,
+        default_missing_value = health::DEFAULT_CHECK_ADDR,
+        conflicts_with = "exhibition"
+    )]
+    healthcheck: Option<SocketAddr>,
+
+    /// Also take players through the relay at this address (`host:port`), for when they
+    /// can't reach this server directly, e.g. behind a home router.
+    #[arg(long, conflicts_with = "exhibition")]
+    relay: Option<String>,
+
+    /// Send players only what their team can see, so the fallen and spectators have no
+    /// enemy positions to pass on.
+    #[arg(long, conflicts_with = "exhibition")]
+    fog_of_war: bool,
+
+    /// Master seed of the games: each game's seed follows from it and the game's code,
+    /// so a game can be reproduced on its own. Random if omitted; logged at startup.
+    #[arg(long, conflicts_with = "exhibition")]
+    seed: Option<u64>,
+
+    /// Close games where nobody joined, left, moved or shot for this many seconds.
+    #[arg(long, default_value_t = lifecycle::DEFAULT_IDLE_TIMEOUT_SECS, conflicts_with = "exhibition")]
+    idle_timeout_secs: u64,
+
+    /// Keep a game this many seconds after its last player left, so they can come back.
+    #[arg(long, default_value_t = lifecycle::DEFAULT_EMPTY_GRACE_SECS, conflicts_with = "exhibition")]
+    empty_grace_secs: u64,
+
+    /// Close lobbies that haven't started after this many seconds.
+    #[arg(long, default_value_t = lifecycle::DEFAULT_MAX_LOBBY_AGE_SECS, conflicts_with = "exhibition")]
+    max_lobby_age_secs: u64,
+
+    /// Warn the players of a game this many seconds before it is closed.
+    #[arg(long, default_value_t = lifecycle::DEFAULT_CLOSE_WARNING_SECS, conflicts_with = "exhibition")]
+    close_warning_secs: u64,
+}
+
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    let args = Args::parse();
+    if let Some(addr) = args.healthcheck {
+        match health::check(addr) {
+            Ok(()) => std::process::exit(0),
+            Err(reason) => {
+                eprintln!("{}", reason);
+                std::process::exit(1);
+            }
+        }
+    }
     init_tracing();
+    crash::install_panic_hook("server", common::version(), CRASH_DIR.into());
+    info!("NeuroBlasters server {}", common::version());
+
+    if let Some([a, b]) = args.exhibition.as_deref() {
+        let config = ExhibitionConfig {
+            contestants: [a.clone(), b.clone()],
+            map: args.map,
+            matches: args.matches,
+            match_duration: game::ROUND_DURATION,
+            replay_dir: args.replays,
+        };
+        exhibition::run(&config)?;
+        return Ok(());
+    }
+
+    let tournament = args
+        .tournament
+        .as_deref()
+        .map(TournamentConfig::load)
+        .transpose()?;
+    let health = Health::new();
+    let status_listener = tokio::net::TcpListener::bind(args.status_addr).await?;
+    tokio::spawn(health::serve(status_listener, health.clone()));
 
-    let mut app = ServerApp::new()?;
+    let sight = if args.fog_of_war {
+        SightRules::FOG_OF_WAR
+    } else {
+        SightRules::OPEN
+    };
+    let lobby = LobbyRules {
+        idle_timeout: Duration::from_secs(args.idle_timeout_secs),
+        empty_grace: Duration::from_secs(args.empty_grace_secs),
+        max_lobby_age: Duration::from_secs(args.max_lobby_age_secs),
+        warning: Duration::from_secs(args.close_warning_secs),
+    };
+    let mut app = ServerApp::new(tournament, sight, lobby, args.seed, !args.no_watch)?;
+    if let Some(relay) = args.relay {
+        tokio::spawn(relay_agent::run(relay, server::SERVER_PORT));
+    }
+    health.set_ready(true);
+    let mut console = BufReader::new(tokio::io::stdin()).lines();
 
     let mut ticker = time::interval(TICK_INTERVAL);
     ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
@@ -27,13 +182,20 @@ async fn main() -> AppResult<()> {
         tokio::select! {
             _ = tokio::signal::ctrl_c() => {
                 info!("Shutting down...");
+                health.set_ready(false);
                 break;
             }
             _ = ticker.tick() => {
                 if let Err(err) = app.tick() {
                     error!(error = %err, "Tick failed");
                 }
+                let (clients, _, _) = app.status();
+                let loads = app.game_loads();
+                let codes: Vec<&str> = loads.iter().map(|(code, _)| code.0.as_str()).collect();
+                crash::set_state(format!("{} clients, games {:?}", clients, codes));
+                health.heartbeat(clients, loads);
             }
+            Ok(Some(command)) = console.next_line() => app.run_command(&command),
         }
     }
 
@@ -43,8 +205,28 @@ async fn main() -> AppResult<()> {
 
 fn init_tracing() {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    let _ = tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_target(false)
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_target(false))
+        .with(
+            fmt::layer()
+                .with_target(false)
+                .with_ansi(false)
+                .with_writer(|| CrashLog),
+        )
         .try_init();
 }
+
+/// Hands log lines to crash reports.
+struct CrashLog;
+
+impl std::io::Write for CrashLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        crash::record_log(String::from_utf8_lossy(buf).trim_end());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
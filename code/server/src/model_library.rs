@@ -0,0 +1,224 @@
+//! The bot models on disk, reloaded while the server runs. Games take their own copy of a
+//! model when they start, so a reload only affects games created afterwards.
+
+use crate::model_transfer::SharedModel;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Tells whether a file changed since it was last read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+struct Entry {
+    stamp: FileStamp,
+    /// `None` if the file could not be loaded and there was no older version
+    model: Option<SharedModel>,
+}
+
+/// What a reload changed.
+#[derive(Debug, Default, PartialEq)]
+pub struct ReloadReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Files that failed to load, with the reason; an older version stays in use
+    pub failed: Vec<(String, String)>,
+}
+
+impl ReloadReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.removed.is_empty()
+            && self.failed.is_empty()
+    }
+
+    fn log(&self) {
+        for (name, e) in &self.failed {
+            warn!(%name, %e, "Failed to load model");
+        }
+        if !self.added.is_empty() || !self.updated.is_empty() || !self.removed.is_empty() {
+            info!(
+                added = ?self.added,
+                updated = ?self.updated,
+                removed = ?self.removed,
+                "Models reloaded"
+            );
+        }
+    }
+}
+
+pub struct ModelLibrary {
+    dir: PathBuf,
+    entries: HashMap<String, Entry>,
+}
+
+impl ModelLibrary {
+    /// An empty library of the `*.bin` files in `dir`; call [`ModelLibrary::reload`] to
+    /// read them.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<SharedModel, String> {
+        self.entries
+            .get(name)
+            .and_then(|entry| entry.model.clone())
+            .ok_or_else(|| format!("Unknown model {}", name))
+    }
+
+    /// Names of the models that loaded, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.model.is_some())
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Whether any file was added, changed or removed since the last reload.
+    pub fn changed_on_disk(&self) -> bool {
+        let files = scan(&self.dir);
+        files.len() != self.entries.len()
+            || files.iter().any(|(name, (_, stamp))| {
+                self.entries
+                    .get(name)
+                    .is_none_or(|entry| entry.stamp != *stamp)
+            })
+    }
+
+    /// Reads new and changed files and forgets removed ones.
+    pub fn reload(&mut self) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        let files = scan(&self.dir);
+
+        self.entries.retain(|name, _| {
+            let keep = files.contains_key(name);
+            if !keep {
+                report.removed.push(name.clone());
+            }
+            keep
+        });
+
+        for (name, (path, stamp)) in files {
+            let previous = self.entries.get(&name);
+            if previous.is_some_and(|entry| entry.stamp == stamp) {
+                continue;
+            }
+            let existed = previous.is_some_and(|entry| entry.model.is_some());
+            let model = match load(&name, &path) {
+                Ok(model) => {
+                    if existed {
+                        report.updated.push(name.clone());
+                    } else {
+                        report.added.push(name.clone());
+                    }
+                    Some(model)
+                }
+                Err(e) => {
+                    report.failed.push((name.clone(), e));
+                    previous.and_then(|entry| entry.model.clone())
+                }
+            };
+            // The stamp is kept even on failure, so a broken file is reported only once
+            self.entries.insert(name, Entry { stamp, model });
+        }
+
+        for list in [&mut report.added, &mut report.updated, &mut report.removed] {
+            list.sort();
+        }
+        report.failed.sort();
+        report.log();
+        report
+    }
+}
+
+fn scan(dir: &Path) -> HashMap<String, (PathBuf, FileStamp)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "bin") {
+                return None;
+            }
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            let name = path.file_stem()?.to_str()?.to_string();
+            let stamp = FileStamp {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+            };
+            Some((name, (path, stamp)))
+        })
+        .collect()
+}
+
+/// Reads a model and checks that it decodes, so a half-written file never reaches a game.
+fn load(name: &str, path: &Path) -> Result<SharedModel, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let model = SharedModel::new(name.to_string(), bytes)?;
+    model.brain()?;
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::module::Module;
+    use burn::record::{BinFileRecorder, FullPrecisionSettings};
+    use burn_ndarray::NdArray;
+    use common::rl::BotBrain;
+
+    fn save_model(dir: &Path, name: &str) {
+        let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
+        BotBrain::<NdArray>::new(&Default::default())
+            .save_file(dir.join(name), &recorder)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_reload_tracks_files() {
+        let dir = std::env::temp_dir().join("neuroblasters_model_library");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut library = ModelLibrary::new(&dir);
+
+        save_model(&dir, "alpha");
+        fs::write(dir.join("broken.bin"), b"not a model").unwrap();
+        assert!(library.changed_on_disk());
+        let report = library.reload();
+        assert_eq!(report.added, vec!["alpha".to_string()]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(library.get("alpha").is_ok());
+        assert!(library.get("broken").is_err());
+        assert_eq!(library.names(), vec!["alpha".to_string()]);
+
+        // A broken file is not retried until it changes
+        assert!(!library.changed_on_disk());
+        assert!(library.reload().is_empty());
+
+        // A game keeps the copy it took
+        let taken = library.get("alpha").unwrap();
+        fs::remove_file(dir.join("alpha.bin")).unwrap();
+        let report = library.reload();
+        assert_eq!(report.removed, vec!["alpha".to_string()]);
+        assert!(library.get("alpha").is_err());
+        assert!(taken.brain().is_ok());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,106 @@
+//! Uploads of bot models to clients that asked for them, a few chunks per tick so game
+//! updates keep flowing.
+
+use burn_ndarray::NdArray;
+use common::protocol::{ClientId, ModelInfo, ServerMessage};
+use common::rl::BotBrain;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// About 1 MB/s at the server tick rate.
+const CHUNKS_PER_TICK: u32 = 1;
+
+/// A model file a game offers to its clients.
+#[derive(Debug, Clone)]
+pub struct SharedModel {
+    pub info: ModelInfo,
+    bytes: Arc<Vec<u8>>,
+}
+
+impl SharedModel {
+    pub fn new(name: String, bytes: Vec<u8>) -> Result<Self, String> {
+        Ok(Self {
+            info: ModelInfo::new(name, &bytes)?,
+            bytes: Arc::new(bytes),
+        })
+    }
+
+    /// A fresh copy of the network stored in the file.
+    pub fn brain(&self) -> Result<BotBrain<NdArray>, String> {
+        BotBrain::from_bytes(self.bytes.to_vec(), &Default::default())
+            .map_err(|e| format!("Failed to load model {}: {}", self.info.name, e))
+    }
+}
+
+struct Upload {
+    model: SharedModel,
+    next_index: u32,
+}
+
+#[derive(Default)]
+pub struct ModelUploads {
+    active: HashMap<ClientId, Upload>,
+}
+
+impl ModelUploads {
+    /// Replaces any upload already going to `client_id`.
+    pub fn start(&mut self, client_id: ClientId, model: SharedModel) {
+        self.active.insert(
+            client_id,
+            Upload {
+                model,
+                next_index: 0,
+            },
+        );
+    }
+
+    pub fn cancel(&mut self, client_id: ClientId) {
+        self.active.remove(&client_id);
+    }
+
+    /// The chunks to send this tick.
+    pub fn tick(&mut self) -> Vec<(ClientId, ServerMessage)> {
+        let mut messages = Vec::new();
+        self.active.retain(|client_id, upload| {
+            let count = upload.model.info.chunk_count();
+            let end = (upload.next_index + CHUNKS_PER_TICK).min(count);
+            for index in upload.next_index..end {
+                let chunk = upload.model.info.chunk(&upload.model.bytes, index);
+                messages.push((*client_id, ServerMessage::ModelChunk(chunk)));
+            }
+            upload.next_index = end;
+            end < count
+        });
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::transfer::{MODEL_CHUNK_SIZE, ModelDownload};
+
+    #[test]
+    fn test_upload_is_paced_and_complete() {
+        let bytes = vec![7; MODEL_CHUNK_SIZE * 2 + 1];
+        let model = SharedModel::new("champion".into(), bytes.clone()).unwrap();
+        let mut download = ModelDownload::new(model.info.clone()).unwrap();
+        let mut uploads = ModelUploads::default();
+        uploads.start(1, model);
+
+        let mut received = None;
+        for _ in 0..3 {
+            let messages = uploads.tick();
+            assert_eq!(messages.len(), CHUNKS_PER_TICK as usize);
+            for (client_id, message) in messages {
+                assert_eq!(client_id, 1);
+                let ServerMessage::ModelChunk(chunk) = message else {
+                    panic!("expected a chunk");
+                };
+                received = download.accept(chunk).unwrap();
+            }
+        }
+        assert_eq!(received, Some(bytes));
+        assert!(uploads.tick().is_empty());
+    }
+}
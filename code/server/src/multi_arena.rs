@@ -0,0 +1,190 @@
+//! Multi-arena games: squads fight 4v4 in the qualifying arenas of one big map at the same
+//! time, then the winning squads meet in the final arena.
+
+use crate::game::winner_by_health;
+use common::game::arena::QUALIFYING_ARENAS;
+use common::game::check_arena_winner;
+use common::game::engine::{GameEngine, Squad};
+use common::protocol::{PlayerId, Team};
+use rand::Rng;
+use std::collections::BTreeMap;
+
+pub(crate) const SQUAD_SIZE: usize = 4;
+/// Every squad of the qualifiers can be all humans.
+pub(crate) const PLAYER_LIMIT: usize = QUALIFYING_ARENAS * 2 * SQUAD_SIZE;
+/// The qualifying round and the final.
+pub(crate) const ROUNDS: u8 = 2;
+/// The final arena comes after the qualifying ones.
+const FINAL_ARENA: usize = QUALIFYING_ARENAS;
+
+#[derive(Default)]
+pub(crate) struct MultiArena {
+    /// Squads of the current round
+    squads: Vec<Squad>,
+    /// Winner of every arena decided so far
+    winners: BTreeMap<usize, Team>,
+}
+
+impl MultiArena {
+    /// Deals the humans out over a squad per team and qualifying arena, then spawns
+    /// everyone. Bots make up the numbers.
+    pub fn start_qualifiers(&mut self, engine: &mut GameEngine) {
+        self.squads = (0..QUALIFYING_ARENAS)
+            .flat_map(|arena| [Squad::new(arena, Team::Blue), Squad::new(arena, Team::Red)])
+            .collect();
+        let humans: Vec<PlayerId> = engine.humans.iter().map(|h| h.id).collect();
+        let count = self.squads.len();
+        for (i, id) in humans.into_iter().enumerate() {
+            self.squads[i % count].members.push(id);
+        }
+        self.winners.clear();
+        engine.prepare_arena_round(&mut self.squads, SQUAD_SIZE);
+    }
+
+    /// Moves the winning squad of every qualifying arena into the final, the first one
+    /// playing Blue. Fallen members come back for it.
+    pub fn start_final(&mut self, engine: &mut GameEngine) {
+        let finalists = self.winners.iter().filter_map(|(&arena, &team)| {
+            self.squads
+                .iter()
+                .find(|s| s.arena == arena && s.team == team)
+        });
+        self.squads = finalists
+            .zip([Team::Blue, Team::Red])
+            .map(|(squad, team)| Squad {
+                arena: FINAL_ARENA,
+                team,
+                members: squad.members.clone(),
+            })
+            .collect();
+        engine.prepare_arena_round(&mut self.squads, SQUAD_SIZE);
+    }
+
+    /// Decides every arena of this round that is over: one team is wiped out, or the time
+    /// is up and the team with more health left wins, `rng` breaking ties. Returns the
+    /// newly decided arenas.
+    pub fn update(
+        &mut self,
+        engine: &GameEngine,
+        timed_out: bool,
+        rng: &mut impl Rng,
+    ) -> Vec<(usize, Team)> {
+        let mut decided = Vec::new();
+        for arena in self.arenas() {
+            if self.winners.contains_key(&arena) {
+                continue;
+            }
+            let tanks = engine
+                .tanks
+                .iter()
+                .filter(|t| engine.map.arena_index(t.position) == Some(arena));
+            let winner = match check_arena_winner(&engine.tanks, &engine.map, arena) {
+                Some(team) => team,
+                // Also covers both squads falling in the same tick
+                None if timed_out || tanks.clone().next().is_none() => winner_by_health(tanks, rng),
+                None => continue,
+            };
+            self.winners.insert(arena, winner);
+            decided.push((arena, winner));
+        }
+        decided
+    }
+
+    /// Every arena of this round has a winner.
+    pub fn round_over(&self) -> bool {
+        self.arenas()
+            .into_iter()
+            .all(|arena| self.winners.contains_key(&arena))
+    }
+
+    /// Winner of the final, once decided.
+    pub fn champion(&self) -> Option<Team> {
+        self.winners.get(&FINAL_ARENA).copied()
+    }
+
+    fn arenas(&self) -> Vec<usize> {
+        let mut arenas: Vec<usize> = self.squads.iter().map(|s| s.arena).collect();
+        arenas.dedup();
+        arenas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::ai::BotRng;
+    use common::protocol::{GameMode, MapDefinition, MapName};
+    use rand::SeedableRng;
+
+    fn engine_with_humans(count: usize) -> GameEngine {
+        let map = MapDefinition::for_mode(MapName::Basic, GameMode::MultiArena);
+        let mut engine = GameEngine::new(map);
+        for i in 0..count {
+            engine.add_player(format!("human{}", i)).unwrap();
+        }
+        engine
+    }
+
+    /// Removes the tanks of `team` from `arena`.
+    fn wipe_out(engine: &mut GameEngine, arena: usize, team: Team) {
+        let map = engine.map.clone();
+        engine
+            .tanks
+            .retain(|t| map.arena_index(t.position) != Some(arena) || t.player_info.team != team);
+    }
+
+    #[test]
+    fn test_winners_advance_to_the_final() {
+        let mut engine = engine_with_humans(3);
+        let mut arenas = MultiArena::default();
+        let mut rng = BotRng::seed_from_u64(0);
+        arenas.start_qualifiers(&mut engine);
+        assert_eq!(engine.tanks.len(), PLAYER_LIMIT);
+
+        // The third human plays in the second arena, for Blue
+        let third = engine.humans[2].id;
+        let squad = arenas.squads.iter().find(|s| s.members.contains(&third));
+        assert_eq!(squad.map(|s| (s.arena, s.team)), Some((1, Team::Blue)));
+
+        wipe_out(&mut engine, 0, Team::Blue);
+        assert_eq!(
+            arenas.update(&engine, false, &mut rng),
+            vec![(0, Team::Red)]
+        );
+        assert!(!arenas.round_over());
+        assert!(arenas.update(&engine, false, &mut rng).is_empty());
+
+        // Time runs out in the second arena: Red has taken more damage
+        for tank in engine.tanks.iter_mut() {
+            if engine.map.arena_index(tank.position) == Some(1)
+                && tank.player_info.team == Team::Red
+            {
+                tank.health = 10.0;
+            }
+        }
+        assert_eq!(
+            arenas.update(&engine, true, &mut rng),
+            vec![(1, Team::Blue)]
+        );
+        assert!(arenas.round_over());
+        assert_eq!(arenas.champion(), None);
+
+        arenas.start_final(&mut engine);
+        assert_eq!(engine.tanks.len(), 2 * SQUAD_SIZE);
+        // The second arena's winners, the third human among them, play Red in the final
+        let me = engine
+            .tanks
+            .iter()
+            .find(|t| t.player_info.id == third)
+            .unwrap();
+        assert_eq!(me.player_info.team, Team::Red);
+        assert_eq!(engine.map.arena_index(me.position), Some(FINAL_ARENA));
+
+        wipe_out(&mut engine, FINAL_ARENA, Team::Blue);
+        assert_eq!(
+            arenas.update(&engine, false, &mut rng),
+            vec![(FINAL_ARENA, Team::Red)]
+        );
+        assert_eq!(arenas.champion(), Some(Team::Red));
+    }
+}
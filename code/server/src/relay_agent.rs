@@ -0,0 +1,196 @@
+//! Keeps a room open on a relay, for servers players can't reach directly (`--relay`).
+//!
+//! Every player coming through the relay gets a socket on localhost that talks to the game
+//! port, so netcode sees each of them at an address of their own.
+
+use common::relay::{KEEPALIVE_INTERVAL, ROOM_TIMEOUT, RelayPacket};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// Large enough for any netcode packet with the relay header around it.
+const MAX_PACKET_SIZE: usize = 2048;
+
+/// A player coming through the relay.
+struct Peer {
+    socket: Arc<UdpSocket>,
+    /// Passes the game port's answers back to the relay
+    forwarder: JoinHandle<()>,
+    last_seen: Instant,
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        self.forwarder.abort();
+    }
+}
+
+/// Registers with the relay at `relay` and forwards its players to the game port, until the
+/// runtime shuts down.
+pub async fn run(relay: String, game_port: u16) {
+    if let Err(err) = relay_players(&relay, game_port).await {
+        warn!(%relay, error = %err, "Relay connection failed; players can only connect directly");
+    }
+}
+
+async fn relay_players(relay: &str, game_port: u16) -> std::io::Result<()> {
+    let relay_addr = tokio::net::lookup_host(relay)
+        .await?
+        .next()
+        .ok_or_else(|| std::io::Error::other("relay not found"))?;
+    let unspecified: SocketAddr = if relay_addr.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = Arc::new(UdpSocket::bind(unspecified).await?);
+    socket.connect(relay_addr).await?;
+    let game_addr: SocketAddr = ([127, 0, 0, 1], game_port).into();
+
+    let mut peers: HashMap<SocketAddr, Peer> = HashMap::new();
+    let mut room_port = None;
+    let mut keepalive = time::interval(KEEPALIVE_INTERVAL);
+    keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    let mut register = Vec::new();
+    RelayPacket::Register.encode(&mut register);
+
+    loop {
+        tokio::select! {
+            _ = keepalive.tick() => {
+                // Also what keeps the NAT mapping to the relay open
+                let _ = socket.send(&register).await;
+                peers.retain(|_, peer| peer.last_seen.elapsed() < ROOM_TIMEOUT);
+            }
+            received = socket.recv(&mut buf) => {
+                let Ok(len) = received else {
+                    // E.g. the relay isn't up yet; the next keepalive tries again
+                    continue;
+                };
+                match RelayPacket::decode(&buf[..len]) {
+                    Some(RelayPacket::Registered { port }) if room_port != Some(port) => {
+                        room_port = Some(port);
+                        info!(
+                            "Players can connect through the relay at {}:{}",
+                            relay.rsplit_once(':').map_or(relay, |(host, _)| host),
+                            port
+                        );
+                    }
+                    Some(RelayPacket::Data { peer, payload }) => {
+                        let entry = match peers.get_mut(&peer) {
+                            Some(entry) => entry,
+                            None => match connect_peer(peer, game_addr, socket.clone()).await {
+                                Ok(entry) => peers.entry(peer).or_insert(entry),
+                                Err(err) => {
+                                    warn!(%peer, error = %err, "Can't relay player");
+                                    continue;
+                                }
+                            },
+                        };
+                        entry.last_seen = Instant::now();
+                        let _ = entry.socket.send(payload).await;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Opens the localhost socket of a new player.
+async fn connect_peer(
+    peer: SocketAddr,
+    game_addr: SocketAddr,
+    relay: Arc<UdpSocket>,
+) -> std::io::Result<Peer> {
+    let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await?);
+    socket.connect(game_addr).await?;
+    let local = socket.clone();
+    let forwarder = tokio::spawn(async move {
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        let mut packet = Vec::new();
+        while let Ok(len) = local.recv(&mut buf).await {
+            packet.clear();
+            RelayPacket::Data {
+                peer,
+                payload: &buf[..len],
+            }
+            .encode(&mut packet);
+            let _ = relay.send(&packet).await;
+        }
+    });
+    Ok(Peer {
+        socket,
+        forwarder,
+        last_seen: Instant::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn receive(socket: &UdpSocket) -> (Vec<u8>, SocketAddr) {
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        let (len, from) = time::timeout(Duration::from_secs(2), socket.recv_from(&mut buf))
+            .await
+            .expect("nothing received")
+            .unwrap();
+        (buf[..len].to_vec(), from)
+    }
+
+    #[tokio::test]
+    async fn test_relayed_players_reach_the_game_port_from_their_own_sockets() {
+        let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let game = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        tokio::spawn(run(
+            relay.local_addr().unwrap().to_string(),
+            game.local_addr().unwrap().port(),
+        ));
+
+        let (register, agent) = receive(&relay).await;
+        assert_eq!(RelayPacket::decode(&register), Some(RelayPacket::Register));
+
+        let players: [SocketAddr; 2] = [
+            "203.0.113.1:5000".parse().unwrap(),
+            "203.0.113.2:5000".parse().unwrap(),
+        ];
+        let mut seen_from = Vec::new();
+        for (i, peer) in players.into_iter().enumerate() {
+            let mut packet = Vec::new();
+            RelayPacket::Data {
+                peer,
+                payload: &[i as u8],
+            }
+            .encode(&mut packet);
+            relay.send_to(&packet, agent).await.unwrap();
+
+            let (payload, from) = receive(&game).await;
+            assert_eq!(payload, [i as u8]);
+            seen_from.push(from);
+
+            // The game's answer goes back to the relay, addressed to the player
+            game.send_to(b"ok", from).await.unwrap();
+            let packet = loop {
+                let (packet, _) = receive(&relay).await;
+                if RelayPacket::decode(&packet) != Some(RelayPacket::Register) {
+                    break packet;
+                }
+            };
+            assert_eq!(
+                RelayPacket::decode(&packet),
+                Some(RelayPacket::Data {
+                    peer,
+                    payload: b"ok"
+                })
+            );
+        }
+        assert_ne!(seen_from[0], seen_from[1]);
+    }
+}
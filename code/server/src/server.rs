@@ -1,17 +1,24 @@
 use std::{net::SocketAddr, net::UdpSocket, time::Duration, time::Instant};
 
 use common::codec::{decode_client_message, encode_server_message};
-use common::protocol::ServerMessage;
+use common::protocol::{GameCode, ServerMessage};
 
+use crate::anti_cheat::AntiCheatStats;
+use crate::lifecycle::LobbyRules;
+use crate::load::GameLoad;
 use crate::server_logic::{MAX_CLIENTS, ServerLogic};
+use crate::sight::SightRules;
+use crate::tournament::TournamentConfig;
 
 use renet::{ClientId, ConnectionConfig, RenetServer, ServerEvent};
 use renet_netcode::{NetcodeServerTransport, ServerAuthentication, ServerConfig};
 use tracing::{info, warn};
 
-const SERVER_PORT: u16 = 8080;
+pub const SERVER_PORT: u16 = 8080;
 const PROTOCOL_ID: u64 = 0;
 const RELIABLE_CHANNEL_ID: u8 = 0;
+/// How often the content directories are checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
 
 type AppResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -22,10 +29,18 @@ pub struct ServerApp {
     logic: ServerLogic,
 
     last_tick: Instant,
+    /// When to look for changed content next; `None` if not watching
+    next_watch: Option<Instant>,
 }
 
 impl ServerApp {
-    pub fn new() -> AppResult<Self> {
+    pub fn new(
+        tournament: Option<TournamentConfig>,
+        sight: SightRules,
+        lobby: LobbyRules,
+        seed: Option<u64>,
+        watch: bool,
+    ) -> AppResult<Self> {
         let current_time = Duration::ZERO;
         let public_addr: SocketAddr = ([0, 0, 0, 0], SERVER_PORT).into();
         let server_config = ServerConfig {
@@ -42,14 +57,78 @@ impl ServerApp {
 
         info!("Server listening on port {}", SERVER_PORT);
 
+        let mut logic = match tournament {
+            Some(config) => ServerLogic::with_tournament(config),
+            None => ServerLogic::new(),
+        };
+        let game_manager = logic.game_manager_mut();
+        game_manager.sight = sight;
+        game_manager.lobby = lobby;
+        if let Some(seed) = seed {
+            game_manager.reseed(seed);
+        }
+        info!(seed = game_manager.seed(), "Master seed of the games");
+        logic.reload_content();
+
         Ok(Self {
             server,
             transport,
-            logic: ServerLogic::new(),
+            logic,
             last_tick: Instant::now(),
+            next_watch: watch.then(|| Instant::now() + WATCH_INTERVAL),
         })
     }
 
+    /// Runs an operator command typed into the server console.
+    pub fn run_command(&mut self, command: &str) {
+        match command.trim() {
+            "" => {}
+            "reload" => {
+                if self.logic.reload_content().is_empty() {
+                    info!("Nothing to reload");
+                }
+            }
+            "status" => {
+                let (clients, games, anti_cheat) = self.status();
+                info!(
+                    clients,
+                    games,
+                    dropped_inputs = anti_cheat.dropped_inputs,
+                    clamped_moves = anti_cheat.clamped_moves,
+                    kicks = anti_cheat.kicks,
+                    "Status"
+                );
+            }
+            "dump" => {
+                for (game_code, load) in self.game_loads() {
+                    info!(
+                        game = %game_code.0,
+                        inputs_received = load.inputs_received,
+                        stale_inputs = load.stale_inputs,
+                        bots_simulated = load.bots_simulated,
+                        bytes_sent = load.bytes_sent,
+                        culled_projectiles = load.culled_projectiles,
+                        "Game load over the last second"
+                    );
+                }
+            }
+            other => warn!(
+                command = %other,
+                "Unknown command (available: reload, status, dump)"
+            ),
+        }
+    }
+
+    /// Connected clients, running games and anti-cheat totals.
+    pub fn status(&self) -> (usize, usize, AntiCheatStats) {
+        self.logic.status()
+    }
+
+    /// Each game's load over the last second.
+    pub fn game_loads(&self) -> Vec<(GameCode, GameLoad)> {
+        self.logic.game_loads()
+    }
+
     pub fn tick(&mut self) -> AppResult<()> {
         let now = Instant::now();
         let dt = now.duration_since(self.last_tick).as_secs_f32();
@@ -62,11 +141,32 @@ impl ServerApp {
         self.process_net_events();
         self.process_client_messages();
 
-        let updates = self.logic.game_manager_mut().tick(dt);
+        if let Some(next_watch) = self.next_watch
+            && now >= next_watch
+        {
+            self.next_watch = Some(now + WATCH_INTERVAL);
+            if self.logic.content_changed_on_disk() {
+                self.logic.reload_content();
+            }
+        }
+
+        for (client_id, message) in self.logic.tick_tournament(Duration::from_secs_f32(dt)) {
+            self.send_message(client_id, message);
+        }
+        for (client_id, message) in self.logic.tick_uploads() {
+            self.send_message(client_id, message);
+        }
+
+        let updates = self.logic.tick_games(dt);
+        for client_id in self.logic.tick_anti_cheat(Duration::from_secs_f32(dt)) {
+            warn!(%client_id, "Kicking client for repeated cheating");
+            self.server.disconnect(client_id);
+        }
 
-        for (recipients, update) in updates {
+        for (recipients, message) in self.logic.pack_updates(updates) {
             // Encode once, send bytes to many.
-            if let Ok(payload) = encode_server_message(&ServerMessage::GameUpdate(update)) {
+            if let Ok(payload) = encode_server_message(&message) {
+                self.logic.record_sent(&recipients, payload.len());
                 for client_id in recipients {
                     self.server
                         .send_message(client_id, RELIABLE_CHANNEL_ID, payload.clone());
@@ -1,20 +1,36 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use common::protocol::{
-    API_VERSION, ApiVersion, ClientMessage, CreateGameResponse, HandshakeResponse,
-    JoinGameResponse, ServerMessage,
+    API_VERSION, ApiVersion, ClientMessage, CreateGameResponse, GameCode, GameUpdate,
+    HandshakeResponse, JoinGameResponse, ServerError, ServerMessage, SessionToken,
+    TournamentJoinResponse,
 };
 use renet::ClientId;
-use tracing::debug;
+use tracing::{debug, info};
 
+use crate::anti_cheat::AntiCheatStats;
+use crate::baselines::Baselines;
 use crate::client::{Client, ClientState};
 use crate::game_manager::GameManager;
+use crate::load::GameLoad;
+use crate::model_library::ReloadReport;
+use crate::model_transfer::ModelUploads;
+use crate::tournament::{Tournament, TournamentConfig};
+use common::ai::BotDifficulty;
+use common::game::balance::balance;
+use common::game::player::TankColor;
 
 pub const MAX_CLIENTS: usize = 64;
 
 pub struct ServerLogic {
     clients: HashMap<ClientId, Client>,
     game_manager: GameManager,
+    tournament: Option<Tournament>,
+    uploads: ModelUploads,
+    anti_cheat: AntiCheatStats,
+    /// Snapshots sent, to send the next ones as changes
+    baselines: Baselines,
 }
 
 impl ServerLogic {
@@ -22,9 +38,111 @@ impl ServerLogic {
         Self {
             clients: HashMap::new(),
             game_manager: GameManager::new(),
+            tournament: None,
+            uploads: ModelUploads::default(),
+            anti_cheat: AntiCheatStats::default(),
+            baselines: Baselines::default(),
         }
     }
 
+    pub fn with_tournament(config: TournamentConfig) -> Self {
+        Self {
+            tournament: Some(Tournament::new(config)),
+            ..Self::new()
+        }
+    }
+
+    /// Advances the tournament, if any. Returns the messages for its entrants.
+    pub fn tick_tournament(&mut self, dt: Duration) -> Vec<(ClientId, ServerMessage)> {
+        match &mut self.tournament {
+            Some(tournament) => tournament.tick(dt, &mut self.clients, &mut self.game_manager),
+            None => Vec::new(),
+        }
+    }
+
+    /// Advances every game by `dt`. Returns the updates to send, and puts the players of
+    /// games that closed back into the lobby.
+    pub fn tick_games(&mut self, dt: f32) -> Vec<(Vec<ClientId>, GameUpdate)> {
+        let updates = self.game_manager.tick(dt);
+        for (game_code, client_id) in self.game_manager.take_closed() {
+            if let Some(client) = self.clients.get_mut(&client_id)
+                && matches!(&client.state, ClientState::InGame { game_code: code, .. } if *code == game_code)
+            {
+                client.state = ClientState::Lobby;
+            }
+        }
+        updates
+    }
+
+    /// Counts the violations of the last tick. Returns the clients that kept violating for
+    /// too long, to be kicked.
+    pub fn tick_anti_cheat(&mut self, dt: Duration) -> Vec<ClientId> {
+        for client_id in self.game_manager.take_clamped_moves() {
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                client.guard.flag_move();
+            }
+            self.anti_cheat.clamped_moves += 1;
+        }
+        let kicked: Vec<ClientId> = self
+            .clients
+            .iter_mut()
+            .filter_map(|(&client_id, client)| client.guard.tick(dt).then_some(client_id))
+            .collect();
+        self.anti_cheat.kicks += kicked.len() as u64;
+        kicked
+    }
+
+    /// Connected clients, running games and violations so far.
+    pub fn status(&self) -> (usize, usize, AntiCheatStats) {
+        (
+            self.clients.len(),
+            self.game_manager.games.len(),
+            self.anti_cheat,
+        )
+    }
+
+    /// Each game's load over the last second.
+    pub fn game_loads(&self) -> Vec<(GameCode, GameLoad)> {
+        self.game_manager.loads()
+    }
+
+    /// The messages carrying `updates`, with snapshots given as changes from ones the
+    /// recipients have where possible.
+    pub fn pack_updates(
+        &mut self,
+        updates: Vec<(Vec<ClientId>, GameUpdate)>,
+    ) -> Vec<(Vec<ClientId>, ServerMessage)> {
+        self.baselines.pack(updates)
+    }
+
+    /// Counts an update of `bytes` sent to `recipients`, who all play one game, against
+    /// that game.
+    pub fn record_sent(&mut self, recipients: &[ClientId], bytes: usize) {
+        let Some(ClientState::InGame { game_code, .. }) = recipients
+            .first()
+            .and_then(|client_id| self.client_state(*client_id))
+        else {
+            return;
+        };
+        if let Some(game) = self.game_manager.games.get_mut(&game_code) {
+            game.load.current.bytes_sent += (bytes * recipients.len()) as u64;
+        }
+    }
+
+    /// Rereads the model files. Running games keep the models they started with.
+    pub fn reload_content(&mut self) -> ReloadReport {
+        self.game_manager.models.reload()
+    }
+
+    pub fn content_changed_on_disk(&self) -> bool {
+        self.game_manager.models.changed_on_disk()
+    }
+
+    /// The model chunks to send this tick.
+    pub fn tick_uploads(&mut self) -> Vec<(ClientId, ServerMessage)> {
+        self.uploads.tick()
+    }
+
     #[cfg(test)]
     #[allow(dead_code)]
     pub fn clients(&self) -> &HashMap<ClientId, Client> {
@@ -50,12 +168,18 @@ impl ServerLogic {
     }
 
     pub fn on_disconnect(&mut self, client_id: ClientId) {
-        // If the client was in a game, remove them from the game.
-        if let Some(ClientState::InGame { game_code, .. }) = self.client_state(client_id)
-            && let Err(e) = self.game_manager.remove_player(&game_code, client_id)
+        // If the client was in a game, remove them from the game; in a match they may
+        // come back to it
+        if let Some(client) = self.clients.get(&client_id)
+            && let ClientState::InGame { game_code, .. } = &client.state
+            && let Err(e) =
+                self.game_manager
+                    .disconnect_player(game_code, client_id, client.session)
         {
             debug!(%client_id, %e, "Failed to remove player from game");
         }
+        self.uploads.cancel(client_id);
+        self.baselines.forget(client_id);
         self.remove_client(client_id);
     }
 
@@ -63,38 +187,103 @@ impl ServerLogic {
         &mut self,
         client_id: ClientId,
         message: ClientMessage,
-    ) -> Result<Option<ServerMessage>, String> {
+    ) -> Result<Option<ServerMessage>, ServerError> {
         // Handle handshake
         if let ClientMessage::Handshake {
             api_version,
             nickname,
+            color,
+            build,
+            balance_hash,
+            session,
         } = message
         {
-            let response = self.handle_handshake(client_id, api_version, nickname)?;
+            if build != common::version() {
+                info!(%client_id, %build, "Client runs a different build");
+            }
+            if balance_hash != balance().content_hash() {
+                debug!(%client_id, "Handshake failed: balance table mismatch");
+                return Ok(Some(ServerMessage::HandshakeResponse(
+                    HandshakeResponse::BalanceMismatch,
+                )));
+            }
+            let response =
+                self.handle_handshake(client_id, api_version, nickname, color, session)?;
             return Ok(Some(ServerMessage::HandshakeResponse(response)));
         }
 
         // Handle other messages
-        let client = self.clients.get_mut(&client_id).ok_or("Unknown sender")?;
+        let client = self
+            .clients
+            .get_mut(&client_id)
+            .ok_or(ServerError::UnknownSender)?;
 
         let (response, new_state) = match (&client.state, message) {
             // Clients may still send a final input packet while transitioning out of a game (e.g.
             // right after LeaveGame). In lobby, ignore inputs instead of erroring.
-            (ClientState::Lobby, ClientMessage::GameInput(_)) => (None, None),
+            (ClientState::Lobby, ClientMessage::GameInput { .. }) => (None, None),
 
-            (ClientState::Lobby, ClientMessage::CreateGame { map, rounds }) => {
-                let response = self.game_manager.create_game(
+            (
+                ClientState::Lobby,
+                ClientMessage::CreateGame {
+                    map,
+                    rounds,
+                    mode,
+                    team_size,
+                    intermission,
+                    firing,
+                    bot_difficulty,
+                    bot_count,
+                    bot_model,
+                },
+            ) => {
+                // The model is taken from the library now, so a reload can't change it
+                // mid-game
+                let model = match bot_model {
+                    Some(name) if bot_difficulty != BotDifficulty::TrainedKiller => {
+                        return Err(ServerError::ModelNeedsTrainedBots(name));
+                    }
+                    Some(name) => {
+                        let shared = self
+                            .game_manager
+                            .models
+                            .get(&name)
+                            .map_err(|_| ServerError::UnknownModel(name.clone()))?;
+                        let brain = shared
+                            .brain()
+                            .map_err(|_| ServerError::BrokenModel(name.clone()))?;
+                        Some((Box::new(brain), shared))
+                    }
+                    None => None,
+                };
+                let mut response = self.game_manager.create_game(
                     client_id,
                     client.nickname.clone(),
                     map,
                     rounds,
+                    mode,
+                    team_size,
                 )?;
 
-                let new_state = match &response {
-                    CreateGameResponse::Ok(info) => Some(ClientState::InGame {
-                        game_code: info.game_code.clone(),
-                        player_id: info.player_id,
-                    }),
+                let new_state = match &mut response {
+                    CreateGameResponse::Ok(info) => {
+                        if let Some(game) = self.game_manager.games.get_mut(&info.game_code) {
+                            if intermission {
+                                game.enable_intermission();
+                            }
+                            game.set_firing(firing);
+                            game.set_bots(bot_difficulty, bot_count.map(usize::from));
+                            if let Some((brain, shared)) = model {
+                                // Offered to the host like to everyone joining later
+                                info.bot_model = Some(shared.info.clone());
+                                game.set_model(None, brain, Some(shared));
+                            }
+                        }
+                        Some(ClientState::InGame {
+                            game_code: info.game_code.clone(),
+                            player_id: info.player_id,
+                        })
+                    }
                     _ => None,
                 };
 
@@ -117,6 +306,27 @@ impl ServerLogic {
                 (Some(ServerMessage::JoinGameResponse(response)), new_state)
             }
 
+            (ClientState::Lobby, ClientMessage::ListModels) => (
+                Some(ServerMessage::ModelList(self.game_manager.models.names())),
+                None,
+            ),
+
+            (ClientState::Lobby, ClientMessage::JoinTournament) => {
+                let response = match &mut self.tournament {
+                    Some(tournament) => tournament.join(client_id, client.nickname.clone()),
+                    None => TournamentJoinResponse::NoTournament,
+                };
+                (Some(ServerMessage::TournamentJoinResponse(response)), None)
+            }
+
+            (ClientState::Lobby, ClientMessage::LeaveTournament) => {
+                self.tournament
+                    .as_mut()
+                    .ok_or(ServerError::NoTournament)?
+                    .leave(client_id)?;
+                (Some(ServerMessage::TournamentLeaveAck), None)
+            }
+
             (ClientState::InGame { game_code, .. }, msg) => match msg {
                 ClientMessage::LeaveGame => {
                     self.game_manager.leave_game(game_code, client_id)?;
@@ -126,17 +336,62 @@ impl ServerLogic {
                     self.game_manager.start_countdown(game_code, client_id)?;
                     (Some(ServerMessage::StartCountdownAck), None)
                 }
-                ClientMessage::GameInput(input) => {
+                ClientMessage::GameInput {
+                    seq,
+                    input,
+                    snapshot,
+                } => {
+                    self.baselines.acknowledge(client_id, snapshot);
+                    if client.guard.allow_input(&input) {
+                        self.game_manager
+                            .submit_input(game_code, client_id, seq, input)?;
+                    } else {
+                        self.anti_cheat.dropped_inputs += 1;
+                    }
+                    (None, None)
+                }
+                ClientMessage::BuyUpgrade(upgrade) => {
                     self.game_manager
-                        .submit_input(game_code, client_id, input)?;
+                        .game_mut(game_code)?
+                        .handle_upgrade(client_id, upgrade);
                     (None, None)
                 }
-                _ => return Err("Invalid message in current state".to_string()),
+                ClientMessage::Ping { kind, world_pos } => {
+                    self.game_manager
+                        .game_mut(game_code)?
+                        .handle_ping(client_id, kind, world_pos);
+                    (None, None)
+                }
+                ClientMessage::RequestModel(hash) => {
+                    let model = self
+                        .game_manager
+                        .games
+                        .get(game_code)
+                        .and_then(|game| game.shared_model())
+                        .filter(|model| model.info.hash == hash);
+                    match model {
+                        Some(model) => {
+                            debug!(%client_id, name = %model.info.name, "Uploading model");
+                            self.uploads.start(client_id, model.clone());
+                            (None, None)
+                        }
+                        None => (Some(ServerMessage::ModelUnavailable(hash)), None),
+                    }
+                }
+                _ => return Err(ServerError::InvalidMessage),
             },
-            (_, _) => return Err("Invalid message in current state".to_string()),
+            (_, _) => return Err(ServerError::InvalidMessage),
         };
 
         if let Some(s) = new_state {
+            if let ClientState::InGame {
+                game_code,
+                player_id,
+            } = &s
+            {
+                self.game_manager
+                    .set_color(game_code, *player_id, client.color);
+            }
             client.state = s;
         }
 
@@ -148,14 +403,16 @@ impl ServerLogic {
         client_id: ClientId,
         api_version: ApiVersion,
         nickname: String,
-    ) -> Result<HandshakeResponse, String> {
+        color: Option<TankColor>,
+        session: Option<SessionToken>,
+    ) -> Result<HandshakeResponse, ServerError> {
         if api_version != API_VERSION {
             debug!(%client_id, ?api_version, "Handshake failed: API version mismatch");
             return Ok(HandshakeResponse::ApiMismatch);
         }
 
         if self.clients.contains_key(&client_id) {
-            return Err("Client already connected".to_string());
+            return Err(ServerError::AlreadyConnected);
         }
 
         if self.clients.len() >= MAX_CLIENTS {
@@ -163,24 +420,61 @@ impl ServerLogic {
             return Ok(HandshakeResponse::ServerFull);
         }
 
+        // The server may not have noticed yet that the dropped connection is gone
+        if let Some(session) = session
+            && let Some(stale) = self
+                .clients
+                .iter()
+                .find(|(_, client)| client.session == session)
+                .map(|(&id, _)| id)
+        {
+            self.on_disconnect(stale);
+        }
+
+        // A client back within the grace period takes its tank back and keeps its token
+        let resumed = session.and_then(|session| {
+            let info = self.game_manager.rejoin(client_id, session)?;
+            Some((session, info))
+        });
+        let (session, state) = match &resumed {
+            Some((session, info)) => {
+                self.game_manager
+                    .set_color(&info.game_code, info.player_id, color);
+                let state = ClientState::InGame {
+                    game_code: info.game_code.clone(),
+                    player_id: info.player_id,
+                };
+                (*session, state)
+            }
+            None => (SessionToken(rand::random()), ClientState::Lobby),
+        };
         self.clients.insert(
             client_id,
             Client {
                 nickname,
-                state: ClientState::Lobby,
+                color,
+                state,
+                session,
+                ..Client::default()
             },
         );
 
-        Ok(HandshakeResponse::Ok)
+        Ok(HandshakeResponse::Ok {
+            build: common::version().into(),
+            session,
+            resumed: resumed.map(|(_, info)| info),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model_transfer::SharedModel;
+    use common::protocol::Team;
     use common::protocol::{
-        ClientMessage, CreateGameResponse, GameCode, HandshakeResponse, JoinGameResponse, MapName,
-        PlayerId, ServerMessage,
+        ClientMessage, CreateGameResponse, FiringMode, GameCode, GameMode, GameState,
+        HandshakeResponse, JoinGameResponse, MapName, PlayerId, ServerMessage, SessionToken,
     };
     use glam::Vec2;
 
@@ -191,6 +485,10 @@ mod tests {
                 ClientMessage::Handshake {
                     api_version: API_VERSION,
                     nickname: nickname.to_string(),
+                    color: None,
+                    build: common::version().into(),
+                    balance_hash: balance().content_hash(),
+                    session: None,
                 },
             )
             .unwrap()
@@ -204,6 +502,13 @@ mod tests {
                 ClientMessage::CreateGame {
                     map: MapName::Basic,
                     rounds: 3,
+                    mode: GameMode::TeamDeathmatch,
+                    team_size: 4,
+                    intermission: false,
+                    firing: FiringMode::Cooldown,
+                    bot_difficulty: BotDifficulty::Hunter,
+                    bot_count: None,
+                    bot_model: None,
                 },
             )
             .unwrap()
@@ -237,7 +542,7 @@ mod tests {
         let resp = handshake(&mut logic, client_id, "marcin");
         assert!(matches!(
             resp,
-            ServerMessage::HandshakeResponse(HandshakeResponse::Ok)
+            ServerMessage::HandshakeResponse(HandshakeResponse::Ok { .. })
         ));
         assert!(matches!(
             logic.client_state(client_id),
@@ -245,6 +550,32 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn picked_color_is_worn_in_games() {
+        let mut logic = ServerLogic::new();
+        let client_id: ClientId = 1;
+        let cyan = TankColor::new(0, 255, 255);
+        logic
+            .handle_message(
+                client_id,
+                ClientMessage::Handshake {
+                    api_version: API_VERSION,
+                    nickname: "marcin".to_string(),
+                    color: Some(cyan),
+                    build: common::version().into(),
+                    balance_hash: balance().content_hash(),
+                    session: None,
+                },
+            )
+            .unwrap();
+        let (game_code, player_id) = create_game(&mut logic, client_id);
+
+        let snapshot = logic.game_manager.games[&game_code].snapshot();
+        let info = &snapshot.engine.tanks[0].player_info;
+        assert_eq!(info.id, player_id);
+        assert_eq!(info.color, Some(cyan.for_team(info.team)));
+    }
+
     #[test]
     fn handshake_wrong_api_version_rejected() {
         let mut logic = ServerLogic::new();
@@ -256,6 +587,10 @@ mod tests {
                 ClientMessage::Handshake {
                     api_version: API_VERSION + 1,
                     nickname: "marcin".to_string(),
+                    color: None,
+                    build: common::version().into(),
+                    balance_hash: balance().content_hash(),
+                    session: None,
                 },
             )
             .unwrap()
@@ -268,6 +603,33 @@ mod tests {
         assert!(logic.client_state(client_id).is_none());
     }
 
+    #[test]
+    fn handshake_other_balance_rejected() {
+        let mut logic = ServerLogic::new();
+        let client_id: ClientId = 1;
+
+        let resp = logic
+            .handle_message(
+                client_id,
+                ClientMessage::Handshake {
+                    api_version: API_VERSION,
+                    nickname: "marcin".to_string(),
+                    color: None,
+                    build: common::version().into(),
+                    balance_hash: balance().content_hash() ^ 1,
+                    session: None,
+                },
+            )
+            .unwrap()
+            .expect("handshake should return a response");
+
+        assert!(matches!(
+            resp,
+            ServerMessage::HandshakeResponse(HandshakeResponse::BalanceMismatch)
+        ));
+        assert!(logic.client_state(client_id).is_none());
+    }
+
     #[test]
     fn handshake_duplicate_client_rejected() {
         let mut logic = ServerLogic::new();
@@ -280,10 +642,14 @@ mod tests {
                 ClientMessage::Handshake {
                     api_version: API_VERSION,
                     nickname: "marcin2".to_string(),
+                    color: None,
+                    build: common::version().into(),
+                    balance_hash: balance().content_hash(),
+                    session: None,
                 },
             )
             .unwrap_err();
-        assert!(err.contains("Client already connected"));
+        assert_eq!(err, ServerError::AlreadyConnected);
     }
 
     #[test]
@@ -326,7 +692,7 @@ mod tests {
         let err = logic
             .handle_message(client_id, ClientMessage::StartCountdown)
             .unwrap_err();
-        assert!(err.contains("Invalid message"));
+        assert_eq!(err, ServerError::InvalidMessage);
     }
 
     #[test]
@@ -345,7 +711,7 @@ mod tests {
         let err = logic
             .handle_message(joiner_id, ClientMessage::StartCountdown)
             .unwrap_err();
-        assert!(err.contains("Only the game master"));
+        assert_eq!(err, ServerError::NotGameMaster);
 
         // Master should be accepted.
         let resp = logic
@@ -356,7 +722,72 @@ mod tests {
     }
 
     #[test]
-    fn join_game_rejected_when_game_not_in_lobby_state() {
+    fn dropped_players_take_their_tank_back_with_their_session() {
+        let mut logic = ServerLogic::new();
+        let _ = handshake(&mut logic, 1, "host");
+        let ServerMessage::HandshakeResponse(HandshakeResponse::Ok { session, .. }) =
+            handshake(&mut logic, 2, "joiner")
+        else {
+            panic!("handshake failed");
+        };
+        let (game_code, _) = create_game(&mut logic, 1);
+        let joiner = join_game(&mut logic, 2, game_code.clone());
+        logic
+            .handle_message(1, ClientMessage::StartCountdown)
+            .unwrap();
+        logic.tick_games(6.0);
+
+        logic.on_disconnect(2);
+        let game = &logic.game_manager().games[&game_code];
+        assert!(!game.client_ids().contains(&2));
+
+        // Someone else's token gets them nothing
+        let reconnect = |logic: &mut ServerLogic, client_id, session| {
+            logic
+                .handle_message(
+                    client_id,
+                    ClientMessage::Handshake {
+                        api_version: API_VERSION,
+                        nickname: "joiner".to_string(),
+                        color: None,
+                        build: common::version().into(),
+                        balance_hash: balance().content_hash(),
+                        session: Some(session),
+                    },
+                )
+                .unwrap()
+        };
+        let Some(ServerMessage::HandshakeResponse(HandshakeResponse::Ok { resumed, .. })) =
+            reconnect(&mut logic, 3, SessionToken(session.0.wrapping_add(1)))
+        else {
+            panic!("handshake failed");
+        };
+        assert!(resumed.is_none());
+
+        let Some(ServerMessage::HandshakeResponse(HandshakeResponse::Ok {
+            resumed: Some(info),
+            session: kept,
+            ..
+        })) = reconnect(&mut logic, 4, session)
+        else {
+            panic!("the joiner wasn't let back in");
+        };
+        assert_eq!(kept, session);
+        assert_eq!(info.player_id, joiner);
+        assert!(matches!(info.state, GameState::Battle(_)));
+        assert!(matches!(
+            logic.client_state(4),
+            Some(ClientState::InGame { player_id, .. }) if player_id == joiner
+        ));
+        assert!(
+            logic.game_manager().games[&game_code]
+                .client_ids()
+                .contains(&4)
+        );
+    }
+
+    #[test]
+    fn join_game_rejected_once_the_battle_started() {
         let mut logic = ServerLogic::new();
         let host_id: ClientId = 1;
         let joiner_id: ClientId = 2;
@@ -367,13 +798,30 @@ mod tests {
         let _ = handshake(&mut logic, late_id, "late");
 
         let (game_code, _host_player_id) = create_game(&mut logic, host_id);
-        let _ = join_game(&mut logic, joiner_id, game_code.clone());
 
         // Start countdown (transition out of lobby).
         let _ = logic
             .handle_message(host_id, ClientMessage::StartCountdown)
             .unwrap();
 
+        // Joining during the countdown still gets a tank, and is told of the countdown
+        let resp = logic
+            .handle_message(
+                joiner_id,
+                ClientMessage::JoinGame {
+                    game_code: game_code.clone(),
+                },
+            )
+            .unwrap()
+            .expect("join_game returns a response");
+        let ServerMessage::JoinGameResponse(JoinGameResponse::Ok(info)) = resp else {
+            panic!("joining during the countdown failed: {:?}", resp);
+        };
+        assert!(matches!(info.state, GameState::Countdown(_)));
+        assert_eq!(info.round, 1);
+
+        logic.tick_games(6.0);
+
         // Late join should be rejected.
         let resp = logic
             .handle_message(late_id, ClientMessage::JoinGame { game_code })
@@ -398,14 +846,39 @@ mod tests {
             move_axis: Vec2::ZERO,
             aim_pos: Vec2::ZERO,
             shoot: true,
+            grenade: false,
         };
 
         let resp = logic
-            .handle_message(host_id, ClientMessage::GameInput(input))
+            .handle_message(
+                host_id,
+                ClientMessage::GameInput {
+                    seq: 0,
+                    input,
+                    snapshot: None,
+                },
+            )
             .unwrap();
         assert!(resp.is_none());
     }
 
+    #[test]
+    fn sent_updates_count_against_their_game() {
+        let mut logic = ServerLogic::new();
+        let _ = handshake(&mut logic, 1, "host");
+        let _ = handshake(&mut logic, 2, "guest");
+        let (game_code, _) = create_game(&mut logic, 1);
+        let _ = join_game(&mut logic, 2, game_code.clone());
+
+        logic.record_sent(&[1, 2], 100);
+        // Lobby clients have no game to count against
+        logic.record_sent(&[3], 100);
+        assert_eq!(
+            logic.game_manager.games[&game_code].load.current.bytes_sent,
+            200
+        );
+    }
+
     #[test]
     fn game_input_in_lobby_is_ignored() {
         let mut logic = ServerLogic::new();
@@ -417,10 +890,18 @@ mod tests {
             move_axis: Vec2::ZERO,
             aim_pos: Vec2::ZERO,
             shoot: false,
+            grenade: false,
         };
 
         let resp = logic
-            .handle_message(client_id, ClientMessage::GameInput(input))
+            .handle_message(
+                client_id,
+                ClientMessage::GameInput {
+                    seq: 0,
+                    input,
+                    snapshot: None,
+                },
+            )
             .unwrap();
         assert!(resp.is_none());
         assert!(matches!(
@@ -452,6 +933,88 @@ mod tests {
         assert_eq!(logic.game_manager_mut().games.len(), 0);
     }
 
+    #[test]
+    fn join_tournament_without_tournament_is_refused() {
+        let mut logic = ServerLogic::new();
+        let _ = handshake(&mut logic, 1, "p1");
+
+        let resp = logic
+            .handle_message(1, ClientMessage::JoinTournament)
+            .unwrap();
+        assert!(matches!(
+            resp,
+            Some(ServerMessage::TournamentJoinResponse(
+                TournamentJoinResponse::NoTournament
+            ))
+        ));
+        assert!(
+            logic
+                .handle_message(1, ClientMessage::LeaveTournament)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn model_is_uploaded_only_to_its_game() {
+        let mut logic = ServerLogic::new();
+        let _ = handshake(&mut logic, 1, "p1");
+        let (game_code, _) = create_game(&mut logic, 1);
+
+        let model = SharedModel::new("champion".into(), vec![1, 2, 3]).unwrap();
+        let hash = model.info.hash;
+        let resp = logic.handle_message(1, ClientMessage::RequestModel(hash));
+        assert_eq!(resp, Ok(Some(ServerMessage::ModelUnavailable(hash))));
+
+        let brain = Box::new(common::rl::BotBrain::new(&Default::default()));
+        let game = logic.game_manager.games.get_mut(&game_code).unwrap();
+        game.set_model(Some(Team::Red), brain, Some(model));
+        let resp = logic.handle_message(1, ClientMessage::RequestModel(hash));
+        assert_eq!(resp, Ok(None));
+
+        let messages = logic.tick_uploads();
+        assert!(matches!(
+            messages.as_slice(),
+            [(1, ServerMessage::ModelChunk(chunk))] if chunk.data == [1, 2, 3]
+        ));
+        assert!(logic.tick_uploads().is_empty());
+    }
+
+    #[test]
+    fn games_only_take_models_from_the_library_for_trained_bots() {
+        let mut logic = ServerLogic::new();
+        let _ = handshake(&mut logic, 1, "host");
+        let resp = logic.handle_message(1, ClientMessage::ListModels).unwrap();
+        assert_eq!(
+            resp,
+            Some(ServerMessage::ModelList(logic.game_manager.models.names()))
+        );
+
+        let create = |difficulty, model: &str| ClientMessage::CreateGame {
+            map: MapName::Basic,
+            rounds: 3,
+            mode: GameMode::TeamDeathmatch,
+            team_size: 4,
+            intermission: false,
+            firing: FiringMode::Cooldown,
+            bot_difficulty: difficulty,
+            bot_count: Some(2),
+            bot_model: Some(model.to_string()),
+        };
+        let err = logic
+            .handle_message(1, create(BotDifficulty::Hunter, "champion"))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ServerError::ModelNeedsTrainedBots("champion".to_string())
+        );
+        let err = logic
+            .handle_message(1, create(BotDifficulty::TrainedKiller, "no such model"))
+            .unwrap_err();
+        assert_eq!(err, ServerError::UnknownModel("no such model".to_string()));
+        assert!(logic.game_manager.games.is_empty());
+        assert!(matches!(logic.client_state(1), Some(ClientState::Lobby)));
+    }
+
     #[test]
     fn non_handshake_from_unknown_sender_is_error() {
         let mut logic = ServerLogic::new();
@@ -461,10 +1024,17 @@ mod tests {
                 ClientMessage::CreateGame {
                     map: MapName::Basic,
                     rounds: 3,
+                    mode: GameMode::TeamDeathmatch,
+                    team_size: 4,
+                    intermission: false,
+                    firing: FiringMode::Cooldown,
+                    bot_difficulty: BotDifficulty::Hunter,
+                    bot_count: None,
+                    bot_model: None,
                 },
             )
             .unwrap_err();
-        assert!(err.contains("Unknown sender"));
+        assert_eq!(err, ServerError::UnknownSender);
     }
 
     #[test]
@@ -524,7 +1094,7 @@ mod tests {
         let err = logic
             .handle_message(other_id, ClientMessage::LeaveGame)
             .unwrap_err();
-        assert!(err.contains("Player not found"));
+        assert_eq!(err, ServerError::NotInGame);
         assert!(matches!(
             logic.client_state(other_id),
             Some(ClientState::InGame { .. })
@@ -547,6 +1117,20 @@ mod tests {
                 },
             )
             .unwrap_err();
-        assert!(err.contains("Invalid message"));
+        assert_eq!(err, ServerError::InvalidMessage);
+    }
+
+    #[test]
+    fn players_of_a_closed_game_are_back_in_the_lobby() {
+        let mut logic = ServerLogic::new();
+        logic.game_manager_mut().lobby.idle_timeout = Duration::from_secs(1);
+        let _ = handshake(&mut logic, 1, "host");
+        let (game_code, _) = create_game(&mut logic, 1);
+
+        logic.tick_games(1.0);
+        assert!(!logic.game_manager().games.contains_key(&game_code));
+        assert!(matches!(logic.client_state(1), Some(ClientState::Lobby)));
+        // Free to make another one
+        create_game(&mut logic, 1);
     }
 }
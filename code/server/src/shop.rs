@@ -0,0 +1,85 @@
+//! Intermissions between rounds, in which players spend the points they earned in battle
+//! on upgrades for their tanks.
+
+use common::game::stats::MatchStats;
+use common::game::upgrades::KILL_POINTS;
+use common::protocol::{KillEvent, Loadout, PlayerId, Upgrade};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How long players have to shop before the next round's countdown.
+pub(crate) const INTERMISSION_DURATION: Duration = Duration::from_secs(15);
+
+#[derive(Default)]
+pub(crate) struct Shop {
+    /// Enemies each player killed over the match
+    kills: BTreeMap<PlayerId, u32>,
+    /// Points each player spent so far
+    spent: BTreeMap<PlayerId, u32>,
+}
+
+impl Shop {
+    pub fn record_kill(&mut self, kill: &KillEvent) {
+        if kill.killer_info.team != kill.victim_info.team {
+            *self.kills.entry(kill.killer_info.id).or_default() += 1;
+        }
+    }
+
+    /// Points `player` has left: what their damage to enemies and kills earned, less what
+    /// they spent.
+    pub fn points(&self, player: PlayerId, stats: &MatchStats) -> u32 {
+        let kills = self.kills.get(&player).copied().unwrap_or(0);
+        let earned = stats.damage_dealt(player) as u32 + KILL_POINTS * kills;
+        earned.saturating_sub(self.spent.get(&player).copied().unwrap_or(0))
+    }
+
+    /// Buys the next level of `upgrade` for `player`, whose upgrades so far are `loadout`.
+    /// Returns whether they could afford it.
+    pub fn buy(
+        &mut self,
+        player: PlayerId,
+        upgrade: Upgrade,
+        loadout: &mut Loadout,
+        stats: &MatchStats,
+    ) -> bool {
+        let points = self.points(player, stats);
+        let mut left = points;
+        if !loadout.buy(upgrade, &mut left) {
+            return false;
+        }
+        *self.spent.entry(player).or_default() += points - left;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::protocol::Team;
+
+    fn kill(killer: PlayerId, victim: PlayerId, victim_team: Team) -> KillEvent {
+        KillEvent {
+            killer_info: PlayerInfo::new(killer, format!("P{}", killer), Team::Blue),
+            victim_info: PlayerInfo::new(victim, format!("P{}", victim), victim_team),
+        }
+    }
+
+    #[test]
+    fn test_kills_earn_points_to_spend() {
+        let stats = MatchStats::default();
+        let mut shop = Shop::default();
+        shop.record_kill(&kill(1, 2, Team::Red));
+        shop.record_kill(&kill(1, 3, Team::Red));
+        // Teamkills earn nothing
+        shop.record_kill(&kill(1, 4, Team::Blue));
+        assert_eq!(shop.points(1, &stats), 2 * KILL_POINTS);
+
+        let mut loadout = Loadout::default();
+        assert!(shop.buy(1, Upgrade::Speed, &mut loadout, &stats));
+        assert_eq!(loadout.speed, 1);
+        assert_eq!(shop.points(1, &stats), 0);
+        assert!(!shop.buy(1, Upgrade::Speed, &mut loadout, &stats));
+        assert_eq!(shop.points(2, &stats), 0);
+    }
+}
@@ -0,0 +1,138 @@
+//! What of the battle each client is sent. With fog of war, a player must not learn more
+//! than their team can see, or dead players and spectators could call out enemies to
+//! those still fighting.
+
+use common::game::wall_between;
+use common::protocol::{EngineSnapshot, MapDefinition, Team};
+
+/// How much of the enemy a recipient of the game updates sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sight {
+    /// Every tank and projectile
+    Everything,
+    /// Their team, and the enemies and shots a living teammate has in line of sight
+    Team,
+    /// Their team only
+    Allies,
+}
+
+/// Sight of each kind of recipient, part of the rules of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SightRules {
+    /// Players with a tank in the round
+    pub alive: Sight,
+    /// Players whose tank is down while their team fights on
+    pub dead: Sight,
+    /// Clients without a team
+    pub spectators: Sight,
+}
+
+impl SightRules {
+    pub const OPEN: Self = Self {
+        alive: Sight::Everything,
+        dead: Sight::Everything,
+        spectators: Sight::Everything,
+    };
+
+    /// The fallen watch through their teammates' eyes, which tells them nothing to relay.
+    pub const FOG_OF_WAR: Self = Self {
+        alive: Sight::Team,
+        dead: Sight::Team,
+        spectators: Sight::Allies,
+    };
+}
+
+impl Default for SightRules {
+    fn default() -> Self {
+        Self::OPEN
+    }
+}
+
+/// Drops from `engine` what a member of `team` with `sight` doesn't see.
+pub fn restrict(
+    engine: &mut EngineSnapshot,
+    team: Option<Team>,
+    sight: Sight,
+    map: &MapDefinition,
+) {
+    if sight == Sight::Everything {
+        return;
+    }
+    let eyes: Vec<_> = engine
+        .tanks
+        .iter()
+        .filter(|t| Some(t.player_info.team) == team)
+        .map(|t| t.position)
+        .collect();
+    let seen = |own: bool, position| {
+        own || (sight == Sight::Team && eyes.iter().any(|eye| !wall_between(*eye, position, map)))
+    };
+    engine
+        .tanks
+        .retain(|t| seen(Some(t.player_info.team) == team, t.position));
+    engine
+        .projectiles
+        .retain(|p| seen(Some(p.owner_info.team) == team, p.position));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::game::player::PlayerInfo;
+    use common::game::units::DEFAULT_UNITS_PER_METER;
+    use common::protocol::{PlayerId, RectWall, Tank};
+    use glam::Vec2;
+
+    fn snapshot() -> EngineSnapshot {
+        let tank =
+            |id, team, x, y| Tank::new(PlayerInfo::new(id, "t".into(), team), Vec2::new(x, y));
+        EngineSnapshot {
+            tanks: vec![
+                tank(0, Team::Blue, 200.0, 500.0),
+                // In the open
+                tank(1, Team::Red, 300.0, 900.0),
+                // Behind the wall in the middle
+                tank(2, Team::Red, 700.0, 500.0),
+            ],
+            projectiles: Vec::new(),
+            moving_walls: Vec::new(),
+        }
+    }
+
+    fn ids(engine: &EngineSnapshot) -> Vec<PlayerId> {
+        engine.tanks.iter().map(|t| t.player_info.id).collect()
+    }
+
+    #[test]
+    fn test_team_sight_hides_enemies_behind_walls() {
+        let map = MapDefinition {
+            width: 1000.0,
+            height: 1000.0,
+            units_per_meter: DEFAULT_UNITS_PER_METER,
+            walls: vec![RectWall {
+                min: Vec2::new(400.0, 400.0),
+                max: Vec2::new(600.0, 600.0),
+            }],
+            spawn_points: Vec::new(),
+            arenas: Vec::new(),
+            teleporters: Vec::new(),
+            one_way_walls: Vec::new(),
+            moving_walls: Vec::new(),
+        };
+        let mut engine = snapshot();
+        restrict(&mut engine, Some(Team::Blue), Sight::Everything, &map);
+        assert_eq!(ids(&engine), [0, 1, 2]);
+
+        restrict(&mut engine, Some(Team::Blue), Sight::Team, &map);
+        assert_eq!(ids(&engine), [0, 1]);
+
+        let mut engine = snapshot();
+        restrict(&mut engine, Some(Team::Blue), Sight::Allies, &map);
+        assert_eq!(ids(&engine), [0]);
+
+        // Without a living teammate nothing of the enemy is seen
+        let mut engine = snapshot();
+        restrict(&mut engine, None, Sight::Team, &map);
+        assert!(engine.tanks.is_empty());
+    }
+}
@@ -0,0 +1,851 @@
+//! Server-run single-elimination tournaments (`server --tournament <file>`). Players sign up
+//! from the server lobby, bots from the config fill the free slots, and the server creates
+//! every match lobby itself. Matches between two bots are simulated in the background.
+
+use crate::client::{Client, ClientState};
+use crate::exhibition::Summary;
+use crate::game::{self, Game};
+use crate::game_manager::GameManager;
+use crate::model_library::ModelLibrary;
+use crate::model_transfer::SharedModel;
+use common::ai::BotDifficulty;
+use common::game::MAX_TEAM_SIZE;
+use common::game::engine::BotFill;
+use common::protocol::{
+    ClientId, GameCode, GameState, MapDefinition, MapName, ServerError, ServerMessage, Team,
+    TournamentInfo, TournamentJoinResponse, TournamentMatchInfo, TournamentStanding,
+    TournamentState,
+};
+use common::rl::contest::{self, Contestant, ContestantSpec};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long an entrant may stay busy (e.g. still in their previous game) before forfeiting.
+const NO_SHOW_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_CAPACITY: u8 = 64;
+
+/// The config file as written by the operator.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TournamentFile {
+    name: String,
+    team_size: u8,
+    rounds: u8,
+    maps: Vec<String>,
+    capacity: u8,
+    signup_secs: u64,
+    #[serde(default)]
+    bots: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentConfig {
+    pub name: String,
+    /// Tanks per side; players are joined by bots up to this size
+    pub team_size: u8,
+    /// Rounds played in every match
+    pub rounds: u8,
+    /// Bracket round `n` is played on `maps[n % maps.len()]`
+    pub maps: Vec<MapName>,
+    /// Most entrants, players and bots together
+    pub capacity: u8,
+    /// Sign-up closes after this long, or earlier once players fill the bracket
+    pub signup: Duration,
+    /// Fill the slots players leave free, in this order
+    pub bots: Vec<ContestantSpec>,
+}
+
+impl TournamentConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let file: TournamentFile =
+            serde_json::from_str(text).map_err(|e| format!("Invalid tournament config: {}", e))?;
+
+        let maps = file
+            .maps
+            .iter()
+            .map(|map| map.parse())
+            .collect::<Result<Vec<MapName>, _>>()?;
+        let bots = file
+            .bots
+            .iter()
+            .map(|bot| bot.parse())
+            .collect::<Result<Vec<ContestantSpec>, _>>()?;
+
+        if maps.is_empty() {
+            return Err("The map pool is empty".to_string());
+        }
+        if !(1..=MAX_TEAM_SIZE).contains(&usize::from(file.team_size)) {
+            return Err(format!("Team size must be 1 to {}", MAX_TEAM_SIZE));
+        }
+        if file.rounds == 0 {
+            return Err("A match needs at least one round".to_string());
+        }
+        if !(2..=MAX_CAPACITY).contains(&file.capacity) {
+            return Err(format!("Capacity must be 2 to {}", MAX_CAPACITY));
+        }
+
+        Ok(Self {
+            name: file.name,
+            team_size: file.team_size,
+            rounds: file.rounds,
+            maps,
+            capacity: file.capacity,
+            signup: Duration::from_secs(file.signup_secs),
+            bots,
+        })
+    }
+}
+
+enum Entrant {
+    Player {
+        client_id: ClientId,
+        nickname: String,
+        /// Left the tournament; forfeits every remaining match
+        withdrawn: bool,
+    },
+    Bot(ContestantSpec),
+}
+
+impl Entrant {
+    fn name(&self) -> String {
+        match self {
+            Entrant::Player { nickname, .. } => nickname.clone(),
+            Entrant::Bot(spec) => spec.to_string(),
+        }
+    }
+
+    fn client_id(&self) -> Option<ClientId> {
+        match self {
+            Entrant::Player { client_id, .. } => Some(*client_id),
+            Entrant::Bot(_) => None,
+        }
+    }
+}
+
+/// Whether an entrant can start their next match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Availability {
+    Ready,
+    /// Connected, but still in another game
+    Busy,
+    /// Disconnected or withdrawn
+    Gone,
+}
+
+enum MatchState {
+    /// Waiting for an earlier match to decide an entrant
+    Pending,
+    /// Both entrants known, waiting `waited` so far for them to be free
+    Waiting {
+        waited: Duration,
+    },
+    Live {
+        game_code: GameCode,
+        teams: [Team; 2],
+    },
+    /// Two bots, played out on another thread, which sends back the winning side
+    Simulating(Receiver<usize>),
+    Done {
+        winner: usize,
+    },
+}
+
+struct Match {
+    /// 1-based bracket round
+    round: u8,
+    /// Entrant indices; `None` is an entrant still to be decided, or a bye in the first round
+    sides: [Option<usize>; 2],
+    map: MapName,
+    /// Where the winner goes: (match index, side)
+    next: Option<(usize, usize)>,
+    state: MatchState,
+}
+
+enum Phase {
+    SignUp { left: Duration },
+    Running,
+    Finished { champion: usize },
+    Cancelled,
+}
+
+pub struct Tournament {
+    config: TournamentConfig,
+    phase: Phase,
+    entrants: Vec<Entrant>,
+    /// The rounds of the bracket back to back, the final last
+    matches: Vec<Match>,
+    /// Last state sent to the entrants
+    published: Option<TournamentInfo>,
+}
+
+impl Tournament {
+    pub fn new(config: TournamentConfig) -> Self {
+        info!(name = %config.name, "Tournament sign-up open");
+        Self {
+            phase: Phase::SignUp {
+                left: config.signup,
+            },
+            config,
+            entrants: Vec::new(),
+            matches: Vec::new(),
+            published: None,
+        }
+    }
+
+    pub fn join(&mut self, client_id: ClientId, nickname: String) -> TournamentJoinResponse {
+        if self.player_index(client_id).is_some() {
+            return TournamentJoinResponse::Ok(self.info());
+        }
+        if !matches!(self.phase, Phase::SignUp { .. })
+            || self.entrants.len() >= usize::from(self.config.capacity)
+        {
+            return TournamentJoinResponse::Closed;
+        }
+
+        info!(%client_id, %nickname, "Player signed up for the tournament");
+        self.entrants.push(Entrant::Player {
+            client_id,
+            nickname,
+            withdrawn: false,
+        });
+        TournamentJoinResponse::Ok(self.info())
+    }
+
+    pub fn leave(&mut self, client_id: ClientId) -> Result<(), ServerError> {
+        let index = self
+            .player_index(client_id)
+            .ok_or(ServerError::NotInTournament)?;
+
+        if matches!(self.phase, Phase::SignUp { .. }) {
+            self.entrants.remove(index);
+        } else if let Entrant::Player { withdrawn, .. } = &mut self.entrants[index] {
+            *withdrawn = true;
+        }
+        info!(%client_id, "Player left the tournament");
+        Ok(())
+    }
+
+    /// Advances sign-up and every match. Returns the messages to send.
+    pub fn tick(
+        &mut self,
+        dt: Duration,
+        clients: &mut HashMap<ClientId, Client>,
+        games: &mut GameManager,
+    ) -> Vec<(ClientId, ServerMessage)> {
+        let mut messages = Vec::new();
+
+        match &mut self.phase {
+            Phase::SignUp { left } => {
+                *left = left.saturating_sub(dt);
+                let full = self.entrants.len() >= usize::from(self.config.capacity);
+                if left.is_zero() || full {
+                    self.start_bracket(clients);
+                }
+            }
+            Phase::Running => {
+                for index in 0..self.matches.len() {
+                    self.step_match(index, dt, clients, games, &mut messages);
+                }
+            }
+            Phase::Finished { .. } | Phase::Cancelled => {}
+        }
+
+        let info = self.info();
+        if self.published.as_ref() != Some(&info) {
+            for entrant in &self.entrants {
+                if let Entrant::Player {
+                    client_id,
+                    withdrawn: false,
+                    ..
+                } = entrant
+                    && clients.contains_key(client_id)
+                {
+                    messages.push((*client_id, ServerMessage::TournamentUpdate(info.clone())));
+                }
+            }
+            self.published = Some(info);
+        }
+        messages
+    }
+
+    pub fn info(&self) -> TournamentInfo {
+        let state = match &self.phase {
+            Phase::SignUp { left } => TournamentState::SignUp {
+                seconds_left: left.as_secs_f32().ceil() as u64,
+                entrants: self.entrants.len() as u8,
+                capacity: self.config.capacity,
+            },
+            Phase::Running => TournamentState::Running {
+                bracket_round: self
+                    .matches
+                    .iter()
+                    .find(|m| !matches!(m.state, MatchState::Done { .. }))
+                    .map_or(0, |m| m.round),
+            },
+            Phase::Finished { champion } => TournamentState::Finished {
+                champion: self.entrants[*champion].name(),
+            },
+            Phase::Cancelled => TournamentState::Cancelled,
+        };
+
+        let matches = self
+            .matches
+            .iter()
+            .filter_map(|m| {
+                let [Some(a), Some(b)] = m.sides else {
+                    return None;
+                };
+                Some(TournamentMatchInfo {
+                    bracket_round: m.round,
+                    entrants: [self.entrants[a].name(), self.entrants[b].name()],
+                    map: m.map,
+                    winner: match m.state {
+                        MatchState::Done { winner } => Some(winner as u8),
+                        _ => None,
+                    },
+                })
+            })
+            .collect();
+
+        TournamentInfo {
+            name: self.config.name.clone(),
+            team_size: self.config.team_size,
+            rounds: self.config.rounds,
+            state,
+            matches,
+            standings: self.standings(),
+        }
+    }
+
+    /// Entrants by wins, the ones still in the running first.
+    fn standings(&self) -> Vec<TournamentStanding> {
+        let mut standings: Vec<TournamentStanding> = self
+            .entrants
+            .iter()
+            .map(|entrant| TournamentStanding {
+                name: entrant.name(),
+                wins: 0,
+                eliminated: false,
+            })
+            .collect();
+        for m in &self.matches {
+            if let ([Some(a), Some(b)], MatchState::Done { winner }) = (m.sides, &m.state) {
+                let (won, lost) = if *winner == 0 { (a, b) } else { (b, a) };
+                standings[won].wins += 1;
+                standings[lost].eliminated = true;
+            }
+        }
+        standings.sort_by_key(|s| (s.eliminated, std::cmp::Reverse(s.wins)));
+        standings
+    }
+
+    fn player_index(&self, client_id: ClientId) -> Option<usize> {
+        self.entrants
+            .iter()
+            .position(|e| e.client_id() == Some(client_id))
+    }
+
+    fn start_bracket(&mut self, clients: &HashMap<ClientId, Client>) {
+        // Players who disconnected during sign-up are dropped; bots go first as the seeds.
+        let players: Vec<Entrant> = std::mem::take(&mut self.entrants)
+            .into_iter()
+            .filter(|e| e.client_id().is_some_and(|id| clients.contains_key(&id)))
+            .collect();
+        let free = usize::from(self.config.capacity).saturating_sub(players.len());
+        self.entrants = self
+            .config
+            .bots
+            .iter()
+            .take(free)
+            .cloned()
+            .map(Entrant::Bot)
+            .chain(players)
+            .collect();
+
+        let count = self.entrants.len();
+        if count < 2 {
+            info!("Tournament cancelled: not enough entrants");
+            self.phase = Phase::Cancelled;
+            return;
+        }
+
+        let size = count.next_power_of_two();
+        let rounds = size.trailing_zeros() as usize;
+        // Round `r` (0-based) holds `size >> (r + 1)` matches and starts after all earlier ones.
+        let offset = |round: usize| size - (size >> round);
+        for round in 0..rounds {
+            for position in 0..size >> (round + 1) {
+                let sides = if round == 0 {
+                    let opponent = size - 1 - position;
+                    [Some(position), (opponent < count).then_some(opponent)]
+                } else {
+                    [None, None]
+                };
+                self.matches.push(Match {
+                    round: round as u8 + 1,
+                    sides,
+                    map: self.config.maps[round % self.config.maps.len()],
+                    next: (round + 1 < rounds)
+                        .then(|| (offset(round + 1) + position / 2, position % 2)),
+                    state: MatchState::Pending,
+                });
+            }
+        }
+
+        self.phase = Phase::Running;
+        info!(entrants = count, "Tournament started");
+
+        // The top seeds get byes when the entrants don't fill the bracket.
+        for index in 0..self.matches.len() {
+            if self.matches[index].round == 1 && self.matches[index].sides[1].is_none() {
+                self.decide(index, 0);
+            }
+        }
+    }
+
+    fn availability(&self, entrant: usize, clients: &HashMap<ClientId, Client>) -> Availability {
+        match &self.entrants[entrant] {
+            Entrant::Bot(_) => Availability::Ready,
+            Entrant::Player {
+                withdrawn: true, ..
+            } => Availability::Gone,
+            Entrant::Player { client_id, .. } => match clients.get(client_id) {
+                None => Availability::Gone,
+                Some(Client {
+                    state: ClientState::Lobby,
+                    ..
+                }) => Availability::Ready,
+                Some(_) => Availability::Busy,
+            },
+        }
+    }
+
+    fn step_match(
+        &mut self,
+        index: usize,
+        dt: Duration,
+        clients: &mut HashMap<ClientId, Client>,
+        games: &mut GameManager,
+        messages: &mut Vec<(ClientId, ServerMessage)>,
+    ) {
+        let [Some(a), Some(b)] = self.matches[index].sides else {
+            return;
+        };
+        if matches!(self.matches[index].state, MatchState::Pending) {
+            self.matches[index].state = MatchState::Waiting {
+                waited: Duration::ZERO,
+            };
+        }
+
+        match &mut self.matches[index].state {
+            MatchState::Waiting { waited } => {
+                *waited += dt;
+                let timed_out = *waited >= NO_SHOW_TIMEOUT;
+                let available = [self.availability(a, clients), self.availability(b, clients)];
+                let absent = |side: usize| match available[side] {
+                    Availability::Ready => false,
+                    Availability::Busy => timed_out,
+                    Availability::Gone => true,
+                };
+
+                if absent(0) || absent(1) {
+                    // If both are missing the better seed goes through.
+                    let winner = if absent(0) && !absent(1) { 1 } else { 0 };
+                    info!(match_index = index, "Tournament match forfeited");
+                    self.decide(index, winner);
+                } else if available == [Availability::Ready; 2] {
+                    self.start_match(index, clients, games, messages);
+                }
+            }
+            MatchState::Live { game_code, teams } => {
+                let teams = *teams;
+                let result = games
+                    .games
+                    .get(game_code)
+                    .map(|game| game.game_state_info());
+                if let Some(GameState::Results { winner, .. }) = result {
+                    // A drawn match sends the better seed through, as a double forfeit does
+                    let side = usize::from(winner == Some(teams[1]));
+                    self.decide(index, side);
+                    return;
+                }
+
+                // A player who leaves the game before the end forfeits.
+                let present = |entrant: usize| match self.entrants[entrant].client_id() {
+                    None => true,
+                    Some(id) => matches!(
+                        clients.get(&id),
+                        Some(Client { state: ClientState::InGame { game_code: code, .. }, .. })
+                            if code == game_code
+                    ),
+                };
+                match (present(a), present(b)) {
+                    (true, true) => {}
+                    (false, true) => self.decide(index, 1),
+                    (_, false) => self.decide(index, 0),
+                }
+            }
+            MatchState::Simulating(results) => match results.try_recv() {
+                Ok(winner) => self.decide(index, winner),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    warn!(match_index = index, "Simulated tournament match failed");
+                    self.decide(index, 0);
+                }
+            },
+            MatchState::Pending | MatchState::Done { .. } => {}
+        }
+    }
+
+    fn start_match(
+        &mut self,
+        index: usize,
+        clients: &mut HashMap<ClientId, Client>,
+        games: &mut GameManager,
+        messages: &mut Vec<(ClientId, ServerMessage)>,
+    ) {
+        let m = &self.matches[index];
+        let sides = m.sides.map(|side| side.expect("both entrants are known"));
+        let map = m.map;
+        let rounds = self.config.rounds;
+        let team_size = usize::from(self.config.team_size);
+
+        let bots = sides.map(|entrant| match &self.entrants[entrant] {
+            Entrant::Bot(spec) => Some(spec.clone()),
+            Entrant::Player { .. } => None,
+        });
+        if let [Some(first), Some(second)] = bots {
+            // Models are taken from the library now, so a reload can't change them mid-match
+            let bots = [first, second].map(|spec| ResolvedBot::resolve(&spec, &games.models));
+            let (sender, receiver) = mpsc::channel();
+            let seed = index as u64;
+            std::thread::spawn(move || {
+                let _ = sender.send(simulate(bots, map, rounds, team_size, seed));
+            });
+            self.matches[index].state = MatchState::Simulating(receiver);
+            return;
+        }
+
+        // Players join in side order and the game puts the first one on Blue.
+        let players: Vec<(ClientId, String)> = sides
+            .iter()
+            .filter_map(|&entrant| match &self.entrants[entrant] {
+                Entrant::Player {
+                    client_id,
+                    nickname,
+                    ..
+                } => Some((*client_id, nickname.clone())),
+                Entrant::Bot(_) => None,
+            })
+            .collect();
+        let teams = if bots[0].is_some() {
+            [Team::Red, Team::Blue]
+        } else {
+            [Team::Blue, Team::Red]
+        };
+
+        let mut fill = BotFill {
+            team_size: Some(team_size),
+            ..BotFill::default()
+        };
+        let mut model = None;
+        for (side, bot) in bots.iter().enumerate() {
+            match bot {
+                Some(ContestantSpec::Scripted(difficulty)) => match teams[side] {
+                    Team::Blue => fill.blue = *difficulty,
+                    Team::Red | Team::Ffa(_) => fill.red = *difficulty,
+                },
+                Some(ContestantSpec::Model(name)) => {
+                    let loaded = games
+                        .models
+                        .get(name)
+                        .and_then(|shared| Ok((shared.brain()?, shared)));
+                    match loaded {
+                        Ok((brain, shared)) => {
+                            model = Some((teams[side], Box::new(brain), shared));
+                        }
+                        Err(e) => {
+                            warn!(%e, "Tournament bot forfeits");
+                            self.decide(index, 1 - side);
+                            return;
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let setup = |game: &mut Game| {
+            game.set_bot_fill(fill);
+            if let Some((team, brain, shared)) = model {
+                game.set_model(Some(team), brain, Some(shared));
+            }
+        };
+        let (game_code, infos) = match games.create_server_game(map, rounds, &players, setup) {
+            Ok(created) => created,
+            Err(e) => {
+                // Most likely the server is full of games; try again next tick.
+                warn!(%e, "Failed to create tournament game");
+                return;
+            }
+        };
+
+        for ((client_id, _), info) in players.iter().zip(infos) {
+            if let Some(client) = clients.get_mut(client_id) {
+                client.state = ClientState::InGame {
+                    game_code: game_code.clone(),
+                    player_id: info.player_id,
+                };
+                games.set_color(&game_code, info.player_id, client.color);
+            }
+            messages.push((*client_id, ServerMessage::TournamentMatch(info)));
+        }
+        info!(match_index = index, ?game_code, "Tournament match started");
+        self.matches[index].state = MatchState::Live { game_code, teams };
+    }
+
+    /// Records the result and moves the winner on, or crowns them after the final.
+    fn decide(&mut self, index: usize, winner: usize) {
+        let m = &mut self.matches[index];
+        m.state = MatchState::Done { winner };
+        let entrant = m.sides[winner].expect("the winner is a known entrant");
+        match m.next {
+            Some((next, side)) => self.matches[next].sides[side] = Some(entrant),
+            None => {
+                info!(champion = %self.entrants[entrant].name(), "Tournament finished");
+                self.phase = Phase::Finished { champion: entrant };
+            }
+        }
+    }
+}
+
+/// A tournament bot with its model file taken from the library, so it can be moved to
+/// the thread simulating its match.
+enum ResolvedBot {
+    Scripted(BotDifficulty),
+    Model(SharedModel),
+}
+
+impl ResolvedBot {
+    fn resolve(spec: &ContestantSpec, models: &ModelLibrary) -> Result<Self, String> {
+        match spec {
+            ContestantSpec::Scripted(difficulty) => Ok(Self::Scripted(*difficulty)),
+            ContestantSpec::Model(name) => models.get(name).map(Self::Model),
+        }
+    }
+
+    fn load(self) -> Result<Contestant, String> {
+        match self {
+            Self::Scripted(difficulty) => Ok(Contestant::Scripted(difficulty)),
+            Self::Model(model) => Ok(Contestant::Model(Box::new(model.brain()?))),
+        }
+    }
+}
+
+/// Plays a match between two bots, alternating sides every round. Returns the winning side;
+/// a tie goes to the better seed on side 0.
+fn simulate(
+    bots: [Result<ResolvedBot, String>; 2],
+    map: MapName,
+    rounds: u8,
+    team_size: usize,
+    seed: u64,
+) -> usize {
+    let contestants = match bots.map(|bot| bot.and_then(ResolvedBot::load)) {
+        [Ok(first), Ok(second)] => [first, second],
+        [Err(e), _] => {
+            warn!(%e, "Tournament bot forfeits");
+            return 1;
+        }
+        [_, Err(e)] => {
+            warn!(%e, "Tournament bot forfeits");
+            return 0;
+        }
+    };
+
+    let map = MapDefinition::load_name(map);
+    let mut summary = Summary::default();
+    for round in 0..rounds {
+        let a_team = if round % 2 == 0 {
+            Team::Blue
+        } else {
+            Team::Red
+        };
+        let report = contest::play_match(
+            &contestants,
+            a_team,
+            &map,
+            Some(team_size),
+            game::ROUND_DURATION,
+            seed ^ u64::from(round),
+            None,
+        );
+        summary.record(report.outcome);
+    }
+    usize::from(summary.wins[1] > summary.wins[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(capacity: u8, bots: Vec<ContestantSpec>) -> TournamentConfig {
+        TournamentConfig {
+            name: "Cup".into(),
+            team_size: 1,
+            rounds: 1,
+            maps: vec![MapName::Basic],
+            capacity,
+            signup: Duration::from_secs(10),
+            bots,
+        }
+    }
+
+    fn connect(clients: &mut HashMap<ClientId, Client>, client_id: ClientId) {
+        clients.insert(client_id, Client::default());
+    }
+
+    fn leave_game(clients: &mut HashMap<ClientId, Client>, games: &mut GameManager, id: ClientId) {
+        let client = clients.get_mut(&id).unwrap();
+        if let ClientState::InGame { game_code, .. } = &client.state {
+            games.leave_game(game_code, id).unwrap();
+        }
+        client.state = ClientState::Lobby;
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = TournamentConfig::parse(
+            r#"{"name": "Cup", "team_size": 2, "rounds": 3, "maps": ["basic", "Tiga"],
+                "capacity": 8, "signup_secs": 120, "bots": ["scripted:hunter", "champion"]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.maps, vec![MapName::Basic, MapName::Tiga]);
+        assert_eq!(
+            config.bots,
+            vec![
+                ContestantSpec::Scripted(BotDifficulty::Hunter),
+                ContestantSpec::Model("champion".into())
+            ]
+        );
+
+        let too_big = r#"{"name": "Cup", "team_size": 9, "rounds": 1, "maps": ["basic"],
+                "capacity": 8, "signup_secs": 0}"#;
+        assert!(TournamentConfig::parse(too_big).is_err());
+        let no_maps = r#"{"name": "Cup", "team_size": 1, "rounds": 1, "maps": [],
+                "capacity": 8, "signup_secs": 0}"#;
+        assert!(TournamentConfig::parse(no_maps).is_err());
+    }
+
+    #[test]
+    fn test_bracket_with_players_and_bye() {
+        let bot = ContestantSpec::Scripted(BotDifficulty::Dummy);
+        let mut tournament = Tournament::new(config(4, vec![bot]));
+        let mut clients = HashMap::new();
+        let mut games = GameManager::new_seeded(0);
+        for id in [1, 2] {
+            connect(&mut clients, id);
+            let response = tournament.join(id, format!("p{}", id));
+            assert!(matches!(response, TournamentJoinResponse::Ok(_)));
+        }
+
+        // Sign-up ends: the bot is the top seed and gets a bye, the players meet.
+        let messages = tournament.tick(Duration::from_secs(10), &mut clients, &mut games);
+        assert!(
+            messages
+                .iter()
+                .all(|(_, m)| matches!(m, ServerMessage::TournamentUpdate(_)))
+        );
+        let messages = tournament.tick(Duration::ZERO, &mut clients, &mut games);
+        let matched = messages
+            .iter()
+            .filter(|(_, m)| matches!(m, ServerMessage::TournamentMatch(_)))
+            .count();
+        assert_eq!(matched, 2);
+        assert_eq!(games.games.len(), 1);
+        assert!(matches!(clients[&1].state, ClientState::InGame { .. }));
+
+        // p2 walks out and forfeits; p1 still sits in the old game, so the final waits.
+        leave_game(&mut clients, &mut games, 2);
+        tournament.tick(Duration::ZERO, &mut clients, &mut games);
+        tournament.tick(Duration::ZERO, &mut clients, &mut games);
+        let info = tournament.info();
+        assert_eq!(info.matches.len(), 2);
+        assert_eq!(info.matches[0].winner, Some(0));
+        assert_eq!(info.standings[0].name, "p1");
+        assert_eq!(info.state, TournamentState::Running { bracket_round: 2 });
+
+        // Back in the lobby, p1 is put into the final against the bot.
+        leave_game(&mut clients, &mut games, 1);
+        let messages = tournament.tick(Duration::ZERO, &mut clients, &mut games);
+        assert!(
+            messages
+                .iter()
+                .any(|(id, m)| *id == 1 && matches!(m, ServerMessage::TournamentMatch(_)))
+        );
+
+        // Walking out of the final and withdrawing hands the title to the bot.
+        leave_game(&mut clients, &mut games, 1);
+        tournament.leave(1).unwrap();
+        let messages = tournament.tick(Duration::ZERO, &mut clients, &mut games);
+        assert!(messages.iter().all(|(id, _)| *id == 2));
+        assert_eq!(
+            tournament.info().state,
+            TournamentState::Finished {
+                champion: "Dummy (scripted)".into()
+            }
+        );
+        assert!(matches!(
+            tournament.join(3, "late".into()),
+            TournamentJoinResponse::Closed
+        ));
+    }
+
+    #[test]
+    fn test_bot_match_is_simulated() {
+        let bots = vec![
+            ContestantSpec::Scripted(BotDifficulty::Dummy),
+            ContestantSpec::Scripted(BotDifficulty::Turret),
+        ];
+        let mut tournament = Tournament::new(config(2, bots));
+        let mut clients = HashMap::new();
+        let mut games = GameManager::new_seeded(0);
+
+        tournament.tick(Duration::from_secs(10), &mut clients, &mut games);
+        for _ in 0..1000 {
+            tournament.tick(Duration::ZERO, &mut clients, &mut games);
+            if matches!(tournament.phase, Phase::Finished { .. }) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(matches!(tournament.phase, Phase::Finished { .. }));
+        assert!(games.games.is_empty());
+    }
+
+    #[test]
+    fn test_too_few_entrants_cancels() {
+        let mut tournament = Tournament::new(config(4, Vec::new()));
+        let mut clients = HashMap::new();
+        let mut games = GameManager::new_seeded(0);
+        connect(&mut clients, 1);
+        tournament.join(1, "solo".into());
+
+        let messages = tournament.tick(Duration::from_secs(10), &mut clients, &mut games);
+        assert_eq!(tournament.info().state, TournamentState::Cancelled);
+        assert!(matches!(
+            messages.as_slice(),
+            [(1, ServerMessage::TournamentUpdate(_))]
+        ));
+    }
+}
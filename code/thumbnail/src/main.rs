@@ -0,0 +1,69 @@
+//! Renders maps to PNG thumbnails without opening a window, to preview a map file after
+//! editing it or to show maps where the game can't draw them.
+
+use clap::Parser;
+use common::game::map_registry::{MAPS_DIR, MapRegistry};
+use common::game::thumbnail::render_thumbnail;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Maps to render, built-in or from `--maps`, or a map file. Every map if left out.
+    names: Vec<String>,
+
+    /// Directory of custom maps.
+    #[arg(long, default_value = MAPS_DIR)]
+    maps: PathBuf,
+
+    /// Length of the longer side of a thumbnail, in pixels.
+    #[arg(long, default_value_t = 256)]
+    size: u32,
+
+    /// Thumbnails are written here, as `<map>.png`.
+    #[arg(long, default_value = "assets/thumbnails")]
+    out: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let registry = MapRegistry::load(&args.maps);
+    let names: Vec<String> = if args.names.is_empty() {
+        registry.names().map(String::from).collect()
+    } else {
+        args.names.clone()
+    };
+    std::fs::create_dir_all(&args.out)
+        .map_err(|e| format!("Can't create {}: {}", args.out.display(), e))?;
+
+    for name in names {
+        let path = Path::new(&name);
+        let (name, map) = if path.is_file() {
+            let stem = path.file_stem().and_then(|stem| stem.to_str());
+            (
+                stem.unwrap_or("map").to_string(),
+                common::game::MapDefinition::from_file(path)?,
+            )
+        } else {
+            let map = registry.resolve(&name)?;
+            (name.to_lowercase(), map)
+        };
+        let thumbnail = render_thumbnail(&map, args.size);
+        let image = RgbaImage::from_raw(thumbnail.width, thumbnail.height, thumbnail.rgba)
+            .expect("thumbnails hold four bytes a pixel");
+        let out = args.out.join(format!("{}.png", name));
+        image
+            .save(&out)
+            .map_err(|e| format!("Can't write {}: {}", out.display(), e))?;
+        println!("{}", out.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+//! Genetic operators: how one generation's parents become the next population.
+//!
+//! The best parents can be kept unchanged (elitism), the rest of the population are
+//! offspring of parents picked by tournament, optionally crossed over with a second one,
+//! and always mutated. The defaults keep every parent and pick uniformly without
+//! crossover.
+
+use burn::module::Param;
+use burn::nn::Linear;
+use burn::tensor::backend::Backend;
+use burn::tensor::{Distribution, Tensor};
+use common::rl::BotBrain;
+use rand::Rng;
+
+/// A brain selected to breed, with the fitness it was selected by.
+pub struct Parent<B: Backend> {
+    pub brain: BotBrain<B>,
+    /// Higher is better; only compared between parents of one generation
+    pub fitness: f32,
+}
+
+/// The operators of a run, from the command line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Operators {
+    /// Best parents copied over unchanged; all of them when `None`
+    pub elitism: Option<usize>,
+    /// Parents drawn per pick, the fittest of which breeds; 1 picks uniformly
+    pub tournament_size: usize,
+    /// Chance that an offspring mixes the weights of two parents instead of copying one
+    pub crossover_rate: f32,
+    /// Strength of the noise added to every offspring
+    pub mutation_rate: f32,
+}
+
+impl Operators {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tournament_size == 0 {
+            return Err("--tournament-size must be at least 1.".into());
+        }
+        if !(0.0..=1.0).contains(&self.crossover_rate) {
+            return Err("--crossover-rate must be between 0.0 and 1.0.".into());
+        }
+        Ok(())
+    }
+}
+
+/// Orders `parents` best first; equally fit parents keep their order.
+pub fn rank<B: Backend>(parents: &mut [Parent<B>]) {
+    parents.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+}
+
+/// The next population of `size` brains bred from `parents`, ranked best first. Elites
+/// come first, so the best parent stays at the front. Tensor randomness (crossover masks
+/// and mutations) comes from the backend's seed, the picks from `rng`.
+pub fn next_population<B: Backend>(
+    parents: &[Parent<B>],
+    size: usize,
+    operators: Operators,
+    rng: &mut impl Rng,
+) -> Vec<BotBrain<B>> {
+    let elites = operators.elitism.unwrap_or(parents.len()).min(size);
+    let mut population: Vec<BotBrain<B>> = parents
+        .iter()
+        .take(elites)
+        .map(|parent| parent.brain.clone())
+        .collect();
+    if parents.is_empty() {
+        return population;
+    }
+
+    while population.len() < size {
+        let first = tournament(parents, operators.tournament_size, rng);
+        // No draw without crossover, so runs without it pick the same parents as before
+        let child =
+            if operators.crossover_rate > 0.0 && rng.random::<f32>() < operators.crossover_rate {
+                let second = tournament(parents, operators.tournament_size, rng);
+                crossover(&first.brain, &second.brain)
+            } else {
+                first.brain.clone()
+            };
+        population.push(child.mutate(operators.mutation_rate));
+    }
+    population
+}
+
+/// The fittest of `size` parents drawn at random (with replacement); the first drawn wins
+/// ties.
+fn tournament<'a, B: Backend>(
+    parents: &'a [Parent<B>],
+    size: usize,
+    rng: &mut impl Rng,
+) -> &'a Parent<B> {
+    let mut best = &parents[rng.random_range(0..parents.len())];
+    for _ in 1..size {
+        let contender = &parents[rng.random_range(0..parents.len())];
+        if contender.fitness > best.fitness {
+            best = contender;
+        }
+    }
+    best
+}
+
+/// A brain taking every weight and bias from `a` or `b`, each with even odds.
+pub fn crossover<B: Backend>(a: &BotBrain<B>, b: &BotBrain<B>) -> BotBrain<B> {
+    let mut child = a.clone();
+    child.linear1 = cross_linear(&a.linear1, &b.linear1);
+    child.linear2 = cross_linear(&a.linear2, &b.linear2);
+    child.linear3 = cross_linear(&a.linear3, &b.linear3);
+    child.output = cross_linear(&a.output, &b.output);
+    child
+}
+
+fn cross_linear<B: Backend>(a: &Linear<B>, b: &Linear<B>) -> Linear<B> {
+    Linear {
+        weight: Param::from_tensor(cross(a.weight.val(), b.weight.val())),
+        bias: match (&a.bias, &b.bias) {
+            (Some(a), Some(b)) => Some(Param::from_tensor(cross(a.val(), b.val()))),
+            _ => a.bias.clone(),
+        },
+    }
+}
+
+/// `a` where a coin lands heads, `b` elsewhere.
+fn cross<B: Backend, const D: usize>(a: Tensor<B, D>, b: Tensor<B, D>) -> Tensor<B, D> {
+    let heads = Tensor::<B, D>::random(a.shape(), Distribution::Uniform(0.0, 1.0), &a.device())
+        .lower_elem(0.5);
+    b.mask_where(heads, a).detach()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::backend::NdArray;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn parent(fitness: f32) -> Parent<NdArray> {
+        Parent {
+            brain: BotBrain::new(&Default::default()),
+            fitness,
+        }
+    }
+
+    fn operators() -> Operators {
+        Operators {
+            elitism: None,
+            tournament_size: 1,
+            crossover_rate: 0.0,
+            mutation_rate: 0.05,
+        }
+    }
+
+    #[test]
+    fn test_elites_are_the_best_parents_unchanged() {
+        let mut parents = vec![parent(1.0), parent(5.0), parent(3.0)];
+        rank(&mut parents);
+        let fitness: Vec<f32> = parents.iter().map(|p| p.fitness).collect();
+        assert_eq!(fitness, [5.0, 3.0, 1.0]);
+        let prints: Vec<u64> = parents.iter().map(|p| p.brain.fingerprint()).collect();
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let elitism = Operators {
+            elitism: Some(1),
+            ..operators()
+        };
+        let population = next_population(&parents, 4, elitism, &mut rng);
+        assert_eq!(population.len(), 4);
+        assert_eq!(population[0].fingerprint(), prints[0]);
+        for child in &population[1..] {
+            assert!(!prints.contains(&child.fingerprint()));
+        }
+
+        // By default every parent is kept
+        let population = next_population(&parents, 4, operators(), &mut rng);
+        let kept: Vec<u64> = population[..3].iter().map(BotBrain::fingerprint).collect();
+        assert_eq!(kept, prints);
+    }
+
+    #[test]
+    fn test_big_tournaments_pick_the_fittest() {
+        let parents = vec![parent(1.0), parent(9.0), parent(3.0)];
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            assert_eq!(tournament(&parents, 32, &mut rng).fitness, 9.0);
+        }
+    }
+
+    #[test]
+    fn test_crossover_takes_every_weight_from_a_parent() {
+        let device = Default::default();
+        let a = BotBrain::<NdArray>::new(&device);
+        let b = BotBrain::<NdArray>::new(&device);
+        let child = crossover(&a, &b);
+
+        let values = |brain: &BotBrain<NdArray>| {
+            brain
+                .output
+                .weight
+                .val()
+                .into_data()
+                .to_vec::<f32>()
+                .unwrap()
+        };
+        let (a, b, child) = (values(&a), values(&b), values(&child));
+        let from_a = child.iter().zip(&a).filter(|(c, a)| c == a).count();
+        let from_b = child.iter().zip(&b).filter(|(c, b)| c == b).count();
+        assert_eq!(from_a + from_b, child.len());
+        assert!(from_a > child.len() / 4 && from_b > child.len() / 4);
+    }
+}
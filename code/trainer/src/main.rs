@@ -1,21 +1,63 @@
 #![recursion_limit = "256"]
-use burn::backend::Wgpu;
+use burn::backend::{Candle, NdArray, Wgpu};
 use burn::module::Module;
 use burn::record::{BinFileRecorder, FullPrecisionSettings};
 use burn::tensor::backend::Backend;
-use clap::Parser;
-use common::ai::BotContext;
+use clap::{Parser, ValueEnum};
+use common::ai::{BotAgent, BotContext, BotDifficulty, BotRng};
+use common::game::balance::OVERRIDE_PATH as BALANCE_PATH;
 use common::game::engine::GameEngine;
-use common::net::protocol::{InputPayload, MapDefinition, PlayerId, Tank, Team};
-use common::rl::{extract_features, BotBrain};
+use common::game::map_generator::MapGenerator;
+use common::game::map_registry::MapRegistry;
+use common::game::upgrades::KILL_POINTS;
+use common::game::{reload_balance, MAX_TEAM_SIZE};
+use common::net::protocol::{Loadout, MapDefinition, MapName, PlayerId, Tank, Team};
+use common::rl::gpu::wgpu_available;
+use common::rl::scenario::{ScenarioDriver, SCENARIO_EXTENSION};
+use common::rl::{action_to_input, extract_features_with, BotBrain, FeatureLayout, Scenario};
+use evolution::{next_population, rank, Operators, Parent};
+use metrics::MetricsRecorder;
+use novelty::{novelty_scores, Behavior, BehaviorTracker};
+use opponent::{parse_opponent, OpponentSpec};
+use pairing::{arrange_matches, unique_matches, MatchKey};
+use progress::{Dashboard, GenerationSummary, MatchSummary, PlainReporter, Reporter};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use reward::{NoveltyWeights, RewardConfig};
+use seeding::{
+    derive_seed, EVAL_STREAM, INIT_STREAM, MAP_STREAM, MATCH_STREAM, MUTATION_STREAM,
+    SELECTION_STREAM, SHUFFLE_STREAM,
+};
+use std::collections::HashMap;
 use std::env;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
-
-type MyBackend = Wgpu;
+use stopping::{EarlyStopping, StoppingRules};
+
+mod evolution;
+mod metrics;
+mod novelty;
+mod opponent;
+mod pairing;
+mod progress;
+mod reward;
+mod seeding;
+mod stopping;
+
+/// What the networks run on.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum BackendChoice {
+    /// The GPU when there is a usable one, the CPU (ndarray) otherwise.
+    Auto,
+    /// The GPU, through wgpu.
+    Wgpu,
+    /// The CPU, through ndarray.
+    Ndarray,
+    /// The CPU, through candle.
+    Candle,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -29,6 +71,20 @@ struct Args {
     #[arg(long, default_value_t = 0.05)]
     mutation_rate: f32,
 
+    /// Best parents copied to the next generation unchanged. All of them when omitted.
+    #[arg(long)]
+    elitism: Option<usize>,
+
+    /// Parents drawn for every offspring, the fittest of which breeds. 1 picks uniformly;
+    /// bigger tournaments favour the fittest parents more.
+    #[arg(long, default_value_t = 1)]
+    tournament_size: usize,
+
+    /// Chance (0.0-1.0) that an offspring takes every weight from one of two parents at
+    /// random instead of copying one parent. It's mutated either way.
+    #[arg(long, default_value_t = 0.0)]
+    crossover_rate: f32,
+
     #[arg(long, default_value_t = 1000)]
     max_ticks: usize,
 
@@ -36,6 +92,117 @@ struct Args {
     /// Saved to assets/models/<name>.bin
     #[arg(long, default_value = "default_model")]
     model_name: String,
+
+    /// Evaluate the whole population against a fixed opponent instead of itself.
+    /// Either a model name from assets/models, or `scripted:<difficulty>`
+    /// (dummy, turret, wanderer, hunter, terminator). Fitness becomes the win-rate against it.
+    #[arg(long, value_parser = parse_opponent)]
+    opponent: Option<OpponentSpec>,
+
+    /// Stop when the best fitness hasn't improved for this many generations.
+    #[arg(long)]
+    patience: Option<usize>,
+
+    /// Stop once the champion's win-rate against the reference reaches this value (0.0-1.0).
+    #[arg(long)]
+    target_win_rate: Option<f32>,
+
+    /// Model (or `scripted:<difficulty>`) used for win-rate checks and the final evaluation.
+    /// Defaults to `--opponent`.
+    #[arg(long, value_parser = parse_opponent)]
+    reference: Option<OpponentSpec>,
+
+    /// Stop after this many minutes of wall-clock time.
+    #[arg(long)]
+    max_minutes: Option<f64>,
+
+    /// Base seed for every random choice (initial weights, pairings, mutations, matches).
+    /// Two runs with the same seed and arguments produce the same models. Random if omitted.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Fitness bonus per unit of behavioral novelty (distance to the most similar bots in
+    /// where they go, how often they shoot and how much they move). 0 disables it.
+    #[arg(long, default_value_t = 0.0)]
+    novelty_bonus: f32,
+
+    /// How many of the most similar bots novelty is measured against.
+    #[arg(long, default_value_t = 5)]
+    novelty_neighbours: usize,
+
+    /// Tanks per team in training matches, 1 to 8. Teams above 4 see more of their
+    /// nearest enemies and teammates, so such models only play teams of a similar size.
+    #[arg(long, default_value_t = 4)]
+    team_size: usize,
+
+    /// Let the bots see their team's latest ping (where and how long ago a teammate pointed
+    /// at). Training matches have no pings yet, so this only prepares models for them.
+    #[arg(long)]
+    ping_features: bool,
+
+    /// Let the bots see their own upgrades and those of the strongest enemy in sight, for
+    /// matches with an intermission. Best combined with `--rounds`.
+    #[arg(long)]
+    upgrade_features: bool,
+
+    /// Let the bots see how much of a shot gets through their front armor and that of
+    /// their four nearest enemies, and which way those enemies face.
+    #[arg(long)]
+    armor_features: bool,
+
+    /// Rounds per training match, each of up to `--max-ticks`. Between rounds every tank
+    /// spends the points it earned on upgrades, as in an intermission, and keeps them for
+    /// the rest of the match. The team that won more rounds wins the match.
+    #[arg(long, default_value_t = 1)]
+    rounds: usize,
+
+    /// Fitness bonus for every round a bot's team wins.
+    #[arg(long, default_value_t = 0.0)]
+    round_win_bonus: f32,
+
+    /// Train on a scripted scenario instead of full matches: a file in assets/scenarios
+    /// (without `.ron`) or a path. Its Learner tanks are played by squads of the population,
+    /// and fitness becomes the win-rate in it. Cannot be combined with `--opponent`.
+    #[arg(long)]
+    scenario: Option<String>,
+
+    /// Map of training matches: a built-in one or a file name from assets/maps (without
+    /// the extension). Scenarios bring their own map.
+    #[arg(long, default_value = "basic")]
+    map: String,
+
+    /// Every generation plays on a freshly generated layout instead of `--map`, so bots
+    /// don't learn one map by heart.
+    #[arg(long)]
+    random_maps: bool,
+
+    /// Print one line per generation instead of the live dashboard, e.g. in CI. Implied
+    /// when the output isn't a terminal.
+    #[arg(long)]
+    quiet: bool,
+
+    /// Where the networks run. The CPU backends are slower but need no GPU, e.g. on CI
+    /// machines and headless servers.
+    #[arg(long, value_enum, default_value_t = BackendChoice::Auto)]
+    backend: BackendChoice,
+
+    /// Write per-generation metrics (best and mean fitness, kills, friendly fire, match
+    /// length) to this file, as CSV or JSON Lines depending on whether it ends in `.csv`
+    /// or `.jsonl`. Replaces the file's contents.
+    #[arg(long)]
+    metrics: Option<PathBuf>,
+
+    /// Print the metrics of every generation, and how much the best fitness moved over
+    /// the last ones.
+    #[arg(long)]
+    metrics_summary: bool,
+}
+
+/// Who controls a tank during a training match.
+#[derive(Clone)]
+enum Driver<B: Backend> {
+    Brain(Box<BotBrain<B>>),
+    Scripted(BotDifficulty),
 }
 
 /// Helper logic to locate the assets directory.
@@ -61,10 +228,72 @@ fn resolve_assets_path(start_dir: &Path) -> PathBuf {
 async fn main() {
     let args = Args::parse();
 
-    println!("Starting Spartan Evolution on GPU (Parallel)...");
+    match args.backend {
+        BackendChoice::Auto if wgpu_available() => {
+            println!("Starting Spartan Evolution on GPU (Parallel)...");
+            train::<Wgpu>(args, Default::default());
+        }
+        BackendChoice::Auto => {
+            eprintln!(
+                "Warning: no usable GPU found, falling back to the CPU. Training will be slower."
+            );
+            println!("Starting Spartan Evolution on CPU (Parallel)...");
+            train::<NdArray>(args, Default::default());
+        }
+        BackendChoice::Wgpu if !wgpu_available() => {
+            eprintln!("No usable GPU found. Pick a CPU backend with --backend ndarray or candle.");
+        }
+        BackendChoice::Wgpu => {
+            println!("Starting Spartan Evolution on GPU (Parallel)...");
+            train::<Wgpu>(args, Default::default());
+        }
+        BackendChoice::Ndarray => {
+            println!("Starting Spartan Evolution on CPU with ndarray (Parallel)...");
+            train::<NdArray>(args, Default::default());
+        }
+        BackendChoice::Candle => {
+            println!("Starting Spartan Evolution on CPU with candle (Parallel)...");
+            train::<Candle>(args, Default::default());
+        }
+    }
+}
+
+fn train<B: Backend>(args: Args, device: B::Device) {
     println!("Configuration: {:?}", args);
 
-    let device = Default::default();
+    if !(1..=MAX_TEAM_SIZE).contains(&args.team_size) {
+        eprintln!("--team-size must be between 1 and {}.", MAX_TEAM_SIZE);
+        return;
+    }
+    let mut layout = FeatureLayout::for_team_size(args.team_size);
+    if args.ping_features {
+        layout = layout.with_ping();
+    }
+    if args.upgrade_features {
+        layout = layout.with_upgrades();
+    }
+    if args.armor_features {
+        layout = layout.with_armor();
+    }
+    if args.rounds == 0 {
+        eprintln!("--rounds must be at least 1.");
+        return;
+    }
+    let format = MatchFormat {
+        max_ticks: args.max_ticks,
+        rounds: args.rounds,
+    };
+    let operators = Operators {
+        elitism: args.elitism,
+        tournament_size: args.tournament_size,
+        crossover_rate: args.crossover_rate,
+        mutation_rate: args.mutation_rate,
+    };
+    if let Err(e) = operators.validate() {
+        eprintln!("{}", e);
+        return;
+    }
+
     let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
 
     // --- Dynamic Path Resolution ---
@@ -98,7 +327,7 @@ async fn main() {
     // 1. Initialize Population
     // Try to load existing model to start with, otherwise random
     let initial_brain =
-        match BotBrain::<MyBackend>::new(&device).load_file(&model_path_str, &recorder, &device) {
+        match BotBrain::<B>::new(&device).load_file(&model_path_str, &recorder, &device) {
             Ok(brain) => {
                 println!("Loaded existing model: {}.bin", args.model_name);
                 brain
@@ -108,238 +337,1060 @@ async fn main() {
                     "No existing model found at {}.bin. Starting from scratch.",
                     model_path_str
                 );
-                BotBrain::new(&device)
+                BotBrain::with_layout(&device, layout)
             }
         };
+    if initial_brain.stored_layout() != Some(layout) {
+        eprintln!(
+            "{}.bin was trained for a different team size, --ping-features, --upgrade-features or --armor-features; pick another --model-name.",
+            args.model_name
+        );
+        return;
+    }
 
-    let mut population: Vec<BotBrain<MyBackend>> = (0..args.population_size)
+    let opponent = match &args.opponent {
+        None => None,
+        Some(spec) => match load_driver(spec, &models_dir, &device, &recorder) {
+            Ok(driver) => {
+                println!("Training against frozen opponent: {:?}", spec);
+                Some(driver)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        },
+    };
+
+    let reference = match &args.reference {
+        None => opponent.clone(),
+        Some(spec) => match load_driver(spec, &models_dir, &device, &recorder) {
+            Ok(driver) => Some(driver),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        },
+    };
+
+    let scenario = match &args.scenario {
+        None => None,
+        Some(_) if opponent.is_some() => {
+            eprintln!("--scenario and --opponent cannot be combined.");
+            return;
+        }
+        Some(name) => match load_scenario(name, &assets_root) {
+            Ok(scenario) => {
+                println!("Training on scenario: {}", scenario.name);
+                Some(scenario)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        },
+    };
+
+    let map = match MapRegistry::load(&assets_root.join("maps")).resolve(&args.map) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    if args.target_win_rate.is_some() && reference.is_none() {
+        eprintln!("--target-win-rate needs a --reference (or --opponent) to measure against.");
+        return;
+    }
+
+    let mut stopping = EarlyStopping::new(
+        StoppingRules {
+            patience: args.patience,
+            target_win_rate: args.target_win_rate,
+            time_budget: args
+                .max_minutes
+                .map(|m| std::time::Duration::from_secs_f64(m * 60.0)),
+        },
+        std::time::Instant::now(),
+    );
+
+    let mut rewards = RewardConfig {
+        novelty: NoveltyWeights {
+            bonus: args.novelty_bonus,
+            neighbours: args.novelty_neighbours,
+            ..Default::default()
+        },
+        round_win_bonus: args.round_win_bonus,
+        ..Default::default()
+    };
+    if let Some(scenario) = &scenario {
+        rewards = rewards.with_overrides(&scenario.rewards);
+    }
+
+    let seed = args.seed.unwrap_or_else(|| rand::rng().random());
+    println!(
+        "Seed: {} (pass --seed {} to reproduce this run)",
+        seed, seed
+    );
+    B::seed(&device, derive_seed(seed, 0, INIT_STREAM));
+
+    let mut population: Vec<BotBrain<B>> = (0..args.population_size)
         .map(|_| initial_brain.mutate(args.mutation_rate)) // Slight mutation from base to create diversity
         .collect();
 
     if population.is_empty() {
-        population.push(BotBrain::new(&device));
+        population.push(BotBrain::with_layout(&device, layout));
     }
 
+    let output: Box<dyn Reporter> = if args.quiet || !std::io::stdout().is_terminal() {
+        Box::new(PlainReporter)
+    } else {
+        Box::new(Dashboard::new(args.generations))
+    };
+    let output: Box<dyn Reporter> = if args.metrics.is_some() || args.metrics_summary {
+        match MetricsRecorder::create(output, args.metrics.as_deref(), args.metrics_summary) {
+            Ok(recorder) => Box::new(recorder),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    } else {
+        output
+    };
+    let reporter = output.as_ref();
+    let settings = MatchSettings {
+        format,
+        map: &map,
+        rewards: &rewards,
+        reporter,
+    };
+
     for gen in 1..=args.generations {
-        let next_gen_parents = Arc::new(Mutex::new(Vec::new()));
+        let started = std::time::Instant::now();
+        // Balance edited while training applies from the next generation on
+        match reload_balance() {
+            Ok(true) => reporter.note(&format!("Reloaded the balance table from {}", BALANCE_PATH)),
+            Ok(false) => {}
+            Err(e) => reporter.note(&format!("  ! {}; keeping the previous balance", e)),
+        }
+        let random_map = args
+            .random_maps
+            .then(|| MapGenerator::default().generate(derive_seed(seed, gen, MAP_STREAM)));
+        let settings = MatchSettings {
+            map: random_map.as_ref().unwrap_or(&map),
+            ..settings
+        };
+        let generation = match (&opponent, &scenario) {
+            (_, Some(scenario)) => {
+                let squads = population.len().div_ceil(scenario.learner_count());
+                reporter.generation_started(gen, squads * SCENARIO_ROUNDS);
+                play_scenario(
+                    &population,
+                    scenario,
+                    &device,
+                    settings,
+                    derive_seed(seed, gen, MATCH_STREAM),
+                )
+            }
+            (Some(opponent), None) => {
+                reporter.generation_started(gen, population.len().div_ceil(args.team_size) * 2);
+                play_against_opponent(
+                    &population,
+                    opponent,
+                    &device,
+                    settings,
+                    derive_seed(seed, gen, MATCH_STREAM),
+                    args.team_size,
+                )
+            }
+            (None, None) => {
+                reporter.generation_started(gen, population.len() / (2 * args.team_size));
+                play_population_matches(
+                    &mut population,
+                    &device,
+                    settings,
+                    derive_seed(seed, gen, SHUFFLE_STREAM),
+                    derive_seed(seed, gen, MATCH_STREAM),
+                    args.team_size,
+                )
+            }
+        };
+        let mut next_gen_parents = generation.parents;
+        rank(&mut next_gen_parents);
+
+        let mut rng = StdRng::seed_from_u64(derive_seed(seed, gen, SELECTION_STREAM));
+        B::seed(&device, derive_seed(seed, gen, MUTATION_STREAM));
+        population = if next_gen_parents.is_empty() {
+            reporter.note("  ! Extinction (No Winners). Resetting population.");
+            (0..args.population_size)
+                .map(|_| BotBrain::with_layout(&device, layout))
+                .collect()
+        } else {
+            next_population(&next_gen_parents, args.population_size, operators, &mut rng)
+        };
+        save_model(&population[0], &models_dir, &args.model_name, &recorder);
+
+        // Only pay for the extra evaluation matches when a target is set.
+        let win_rate = match (&reference, args.target_win_rate) {
+            (Some(reference), Some(_)) => Some(evaluate_win_rate(
+                &population[0],
+                reference,
+                &device,
+                format,
+                derive_seed(seed, gen, EVAL_STREAM),
+                args.team_size,
+            )),
+            _ => None,
+        };
 
-        fastrand::shuffle(&mut population);
+        reporter.generation_finished(&GenerationSummary {
+            generation: gen,
+            best_fitness: generation.best_fitness,
+            mean_fitness: generation.mean_fitness,
+            win_rate,
+            elapsed: started.elapsed(),
+        });
 
-        thread::scope(|s| {
-            for (match_idx, match_chunk) in population.chunks(8).enumerate() {
-                if match_chunk.len() < 8 {
-                    continue;
-                }
+        if let Some(reason) =
+            stopping.update(generation.best_fitness, win_rate, std::time::Instant::now())
+        {
+            reporter.note(&format!("Stopping early after gen {}: {}", gen, reason));
+            break;
+        }
+    }
+    drop(output);
+    println!("Latest generation saved to {}.bin", model_path_str);
 
-                let parents_handle = next_gen_parents.clone();
-                let device = device.clone();
-                let blue_team = match_chunk[0..4].to_vec();
-                let red_team = match_chunk[4..8].to_vec();
-                let max_ticks = args.max_ticks;
-
-                s.spawn(move || {
-                    let stats = run_4v4_match(&blue_team, &red_team, &device, max_ticks);
-
-                    let blue_kills: i32 = stats
-                        .iter()
-                        .filter(|s| s.team == Team::Blue)
-                        .map(|s| s.kills)
-                        .sum();
-                    let red_kills: i32 = stats
-                        .iter()
-                        .filter(|s| s.team == Team::Red)
-                        .map(|s| s.kills)
-                        .sum();
-
-                    let blue_alive = stats
-                        .iter()
-                        .filter(|s| s.team == Team::Blue && s.alive)
-                        .count();
-                    let red_alive = stats
-                        .iter()
-                        .filter(|s| s.team == Team::Red && s.alive)
-                        .count();
-
-                    let winning_team = if blue_alive > red_alive {
-                        Some(Team::Blue)
-                    } else if red_alive > blue_alive {
-                        Some(Team::Red)
-                    } else if blue_kills > red_kills {
-                        Some(Team::Blue)
-                    } else if red_kills > blue_kills {
-                        Some(Team::Red)
-                    } else {
-                        None
-                    };
+    // --- Final Evaluation ---
+    let champion = &population[0];
+    if let Some(best) = stopping.best_fitness() {
+        println!("Best fitness reached: {:.2}", best);
+    }
+    if let Some(reference) = &reference {
+        let win_rate = evaluate_win_rate(
+            champion,
+            reference,
+            &device,
+            format,
+            derive_seed(seed, 0, EVAL_STREAM),
+            args.team_size,
+        );
+        println!("Final win-rate against reference: {:.0}%", win_rate * 100.0);
+    }
 
-                    if blue_kills > 0 || red_kills > 0 {
-                        println!(
-                            "  > Match {} ended. Kills B:{}/R:{}. Winner: {:?}",
-                            match_idx + 1,
-                            blue_kills,
-                            red_kills,
-                            winning_team
-                        );
-                    }
+    let final_name = format!("{}_final", args.model_name);
+    save_model(champion, &models_dir, &final_name, &recorder);
+    println!(
+        "Saved final model to {}.bin",
+        models_dir.join(final_name).display()
+    );
+}
 
-                    let mut candidates: Vec<&BotStats> = if let Some(winner) = winning_team {
-                        stats.iter().filter(|s| s.team == winner).collect()
-                    } else {
-                        stats.iter().collect()
-                    };
+/// Loads an `--opponent`/`--reference` spec into a match driver. Models keep the feature
+/// layout they were trained with, whatever the trainer's own.
+fn load_driver<B: Backend>(
+    spec: &OpponentSpec,
+    models_dir: &Path,
+    device: &B::Device,
+    recorder: &BinFileRecorder<FullPrecisionSettings>,
+) -> Result<Driver<B>, String> {
+    match spec {
+        OpponentSpec::Scripted(difficulty) => Ok(Driver::Scripted(*difficulty)),
+        OpponentSpec::Model(name) => {
+            let path = models_dir.join(name);
+            let path_str = path.to_str().expect("Path contains invalid unicode");
+            let brain = BotBrain::<B>::new(device)
+                .load_file(path_str, recorder, device)
+                .map_err(|e| format!("Failed to load model {}.bin: {}", path_str, e))?;
+            if brain.stored_layout().is_none() {
+                return Err(format!(
+                    "Model {}.bin takes {} inputs, which no feature layout has.",
+                    path_str,
+                    brain.input_count()
+                ));
+            }
+            Ok(Driver::Brain(Box::new(brain)))
+        }
+    }
+}
 
-                    candidates.sort_by(|a, b| b.total_score.partial_cmp(&a.total_score).unwrap());
+/// Loads `--scenario`: a path to a scenario file, or its name in `<assets_root>/scenarios`.
+fn load_scenario(name: &str, assets_root: &Path) -> Result<Scenario, String> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return Scenario::load(path);
+    }
+    let path = assets_root
+        .join("scenarios")
+        .join(name)
+        .with_extension(SCENARIO_EXTENSION);
+    Scenario::load(&path)
+}
+
+/// Saves `brain` to `<models_dir>/<name>.bin`.
+///
+/// Saves to a temporary file first, then renames it, so the client never reads a partial file.
+fn save_model<B: Backend>(
+    brain: &BotBrain<B>,
+    models_dir: &Path,
+    name: &str,
+    recorder: &BinFileRecorder<FullPrecisionSettings>,
+) {
+    // Burn's save_file appends .bin, so if we provide "name_tmp", it writes "name_tmp.bin"
+    let temp_name = format!("{}_tmp", name);
+
+    let temp_file_path = models_dir.join(format!("{}.bin", temp_name));
+    let final_file_path = models_dir.join(format!("{}.bin", name));
+
+    // Base path string for Burn (it appends .bin)
+    let temp_base_path = models_dir.join(&temp_name);
+    let temp_base_str = temp_base_path.to_str().expect("Invalid path string");
+
+    if brain.clone().save_file(temp_base_str, recorder).is_ok() {
+        let _ = std::fs::rename(temp_file_path, final_file_path);
+    }
+}
 
-                    let mut guard = parents_handle.lock().unwrap();
-                    for i in 0..2 {
-                        if let Some(stat) = candidates.get(i) {
-                            let original_idx = stat.original_index;
+/// Plays a squad of `brain` clones against `reference` on every map from both sides.
+/// Match `n` is seeded with `seed + n`.
+fn evaluate_win_rate<B: Backend>(
+    brain: &BotBrain<B>,
+    reference: &Driver<B>,
+    device: &B::Device,
+    format: MatchFormat,
+    seed: u64,
+    team_size: usize,
+) -> f32 {
+    let ours = vec![Driver::Brain(Box::new(brain.clone())); team_size];
+    let theirs = vec![reference.clone(); team_size];
+
+    let mut map_name = MapName::Basic;
+    let mut wins = 0;
+    let mut matches = 0;
+    loop {
+        let map = MapDefinition::load_name(map_name);
+        for our_team in [Team::Blue, Team::Red] {
+            let (blue, red) = if our_team == Team::Blue {
+                (&ours, &theirs)
+            } else {
+                (&theirs, &ours)
+            };
+            let match_seed = seed.wrapping_add(matches as u64);
+            let stats = run_match(
+                blue,
+                red,
+                &map,
+                device,
+                format,
+                &RewardConfig::default(),
+                match_seed,
+            );
+            wins += u32::from(match_winner(&stats) == Some(our_team));
+            matches += 1;
+        }
 
-                            let brain = if original_idx < 4 {
-                                &blue_team[original_idx]
-                            } else {
-                                &red_team[original_idx - 4]
-                            };
-                            guard.push(brain.clone());
-                        }
-                    }
-                });
+        map_name = map_name.next();
+        if map_name == MapName::Basic {
+            break;
+        }
+    }
+
+    wins as f32 / matches as f32
+}
+
+/// How long a training match lasts.
+#[derive(Debug, Clone, Copy)]
+struct MatchFormat {
+    /// Tick limit of every round
+    max_ticks: usize,
+    rounds: usize,
+}
+
+/// What every training match of a run shares.
+#[derive(Clone, Copy)]
+struct MatchSettings<'a> {
+    format: MatchFormat,
+    map: &'a MapDefinition,
+    rewards: &'a RewardConfig,
+    reporter: &'a dyn Reporter,
+}
+
+/// Outcome of one generation of matches.
+struct GenerationResult<B: Backend> {
+    /// Parents with their score (self-play) or win-rate (otherwise).
+    parents: Vec<Parent<B>>,
+    /// Best score (self-play) or win-rate (against `--opponent`) seen this generation.
+    best_fitness: f32,
+    /// Mean of the same measure over the population.
+    mean_fitness: f32,
+}
+
+/// Standard self-play: the shuffled population is split into `team_size` a side
+/// matches and the two best bots of each winning team become parents.
+///
+/// Clones of the same brain are kept on the same side where possible. Every match in
+/// a generation uses `match_seed`, so repeated line-ups would give the same result and
+/// are only played once. Parents are returned in match order, so the result doesn't
+/// depend on which thread finishes first. With a novelty bonus, scores are adjusted
+/// against the whole population once every match has finished.
+fn play_population_matches<B: Backend>(
+    population: &mut [BotBrain<B>],
+    device: &B::Device,
+    settings: MatchSettings,
+    shuffle_seed: u64,
+    match_seed: u64,
+    team_size: usize,
+) -> GenerationResult<B> {
+    let MatchSettings {
+        format,
+        map,
+        rewards,
+        reporter,
+    } = settings;
+    let match_size = 2 * team_size;
+    let results = Arc::new(Mutex::new(HashMap::new()));
+    let map_hash = map.content_hash();
+
+    fastrand::Rng::with_seed(shuffle_seed).shuffle(population);
+    let fingerprints: Vec<u64> = population.iter().map(BotBrain::fingerprint).collect();
+    let order = arrange_matches(&fingerprints, team_size);
+    let arranged: Vec<_> = order.iter().map(|&i| population[i].clone()).collect();
+    population.clone_from_slice(&arranged);
+
+    let keys: Vec<MatchKey> = order
+        .chunks_exact(match_size)
+        .map(|chunk| {
+            let (blue, red) = chunk.split_at(team_size);
+            let prints = |slots: &[usize]| slots.iter().map(|&i| fingerprints[i]).collect();
+            MatchKey {
+                blue: prints(blue),
+                red: prints(red),
+                seed: match_seed,
+                map: map_hash,
             }
-        });
+        })
+        .collect();
+    let unique = unique_matches(&keys);
+    if unique.len() < keys.len() {
+        reporter.note(&format!(
+            "  > Reusing results for {} repeated line-ups",
+            keys.len() - unique.len()
+        ));
+    }
 
-        let next_gen_parents = Arc::try_unwrap(next_gen_parents)
-            .unwrap()
-            .into_inner()
-            .unwrap();
+    thread::scope(|s| {
+        for &match_idx in &unique {
+            let match_chunk = &population[match_idx * match_size..][..match_size];
+            let results_handle = results.clone();
+            let key = keys[match_idx].clone();
+            let device = device.clone();
+            let blue_team: Vec<_> = match_chunk[..team_size]
+                .iter()
+                .map(|b| Driver::Brain(Box::new(b.clone())))
+                .collect();
+            let red_team: Vec<_> = match_chunk[team_size..]
+                .iter()
+                .map(|b| Driver::Brain(Box::new(b.clone())))
+                .collect();
 
-        let mut new_pop = Vec::with_capacity(args.population_size);
+            s.spawn(move || {
+                let stats = run_match(
+                    &blue_team, &red_team, map, &device, format, rewards, match_seed,
+                );
 
-        for parent in &next_gen_parents {
-            new_pop.push(parent.clone());
+                reporter.match_finished(&match_summary(match_idx, &stats));
+                results_handle.lock().unwrap().insert(key, stats);
+            });
         }
+    });
 
-        let mut rng = rand::rng();
-        if next_gen_parents.is_empty() {
-            println!("  ! Extinction (No Winners). Resetting population.");
-            new_pop = (0..args.population_size)
-                .map(|_| BotBrain::new(&device))
-                .collect();
+    let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+    let mut outcomes: Vec<(usize, Vec<BotStats>)> = keys
+        .iter()
+        .enumerate()
+        .map(|(match_idx, key)| (match_idx, results[key].clone()))
+        .collect();
+    for (match_idx, stats) in &outcomes {
+        if !unique.contains(match_idx) {
+            reporter.match_finished(&match_summary(*match_idx, stats));
+        }
+    }
+
+    if rewards.novelty.bonus > 0.0 {
+        let behaviors: Vec<Behavior> = outcomes
+            .iter()
+            .flat_map(|(_, stats)| stats.iter().map(|s| s.behavior.clone()))
+            .collect();
+        let novelty = novelty_scores(&behaviors, &rewards.novelty);
+        let stats = outcomes.iter_mut().flat_map(|(_, stats)| stats.iter_mut());
+        for (stat, novelty) in stats.zip(novelty) {
+            stat.total_score += rewards.novelty.bonus * novelty;
+        }
+    }
+
+    let scores: Vec<f32> = outcomes
+        .iter()
+        .flat_map(|(_, stats)| stats.iter().map(|s| s.total_score))
+        .collect();
+    let mean_fitness = scores.iter().sum::<f32>() / scores.len().max(1) as f32;
+
+    let mut parents = Vec::new();
+    let mut best_fitness = f32::NEG_INFINITY;
+    for (match_idx, stats) in &outcomes {
+        let mut candidates: Vec<&BotStats> = if let Some(winner) = match_winner(stats) {
+            stats.iter().filter(|s| s.team == winner).collect()
         } else {
-            while new_pop.len() < args.population_size {
-                let parent = &next_gen_parents[rng.random_range(0..next_gen_parents.len())];
-                new_pop.push(parent.mutate(args.mutation_rate));
-            }
+            stats.iter().collect()
+        };
+
+        candidates.sort_by(|a, b| b.total_score.partial_cmp(&a.total_score).unwrap());
+
+        if let Some(best) = candidates.first() {
+            best_fitness = best_fitness.max(best.total_score);
         }
 
-        population = new_pop;
-        println!("Gen {} Complete. Saving to {}", gen, model_path_str);
+        for stat in candidates.iter().take(2) {
+            parents.push(Parent {
+                brain: population[match_idx * match_size + stat.original_index].clone(),
+                fitness: stat.total_score,
+            });
+        }
+    }
 
-        // --- Atomic Save ---
-        // Save to a temporary file first, then rename to ensure the client doesn't read a partial file.
-        // Burn's save_file appends .bin, so if we provide "name_tmp", it writes "name_tmp.bin"
+    GenerationResult {
+        parents,
+        best_fitness,
+        mean_fitness,
+    }
+}
 
-        let temp_name = format!("{}_tmp", args.model_name);
+/// Fitness of one bot when evaluated against a fixed opponent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OpponentFitness {
+    index: usize,
+    win_rate: f32,
+    score: f32,
+}
 
-        // Construct paths using PathBuf for robustness
-        let temp_file_path = models_dir.join(format!("{}.bin", temp_name));
-        let final_file_path = models_dir.join(format!("{}.bin", args.model_name));
+/// Asymmetric training: every squad of `team_size` plays the opponent once from each side, and
+/// the bots with the best win-rate against it (ties broken by score) become parents.
+fn play_against_opponent<B: Backend>(
+    population: &[BotBrain<B>],
+    opponent: &Driver<B>,
+    device: &B::Device,
+    settings: MatchSettings,
+    match_seed: u64,
+    team_size: usize,
+) -> GenerationResult<B> {
+    let MatchSettings {
+        format,
+        map,
+        rewards,
+        reporter,
+    } = settings;
+    let results = Arc::new(Mutex::new(Vec::new()));
+
+    thread::scope(|s| {
+        for (squad_idx, squad) in population.chunks(team_size).enumerate() {
+            let results_handle = results.clone();
+            let device = device.clone();
+            let ours: Vec<_> = squad
+                .iter()
+                .map(|b| Driver::Brain(Box::new(b.clone())))
+                .collect();
+            let theirs: Vec<_> = vec![opponent.clone(); team_size];
 
-        // Base path string for Burn (it appends .bin)
-        let temp_base_path = models_dir.join(&temp_name);
-        let temp_base_str = temp_base_path.to_str().expect("Invalid path string");
+            s.spawn(move || {
+                let mut wins = vec![0u32; ours.len()];
+                let mut scores = vec![0.0f32; ours.len()];
+                let mut behaviors = vec![Vec::new(); ours.len()];
 
-        if population[0]
-            .clone()
-            .save_file(temp_base_str, &recorder)
-            .is_ok()
-        {
-            let _ = std::fs::rename(temp_file_path, final_file_path);
+                for (side, squad_team) in [Team::Blue, Team::Red].into_iter().enumerate() {
+                    let (blue, red) = if squad_team == Team::Blue {
+                        (&ours, &theirs)
+                    } else {
+                        (&theirs, &ours)
+                    };
+                    let seed = match_seed.wrapping_add((squad_idx * 2 + side) as u64);
+                    let stats = run_match(blue, red, map, &device, format, rewards, seed);
+                    reporter.match_finished(&match_summary(squad_idx * 2 + side, &stats));
+                    let won = match_winner(&stats) == Some(squad_team);
+
+                    for stat in stats.iter().filter(|s| s.team == squad_team) {
+                        let member = stat.original_index % team_size;
+                        if member < ours.len() {
+                            wins[member] += u32::from(won);
+                            scores[member] += stat.total_score;
+                            behaviors[member].push(stat.behavior.clone());
+                        }
+                    }
+                }
+
+                let mut guard = results_handle.lock().unwrap();
+                for (i, ((wins, score), behaviors)) in
+                    wins.into_iter().zip(scores).zip(behaviors).enumerate()
+                {
+                    let fitness = OpponentFitness {
+                        index: squad_idx * team_size + i,
+                        win_rate: wins as f32 / 2.0,
+                        score,
+                    };
+                    guard.push((fitness, behaviors));
+                }
+            });
         }
+    });
+
+    let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+    select_parents(
+        population,
+        results,
+        rewards,
+        population.len() / team_size,
+        "vs opponent",
+        reporter,
+    )
+}
+
+/// How many times every squad plays a scenario per generation, each time with another seed.
+const SCENARIO_ROUNDS: usize = 2;
+
+/// Curriculum training: squads of the population drive the scenario's Learner tanks, and
+/// the bots whose side wins most often (ties broken by score) become parents.
+fn play_scenario<B: Backend>(
+    population: &[BotBrain<B>],
+    scenario: &Scenario,
+    device: &B::Device,
+    settings: MatchSettings,
+    match_seed: u64,
+) -> GenerationResult<B> {
+    let MatchSettings {
+        format,
+        rewards,
+        reporter,
+        ..
+    } = settings;
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let squad_size = scenario.learner_count();
+    let learner_team = scenario.learner_team();
+
+    thread::scope(|s| {
+        for (squad_idx, squad) in population.chunks(squad_size).enumerate() {
+            let results_handle = results.clone();
+            let device = device.clone();
+            let ours: Vec<_> = squad
+                .iter()
+                .map(|b| Driver::Brain(Box::new(b.clone())))
+                .collect();
+
+            s.spawn(move || {
+                // Learner tanks take the squad's bots in order, repeating them in a short squad
+                let mut learners = ours.iter().cycle();
+                let mut members = Vec::new();
+                let drivers: Vec<Driver<B>> = scenario
+                    .tanks
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, tank)| match tank.driver {
+                        ScenarioDriver::Learner => {
+                            members.push(slot);
+                            learners.next().unwrap().clone()
+                        }
+                        ScenarioDriver::Scripted(difficulty) => Driver::Scripted(difficulty),
+                    })
+                    .collect();
+                let drivers: Vec<&Driver<B>> = drivers.iter().collect();
+
+                let mut wins = vec![0u32; ours.len()];
+                let mut scores = vec![0.0f32; ours.len()];
+                let mut behaviors = vec![Vec::new(); ours.len()];
+                for round in 0..SCENARIO_ROUNDS {
+                    let seed =
+                        match_seed.wrapping_add((squad_idx * SCENARIO_ROUNDS + round) as u64);
+                    let stats = play_match(
+                        scenario.spawn(),
+                        &drivers,
+                        &device,
+                        rewards,
+                        seed,
+                        |engine, tick, kills| {
+                            scenario.is_over(engine, tick, kills, format.max_ticks)
+                        },
+                    );
+                    let survivors = stats.iter().filter(|s| s.alive).map(|s| s.team);
+                    let winner = scenario.winner(survivors);
+                    let won = winner == learner_team;
+                    reporter.match_finished(&MatchSummary {
+                        winner,
+                        ..match_summary(squad_idx * SCENARIO_ROUNDS + round, &stats)
+                    });
+
+                    for (i, &slot) in members.iter().enumerate() {
+                        let member = i % ours.len();
+                        wins[member] += u32::from(won);
+                        scores[member] += stats[slot].total_score;
+                        behaviors[member].push(stats[slot].behavior.clone());
+                    }
+                }
+
+                let mut guard = results_handle.lock().unwrap();
+                for (i, ((wins, score), behaviors)) in
+                    wins.into_iter().zip(scores).zip(behaviors).enumerate()
+                {
+                    let fitness = OpponentFitness {
+                        index: squad_idx * squad_size + i,
+                        win_rate: wins as f32 / SCENARIO_ROUNDS as f32,
+                        score,
+                    };
+                    guard.push((fitness, behaviors));
+                }
+            });
+        }
+    });
+
+    let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+    select_parents(
+        population,
+        results,
+        rewards,
+        population.len().div_ceil(squad_size),
+        "in scenario",
+        reporter,
+    )
+}
+
+/// Turns the fitness of every bot, with the behaviors seen in its matches, into the
+/// generation's parents: adds the novelty bonus, then keeps the best `count` by win-rate.
+fn select_parents<B: Backend>(
+    population: &[BotBrain<B>],
+    mut results: Vec<(OpponentFitness, Vec<Behavior>)>,
+    rewards: &RewardConfig,
+    count: usize,
+    label: &str,
+    reporter: &dyn Reporter,
+) -> GenerationResult<B> {
+    results.sort_by_key(|(fitness, _)| fitness.index);
+
+    if rewards.novelty.bonus > 0.0 {
+        // Each bot played twice; its novelty is the average over both matches.
+        let behaviors: Vec<Behavior> = results
+            .iter()
+            .flat_map(|(_, behaviors)| behaviors.iter().cloned())
+            .collect();
+        let mut novelty = novelty_scores(&behaviors, &rewards.novelty).into_iter();
+        for (fitness, behaviors) in &mut results {
+            let total: f32 = novelty.by_ref().take(behaviors.len()).sum();
+            fitness.score += rewards.novelty.bonus * total / behaviors.len().max(1) as f32;
+        }
+    }
+
+    let results: Vec<OpponentFitness> = results.into_iter().map(|(fitness, _)| fitness).collect();
+    let mean_fitness =
+        results.iter().map(|f| f.win_rate).sum::<f32>() / results.len().max(1) as f32;
+    let parents = select_by_win_rate(results, count);
+
+    if let Some(best) = parents.first() {
+        reporter.note(&format!(
+            "  > Best win-rate {}: {:.0}% (score {:.0})",
+            label,
+            best.win_rate * 100.0,
+            best.score
+        ));
+    }
+
+    GenerationResult {
+        best_fitness: parents.first().map_or(0.0, |f| f.win_rate),
+        mean_fitness,
+        parents: parents
+            .into_iter()
+            .filter(|f| f.win_rate > 0.0 || f.score > 0.0)
+            .map(|f| Parent {
+                brain: population[f.index].clone(),
+                fitness: f.win_rate,
+            })
+            .collect(),
     }
 }
 
-#[derive(Debug)]
+/// Orders bots by win-rate (then score, then index) and keeps the best `count`.
+fn select_by_win_rate(mut results: Vec<OpponentFitness>, count: usize) -> Vec<OpponentFitness> {
+    results.sort_by(|a, b| {
+        b.win_rate
+            .total_cmp(&a.win_rate)
+            .then(b.score.total_cmp(&a.score))
+            .then(a.index.cmp(&b.index))
+    });
+    results.truncate(count.max(1));
+    results
+}
+
+fn team_kills(stats: &[BotStats], team: Team) -> i32 {
+    stats
+        .iter()
+        .filter(|s| s.team == team)
+        .map(|s| s.kills)
+        .sum()
+}
+
+/// What the reporters are told about match `index`, added up from its bots' `stats`.
+fn match_summary(index: usize, stats: &[BotStats]) -> MatchSummary {
+    MatchSummary {
+        index,
+        blue_kills: team_kills(stats, Team::Blue),
+        red_kills: team_kills(stats, Team::Red),
+        winner: match_winner(stats),
+        best_score: stats
+            .iter()
+            .map(|s| s.total_score)
+            .fold(f32::NEG_INFINITY, f32::max),
+        friendly_fire: stats.iter().map(|s| s.friendly_kills).sum(),
+        ticks: stats.first().map_or(0, |s| s.ticks),
+    }
+}
+
+/// Team with more rounds won wins; survivors of the last round, then kills break ties.
+fn match_winner(stats: &[BotStats]) -> Option<Team> {
+    let rounds_won = |team| {
+        stats
+            .iter()
+            .filter(|s| s.team == team)
+            .map(|s| s.rounds_won)
+            .max()
+            .unwrap_or(0)
+    };
+    let (blue_rounds, red_rounds) = (rounds_won(Team::Blue), rounds_won(Team::Red));
+    let alive = |team| stats.iter().filter(|s| s.team == team && s.alive).count();
+    let (blue_alive, red_alive) = (alive(Team::Blue), alive(Team::Red));
+    let (blue_kills, red_kills) = (team_kills(stats, Team::Blue), team_kills(stats, Team::Red));
+
+    if blue_rounds > red_rounds {
+        Some(Team::Blue)
+    } else if red_rounds > blue_rounds {
+        Some(Team::Red)
+    } else if blue_alive > red_alive {
+        Some(Team::Blue)
+    } else if red_alive > blue_alive {
+        Some(Team::Red)
+    } else if blue_kills > red_kills {
+        Some(Team::Blue)
+    } else if red_kills > blue_kills {
+        Some(Team::Red)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
 struct BotStats {
     original_index: usize,
     team: Team,
     kills: i32,
     friendly_kills: i32,
+    /// Damage dealt to enemies
+    damage_dealt: f32,
     alive: bool,
+    rounds_won: u32,
     total_score: f32,
+    behavior: Behavior,
+    /// Ticks the match lasted, the same for every bot in it
+    ticks: usize,
 }
 
-fn run_4v4_match<B: Backend>(
-    blue_brains: &[BotBrain<B>],
-    red_brains: &[BotBrain<B>],
+/// Plays one match of `format.rounds` rounds between equally sized teams; stats are
+/// indexed by slot (Blue first, then Red) and summed over the rounds. Between rounds
+/// tanks buy upgrades with the points they earned. All randomness inside the match is
+/// derived from `seed`.
+fn run_match<B: Backend>(
+    blue_drivers: &[Driver<B>],
+    red_drivers: &[Driver<B>],
+    map: &MapDefinition,
     device: &B::Device,
-    max_ticks: usize,
+    format: MatchFormat,
+    rewards: &RewardConfig,
+    seed: u64,
 ) -> Vec<BotStats> {
-    let mut engine = GameEngine::new(MapDefinition::load());
+    let drivers: Vec<&Driver<B>> = blue_drivers.iter().chain(red_drivers).collect();
+    let mut loadouts = vec![Loadout::default(); drivers.len()];
+    let mut points = vec![0u32; drivers.len()];
+    let mut match_stats: Vec<BotStats> = Vec::new();
+    let mut behaviors = vec![Vec::new(); drivers.len()];
+
+    for round in 0..format.rounds {
+        let engine = spawn_teams(blue_drivers.len(), red_drivers.len(), map, &loadouts);
+        let mut stats = play_match(
+            engine,
+            &drivers,
+            device,
+            rewards,
+            seed.wrapping_add(round as u64),
+            |engine, tick, _| {
+                tick >= format.max_ticks
+                    || [Team::Blue, Team::Red].into_iter().any(|team| {
+                        !engine
+                            .tanks
+                            .iter()
+                            .any(|p| p.player_info.team == team && p.health > 0.0)
+                    })
+            },
+        );
 
-    // Spawn Blue (Face East 0.0)
-    for (i, _) in blue_brains.iter().enumerate() {
-        if i + 4 < engine.map.spawn_points.len() {
-            let spawn = engine.map.spawn_points[i + 4].1;
-            engine.tanks.push(Tank::new(
-                common::game::player::PlayerInfo::new(
-                    i as PlayerId,
-                    format!("Blue_{}", i),
-                    Team::Blue,
-                ),
-                spawn,
-            ));
+        let winner = match_winner(&stats);
+        for (slot, stat) in stats.iter_mut().enumerate() {
+            if Some(stat.team) == winner {
+                stat.rounds_won = 1;
+                stat.total_score += rewards.round_win_bonus;
+            }
+            // Points as the intermission shop counts them
+            points[slot] += stat.damage_dealt as u32 + KILL_POINTS * stat.kills as u32;
+            loadouts[slot].buy_cheapest(&mut points[slot]);
+            behaviors[slot].push(stat.behavior.clone());
+        }
+
+        if match_stats.is_empty() {
+            match_stats = stats;
+        } else {
+            for (total, round) in match_stats.iter_mut().zip(stats) {
+                total.kills += round.kills;
+                total.friendly_kills += round.friendly_kills;
+                total.damage_dealt += round.damage_dealt;
+                total.alive = round.alive;
+                total.rounds_won += round.rounds_won;
+                total.total_score += round.total_score;
+                total.ticks += round.ticks;
+            }
         }
     }
+
+    for (stat, behaviors) in match_stats.iter_mut().zip(&behaviors) {
+        stat.behavior = Behavior::mean(behaviors);
+    }
+    match_stats
+}
+
+/// A fresh engine with both teams at their spawns, tank `i` having id `i` and the
+/// upgrades of `loadouts[i]`.
+fn spawn_teams(
+    team_size: usize,
+    red_size: usize,
+    map: &MapDefinition,
+    loadouts: &[Loadout],
+) -> GameEngine {
+    let mut engine = GameEngine::new(map.clone());
+
+    // Spawn Blue (Face East 0.0)
+    for (i, spawn) in map
+        .team_spawns(Team::Blue, team_size)
+        .into_iter()
+        .enumerate()
+    {
+        engine.tanks.push(Tank::new(
+            common::game::player::PlayerInfo::new(i as PlayerId, format!("Blue_{}", i), Team::Blue),
+            spawn,
+        ));
+    }
     // Spawn Red (Face West PI)
-    for (i, _) in red_brains.iter().enumerate() {
-        if i < engine.map.spawn_points.len() {
-            let spawn = engine.map.spawn_points[i].1;
-            let mut p = Tank::new(
-                common::game::player::PlayerInfo::new(
-                    (i + 4) as PlayerId,
-                    format!("Red_{}", i),
-                    Team::Red,
-                ),
-                spawn,
-            );
-            p.rotation = std::f32::consts::PI;
-            engine.tanks.push(p);
-        }
+    for (i, spawn) in map.team_spawns(Team::Red, red_size).into_iter().enumerate() {
+        let mut p = Tank::new(
+            common::game::player::PlayerInfo::new(
+                (i + team_size) as PlayerId,
+                format!("Red_{}", i),
+                Team::Red,
+            ),
+            spawn,
+        );
+        p.rotation = std::f32::consts::PI;
+        engine.tanks.push(p);
+    }
+
+    for (tank, loadout) in engine.tanks.iter_mut().zip(loadouts) {
+        tank.apply_loadout(loadout);
+        engine.loadouts.insert(tank.player_info.id, *loadout);
     }
+    engine
+}
 
-    let mut stats: Vec<BotStats> = (0..8)
+/// Plays out `engine`, where tank `i` has id `i` and is controlled by `drivers[i]`, until
+/// `is_over(engine, ticks, kills)` holds. Stats are indexed by tank id.
+fn play_match<B: Backend>(
+    mut engine: GameEngine,
+    drivers: &[&Driver<B>],
+    device: &B::Device,
+    rewards: &RewardConfig,
+    seed: u64,
+    is_over: impl Fn(&GameEngine, usize, usize) -> bool,
+) -> Vec<BotStats> {
+    let slots = drivers.len();
+    let teams: Vec<Team> = engine.tanks.iter().map(|t| t.player_info.team).collect();
+    let team_of = |slot: usize| teams[slot];
+    let driver_of = |slot: usize| drivers[slot];
+
+    // Scripted drivers keep their own state (paths, RNG) for the whole match.
+    let mut scripted: std::collections::HashMap<PlayerId, BotAgent> = engine
+        .tanks
+        .iter()
+        .filter_map(|tank| {
+            let id = tank.player_info.id as usize;
+            match driver_of(id) {
+                Driver::Scripted(difficulty) => Some((
+                    tank.player_info.id,
+                    BotAgent::new(tank.player_info.clone(), *difficulty, seed ^ id as u64),
+                )),
+                Driver::Brain(_) => None,
+            }
+        })
+        .collect();
+
+    let mut stats: Vec<BotStats> = (0..slots)
         .map(|i| BotStats {
             original_index: i,
-            team: if i < 4 { Team::Blue } else { Team::Red },
+            team: team_of(i),
             kills: 0,
             friendly_kills: 0,
+            damage_dealt: 0.0,
             alive: true,
+            rounds_won: 0,
             total_score: 0.0,
+            behavior: Behavior::default(),
+            ticks: 0,
         })
         .collect();
+    let mut trackers = vec![BehaviorTracker::default(); slots];
 
-    for _tick in 0..max_ticks {
-        let blue_cnt = engine
-            .tanks
-            .iter()
-            .filter(|p| p.player_info.team == Team::Blue && p.health > 0.0)
-            .count();
-        let red_cnt = engine
-            .tanks
-            .iter()
-            .filter(|p| p.player_info.team == Team::Red && p.health > 0.0)
-            .count();
-        if blue_cnt == 0 || red_cnt == 0 {
+    let mut rng = BotRng::seed_from_u64(seed);
+    let mut deaths = 0;
+
+    let mut ticks = 0;
+    for tick in 0.. {
+        if is_over(&engine, tick, deaths) {
+            ticks = tick;
             break;
         }
 
         let mut inputs = std::collections::HashMap::new();
-        let mut rng = StdRng::seed_from_u64(0);
 
-        for (i, player) in engine.tanks.iter().enumerate() {
+        for player in engine.tanks.iter() {
             if player.health <= 0.0 {
                 continue;
             }
+            if let Some(tracker) = trackers.get_mut(player.player_info.id as usize) {
+                tracker.observe(player.position, player.speed, &engine.map);
+            }
+
+            let id = player.player_info.id;
+            if let Some(agent) = scripted.get_mut(&id) {
+                let input = agent.generate_input(
+                    player,
+                    &engine.tanks,
+                    &engine.projectiles,
+                    &engine.map,
+                    0.033,
+                    engine.blackboard.ping_for(player, &engine.map),
+                );
+                inputs.insert(id, input);
+                continue;
+            }
 
             let ctx = BotContext {
                 me: player,
@@ -348,98 +1399,228 @@ fn run_4v4_match<B: Backend>(
                 map: &engine.map,
                 dt: 0.033,
                 rng: &mut rng,
+                ping: engine.blackboard.ping_for(player, &engine.map),
             };
-            let brain = if i < 4 {
-                &blue_brains[i]
-            } else {
-                &red_brains[i - 4]
+            let Driver::Brain(brain) = driver_of(id as usize) else {
+                continue;
             };
-            let output = brain.forward(extract_features(&ctx, device));
+            let output = brain.forward(extract_features_with(&ctx, brain.layout(), device));
             let values = output.into_data().to_vec::<f32>().unwrap();
-            inputs.insert(player.player_info.id, action_to_input(&values, &ctx));
+            inputs.insert(player.player_info.id, action_to_input(&values, ctx.me));
         }
 
+        let first_new_projectile = engine.projectile_id_counter;
         let result = engine.tick(0.033, inputs);
 
+        for projectile in &engine.projectiles {
+            if projectile.id >= first_new_projectile {
+                if let Some(tracker) = trackers.get_mut(projectile.owner_info.id as usize) {
+                    tracker.record_shot();
+                }
+            }
+        }
+
         for dmg in result.damage {
-            let victim_team = if dmg.victim_id < 4 {
-                Team::Blue
-            } else {
-                Team::Red
-            };
+            let victim_team = team_of(dmg.victim_id as usize);
             if let Some(attacker) = stats.get_mut(dmg.attacker_id as usize) {
                 if attacker.team != victim_team {
-                    attacker.total_score += dmg.amount;
+                    attacker.damage_dealt += dmg.amount;
+                    attacker.total_score += dmg.amount * rewards.enemy_damage;
                 } else {
-                    attacker.total_score -= dmg.amount * 2.0;
+                    attacker.total_score -= dmg.amount * rewards.friendly_damage_penalty;
                     attacker.friendly_kills += 1;
                 }
             }
         }
 
+        deaths += result.kills.len();
         for kill in result.kills {
-            let victim_team = if kill.victim_info.id < 4 {
-                Team::Blue
-            } else {
-                Team::Red
-            };
+            let victim_team = team_of(kill.victim_info.id as usize);
             if let Some(killer) = stats.get_mut(kill.killer_info.id as usize) {
                 if killer.team != victim_team {
                     killer.kills += 1;
-                    killer.total_score += 500.0;
+                    killer.total_score += rewards.kill_bonus;
                 } else {
-                    killer.total_score -= 1000.0;
+                    killer.total_score -= rewards.teamkill_penalty;
                 }
             }
         }
     }
 
-    for player in &engine.tanks {
-        if player.player_info.id < 8 {
-            stats[player.player_info.id as usize].alive = player.health > 0.0;
-        }
+    // The engine drops tanks as they die
+    for (slot, stat) in stats.iter_mut().enumerate() {
+        stat.alive = engine
+            .tanks
+            .iter()
+            .any(|p| p.player_info.id as usize == slot && p.health > 0.0);
+    }
+    for (stat, tracker) in stats.iter_mut().zip(&trackers) {
+        stat.behavior = tracker.finish(0.033);
+        stat.ticks = ticks;
     }
     stats
 }
 
-fn action_to_input(actions: &[f32], ctx: &BotContext) -> InputPayload {
-    let move_fwd = actions[0].tanh();
-    let move_side = actions[1].tanh();
-    let aim_fwd = actions[2];
-    let aim_side = actions[3];
-    let shoot_val = actions[4];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn stat(slot: usize, kills: i32, alive: bool) -> BotStats {
+        BotStats {
+            original_index: slot,
+            team: if slot < 4 { Team::Blue } else { Team::Red },
+            kills,
+            friendly_kills: 0,
+            damage_dealt: 0.0,
+            alive,
+            rounds_won: 0,
+            total_score: 0.0,
+            behavior: Behavior::default(),
+            ticks: 0,
+        }
+    }
+
+    #[test]
+    fn test_match_winner_prefers_survivors_then_kills() {
+        let more_alive = vec![stat(0, 0, true), stat(1, 0, true), stat(4, 3, true)];
+        assert_eq!(match_winner(&more_alive), Some(Team::Blue));
 
-    let (sin, cos) = ctx.me.rotation.sin_cos();
+        let more_kills = vec![stat(0, 1, true), stat(4, 2, true)];
+        assert_eq!(match_winner(&more_kills), Some(Team::Red));
 
-    let world_move = glam::Vec2::new(
-        move_fwd * cos - move_side * sin,
-        move_fwd * sin + move_side * cos,
-    );
+        let even = vec![stat(0, 1, false), stat(4, 1, false)];
+        assert_eq!(match_winner(&even), None);
 
-    let world_aim_dir = glam::Vec2::new(
-        aim_fwd * cos - aim_side * sin,
-        aim_fwd * sin + aim_side * cos,
-    );
+        let mut more_rounds = more_alive.clone();
+        more_rounds[2].rounds_won = 2;
+        more_rounds[0].rounds_won = 1;
+        assert_eq!(match_winner(&more_rounds), Some(Team::Red));
+    }
 
-    let final_aim_dir = if world_aim_dir.length_squared() < 0.001 {
-        glam::Vec2::new(cos, sin)
-    } else {
-        world_aim_dir.normalize()
-    };
+    #[test]
+    fn test_rounds_carry_upgrades_over() {
+        let device = Default::default();
+        let drivers = vec![Driver::<NdArray>::Scripted(BotDifficulty::Terminator); 2];
+        let dummies = vec![Driver::<NdArray>::Scripted(BotDifficulty::Dummy); 2];
+        let format = MatchFormat {
+            max_ticks: 300,
+            rounds: 3,
+        };
+        let stats = run_match(
+            &drivers,
+            &dummies,
+            &MapDefinition::load(),
+            &device,
+            format,
+            &RewardConfig {
+                round_win_bonus: 1000.0,
+                ..Default::default()
+            },
+            7,
+        );
+        assert_eq!(stats.len(), 4);
+        assert!(stats[0].rounds_won + stats[2].rounds_won <= 3);
+        // Dummies never shoot, so all they score is the bonus for the rounds they won
+        for dummy in &stats[2..] {
+            assert_eq!(dummy.damage_dealt, 0.0);
+            assert_eq!(dummy.total_score, 1000.0 * dummy.rounds_won as f32);
+        }
+
+        let loadouts = [Loadout {
+            speed: 2,
+            ..Default::default()
+        }];
+        let engine = spawn_teams(1, 1, &MapDefinition::load(), &loadouts);
+        assert!(engine.tanks[0].upgrade_level(common::net::protocol::Upgrade::Speed) > 1.9);
+        assert_eq!(engine.loadouts.len(), 1);
+    }
+
+    #[test]
+    fn test_brains_evolve_and_play_on_candle() {
+        let device = Default::default();
+        let brain = BotBrain::<Candle>::new(&device);
+        let child = brain.mutate(0.05);
+        assert_ne!(brain.fingerprint(), child.fingerprint());
+
+        let brains = vec![
+            Driver::Brain(Box::new(brain)),
+            Driver::Brain(Box::new(child)),
+        ];
+        let dummies = vec![Driver::<Candle>::Scripted(BotDifficulty::Dummy); 2];
+        let format = MatchFormat {
+            max_ticks: 30,
+            rounds: 1,
+        };
+        let stats = run_match(
+            &brains,
+            &dummies,
+            &MapDefinition::load(),
+            &device,
+            format,
+            &RewardConfig::default(),
+            3,
+        );
+        assert_eq!(stats.len(), 4);
+    }
+
+    #[test]
+    fn test_select_by_win_rate_orders_by_win_rate_then_score() {
+        let fitness = |index, win_rate, score| OpponentFitness {
+            index,
+            win_rate,
+            score,
+        };
+        let results = vec![
+            fitness(0, 0.5, 100.0),
+            fitness(1, 1.0, 10.0),
+            fitness(2, 0.5, 900.0),
+            fitness(3, 0.0, 5000.0),
+        ];
+
+        let selected = select_by_win_rate(results, 3);
+        let order: Vec<usize> = selected.iter().map(|f| f.index).collect();
+        assert_eq!(order, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_model_opponents_keep_their_own_layout() {
+        let device = Default::default();
+        let recorder = BinFileRecorder::<FullPrecisionSettings>::default();
+        let dir = std::env::temp_dir().join("trainer_opponent_layouts");
+        fs::create_dir_all(&dir).unwrap();
+
+        let layout = FeatureLayout::for_team_size(8).with_ping();
+        BotBrain::<NdArray>::with_layout(&device, layout)
+            .save_file(dir.join("big"), &recorder)
+            .unwrap();
+        let spec = OpponentSpec::Model("big".into());
+        match load_driver::<NdArray>(&spec, &dir, &device, &recorder) {
+            Ok(Driver::Brain(brain)) => assert_eq!(brain.stored_layout(), Some(layout)),
+            _ => panic!("the model should load"),
+        }
 
-    let aim_pos = ctx.me.position + (final_aim_dir * 100.0);
+        let mut odd = BotBrain::<NdArray>::new(&device);
+        odd.linear1 = burn::nn::LinearConfig::new(7, odd.linear1.weight.dims()[1]).init(&device);
+        odd.save_file(dir.join("odd"), &recorder).unwrap();
+        let spec = OpponentSpec::Model("odd".into());
+        let error = load_driver::<NdArray>(&spec, &dir, &device, &recorder).err();
+        assert!(error.unwrap().contains("takes 7 inputs"));
 
-    InputPayload {
-        move_axis: world_move,
-        aim_pos,
-        shoot: shoot_val > 0.0,
+        fs::remove_dir_all(&dir).unwrap();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
+    #[test]
+    fn test_load_scenario_by_name_or_path() {
+        let assets = resolve_assets_path(&env::current_dir().unwrap());
+        let scenario = load_scenario("corner_2v1", &assets).unwrap();
+        assert_eq!(scenario.learner_count(), 2);
+
+        let path = assets.join("scenarios").join("corner_2v1.ron");
+        let by_path = load_scenario(path.to_str().unwrap(), Path::new("nowhere")).unwrap();
+        assert_eq!(by_path, scenario);
+        assert!(load_scenario("no_such_scenario", &assets).is_err());
+    }
 
     #[test]
     fn test_resolve_assets_path_logic_deeply_nested() {
@@ -0,0 +1,308 @@
+//! Per-generation training metrics, to tell whether a run is converging.
+//!
+//! [`MetricsRecorder`] sits in front of another [`Reporter`]: it adds up the matches of
+//! every generation, writes one row per generation to a CSV or JSON Lines file, and can
+//! add a summary line to what the user sees.
+
+use crate::progress::{GenerationSummary, MatchSummary, Reporter};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Generations the fitness trend in the summary line looks back over.
+const TREND_WINDOW: usize = 10;
+
+const CSV_HEADER: &str = "generation,best_fitness,mean_fitness,win_rate,matches,kills,friendly_fire,mean_match_ticks,elapsed_secs";
+
+/// How metrics files are written, told apart by their extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl MetricsFormat {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Ok(Self::Csv),
+            Some("jsonl") => Ok(Self::JsonLines),
+            _ => Err(format!(
+                "Metrics file {} must end in .csv or .jsonl",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// What one generation's matches added up to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationMetrics {
+    pub generation: usize,
+    pub best_fitness: f32,
+    pub mean_fitness: f32,
+    pub win_rate: Option<f32>,
+    pub matches: usize,
+    pub kills: i32,
+    /// Hits on teammates
+    pub friendly_fire: i32,
+    pub mean_match_ticks: f32,
+    pub elapsed_secs: f32,
+}
+
+impl GenerationMetrics {
+    pub fn new(summary: &GenerationSummary, matches: &[MatchSummary]) -> Self {
+        let ticks: usize = matches.iter().map(|m| m.ticks).sum();
+        Self {
+            generation: summary.generation,
+            best_fitness: summary.best_fitness,
+            mean_fitness: summary.mean_fitness,
+            win_rate: summary.win_rate,
+            matches: matches.len(),
+            kills: matches.iter().map(|m| m.blue_kills + m.red_kills).sum(),
+            friendly_fire: matches.iter().map(|m| m.friendly_fire).sum(),
+            mean_match_ticks: ticks as f32 / matches.len().max(1) as f32,
+            elapsed_secs: summary.elapsed.as_secs_f32(),
+        }
+    }
+
+    /// The generation as a row under [`CSV_HEADER`]; unknown values are left empty.
+    fn csv_row(&self) -> String {
+        let number = |v: Option<f32>| {
+            v.filter(|v| v.is_finite())
+                .map_or(String::new(), |v| v.to_string())
+        };
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.generation,
+            number(Some(self.best_fitness)),
+            number(Some(self.mean_fitness)),
+            number(self.win_rate),
+            self.matches,
+            self.kills,
+            self.friendly_fire,
+            number(Some(self.mean_match_ticks)),
+            number(Some(self.elapsed_secs)),
+        )
+    }
+
+    /// The generation as one JSON object; unknown values are `null`.
+    fn json_line(&self) -> String {
+        let number = |v: Option<f32>| {
+            v.filter(|v| v.is_finite())
+                .map_or("null".to_string(), |v| v.to_string())
+        };
+        format!(
+            "{{\"generation\":{},\"best_fitness\":{},\"mean_fitness\":{},\"win_rate\":{},\"matches\":{},\"kills\":{},\"friendly_fire\":{},\"mean_match_ticks\":{},\"elapsed_secs\":{}}}",
+            self.generation,
+            number(Some(self.best_fitness)),
+            number(Some(self.mean_fitness)),
+            number(self.win_rate),
+            self.matches,
+            self.kills,
+            self.friendly_fire,
+            number(Some(self.mean_match_ticks)),
+            number(Some(self.elapsed_secs)),
+        )
+    }
+}
+
+/// Where the rows go.
+struct MetricsFile {
+    writer: Box<dyn Write + Send>,
+    format: MetricsFormat,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    /// Matches of the generation being played
+    matches: Vec<MatchSummary>,
+    /// Best fitness of every finished generation
+    best: Vec<f32>,
+    /// Whether writing already failed once, to complain only once
+    failed: bool,
+}
+
+/// Records metrics of every generation, passing everything on to `inner`.
+pub struct MetricsRecorder {
+    inner: Box<dyn Reporter>,
+    file: Option<Mutex<MetricsFile>>,
+    /// Whether to add a summary line per generation to the output
+    summary: bool,
+    state: Mutex<MetricsState>,
+}
+
+impl MetricsRecorder {
+    /// Writes metrics to `path`, if given, replacing what it held before.
+    pub fn create(
+        inner: Box<dyn Reporter>,
+        path: Option<&Path>,
+        summary: bool,
+    ) -> Result<Self, String> {
+        let file = match path {
+            None => None,
+            Some(path) => {
+                let format = MetricsFormat::from_path(path)?;
+                let file = File::create(path).map_err(|e| {
+                    format!("Failed to create metrics file {}: {}", path.display(), e)
+                })?;
+                let mut writer: Box<dyn Write + Send> = Box::new(BufWriter::new(file));
+                if format == MetricsFormat::Csv {
+                    writeln!(writer, "{}", CSV_HEADER).map_err(|e| e.to_string())?;
+                }
+                Some(Mutex::new(MetricsFile { writer, format }))
+            }
+        };
+        Ok(Self {
+            inner,
+            file,
+            summary,
+            state: Mutex::default(),
+        })
+    }
+
+    /// Appends `metrics` to the file and flushes it, so the file can be followed live.
+    fn write(&self, metrics: &GenerationMetrics) -> std::io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let mut file = file.lock().unwrap();
+        let line = match file.format {
+            MetricsFormat::Csv => metrics.csv_row(),
+            MetricsFormat::JsonLines => metrics.json_line(),
+        };
+        writeln!(file.writer, "{}", line)?;
+        file.writer.flush()
+    }
+}
+
+impl Reporter for MetricsRecorder {
+    fn generation_started(&self, generation: usize, matches: usize) {
+        self.state.lock().unwrap().matches.clear();
+        self.inner.generation_started(generation, matches);
+    }
+
+    fn match_finished(&self, summary: &MatchSummary) {
+        self.state.lock().unwrap().matches.push(summary.clone());
+        self.inner.match_finished(summary);
+    }
+
+    fn generation_finished(&self, summary: &GenerationSummary) {
+        self.inner.generation_finished(summary);
+
+        let mut state = self.state.lock().unwrap();
+        let metrics = GenerationMetrics::new(summary, &state.matches);
+        state.best.push(summary.best_fitness);
+        if let Err(e) = self.write(&metrics) {
+            if !state.failed {
+                self.inner
+                    .note(&format!("  ! Failed to write training metrics: {}", e));
+            }
+            state.failed = true;
+        }
+        if self.summary {
+            self.inner.note(&summary_line(&metrics, &state.best));
+        }
+    }
+
+    fn note(&self, message: &str) {
+        self.inner.note(message);
+    }
+}
+
+/// A generation's metrics in a line, with how much the best fitness moved lately.
+fn summary_line(metrics: &GenerationMetrics, best: &[f32]) -> String {
+    let mut line = format!(
+        "  > {} kills, {} friendly fire hits in {} matches of {:.0} ticks on average",
+        metrics.kills, metrics.friendly_fire, metrics.matches, metrics.mean_match_ticks
+    );
+    if let Some((change, back)) = trend(best, TREND_WINDOW) {
+        let since = metrics.generation.saturating_sub(back);
+        let _ = write!(line, "; best fitness {:+.2} since gen {}", change, since);
+    }
+    line
+}
+
+/// How much the last of `values` differs from the one up to `window` entries before it,
+/// and how many entries back that one is. `None` until there are two finite values.
+fn trend(values: &[f32], window: usize) -> Option<(f32, usize)> {
+    let last = *values.last().filter(|v| v.is_finite())?;
+    let start = values.len().saturating_sub(window + 1);
+    let (i, first) = values[start..]
+        .iter()
+        .enumerate()
+        .find(|(_, v)| v.is_finite())?;
+    let back = values.len() - 1 - (start + i);
+    (back > 0).then_some((last - first, back))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn match_summary(index: usize, kills: i32, friendly_fire: i32, ticks: usize) -> MatchSummary {
+        MatchSummary {
+            index,
+            blue_kills: kills,
+            red_kills: 1,
+            winner: None,
+            best_score: 0.0,
+            friendly_fire,
+            ticks,
+        }
+    }
+
+    #[test]
+    fn test_generation_metrics_add_up_the_matches() {
+        let summary = GenerationSummary {
+            generation: 2,
+            best_fitness: 812.5,
+            mean_fitness: f32::NEG_INFINITY,
+            win_rate: None,
+            elapsed: Duration::from_millis(1500),
+        };
+        let metrics = GenerationMetrics::new(
+            &summary,
+            &[match_summary(0, 3, 2, 600), match_summary(1, 0, 1, 1000)],
+        );
+        assert_eq!(metrics.kills, 5);
+        assert_eq!(metrics.friendly_fire, 3);
+        assert_eq!(metrics.mean_match_ticks, 800.0);
+
+        assert_eq!(metrics.csv_row(), "2,812.5,,,2,5,3,800,1.5");
+        assert_eq!(
+            CSV_HEADER.split(',').count(),
+            metrics.csv_row().split(',').count()
+        );
+        assert_eq!(
+            metrics.json_line(),
+            "{\"generation\":2,\"best_fitness\":812.5,\"mean_fitness\":null,\"win_rate\":null,\"matches\":2,\"kills\":5,\"friendly_fire\":3,\"mean_match_ticks\":800,\"elapsed_secs\":1.5}"
+        );
+    }
+
+    #[test]
+    fn test_metrics_format_follows_the_extension() {
+        assert_eq!(
+            MetricsFormat::from_path(Path::new("runs/a.csv")),
+            Ok(MetricsFormat::Csv)
+        );
+        assert_eq!(
+            MetricsFormat::from_path(Path::new("a.jsonl")),
+            Ok(MetricsFormat::JsonLines)
+        );
+        assert!(MetricsFormat::from_path(Path::new("a.txt")).is_err());
+        assert!(MetricsFormat::from_path(Path::new("metrics")).is_err());
+    }
+
+    #[test]
+    fn test_trend_looks_back_over_the_window() {
+        assert_eq!(trend(&[], 10), None);
+        assert_eq!(trend(&[5.0], 10), None);
+        assert_eq!(trend(&[1.0, 2.0, 4.0], 10), Some((3.0, 2)));
+        assert_eq!(trend(&[1.0, 2.0, 4.0], 1), Some((2.0, 1)));
+        // Generations without matches have no fitness to compare
+        assert_eq!(trend(&[f32::NEG_INFINITY, 2.0, 4.0], 10), Some((2.0, 1)));
+    }
+}
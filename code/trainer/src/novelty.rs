@@ -0,0 +1,203 @@
+//! Behavioral novelty, used to keep the population from collapsing onto a single strategy.
+//!
+//! Each bot's match is summarized as a [`Behavior`]. A bot's novelty is its average
+//! distance to the most similar bots in the population, so bots that play like everyone
+//! else get little or no bonus.
+
+use common::game::balance;
+use common::net::protocol::MapDefinition;
+use glam::Vec2;
+
+use crate::reward::NoveltyWeights;
+
+/// The map is split into `HEATMAP_SIZE x HEATMAP_SIZE` cells for the position heatmap.
+pub const HEATMAP_SIZE: usize = 4;
+
+/// Coarse summary of how a bot played one match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Behavior {
+    /// Fraction of its alive time the bot spent in each map cell.
+    pub heatmap: [f32; HEATMAP_SIZE * HEATMAP_SIZE],
+    /// Shots fired, relative to the maximum fire rate (0.0-1.0).
+    pub fire_rate: f32,
+    /// Average speed, relative to the tank's top speed (0.0-1.0).
+    pub movement: f32,
+}
+
+impl Behavior {
+    /// Weighted distance between two behaviors.
+    pub fn distance(&self, other: &Behavior, weights: &NoveltyWeights) -> f32 {
+        // L1 distance between two distributions is in 0.0-2.0, halve it to match the others.
+        let position: f32 = self
+            .heatmap
+            .iter()
+            .zip(&other.heatmap)
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>()
+            / 2.0;
+
+        weights.position * position
+            + weights.shots * (self.fire_rate - other.fire_rate).abs()
+            + weights.movement * (self.movement - other.movement).abs()
+    }
+}
+
+impl Behavior {
+    /// The average of `behaviors`, e.g. of the rounds of one match.
+    pub fn mean(behaviors: &[Behavior]) -> Behavior {
+        let mut mean = Behavior::default();
+        if behaviors.is_empty() {
+            return mean;
+        }
+        let n = behaviors.len() as f32;
+        for behavior in behaviors {
+            for (cell, share) in mean.heatmap.iter_mut().zip(&behavior.heatmap) {
+                *cell += share / n;
+            }
+            mean.fire_rate += behavior.fire_rate / n;
+            mean.movement += behavior.movement / n;
+        }
+        mean
+    }
+}
+
+/// Accumulates a [`Behavior`] tick by tick while a match runs.
+#[derive(Debug, Clone, Default)]
+pub struct BehaviorTracker {
+    cells: [u32; HEATMAP_SIZE * HEATMAP_SIZE],
+    ticks: u32,
+    shots: u32,
+    distance: f32,
+    last_position: Option<Vec2>,
+    top_speed: f32,
+}
+
+impl BehaviorTracker {
+    /// Records one tick in which the bot is alive at `position`.
+    pub fn observe(&mut self, position: Vec2, top_speed: f32, map: &MapDefinition) {
+        let cell = |value: f32, size: f32| {
+            ((value / size * HEATMAP_SIZE as f32) as usize).min(HEATMAP_SIZE - 1)
+        };
+        let x = cell(position.x.max(0.0), map.width);
+        let y = cell(position.y.max(0.0), map.height);
+        self.cells[y * HEATMAP_SIZE + x] += 1;
+
+        if let Some(last) = self.last_position {
+            self.distance += last.distance(position);
+        }
+        self.last_position = Some(position);
+        self.top_speed = top_speed;
+        self.ticks += 1;
+    }
+
+    pub fn record_shot(&mut self) {
+        self.shots += 1;
+    }
+
+    pub fn finish(&self, dt: f32) -> Behavior {
+        if self.ticks == 0 {
+            return Behavior::default();
+        }
+
+        let mut heatmap = [0.0; HEATMAP_SIZE * HEATMAP_SIZE];
+
+        for (share, count) in heatmap.iter_mut().zip(&self.cells) {
+            *share = *count as f32 / self.ticks as f32;
+        }
+        let alive_time = self.ticks as f32 * dt;
+        let max_shots = alive_time / balance().fire_interval;
+        let max_distance = alive_time * self.top_speed;
+
+        Behavior {
+            heatmap,
+            fire_rate: (self.shots as f32 / max_shots).min(1.0),
+            movement: if max_distance > 0.0 {
+                (self.distance / max_distance).min(1.0)
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Novelty of every behavior: its mean distance to its `weights.neighbours` nearest
+/// neighbours in `behaviors`.
+pub fn novelty_scores(behaviors: &[Behavior], weights: &NoveltyWeights) -> Vec<f32> {
+    behaviors
+        .iter()
+        .enumerate()
+        .map(|(i, behavior)| {
+            let mut distances: Vec<f32> = behaviors
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, other)| behavior.distance(other, weights))
+                .collect();
+            if distances.is_empty() {
+                return 0.0;
+            }
+            distances.sort_by(f32::total_cmp);
+            distances.truncate(weights.neighbours.max(1));
+            distances.iter().sum::<f32>() / distances.len() as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn behavior_in_cell(cell: usize, fire_rate: f32) -> Behavior {
+        let mut heatmap = [0.0; HEATMAP_SIZE * HEATMAP_SIZE];
+        heatmap[cell] = 1.0;
+        Behavior {
+            heatmap,
+            fire_rate,
+            movement: 0.5,
+        }
+    }
+
+    #[test]
+    fn tracker_builds_heatmap_and_rates() {
+        let map = MapDefinition::load();
+        let mut tracker = BehaviorTracker::default();
+        let dt = 0.1;
+
+        tracker.observe(Vec2::new(1.0, 1.0), 100.0, &map);
+        tracker.record_shot();
+        tracker.observe(Vec2::new(11.0, 1.0), 100.0, &map);
+
+        let behavior = tracker.finish(dt);
+        assert_eq!(behavior.heatmap[0], 1.0);
+        // 2 ticks * 0.1s = 0.2s alive: one shot is the maximum, 10 units of 20 possible.
+        assert!((behavior.fire_rate - 1.0).abs() < 1e-6);
+        assert!((behavior.movement - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn outlier_is_more_novel_than_the_crowd() {
+        let weights = NoveltyWeights {
+            neighbours: 2,
+            ..Default::default()
+        };
+        let behaviors = vec![
+            behavior_in_cell(0, 0.5),
+            behavior_in_cell(0, 0.5),
+            behavior_in_cell(0, 0.4),
+            behavior_in_cell(15, 0.0),
+        ];
+
+        let scores = novelty_scores(&behaviors, &weights);
+        assert!(scores[3] > scores[0]);
+        assert!(scores[3] > scores[2]);
+        assert!(scores[0] < 0.1);
+    }
+
+    #[test]
+    fn identical_behaviors_have_no_novelty() {
+        let weights = NoveltyWeights::default();
+        let behaviors = vec![behavior_in_cell(5, 0.3); 3];
+        assert_eq!(novelty_scores(&behaviors, &weights), vec![0.0; 3]);
+        assert_eq!(novelty_scores(&behaviors[..1], &weights), vec![0.0]);
+    }
+}
@@ -0,0 +1,60 @@
+use common::ai::BotDifficulty;
+
+/// Fixed adversary the whole population is evaluated against (`--opponent`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpponentSpec {
+    /// A saved model from `assets/models/<name>.bin`, never mutated.
+    Model(String),
+    /// One of the built-in scripted bots.
+    Scripted(BotDifficulty),
+}
+
+const SCRIPTED_PREFIX: &str = "scripted:";
+
+/// Parses `--opponent` values: either a model name, or `scripted:<difficulty>`
+/// (e.g. `scripted:terminator`).
+pub fn parse_opponent(value: &str) -> Result<OpponentSpec, String> {
+    let Some(difficulty) = value.strip_prefix(SCRIPTED_PREFIX) else {
+        let name = value.strip_suffix(".bin").unwrap_or(value);
+        if name.is_empty() {
+            return Err("opponent model name cannot be empty".to_string());
+        }
+        return Ok(OpponentSpec::Model(name.to_string()));
+    };
+
+    let difficulty = difficulty
+        .parse::<BotDifficulty>()
+        .map_err(|e| format!("unknown scripted opponent: {}", e))?;
+    Ok(OpponentSpec::Scripted(difficulty))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_model_names() {
+        assert_eq!(
+            parse_opponent("champion"),
+            Ok(OpponentSpec::Model("champion".into()))
+        );
+        assert_eq!(
+            parse_opponent("champion.bin"),
+            Ok(OpponentSpec::Model("champion".into()))
+        );
+        assert!(parse_opponent("").is_err());
+    }
+
+    #[test]
+    fn parses_scripted_difficulties() {
+        assert_eq!(
+            parse_opponent("scripted:Hunter"),
+            Ok(OpponentSpec::Scripted(BotDifficulty::Hunter))
+        );
+        assert_eq!(
+            parse_opponent("scripted:terminator"),
+            Ok(OpponentSpec::Scripted(BotDifficulty::Terminator))
+        );
+        assert!(parse_opponent("scripted:godlike").is_err());
+    }
+}
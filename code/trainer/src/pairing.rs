@@ -0,0 +1,129 @@
+//! Match-making within a generation: keeps clones of the same brain apart and makes
+//! sure a given line-up is only played once.
+
+use std::collections::HashSet;
+
+/// Everything that decides the outcome of a match. Brains are identified by
+/// [`common::rl::BotBrain::fingerprint`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MatchKey {
+    pub blue: Vec<u64>,
+    pub red: Vec<u64>,
+    pub seed: u64,
+    pub map: u64,
+}
+
+/// Orders bots into matches of `team_size` a side (Blue slots first, then Red) so that,
+/// where possible, no brain faces a clone of itself. Returns indices into
+/// `fingerprints`; bots that don't fill a whole match are left at the end.
+pub fn arrange_matches(fingerprints: &[u64], team_size: usize) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..fingerprints.len()).collect();
+    let mut order = Vec::with_capacity(fingerprints.len());
+
+    while remaining.len() >= 2 * team_size {
+        // Clones of bots already on Blue join them there, so Red can be kept clone-free.
+        let mut blue = Vec::with_capacity(team_size);
+        let mut blue_prints = HashSet::new();
+        while blue.len() < team_size {
+            let pos = remaining
+                .iter()
+                .position(|&i| blue_prints.contains(&fingerprints[i]))
+                .unwrap_or(0);
+            let i = remaining.remove(pos);
+            blue_prints.insert(fingerprints[i]);
+            blue.push(i);
+        }
+
+        let mut red: Vec<usize> = remaining
+            .iter()
+            .copied()
+            .filter(|&i| !blue_prints.contains(&fingerprints[i]))
+            .take(team_size)
+            .collect();
+        // Not enough distinct brains left: fill up with clones rather than skip the match.
+        for &i in &remaining {
+            if red.len() == team_size {
+                break;
+            }
+            if !red.contains(&i) {
+                red.push(i);
+            }
+        }
+        remaining.retain(|i| !red.contains(i));
+
+        order.extend(blue);
+        order.extend(red);
+    }
+
+    order.extend(remaining);
+    order
+}
+
+/// Indices of the first match with each key; the rest are repeats whose results can
+/// be reused.
+pub fn unique_matches(keys: &[MatchKey]) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    (0..keys.len()).filter(|&i| seen.insert(&keys[i])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red_faces_blue_clone(fingerprints: &[u64], order: &[usize]) -> bool {
+        order.chunks(8).filter(|m| m.len() == 8).any(|m| {
+            m[4..]
+                .iter()
+                .any(|r| m[..4].iter().any(|b| fingerprints[*b] == fingerprints[*r]))
+        })
+    }
+
+    #[test]
+    fn keeps_clones_on_the_same_side() {
+        // Two brains, each cloned four times, shuffled so naive chunking mixes them.
+        let fingerprints = [1, 2, 1, 2, 1, 2, 1, 2];
+        let order = arrange_matches(&fingerprints, 4);
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..8).collect::<Vec<_>>());
+        assert!(red_faces_blue_clone(
+            &fingerprints,
+            &[0, 1, 2, 3, 4, 5, 6, 7]
+        ));
+        assert!(!red_faces_blue_clone(&fingerprints, &order));
+    }
+
+    #[test]
+    fn falls_back_to_clones_when_unavoidable() {
+        let fingerprints = [7; 10];
+        let order = arrange_matches(&fingerprints, 4);
+        assert_eq!(order, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn arranges_bigger_teams() {
+        // Eight brains, each cloned twice: an 8v8 match keeps every pair on one side.
+        let fingerprints: Vec<u64> = (0..16).map(|i| i % 8).collect();
+        let order = arrange_matches(&fingerprints, 8);
+
+        let (blue, red) = order.split_at(8);
+        assert!(red
+            .iter()
+            .all(|r| blue.iter().all(|b| fingerprints[*b] != fingerprints[*r])));
+    }
+
+    #[test]
+    fn repeated_line_ups_are_played_once() {
+        let key = |seed| MatchKey {
+            blue: vec![1, 2, 3, 4],
+            red: vec![5, 6, 7, 8],
+            seed,
+            map: 0,
+        };
+        assert_eq!(
+            unique_matches(&[key(0), key(1), key(0), key(0)]),
+            vec![0, 1]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! How the trainer reports progress while it runs.
+//!
+//! The training loop only talks to a [`Reporter`]; what the user sees depends on the
+//! implementation: a live terminal [`Dashboard`], or [`PlainReporter`] lines for `--quiet`
+//! runs and logs that aren't a terminal.
+
+use common::net::protocol::Team;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Match results the dashboard keeps on screen.
+const RECENT_MATCHES: usize = 5;
+/// Generations shown in the fitness sparklines.
+const SPARKLINE_WIDTH: usize = 40;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One finished training match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchSummary {
+    /// Position of the match in its generation
+    pub index: usize,
+    pub blue_kills: i32,
+    pub red_kills: i32,
+    pub winner: Option<Team>,
+    /// Best score of a bot in the match
+    pub best_score: f32,
+    /// Hits on teammates
+    pub friendly_fire: i32,
+    /// Ticks played, over all rounds
+    pub ticks: usize,
+}
+
+/// One finished generation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationSummary {
+    pub generation: usize,
+    /// Best score (self-play) or win-rate (against an opponent or in a scenario)
+    pub best_fitness: f32,
+    /// Mean of the same measure over the population
+    pub mean_fitness: f32,
+    /// Champion's win-rate against the reference, when it was measured
+    pub win_rate: Option<f32>,
+    pub elapsed: Duration,
+}
+
+/// Receives everything the trainer has to say. Called from match threads, hence `Sync`.
+pub trait Reporter: Sync {
+    fn generation_started(&self, generation: usize, matches: usize);
+    fn match_finished(&self, summary: &MatchSummary);
+    fn generation_finished(&self, summary: &GenerationSummary);
+    /// Anything else worth telling the user.
+    fn note(&self, message: &str);
+}
+
+/// One line per generation, and the notes. Nothing per match.
+pub struct PlainReporter;
+
+impl Reporter for PlainReporter {
+    fn generation_started(&self, _generation: usize, _matches: usize) {}
+
+    fn match_finished(&self, _summary: &MatchSummary) {}
+
+    fn generation_finished(&self, summary: &GenerationSummary) {
+        println!("{}", generation_line(summary));
+    }
+
+    fn note(&self, message: &str) {
+        println!("{}", message);
+    }
+}
+
+/// Live view of the run: a progress bar over the matches of the current generation, the
+/// latest match results and sparklines of the best and mean fitness so far.
+pub struct Dashboard {
+    multi: MultiProgress,
+    header: ProgressBar,
+    matches: ProgressBar,
+    rows: Vec<ProgressBar>,
+    state: Mutex<DashboardState>,
+}
+
+#[derive(Default)]
+struct DashboardState {
+    generations: usize,
+    recent: VecDeque<MatchSummary>,
+    best: Vec<f32>,
+    mean: Vec<f32>,
+}
+
+impl Dashboard {
+    pub fn new(generations: usize) -> Self {
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
+        let text = ProgressStyle::with_template("{msg}").unwrap();
+        let line = || multi.add(ProgressBar::new(0).with_style(text.clone()));
+
+        let header = line();
+        let matches = multi.add(
+            ProgressBar::new(0).with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>8} [{bar:40.cyan/blue}] {pos}/{len} matches ({elapsed})",
+                )
+                .unwrap()
+                .progress_chars("=> "),
+            ),
+        );
+        let rows = (0..=RECENT_MATCHES).map(|_| line()).collect();
+
+        Self {
+            multi,
+            header,
+            matches,
+            rows,
+            state: Mutex::new(DashboardState {
+                generations,
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn redraw(&self, state: &DashboardState) {
+        let spark = |values: &[f32]| {
+            let shown = &values[values.len().saturating_sub(SPARKLINE_WIDTH)..];
+            let last = values.last().map_or(String::new(), |v| format!("{:.2}", v));
+            format!("{} {}", sparkline(shown), last)
+        };
+        self.header.set_message(format!(
+            "best {}\nmean {}",
+            spark(&state.best),
+            spark(&state.mean)
+        ));
+
+        self.rows[0].set_message(format!(
+            "{:>6} {:>8} {:>8} {:>7} {:>10}",
+            "match", "blue K", "red K", "winner", "best score"
+        ));
+        for (i, row) in self.rows[1..].iter().enumerate() {
+            row.set_message(state.recent.get(i).map_or(String::new(), |m| {
+                format!(
+                    "{:>6} {:>8} {:>8} {:>7} {:>10.0}",
+                    m.index + 1,
+                    m.blue_kills,
+                    m.red_kills,
+                    m.winner.map_or("-".to_string(), |t| format!("{:?}", t)),
+                    m.best_score
+                )
+            }));
+        }
+    }
+}
+
+impl Reporter for Dashboard {
+    fn generation_started(&self, generation: usize, matches: usize) {
+        let state = self.state.lock().unwrap();
+        self.matches
+            .set_prefix(format!("{}/{}", generation, state.generations));
+        self.matches.set_length(matches as u64);
+        self.matches.reset();
+        self.redraw(&state);
+    }
+
+    fn match_finished(&self, summary: &MatchSummary) {
+        let mut state = self.state.lock().unwrap();
+        state.recent.push_front(summary.clone());
+        state.recent.truncate(RECENT_MATCHES);
+        self.matches.inc(1);
+        self.redraw(&state);
+    }
+
+    fn generation_finished(&self, summary: &GenerationSummary) {
+        let mut state = self.state.lock().unwrap();
+        state.best.push(summary.best_fitness);
+        state.mean.push(summary.mean_fitness);
+        self.redraw(&state);
+        self.note(&generation_line(summary));
+    }
+
+    fn note(&self, message: &str) {
+        let _ = self.multi.println(message);
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        let _ = self.multi.clear();
+    }
+}
+
+fn generation_line(summary: &GenerationSummary) -> String {
+    let mut line = format!(
+        "Gen {}: best {:.2}, mean {:.2}",
+        summary.generation, summary.best_fitness, summary.mean_fitness
+    );
+    if let Some(win_rate) = summary.win_rate {
+        line += &format!(", win-rate vs reference {:.0}%", win_rate * 100.0);
+    }
+    line + &format!(" ({:.1}s)", summary.elapsed.as_secs_f32())
+}
+
+/// `values` as a row of bars, scaled between their minimum and maximum.
+fn sparkline(values: &[f32]) -> String {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let top = SPARK_LEVELS.len() - 1;
+    values
+        .iter()
+        .map(|&v| {
+            let level = if max > min {
+                ((v - min) / (max - min) * top as f32).round() as usize
+            } else {
+                0
+            };
+            SPARK_LEVELS[level.min(top)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_scales_between_min_and_max() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[3.0, 3.0]), "▁▁");
+        assert_eq!(sparkline(&[0.0, 7.0, 3.5, 1.0]), "▁█▅▂");
+        assert_eq!(sparkline(&[f32::NAN, 1.0, 2.0]), "▁▁█");
+    }
+
+    #[test]
+    fn test_generation_line_mentions_the_win_rate_when_measured() {
+        let mut summary = GenerationSummary {
+            generation: 3,
+            best_fitness: 812.0,
+            mean_fitness: 120.25,
+            win_rate: None,
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(
+            generation_line(&summary),
+            "Gen 3: best 812.00, mean 120.25 (1.5s)"
+        );
+        summary.win_rate = Some(0.5);
+        assert!(generation_line(&summary).contains("win-rate vs reference 50%"));
+    }
+}
@@ -0,0 +1,74 @@
+use common::rl::scenario::RewardOverrides;
+
+/// Weights used to turn what happened in a match into a bot's fitness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RewardConfig {
+    /// Score per point of damage dealt to an enemy.
+    pub enemy_damage: f32,
+    /// Score lost per point of damage dealt to a teammate.
+    pub friendly_damage_penalty: f32,
+    /// Bonus for killing an enemy.
+    pub kill_bonus: f32,
+    /// Score lost for killing a teammate.
+    pub teamkill_penalty: f32,
+    /// Bonus for every round the bot's team wins in matches of several rounds.
+    pub round_win_bonus: f32,
+    /// Diversity pressure, see [`crate::novelty`].
+    pub novelty: NoveltyWeights,
+}
+
+/// How much behavioral novelty is worth, and how behaviors are compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoveltyWeights {
+    /// Score added per unit of novelty. `0.0` disables the novelty term.
+    pub bonus: f32,
+    /// How many nearest neighbours a bot is compared against.
+    pub neighbours: usize,
+    /// Weight of the difference in where on the map the bots spent their time.
+    pub position: f32,
+    /// Weight of the difference in fire rate.
+    pub shots: f32,
+    /// Weight of the difference in how much the bots moved.
+    pub movement: f32,
+}
+
+impl RewardConfig {
+    /// These weights with the ones a scenario sets replaced.
+    pub fn with_overrides(self, overrides: &RewardOverrides) -> Self {
+        Self {
+            enemy_damage: overrides.enemy_damage.unwrap_or(self.enemy_damage),
+            friendly_damage_penalty: overrides
+                .friendly_damage_penalty
+                .unwrap_or(self.friendly_damage_penalty),
+            kill_bonus: overrides.kill_bonus.unwrap_or(self.kill_bonus),
+            teamkill_penalty: overrides.teamkill_penalty.unwrap_or(self.teamkill_penalty),
+            round_win_bonus: self.round_win_bonus,
+            novelty: self.novelty,
+        }
+    }
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            enemy_damage: 1.0,
+            friendly_damage_penalty: 2.0,
+            kill_bonus: 500.0,
+            teamkill_penalty: 1000.0,
+            round_win_bonus: 0.0,
+            novelty: NoveltyWeights::default(),
+        }
+    }
+}
+
+impl Default for NoveltyWeights {
+    fn default() -> Self {
+        Self {
+            bonus: 0.0,
+            neighbours: 5,
+            position: 1.0,
+            shots: 1.0,
+            movement: 1.0,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+/// Configurable rules for ending a training run before `--generations` is reached.
+#[derive(Debug, Clone, Default)]
+pub struct StoppingRules {
+    /// Stop after this many generations without a new best fitness.
+    pub patience: Option<usize>,
+    /// Stop once the champion's win-rate against the reference reaches this value.
+    pub target_win_rate: Option<f32>,
+    /// Stop once this much wall-clock time has passed.
+    pub time_budget: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Plateau { generations: usize },
+    TargetReached { win_rate: f32 },
+    TimeBudget,
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Plateau { generations } => {
+                write!(f, "no improvement for {} generations", generations)
+            }
+            StopReason::TargetReached { win_rate } => {
+                write!(f, "win-rate {:.0}% reached the target", win_rate * 100.0)
+            }
+            StopReason::TimeBudget => write!(f, "time budget exhausted"),
+        }
+    }
+}
+
+/// Tracks progress across generations and decides when to stop.
+pub struct EarlyStopping {
+    rules: StoppingRules,
+    best_fitness: Option<f32>,
+    stale_generations: usize,
+    started: Instant,
+}
+
+impl EarlyStopping {
+    pub fn new(rules: StoppingRules, started: Instant) -> Self {
+        Self {
+            rules,
+            best_fitness: None,
+            stale_generations: 0,
+            started,
+        }
+    }
+
+    pub fn best_fitness(&self) -> Option<f32> {
+        self.best_fitness
+    }
+
+    /// Records the result of a generation; returns why training should stop, if it should.
+    pub fn update(
+        &mut self,
+        best_fitness: f32,
+        win_rate: Option<f32>,
+        now: Instant,
+    ) -> Option<StopReason> {
+        match self.best_fitness {
+            Some(best) if best_fitness <= best => self.stale_generations += 1,
+            _ => {
+                self.best_fitness = Some(best_fitness);
+                self.stale_generations = 0;
+            }
+        }
+
+        if let (Some(target), Some(win_rate)) = (self.rules.target_win_rate, win_rate) {
+            if win_rate >= target {
+                return Some(StopReason::TargetReached { win_rate });
+            }
+        }
+        if let Some(patience) = self.rules.patience {
+            if self.stale_generations >= patience {
+                return Some(StopReason::Plateau {
+                    generations: self.stale_generations,
+                });
+            }
+        }
+        if let Some(budget) = self.rules.time_budget {
+            if now.duration_since(self.started) >= budget {
+                return Some(StopReason::TimeBudget);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_after_patience_without_improvement() {
+        let start = Instant::now();
+        let rules = StoppingRules {
+            patience: Some(2),
+            ..Default::default()
+        };
+        let mut stopping = EarlyStopping::new(rules, start);
+
+        assert_eq!(stopping.update(10.0, None, start), None);
+        assert_eq!(stopping.update(12.0, None, start), None);
+        assert_eq!(stopping.update(11.0, None, start), None);
+        assert_eq!(
+            stopping.update(12.0, None, start),
+            Some(StopReason::Plateau { generations: 2 })
+        );
+        assert_eq!(stopping.best_fitness(), Some(12.0));
+    }
+
+    #[test]
+    fn stops_when_target_win_rate_reached() {
+        let start = Instant::now();
+        let rules = StoppingRules {
+            target_win_rate: Some(0.75),
+            ..Default::default()
+        };
+        let mut stopping = EarlyStopping::new(rules, start);
+
+        assert_eq!(stopping.update(1.0, Some(0.5), start), None);
+        assert_eq!(stopping.update(1.0, None, start), None);
+        assert_eq!(
+            stopping.update(1.0, Some(0.8), start),
+            Some(StopReason::TargetReached { win_rate: 0.8 })
+        );
+    }
+
+    #[test]
+    fn stops_when_time_budget_exhausted() {
+        let start = Instant::now();
+        let rules = StoppingRules {
+            time_budget: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let mut stopping = EarlyStopping::new(rules, start);
+
+        assert_eq!(
+            stopping.update(1.0, None, start + Duration::from_secs(59)),
+            None
+        );
+        assert_eq!(
+            stopping.update(2.0, None, start + Duration::from_secs(60)),
+            Some(StopReason::TimeBudget)
+        );
+    }
+
+    #[test]
+    fn never_stops_without_rules() {
+        let start = Instant::now();
+        let mut stopping = EarlyStopping::new(StoppingRules::default(), start);
+        for _ in 0..100 {
+            assert_eq!(
+                stopping.update(0.0, Some(1.0), start + Duration::from_secs(3600)),
+                None
+            );
+        }
+    }
+}